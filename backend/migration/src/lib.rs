@@ -1,12 +1,62 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20261018_000001_login_throttling;
+mod m20261018_000002_mfa;
+mod m20261018_000003_account_tokens;
+mod m20261018_000004_oidc_hardening;
+mod m20261018_000005_identity_providers;
+mod m20261018_000006_scim;
+mod m20261018_000007_api_tokens;
+mod m20261018_000008_entity_hierarchy;
+mod m20261018_000009_entity_lifecycle;
+mod m20261018_000010_rgpd_record_of_processing;
+mod m20261018_000011_rgpd_register_exports;
+mod m20261018_000012_rgpd_dpia;
+mod m20261018_000013_rgpd_taxonomy;
+mod m20261018_000014_rgpd_retention;
+mod m20261018_000015_rgpd_processors;
+mod m20261018_000016_rgpd_transfer_assessments;
+mod m20261018_000017_rgpd_consents;
+mod m20261018_000018_rgpd_breach_assessments;
+mod m20261018_000019_rgpd_breach_templates;
+mod m20261018_000020_email_domains;
+mod m20261018_000021_api_token_entities;
+mod m20261018_000022_rgpd_retention_archive;
+mod m20261018_000023_rgpd_retention_register_rules;
+mod m20261018_000024_federated_mfa;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20261018_000001_login_throttling::Migration),
+            Box::new(m20261018_000002_mfa::Migration),
+            Box::new(m20261018_000003_account_tokens::Migration),
+            Box::new(m20261018_000004_oidc_hardening::Migration),
+            Box::new(m20261018_000005_identity_providers::Migration),
+            Box::new(m20261018_000006_scim::Migration),
+            Box::new(m20261018_000007_api_tokens::Migration),
+            Box::new(m20261018_000008_entity_hierarchy::Migration),
+            Box::new(m20261018_000009_entity_lifecycle::Migration),
+            Box::new(m20261018_000010_rgpd_record_of_processing::Migration),
+            Box::new(m20261018_000011_rgpd_register_exports::Migration),
+            Box::new(m20261018_000012_rgpd_dpia::Migration),
+            Box::new(m20261018_000013_rgpd_taxonomy::Migration),
+            Box::new(m20261018_000014_rgpd_retention::Migration),
+            Box::new(m20261018_000015_rgpd_processors::Migration),
+            Box::new(m20261018_000016_rgpd_transfer_assessments::Migration),
+            Box::new(m20261018_000017_rgpd_consents::Migration),
+            Box::new(m20261018_000018_rgpd_breach_assessments::Migration),
+            Box::new(m20261018_000019_rgpd_breach_templates::Migration),
+            Box::new(m20261018_000020_email_domains::Migration),
+            Box::new(m20261018_000021_api_token_entities::Migration),
+            Box::new(m20261018_000022_rgpd_retention_archive::Migration),
+            Box::new(m20261018_000023_rgpd_retention_register_rules::Migration),
+            Box::new(m20261018_000024_federated_mfa::Migration),
+        ]
     }
 }
@@ -0,0 +1,98 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create login_failures table (journal consultable par les administrateurs)
+        manager
+            .create_table(
+                Table::create()
+                    .table(LoginFailures::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(LoginFailures::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(LoginFailures::Email).string().not_null())
+                    .col(ColumnDef::new(LoginFailures::UserId).uuid())
+                    .col(ColumnDef::new(LoginFailures::IpAddress).string())
+                    .col(ColumnDef::new(LoginFailures::Reason).string().not_null())
+                    .col(ColumnDef::new(LoginFailures::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_login_failures_user_id")
+                            .from(LoginFailures::Table, LoginFailures::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_login_failures_user_id_created_at")
+                    .table(LoginFailures::Table)
+                    .col(LoginFailures::UserId)
+                    .col(LoginFailures::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create login_throttle table (état partagé du limiteur PostgreSQL)
+        manager
+            .create_table(
+                Table::create()
+                    .table(LoginThrottle::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(LoginThrottle::Key).string().not_null().primary_key())
+                    .col(ColumnDef::new(LoginThrottle::Failures).integer().not_null().default(0))
+                    .col(ColumnDef::new(LoginThrottle::LastFailureAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(LoginThrottle::BlockedUntil).timestamp_with_time_zone())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LoginThrottle::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(LoginFailures::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum LoginFailures {
+    Table,
+    Id,
+    Email,
+    UserId,
+    IpAddress,
+    Reason,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum LoginThrottle {
+    Table,
+    Key,
+    Failures,
+    LastFailureAt,
+    BlockedUntil,
+}
@@ -0,0 +1,222 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Politique d'entité : MFA obligatoire pour ses membres
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Entities::Table)
+                    .add_column(ColumnDef::new(Entities::RequireMfa).boolean().not_null().default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create user_totp table
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserTotp::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(UserTotp::UserId).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(UserTotp::Secret).string().not_null())
+                    .col(ColumnDef::new(UserTotp::ConfirmedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(UserTotp::LastUsedStep).big_integer())
+                    .col(ColumnDef::new(UserTotp::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_totp_user_id")
+                            .from(UserTotp::Table, UserTotp::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create user_recovery_codes table
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserRecoveryCodes::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(UserRecoveryCodes::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(UserRecoveryCodes::UserId).uuid().not_null())
+                    .col(ColumnDef::new(UserRecoveryCodes::CodeHash).string().not_null())
+                    .col(ColumnDef::new(UserRecoveryCodes::UsedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(UserRecoveryCodes::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_recovery_codes_user_id")
+                            .from(UserRecoveryCodes::Table, UserRecoveryCodes::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_user_recovery_codes_user_id")
+                    .table(UserRecoveryCodes::Table)
+                    .col(UserRecoveryCodes::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create webauthn_credentials table
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebauthnCredentials::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(WebauthnCredentials::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(WebauthnCredentials::UserId).uuid().not_null())
+                    .col(ColumnDef::new(WebauthnCredentials::Name).string().not_null())
+                    .col(ColumnDef::new(WebauthnCredentials::Passkey).json_binary().not_null())
+                    .col(ColumnDef::new(WebauthnCredentials::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(WebauthnCredentials::LastUsedAt).timestamp_with_time_zone())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_webauthn_credentials_user_id")
+                            .from(WebauthnCredentials::Table, WebauthnCredentials::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_webauthn_credentials_user_id")
+                    .table(WebauthnCredentials::Table)
+                    .col(WebauthnCredentials::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create mfa_challenges table
+        manager
+            .create_table(
+                Table::create()
+                    .table(MfaChallenges::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(MfaChallenges::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(MfaChallenges::UserId).uuid().not_null())
+                    .col(ColumnDef::new(MfaChallenges::TokenHash).string().not_null().unique_key())
+                    .col(ColumnDef::new(MfaChallenges::Purpose).string().not_null())
+                    .col(ColumnDef::new(MfaChallenges::State).json_binary())
+                    .col(ColumnDef::new(MfaChallenges::Attempts).integer().not_null().default(0))
+                    .col(ColumnDef::new(MfaChallenges::ExpiresAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(MfaChallenges::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_mfa_challenges_user_id")
+                            .from(MfaChallenges::Table, MfaChallenges::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MfaChallenges::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(WebauthnCredentials::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(UserRecoveryCodes::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(UserTotp::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Entities::Table)
+                    .drop_column(Entities::RequireMfa)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Entities {
+    Table,
+    RequireMfa,
+}
+
+#[derive(DeriveIden)]
+enum UserTotp {
+    Table,
+    UserId,
+    Secret,
+    ConfirmedAt,
+    LastUsedStep,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum UserRecoveryCodes {
+    Table,
+    Id,
+    UserId,
+    CodeHash,
+    UsedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum WebauthnCredentials {
+    Table,
+    Id,
+    UserId,
+    Name,
+    Passkey,
+    CreatedAt,
+    LastUsedAt,
+}
+
+#[derive(DeriveIden)]
+enum MfaChallenges {
+    Table,
+    Id,
+    UserId,
+    TokenHash,
+    Purpose,
+    State,
+    Attempts,
+    ExpiresAt,
+    CreatedAt,
+}
@@ -0,0 +1,104 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(ColumnDef::new(Users::EmailVerifiedAt).timestamp_with_time_zone())
+                    .add_column(ColumnDef::new(Users::SessionsValidAfter).timestamp_with_time_zone())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Les comptes existants sont considérés comme vérifiés
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Users::Table)
+                    .value(Users::EmailVerifiedAt, Expr::col(Users::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create user_tokens table
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserTokens::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(UserTokens::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(UserTokens::UserId).uuid().not_null())
+                    .col(ColumnDef::new(UserTokens::Purpose).string().not_null())
+                    .col(ColumnDef::new(UserTokens::ExpiresAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(UserTokens::UsedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(UserTokens::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_tokens_user_id")
+                            .from(UserTokens::Table, UserTokens::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_user_tokens_user_id_purpose")
+                    .table(UserTokens::Table)
+                    .col(UserTokens::UserId)
+                    .col(UserTokens::Purpose)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserTokens::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::SessionsValidAfter)
+                    .drop_column(Users::EmailVerifiedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+    CreatedAt,
+    EmailVerifiedAt,
+    SessionsValidAfter,
+}
+
+#[derive(DeriveIden)]
+enum UserTokens {
+    Table,
+    Id,
+    UserId,
+    Purpose,
+    ExpiresAt,
+    UsedAt,
+    CreatedAt,
+}
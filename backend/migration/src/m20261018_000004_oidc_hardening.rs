@@ -0,0 +1,105 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create oidc_login_states table (state, nonce et PKCE conservés côté serveur)
+        manager
+            .create_table(
+                Table::create()
+                    .table(OidcLoginStates::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(OidcLoginStates::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(OidcLoginStates::StateHash).string().not_null().unique_key())
+                    .col(ColumnDef::new(OidcLoginStates::Nonce).string().not_null())
+                    .col(ColumnDef::new(OidcLoginStates::CodeVerifier).string().not_null())
+                    .col(ColumnDef::new(OidcLoginStates::ExpiresAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(OidcLoginStates::CreatedAt).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create user_identities table
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserIdentities::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(UserIdentities::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(UserIdentities::UserId).uuid().not_null())
+                    .col(ColumnDef::new(UserIdentities::Issuer).string().not_null())
+                    .col(ColumnDef::new(UserIdentities::Subject).string().not_null())
+                    .col(ColumnDef::new(UserIdentities::Email).string())
+                    .col(ColumnDef::new(UserIdentities::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(UserIdentities::LastLoginAt).timestamp_with_time_zone())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_identities_user_id")
+                            .from(UserIdentities::Table, UserIdentities::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_user_identities_issuer_subject")
+                    .table(UserIdentities::Table)
+                    .col(UserIdentities::Issuer)
+                    .col(UserIdentities::Subject)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserIdentities::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(OidcLoginStates::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum OidcLoginStates {
+    Table,
+    Id,
+    StateHash,
+    Nonce,
+    CodeVerifier,
+    ExpiresAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum UserIdentities {
+    Table,
+    Id,
+    UserId,
+    Issuer,
+    Subject,
+    Email,
+    CreatedAt,
+    LastLoginAt,
+}
@@ -0,0 +1,163 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create identity_providers table (fournisseurs OIDC / SAML par entité)
+        manager
+            .create_table(
+                Table::create()
+                    .table(IdentityProviders::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(IdentityProviders::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(IdentityProviders::EntityId).uuid().not_null())
+                    .col(ColumnDef::new(IdentityProviders::Name).string().not_null())
+                    .col(ColumnDef::new(IdentityProviders::Protocol).string().not_null())
+                    .col(ColumnDef::new(IdentityProviders::Issuer).string().not_null())
+                    .col(ColumnDef::new(IdentityProviders::ClientId).string())
+                    .col(ColumnDef::new(IdentityProviders::ClientSecret).string())
+                    .col(ColumnDef::new(IdentityProviders::SsoUrl).string())
+                    .col(ColumnDef::new(IdentityProviders::Certificate).text())
+                    .col(ColumnDef::new(IdentityProviders::EmailDomains).json_binary().not_null().default("[]"))
+                    .col(ColumnDef::new(IdentityProviders::GroupsClaim).string().not_null().default("groups"))
+                    .col(ColumnDef::new(IdentityProviders::RoleMapping).json_binary().not_null().default("[]"))
+                    .col(ColumnDef::new(IdentityProviders::DefaultRole).string())
+                    .col(ColumnDef::new(IdentityProviders::IsEnabled).boolean().not_null().default(true))
+                    .col(ColumnDef::new(IdentityProviders::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(IdentityProviders::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_identity_providers_entity_id")
+                            .from(IdentityProviders::Table, IdentityProviders::EntityId)
+                            .to(Entities::Table, Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_identity_providers_entity_id")
+                    .table(IdentityProviders::Table)
+                    .col(IdentityProviders::EntityId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create saml_requests table (AuthnRequest en attente de réponse)
+        manager
+            .create_table(
+                Table::create()
+                    .table(SamlRequests::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(SamlRequests::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(SamlRequests::RequestId).string().not_null().unique_key())
+                    .col(ColumnDef::new(SamlRequests::ProviderId).uuid().not_null())
+                    .col(ColumnDef::new(SamlRequests::ExpiresAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(SamlRequests::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_saml_requests_provider_id")
+                            .from(SamlRequests::Table, SamlRequests::ProviderId)
+                            .to(IdentityProviders::Table, IdentityProviders::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Fournisseur utilisé par une connexion OIDC en cours
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OidcLoginStates::Table)
+                    .add_column(ColumnDef::new(OidcLoginStates::ProviderId).uuid())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_oidc_login_states_provider_id")
+                            .from_tbl(OidcLoginStates::Table)
+                            .from_col(OidcLoginStates::ProviderId)
+                            .to_tbl(IdentityProviders::Table)
+                            .to_col(IdentityProviders::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OidcLoginStates::Table)
+                    .drop_foreign_key(Alias::new("fk_oidc_login_states_provider_id"))
+                    .drop_column(OidcLoginStates::ProviderId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(SamlRequests::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(IdentityProviders::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Entities {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum OidcLoginStates {
+    Table,
+    ProviderId,
+}
+
+#[derive(DeriveIden)]
+enum IdentityProviders {
+    Table,
+    Id,
+    EntityId,
+    Name,
+    Protocol,
+    Issuer,
+    ClientId,
+    ClientSecret,
+    SsoUrl,
+    Certificate,
+    EmailDomains,
+    GroupsClaim,
+    RoleMapping,
+    DefaultRole,
+    IsEnabled,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum SamlRequests {
+    Table,
+    Id,
+    RequestId,
+    ProviderId,
+    ExpiresAt,
+    CreatedAt,
+}
@@ -0,0 +1,230 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create scim_tokens table (jeton bearer du client SCIM, un par entité)
+        manager
+            .create_table(
+                Table::create()
+                    .table(ScimTokens::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ScimTokens::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(ScimTokens::EntityId).uuid().not_null().unique_key())
+                    .col(ColumnDef::new(ScimTokens::TokenHash).string().not_null().unique_key())
+                    .col(ColumnDef::new(ScimTokens::RoleMapping).json_binary().not_null().default("[]"))
+                    .col(ColumnDef::new(ScimTokens::DefaultRole).string().not_null().default("member"))
+                    .col(ColumnDef::new(ScimTokens::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(ScimTokens::LastUsedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(ScimTokens::ExpiresAt).timestamp_with_time_zone())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_scim_tokens_entity_id")
+                            .from(ScimTokens::Table, ScimTokens::EntityId)
+                            .to(Entities::Table, Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create scim_users table (ressources User SCIM d'une entité)
+        manager
+            .create_table(
+                Table::create()
+                    .table(ScimUsers::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ScimUsers::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(ScimUsers::EntityId).uuid().not_null())
+                    .col(ColumnDef::new(ScimUsers::UserId).uuid().not_null())
+                    .col(ColumnDef::new(ScimUsers::ExternalId).string())
+                    .col(ColumnDef::new(ScimUsers::Active).boolean().not_null().default(true))
+                    .col(ColumnDef::new(ScimUsers::Version).integer().not_null().default(1))
+                    .col(ColumnDef::new(ScimUsers::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(ScimUsers::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_scim_users_entity_id")
+                            .from(ScimUsers::Table, ScimUsers::EntityId)
+                            .to(Entities::Table, Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_scim_users_user_id")
+                            .from(ScimUsers::Table, ScimUsers::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_scim_users_entity_user")
+                    .table(ScimUsers::Table)
+                    .col(ScimUsers::EntityId)
+                    .col(ScimUsers::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create scim_groups table
+        manager
+            .create_table(
+                Table::create()
+                    .table(ScimGroups::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ScimGroups::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(ScimGroups::EntityId).uuid().not_null())
+                    .col(ColumnDef::new(ScimGroups::DisplayName).string().not_null())
+                    .col(ColumnDef::new(ScimGroups::ExternalId).string())
+                    .col(ColumnDef::new(ScimGroups::Version).integer().not_null().default(1))
+                    .col(ColumnDef::new(ScimGroups::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(ScimGroups::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_scim_groups_entity_id")
+                            .from(ScimGroups::Table, ScimGroups::EntityId)
+                            .to(Entities::Table, Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_scim_groups_entity_display_name")
+                    .table(ScimGroups::Table)
+                    .col(ScimGroups::EntityId)
+                    .col(ScimGroups::DisplayName)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create scim_group_members table
+        manager
+            .create_table(
+                Table::create()
+                    .table(ScimGroupMembers::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ScimGroupMembers::GroupId).uuid().not_null())
+                    .col(ColumnDef::new(ScimGroupMembers::ScimUserId).uuid().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(ScimGroupMembers::GroupId)
+                            .col(ScimGroupMembers::ScimUserId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_scim_group_members_group_id")
+                            .from(ScimGroupMembers::Table, ScimGroupMembers::GroupId)
+                            .to(ScimGroups::Table, ScimGroups::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_scim_group_members_scim_user_id")
+                            .from(ScimGroupMembers::Table, ScimGroupMembers::ScimUserId)
+                            .to(ScimUsers::Table, ScimUsers::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ScimGroupMembers::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ScimGroups::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ScimUsers::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ScimTokens::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Entities {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ScimTokens {
+    Table,
+    Id,
+    EntityId,
+    TokenHash,
+    RoleMapping,
+    DefaultRole,
+    CreatedAt,
+    LastUsedAt,
+    ExpiresAt,
+}
+
+#[derive(DeriveIden)]
+enum ScimUsers {
+    Table,
+    Id,
+    EntityId,
+    UserId,
+    ExternalId,
+    Active,
+    Version,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ScimGroups {
+    Table,
+    Id,
+    EntityId,
+    DisplayName,
+    ExternalId,
+    Version,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ScimGroupMembers {
+    Table,
+    GroupId,
+    ScimUserId,
+}
@@ -0,0 +1,141 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create service_accounts table (comptes non humains, rattachés à une seule entité)
+        manager
+            .create_table(
+                Table::create()
+                    .table(ServiceAccounts::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ServiceAccounts::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(ServiceAccounts::EntityId).uuid().not_null())
+                    .col(ColumnDef::new(ServiceAccounts::UserId).uuid().not_null().unique_key())
+                    .col(ColumnDef::new(ServiceAccounts::Name).string().not_null())
+                    .col(ColumnDef::new(ServiceAccounts::Description).text())
+                    .col(ColumnDef::new(ServiceAccounts::CreatedBy).uuid())
+                    .col(ColumnDef::new(ServiceAccounts::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_service_accounts_entity_id")
+                            .from(ServiceAccounts::Table, ServiceAccounts::EntityId)
+                            .to(Entities::Table, Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_service_accounts_user_id")
+                            .from(ServiceAccounts::Table, ServiceAccounts::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_service_accounts_created_by")
+                            .from(ServiceAccounts::Table, ServiceAccounts::CreatedBy)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create api_tokens table (jetons personnels et de comptes de service, stockés hachés)
+        manager
+            .create_table(
+                Table::create()
+                    .table(ApiTokens::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ApiTokens::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(ApiTokens::UserId).uuid().not_null())
+                    .col(ColumnDef::new(ApiTokens::Name).string().not_null())
+                    .col(ColumnDef::new(ApiTokens::TokenPrefix).string().not_null())
+                    .col(ColumnDef::new(ApiTokens::TokenHash).string().not_null().unique_key())
+                    .col(ColumnDef::new(ApiTokens::Scopes).json_binary().not_null())
+                    .col(ColumnDef::new(ApiTokens::Mfa).boolean().not_null().default(false))
+                    .col(ColumnDef::new(ApiTokens::ExpiresAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(ApiTokens::LastUsedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(ApiTokens::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_api_tokens_user_id")
+                            .from(ApiTokens::Table, ApiTokens::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_api_tokens_user_id")
+                    .table(ApiTokens::Table)
+                    .col(ApiTokens::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ApiTokens::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ServiceAccounts::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Entities {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ServiceAccounts {
+    Table,
+    Id,
+    EntityId,
+    UserId,
+    Name,
+    Description,
+    CreatedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ApiTokens {
+    Table,
+    Id,
+    UserId,
+    Name,
+    TokenPrefix,
+    TokenHash,
+    Scopes,
+    Mfa,
+    ExpiresAt,
+    LastUsedAt,
+    CreatedAt,
+}
@@ -0,0 +1,92 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Entité parente (groupe / filiale) ; une entité ayant des filiales ne
+        // peut pas être supprimée sans les détacher
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Entities::Table)
+                    .add_column(ColumnDef::new(Entities::ParentId).uuid())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_entities_parent_id")
+                            .from_tbl(Entities::Table)
+                            .from_col(Entities::ParentId)
+                            .to_tbl(Entities::Table)
+                            .to_col(Entities::Id)
+                            .on_delete(ForeignKeyAction::Restrict)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_entities_parent_id")
+                    .table(Entities::Table)
+                    .col(Entities::ParentId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Traitements définis au niveau du groupe et repris par les filiales
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdRegister::Table)
+                    .add_column(ColumnDef::new(RgpdRegister::SharedWithSubsidiaries).boolean().not_null().default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdRegister::Table)
+                    .drop_column(RgpdRegister::SharedWithSubsidiaries)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(Index::drop().name("idx_entities_parent_id").table(Entities::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Entities::Table)
+                    .drop_foreign_key(Alias::new("fk_entities_parent_id"))
+                    .drop_column(Entities::ParentId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Entities {
+    Table,
+    Id,
+    ParentId,
+}
+
+#[derive(DeriveIden)]
+enum RgpdRegister {
+    Table,
+    SharedWithSubsidiaries,
+}
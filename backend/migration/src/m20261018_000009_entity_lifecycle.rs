@@ -0,0 +1,190 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Archivage (lecture seule) et suppression programmée
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Entities::Table)
+                    .add_column(ColumnDef::new(Entities::ArchivedAt).timestamp_with_time_zone())
+                    .add_column(ColumnDef::new(Entities::DeletionScheduledAt).timestamp_with_time_zone())
+                    .add_column(ColumnDef::new(Entities::DeletionRequestedBy).uuid())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_entities_deletion_requested_by")
+                            .from_tbl(Entities::Table)
+                            .from_col(Entities::DeletionRequestedBy)
+                            .to_tbl(Users::Table)
+                            .to_col(Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Éléments du catalogue propres à une entité ; `NULL` = référentiel partagé
+        for (table, fk, idx) in [
+            (CatalogueTable::Endpoints, "fk_catalogue_endpoints_entity_id", "idx_catalogue_endpoints_entity_id"),
+            (CatalogueTable::LicenseKeys, "fk_catalogue_license_keys_entity_id", "idx_catalogue_license_keys_entity_id"),
+            (CatalogueTable::SoftwareVersions, "fk_catalogue_software_versions_entity_id", "idx_catalogue_software_versions_entity_id"),
+            (CatalogueTable::EncryptionAlgorithms, "fk_catalogue_encryption_algorithms_entity_id", "idx_catalogue_encryption_algorithms_entity_id"),
+            (CatalogueTable::Relations, "fk_catalogue_relations_entity_id", "idx_catalogue_relations_entity_id"),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .add_column(ColumnDef::new(Catalogue::EntityId).uuid())
+                        .add_foreign_key(
+                            TableForeignKey::new()
+                                .name(fk)
+                                .from_tbl(table)
+                                .from_col(Catalogue::EntityId)
+                                .to_tbl(Entities::Table)
+                                .to_col(Entities::Id)
+                                .on_delete(ForeignKeyAction::Cascade)
+                                .on_update(ForeignKeyAction::Cascade),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+
+            manager
+                .create_index(
+                    Index::create()
+                        .name(idx)
+                        .table(table)
+                        .col(Catalogue::EntityId)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // Journal du cycle de vie : conservé après la suppression de l'entité
+        manager
+            .create_table(
+                Table::create()
+                    .table(EntityLifecycleEvents::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(EntityLifecycleEvents::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(EntityLifecycleEvents::EntityId).uuid().not_null())
+                    .col(ColumnDef::new(EntityLifecycleEvents::EntityName).string().not_null())
+                    .col(ColumnDef::new(EntityLifecycleEvents::Action).string().not_null())
+                    .col(ColumnDef::new(EntityLifecycleEvents::ActorId).uuid())
+                    .col(ColumnDef::new(EntityLifecycleEvents::Details).json_binary())
+                    .col(ColumnDef::new(EntityLifecycleEvents::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_entity_lifecycle_events_actor_id")
+                            .from(EntityLifecycleEvents::Table, EntityLifecycleEvents::ActorId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_entity_lifecycle_events_entity_id")
+                    .table(EntityLifecycleEvents::Table)
+                    .col(EntityLifecycleEvents::EntityId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EntityLifecycleEvents::Table).to_owned())
+            .await?;
+
+        for (table, fk) in [
+            (CatalogueTable::Endpoints, "fk_catalogue_endpoints_entity_id"),
+            (CatalogueTable::LicenseKeys, "fk_catalogue_license_keys_entity_id"),
+            (CatalogueTable::SoftwareVersions, "fk_catalogue_software_versions_entity_id"),
+            (CatalogueTable::EncryptionAlgorithms, "fk_catalogue_encryption_algorithms_entity_id"),
+            (CatalogueTable::Relations, "fk_catalogue_relations_entity_id"),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .drop_foreign_key(Alias::new(fk))
+                        .drop_column(Catalogue::EntityId)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Entities::Table)
+                    .drop_foreign_key(Alias::new("fk_entities_deletion_requested_by"))
+                    .drop_column(Entities::DeletionRequestedBy)
+                    .drop_column(Entities::DeletionScheduledAt)
+                    .drop_column(Entities::ArchivedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Entities {
+    Table,
+    Id,
+    ArchivedAt,
+    DeletionScheduledAt,
+    DeletionRequestedBy,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum CatalogueTable {
+    #[sea_orm(iden = "catalogue_endpoints")]
+    Endpoints,
+    #[sea_orm(iden = "catalogue_license_keys")]
+    LicenseKeys,
+    #[sea_orm(iden = "catalogue_software_versions")]
+    SoftwareVersions,
+    #[sea_orm(iden = "catalogue_encryption_algorithms")]
+    EncryptionAlgorithms,
+    #[sea_orm(iden = "catalogue_relations")]
+    Relations,
+}
+
+#[derive(DeriveIden)]
+enum Catalogue {
+    EntityId,
+}
+
+#[derive(DeriveIden)]
+enum EntityLifecycleEvents {
+    Table,
+    Id,
+    EntityId,
+    EntityName,
+    Action,
+    ActorId,
+    Details,
+    CreatedAt,
+}
@@ -0,0 +1,347 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Référentiels par entité : catégories de données, catégories de
+        // personnes concernées, tiers (responsables, DPO, sous-traitants,
+        // destinataires) et mesures de sécurité
+        for (table, unique) in [
+            (Referential::DataCategories, Some("idx_rgpd_data_categories_entity_name")),
+            (Referential::DataSubjects, Some("idx_rgpd_data_subjects_entity_name")),
+            (Referential::Parties, None),
+            (Referential::SecurityMeasures, None),
+        ] {
+            let mut create = Table::create();
+            create
+                .table(table)
+                .if_not_exists()
+                .col(ColumnDef::new(Rgpd::Id).uuid().not_null().primary_key())
+                .col(ColumnDef::new(Rgpd::EntityId).uuid().not_null())
+                .col(ColumnDef::new(Rgpd::Name).string().not_null())
+                .col(ColumnDef::new(Rgpd::Description).text());
+            match table {
+                Referential::Parties => {
+                    create
+                        .col(ColumnDef::new(Rgpd::Category).string().not_null())
+                        .col(ColumnDef::new(Rgpd::ContactName).string())
+                        .col(ColumnDef::new(Rgpd::Email).string())
+                        .col(ColumnDef::new(Rgpd::Phone).string())
+                        .col(ColumnDef::new(Rgpd::Address).text())
+                        .col(ColumnDef::new(Rgpd::Country).string_len(2));
+                }
+                Referential::SecurityMeasures => {
+                    create
+                        .col(ColumnDef::new(Rgpd::Category).string().not_null())
+                        .col(ColumnDef::new(Rgpd::CatalogueItemType).string())
+                        .col(ColumnDef::new(Rgpd::CatalogueItemId).uuid());
+                }
+                _ => {}
+            }
+            create
+                .col(ColumnDef::new(Rgpd::CreatedAt).timestamp_with_time_zone().not_null())
+                .col(ColumnDef::new(Rgpd::UpdatedAt).timestamp_with_time_zone().not_null())
+                .foreign_key(
+                    ForeignKey::create()
+                        .from(table, Rgpd::EntityId)
+                        .to(Entities::Table, Entities::Id)
+                        .on_delete(ForeignKeyAction::Cascade)
+                        .on_update(ForeignKeyAction::Cascade),
+                );
+            manager.create_table(create.to_owned()).await?;
+
+            let mut index = Index::create();
+            index.table(table).col(Rgpd::EntityId);
+            match unique {
+                Some(name) => index.name(name).col(Rgpd::Name).unique(),
+                None => index.name(format!("idx_{}_entity_id", table.to_string())),
+            };
+            manager.create_index(index.to_owned()).await?;
+        }
+
+        // Liens entre une entrée du registre et les référentiels
+        for (table, column, target) in [
+            (Link::DataCategories, Rgpd::DataCategoryId, Referential::DataCategories),
+            (Link::DataSubjects, Rgpd::DataSubjectId, Referential::DataSubjects),
+            (Link::Parties, Rgpd::PartyId, Referential::Parties),
+            (Link::SecurityMeasures, Rgpd::SecurityMeasureId, Referential::SecurityMeasures),
+        ] {
+            let mut create = Table::create();
+            create
+                .table(table)
+                .if_not_exists()
+                .col(ColumnDef::new(Rgpd::RegisterEntryId).uuid().not_null())
+                .col(ColumnDef::new(column).uuid().not_null());
+            let mut primary_key = Index::create();
+            primary_key.col(Rgpd::RegisterEntryId).col(column);
+            // Un même tiers peut intervenir à plusieurs titres (ex. DPO et destinataire)
+            if let Link::Parties = table {
+                create.col(ColumnDef::new(Rgpd::Role).string().not_null());
+                primary_key.col(Rgpd::Role);
+            }
+            create
+                .primary_key(&mut primary_key)
+                .foreign_key(
+                    ForeignKey::create()
+                        .from(table, Rgpd::RegisterEntryId)
+                        .to(RgpdRegister::Table, RgpdRegister::Id)
+                        .on_delete(ForeignKeyAction::Cascade)
+                        .on_update(ForeignKeyAction::Cascade),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .from(table, column)
+                        .to(target, Rgpd::Id)
+                        .on_delete(ForeignKeyAction::Cascade)
+                        .on_update(ForeignKeyAction::Cascade),
+                );
+            manager.create_table(create.to_owned()).await?;
+        }
+
+        // Transferts hors UE et garanties appropriées (Art. 30(1)(e), 46, 49)
+        manager
+            .create_table(
+                Table::create()
+                    .table(Transfers::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Transfers::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(Transfers::RegisterEntryId).uuid().not_null())
+                    .col(ColumnDef::new(Transfers::PartyId).uuid())
+                    .col(ColumnDef::new(Transfers::Country).string_len(2).not_null())
+                    .col(ColumnDef::new(Transfers::Safeguard).string().not_null())
+                    .col(ColumnDef::new(Transfers::SafeguardDetails).text())
+                    .col(ColumnDef::new(Transfers::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_transfers_register_entry_id")
+                            .from(Transfers::Table, Transfers::RegisterEntryId)
+                            .to(RgpdRegister::Table, RgpdRegister::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_transfers_party_id")
+                            .from(Transfers::Table, Transfers::PartyId)
+                            .to(Referential::Parties, Rgpd::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_transfers_register_entry_id")
+                    .table(Transfers::Table)
+                    .col(Transfers::RegisterEntryId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Registre du responsable (Art. 30(1)) ou du sous-traitant (Art. 30(2)) ;
+        // la base légale ne concerne que le premier
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdRegister::Table)
+                    .add_column(ColumnDef::new(RgpdRegister::RecordType).string().not_null().default("controller"))
+                    .modify_column(ColumnDef::new(RgpdRegister::LegalBasis).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Reprise des listes libres dans les référentiels
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "INSERT INTO rgpd_data_categories (id, entity_id, name, created_at, updated_at)
+             SELECT gen_random_uuid(), entity_id, name, now(), now() FROM (
+                 SELECT DISTINCT r.entity_id, btrim(v.value) AS name
+                 FROM rgpd_register r, jsonb_array_elements_text(COALESCE(r.data_categories, '[]'::jsonb)) v
+                 WHERE btrim(v.value) <> ''
+             ) s",
+        ).await?;
+        db.execute_unprepared(
+            "INSERT INTO rgpd_register_data_categories (register_entry_id, data_category_id)
+             SELECT DISTINCT r.id, c.id
+             FROM rgpd_register r, jsonb_array_elements_text(COALESCE(r.data_categories, '[]'::jsonb)) v, rgpd_data_categories c
+             WHERE c.entity_id = r.entity_id AND c.name = btrim(v.value)",
+        ).await?;
+        db.execute_unprepared(
+            "INSERT INTO rgpd_data_subjects (id, entity_id, name, created_at, updated_at)
+             SELECT gen_random_uuid(), entity_id, name, now(), now() FROM (
+                 SELECT DISTINCT r.entity_id, btrim(v.value) AS name
+                 FROM rgpd_register r, jsonb_array_elements_text(COALESCE(r.data_subjects, '[]'::jsonb)) v
+                 WHERE btrim(v.value) <> ''
+             ) s",
+        ).await?;
+        db.execute_unprepared(
+            "INSERT INTO rgpd_register_data_subjects (register_entry_id, data_subject_id)
+             SELECT DISTINCT r.id, c.id
+             FROM rgpd_register r, jsonb_array_elements_text(COALESCE(r.data_subjects, '[]'::jsonb)) v, rgpd_data_subjects c
+             WHERE c.entity_id = r.entity_id AND c.name = btrim(v.value)",
+        ).await?;
+        db.execute_unprepared(
+            "INSERT INTO rgpd_parties (id, entity_id, name, category, created_at, updated_at)
+             SELECT gen_random_uuid(), entity_id, name, 'organisation', now(), now() FROM (
+                 SELECT DISTINCT r.entity_id, btrim(v.value) AS name
+                 FROM rgpd_register r, jsonb_array_elements_text(COALESCE(r.recipients, '[]'::jsonb)) v
+                 WHERE btrim(v.value) <> ''
+             ) s",
+        ).await?;
+        db.execute_unprepared(
+            "INSERT INTO rgpd_register_parties (register_entry_id, party_id, role)
+             SELECT DISTINCT r.id, p.id, 'recipient'
+             FROM rgpd_register r, jsonb_array_elements_text(COALESCE(r.recipients, '[]'::jsonb)) v, rgpd_parties p
+             WHERE p.entity_id = r.entity_id AND p.name = btrim(v.value)",
+        ).await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdRegister::Table)
+                    .drop_column(RgpdRegister::DataCategories)
+                    .drop_column(RgpdRegister::DataSubjects)
+                    .drop_column(RgpdRegister::Recipients)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdRegister::Table)
+                    .add_column(ColumnDef::new(RgpdRegister::DataCategories).json_binary())
+                    .add_column(ColumnDef::new(RgpdRegister::DataSubjects).json_binary())
+                    .add_column(ColumnDef::new(RgpdRegister::Recipients).json_binary())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Les listes sont reconstituées à partir des noms des référentiels
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "UPDATE rgpd_register r SET
+                 data_categories = COALESCE((SELECT jsonb_agg(c.name ORDER BY c.name) FROM rgpd_register_data_categories l
+                     JOIN rgpd_data_categories c ON c.id = l.data_category_id WHERE l.register_entry_id = r.id), '[]'::jsonb),
+                 data_subjects = COALESCE((SELECT jsonb_agg(c.name ORDER BY c.name) FROM rgpd_register_data_subjects l
+                     JOIN rgpd_data_subjects c ON c.id = l.data_subject_id WHERE l.register_entry_id = r.id), '[]'::jsonb),
+                 recipients = COALESCE((SELECT jsonb_agg(DISTINCT p.name) FROM rgpd_register_parties l
+                     JOIN rgpd_parties p ON p.id = l.party_id WHERE l.register_entry_id = r.id AND l.role IN ('recipient', 'processor')), '[]'::jsonb),
+                 legal_basis = COALESCE(r.legal_basis, '')",
+        ).await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdRegister::Table)
+                    .drop_column(RgpdRegister::RecordType)
+                    .modify_column(ColumnDef::new(RgpdRegister::LegalBasis).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Transfers::Table).to_owned())
+            .await?;
+        for table in [Link::DataCategories, Link::DataSubjects, Link::Parties, Link::SecurityMeasures] {
+            manager
+                .drop_table(Table::drop().table(table).to_owned())
+                .await?;
+        }
+        for table in [Referential::DataCategories, Referential::DataSubjects, Referential::Parties, Referential::SecurityMeasures] {
+            manager
+                .drop_table(Table::drop().table(table).to_owned())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Entities {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum RgpdRegister {
+    Table,
+    Id,
+    LegalBasis,
+    DataCategories,
+    DataSubjects,
+    Recipients,
+    RecordType,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum Referential {
+    #[sea_orm(iden = "rgpd_data_categories")]
+    DataCategories,
+    #[sea_orm(iden = "rgpd_data_subjects")]
+    DataSubjects,
+    #[sea_orm(iden = "rgpd_parties")]
+    Parties,
+    #[sea_orm(iden = "rgpd_security_measures")]
+    SecurityMeasures,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum Link {
+    #[sea_orm(iden = "rgpd_register_data_categories")]
+    DataCategories,
+    #[sea_orm(iden = "rgpd_register_data_subjects")]
+    DataSubjects,
+    #[sea_orm(iden = "rgpd_register_parties")]
+    Parties,
+    #[sea_orm(iden = "rgpd_register_security_measures")]
+    SecurityMeasures,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum Rgpd {
+    Id,
+    EntityId,
+    Name,
+    Description,
+    Category,
+    ContactName,
+    Email,
+    Phone,
+    Address,
+    Country,
+    CatalogueItemType,
+    CatalogueItemId,
+    RegisterEntryId,
+    DataCategoryId,
+    DataSubjectId,
+    PartyId,
+    SecurityMeasureId,
+    Role,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Transfers {
+    #[sea_orm(iden = "rgpd_transfers")]
+    Table,
+    Id,
+    RegisterEntryId,
+    PartyId,
+    Country,
+    Safeguard,
+    SafeguardDetails,
+    CreatedAt,
+}
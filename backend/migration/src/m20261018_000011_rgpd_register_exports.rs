@@ -0,0 +1,91 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Journal des exports du registre : chaque document produit reçoit un
+        // numéro de version propre à l'entité et l'empreinte de son contenu
+        manager
+            .create_table(
+                Table::create()
+                    .table(RgpdRegisterExports::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RgpdRegisterExports::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RgpdRegisterExports::EntityId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdRegisterExports::Version).integer().not_null())
+                    .col(ColumnDef::new(RgpdRegisterExports::Format).string().not_null())
+                    .col(ColumnDef::new(RgpdRegisterExports::EntryCount).integer().not_null())
+                    .col(ColumnDef::new(RgpdRegisterExports::Sha256).string().not_null())
+                    .col(ColumnDef::new(RgpdRegisterExports::GeneratedBy).uuid())
+                    .col(ColumnDef::new(RgpdRegisterExports::GeneratedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_register_exports_entity_id")
+                            .from(RgpdRegisterExports::Table, RgpdRegisterExports::EntityId)
+                            .to(Entities::Table, Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_register_exports_generated_by")
+                            .from(RgpdRegisterExports::Table, RgpdRegisterExports::GeneratedBy)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_register_exports_entity_version")
+                    .table(RgpdRegisterExports::Table)
+                    .col(RgpdRegisterExports::EntityId)
+                    .col(RgpdRegisterExports::Version)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RgpdRegisterExports::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Entities {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum RgpdRegisterExports {
+    Table,
+    Id,
+    EntityId,
+    Version,
+    Format,
+    EntryCount,
+    Sha256,
+    GeneratedBy,
+    GeneratedAt,
+}
@@ -0,0 +1,365 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Analyse d'impact (Art. 35) : une par fiche du registre, avec le
+        // questionnaire de pré-évaluation, les sections de l'analyse, l'avis du
+        // DPO et la validation du responsable
+        manager
+            .create_table(
+                Table::create()
+                    .table(RgpdDpias::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RgpdDpias::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RgpdDpias::EntityId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdDpias::RegisterEntryId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdDpias::Title).string().not_null())
+                    .col(ColumnDef::new(RgpdDpias::Status).string().not_null().default("screening"))
+                    .col(ColumnDef::new(RgpdDpias::ScreeningCriteria).json_binary().not_null().default(Expr::cust("'[]'::jsonb")))
+                    .col(ColumnDef::new(RgpdDpias::ScreeningJustification).text())
+                    .col(ColumnDef::new(RgpdDpias::ScreenedBy).uuid())
+                    .col(ColumnDef::new(RgpdDpias::ScreenedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(RgpdDpias::Context).text())
+                    .col(ColumnDef::new(RgpdDpias::Necessity).text())
+                    .col(ColumnDef::new(RgpdDpias::Proportionality).text())
+                    .col(ColumnDef::new(RgpdDpias::DataSubjectRights).text())
+                    .col(ColumnDef::new(RgpdDpias::DpoRecommendation).string())
+                    .col(ColumnDef::new(RgpdDpias::DpoOpinion).text())
+                    .col(ColumnDef::new(RgpdDpias::DpoOpinionBy).uuid())
+                    .col(ColumnDef::new(RgpdDpias::DpoOpinionAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(RgpdDpias::Version).integer().not_null().default(0))
+                    .col(ColumnDef::new(RgpdDpias::NextReviewAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(RgpdDpias::LastReviewedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(RgpdDpias::CreatedBy).uuid())
+                    .col(ColumnDef::new(RgpdDpias::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(RgpdDpias::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_dpias_entity_id")
+                            .from(RgpdDpias::Table, RgpdDpias::EntityId)
+                            .to(Entities::Table, Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_dpias_register_entry_id")
+                            .from(RgpdDpias::Table, RgpdDpias::RegisterEntryId)
+                            .to(RgpdRegister::Table, RgpdRegister::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(&mut user_fk("fk_rgpd_dpias_screened_by", RgpdDpias::Table, RgpdDpias::ScreenedBy))
+                    .foreign_key(&mut user_fk("fk_rgpd_dpias_dpo_opinion_by", RgpdDpias::Table, RgpdDpias::DpoOpinionBy))
+                    .foreign_key(&mut user_fk("fk_rgpd_dpias_created_by", RgpdDpias::Table, RgpdDpias::CreatedBy))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_dpias_register_entry_id")
+                    .table(RgpdDpias::Table)
+                    .col(RgpdDpias::RegisterEntryId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_dpias_entity_id")
+                    .table(RgpdDpias::Table)
+                    .col(RgpdDpias::EntityId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Risques pour les droits et libertés : vraisemblance et gravité (1 à 4),
+        // avant et après mesures
+        manager
+            .create_table(
+                Table::create()
+                    .table(RgpdDpiaRisks::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RgpdDpiaRisks::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RgpdDpiaRisks::DpiaId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdDpiaRisks::RiskType).string().not_null())
+                    .col(ColumnDef::new(RgpdDpiaRisks::Description).text().not_null())
+                    .col(ColumnDef::new(RgpdDpiaRisks::Impacts).text())
+                    .col(ColumnDef::new(RgpdDpiaRisks::Threats).text())
+                    .col(ColumnDef::new(RgpdDpiaRisks::Likelihood).integer().not_null())
+                    .col(ColumnDef::new(RgpdDpiaRisks::Severity).integer().not_null())
+                    .col(ColumnDef::new(RgpdDpiaRisks::ResidualLikelihood).integer())
+                    .col(ColumnDef::new(RgpdDpiaRisks::ResidualSeverity).integer())
+                    .col(ColumnDef::new(RgpdDpiaRisks::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(RgpdDpiaRisks::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_dpia_risks_dpia_id")
+                            .from(RgpdDpiaRisks::Table, RgpdDpiaRisks::DpiaId)
+                            .to(RgpdDpias::Table, RgpdDpias::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Mesures prévues, rattachées au risque qu'elles traitent et, en option,
+        // à une mesure de sécurité du référentiel
+        manager
+            .create_table(
+                Table::create()
+                    .table(RgpdDpiaMeasures::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RgpdDpiaMeasures::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RgpdDpiaMeasures::DpiaId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdDpiaMeasures::RiskId).uuid())
+                    .col(ColumnDef::new(RgpdDpiaMeasures::SecurityMeasureId).uuid())
+                    .col(ColumnDef::new(RgpdDpiaMeasures::Description).text().not_null())
+                    .col(ColumnDef::new(RgpdDpiaMeasures::MeasureType).string().not_null())
+                    .col(ColumnDef::new(RgpdDpiaMeasures::Status).string().not_null().default("planned"))
+                    .col(ColumnDef::new(RgpdDpiaMeasures::Owner).string())
+                    .col(ColumnDef::new(RgpdDpiaMeasures::DueDate).timestamp_with_time_zone())
+                    .col(ColumnDef::new(RgpdDpiaMeasures::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(RgpdDpiaMeasures::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_dpia_measures_dpia_id")
+                            .from(RgpdDpiaMeasures::Table, RgpdDpiaMeasures::DpiaId)
+                            .to(RgpdDpias::Table, RgpdDpias::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_dpia_measures_risk_id")
+                            .from(RgpdDpiaMeasures::Table, RgpdDpiaMeasures::RiskId)
+                            .to(RgpdDpiaRisks::Table, RgpdDpiaRisks::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_dpia_measures_security_measure_id")
+                            .from(RgpdDpiaMeasures::Table, RgpdDpiaMeasures::SecurityMeasureId)
+                            .to(RgpdSecurityMeasures::Table, RgpdSecurityMeasures::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Versions validées : copie figée de l'analyse à chaque décision
+        manager
+            .create_table(
+                Table::create()
+                    .table(RgpdDpiaVersions::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RgpdDpiaVersions::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RgpdDpiaVersions::DpiaId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdDpiaVersions::Version).integer().not_null())
+                    .col(ColumnDef::new(RgpdDpiaVersions::Decision).string().not_null())
+                    .col(ColumnDef::new(RgpdDpiaVersions::Comment).text())
+                    .col(ColumnDef::new(RgpdDpiaVersions::Snapshot).json_binary().not_null())
+                    .col(ColumnDef::new(RgpdDpiaVersions::SignedOffBy).uuid())
+                    .col(ColumnDef::new(RgpdDpiaVersions::SignedOffAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_dpia_versions_dpia_id")
+                            .from(RgpdDpiaVersions::Table, RgpdDpiaVersions::DpiaId)
+                            .to(RgpdDpias::Table, RgpdDpias::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(&mut user_fk("fk_rgpd_dpia_versions_signed_off_by", RgpdDpiaVersions::Table, RgpdDpiaVersions::SignedOffBy))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_dpia_versions_dpia_version")
+                    .table(RgpdDpiaVersions::Table)
+                    .col(RgpdDpiaVersions::DpiaId)
+                    .col(RgpdDpiaVersions::Version)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Revues périodiques d'une analyse validée
+        manager
+            .create_table(
+                Table::create()
+                    .table(RgpdDpiaReviews::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RgpdDpiaReviews::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RgpdDpiaReviews::DpiaId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdDpiaReviews::Version).integer().not_null())
+                    .col(ColumnDef::new(RgpdDpiaReviews::Outcome).string().not_null())
+                    .col(ColumnDef::new(RgpdDpiaReviews::Comment).text())
+                    .col(ColumnDef::new(RgpdDpiaReviews::ReviewedBy).uuid())
+                    .col(ColumnDef::new(RgpdDpiaReviews::ReviewedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_dpia_reviews_dpia_id")
+                            .from(RgpdDpiaReviews::Table, RgpdDpiaReviews::DpiaId)
+                            .to(RgpdDpias::Table, RgpdDpias::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(&mut user_fk("fk_rgpd_dpia_reviews_reviewed_by", RgpdDpiaReviews::Table, RgpdDpiaReviews::ReviewedBy))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in [
+            RgpdDpiaReviews::Table.into_iden(),
+            RgpdDpiaVersions::Table.into_iden(),
+            RgpdDpiaMeasures::Table.into_iden(),
+            RgpdDpiaRisks::Table.into_iden(),
+            RgpdDpias::Table.into_iden(),
+        ] {
+            manager
+                .drop_table(Table::drop().table(table).to_owned())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+// Auteur d'une étape : conservé à NULL si le compte disparaît
+fn user_fk(name: &str, table: impl IntoIden, column: impl IntoIden) -> ForeignKeyCreateStatement {
+    ForeignKey::create()
+        .name(name)
+        .from(table, column)
+        .to(Users::Table, Users::Id)
+        .on_delete(ForeignKeyAction::SetNull)
+        .on_update(ForeignKeyAction::Cascade)
+        .to_owned()
+}
+
+#[derive(DeriveIden)]
+enum Entities {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum RgpdRegister {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum RgpdSecurityMeasures {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum RgpdDpias {
+    Table,
+    Id,
+    EntityId,
+    RegisterEntryId,
+    Title,
+    Status,
+    ScreeningCriteria,
+    ScreeningJustification,
+    ScreenedBy,
+    ScreenedAt,
+    Context,
+    Necessity,
+    Proportionality,
+    DataSubjectRights,
+    DpoRecommendation,
+    DpoOpinion,
+    DpoOpinionBy,
+    DpoOpinionAt,
+    Version,
+    NextReviewAt,
+    LastReviewedAt,
+    CreatedBy,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum RgpdDpiaRisks {
+    Table,
+    Id,
+    DpiaId,
+    RiskType,
+    Description,
+    Impacts,
+    Threats,
+    Likelihood,
+    Severity,
+    ResidualLikelihood,
+    ResidualSeverity,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum RgpdDpiaMeasures {
+    Table,
+    Id,
+    DpiaId,
+    RiskId,
+    SecurityMeasureId,
+    Description,
+    MeasureType,
+    Status,
+    Owner,
+    DueDate,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum RgpdDpiaVersions {
+    Table,
+    Id,
+    DpiaId,
+    Version,
+    Decision,
+    Comment,
+    Snapshot,
+    SignedOffBy,
+    SignedOffAt,
+}
+
+#[derive(DeriveIden)]
+enum RgpdDpiaReviews {
+    Table,
+    Id,
+    DpiaId,
+    Version,
+    Outcome,
+    Comment,
+    ReviewedBy,
+    ReviewedAt,
+}
@@ -0,0 +1,138 @@
+use sea_orm_migration::prelude::*;
+
+// Bases légales de l'article 6 reconnues dans le texte libre saisi jusqu'ici,
+// dans l'ordre où elles sont recherchées
+const LEGAL_BASES: &[(&str, &[&str])] = &[
+    ("consent", &["consent"]),
+    ("contract", &["contrat", "contract"]),
+    ("legal_obligation", &["obligation"]),
+    ("vital_interests", &["vital", "vitaux"]),
+    ("legitimate_interests", &["légitime", "legitime", "legitimate"]),
+    ("public_task", &["public", "publique"]),
+];
+
+// Catégories sensibles reconnues dans le nom des catégories existantes
+const KINDS: &[(&str, &[&str])] = &[
+    ("health", &["santé", "sante", "health", "médical", "medical"]),
+    ("genetic", &["génétique", "genetique", "genetic"]),
+    ("biometric", &["biométri", "biometri"]),
+    ("racial_ethnic_origin", &["ethnique", "ethnic", "racial"]),
+    ("political_opinions", &["politique", "political"]),
+    ("religious_beliefs", &["religi", "philosophi"]),
+    ("trade_union_membership", &["syndic", "trade union"]),
+    ("sex_life_orientation", &["sexuel", "sexual"]),
+    ("criminal_offences", &["infraction", "condamnation", "pénal", "criminal"]),
+];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdRegister::Table)
+                    .add_column(ColumnDef::new(RgpdRegister::LegalBasisDetails).text())
+                    .add_column(ColumnDef::new(RgpdRegister::SpecialCategoryException).string())
+                    .add_column(ColumnDef::new(RgpdRegister::CriminalDataBasis).text())
+                    .add_column(ColumnDef::new(RgpdRegister::ConsentMechanism).text())
+                    .add_column(ColumnDef::new(RgpdRegister::RetentionMonths).integer())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DataCategories::Table)
+                    .add_column(ColumnDef::new(DataCategories::Kind).string().not_null().default("other"))
+                    .add_column(ColumnDef::new(DataCategories::RetentionCeilingMonths).integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Le texte libre est conservé en précision ; une base non reconnue
+        // reste à qualifier
+        let db = manager.get_connection();
+        let keys = LEGAL_BASES.iter().map(|(key, _)| format!("'{}'", key)).collect::<Vec<_>>().join(", ");
+        db.execute_unprepared(&format!(
+            "UPDATE rgpd_register SET legal_basis_details = legal_basis
+             WHERE btrim(COALESCE(legal_basis, '')) <> '' AND legal_basis NOT IN ({})",
+            keys,
+        )).await?;
+        for (key, keywords) in LEGAL_BASES {
+            for keyword in *keywords {
+                db.execute_unprepared(&format!(
+                    "UPDATE rgpd_register SET legal_basis = '{}'
+                     WHERE legal_basis = legal_basis_details AND lower(legal_basis_details) LIKE '%{}%'",
+                    key, keyword,
+                )).await?;
+            }
+        }
+        db.execute_unprepared(&format!(
+            "UPDATE rgpd_register SET legal_basis = NULL WHERE legal_basis NOT IN ({})",
+            keys,
+        )).await?;
+
+        for (kind, keywords) in KINDS {
+            for keyword in *keywords {
+                db.execute_unprepared(&format!(
+                    "UPDATE rgpd_data_categories SET kind = '{}' WHERE kind = 'other' AND lower(name) LIKE '%{}%'",
+                    kind, keyword,
+                )).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "UPDATE rgpd_register SET legal_basis = COALESCE(legal_basis_details, legal_basis)",
+        ).await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DataCategories::Table)
+                    .drop_column(DataCategories::Kind)
+                    .drop_column(DataCategories::RetentionCeilingMonths)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdRegister::Table)
+                    .drop_column(RgpdRegister::LegalBasisDetails)
+                    .drop_column(RgpdRegister::SpecialCategoryException)
+                    .drop_column(RgpdRegister::CriminalDataBasis)
+                    .drop_column(RgpdRegister::ConsentMechanism)
+                    .drop_column(RgpdRegister::RetentionMonths)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum RgpdRegister {
+    Table,
+    LegalBasisDetails,
+    SpecialCategoryException,
+    CriminalDataBasis,
+    ConsentMechanism,
+    RetentionMonths,
+}
+
+#[derive(DeriveIden)]
+enum DataCategories {
+    #[sea_orm(iden = "rgpd_data_categories")]
+    Table,
+    Kind,
+    RetentionCeilingMonths,
+}
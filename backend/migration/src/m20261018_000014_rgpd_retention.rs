@@ -0,0 +1,306 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Règle de conservation structurée des fiches : la durée existante
+        // (retention_months), l'événement qui la déclenche et le sort des données
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdRegister::Table)
+                    .add_column(ColumnDef::new(RgpdRegister::RetentionTrigger).string())
+                    .add_column(ColumnDef::new(RgpdRegister::RetentionAction).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Dates de clôture servant de point de départ, et trace de l'anonymisation
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdAccessRequests::Table)
+                    .add_column(ColumnDef::new(RgpdAccessRequests::AnonymizedAt).timestamp_with_time_zone())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdBreaches::Table)
+                    .add_column(ColumnDef::new(RgpdBreaches::ClosedAt).timestamp_with_time_zone())
+                    .add_column(ColumnDef::new(RgpdBreaches::AnonymizedAt).timestamp_with_time_zone())
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "UPDATE rgpd_access_requests SET completed_at = updated_at
+             WHERE status = 'rejected' AND completed_at IS NULL",
+        ).await?;
+        db.execute_unprepared(
+            "UPDATE rgpd_breaches SET closed_at = updated_at
+             WHERE status IN ('resolved', 'reported')",
+        ).await?;
+
+        // Pièces jointes des violations
+        manager
+            .create_table(
+                Table::create()
+                    .table(RgpdBreachAttachments::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RgpdBreachAttachments::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RgpdBreachAttachments::EntityId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdBreachAttachments::BreachId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdBreachAttachments::FileName).string().not_null())
+                    .col(ColumnDef::new(RgpdBreachAttachments::ContentType).string())
+                    .col(ColumnDef::new(RgpdBreachAttachments::FileSize).big_integer().not_null())
+                    .col(ColumnDef::new(RgpdBreachAttachments::StorageType).string().not_null())
+                    .col(ColumnDef::new(RgpdBreachAttachments::FilePath).string().not_null())
+                    .col(ColumnDef::new(RgpdBreachAttachments::UploadedBy).uuid())
+                    .col(ColumnDef::new(RgpdBreachAttachments::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_breach_attachments_entity_id")
+                            .from(RgpdBreachAttachments::Table, RgpdBreachAttachments::EntityId)
+                            .to(Entities::Table, Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_breach_attachments_breach_id")
+                            .from(RgpdBreachAttachments::Table, RgpdBreachAttachments::BreachId)
+                            .to(RgpdBreaches::Table, RgpdBreaches::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(&mut user_fk("fk_rgpd_breach_attachments_uploaded_by", RgpdBreachAttachments::Table, RgpdBreachAttachments::UploadedBy))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_breach_attachments_breach_id")
+                    .table(RgpdBreachAttachments::Table)
+                    .col(RgpdBreachAttachments::BreachId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Politiques de conservation des données tenues par HyperCyber, une par
+        // type d'enregistrement et par entité ; à défaut, la politique par défaut s'applique
+        manager
+            .create_table(
+                Table::create()
+                    .table(RgpdRetentionPolicies::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RgpdRetentionPolicies::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RgpdRetentionPolicies::EntityId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdRetentionPolicies::RecordType).string().not_null())
+                    .col(ColumnDef::new(RgpdRetentionPolicies::DurationMonths).integer().not_null())
+                    .col(ColumnDef::new(RgpdRetentionPolicies::Trigger).string().not_null())
+                    .col(ColumnDef::new(RgpdRetentionPolicies::Action).string().not_null())
+                    .col(ColumnDef::new(RgpdRetentionPolicies::Enabled).boolean().not_null().default(true))
+                    .col(ColumnDef::new(RgpdRetentionPolicies::UpdatedBy).uuid())
+                    .col(ColumnDef::new(RgpdRetentionPolicies::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(RgpdRetentionPolicies::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_retention_policies_entity_id")
+                            .from(RgpdRetentionPolicies::Table, RgpdRetentionPolicies::EntityId)
+                            .to(Entities::Table, Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(&mut user_fk("fk_rgpd_retention_policies_updated_by", RgpdRetentionPolicies::Table, RgpdRetentionPolicies::UpdatedBy))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_retention_policies_entity_record_type")
+                    .table(RgpdRetentionPolicies::Table)
+                    .col(RgpdRetentionPolicies::EntityId)
+                    .col(RgpdRetentionPolicies::RecordType)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Rapport d'exécution : une ligne par entité et par passage
+        manager
+            .create_table(
+                Table::create()
+                    .table(RgpdRetentionRuns::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RgpdRetentionRuns::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RgpdRetentionRuns::EntityId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdRetentionRuns::TriggeredBy).uuid())
+                    .col(ColumnDef::new(RgpdRetentionRuns::DryRun).boolean().not_null().default(false))
+                    .col(ColumnDef::new(RgpdRetentionRuns::Status).string().not_null())
+                    .col(ColumnDef::new(RgpdRetentionRuns::Report).json_binary().not_null().default(Expr::cust("'[]'::jsonb")))
+                    .col(ColumnDef::new(RgpdRetentionRuns::Error).text())
+                    .col(ColumnDef::new(RgpdRetentionRuns::StartedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(RgpdRetentionRuns::FinishedAt).timestamp_with_time_zone())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_retention_runs_entity_id")
+                            .from(RgpdRetentionRuns::Table, RgpdRetentionRuns::EntityId)
+                            .to(Entities::Table, Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(&mut user_fk("fk_rgpd_retention_runs_triggered_by", RgpdRetentionRuns::Table, RgpdRetentionRuns::TriggeredBy))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_retention_runs_entity_started_at")
+                    .table(RgpdRetentionRuns::Table)
+                    .col(RgpdRetentionRuns::EntityId)
+                    .col(RgpdRetentionRuns::StartedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in [
+            RgpdRetentionRuns::Table.into_iden(),
+            RgpdRetentionPolicies::Table.into_iden(),
+            RgpdBreachAttachments::Table.into_iden(),
+        ] {
+            manager
+                .drop_table(Table::drop().table(table).to_owned())
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdBreaches::Table)
+                    .drop_column(RgpdBreaches::ClosedAt)
+                    .drop_column(RgpdBreaches::AnonymizedAt)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdAccessRequests::Table)
+                    .drop_column(RgpdAccessRequests::AnonymizedAt)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdRegister::Table)
+                    .drop_column(RgpdRegister::RetentionTrigger)
+                    .drop_column(RgpdRegister::RetentionAction)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+// Auteur d'une action : conservé à NULL si le compte disparaît
+fn user_fk(name: &str, table: impl IntoIden, column: impl IntoIden) -> ForeignKeyCreateStatement {
+    ForeignKey::create()
+        .name(name)
+        .from(table, column)
+        .to(Users::Table, Users::Id)
+        .on_delete(ForeignKeyAction::SetNull)
+        .on_update(ForeignKeyAction::Cascade)
+        .to_owned()
+}
+
+#[derive(DeriveIden)]
+enum Entities {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum RgpdRegister {
+    Table,
+    RetentionTrigger,
+    RetentionAction,
+}
+
+#[derive(DeriveIden)]
+enum RgpdAccessRequests {
+    Table,
+    AnonymizedAt,
+}
+
+#[derive(DeriveIden)]
+enum RgpdBreaches {
+    Table,
+    Id,
+    ClosedAt,
+    AnonymizedAt,
+}
+
+#[derive(DeriveIden)]
+enum RgpdBreachAttachments {
+    Table,
+    Id,
+    EntityId,
+    BreachId,
+    FileName,
+    ContentType,
+    FileSize,
+    StorageType,
+    FilePath,
+    UploadedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum RgpdRetentionPolicies {
+    Table,
+    Id,
+    EntityId,
+    RecordType,
+    DurationMonths,
+    Trigger,
+    Action,
+    Enabled,
+    UpdatedBy,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum RgpdRetentionRuns {
+    Table,
+    Id,
+    EntityId,
+    TriggeredBy,
+    DryRun,
+    Status,
+    Report,
+    Error,
+    StartedAt,
+    FinishedAt,
+}
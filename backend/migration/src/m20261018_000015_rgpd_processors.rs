@@ -0,0 +1,207 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Fiche fournisseur d'un tiers agissant comme sous-traitant (Art. 28) :
+        // contrat de sous-traitance, encadrement des transferts, évaluation sécurité
+        manager
+            .create_table(
+                Table::create()
+                    .table(RgpdProcessors::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RgpdProcessors::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RgpdProcessors::EntityId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdProcessors::PartyId).uuid().not_null().unique_key())
+                    .col(ColumnDef::new(RgpdProcessors::Services).text())
+                    .col(ColumnDef::new(RgpdProcessors::TransferMechanism).string())
+                    .col(ColumnDef::new(RgpdProcessors::TransferDetails).text())
+                    .col(ColumnDef::new(RgpdProcessors::DpaReference).string())
+                    .col(ColumnDef::new(RgpdProcessors::DpaSignedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(RgpdProcessors::DpaExpiresAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(RgpdProcessors::SecurityScore).integer())
+                    .col(ColumnDef::new(RgpdProcessors::SecurityAssessedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(RgpdProcessors::Notes).text())
+                    .col(ColumnDef::new(RgpdProcessors::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(RgpdProcessors::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_processors_entity_id")
+                            .from(RgpdProcessors::Table, RgpdProcessors::EntityId)
+                            .to(Entities::Table, Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_processors_party_id")
+                            .from(RgpdProcessors::Table, RgpdProcessors::PartyId)
+                            .to(RgpdParties::Table, RgpdParties::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_processors_entity_id")
+                    .table(RgpdProcessors::Table)
+                    .col(RgpdProcessors::EntityId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Sous-traitants ultérieurs autorisés (Art. 28(2) et 28(4))
+        manager
+            .create_table(
+                Table::create()
+                    .table(RgpdSubProcessors::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RgpdSubProcessors::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RgpdSubProcessors::ProcessorId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdSubProcessors::PartyId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdSubProcessors::Service).text())
+                    .col(ColumnDef::new(RgpdSubProcessors::AuthorisedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(RgpdSubProcessors::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_sub_processors_processor_id")
+                            .from(RgpdSubProcessors::Table, RgpdSubProcessors::ProcessorId)
+                            .to(RgpdProcessors::Table, RgpdProcessors::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_sub_processors_party_id")
+                            .from(RgpdSubProcessors::Table, RgpdSubProcessors::PartyId)
+                            .to(RgpdParties::Table, RgpdParties::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_sub_processors_processor_party")
+                    .table(RgpdSubProcessors::Table)
+                    .col(RgpdSubProcessors::ProcessorId)
+                    .col(RgpdSubProcessors::PartyId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Équipements du catalogue hébergés ou opérés par le fournisseur ; le lien
+        // est propre à l'entité, les équipements partagés pouvant être liés par chacune
+        manager
+            .create_table(
+                Table::create()
+                    .table(RgpdProcessorEndpoints::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RgpdProcessorEndpoints::ProcessorId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdProcessorEndpoints::EndpointId).uuid().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(RgpdProcessorEndpoints::ProcessorId)
+                            .col(RgpdProcessorEndpoints::EndpointId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_processor_endpoints_processor_id")
+                            .from(RgpdProcessorEndpoints::Table, RgpdProcessorEndpoints::ProcessorId)
+                            .to(RgpdProcessors::Table, RgpdProcessors::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_processor_endpoints_endpoint_id")
+                            .from(RgpdProcessorEndpoints::Table, RgpdProcessorEndpoints::EndpointId)
+                            .to(CatalogueEndpoints::Table, CatalogueEndpoints::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in [
+            RgpdProcessorEndpoints::Table.into_iden(),
+            RgpdSubProcessors::Table.into_iden(),
+            RgpdProcessors::Table.into_iden(),
+        ] {
+            manager
+                .drop_table(Table::drop().table(table).to_owned())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Entities {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum RgpdParties {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum CatalogueEndpoints {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum RgpdProcessors {
+    Table,
+    Id,
+    EntityId,
+    PartyId,
+    Services,
+    TransferMechanism,
+    TransferDetails,
+    DpaReference,
+    DpaSignedAt,
+    DpaExpiresAt,
+    SecurityScore,
+    SecurityAssessedAt,
+    Notes,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum RgpdSubProcessors {
+    Table,
+    Id,
+    ProcessorId,
+    PartyId,
+    Service,
+    AuthorisedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum RgpdProcessorEndpoints {
+    Table,
+    ProcessorId,
+    EndpointId,
+}
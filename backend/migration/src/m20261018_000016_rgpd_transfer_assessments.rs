@@ -0,0 +1,139 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Un transfert relève d'une fiche du registre ou d'une fiche fournisseur ;
+        // il précise le module des clauses types, les mesures supplémentaires et
+        // l'évaluation d'impact du transfert (arrêt Schrems II)
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdTransfers::Table)
+                    .modify_column(ColumnDef::new(RgpdTransfers::RegisterEntryId).uuid().null())
+                    .add_column(ColumnDef::new(RgpdTransfers::ProcessorId).uuid())
+                    .add_column(ColumnDef::new(RgpdTransfers::SccModule).string())
+                    .add_column(ColumnDef::new(RgpdTransfers::SupplementaryMeasures).text())
+                    .add_column(ColumnDef::new(RgpdTransfers::TiaOutcome).string())
+                    .add_column(ColumnDef::new(RgpdTransfers::TiaNotes).text())
+                    .add_column(ColumnDef::new(RgpdTransfers::TiaAssessedAt).timestamp_with_time_zone())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_rgpd_transfers_processor_id")
+                            .from_tbl(RgpdTransfers::Table)
+                            .from_col(RgpdTransfers::ProcessorId)
+                            .to_tbl(RgpdProcessors::Table)
+                            .to_col(RgpdProcessors::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_transfers_processor_id")
+                    .table(RgpdTransfers::Table)
+                    .col(RgpdTransfers::ProcessorId)
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "ALTER TABLE rgpd_transfers ADD CONSTRAINT chk_rgpd_transfers_owner
+             CHECK ((register_entry_id IS NULL) <> (processor_id IS NULL))",
+        ).await?;
+
+        // Le mécanisme saisi sur la fiche fournisseur devient un transfert vers le
+        // pays du tiers ; sans pays connu, il est conservé dans les notes
+        db.execute_unprepared(
+            "INSERT INTO rgpd_transfers (id, processor_id, party_id, country, safeguard, safeguard_details, created_at)
+             SELECT gen_random_uuid(), p.id, p.party_id, t.country, p.transfer_mechanism, p.transfer_details, p.updated_at
+             FROM rgpd_processors p JOIN rgpd_parties t ON t.id = p.party_id
+             WHERE p.transfer_mechanism IS NOT NULL AND t.country IS NOT NULL",
+        ).await?;
+        db.execute_unprepared(
+            "UPDATE rgpd_processors p SET notes = concat_ws(E'\\n\\n', p.notes,
+                 'Transfer mechanism: ' || p.transfer_mechanism || coalesce(' — ' || p.transfer_details, ''))
+             FROM rgpd_parties t
+             WHERE t.id = p.party_id AND p.transfer_mechanism IS NOT NULL AND t.country IS NULL",
+        ).await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdProcessors::Table)
+                    .drop_column(RgpdProcessors::TransferMechanism)
+                    .drop_column(RgpdProcessors::TransferDetails)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdProcessors::Table)
+                    .add_column(ColumnDef::new(RgpdProcessors::TransferMechanism).string())
+                    .add_column(ColumnDef::new(RgpdProcessors::TransferDetails).text())
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "UPDATE rgpd_processors p SET transfer_mechanism = t.safeguard, transfer_details = t.safeguard_details
+             FROM (SELECT DISTINCT ON (processor_id) processor_id, safeguard, safeguard_details
+                   FROM rgpd_transfers WHERE processor_id IS NOT NULL
+                   ORDER BY processor_id, created_at) t
+             WHERE t.processor_id = p.id",
+        ).await?;
+        db.execute_unprepared("DELETE FROM rgpd_transfers WHERE processor_id IS NOT NULL").await?;
+        db.execute_unprepared("ALTER TABLE rgpd_transfers DROP CONSTRAINT chk_rgpd_transfers_owner").await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdTransfers::Table)
+                    .drop_foreign_key(Alias::new("fk_rgpd_transfers_processor_id"))
+                    .drop_column(RgpdTransfers::ProcessorId)
+                    .drop_column(RgpdTransfers::SccModule)
+                    .drop_column(RgpdTransfers::SupplementaryMeasures)
+                    .drop_column(RgpdTransfers::TiaOutcome)
+                    .drop_column(RgpdTransfers::TiaNotes)
+                    .drop_column(RgpdTransfers::TiaAssessedAt)
+                    .modify_column(ColumnDef::new(RgpdTransfers::RegisterEntryId).uuid().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum RgpdTransfers {
+    Table,
+    RegisterEntryId,
+    ProcessorId,
+    SccModule,
+    SupplementaryMeasures,
+    TiaOutcome,
+    TiaNotes,
+    TiaAssessedAt,
+}
+
+#[derive(DeriveIden)]
+enum RgpdProcessors {
+    Table,
+    Id,
+    TransferMechanism,
+    TransferDetails,
+}
@@ -0,0 +1,213 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Versions successives du texte de consentement présenté aux personnes
+        // pour un traitement du registre ; une version publiée ne change plus
+        manager
+            .create_table(
+                Table::create()
+                    .table(RgpdConsentPurposes::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RgpdConsentPurposes::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RgpdConsentPurposes::EntityId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdConsentPurposes::RegisterEntryId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdConsentPurposes::Version).integer().not_null())
+                    .col(ColumnDef::new(RgpdConsentPurposes::Wording).text().not_null())
+                    .col(ColumnDef::new(RgpdConsentPurposes::CreatedBy).uuid())
+                    .col(ColumnDef::new(RgpdConsentPurposes::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_consent_purposes_entity_id")
+                            .from(RgpdConsentPurposes::Table, RgpdConsentPurposes::EntityId)
+                            .to(Entities::Table, Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_consent_purposes_register_entry_id")
+                            .from(RgpdConsentPurposes::Table, RgpdConsentPurposes::RegisterEntryId)
+                            .to(RgpdRegister::Table, RgpdRegister::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_consent_purposes_created_by")
+                            .from(RgpdConsentPurposes::Table, RgpdConsentPurposes::CreatedBy)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_consent_purposes_entry_version")
+                    .table(RgpdConsentPurposes::Table)
+                    .col(RgpdConsentPurposes::RegisterEntryId)
+                    .col(RgpdConsentPurposes::Version)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Registre des consentements (Art. 7(1)) : une ligne par recueil, la
+        // personne n'étant connue que par l'empreinte de son identifiant
+        manager
+            .create_table(
+                Table::create()
+                    .table(RgpdConsents::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RgpdConsents::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RgpdConsents::EntityId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdConsents::RegisterEntryId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdConsents::PurposeId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdConsents::SubjectHash).string_len(64).not_null())
+                    .col(ColumnDef::new(RgpdConsents::Channel).string().not_null())
+                    .col(ColumnDef::new(RgpdConsents::Source).string())
+                    .col(ColumnDef::new(RgpdConsents::ExternalId).string())
+                    .col(ColumnDef::new(RgpdConsents::ConsentedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(RgpdConsents::WithdrawnAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(RgpdConsents::WithdrawalChannel).string())
+                    .col(ColumnDef::new(RgpdConsents::RecordedBy).uuid())
+                    .col(ColumnDef::new(RgpdConsents::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_consents_entity_id")
+                            .from(RgpdConsents::Table, RgpdConsents::EntityId)
+                            .to(Entities::Table, Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_consents_register_entry_id")
+                            .from(RgpdConsents::Table, RgpdConsents::RegisterEntryId)
+                            .to(RgpdRegister::Table, RgpdRegister::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_consents_purpose_id")
+                            .from(RgpdConsents::Table, RgpdConsents::PurposeId)
+                            .to(RgpdConsentPurposes::Table, RgpdConsentPurposes::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_consents_recorded_by")
+                            .from(RgpdConsents::Table, RgpdConsents::RecordedBy)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_consents_entry_subject")
+                    .table(RgpdConsents::Table)
+                    .col(RgpdConsents::RegisterEntryId)
+                    .col(RgpdConsents::SubjectHash)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_consents_entity_subject")
+                    .table(RgpdConsents::Table)
+                    .col(RgpdConsents::EntityId)
+                    .col(RgpdConsents::SubjectHash)
+                    .to_owned(),
+            )
+            .await?;
+        // Clé d'idempotence fournie par l'application émettrice
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_consents_entity_external_id")
+                    .table(RgpdConsents::Table)
+                    .col(RgpdConsents::EntityId)
+                    .col(RgpdConsents::ExternalId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in [
+            RgpdConsents::Table.into_iden(),
+            RgpdConsentPurposes::Table.into_iden(),
+        ] {
+            manager
+                .drop_table(Table::drop().table(table).to_owned())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Entities {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum RgpdRegister {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum RgpdConsentPurposes {
+    Table,
+    Id,
+    EntityId,
+    RegisterEntryId,
+    Version,
+    Wording,
+    CreatedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum RgpdConsents {
+    Table,
+    Id,
+    EntityId,
+    RegisterEntryId,
+    PurposeId,
+    SubjectHash,
+    Channel,
+    Source,
+    ExternalId,
+    ConsentedAt,
+    WithdrawnAt,
+    WithdrawalChannel,
+    RecordedBy,
+    CreatedAt,
+}
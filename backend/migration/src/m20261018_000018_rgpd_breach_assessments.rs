@@ -0,0 +1,68 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Évaluation de gravité selon la méthode de l'ENISA : critères retenus,
+        // score et motifs en jsonb, la justification libre étant à part pour
+        // être effacée avec la description lors de l'anonymisation
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdBreaches::Table)
+                    .add_column(ColumnDef::new(RgpdBreaches::Assessment).json_binary())
+                    .add_column(ColumnDef::new(RgpdBreaches::AssessmentJustification).text())
+                    .add_column(ColumnDef::new(RgpdBreaches::AssessedBy).uuid())
+                    .add_column(ColumnDef::new(RgpdBreaches::AssessedAt).timestamp_with_time_zone())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_rgpd_breaches_assessed_by")
+                            .from_tbl(RgpdBreaches::Table)
+                            .from_col(RgpdBreaches::AssessedBy)
+                            .to_tbl(Users::Table)
+                            .to_col(Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdBreaches::Table)
+                    .drop_foreign_key(Alias::new("fk_rgpd_breaches_assessed_by"))
+                    .drop_column(RgpdBreaches::Assessment)
+                    .drop_column(RgpdBreaches::AssessmentJustification)
+                    .drop_column(RgpdBreaches::AssessedBy)
+                    .drop_column(RgpdBreaches::AssessedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum RgpdBreaches {
+    Table,
+    Assessment,
+    AssessmentJustification,
+    AssessedBy,
+    AssessedAt,
+}
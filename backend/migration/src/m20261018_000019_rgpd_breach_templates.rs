@@ -0,0 +1,94 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Modèles de notification (autorité, personnes concernées) propres à
+        // l'entité, un par type et par langue ; les modèles fournis s'appliquent
+        // à défaut
+        manager
+            .create_table(
+                Table::create()
+                    .table(RgpdBreachTemplates::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(RgpdBreachTemplates::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(RgpdBreachTemplates::EntityId).uuid().not_null())
+                    .col(ColumnDef::new(RgpdBreachTemplates::Kind).string().not_null())
+                    .col(ColumnDef::new(RgpdBreachTemplates::Language).string_len(2).not_null())
+                    .col(ColumnDef::new(RgpdBreachTemplates::Title).string().not_null())
+                    .col(ColumnDef::new(RgpdBreachTemplates::Body).text().not_null())
+                    .col(ColumnDef::new(RgpdBreachTemplates::UpdatedBy).uuid())
+                    .col(ColumnDef::new(RgpdBreachTemplates::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(RgpdBreachTemplates::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_breach_templates_entity_id")
+                            .from(RgpdBreachTemplates::Table, RgpdBreachTemplates::EntityId)
+                            .to(Entities::Table, Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rgpd_breach_templates_updated_by")
+                            .from(RgpdBreachTemplates::Table, RgpdBreachTemplates::UpdatedBy)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rgpd_breach_templates_entity_kind_language")
+                    .table(RgpdBreachTemplates::Table)
+                    .col(RgpdBreachTemplates::EntityId)
+                    .col(RgpdBreachTemplates::Kind)
+                    .col(RgpdBreachTemplates::Language)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RgpdBreachTemplates::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Entities {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum RgpdBreachTemplates {
+    Table,
+    Id,
+    EntityId,
+    Kind,
+    Language,
+    Title,
+    Body,
+    UpdatedBy,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,78 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Domaines email dont l'entité a prouvé la maîtrise ; seuls ceux-ci
+        // servent à la découverte SSO et au provisioning
+        manager
+            .create_table(
+                Table::create()
+                    .table(EmailDomains::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(EmailDomains::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(EmailDomains::EntityId).uuid().not_null())
+                    .col(ColumnDef::new(EmailDomains::Domain).string().not_null())
+                    .col(ColumnDef::new(EmailDomains::VerificationToken).string().not_null())
+                    .col(ColumnDef::new(EmailDomains::VerifiedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(EmailDomains::CreatedAt).timestamp_with_time_zone().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_email_domains_entity_id")
+                            .from(EmailDomains::Table, EmailDomains::EntityId)
+                            .to(Entities::Table, Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_email_domains_entity_domain")
+                    .table(EmailDomains::Table)
+                    .col(EmailDomains::EntityId)
+                    .col(EmailDomains::Domain)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Un domaine n'est vérifié que pour une seule entité
+        manager.get_connection().execute_unprepared(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_email_domains_verified_domain
+             ON email_domains (domain) WHERE verified_at IS NOT NULL",
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EmailDomains::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Entities {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum EmailDomains {
+    Table,
+    Id,
+    EntityId,
+    Domain,
+    VerificationToken,
+    VerifiedAt,
+    CreatedAt,
+}
@@ -0,0 +1,62 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Entité à laquelle le jeton est limité (toujours renseignée pour un compte de service)
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ApiTokens::Table)
+                    .add_column(ColumnDef::new(ApiTokens::EntityId).uuid())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_api_tokens_entity_id")
+                            .from_tbl(ApiTokens::Table)
+                            .from_col(ApiTokens::EntityId)
+                            .to_tbl(Entities::Table)
+                            .to_col(Entities::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager.get_connection().execute_unprepared(
+            "UPDATE api_tokens SET entity_id = service_accounts.entity_id
+             FROM service_accounts WHERE service_accounts.user_id = api_tokens.user_id",
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ApiTokens::Table)
+                    .drop_foreign_key(Alias::new("fk_api_tokens_entity_id"))
+                    .drop_column(ApiTokens::EntityId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Entities {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ApiTokens {
+    Table,
+    EntityId,
+}
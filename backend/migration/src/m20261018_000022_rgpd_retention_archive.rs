@@ -0,0 +1,64 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Archivage intermédiaire : le dossier sort des vues courantes sans être modifié
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdAccessRequests::Table)
+                    .add_column(ColumnDef::new(RgpdAccessRequests::ArchivedAt).timestamp_with_time_zone())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdBreaches::Table)
+                    .add_column(ColumnDef::new(RgpdBreaches::ArchivedAt).timestamp_with_time_zone())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdBreaches::Table)
+                    .drop_column(RgpdBreaches::ArchivedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdAccessRequests::Table)
+                    .drop_column(RgpdAccessRequests::ArchivedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum RgpdAccessRequests {
+    Table,
+    ArchivedAt,
+}
+
+#[derive(DeriveIden)]
+enum RgpdBreaches {
+    Table,
+    ArchivedAt,
+}
@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Fiche du registre dont la règle de conservation s'applique ; à sa
+        // suppression, la politique garde la dernière règle recopiée
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdRetentionPolicies::Table)
+                    .add_column(ColumnDef::new(RgpdRetentionPolicies::RegisterEntryId).uuid())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_rgpd_retention_policies_register_entry_id")
+                            .from_tbl(RgpdRetentionPolicies::Table)
+                            .from_col(RgpdRetentionPolicies::RegisterEntryId)
+                            .to_tbl(RgpdRegister::Table)
+                            .to_col(RgpdRegister::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(RgpdRetentionPolicies::Table)
+                    .drop_foreign_key(Alias::new("fk_rgpd_retention_policies_register_entry_id"))
+                    .drop_column(RgpdRetentionPolicies::RegisterEntryId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum RgpdRegister {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum RgpdRetentionPolicies {
+    Table,
+    RegisterEntryId,
+}
@@ -0,0 +1,68 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Second facteur vérifié par le fournisseur : reconnu seulement si l'entité lui fait confiance
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(IdentityProviders::Table)
+                    .add_column(ColumnDef::new(IdentityProviders::MfaTrusted).boolean().not_null().default(false))
+                    .add_column(ColumnDef::new(IdentityProviders::MfaAcrValues).json_binary().not_null().default("[]"))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Authentification déclarée par le fournisseur, transmise du callback à l'échange du code
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserTokens::Table)
+                    .add_column(ColumnDef::new(UserTokens::Context).json_binary())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserTokens::Table)
+                    .drop_column(UserTokens::Context)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(IdentityProviders::Table)
+                    .drop_column(IdentityProviders::MfaAcrValues)
+                    .drop_column(IdentityProviders::MfaTrusted)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum IdentityProviders {
+    Table,
+    MfaTrusted,
+    MfaAcrValues,
+}
+
+#[derive(DeriveIden)]
+enum UserTokens {
+    Table,
+    Context,
+}
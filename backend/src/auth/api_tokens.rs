@@ -0,0 +1,388 @@
+use actix_web::http::Method;
+use chrono::{Duration, Utc};
+use rand::{RngCore, rngs::OsRng};
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait, ColumnTrait, QueryFilter, Set, IntoActiveModel, ActiveModelTrait};
+use uuid::Uuid;
+use crate::auth::jwt::{Claims, AMR_API, AMR_MFA, TOKEN_TYPE_ACCESS};
+use crate::auth::models::ApiTokenResponse;
+use crate::config::Config;
+use crate::auth::mfa::sha256_hex;
+use crate::errors::ApiError;
+use crate::entities_orm::api_token::{self, Entity as ApiTokenEntity, Column as ApiTokenColumn, ActiveModel as ApiTokenActiveModel};
+use crate::entities_orm::service_account::{Entity as ServiceAccountEntity, Column as ServiceAccountColumn};
+use crate::entities_orm::user::Entity as UserEntity;
+
+/// Préfixe qui distingue un jeton d'API d'un JWT dans l'en-tête `Authorization`.
+pub const TOKEN_PREFIX: &str = "hct_";
+
+// Permissions accordées à un jeton ; `:write` inclut `:read`
+pub const SCOPES: &[&str] = &[
+    "entities:read", "entities:write",
+    "catalogue:read", "catalogue:write",
+    "rgpd:read", "rgpd:write",
+    "consents:read", "consents:write",
+];
+
+// Caractères du jeton conservés en clair pour l'identifier (préfixe compris)
+const DISPLAY_PREFIX_LENGTH: usize = 12;
+// Évite une écriture en base à chaque appel d'un script
+const LAST_USED_PRECISION_SECONDS: i64 = 60;
+
+/// Jeton d'API authentifié : identité de l'appelant et permissions accordées.
+pub struct ApiTokenAuth {
+    pub claims: Claims,
+    pub scopes: Vec<String>,
+    pub entity_id: Option<Uuid>,
+}
+
+pub fn token_scopes(token: &api_token::Model) -> Vec<String> {
+    serde_json::from_value(token.scopes.clone()).unwrap_or_default()
+}
+
+pub fn to_response(token: api_token::Model) -> ApiTokenResponse {
+    ApiTokenResponse {
+        scopes: token_scopes(&token),
+        id: token.id,
+        name: token.name,
+        token_prefix: token.token_prefix,
+        entity_id: token.entity_id,
+        expires_at: token.expires_at,
+        last_used_at: token.last_used_at,
+        created_at: token.created_at,
+    }
+}
+
+pub fn check_expiry(config: &Config, expires_at: chrono::DateTime<Utc>) -> Result<(), ApiError> {
+    let now = Utc::now();
+    if expires_at <= now {
+        return Err(ApiError::validation("expires_at", "in_past", "Expiration date must be in the future"));
+    }
+    if expires_at > now + Duration::days(config.api_token_max_lifetime_days) {
+        return Err(ApiError::validation(
+            "expires_at",
+            "too_far",
+            format!("API tokens cannot be valid for more than {} days", config.api_token_max_lifetime_days),
+        ));
+    }
+    Ok(())
+}
+
+/// Crée un jeton pour l'utilisateur (humain ou compte de service), limité à
+/// `entity_id` s'il est fourni. Le jeton en clair n'est renvoyé qu'ici ; seule
+/// son empreinte SHA-256 est conservée.
+pub async fn issue(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    entity_id: Option<Uuid>,
+    name: &str,
+    scopes: &[String],
+    mfa: bool,
+    expires_at: chrono::DateTime<Utc>,
+) -> Result<(api_token::Model, String), ApiError> {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token = format!("{}{}", TOKEN_PREFIX, bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+
+    let mut scopes = scopes.to_vec();
+    scopes.sort();
+    scopes.dedup();
+
+    let record = ApiTokenActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        entity_id: Set(entity_id),
+        name: Set(name.trim().to_string()),
+        token_prefix: Set(token[..DISPLAY_PREFIX_LENGTH].to_string()),
+        token_hash: Set(sha256_hex(&token)),
+        scopes: Set(serde_json::json!(scopes)),
+        mfa: Set(mfa),
+        expires_at: Set(expires_at),
+        last_used_at: Set(None),
+        created_at: Set(Utc::now()),
+    };
+    let record = ApiTokenEntity::insert(record)
+        .exec_with_returning(db)
+        .await?;
+    Ok((record, token))
+}
+
+/// Vérifie un jeton présenté en bearer. Les claims produites ont pour `iat` la
+/// création du jeton ; la révocation des sessions (`sessions_valid_after`) ne
+/// s'applique pas aux jetons d'API, qui restent valides jusqu'à leur échéance
+/// ou leur suppression.
+pub async fn authenticate(db: &DatabaseConnection, token: &str) -> Result<ApiTokenAuth, ApiError> {
+    let invalid = || ApiError::Unauthorized("Invalid or missing token".to_string());
+
+    let record = ApiTokenEntity::find()
+        .filter(ApiTokenColumn::TokenHash.eq(sha256_hex(token)))
+        .one(db)
+        .await?
+        .ok_or_else(invalid)?;
+
+    let now = Utc::now();
+    if record.expires_at <= now {
+        return Err(ApiError::Unauthorized("API token expired".to_string()));
+    }
+
+    let user = UserEntity::find_by_id(record.user_id)
+        .one(db)
+        .await?
+        .ok_or_else(invalid)?;
+
+    let mut amr = vec![AMR_API.to_string()];
+    if record.mfa {
+        amr.push(AMR_MFA.to_string());
+    }
+    let claims = Claims {
+        user_id: user.id,
+        email: user.email,
+        exp: record.expires_at.timestamp(),
+        iat: record.created_at.timestamp(),
+        iss: String::new(),
+        aud: String::new(),
+        jti: record.id.to_string(),
+        amr,
+        acr: None,
+        typ: TOKEN_TYPE_ACCESS.to_string(),
+        active_entity_id: None,
+    };
+    let scopes = token_scopes(&record);
+    let entity_id = record.entity_id;
+
+    if record.last_used_at.is_none_or(|t| now - t > Duration::seconds(LAST_USED_PRECISION_SECONDS)) {
+        let mut record = record.into_active_model();
+        record.last_used_at = Set(Some(now));
+        record.update(db).await?;
+    }
+
+    Ok(ApiTokenAuth { claims, scopes, entity_id })
+}
+
+/// Permission exigée d'un jeton d'API pour la route appelée. `None` : route
+/// réservée aux sessions interactives (compte, MFA, administration des
+/// fournisseurs d'identité, du SCIM, des jetons eux-mêmes et cycle de vie
+/// des entités : archivage, suppression, export).
+pub fn required_scope(method: &Method, path: &str) -> Option<String> {
+    let segments: Vec<&str> = path.trim_start_matches("/api")
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    let resource = match segments.as_slice() {
+        ["catalogue", ..] => "catalogue",
+        // Registre des consentements, alimenté par les applications de l'entité
+        ["entities", _, "rgpd", "consents", ..] => "consents",
+        ["rgpd", ..] | ["entities", _, "rgpd", ..] => "rgpd",
+        ["entities"] | ["entities", _] | ["entities", _, "users" | "subsidiaries"] => "entities",
+        _ => return None,
+    };
+    // La preuve se demande en POST pour que l'identifiant de la personne reste hors de l'URL
+    let read = *method == Method::GET || *method == Method::HEAD
+        || matches!(segments.as_slice(), ["entities", _, "rgpd", "consents", "proof"]);
+    let access = if read { "read" } else { "write" };
+    Some(format!("{}:{}", resource, access))
+}
+
+pub fn has_scope(scopes: &[String], required: &str) -> bool {
+    let write = required.strip_suffix(":read").map(|resource| format!("{}:write", resource));
+    scopes.iter().any(|s| s == required || write.as_deref() == Some(s.as_str()))
+}
+
+pub fn check_scope(method: &Method, path: &str, scopes: &[String]) -> Result<(), ApiError> {
+    match required_scope(method, path) {
+        Some(scope) if has_scope(scopes, &scope) => Ok(()),
+        Some(scope) => Err(ApiError::Forbidden(format!("API token is missing the {} scope", scope))),
+        None => Err(ApiError::Forbidden("This endpoint is not available to API tokens".to_string())),
+    }
+}
+
+/// Supprime les jetons de l'utilisateur, ou seulement ceux limités à
+/// `entity_id`. Renvoie le nombre de jetons révoqués.
+pub async fn revoke(db: &DatabaseConnection, user_id: Uuid, entity_id: Option<Uuid>) -> Result<u64, DbErr> {
+    let mut query = ApiTokenEntity::delete_many().filter(ApiTokenColumn::UserId.eq(user_id));
+    if let Some(entity_id) = entity_id {
+        query = query.filter(ApiTokenColumn::EntityId.eq(entity_id));
+    }
+    Ok(query.exec(db).await?.rows_affected)
+}
+
+pub async fn is_service_account(db: &DatabaseConnection, user_id: Uuid) -> Result<bool, ApiError> {
+    let account = ServiceAccountEntity::find()
+        .filter(ServiceAccountColumn::UserId.eq(user_id))
+        .one(db)
+        .await?;
+    Ok(account.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test::{init_service, try_call_service, TestRequest}, web, App, HttpResponse};
+    use crate::middleware::{AuthMiddleware, EntityMemberships};
+    use crate::test_support;
+    use super::*;
+
+    const ENTITY: &str = "/api/entities/6f1c2a52-8a0e-4e8b-9a53-0d1f1b2c3d4e";
+
+    fn scopes(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn allowed(method: Method, path: &str, granted: &[&str]) -> bool {
+        check_scope(&method, path, &scopes(granted)).is_ok()
+    }
+
+    #[test]
+    fn maps_routes_to_scopes() {
+        let cases = [
+            (Method::GET, "/api/entities", Some("entities:read")),
+            (Method::POST, "/api/entities", Some("entities:write")),
+            (Method::GET, &format!("{ENTITY}/users"), Some("entities:read")),
+            (Method::PUT, ENTITY, Some("entities:write")),
+            (Method::HEAD, &format!("{ENTITY}/subsidiaries"), Some("entities:read")),
+            (Method::GET, "/api/catalogue/measures", Some("catalogue:read")),
+            (Method::DELETE, "/api/catalogue/measures/1", Some("catalogue:write")),
+            (Method::GET, &format!("{ENTITY}/rgpd/register"), Some("rgpd:read")),
+            (Method::POST, &format!("{ENTITY}/rgpd/breaches"), Some("rgpd:write")),
+            (Method::GET, "/api/rgpd/register", Some("rgpd:read")),
+            (Method::POST, &format!("{ENTITY}/rgpd/consents"), Some("consents:write")),
+            (Method::POST, &format!("{ENTITY}/rgpd/consents/withdraw"), Some("consents:write")),
+            // Lecture de preuve demandée en POST
+            (Method::POST, &format!("{ENTITY}/rgpd/consents/proof"), Some("consents:read")),
+        ];
+        for (method, path, expected) in cases {
+            assert_eq!(required_scope(&method, path).as_deref(), expected, "{} {}", method, path);
+        }
+    }
+
+    #[test]
+    fn write_scope_includes_read() {
+        let cases = [
+            (&["entities:read"][..], "entities:read", true),
+            (&["entities:write"][..], "entities:read", true),
+            (&["entities:write"][..], "entities:write", true),
+            (&["entities:read"][..], "entities:write", false),
+            (&["rgpd:write"][..], "consents:read", false),
+            (&["consents:write"][..], "rgpd:read", false),
+            (&[][..], "rgpd:read", false),
+            // Une chaîne proche n'accorde rien
+            (&["entities:read:write"][..], "entities:read", false),
+            (&["entities"][..], "entities:read", false),
+        ];
+        for (granted, required, expected) in cases {
+            assert_eq!(has_scope(&scopes(granted), required), expected, "{:?} -> {}", granted, required);
+        }
+    }
+
+    #[test]
+    fn read_scope_cannot_write() {
+        let read = &["entities:read", "catalogue:read", "rgpd:read", "consents:read"][..];
+        assert!(allowed(Method::GET, ENTITY, read));
+        assert!(allowed(Method::GET, &format!("{ENTITY}/rgpd/breaches"), read));
+        assert!(allowed(Method::POST, &format!("{ENTITY}/rgpd/consents/proof"), read));
+        for (method, path) in [
+            (Method::DELETE, "/api/catalogue/measures/1".to_string()),
+            (Method::POST, "/api/entities".to_string()),
+            (Method::PUT, ENTITY.to_string()),
+            (Method::POST, format!("{ENTITY}/rgpd/register")),
+            (Method::DELETE, format!("{ENTITY}/rgpd/dpias/1")),
+            (Method::PATCH, format!("{ENTITY}/rgpd/breaches/1")),
+            (Method::POST, format!("{ENTITY}/rgpd/consents")),
+        ] {
+            assert!(!allowed(method.clone(), &path, read), "{} {}", method, path);
+        }
+    }
+
+    #[test]
+    fn scopes_do_not_cross_resources() {
+        assert!(!allowed(Method::GET, &format!("{ENTITY}/rgpd/register"), &["entities:write"]));
+        assert!(!allowed(Method::POST, &format!("{ENTITY}/rgpd/consents"), &["rgpd:write"]));
+        assert!(!allowed(Method::GET, &format!("{ENTITY}/rgpd/breaches"), &["consents:write"]));
+        assert!(!allowed(Method::GET, "/api/catalogue/measures", &["entities:write", "rgpd:write"]));
+    }
+
+    #[test]
+    fn interactive_routes_are_denied() {
+        let all = SCOPES;
+        for (method, path) in [
+            (Method::GET, "/api/auth/me".to_string()),
+            (Method::POST, "/api/auth/tokens".to_string()),
+            (Method::POST, "/api/auth/password/change".to_string()),
+            (Method::POST, "/api/auth/mfa/totp/disable".to_string()),
+            (Method::GET, format!("{ENTITY}/identity-providers")),
+            (Method::PUT, format!("{ENTITY}/scim")),
+            (Method::POST, format!("{ENTITY}/service-accounts/1/tokens")),
+            (Method::POST, format!("{ENTITY}/archive")),
+            (Method::POST, format!("{ENTITY}/deletion")),
+            (Method::GET, format!("{ENTITY}/export")),
+            (Method::PUT, format!("{ENTITY}/parent")),
+            (Method::GET, format!("{ENTITY}/login-failures")),
+            (Method::GET, "/api/scim/v2/Users".to_string()),
+            (Method::GET, "/api/unknown".to_string()),
+            (Method::GET, "/".to_string()),
+        ] {
+            assert_eq!(required_scope(&method, &path), None, "{} {}", method, path);
+            assert!(!allowed(method.clone(), &path, all), "{} {}", method, path);
+        }
+    }
+
+    // Statut de `method path` présenté avec le jeton, derrière `AuthMiddleware`
+    macro_rules! status {
+        ($app:expr, $method:expr, $path:expr, $token:expr) => {{
+            let request = TestRequest::default()
+                .method($method)
+                .uri(&$path)
+                .insert_header(("Authorization", format!("Bearer {}", $token)))
+                .to_request();
+            match try_call_service(&$app, request).await {
+                Ok(response) => response.status(),
+                Err(error) => error.as_response_error().status_code(),
+            }
+        }};
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn entity_token_cannot_reach_other_entities() {
+        let db = test_support::database().await;
+        let user = test_support::create_user(&db, true).await;
+        let group = test_support::create_entity(&db, None).await;
+        let subsidiary = test_support::create_entity(&db, Some(group.id)).await;
+        let other = test_support::create_entity(&db, None).await;
+        test_support::add_member(&db, user.id, group.id, "admin").await;
+        test_support::add_member(&db, user.id, other.id, "admin").await;
+
+        let expires_at = Utc::now() + Duration::days(1);
+        let (_, bound) = issue(&db, user.id, Some(group.id), "ci", &scopes(&["entities:read"]), false, expires_at)
+            .await.unwrap();
+        let (_, unbound) = issue(&db, user.id, None, "ci", &scopes(&["entities:read"]), false, expires_at)
+            .await.unwrap();
+        assert_eq!(authenticate(&db, &bound).await.unwrap().entity_id, Some(group.id));
+
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .service(
+                    web::scope("/api/entities")
+                        .wrap(AuthMiddleware)
+                        .route("/{id}", web::get().to(HttpResponse::Ok))
+                        .route("/{id}", web::put().to(HttpResponse::Ok))
+                )
+        ).await;
+        let path = |id: Uuid| format!("/api/entities/{}", id);
+
+        // Le jeton lié atteint son entité et ses filiales, pas le reste
+        assert_eq!(status!(app, Method::GET, path(group.id), bound), StatusCode::OK);
+        assert_eq!(status!(app, Method::GET, path(subsidiary.id), bound), StatusCode::OK);
+        assert_eq!(status!(app, Method::GET, path(other.id), bound), StatusCode::FORBIDDEN);
+        assert_eq!(status!(app, Method::PUT, path(group.id), bound), StatusCode::FORBIDDEN);
+        assert_eq!(status!(app, Method::GET, path(other.id), unbound), StatusCode::OK);
+
+        let mut memberships = EntityMemberships::load(&db, user.id).await.unwrap();
+        memberships.restrict_to(&db, group.id).await.unwrap();
+        assert_eq!(memberships.role(subsidiary.id), Some("admin"));
+        assert_eq!(memberships.role(other.id), None);
+
+        test_support::delete_user(&db, user.id).await;
+        test_support::delete_entities(&db, &[group.id, subsidiary.id, other.id]).await;
+    }
+}
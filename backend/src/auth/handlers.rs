@@ -1,92 +1,194 @@
-use actix_web::{web, HttpResponse, HttpRequest};
-use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, Set};
+use actix_web::{http::header, web, HttpMessage, HttpResponse, HttpRequest};
+use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, QueryOrder, Set, IntoActiveModel, ActiveModelTrait};
+use webauthn_rs::Webauthn;
+use webauthn_rs::prelude::{CredentialID, Passkey, PasskeyAuthentication, PasskeyRegistration};
 use bcrypt::{hash, verify, DEFAULT_COST};
 use crate::auth::models::*;
-use crate::auth::jwt::{create_token, create_refresh_token, verify_token};
+use crate::auth::jwt::{Claims, JwtKeys, SessionContext, create_token, create_refresh_token, verify_refresh_token, validate_session, AMR_PASSWORD, AMR_OTP, AMR_HARDWARE_KEY, AMR_FEDERATED, AMR_MFA};
+use crate::auth::{api_tokens, mfa, oidc, saml, sso, tokens};
+use crate::mailer::{Email, Mailer};
+use crate::outbound::OutboundClient;
+use crate::auth::throttle::{LoginThrottle, ThrottleKey};
 use crate::config::Config;
-use crate::middleware::get_current_user_id;
-use crate::entities_orm::user::{Entity as UserEntity, Column as UserColumn, ActiveModel as UserActiveModel};
+use crate::errors::ApiError;
+use crate::validation::ValidatedJson;
+use crate::middleware::{client_ip, get_active_entity_id, get_current_user_id, get_entity_role, get_inherited_from, get_member_entity_ids, EntityMemberships};
+use crate::entities_orm::user::{Entity as UserEntity, Column as UserColumn, ActiveModel as UserActiveModel, Model as UserModel};
+use crate::entities_orm::user_totp::{Entity as UserTotpEntity, ActiveModel as UserTotpActiveModel};
+use crate::entities_orm::recovery_code::{Entity as RecoveryCodeEntity, Column as RecoveryCodeColumn};
+use crate::entities_orm::webauthn_credential::{Entity as WebauthnCredentialEntity, Column as WebauthnCredentialColumn, ActiveModel as WebauthnCredentialActiveModel};
+use crate::entities_orm::login_failure::{Entity as LoginFailureEntity, ActiveModel as LoginFailureActiveModel};
+use crate::entities_orm::api_token::{Entity as ApiTokenEntity, Column as ApiTokenColumn};
+use std::sync::OnceLock;
 use uuid::Uuid;
 use chrono::Utc;
 
+// Hash factice vérifié quand le compte n'existe pas, pour que la réponse
+// prenne le même temps qu'avec un compte existant (pas d'énumération).
+fn dummy_password_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    DUMMY_HASH.get_or_init(|| {
+        hash("hypercyber-dummy-password", DEFAULT_COST).unwrap_or_default()
+    })
+}
+
+// Journalise un échec de connexion ; une erreur d'écriture ne doit pas bloquer la réponse
+async fn record_login_failure(
+    db: &DatabaseConnection,
+    email: &str,
+    user_id: Option<Uuid>,
+    ip_address: Option<String>,
+    reason: &str,
+) {
+    let failure = LoginFailureActiveModel {
+        id: Set(Uuid::new_v4()),
+        email: Set(email.trim().to_lowercase()),
+        user_id: Set(user_id),
+        ip_address: Set(ip_address),
+        reason: Set(reason.to_string()),
+        created_at: Set(Utc::now()),
+    };
+    if let Err(e) = LoginFailureEntity::insert(failure).exec(db).await {
+        log::error!("Failed to record login failure: {}", e);
+    }
+}
+
 pub async fn login(
     db: web::Data<DatabaseConnection>,
     config: web::Data<Config>,
-    req: web::Json<LoginRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
+    jwt_keys: web::Data<JwtKeys>,
+    throttle: web::Data<dyn LoginThrottle>,
+    http_req: HttpRequest,
+    req: ValidatedJson<LoginRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let ip = client_ip(&http_req, config.behind_proxy);
+    let mut keys = vec![ThrottleKey::account(&req.email)];
+    if let Some(ip) = &ip {
+        keys.push(ThrottleKey::Ip(ip.clone()));
+    }
+
+    // La tentative est comptée avant la vérification du mot de passe
+    for (i, key) in keys.iter().enumerate() {
+        if let Some(retry_after) = throttle.record_attempt(key).await
+            .map_err(ApiError::internal("Login throttle failure"))?
+        {
+            // Une tentative refusée ne compte pas contre les autres clés
+            for counted in &keys[..i] {
+                throttle.release(counted).await
+                    .map_err(ApiError::internal("Login throttle failure"))?;
+            }
+            record_login_failure(db.get_ref(), &req.email, None, ip.clone(), "throttled").await;
+            return Err(ApiError::TooManyRequests { retry_after });
+        }
+    }
+
     let user = UserEntity::find()
         .filter(UserColumn::Email.eq(&req.email))
         .filter(UserColumn::IsActive.eq(true))
         .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
+
+    // Le hash est toujours vérifié, même pour un compte inconnu
+    let password_hash = user.as_ref()
+        .map(|u| u.password_hash.as_str())
+        .unwrap_or_else(|| dummy_password_hash());
+    let password_ok = verify(&req.password, password_hash).unwrap_or(false);
 
     let user = match user {
-        Some(u) => u,
-        None => return Ok(HttpResponse::Unauthorized().json(serde_json::json!({
-            "error": "Invalid credentials"
-        }))),
+        Some(u) if password_ok => u,
+        user => {
+            for key in &keys {
+                throttle.record_failure(key).await
+                    .map_err(ApiError::internal("Login throttle failure"))?;
+            }
+            let reason = if user.is_some() { "invalid_password" } else { "unknown_account" };
+            record_login_failure(db.get_ref(), &req.email, user.map(|u| u.id), ip, reason).await;
+            return Err(ApiError::Unauthorized("Invalid credentials".to_string()));
+        }
     };
 
-    if !verify(&req.password, &user.password_hash).unwrap_or(false) {
-        return Ok(HttpResponse::Unauthorized().json(serde_json::json!({
-            "error": "Invalid credentials"
-        })));
+    for key in &keys {
+        throttle.release(key).await
+            .map_err(ApiError::internal("Login throttle failure"))?;
     }
 
-    let token = create_token(user.id, user.email.clone(), &config)
-        .map_err(|e| {
-            log::error!("JWT error: {}", e);
-            actix_web::error::ErrorInternalServerError("Token creation failed")
-        })?;
+    if config.require_email_verification && user.email_verified_at.is_none() {
+        return Err(ApiError::EmailNotVerified);
+    }
 
-    let refresh_token = create_refresh_token(user.id, user.email.clone())
-        .map_err(|e| {
-            log::error!("JWT error: {}", e);
-            actix_web::error::ErrorInternalServerError("Token creation failed")
-        })?;
+    // Second facteur activé : on n'émet qu'un jeton intermédiaire. Les échecs
+    // antérieurs restent comptés jusqu'à la validation du second facteur.
+    let methods = mfa::user_mfa_methods(db.get_ref(), user.id).await?;
+    if !methods.is_empty() {
+        return mfa_challenge_response(db.get_ref(), user.id, methods, &SessionContext::with_methods(&[AMR_PASSWORD])).await;
+    }
 
-    Ok(HttpResponse::Ok().json(AuthResponse {
+    throttle.reset(&keys[0]).await
+        .map_err(ApiError::internal("Login throttle failure"))?;
+    Ok(HttpResponse::Ok().json(auth_response(user, SessionContext::with_methods(&[AMR_PASSWORD]), &config, &jwt_keys)?))
+}
+
+// Jeton intermédiaire : les jetons de session ne sont émis qu'après le second facteur
+async fn mfa_challenge_response(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    methods: Vec<String>,
+    session: &SessionContext,
+) -> Result<HttpResponse, ApiError> {
+    let mfa_token = mfa::create_login_challenge(db, user_id, session).await?;
+    Ok(HttpResponse::Ok()
+        .append_header(("Cache-Control", "no-store"))
+        .json(MfaChallengeResponse {
+            mfa_required: true,
+            mfa_token,
+            methods,
+            expires_in: mfa::CHALLENGE_TTL_SECONDS,
+        }))
+}
+
+// Émet le couple jeton d'accès / jeton de rafraîchissement pour un utilisateur authentifié
+fn auth_response(user: UserModel, session: SessionContext, config: &Config, jwt_keys: &JwtKeys) -> Result<AuthResponse, ApiError> {
+    let active_entity_id = session.active_entity_id;
+    let token = create_token(user.id, user.email.clone(), session.clone(), config, jwt_keys)
+        .map_err(ApiError::internal("Token creation failed"))?;
+
+    let refresh_token = create_refresh_token(user.id, user.email.clone(), session, jwt_keys)
+        .map_err(ApiError::internal("Token creation failed"))?;
+
+    Ok(AuthResponse {
         token,
         refresh_token,
+        active_entity_id,
         user: UserInfo {
             id: user.id,
             email: user.email,
             first_name: user.first_name,
             last_name: user.last_name,
         },
-    }))
+    })
 }
 
 pub async fn register(
     db: web::Data<DatabaseConnection>,
     config: web::Data<Config>,
-    req: web::Json<RegisterRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
+    jwt_keys: web::Data<JwtKeys>,
+    mailer: web::Data<dyn Mailer>,
+    req: ValidatedJson<RegisterRequest>,
+) -> Result<HttpResponse, ApiError> {
+    config.password_policy.check("password", &req.password)?;
+
     // Check if user already exists
     let existing = UserEntity::find()
         .filter(UserColumn::Email.eq(&req.email))
         .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     if existing.is_some() {
-        return Ok(HttpResponse::Conflict().json(serde_json::json!({
-            "error": "User already exists"
-        })));
+        return Err(ApiError::Conflict("User already exists".to_string()));
     }
 
     let password_hash = hash(&req.password, DEFAULT_COST)
-        .map_err(|e| {
-            log::error!("Bcrypt error: {}", e);
-            actix_web::error::ErrorInternalServerError("Password hashing failed")
-        })?;
+        .map_err(ApiError::internal("Password hashing failed"))?;
 
     let now = Utc::now();
     let user = UserActiveModel {
@@ -95,73 +197,63 @@ pub async fn register(
         password_hash: Set(password_hash),
         first_name: Set(req.first_name.clone()),
         last_name: Set(req.last_name.clone()),
+        // `is_active` indique un compte activé ; la vérification de l'email est suivie à part
         is_active: Set(true),
+        email_verified_at: Set((!config.require_email_verification).then_some(now)),
+        sessions_valid_after: Set(None),
         created_at: Set(now),
         updated_at: Set(now),
     };
 
     let user = UserEntity::insert(user)
         .exec_with_returning(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    let token = create_token(user.id, user.email.clone(), &config)
-        .map_err(|e| {
-            log::error!("JWT error: {}", e);
-            actix_web::error::ErrorInternalServerError("Token creation failed")
-        })?;
+        .await?;
 
-    let refresh_token = create_refresh_token(user.id, user.email.clone())
-        .map_err(|e| {
-            log::error!("JWT error: {}", e);
-            actix_web::error::ErrorInternalServerError("Token creation failed")
-        })?;
+    // Aucune session n'est ouverte avant la vérification de l'adresse
+    if config.require_email_verification {
+        send_account_email(db.get_ref(), &config, mailer.get_ref(), &user, tokens::PURPOSE_EMAIL_VERIFICATION).await?;
+        return Ok(HttpResponse::Created().json(RegisterResponse {
+            user: UserInfo {
+                id: user.id,
+                email: user.email,
+                first_name: user.first_name,
+                last_name: user.last_name,
+            },
+            email_verification_required: true,
+        }));
+    }
 
-    Ok(HttpResponse::Created().json(AuthResponse {
-        token,
-        refresh_token,
-        user: UserInfo {
-            id: user.id,
-            email: user.email,
-            first_name: user.first_name,
-            last_name: user.last_name,
-        },
-    }))
+    Ok(HttpResponse::Created().json(auth_response(user, SessionContext::with_methods(&[AMR_PASSWORD]), &config, &jwt_keys)?))
 }
 
 pub async fn refresh_token(
     db: web::Data<DatabaseConnection>,
     config: web::Data<Config>,
-    req: web::Json<serde_json::Value>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let refresh_token = req.get("refresh_token")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| actix_web::error::ErrorBadRequest("Missing refresh_token"))?;
-
-    let claims = verify_token(refresh_token, &config)
-        .map_err(|_| actix_web::error::ErrorUnauthorized("Invalid refresh token"))?;
+    jwt_keys: web::Data<JwtKeys>,
+    req: ValidatedJson<RefreshTokenRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let claims = verify_refresh_token(&req.refresh_token, &jwt_keys)
+        .map_err(|_| ApiError::Unauthorized("Invalid refresh token".to_string()))?;
+    validate_session(db.get_ref(), &claims).await?;
 
     // Verify user still exists and is active
     let user = UserEntity::find()
         .filter(UserColumn::Id.eq(claims.user_id))
         .filter(UserColumn::IsActive.eq(true))
         .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
-    let user = user.ok_or_else(|| actix_web::error::ErrorUnauthorized("User not found"))?;
+    let user = user.ok_or_else(|| ApiError::Unauthorized("User not found".to_string()))?;
 
-    let token = create_token(user.id, user.email.clone(), &config)
-        .map_err(|e| {
-            log::error!("JWT error: {}", e);
-            actix_web::error::ErrorInternalServerError("Token creation failed")
-        })?;
+    // Le niveau d'authentification de la session d'origine est conservé, ainsi
+    // que l'entité active tant que l'utilisateur en est membre
+    let memberships = EntityMemberships::load(db.get_ref(), user.id).await?;
+    let session = SessionContext {
+        active_entity_id: claims.active_entity_id.filter(|id| memberships.role(*id).is_some()),
+        ..claims.session()
+    };
+    let token = create_token(user.id, user.email.clone(), session, &config, &jwt_keys)
+        .map_err(ApiError::internal("Token creation failed"))?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "token": token
@@ -171,178 +263,1533 @@ pub async fn refresh_token(
 pub async fn get_current_user(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
-) -> Result<HttpResponse, actix_web::Error> {
+) -> Result<HttpResponse, ApiError> {
     let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
 
     let user = UserEntity::find()
         .filter(UserColumn::Id.eq(user_id))
         .filter(UserColumn::IsActive.eq(true))
         .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
+
+    let user = user.ok_or_else(|| ApiError::NotFound("User not found".to_string()))?;
+
+    let mut memberships: Vec<EntityMembership> = get_member_entity_ids(&req)
+        .into_iter()
+        .filter_map(|entity_id| get_entity_role(&req, entity_id).map(|role| EntityMembership {
+            entity_id,
+            role,
+            inherited_from: get_inherited_from(&req, entity_id),
+        }))
+        .collect();
+    memberships.sort_by_key(|m| m.entity_id);
+
+    Ok(HttpResponse::Ok().json(CurrentUserResponse {
+        user: UserInfo {
+            id: user.id,
+            email: user.email,
+            first_name: user.first_name,
+            last_name: user.last_name,
+        },
+        active_entity_id: get_active_entity_id(&req),
+        memberships,
+    }))
+}
+
+/// Sélectionne l'entité de travail : de nouveaux jetons sont émis avec
+/// `active_entity_id`. L'appartenance et la politique MFA de l'entité sont
+/// vérifiées ; le niveau d'authentification de la session est conservé.
+pub async fn switch_entity(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    jwt_keys: web::Data<JwtKeys>,
+    http_req: HttpRequest,
+    req: ValidatedJson<SwitchEntityRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let claims = http_req.extensions().get::<Claims>().cloned()
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
 
-    let user = user.ok_or_else(|| actix_web::error::ErrorNotFound("User not found"))?;
+    let role = get_entity_role(&http_req, req.entity_id)
+        .ok_or_else(|| ApiError::Forbidden("Access denied".to_string()))?;
+    mfa::enforce_entity_mfa(db.get_ref(), &http_req, req.entity_id).await?;
 
-    Ok(HttpResponse::Ok().json(UserInfo {
-        id: user.id,
-        email: user.email,
-        first_name: user.first_name,
-        last_name: user.last_name,
+    let user = UserEntity::find_by_id(claims.user_id)
+        .one(db.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::Unauthorized("User not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(SwitchEntityResponse {
+        auth: auth_response(user, SessionContext { active_entity_id: Some(req.entity_id), ..claims.session() }, &config, &jwt_keys)?,
+        role,
     }))
 }
 
 pub async fn oidc_authorize(
+    db: web::Data<DatabaseConnection>,
     config: web::Data<Config>,
-) -> Result<HttpResponse, actix_web::Error> {
-    if config.oidc_issuer.is_none() || config.oidc_client_id.is_none() || config.oidc_redirect_uri.is_none() {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "OIDC not configured"
-        })));
-    }
+    http: web::Data<OutboundClient>,
+) -> Result<HttpResponse, ApiError> {
+    let client = oidc::OidcClient::from_config(&config)
+        .ok_or_else(|| ApiError::BadRequest("OIDC not configured".to_string()))?;
+
+    redirect_to_oidc_provider(db.get_ref(), &http, &client, None).await
+}
 
-    let issuer = config.oidc_issuer.as_ref().unwrap();
-    let discovery = crate::auth::oidc::discover_oidc_config(issuer)
+async fn redirect_to_oidc_provider(
+    db: &DatabaseConnection,
+    http: &OutboundClient,
+    client: &oidc::OidcClient,
+    provider_id: Option<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    let discovery = oidc::discover_oidc_config(http, &client.issuer)
         .await
-        .map_err(|e| {
-            log::error!("OIDC discovery error: {}", e);
-            actix_web::error::ErrorInternalServerError("OIDC discovery failed")
-        })?;
+        .map_err(ApiError::internal("OIDC discovery failed"))?;
 
-    let client_id = config.oidc_client_id.as_ref().unwrap();
-    let redirect_uri = config.oidc_redirect_uri.as_ref().unwrap();
-    
-    let auth_url = format!(
-        "{}?client_id={}&redirect_uri={}&response_type=code&scope=openid email profile",
-        discovery.authorization_endpoint,
-        client_id,
-        urlencoding::encode(redirect_uri)
-    );
+    let login_state = oidc::create_login_state(db, provider_id).await?;
+    let auth_url = oidc::build_authorization_url(client, &discovery, &login_state)
+        .map_err(ApiError::internal("Failed to build OIDC authorization URL"))?;
 
     Ok(HttpResponse::Found()
         .append_header(("Location", auth_url))
+        .cookie(oidc::login_state_cookie(&login_state))
         .finish())
 }
 
 pub async fn oidc_callback(
     db: web::Data<DatabaseConnection>,
     config: web::Data<Config>,
-    req: HttpRequest,
-) -> Result<HttpResponse, actix_web::Error> {
-    let code = req
-        .uri()
-        .query()
-        .and_then(|q| {
-            q.split('&')
-                .find_map(|pair| {
-                    let mut parts = pair.split('=');
-                    if parts.next() == Some("code") {
-                        parts.next().and_then(|c| {
-                            urlencoding::decode(c)
-                                .ok()
-                                .map(|decoded| decoded.to_string())
-                        })
-                    } else {
-                        None
-                    }
-                })
-        })
-        .ok_or_else(|| actix_web::error::ErrorBadRequest("Missing authorization code"))?;
+    http: web::Data<OutboundClient>,
+    jwks: web::Data<oidc::JwksCache>,
+    http_req: HttpRequest,
+    query: web::Query<OidcCallbackQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let query = query.into_inner();
+    if let Some(error) = query.error {
+        log::warn!("OIDC provider returned an error: {} {}", error, query.error_description.unwrap_or_default());
+        return Err(ApiError::Unauthorized("OIDC authentication failed".to_string()));
+    }
 
-    let issuer = config.oidc_issuer.as_ref()
-        .ok_or_else(|| actix_web::error::ErrorInternalServerError("OIDC not configured"))?;
+    // Le state est à usage unique : il porte le nonce, le vérificateur PKCE et le fournisseur
+    let state = query.state
+        .ok_or_else(|| ApiError::BadRequest("Missing OIDC state".to_string()))?;
+    // Le state doit avoir été émis pour ce navigateur ; sinon il n'est pas consommé
+    if !oidc::state_matches_cookie(&http_req, &state) {
+        return Err(ApiError::BadRequest("OIDC state does not match this browser".to_string()));
+    }
+    let login_state = oidc::take_login_state(db.get_ref(), &state)
+        .await?
+        .ok_or_else(|| ApiError::BadRequest("Invalid or expired OIDC state".to_string()))?;
+    let code = query.code
+        .ok_or_else(|| ApiError::BadRequest("Missing authorization code".to_string()))?;
 
-    let discovery = crate::auth::oidc::discover_oidc_config(issuer)
-        .await
-        .map_err(|e| {
-            log::error!("OIDC discovery error: {}", e);
-            actix_web::error::ErrorInternalServerError("OIDC discovery failed")
-        })?;
+    let provider = match login_state.provider_id {
+        Some(provider_id) => Some(sso::find_enabled_provider(db.get_ref(), provider_id).await?),
+        None => None,
+    };
+    let client = match &provider {
+        Some(p) => oidc::OidcClient::from_provider(p, &config),
+        None => oidc::OidcClient::from_config(&config),
+    }.ok_or_else(|| ApiError::Internal("OIDC not configured".to_string()))?;
 
-    let token_response = crate::auth::oidc::exchange_code_for_token(&code, &config, &discovery)
+    let discovery = oidc::discover_oidc_config(&http, &client.issuer)
         .await
-        .map_err(|e| {
-            log::error!("Token exchange error: {}", e);
-            actix_web::error::ErrorInternalServerError("Token exchange failed")
-        })?;
+        .map_err(ApiError::internal("OIDC discovery failed"))?;
 
-    let user_info = crate::auth::oidc::get_user_info(&token_response.access_token, &discovery)
+    let token_response = oidc::exchange_code_for_token(&http, &code, &login_state.code_verifier, &client, &discovery)
         .await
-        .map_err(|e| {
-            log::error!("User info error: {}", e);
-            actix_web::error::ErrorInternalServerError("Failed to get user info")
-        })?;
-
-    // Get or create user
-    let email = user_info.email
-        .ok_or_else(|| actix_web::error::ErrorBadRequest("Email not provided by OIDC provider"))?;
+        .map_err(ApiError::internal("Token exchange failed"))?;
 
-    let user = UserEntity::find()
-        .filter(UserColumn::Email.eq(&email))
-        .one(db.get_ref())
+    let id_token = token_response.id_token.as_deref()
+        .ok_or_else(|| ApiError::Unauthorized("OIDC provider returned no id_token".to_string()))?;
+    let claims = oidc::verify_id_token(id_token, &login_state.nonce, &client, &discovery, &jwks)
         .await
         .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
+            log::warn!("id_token rejected: {:#}", e);
+            ApiError::Unauthorized("Invalid id_token".to_string())
         })?;
 
-    let user = if let Some(u) = user {
-        u
-    } else {
-        // Create new user from OIDC
-        let password_hash = bcrypt::hash("oidc-user", bcrypt::DEFAULT_COST)
-            .map_err(|e| {
-                log::error!("Bcrypt error: {}", e);
-                actix_web::error::ErrorInternalServerError("Password hashing failed")
-            })?;
-
-        let now = Utc::now();
-        let new_user = UserActiveModel {
-            id: Set(Uuid::new_v4()),
-            email: Set(email.clone()),
-            password_hash: Set(password_hash),
-            first_name: Set(user_info.given_name),
-            last_name: Set(user_info.family_name),
-            is_active: Set(true),
-            created_at: Set(now),
-            updated_at: Set(now),
-        };
+    let groups_claim = provider.as_ref().map(|p| p.groups_claim.as_str()).unwrap_or("groups");
+    let mut identity = sso::FederatedIdentity {
+        issuer: claims.iss.clone(),
+        subject: claims.sub.clone(),
+        email: claims.email.clone(),
+        email_verified: claims.email_verified(),
+        given_name: claims.given_name.clone(),
+        family_name: claims.family_name.clone(),
+        groups: sso::claim_values(claims.additional.get(groups_claim)),
+    };
 
-        UserEntity::insert(new_user)
-            .exec_with_returning(db.get_ref())
+    // Sans email dans l'id_token, on interroge le endpoint userinfo (même subject)
+    if identity.email.is_none() {
+        let user_info = oidc::get_user_info(&http, &token_response.access_token, &discovery)
             .await
-            .map_err(|e| {
-                log::error!("Database error: {}", e);
-                actix_web::error::ErrorInternalServerError("Database error")
-            })?
-    };
+            .map_err(ApiError::internal("Failed to get user info"))?;
+        if user_info.sub != claims.sub {
+            return Err(ApiError::Unauthorized("OIDC userinfo subject mismatch".to_string()));
+        }
+        identity.email_verified = user_info.email_verified();
+        identity.email = user_info.email;
+        identity.given_name = identity.given_name.or(user_info.given_name);
+        identity.family_name = identity.family_name.or(user_info.family_name);
+        if identity.groups.is_empty() {
+            identity.groups = sso::claim_values(user_info.additional.get(groups_claim));
+        }
+    }
 
-    let token = create_token(user.id, user.email.clone(), &config)
-        .map_err(|e| {
-            log::error!("JWT error: {}", e);
-            actix_web::error::ErrorInternalServerError("Token creation failed")
-        })?;
+    let (trusted, mfa_acr_values) = sso::mfa_trust(provider.as_ref(), &config);
+    let session = SessionContext {
+        amr: sso::federated_amr(&claims.amr, claims.acr.as_deref(), trusted, &mfa_acr_values),
+        acr: claims.acr.clone(),
+        active_entity_id: None,
+    };
 
-    let refresh_token = create_refresh_token(user.id, user.email.clone())
-        .map_err(|e| {
-            log::error!("JWT error: {}", e);
-            actix_web::error::ErrorInternalServerError("Token creation failed")
-        })?;
+    let user = sso::resolve_user(db.get_ref(), identity, provider.as_ref()).await?;
+    let mut response = federated_login_redirect(db.get_ref(), &config, user.id, &session).await?;
+    response.add_cookie(&oidc::clear_login_state_cookie())
+        .map_err(ApiError::internal("Failed to clear the OIDC state cookie"))?;
+    Ok(response)
+}
 
-    // Redirect to frontend with tokens
-    let frontend_url = std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:5173".to_string());
+// Seul un code à usage unique transite par l'URL ; les jetons sont obtenus
+// par le frontend via POST /auth/oidc/exchange. L'authentification déclarée
+// par le fournisseur reste côté serveur, attachée au code.
+async fn federated_login_redirect(
+    db: &DatabaseConnection,
+    config: &Config,
+    user_id: Uuid,
+    session: &SessionContext,
+) -> Result<HttpResponse, ApiError> {
+    let context = serde_json::to_value(session)
+        .map_err(ApiError::internal("Federated session serialization failed"))?;
+    let code = tokens::issue_with_context(
+        db,
+        &config.jwt_secret,
+        user_id,
+        tokens::PURPOSE_OIDC_LOGIN,
+        tokens::OIDC_LOGIN_CODE_TTL_SECONDS,
+        Some(context),
+    ).await?;
     let redirect_url = format!(
-        "{}/auth/callback?token={}&refresh_token={}",
-        frontend_url,
-        urlencoding::encode(&token),
-        urlencoding::encode(&refresh_token)
+        "{}/auth/callback?code={}",
+        config.frontend_url.trim_end_matches('/'),
+        urlencoding::encode(&code)
     );
 
     Ok(HttpResponse::Found()
         .append_header(("Location", redirect_url))
+        .append_header(("Cache-Control", "no-store"))
+        .append_header(("Referrer-Policy", "no-referrer"))
         .finish())
 }
 
+pub async fn oidc_exchange(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    jwt_keys: web::Data<JwtKeys>,
+    req: ValidatedJson<OidcExchangeRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let (user_id, context) = tokens::consume_with_context(db.get_ref(), &config.jwt_secret, &req.code, tokens::PURPOSE_OIDC_LOGIN)
+        .await
+        .map_err(|e| match e {
+            ApiError::Validation(_) => ApiError::Unauthorized("Invalid or expired code".to_string()),
+            other => other,
+        })?;
+    let user = find_active_user(db.get_ref(), user_id).await?;
+    let session: SessionContext = context
+        .and_then(|c| serde_json::from_value(c).ok())
+        .unwrap_or_else(|| SessionContext::with_methods(&[AMR_FEDERATED]));
+
+    // Comme pour le mot de passe, le second facteur enrôlé est exigé, sauf si
+    // un fournisseur reconnu l'a déjà vérifié
+    if !session.amr.iter().any(|m| m == AMR_MFA) {
+        let methods = mfa::user_mfa_methods(db.get_ref(), user.id).await?;
+        if !methods.is_empty() {
+            return mfa_challenge_response(db.get_ref(), user.id, methods, &session).await;
+        }
+    }
+
+    Ok(HttpResponse::Ok()
+        .append_header(("Cache-Control", "no-store"))
+        .json(auth_response(user, session, &config, &jwt_keys)?))
+}
+
+// Connexion via le fournisseur d'identité d'une entité (OIDC ou SAML)
+
+pub async fn sso_discover(
+    db: web::Data<DatabaseConnection>,
+    req: ValidatedJson<EmailRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let provider = sso::find_provider_for_email(db.get_ref(), &req.email)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("No identity provider for this email domain".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(SsoDiscoveryResponse {
+        authorize_url: format!("/auth/sso/{}/authorize", provider.id),
+        provider_id: provider.id,
+        name: provider.name,
+        protocol: provider.protocol,
+    }))
+}
+
+pub async fn sso_authorize(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    http: web::Data<OutboundClient>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    let provider = sso::find_enabled_provider(db.get_ref(), path.into_inner()).await?;
+
+    if provider.protocol == sso::PROTOCOL_SAML {
+        let sso_url = provider.sso_url.as_deref()
+            .ok_or_else(|| ApiError::Internal("SAML provider has no SSO URL".to_string()))?;
+        let request_id = saml::create_request(db.get_ref(), provider.id).await?;
+        let auth_url = saml::build_authn_request_url(sso_url, &request_id, &config.saml_entity_id(), &config.saml_acs_url())
+            .map_err(ApiError::internal("Failed to build SAML authentication request"))?;
+        return Ok(HttpResponse::Found()
+            .append_header(("Location", auth_url))
+            .cookie(saml::request_cookie(&request_id))
+            .finish());
+    }
+
+    let client = oidc::OidcClient::from_provider(&provider, &config)
+        .ok_or_else(|| ApiError::Internal("OIDC provider has no client ID".to_string()))?;
+    redirect_to_oidc_provider(db.get_ref(), &http, &client, Some(provider.id)).await
+}
+
+pub async fn saml_acs(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    http_req: HttpRequest,
+    form: web::Form<SamlAcsForm>,
+) -> Result<HttpResponse, ApiError> {
+    let response = saml::SamlResponse::decode(&form.saml_response).map_err(|e| {
+        log::warn!("SAML response rejected: {:#}", e);
+        ApiError::BadRequest("Invalid SAML response".to_string())
+    })?;
+
+    // La requête d'origine désigne le fournisseur dont le certificat doit avoir signé la réponse
+    let request_id = response.in_response_to()
+        .ok_or_else(|| ApiError::BadRequest("Unsolicited SAML responses are not accepted".to_string()))?;
+    // La requête doit avoir été émise pour ce navigateur ; sinon elle n'est pas consommée
+    if !saml::request_matches_cookie(&http_req, request_id) {
+        return Err(ApiError::BadRequest("SAML response does not match this browser".to_string()));
+    }
+    let request = saml::take_request(db.get_ref(), request_id)
+        .await?
+        .ok_or_else(|| ApiError::BadRequest("Invalid or expired SAML request".to_string()))?;
+    let provider = sso::find_enabled_provider(db.get_ref(), request.provider_id).await?;
+
+    let sp_entity_id = config.saml_entity_id();
+    let acs_url = config.saml_acs_url();
+    let assertion = response.validate(&saml::SamlExpectations {
+        request_id: &request.request_id,
+        idp_issuer: &provider.issuer,
+        idp_certificate: provider.certificate.as_deref().unwrap_or_default(),
+        sp_entity_id: &sp_entity_id,
+        acs_url: &acs_url,
+    }).map_err(|e| {
+        log::warn!("SAML assertion rejected: {:#}", e);
+        ApiError::Unauthorized("Invalid SAML assertion".to_string())
+    })?;
+
+    let email = assertion.first_attribute(sso::SAML_EMAIL_ATTRIBUTES)
+        .or_else(|| assertion.name_id.contains('@').then(|| assertion.name_id.clone()));
+    let identity = sso::FederatedIdentity {
+        email,
+        // SAML n'a pas d'équivalent à email_verified : seuls les domaines du fournisseur sont acceptés
+        email_verified: false,
+        given_name: assertion.first_attribute(sso::SAML_GIVEN_NAME_ATTRIBUTES),
+        family_name: assertion.first_attribute(sso::SAML_FAMILY_NAME_ATTRIBUTES),
+        groups: assertion.attributes.get(&provider.groups_claim).cloned().unwrap_or_default(),
+        issuer: assertion.issuer,
+        subject: assertion.name_id,
+    };
+
+    let (trusted, mfa_acr_values) = sso::mfa_trust(Some(&provider), &config);
+    let acr = assertion.authn_context_class_ref.clone();
+    let session = SessionContext {
+        amr: sso::federated_amr(&[], acr.as_deref(), trusted, &mfa_acr_values),
+        acr,
+        active_entity_id: None,
+    };
+
+    let user = sso::resolve_user(db.get_ref(), identity, Some(&provider)).await?;
+    let mut response = federated_login_redirect(db.get_ref(), &config, user.id, &session).await?;
+    response.add_cookie(&saml::clear_request_cookie())
+        .map_err(ApiError::internal("Failed to clear the SAML request cookie"))?;
+    Ok(response)
+}
+
+pub async fn saml_metadata(
+    config: web::Data<Config>,
+) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok()
+        .content_type("application/samlmetadata+xml")
+        .body(saml::sp_metadata(&config.saml_entity_id(), &config.saml_acs_url())))
+}
+
+async fn find_active_user(db: &DatabaseConnection, user_id: Uuid) -> Result<UserModel, ApiError> {
+    UserEntity::find()
+        .filter(UserColumn::Id.eq(user_id))
+        .filter(UserColumn::IsActive.eq(true))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::Unauthorized("User not found".to_string()))
+}
+
+// Second facteur : étape de connexion (après le mot de passe)
+
+// Les échecs du second facteur comptent contre le compte, comme ceux du mot de
+// passe : relancer la connexion ne donne pas de nouvelles tentatives.
+async fn start_second_factor(throttle: &dyn LoginThrottle, key: &ThrottleKey) -> Result<(), ApiError> {
+    if let Some(retry_after) = throttle.record_attempt(key).await
+        .map_err(ApiError::internal("Login throttle failure"))?
+    {
+        return Err(ApiError::TooManyRequests { retry_after });
+    }
+    Ok(())
+}
+
+async fn finish_second_factor(throttle: &dyn LoginThrottle, key: &ThrottleKey, verified: bool) -> Result<(), ApiError> {
+    let result = if verified { throttle.reset(key).await } else { throttle.record_failure(key).await };
+    result.map_err(ApiError::internal("Login throttle failure"))
+}
+
+pub async fn login_mfa(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    jwt_keys: web::Data<JwtKeys>,
+    throttle: web::Data<dyn LoginThrottle>,
+    req: ValidatedJson<MfaVerifyRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let challenge = mfa::find_challenge(db.get_ref(), &req.mfa_token, mfa::CHALLENGE_LOGIN).await?;
+    let user = find_active_user(db.get_ref(), challenge.user_id).await?;
+    let key = ThrottleKey::account(&user.email);
+    start_second_factor(throttle.get_ref(), &key).await?;
+
+    let verified = if req.method == mfa::METHOD_TOTP {
+        mfa::verify_totp(db.get_ref(), &config, user.id, &req.code, true).await?
+    } else {
+        mfa::use_recovery_code(db.get_ref(), user.id, &req.code).await?
+    };
+    finish_second_factor(throttle.get_ref(), &key, verified).await?;
+
+    if !verified {
+        mfa::record_challenge_failure(db.get_ref(), challenge).await?;
+        return Err(ApiError::Unauthorized("Invalid MFA code".to_string()));
+    }
+    let session = mfa::pending_session(&challenge);
+    mfa::consume_challenge(db.get_ref(), challenge).await?;
+
+    complete_mfa_login(db.get_ref(), user, &session, AMR_OTP, &config, &jwt_keys).await
+}
+
+pub async fn login_webauthn_start(
+    db: web::Data<DatabaseConnection>,
+    webauthn: web::Data<Webauthn>,
+    req: ValidatedJson<MfaTokenRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let challenge = mfa::find_challenge(db.get_ref(), &req.mfa_token, mfa::CHALLENGE_LOGIN).await?;
+
+    let passkeys: Vec<Passkey> = mfa::load_passkeys(db.get_ref(), challenge.user_id).await?
+        .into_iter()
+        .map(|(_, passkey)| passkey)
+        .collect();
+    if passkeys.is_empty() {
+        return Err(ApiError::BadRequest("No WebAuthn credential registered".to_string()));
+    }
+
+    let (options, state) = webauthn.start_passkey_authentication(&passkeys)
+        .map_err(ApiError::internal("WebAuthn challenge failed"))?;
+    let state = serde_json::to_value(&state)
+        .map_err(ApiError::internal("WebAuthn challenge failed"))?;
+    mfa::set_passkey_state(db.get_ref(), challenge, state).await?;
+
+    Ok(HttpResponse::Ok().json(options))
+}
+
+pub async fn login_webauthn_finish(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    jwt_keys: web::Data<JwtKeys>,
+    webauthn: web::Data<Webauthn>,
+    throttle: web::Data<dyn LoginThrottle>,
+    req: ValidatedJson<WebauthnLoginFinishRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let challenge = mfa::find_challenge(db.get_ref(), &req.mfa_token, mfa::CHALLENGE_LOGIN).await?;
+    let user = find_active_user(db.get_ref(), challenge.user_id).await?;
+    let user_id = user.id;
+    let key = ThrottleKey::account(&user.email);
+
+    let state: PasskeyAuthentication = mfa::passkey_state(&challenge)
+        .and_then(|s| serde_json::from_value(s).ok())
+        .ok_or_else(|| ApiError::BadRequest("WebAuthn authentication not started".to_string()))?;
+
+    start_second_factor(throttle.get_ref(), &key).await?;
+    let result = match webauthn.finish_passkey_authentication(&req.credential, &state) {
+        Ok(result) => result,
+        Err(e) => {
+            log::warn!("WebAuthn authentication failed for user {}: {}", user_id, e);
+            finish_second_factor(throttle.get_ref(), &key, false).await?;
+            mfa::record_challenge_failure(db.get_ref(), challenge).await?;
+            return Err(ApiError::Unauthorized("Invalid WebAuthn assertion".to_string()));
+        }
+    };
+
+    // Met à jour le compteur de signature de la clé utilisée
+    for (credential, mut passkey) in mfa::load_passkeys(db.get_ref(), user_id).await? {
+        if passkey.cred_id() != result.cred_id() {
+            continue;
+        }
+        passkey.update_credential(&result);
+        let mut credential = credential.into_active_model();
+        credential.passkey = Set(serde_json::to_value(&passkey)
+            .map_err(ApiError::internal("WebAuthn credential update failed"))?);
+        credential.last_used_at = Set(Some(Utc::now()));
+        credential.update(db.get_ref()).await?;
+    }
+    let session = mfa::pending_session(&challenge);
+    mfa::consume_challenge(db.get_ref(), challenge).await?;
+    finish_second_factor(throttle.get_ref(), &key, true).await?;
+
+    complete_mfa_login(db.get_ref(), user, &session, AMR_HARDWARE_KEY, &config, &jwt_keys).await
+}
+
+// L'entité active d'une élévation est conservée tant que l'utilisateur en est membre
+async fn complete_mfa_login(
+    db: &DatabaseConnection,
+    user: UserModel,
+    session: &SessionContext,
+    method: &str,
+    config: &Config,
+    jwt_keys: &JwtKeys,
+) -> Result<HttpResponse, ApiError> {
+    let active_entity_id = match session.active_entity_id {
+        Some(entity_id) => {
+            let memberships = EntityMemberships::load(db, user.id).await?;
+            memberships.role(entity_id).is_some().then_some(entity_id)
+        }
+        None => None,
+    };
+    let session = SessionContext {
+        amr: session.amr_with_factor(method),
+        acr: session.acr.clone(),
+        active_entity_id,
+    };
+    Ok(HttpResponse::Ok().json(auth_response(user, session, config, jwt_keys)?))
+}
+
+/// Élévation d'une session ouverte sans second facteur (fournisseur fédéré non
+/// reconnu, facteur enrôlé après la connexion) : le challenge se valide par
+/// `/auth/login/mfa` ou `/auth/login/webauthn/*`, qui émettent des jetons
+/// conservant les méthodes, l'acr et l'entité active de la session.
+pub async fn mfa_step_up(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let claims = req.extensions().get::<Claims>().cloned()
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+    if claims.is_mfa() {
+        return Err(ApiError::BadRequest("Session already verified with a second factor".to_string()));
+    }
+
+    let methods = mfa::user_mfa_methods(db.get_ref(), claims.user_id).await?;
+    if methods.is_empty() {
+        return Err(ApiError::BadRequest("No second factor enrolled".to_string()));
+    }
+
+    mfa_challenge_response(db.get_ref(), claims.user_id, methods, &claims.session()).await
+}
+
+// Second facteur : gestion par l'utilisateur connecté
+
+// Ajouter ou retirer un facteur exige une session déjà validée par MFA,
+// sauf pour l'enrôlement du tout premier facteur.
+async fn require_mfa_session_if_enrolled(db: &DatabaseConnection, req: &HttpRequest, user_id: Uuid) -> Result<(), ApiError> {
+    if !mfa::session_has_mfa(req) && !mfa::user_mfa_methods(db, user_id).await?.is_empty() {
+        return Err(ApiError::MfaRequired);
+    }
+    Ok(())
+}
+
+// Les codes de secours n'ont plus d'usage une fois le dernier facteur retiré
+async fn drop_recovery_codes_if_unused(db: &DatabaseConnection, user_id: Uuid) -> Result<(), ApiError> {
+    if mfa::user_mfa_methods(db, user_id).await?.is_empty() {
+        RecoveryCodeEntity::delete_many()
+            .filter(RecoveryCodeColumn::UserId.eq(user_id))
+            .exec(db)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn recovery_codes_if_missing(db: &DatabaseConnection, user_id: Uuid) -> Result<Vec<String>, ApiError> {
+    if mfa::remaining_recovery_codes(db, user_id).await? > 0 {
+        return Ok(Vec::new());
+    }
+    Ok(mfa::regenerate_recovery_codes(db, user_id).await?)
+}
+
+pub async fn mfa_status(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = get_current_user_id(&req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+
+    let totp = UserTotpEntity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await?;
+
+    let credentials = WebauthnCredentialEntity::find()
+        .filter(WebauthnCredentialColumn::UserId.eq(user_id))
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(|c| WebauthnCredentialInfo {
+            id: c.id,
+            name: c.name,
+            created_at: c.created_at,
+            last_used_at: c.last_used_at,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(MfaStatus {
+        totp_enabled: totp.is_some_and(|t| t.confirmed_at.is_some()),
+        webauthn_credentials: credentials,
+        recovery_codes_remaining: mfa::remaining_recovery_codes(db.get_ref(), user_id).await?,
+    }))
+}
+
+pub async fn totp_enroll(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = get_current_user_id(&req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+    require_mfa_session_if_enrolled(db.get_ref(), &req, user_id).await?;
+    let user = find_active_user(db.get_ref(), user_id).await?;
+
+    let existing = UserTotpEntity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await?;
+    if existing.as_ref().is_some_and(|t| t.confirmed_at.is_some()) {
+        return Err(ApiError::Conflict("TOTP already enabled".to_string()));
+    }
+
+    // Le secret reste inactif tant qu'un code n'a pas été confirmé
+    let (secret, otpauth_url) = mfa::new_totp_secret(&config.mfa_issuer, &user.email)?;
+    let stored_secret = mfa::encrypt_secret(&config, user_id, &secret)?;
+    match existing {
+        Some(t) => {
+            let mut totp = t.into_active_model();
+            totp.secret = Set(stored_secret);
+            totp.last_used_step = Set(None);
+            totp.created_at = Set(Utc::now());
+            totp.update(db.get_ref()).await?;
+        }
+        None => {
+            let totp = UserTotpActiveModel {
+                user_id: Set(user_id),
+                secret: Set(stored_secret),
+                confirmed_at: Set(None),
+                last_used_step: Set(None),
+                created_at: Set(Utc::now()),
+            };
+            UserTotpEntity::insert(totp).exec(db.get_ref()).await?;
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(TotpEnrollResponse { secret, otpauth_url }))
+}
+
+pub async fn totp_confirm(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    body: ValidatedJson<TotpCodeRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = get_current_user_id(&req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+
+    let pending = UserTotpEntity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await?;
+    match pending {
+        None => return Err(ApiError::NotFound("No TOTP enrollment in progress".to_string())),
+        Some(t) if t.confirmed_at.is_some() => return Err(ApiError::Conflict("TOTP already enabled".to_string())),
+        Some(_) => {}
+    }
+
+    if !mfa::verify_totp(db.get_ref(), &config, user_id, &body.code, false).await? {
+        return Err(ApiError::validation("code", "invalid_code", "Invalid TOTP code"));
+    }
+
+    let totp = UserTotpEntity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::NotFound("No TOTP enrollment in progress".to_string()))?;
+    let mut totp = totp.into_active_model();
+    totp.confirmed_at = Set(Some(Utc::now()));
+    totp.update(db.get_ref()).await?;
+
+    let recovery_codes = recovery_codes_if_missing(db.get_ref(), user_id).await?;
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes }))
+}
+
+pub async fn totp_disable(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    body: ValidatedJson<TotpCodeRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = get_current_user_id(&req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+    if !mfa::session_has_mfa(&req) {
+        return Err(ApiError::MfaRequired);
+    }
+
+    if !mfa::verify_totp(db.get_ref(), &config, user_id, &body.code, true).await? {
+        return Err(ApiError::validation("code", "invalid_code", "Invalid TOTP code"));
+    }
+
+    UserTotpEntity::delete_by_id(user_id)
+        .exec(db.get_ref())
+        .await?;
+    drop_recovery_codes_if_unused(db.get_ref(), user_id).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn regenerate_recovery_codes(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = get_current_user_id(&req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+    if !mfa::session_has_mfa(&req) {
+        return Err(ApiError::MfaRequired);
+    }
+
+    let recovery_codes = mfa::regenerate_recovery_codes(db.get_ref(), user_id).await?;
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes }))
+}
+
+pub async fn webauthn_register_start(
+    db: web::Data<DatabaseConnection>,
+    webauthn: web::Data<Webauthn>,
+    req: HttpRequest,
+    body: ValidatedJson<WebauthnRegisterStartRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = get_current_user_id(&req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+    require_mfa_session_if_enrolled(db.get_ref(), &req, user_id).await?;
+    let user = find_active_user(db.get_ref(), user_id).await?;
+
+    // Empêche d'enregistrer deux fois le même authentificateur
+    let exclude: Vec<CredentialID> = mfa::load_passkeys(db.get_ref(), user_id).await?
+        .iter()
+        .map(|(_, passkey)| passkey.cred_id().clone())
+        .collect();
+
+    let display_name = match (&user.first_name, &user.last_name) {
+        (Some(first), Some(last)) => format!("{} {}", first, last),
+        _ => user.email.clone(),
+    };
+    let (options, registration) = webauthn
+        .start_passkey_registration(user.id, &user.email, &display_name, Some(exclude))
+        .map_err(ApiError::internal("WebAuthn registration failed"))?;
+
+    let state = serde_json::json!({
+        "name": body.name.trim(),
+        "registration": serde_json::to_value(&registration)
+            .map_err(ApiError::internal("WebAuthn registration failed"))?,
+    });
+    let registration_token = mfa::create_challenge(
+        db.get_ref(), user_id, mfa::CHALLENGE_WEBAUTHN_REGISTRATION, Some(state),
+    ).await?;
+
+    Ok(HttpResponse::Ok().json(WebauthnRegisterStartResponse { registration_token, options }))
+}
+
+pub async fn webauthn_register_finish(
+    db: web::Data<DatabaseConnection>,
+    webauthn: web::Data<Webauthn>,
+    req: HttpRequest,
+    body: ValidatedJson<WebauthnRegisterFinishRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = get_current_user_id(&req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+
+    let challenge = mfa::find_challenge(
+        db.get_ref(), &body.registration_token, mfa::CHALLENGE_WEBAUTHN_REGISTRATION,
+    ).await?;
+    if challenge.user_id != user_id {
+        return Err(ApiError::Unauthorized("Invalid or expired MFA token".to_string()));
+    }
+
+    let state = challenge.state.clone().unwrap_or_default();
+    let name = state.get("name").and_then(|n| n.as_str()).unwrap_or("Passkey").to_string();
+    let registration: PasskeyRegistration = state.get("registration")
+        .cloned()
+        .and_then(|r| serde_json::from_value(r).ok())
+        .ok_or_else(|| ApiError::BadRequest("WebAuthn registration not started".to_string()))?;
+
+    let passkey = match webauthn.finish_passkey_registration(&body.credential, &registration) {
+        Ok(passkey) => passkey,
+        Err(e) => {
+            log::warn!("WebAuthn registration failed for user {}: {}", user_id, e);
+            mfa::record_challenge_failure(db.get_ref(), challenge).await?;
+            return Err(ApiError::validation("credential", "invalid_credential", "Invalid WebAuthn registration"));
+        }
+    };
+    mfa::consume_challenge(db.get_ref(), challenge).await?;
+
+    let credential = WebauthnCredentialActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        name: Set(name),
+        passkey: Set(serde_json::to_value(&passkey)
+            .map_err(ApiError::internal("WebAuthn registration failed"))?),
+        created_at: Set(Utc::now()),
+        last_used_at: Set(None),
+    };
+    let credential = WebauthnCredentialEntity::insert(credential)
+        .exec_with_returning(db.get_ref())
+        .await?;
+
+    let recovery_codes = recovery_codes_if_missing(db.get_ref(), user_id).await?;
+    Ok(HttpResponse::Created().json(WebauthnRegisterFinishResponse {
+        credential: WebauthnCredentialInfo {
+            id: credential.id,
+            name: credential.name,
+            created_at: credential.created_at,
+            last_used_at: credential.last_used_at,
+        },
+        recovery_codes,
+    }))
+}
+
+pub async fn delete_webauthn_credential(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = get_current_user_id(&req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+    if !mfa::session_has_mfa(&req) {
+        return Err(ApiError::MfaRequired);
+    }
+
+    let result = WebauthnCredentialEntity::delete_many()
+        .filter(WebauthnCredentialColumn::Id.eq(path.into_inner()))
+        .filter(WebauthnCredentialColumn::UserId.eq(user_id))
+        .exec(db.get_ref())
+        .await?;
+    if result.rows_affected == 0 {
+        return Err(ApiError::NotFound("Credential not found".to_string()));
+    }
+    drop_recovery_codes_if_unused(db.get_ref(), user_id).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Vérification d'email et mots de passe
+
+// Délai minimal entre deux emails du même type pour un compte
+const ACCOUNT_EMAIL_COOLDOWN_SECONDS: i64 = 60;
+
+// Envoie un lien contenant un jeton à usage unique. Un échec d'envoi est
+// journalisé sans être renvoyé au client (pas d'énumération des comptes).
+async fn send_account_email(
+    db: &DatabaseConnection,
+    config: &Config,
+    mailer: &dyn Mailer,
+    user: &UserModel,
+    purpose: &str,
+) -> Result<(), ApiError> {
+    let (ttl, path, subject, intro) = if purpose == tokens::PURPOSE_PASSWORD_RESET {
+        (
+            tokens::PASSWORD_RESET_TTL_SECONDS,
+            "reset-password",
+            "Réinitialisation de votre mot de passe",
+            "Pour choisir un nouveau mot de passe, ouvrez le lien suivant (valable 1 heure) :",
+        )
+    } else {
+        (
+            tokens::EMAIL_VERIFICATION_TTL_SECONDS,
+            "verify-email",
+            "Vérification de votre adresse email",
+            "Pour confirmer votre adresse email, ouvrez le lien suivant (valable 24 heures) :",
+        )
+    };
+
+    let token = tokens::issue(db, &config.jwt_secret, user.id, purpose, ttl).await?;
+    let email = Email {
+        to: user.email.clone(),
+        subject: subject.to_string(),
+        body: format!(
+            "Bonjour,\n\n{}\n{}/{}?token={}\n\nSi vous n'êtes pas à l'origine de cette demande, ignorez ce message.\n",
+            intro,
+            config.frontend_url.trim_end_matches('/'),
+            path,
+            urlencoding::encode(&token)
+        ),
+    };
+    if let Err(e) = mailer.send(&email).await {
+        log::error!("Failed to send {} email to user {}: {}", purpose, user.id, e);
+    }
+    Ok(())
+}
+
+async fn send_password_changed_notice(mailer: &dyn Mailer, user: &UserModel) {
+    let email = Email {
+        to: user.email.clone(),
+        subject: "Votre mot de passe a été modifié".to_string(),
+        body: "Bonjour,\n\nLe mot de passe de votre compte HyperCyber vient d'être modifié et les autres sessions ont été fermées.\nSi vous n'êtes pas à l'origine de ce changement, contactez immédiatement votre administrateur.\n".to_string(),
+    };
+    if let Err(e) = mailer.send(&email).await {
+        log::error!("Failed to send password change notice to user {}: {}", user.id, e);
+    }
+}
+
+// Enregistre un nouveau mot de passe et invalide tous les jetons émis auparavant
+async fn store_new_password(db: &DatabaseConnection, user: UserModel, new_password: &str) -> Result<UserModel, ApiError> {
+    let password_hash = hash(new_password, DEFAULT_COST)
+        .map_err(ApiError::internal("Password hashing failed"))?;
+
+    let now = Utc::now();
+    let verified_at = user.email_verified_at.unwrap_or(now);
+    let mut user = user.into_active_model();
+    user.password_hash = Set(password_hash);
+    user.sessions_valid_after = Set(Some(now));
+    user.email_verified_at = Set(Some(verified_at));
+    user.updated_at = Set(now);
+    Ok(user.update(db).await?)
+}
+
+pub async fn verify_email(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: ValidatedJson<TokenRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = tokens::consume(db.get_ref(), &config.jwt_secret, &req.token, tokens::PURPOSE_EMAIL_VERIFICATION).await?;
+    let user = find_active_user(db.get_ref(), user_id).await?;
+
+    if user.email_verified_at.is_none() {
+        let mut user = user.into_active_model();
+        user.email_verified_at = Set(Some(Utc::now()));
+        user.update(db.get_ref()).await?;
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn resend_verification_email(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    mailer: web::Data<dyn Mailer>,
+    req: ValidatedJson<EmailRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user = UserEntity::find()
+        .filter(UserColumn::Email.eq(&req.email))
+        .filter(UserColumn::IsActive.eq(true))
+        .filter(UserColumn::EmailVerifiedAt.is_null())
+        .one(db.get_ref())
+        .await?;
+
+    // Réponse identique que le compte existe ou non
+    if let Some(user) = user
+        && !tokens::recently_issued(db.get_ref(), user.id, tokens::PURPOSE_EMAIL_VERIFICATION, ACCOUNT_EMAIL_COOLDOWN_SECONDS).await?
+    {
+        send_account_email(db.get_ref(), &config, mailer.get_ref(), &user, tokens::PURPOSE_EMAIL_VERIFICATION).await?;
+    }
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
+pub async fn forgot_password(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    mailer: web::Data<dyn Mailer>,
+    req: ValidatedJson<EmailRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user = UserEntity::find()
+        .filter(UserColumn::Email.eq(&req.email))
+        .filter(UserColumn::IsActive.eq(true))
+        .one(db.get_ref())
+        .await?;
+
+    // Réponse identique que le compte existe ou non
+    if let Some(user) = user
+        && !tokens::recently_issued(db.get_ref(), user.id, tokens::PURPOSE_PASSWORD_RESET, ACCOUNT_EMAIL_COOLDOWN_SECONDS).await?
+    {
+        send_account_email(db.get_ref(), &config, mailer.get_ref(), &user, tokens::PURPOSE_PASSWORD_RESET).await?;
+    }
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
+pub async fn reset_password(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    mailer: web::Data<dyn Mailer>,
+    throttle: web::Data<dyn LoginThrottle>,
+    req: ValidatedJson<ResetPasswordRequest>,
+) -> Result<HttpResponse, ApiError> {
+    config.password_policy.check("new_password", &req.new_password)?;
+
+    let user_id = tokens::consume(db.get_ref(), &config.jwt_secret, &req.token, tokens::PURPOSE_PASSWORD_RESET).await?;
+    let user = find_active_user(db.get_ref(), user_id).await?;
+    let user = store_new_password(db.get_ref(), user, &req.new_password).await?;
+    // Récupération d'un compte peut-être compromis : les jetons d'API tombent avec les sessions
+    let revoked = api_tokens::revoke(db.get_ref(), user.id, None).await?;
+    if revoked > 0 {
+        log::info!("Revoked {} API tokens of user {} after a password reset", revoked, user.id);
+    }
+
+    // Le verrouillage éventuel du compte n'a plus lieu d'être
+    throttle.reset(&ThrottleKey::account(&user.email)).await
+        .map_err(ApiError::internal("Login throttle failure"))?;
+    send_password_changed_notice(mailer.get_ref(), &user).await;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn change_password(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    jwt_keys: web::Data<JwtKeys>,
+    mailer: web::Data<dyn Mailer>,
+    http_req: HttpRequest,
+    req: ValidatedJson<ChangePasswordRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let claims = http_req.extensions().get::<Claims>().cloned()
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+
+    let user = find_active_user(db.get_ref(), claims.user_id).await?;
+    if !verify(&req.current_password, &user.password_hash).unwrap_or(false) {
+        return Err(ApiError::validation("current_password", "invalid_password", "Current password is incorrect"));
+    }
+    config.password_policy.check("new_password", &req.new_password)?;
+
+    let user = store_new_password(db.get_ref(), user, &req.new_password).await?;
+    send_password_changed_notice(mailer.get_ref(), &user).await;
+
+    // Les autres sessions sont révoquées ; la session courante reçoit de nouveaux jetons
+    Ok(HttpResponse::Ok().json(auth_response(user, claims.session(), &config, &jwt_keys)?))
+}
+
+pub async fn list_api_tokens(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = get_current_user_id(&req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+
+    let tokens: Vec<ApiTokenResponse> = ApiTokenEntity::find()
+        .filter(ApiTokenColumn::UserId.eq(user_id))
+        .order_by_desc(ApiTokenColumn::CreatedAt)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(api_tokens::to_response)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+/// Jeton d'API personnel : il agit avec les droits de l'utilisateur, limités
+/// aux permissions demandées et, si `entity_id` est fourni, à cette entité et
+/// à ses filiales. Le jeton n'est renvoyé qu'une fois.
+pub async fn create_api_token(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    http_req: HttpRequest,
+    req: ValidatedJson<CreateApiTokenRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = get_current_user_id(&http_req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+    api_tokens::check_expiry(&config, req.expires_at)?;
+    if let Some(entity_id) = req.entity_id
+        && get_entity_role(&http_req, entity_id).is_none()
+    {
+        return Err(ApiError::Forbidden("Access denied".to_string()));
+    }
+
+    // Le jeton hérite du second facteur de la session qui l'a créé
+    let (token, secret) = api_tokens::issue(
+        db.get_ref(),
+        user_id,
+        req.entity_id,
+        &req.name,
+        &req.scopes,
+        mfa::session_has_mfa(&http_req),
+        req.expires_at,
+    ).await?;
+
+    Ok(HttpResponse::Created().json(CreatedApiTokenResponse {
+        api_token: api_tokens::to_response(token),
+        token: secret,
+    }))
+}
+
+pub async fn delete_api_token(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = get_current_user_id(&req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+
+    let result = ApiTokenEntity::delete_many()
+        .filter(ApiTokenColumn::Id.eq(path.into_inner()))
+        .filter(ApiTokenColumn::UserId.eq(user_id))
+        .exec(db.get_ref())
+        .await?;
+    if result.rows_affected == 0 {
+        return Err(ApiError::NotFound("API token not found".to_string()));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Clés publiques de vérification des jetons de session (RFC 7517). Les clés
+/// retirées de `JWT_KEYS_DIR` disparaissent du document au redémarrage.
+pub async fn get_jwks(jwt_keys: web::Data<JwtKeys>) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, "public, max-age=300"))
+        .json(jwt_keys.jwks())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use actix_web::{test, App};
+    use actix_web::body::MessageBody;
+    use actix_web::dev::ServiceResponse;
+    use actix_web::http::StatusCode;
+    use serde_json::{json, Value};
+    use crate::auth::jwt::TOKEN_TYPE_ACCESS;
+    use crate::auth::throttle::InMemoryLoginThrottle;
+    use crate::middleware::AuthMiddleware;
+    use crate::test_support::{self, Mailbox, MockIssuer, TEST_PASSWORD};
+
+    const NEW_PASSWORD: &str = "Changed-Password-2";
+    const OIDC_CLIENT_ID: &str = "hypercyber-test";
+
+    // Routes d'authentification de `main.rs`, avec le mailer `file` de la boîte de test
+    macro_rules! test_app {
+        ($db:expr, $config:expr, $mailbox:expr) => {{
+            let mailer: Arc<dyn Mailer> = Arc::new($mailbox.mailer(&$config));
+            let throttle: Arc<dyn LoginThrottle> = Arc::new(InMemoryLoginThrottle::new($config.login_throttle.clone()));
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new($db.clone()))
+                    .app_data(web::Data::new($config.clone()))
+                    .app_data(web::Data::new(JwtKeys::from_config(&$config).unwrap()))
+                    .app_data(web::Data::from(mailer))
+                    .app_data(web::Data::from(throttle))
+                    .app_data(web::Data::new(OutboundClient::new(true).unwrap()))
+                    .app_data(web::Data::new(oidc::JwksCache::new(OutboundClient::new(true).unwrap())))
+                    .service(
+                        web::scope("/api/auth")
+                            .route("/login", web::post().to(login))
+                            .route("/login/mfa", web::post().to(login_mfa))
+                            .route("/oidc/authorize", web::get().to(oidc_authorize))
+                            .route("/oidc/callback", web::get().to(oidc_callback))
+                            .route("/oidc/exchange", web::post().to(oidc_exchange))
+                            .route("/refresh", web::post().to(refresh_token))
+                            .route("/verify-email", web::post().to(verify_email))
+                            .route("/verify-email/resend", web::post().to(resend_verification_email))
+                            .route("/password/forgot", web::post().to(forgot_password))
+                            .route("/password/reset", web::post().to(reset_password))
+                            .service(
+                                web::resource("/me")
+                                    .wrap(AuthMiddleware)
+                                    .route(web::get().to(get_current_user))
+                            )
+                            .service(
+                                web::resource("/password/change")
+                                    .wrap(AuthMiddleware)
+                                    .route(web::post().to(change_password))
+                            )
+                            .service(
+                                web::resource("/mfa/step-up")
+                                    .wrap(AuthMiddleware)
+                                    .route(web::post().to(mfa_step_up))
+                            )
+                    )
+            ).await
+        }};
+    }
+
+    macro_rules! send {
+        ($app:expr, $request:expr) => {
+            match test::try_call_service(&$app, $request.to_request()).await {
+                Ok(response) => read(response).await,
+                // Les refus du middleware d'authentification remontent en erreur de service
+                Err(error) => (error.as_response_error().status_code(), Value::Null),
+            }
+        };
+    }
+
+    // Cible d'une redirection 302
+    macro_rules! redirect {
+        ($app:expr, $request:expr) => {{
+            let response = test::call_service(&$app, $request.to_request()).await;
+            assert_eq!(response.status(), StatusCode::FOUND);
+            response.headers().get(header::LOCATION).unwrap().to_str().unwrap().to_string()
+        }};
+    }
+
+    async fn read<B: MessageBody>(response: ServiceResponse<B>) -> (StatusCode, Value) {
+        let status = response.status();
+        let body = test::read_body(response).await;
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    fn post(path: &str, body: Value) -> test::TestRequest {
+        test::TestRequest::post().uri(path).set_json(body)
+    }
+
+    fn me(token: &str) -> test::TestRequest {
+        test::TestRequest::get()
+            .uri("/api/auth/me")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+    }
+
+    fn error_code(body: &Value) -> &str {
+        body["errors"][0]["code"].as_str().unwrap_or_default()
+    }
+
+    // `iat` est à la seconde : une session n'est révoquée que si elle a été
+    // ouverte avant la seconde du changement de mot de passe
+    async fn next_second() {
+        actix_web::rt::time::sleep(Duration::from_millis(1100)).await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn verification_link_is_single_use() {
+        let db = test_support::database().await;
+        let config = test_support::config();
+        let mailbox = Mailbox::default();
+        let app = test_app!(db, config, mailbox);
+        let user = test_support::create_user(&db, false).await;
+        let credentials = json!({ "email": user.email, "password": TEST_PASSWORD });
+
+        let (status, _) = send!(app, post("/api/auth/login", credentials.clone()));
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _) = send!(app, post("/api/auth/verify-email/resend", json!({ "email": user.email })));
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let token = mailbox.token(&user.email, "verify-email");
+
+        let (status, _) = send!(app, post("/api/auth/verify-email", json!({ "token": token })));
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, body) = send!(app, post("/api/auth/verify-email", json!({ "token": token })));
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(error_code(&body), "invalid_token");
+
+        let (status, _) = send!(app, post("/api/auth/login", credentials));
+        assert_eq!(status, StatusCode::OK);
+
+        test_support::delete_user(&db, user.id).await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn password_reset_is_single_use_and_revokes_sessions() {
+        let db = test_support::database().await;
+        let config = test_support::config();
+        let mailbox = Mailbox::default();
+        let app = test_app!(db, config, mailbox);
+        let user = test_support::create_user(&db, true).await;
+
+        let (status, session) = send!(app, post("/api/auth/login", json!({ "email": user.email, "password": TEST_PASSWORD })));
+        assert_eq!(status, StatusCode::OK);
+        let access_token = session["token"].as_str().unwrap();
+        let refresh_token = session["refresh_token"].as_str().unwrap();
+        assert_eq!(send!(app, me(access_token)).0, StatusCode::OK);
+        next_second().await;
+
+        let (status, _) = send!(app, post("/api/auth/password/forgot", json!({ "email": user.email })));
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let token = mailbox.token(&user.email, "reset-password");
+
+        let reset = json!({ "token": token, "new_password": NEW_PASSWORD });
+        let (status, _) = send!(app, post("/api/auth/password/reset", reset));
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let replay = json!({ "token": token, "new_password": "Replayed-Password-3" });
+        let (status, body) = send!(app, post("/api/auth/password/reset", replay));
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(error_code(&body), "invalid_token");
+
+        // Les jetons émis avant la réinitialisation sont refusés
+        assert_eq!(send!(app, me(access_token)).0, StatusCode::UNAUTHORIZED);
+        let (status, _) = send!(app, post("/api/auth/refresh", json!({ "refresh_token": refresh_token })));
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = send!(app, post("/api/auth/login", json!({ "email": user.email, "password": TEST_PASSWORD })));
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, session) = send!(app, post("/api/auth/login", json!({ "email": user.email, "password": NEW_PASSWORD })));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(send!(app, me(session["token"].as_str().unwrap())).0, StatusCode::OK);
+
+        test_support::delete_user(&db, user.id).await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn expired_reset_link_is_rejected() {
+        let db = test_support::database().await;
+        let config = test_support::config();
+        let mailbox = Mailbox::default();
+        let app = test_app!(db, config, mailbox);
+        let user = test_support::create_user(&db, true).await;
+
+        let token = tokens::issue(&db, &config.jwt_secret, user.id, tokens::PURPOSE_PASSWORD_RESET, -1).await.unwrap();
+        let reset = json!({ "token": token, "new_password": NEW_PASSWORD });
+        let (status, body) = send!(app, post("/api/auth/password/reset", reset));
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(error_code(&body), "invalid_token");
+
+        let (status, _) = send!(app, post("/api/auth/login", json!({ "email": user.email, "password": TEST_PASSWORD })));
+        assert_eq!(status, StatusCode::OK);
+
+        test_support::delete_user(&db, user.id).await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn password_change_revokes_other_sessions() {
+        let db = test_support::database().await;
+        let config = test_support::config();
+        let mailbox = Mailbox::default();
+        let app = test_app!(db, config, mailbox);
+        let user = test_support::create_user(&db, true).await;
+        let credentials = json!({ "email": user.email, "password": TEST_PASSWORD });
+
+        let (_, current) = send!(app, post("/api/auth/login", credentials.clone()));
+        let (_, other) = send!(app, post("/api/auth/login", credentials));
+        let current = current["token"].as_str().unwrap();
+        let other = other["token"].as_str().unwrap();
+        next_second().await;
+
+        let change = post("/api/auth/password/change", json!({ "current_password": TEST_PASSWORD, "new_password": NEW_PASSWORD }))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", current)));
+        let (status, renewed) = send!(app, change);
+        assert_eq!(status, StatusCode::OK);
+
+        // Seule la session courante continue, avec les jetons renvoyés
+        assert_eq!(send!(app, me(other)).0, StatusCode::UNAUTHORIZED);
+        assert_eq!(send!(app, me(current)).0, StatusCode::UNAUTHORIZED);
+        assert_eq!(send!(app, me(renewed["token"].as_str().unwrap())).0, StatusCode::OK);
+        assert_eq!(mailbox.messages(&user.email).len(), 1);
+
+        test_support::delete_user(&db, user.id).await;
+    }
+
+    fn query_param(url: &str, name: &str) -> String {
+        reqwest::Url::parse(url).unwrap()
+            .query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+            .unwrap()
+    }
+
+    // Fournisseur OIDC global pointant sur le fournisseur local
+    fn oidc_config(mock: &MockIssuer) -> Config {
+        let mut config = test_support::config();
+        config.oidc_issuer = Some(mock.issuer.clone());
+        config.oidc_client_id = Some(OIDC_CLIENT_ID.to_string());
+        config.oidc_client_secret = None;
+        config.oidc_redirect_uri = None;
+        config
+    }
+
+    // Connexion OIDC complète jusqu'à l'échange du code par le frontend
+    // Redirection vers le fournisseur et cookie qui lie le state au navigateur
+    macro_rules! oidc_authorize {
+        ($app:expr) => {{
+            let response = test::call_service(&$app, test::TestRequest::get().uri("/api/auth/oidc/authorize").to_request()).await;
+            assert_eq!(response.status(), StatusCode::FOUND);
+            let cookie = response.response().cookies()
+                .find(|c| c.name() == oidc::LOGIN_STATE_COOKIE)
+                .expect("state cookie")
+                .into_owned();
+            assert_eq!(cookie.http_only(), Some(true));
+            assert_eq!(cookie.secure(), Some(true));
+            assert_eq!(cookie.same_site(), Some(actix_web::cookie::SameSite::Lax));
+            let location = response.headers().get(header::LOCATION).unwrap().to_str().unwrap().to_string();
+            (location, cookie)
+        }};
+    }
+
+    // Connexion OIDC complète jusqu'à l'échange du code par le frontend
+    macro_rules! oidc_login {
+        ($app:expr, $mock:expr, $config:expr, $email:expr) => {{
+            let (authorization_url, cookie) = oidc_authorize!($app);
+            let callback = $mock.authorize(&authorization_url, $email).await;
+            let callback = callback.strip_prefix($config.api_base_url.as_str()).unwrap().to_string();
+            let frontend_url = redirect!($app, test::TestRequest::get().uri(&callback).cookie(cookie));
+            let code = query_param(&frontend_url, "code");
+            send!($app, post("/api/auth/oidc/exchange", json!({ "code": code })))
+        }};
+    }
+
+    fn access_claims(config: &Config, body: &Value) -> Claims {
+        JwtKeys::from_config(config).unwrap()
+            .verify(body["token"].as_str().unwrap(), TOKEN_TYPE_ACCESS)
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn oidc_login_state_and_code_are_single_use() {
+        let db = test_support::database().await;
+        let mock = MockIssuer::start(OIDC_CLIENT_ID).await;
+        let config = oidc_config(&mock);
+        let mailbox = Mailbox::default();
+        let app = test_app!(db, config, mailbox);
+        let user = test_support::create_user(&db, true).await;
+
+        let (authorization_url, cookie) = oidc_authorize!(app);
+        let callback = mock.authorize(&authorization_url, &user.email).await;
+        let callback = callback.strip_prefix(config.api_base_url.as_str()).unwrap().to_string();
+
+        // CSRF de connexion : sans le cookie du navigateur qui a lancé la
+        // connexion, ou avec celui d'une autre tentative, le state est refusé
+        // sans être consommé
+        let (status, _) = send!(app, test::TestRequest::get().uri(&callback));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (_, other_cookie) = oidc_authorize!(app);
+        let (status, _) = send!(app, test::TestRequest::get().uri(&callback).cookie(other_cookie));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // Le fournisseur a exigé le vérificateur PKCE lié au state ; le cookie est effacé
+        let response = test::call_service(&app, test::TestRequest::get().uri(&callback).cookie(cookie.clone()).to_request()).await;
+        assert_eq!(response.status(), StatusCode::FOUND);
+        let cleared = response.response().cookies().find(|c| c.name() == oidc::LOGIN_STATE_COOKIE).unwrap();
+        assert_eq!(cleared.value(), "");
+        assert_eq!(cleared.max_age(), Some(actix_web::cookie::time::Duration::ZERO));
+        let frontend_url = response.headers().get(header::LOCATION).unwrap().to_str().unwrap().to_string();
+        let code = query_param(&frontend_url, "code");
+        let (status, session) = send!(app, post("/api/auth/oidc/exchange", json!({ "code": code })));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(session["user"]["id"], json!(user.id));
+        assert_eq!(send!(app, me(session["token"].as_str().unwrap())).0, StatusCode::OK);
+
+        let (status, _) = send!(app, test::TestRequest::get().uri(&callback).cookie(cookie));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = send!(app, post("/api/auth/oidc/exchange", json!({ "code": code })));
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // Un state qui n'a pas été émis par le serveur est refusé
+        let (authorization_url, cookie) = oidc_authorize!(app);
+        let callback = mock.authorize(&authorization_url, &user.email).await;
+        let forged = format!(
+            "/api/auth/oidc/callback?code={}&state=forged-state",
+            query_param(&callback, "code")
+        );
+        let (status, _) = send!(app, test::TestRequest::get().uri(&forged).cookie(cookie));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        test_support::delete_user(&db, user.id).await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn federated_login_requires_enrolled_second_factor() {
+        let db = test_support::database().await;
+        let mock = MockIssuer::start(OIDC_CLIENT_ID).await;
+        let config = oidc_config(&mock);
+        let mailbox = Mailbox::default();
+        let app = test_app!(db, config, mailbox);
+        let user = test_support::create_user(&db, true).await;
+        let secret = test_support::enroll_totp(&db, &config, user.id).await;
+
+        // Fournisseur non reconnu : le second facteur qu'il déclare ne compte pas
+        mock.authenticate_with(json!({ "amr": ["pwd", "mfa"], "acr": "urn:test:mfa" }));
+        let (status, challenge) = oidc_login!(app, mock, config, &user.email);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(challenge["mfa_required"], json!(true));
+        assert!(challenge.get("token").is_none());
+
+        let (status, session) = send!(app, post("/api/auth/login/mfa", json!({
+            "mfa_token": challenge["mfa_token"],
+            "method": "totp",
+            "code": test_support::totp_code(&secret, 0),
+        })));
+        assert_eq!(status, StatusCode::OK);
+        let claims = access_claims(&config, &session);
+        assert_eq!(claims.amr, vec!["fed", "otp", "mfa"]);
+        assert_eq!(claims.acr.as_deref(), Some("urn:test:mfa"));
+
+        // Fournisseur reconnu : l'acr attendu atteste le second facteur
+        let mut trusted = config.clone();
+        trusted.oidc_trust_mfa = true;
+        trusted.oidc_mfa_acr_values = vec!["urn:test:mfa".to_string()];
+        let trusted_app = test_app!(db, trusted, mailbox);
+        mock.authenticate_with(json!({ "amr": ["pwd", "otp"], "acr": "urn:test:mfa" }));
+        let (status, session) = oidc_login!(trusted_app, mock, trusted, &user.email);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(access_claims(&trusted, &session).amr, vec!["fed", "pwd", "otp", "mfa"]);
+
+        // Sans amr "mfa" ni acr attendu, même un fournisseur reconnu passe par le challenge
+        mock.authenticate_with(json!({ "amr": ["pwd"], "acr": "urn:test:password" }));
+        let (status, challenge) = oidc_login!(trusted_app, mock, trusted, &user.email);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(challenge["mfa_required"], json!(true));
+
+        test_support::delete_user(&db, user.id).await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn step_up_adds_second_factor_to_session() {
+        let db = test_support::database().await;
+        let config = test_support::config();
+        let mailbox = Mailbox::default();
+        let app = test_app!(db, config, mailbox);
+        let user = test_support::create_user(&db, true).await;
+        let step_up = |token: &str| test::TestRequest::post()
+            .uri("/api/auth/mfa/step-up")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)));
+
+        // Session fédérée ouverte sans second facteur
+        let session = SessionContext {
+            acr: Some("urn:test:password".to_string()),
+            ..SessionContext::with_methods(&[AMR_FEDERATED])
+        };
+        let token = create_token(user.id, user.email.clone(), session, &config, &JwtKeys::from_config(&config).unwrap()).unwrap();
+
+        let (status, _) = send!(app, step_up(&token));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let secret = test_support::enroll_totp(&db, &config, user.id).await;
+        let (status, challenge) = send!(app, step_up(&token));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(challenge["mfa_required"], json!(true));
+
+        let (status, elevated) = send!(app, post("/api/auth/login/mfa", json!({
+            "mfa_token": challenge["mfa_token"],
+            "method": "totp",
+            "code": test_support::totp_code(&secret, 0),
+        })));
+        assert_eq!(status, StatusCode::OK);
+        let claims = access_claims(&config, &elevated);
+        assert_eq!(claims.amr, vec!["fed", "otp", "mfa"]);
+        assert_eq!(claims.acr.as_deref(), Some("urn:test:password"));
+
+        let (status, _) = send!(app, step_up(elevated["token"].as_str().unwrap()));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        test_support::delete_user(&db, user.id).await;
+    }
+}
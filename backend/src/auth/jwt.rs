@@ -1,48 +1,353 @@
-use jsonwebtoken::{encode, EncodingKey, Header, DecodingKey, Validation, Algorithm, decode};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use anyhow::{anyhow, bail, Context};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use jsonwebtoken::{encode, EncodingKey, Header, DecodingKey, Validation, Algorithm, decode, decode_header};
+use openssl::pkey::{Id, PKey, Public};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use crate::config::Config;
 use chrono::Utc;
+use sea_orm::{DatabaseConnection, EntityTrait};
+use crate::errors::ApiError;
+use crate::entities_orm::user::Entity as UserEntity;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Méthodes d'authentification (RFC 8176) portées par le claim `amr`.
+pub const AMR_PASSWORD: &str = "pwd";
+pub const AMR_OTP: &str = "otp";
+pub const AMR_HARDWARE_KEY: &str = "hwk";
+pub const AMR_FEDERATED: &str = "fed";
+pub const AMR_MFA: &str = "mfa";
+// Hors RFC 8176 : requête authentifiée par un jeton d'API
+pub const AMR_API: &str = "api";
+
+/// Type de jeton (claim `typ`) : seuls les jetons d'accès ouvrent l'API, seuls
+/// les jetons de rafraîchissement sont acceptés par `/auth/refresh`.
+pub const TOKEN_TYPE_ACCESS: &str = "access";
+pub const TOKEN_TYPE_REFRESH: &str = "refresh";
+
+// Taille minimale des clés RSA acceptées
+const MIN_RSA_BITS: u32 = 2048;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub user_id: uuid::Uuid,
     pub email: String,
     pub exp: i64,
+    #[serde(default)]
+    pub iat: i64,
+    #[serde(default)]
+    pub iss: String,
+    #[serde(default)]
+    pub aud: String,
+    #[serde(default)]
+    pub jti: String, // Identifiant unique du jeton
+    #[serde(default)]
+    pub amr: Vec<String>,
+    // Niveau d'authentification déclaré par le fournisseur d'identité fédéré
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acr: Option<String>,
+    // Jetons émis avant l'introduction du claim : ni accès ni rafraîchissement
+    #[serde(default)]
+    pub typ: String,
+    // Entité sélectionnée (`/auth/switch-entity`) ; les rôles ne sont pas
+    // embarqués, ils sont relus à chaque requête par le middleware
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_entity_id: Option<uuid::Uuid>,
 }
 
 impl Claims {
-    pub fn new(user_id: uuid::Uuid, email: String, expiration_seconds: i64) -> Self {
-        Self {
+    /// Vrai si la session a été ouverte avec un second facteur.
+    pub fn is_mfa(&self) -> bool {
+        self.amr.iter().any(|m| m == AMR_MFA)
+    }
+
+    pub fn session(&self) -> SessionContext {
+        SessionContext {
+            amr: self.amr.clone(),
+            acr: self.acr.clone(),
+            active_entity_id: self.active_entity_id,
+        }
+    }
+}
+
+/// Authentification et entité active d'une session, reportées dans ses jetons.
+/// Conservé côté serveur tant que le second facteur n'est pas validé.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionContext {
+    pub amr: Vec<String>,
+    #[serde(default)]
+    pub acr: Option<String>,
+    #[serde(default)]
+    pub active_entity_id: Option<uuid::Uuid>,
+}
+
+impl SessionContext {
+    pub fn with_methods(methods: &[&str]) -> Self {
+        Self { amr: methods.iter().map(|m| m.to_string()).collect(), ..Self::default() }
+    }
+
+    /// Méthodes de la session complétées par le second facteur `method`.
+    pub fn amr_with_factor(&self, method: &str) -> Vec<String> {
+        let mut amr = self.amr.clone();
+        for m in [method, AMR_MFA] {
+            if !amr.iter().any(|a| a == m) {
+                amr.push(m.to_string());
+            }
+        }
+        amr
+    }
+}
+
+// Clé de vérification, publiée dans le JWKS sous son `kid`
+struct VerificationKey {
+    algorithm: Algorithm,
+    key: DecodingKey,
+    jwk: Value,
+}
+
+/// Clés de signature des jetons de session. Avec `JWT_SIGNING_KEY_ID`, les
+/// jetons sont signés en RS256 ou EdDSA par la clé `<kid>.pem` de
+/// `JWT_KEYS_DIR` ; toutes les clés du répertoire restent acceptées en
+/// vérification, ce qui permet la rotation. Sans clé, repli sur HS256 avec
+/// `JWT_SECRET`.
+pub struct JwtKeys {
+    signing_kid: Option<String>,
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    verification_keys: HashMap<String, VerificationKey>,
+    hmac_key: Option<DecodingKey>,
+    issuer: String,
+    audience: String,
+}
+
+impl JwtKeys {
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        config.check_jwt_secret().map_err(|e| anyhow!(e))?;
+
+        let issuer = config.jwt_issuer.clone();
+        let audience = config.jwt_audience.clone();
+
+        let Some(signing_kid) = config.jwt_signing_key_id.clone() else {
+            if !config.is_development() {
+                log::warn!("JWT_SIGNING_KEY_ID is not set: session tokens are signed with HS256 and no key is published");
+            }
+            return Ok(Self {
+                signing_kid: None,
+                algorithm: Algorithm::HS256,
+                encoding_key: EncodingKey::from_secret(config.jwt_secret.as_ref()),
+                verification_keys: HashMap::new(),
+                hmac_key: Some(DecodingKey::from_secret(config.jwt_secret.as_ref())),
+                issuer,
+                audience,
+            });
+        };
+
+        let dir = config.jwt_keys_dir.as_deref()
+            .ok_or_else(|| anyhow!("JWT_KEYS_DIR is required when JWT_SIGNING_KEY_ID is set"))?;
+
+        let mut verification_keys = HashMap::new();
+        let mut signing = None;
+        for entry in fs::read_dir(dir).with_context(|| format!("Cannot read JWT_KEYS_DIR {}", dir))? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("pem") {
+                continue;
+            }
+            let kid = path.file_stem()
+                .and_then(|s| s.to_str())
+                .ok_or_else(|| anyhow!("Invalid key file name {}", path.display()))?
+                .to_string();
+            let (verification, encoding) = load_key(&path, &kid)?;
+            if kid == signing_kid {
+                let encoding = encoding
+                    .ok_or_else(|| anyhow!("Signing key {} must be a private key", kid))?;
+                signing = Some((verification.algorithm, encoding));
+            }
+            verification_keys.insert(kid, verification);
+        }
+
+        let (algorithm, encoding_key) = signing
+            .ok_or_else(|| anyhow!("Signing key {}.pem not found in {}", signing_kid, dir))?;
+
+        Ok(Self {
+            signing_kid: Some(signing_kid),
+            algorithm,
+            encoding_key,
+            verification_keys,
+            hmac_key: None,
+            issuer,
+            audience,
+        })
+    }
+
+    /// Document JWKS des clés publiques de vérification (vide en HS256).
+    pub fn jwks(&self) -> Value {
+        let mut kids: Vec<&String> = self.verification_keys.keys().collect();
+        kids.sort();
+        let keys: Vec<&Value> = kids.into_iter()
+            .map(|kid| &self.verification_keys[kid].jwk)
+            .collect();
+        json!({ "keys": keys })
+    }
+
+    pub fn issue(&self, user_id: uuid::Uuid, email: String, session: SessionContext, typ: &str, expiration_seconds: i64) -> Result<String, jsonwebtoken::errors::Error> {
+        let now = Utc::now().timestamp();
+        let claims = Claims {
             user_id,
             email,
-            exp: Utc::now().timestamp() + expiration_seconds,
+            exp: now + expiration_seconds,
+            iat: now,
+            iss: self.issuer.clone(),
+            aud: self.audience.clone(),
+            jti: uuid::Uuid::new_v4().to_string(),
+            amr: session.amr,
+            acr: session.acr,
+            typ: typ.to_string(),
+            active_entity_id: session.active_entity_id,
+        };
+        let mut header = Header::new(self.algorithm);
+        header.kid = self.signing_kid.clone();
+        encode(&header, &claims, &self.encoding_key)
+    }
+
+    /// Vérifie signature, expiration, émetteur, audience et type de jeton. Le
+    /// `kid` de l'en-tête désigne la clé ; son algorithme fait foi, pas celui annoncé.
+    pub fn verify(&self, token: &str, typ: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+        use jsonwebtoken::errors::ErrorKind;
+
+        let header = decode_header(token)?;
+        let (algorithm, key) = match (&header.kid, &self.hmac_key) {
+            (Some(kid), _) => {
+                let key = self.verification_keys.get(kid).ok_or(ErrorKind::InvalidKeyFormat)?;
+                (key.algorithm, &key.key)
+            }
+            (None, Some(hmac_key)) => (Algorithm::HS256, hmac_key),
+            (None, None) => return Err(ErrorKind::InvalidToken.into()),
+        };
+
+        let mut validation = Validation::new(algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["exp", "iat", "iss", "aud"]);
+        let claims = decode::<Claims>(token, key, &validation)?.claims;
+        if claims.typ != typ {
+            return Err(ErrorKind::InvalidToken.into());
         }
+        Ok(claims)
     }
 }
 
-pub fn create_token(user_id: uuid::Uuid, email: String, config: &Config) -> Result<String, jsonwebtoken::errors::Error> {
-    let claims = Claims::new(user_id, email, config.jwt_expiration);
-    encode(
-        &Header::new(Algorithm::HS256),
-        &claims,
-        &EncodingKey::from_secret(config.jwt_secret.as_ref()),
-    )
+// Clé PEM privée (PKCS#1 ou PKCS#8) ou publique : algorithme déduit du type de clé
+fn load_key(path: &Path, kid: &str) -> anyhow::Result<(VerificationKey, Option<EncodingKey>)> {
+    let pem = fs::read(path).with_context(|| format!("Cannot read {}", path.display()))?;
+
+    let (public, private_pkcs8) = match PKey::private_key_from_pem(&pem) {
+        Ok(private) => {
+            let public: PKey<Public> = PKey::public_key_from_der(&private.public_key_to_der()?)?;
+            (public, Some(private.private_key_to_pem_pkcs8()?))
+        }
+        Err(_) => {
+            let public = PKey::public_key_from_pem(&pem)
+                .with_context(|| format!("{} is neither a private nor a public PEM key", path.display()))?;
+            (public, None)
+        }
+    };
+
+    match public.id() {
+        Id::RSA => {
+            let rsa = public.rsa()?;
+            if rsa.size() * 8 < MIN_RSA_BITS {
+                bail!("RSA key {} must be at least {} bits", kid, MIN_RSA_BITS);
+            }
+            let n = URL_SAFE_NO_PAD.encode(rsa.n().to_vec());
+            let e = URL_SAFE_NO_PAD.encode(rsa.e().to_vec());
+            let verification = VerificationKey {
+                algorithm: Algorithm::RS256,
+                key: DecodingKey::from_rsa_components(&n, &e)?,
+                jwk: json!({ "kty": "RSA", "use": "sig", "alg": "RS256", "kid": kid, "n": n, "e": e }),
+            };
+            let encoding = private_pkcs8.map(|pem| EncodingKey::from_rsa_pem(&pem)).transpose()?;
+            Ok((verification, encoding))
+        }
+        Id::ED25519 => {
+            let x = URL_SAFE_NO_PAD.encode(public.raw_public_key()?);
+            let verification = VerificationKey {
+                algorithm: Algorithm::EdDSA,
+                key: DecodingKey::from_ed_components(&x)?,
+                jwk: json!({ "kty": "OKP", "crv": "Ed25519", "use": "sig", "alg": "EdDSA", "kid": kid, "x": x }),
+            };
+            let encoding = private_pkcs8.map(|pem| EncodingKey::from_ed_pem(&pem)).transpose()?;
+            Ok((verification, encoding))
+        }
+        other => bail!("Unsupported key type {:?} for {} (RSA or Ed25519 expected)", other, kid),
+    }
 }
 
-pub fn create_refresh_token(user_id: uuid::Uuid, email: String) -> Result<String, jsonwebtoken::errors::Error> {
-    let config = crate::config::Config::from_env();
-    let claims = Claims::new(user_id, email, 7 * 24 * 60 * 60); // 7 days
-    encode(
-        &Header::new(Algorithm::HS256),
-        &claims,
-        &EncodingKey::from_secret(config.jwt_secret.as_ref()),
-    )
+pub fn create_token(user_id: uuid::Uuid, email: String, session: SessionContext, config: &Config, keys: &JwtKeys) -> Result<String, jsonwebtoken::errors::Error> {
+    keys.issue(user_id, email, session, TOKEN_TYPE_ACCESS, config.jwt_expiration)
 }
 
-pub fn verify_token(token: &str, config: &Config) -> Result<Claims, jsonwebtoken::errors::Error> {
-    let decoding_key = DecodingKey::from_secret(config.jwt_secret.as_ref());
-    let validation = Validation::new(Algorithm::HS256);
-    let token_data = decode::<Claims>(token, &decoding_key, &validation)?;
-    Ok(token_data.claims)
+pub fn create_refresh_token(user_id: uuid::Uuid, email: String, session: SessionContext, keys: &JwtKeys) -> Result<String, jsonwebtoken::errors::Error> {
+    keys.issue(user_id, email, session, TOKEN_TYPE_REFRESH, 7 * 24 * 60 * 60) // 7 days
 }
 
+pub fn verify_refresh_token(token: &str, keys: &JwtKeys) -> Result<Claims, jsonwebtoken::errors::Error> {
+    keys.verify(token, TOKEN_TYPE_REFRESH)
+}
+
+/// Vérifie que la session portée par le jeton est toujours valide : compte
+/// actif et jeton émis après la dernière révocation (changement de mot de passe…).
+/// Les jetons d'API échappent à cette révocation : ils sont supprimés
+/// explicitement (voir `api_tokens::revoke`).
+pub async fn validate_session(db: &DatabaseConnection, claims: &Claims) -> Result<(), ApiError> {
+    let user = UserEntity::find_by_id(claims.user_id)
+        .one(db)
+        .await?
+        .filter(|u| u.is_active)
+        .ok_or_else(|| ApiError::Unauthorized("Invalid or missing token".to_string()))?;
+
+    let api_token = claims.amr.iter().any(|m| m == AMR_API);
+    if !api_token && user.sessions_valid_after.is_some_and(|t| claims.iat < t.timestamp()) {
+        return Err(ApiError::Unauthorized("Session revoked".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hs256_keys() -> JwtKeys {
+        JwtKeys {
+            signing_kid: None,
+            algorithm: Algorithm::HS256,
+            encoding_key: EncodingKey::from_secret(b"test-secret"),
+            verification_keys: HashMap::new(),
+            hmac_key: Some(DecodingKey::from_secret(b"test-secret")),
+            issuer: "https://api.test".to_string(),
+            audience: "hypercyber".to_string(),
+        }
+    }
+
+    #[test]
+    fn token_types_are_not_interchangeable() {
+        let keys = hs256_keys();
+        let user_id = uuid::Uuid::new_v4();
+        let access = keys.issue(user_id, "a@test".to_string(), SessionContext::default(), TOKEN_TYPE_ACCESS, 60).unwrap();
+        let refresh = create_refresh_token(user_id, "a@test".to_string(), SessionContext::default(), &keys).unwrap();
+
+        assert_eq!(keys.verify(&access, TOKEN_TYPE_ACCESS).unwrap().user_id, user_id);
+        assert!(keys.verify(&refresh, TOKEN_TYPE_ACCESS).is_err());
+        assert_eq!(verify_refresh_token(&refresh, &keys).unwrap().typ, TOKEN_TYPE_REFRESH);
+        assert!(verify_refresh_token(&access, &keys).is_err());
+    }
+
+    #[test]
+    fn tokens_without_type_are_rejected() {
+        let keys = hs256_keys();
+        let legacy = keys.issue(uuid::Uuid::new_v4(), "a@test".to_string(), SessionContext::default(), "", 60).unwrap();
+        assert!(keys.verify(&legacy, TOKEN_TYPE_ACCESS).is_err());
+        assert!(keys.verify(&legacy, TOKEN_TYPE_REFRESH).is_err());
+    }
+}
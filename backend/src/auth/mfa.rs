@@ -0,0 +1,412 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use actix_web::{HttpMessage, HttpRequest};
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use rand::{Rng, RngCore, rngs::OsRng};
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait, ColumnTrait, Condition, QueryFilter, PaginatorTrait, Set, IntoActiveModel, ActiveModelTrait};
+use sea_orm::sea_query::Expr;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+use webauthn_rs::prelude::{Passkey, Url};
+use webauthn_rs::{Webauthn, WebauthnBuilder};
+use crate::auth::jwt::{Claims, SessionContext, AMR_PASSWORD};
+use crate::config::Config;
+use crate::errors::ApiError;
+use crate::entities_orm::entity::{Entity as EntityEntity, Column as EntityColumn};
+use crate::entities_orm::user_totp::{Entity as UserTotpEntity, Column as UserTotpColumn, ActiveModel as UserTotpActiveModel};
+use crate::entities_orm::recovery_code::{Entity as RecoveryCodeEntity, Column as RecoveryCodeColumn, ActiveModel as RecoveryCodeActiveModel};
+use crate::entities_orm::webauthn_credential::{self, Entity as WebauthnCredentialEntity, Column as WebauthnCredentialColumn};
+use crate::entities_orm::mfa_challenge::{self, Entity as MfaChallengeEntity, Column as MfaChallengeColumn, ActiveModel as MfaChallengeActiveModel};
+
+pub const METHOD_TOTP: &str = "totp";
+pub const METHOD_RECOVERY_CODE: &str = "recovery_code";
+pub const METHOD_WEBAUTHN: &str = "webauthn";
+
+pub const CHALLENGE_LOGIN: &str = "login";
+pub const CHALLENGE_WEBAUTHN_REGISTRATION: &str = "webauthn_registration";
+
+/// Durée de validité du jeton intermédiaire émis après le mot de passe.
+pub const CHALLENGE_TTL_SECONDS: i64 = 300;
+const CHALLENGE_MAX_ATTEMPTS: i32 = 5;
+
+// Clés de l'état d'un challenge de connexion
+const STATE_SESSION: &str = "session";
+const STATE_PASSKEY: &str = "passkey";
+
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+// Paramètres RFC 6238 compatibles avec toutes les applications d'authentification
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP: u64 = 30;
+
+// Secrets TOTP chiffrés : `v1:` suivi du nonce, du texte chiffré et du tag en base64
+const SECRET_PREFIX: &str = "v1:";
+const SECRET_NONCE_LENGTH: usize = 12;
+const SECRET_TAG_LENGTH: usize = 16;
+
+pub fn build_webauthn(config: &Config) -> anyhow::Result<Webauthn> {
+    let origin = Url::parse(&config.webauthn_rp_origin)?;
+    let webauthn = WebauthnBuilder::new(&config.webauthn_rp_id, &origin)?
+        .rp_name(&config.mfa_issuer)
+        .build()?;
+    Ok(webauthn)
+}
+
+pub fn sha256_hex(value: &str) -> String {
+    Sha256::digest(value.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// ---- TOTP ----
+
+fn build_totp(secret: Vec<u8>, issuer: &str, account: &str) -> Result<TOTP, ApiError> {
+    TOTP::new(Algorithm::SHA1, TOTP_DIGITS, 1, TOTP_STEP, secret, Some(issuer.replace(':', "")), account.to_string())
+        .map_err(ApiError::internal("TOTP configuration failed"))
+}
+
+/// Génère un nouveau secret TOTP et renvoie `(secret base32, URL otpauth://)`.
+pub fn new_totp_secret(issuer: &str, account: &str) -> Result<(String, String), ApiError> {
+    let secret = Secret::generate_secret()
+        .to_bytes()
+        .map_err(ApiError::internal("TOTP secret generation failed"))?;
+    let totp = build_totp(secret, issuer, account)?;
+    Ok((totp.get_secret_base32(), totp.get_url()))
+}
+
+/// Vérifie un code TOTP (± une période) et renvoie le pas de temps utilisé.
+/// Un pas déjà consommé est refusé pour empêcher le rejeu.
+fn match_totp_step(secret_base32: &str, code: &str, last_used_step: Option<i64>) -> Result<Option<i64>, ApiError> {
+    let secret = Secret::Encoded(secret_base32.to_string())
+        .to_bytes()
+        .map_err(ApiError::internal("Invalid stored TOTP secret"))?;
+    let totp = build_totp(secret, "", "")?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(ApiError::internal("System clock error"))?
+        .as_secs();
+    let current = (now / TOTP_STEP) as i64;
+
+    Ok((current - 1..=current + 1)
+        .filter(|step| last_used_step.is_none_or(|last| *step > last))
+        .find(|step| constant_time_eq(totp.generate(*step as u64 * TOTP_STEP).as_bytes(), code.trim().as_bytes())))
+}
+
+/// Vérifie un code contre le secret TOTP de l'utilisateur. Si `confirmed_only`
+/// est faux, un secret en cours d'enrôlement est aussi accepté.
+pub async fn verify_totp(db: &DatabaseConnection, config: &Config, user_id: Uuid, code: &str, confirmed_only: bool) -> Result<bool, ApiError> {
+    let totp = UserTotpEntity::find_by_id(user_id).one(db).await?;
+    let totp = match totp {
+        Some(t) if !confirmed_only || t.confirmed_at.is_some() => t,
+        _ => return Ok(false),
+    };
+
+    let secret = decrypt_secret(config, user_id, &totp.secret)?;
+    let Some(step) = match_totp_step(&secret, code, totp.last_used_step)? else {
+        return Ok(false);
+    };
+
+    // Mise à jour conditionnelle : deux requêtes concurrentes ne peuvent pas utiliser le même pas
+    let result = UserTotpEntity::update_many()
+        .col_expr(UserTotpColumn::LastUsedStep, Expr::value(step))
+        .filter(UserTotpColumn::UserId.eq(user_id))
+        .filter(
+            Condition::any()
+                .add(UserTotpColumn::LastUsedStep.is_null())
+                .add(UserTotpColumn::LastUsedStep.lt(step)),
+        )
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected == 1)
+}
+
+// ---- Chiffrement des secrets TOTP ----
+
+// Clé AES-256 dérivée de `MFA_ENCRYPTION_KEY`
+fn secret_key(config: &Config) -> [u8; 32] {
+    Sha256::digest(config.mfa_encryption_key.as_bytes()).into()
+}
+
+/// Chiffre un secret TOTP pour le stockage (AES-256-GCM, lié à l'utilisateur).
+pub fn encrypt_secret(config: &Config, user_id: Uuid, secret: &str) -> Result<String, ApiError> {
+    let mut nonce = [0u8; SECRET_NONCE_LENGTH];
+    OsRng.fill_bytes(&mut nonce);
+    let mut tag = [0u8; SECRET_TAG_LENGTH];
+    let ciphertext = encrypt_aead(Cipher::aes_256_gcm(), &secret_key(config), Some(&nonce), user_id.as_bytes(), secret.as_bytes(), &mut tag)
+        .map_err(ApiError::internal("TOTP secret encryption failed"))?;
+    Ok(format!("{}{}", SECRET_PREFIX, STANDARD_NO_PAD.encode([&nonce[..], &ciphertext, &tag].concat())))
+}
+
+fn decrypt_secret(config: &Config, user_id: Uuid, stored: &str) -> Result<String, ApiError> {
+    let invalid = || ApiError::Internal("Invalid stored TOTP secret".to_string());
+    let bytes = stored.strip_prefix(SECRET_PREFIX)
+        .and_then(|encoded| STANDARD_NO_PAD.decode(encoded).ok())
+        .filter(|bytes| bytes.len() > SECRET_NONCE_LENGTH + SECRET_TAG_LENGTH)
+        .ok_or_else(invalid)?;
+    let (nonce, rest) = bytes.split_at(SECRET_NONCE_LENGTH);
+    let (ciphertext, tag) = rest.split_at(rest.len() - SECRET_TAG_LENGTH);
+    let secret = decrypt_aead(Cipher::aes_256_gcm(), &secret_key(config), Some(nonce), user_id.as_bytes(), ciphertext, tag)
+        .map_err(ApiError::internal("TOTP secret decryption failed"))?;
+    String::from_utf8(secret).map_err(|_| invalid())
+}
+
+/// Chiffre les secrets enregistrés en clair avant l'introduction du chiffrement.
+pub async fn encrypt_legacy_secrets(db: &DatabaseConnection, config: &Config) -> anyhow::Result<u64> {
+    let legacy = UserTotpEntity::find()
+        .filter(UserTotpColumn::Secret.not_like(format!("{}%", SECRET_PREFIX)))
+        .all(db)
+        .await?;
+    let count = legacy.len() as u64;
+    for totp in legacy {
+        let secret = encrypt_secret(config, totp.user_id, &totp.secret)
+            .map_err(|_| anyhow::anyhow!("TOTP secret encryption failed"))?;
+        let mut totp: UserTotpActiveModel = totp.into_active_model();
+        totp.secret = Set(secret);
+        totp.update(db).await?;
+    }
+    Ok(count)
+}
+
+// ---- Codes de secours ----
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Remplace les codes de secours de l'utilisateur et renvoie les nouveaux en clair
+/// (ils ne sont stockés que hachés et ne peuvent plus être relus ensuite).
+pub async fn regenerate_recovery_codes(db: &DatabaseConnection, user_id: Uuid) -> Result<Vec<String>, DbErr> {
+    RecoveryCodeEntity::delete_many()
+        .filter(RecoveryCodeColumn::UserId.eq(user_id))
+        .exec(db)
+        .await?;
+
+    let mut rng = OsRng;
+    let now = Utc::now();
+    let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    let mut rows = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for _ in 0..RECOVERY_CODE_COUNT {
+        let raw: String = (0..10)
+            .map(|_| RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+            .collect();
+        rows.push(RecoveryCodeActiveModel {
+            id: Set(Uuid::new_v4()),
+            user_id: Set(user_id),
+            code_hash: Set(sha256_hex(&raw)),
+            used_at: Set(None),
+            created_at: Set(now),
+        });
+        codes.push(format!("{}-{}", &raw[..5], &raw[5..]));
+    }
+
+    RecoveryCodeEntity::insert_many(rows).exec(db).await?;
+    Ok(codes)
+}
+
+pub async fn remaining_recovery_codes(db: &DatabaseConnection, user_id: Uuid) -> Result<u64, DbErr> {
+    RecoveryCodeEntity::find()
+        .filter(RecoveryCodeColumn::UserId.eq(user_id))
+        .filter(RecoveryCodeColumn::UsedAt.is_null())
+        .count(db)
+        .await
+}
+
+/// Consomme un code de secours ; renvoie faux s'il est inconnu ou déjà utilisé.
+pub async fn use_recovery_code(db: &DatabaseConnection, user_id: Uuid, code: &str) -> Result<bool, DbErr> {
+    // Mise à jour conditionnelle : un code ne peut être consommé qu'une fois, même en concurrence
+    let result = RecoveryCodeEntity::update_many()
+        .col_expr(RecoveryCodeColumn::UsedAt, Expr::value(Utc::now()))
+        .filter(RecoveryCodeColumn::UserId.eq(user_id))
+        .filter(RecoveryCodeColumn::CodeHash.eq(sha256_hex(&normalize_recovery_code(code))))
+        .filter(RecoveryCodeColumn::UsedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected > 0)
+}
+
+// ---- WebAuthn ----
+
+/// Charge les passkeys enregistrées de l'utilisateur avec leur ligne en base.
+pub async fn load_passkeys(db: &DatabaseConnection, user_id: Uuid) -> Result<Vec<(webauthn_credential::Model, Passkey)>, ApiError> {
+    WebauthnCredentialEntity::find()
+        .filter(WebauthnCredentialColumn::UserId.eq(user_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|c| {
+            let passkey = serde_json::from_value(c.passkey.clone())
+                .map_err(ApiError::internal("Invalid stored WebAuthn credential"))?;
+            Ok((c, passkey))
+        })
+        .collect()
+}
+
+// ---- Méthodes disponibles ----
+
+/// Seconds facteurs utilisables par l'utilisateur ; vide si la MFA n'est pas activée.
+pub async fn user_mfa_methods(db: &DatabaseConnection, user_id: Uuid) -> Result<Vec<String>, DbErr> {
+    let mut methods = Vec::new();
+
+    let totp = UserTotpEntity::find()
+        .filter(UserTotpColumn::UserId.eq(user_id))
+        .filter(UserTotpColumn::ConfirmedAt.is_not_null())
+        .one(db)
+        .await?;
+    if totp.is_some() {
+        methods.push(METHOD_TOTP.to_string());
+    }
+
+    let credentials = WebauthnCredentialEntity::find()
+        .filter(WebauthnCredentialColumn::UserId.eq(user_id))
+        .count(db)
+        .await?;
+    if credentials > 0 {
+        methods.push(METHOD_WEBAUTHN.to_string());
+    }
+
+    if !methods.is_empty() && remaining_recovery_codes(db, user_id).await? > 0 {
+        methods.push(METHOD_RECOVERY_CODE.to_string());
+    }
+
+    Ok(methods)
+}
+
+// ---- Jetons de challenge ----
+
+/// Crée un challenge et renvoie le jeton opaque à transmettre au client.
+pub async fn create_challenge(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    purpose: &str,
+    state: Option<serde_json::Value>,
+) -> Result<String, DbErr> {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    // Un seul challenge en cours par utilisateur et par usage
+    MfaChallengeEntity::delete_many()
+        .filter(MfaChallengeColumn::UserId.eq(user_id))
+        .filter(MfaChallengeColumn::Purpose.eq(purpose))
+        .exec(db)
+        .await?;
+
+    let now = Utc::now();
+    let challenge = MfaChallengeActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        token_hash: Set(sha256_hex(&token)),
+        purpose: Set(purpose.to_string()),
+        state: Set(state),
+        attempts: Set(0),
+        expires_at: Set(now + Duration::seconds(CHALLENGE_TTL_SECONDS)),
+        created_at: Set(now),
+    };
+    MfaChallengeEntity::insert(challenge).exec(db).await?;
+    Ok(token)
+}
+
+/// Retrouve un challenge valide (non expiré, tentatives restantes).
+pub async fn find_challenge(db: &DatabaseConnection, token: &str, purpose: &str) -> Result<mfa_challenge::Model, ApiError> {
+    let challenge = MfaChallengeEntity::find()
+        .filter(MfaChallengeColumn::TokenHash.eq(sha256_hex(token)))
+        .filter(MfaChallengeColumn::Purpose.eq(purpose))
+        .one(db)
+        .await?;
+
+    match challenge {
+        Some(c) if c.expires_at > Utc::now() && c.attempts < CHALLENGE_MAX_ATTEMPTS => Ok(c),
+        _ => Err(ApiError::Unauthorized("Invalid or expired MFA token".to_string())),
+    }
+}
+
+/// Crée le challenge de connexion qui portera la session en attente.
+pub async fn create_login_challenge(db: &DatabaseConnection, user_id: Uuid, session: &SessionContext) -> Result<String, DbErr> {
+    create_challenge(db, user_id, CHALLENGE_LOGIN, Some(json!({ STATE_SESSION: session }))).await
+}
+
+/// Session en attente d'un challenge de connexion ; à défaut, connexion par mot de passe.
+pub fn pending_session(challenge: &mfa_challenge::Model) -> SessionContext {
+    challenge.state.as_ref()
+        .and_then(|state| state.get(STATE_SESSION))
+        .and_then(|session| serde_json::from_value(session.clone()).ok())
+        .unwrap_or_else(|| SessionContext::with_methods(&[AMR_PASSWORD]))
+}
+
+/// Enregistre l'état d'authentification WebAuthn à côté de la session en attente.
+pub async fn set_passkey_state(db: &DatabaseConnection, challenge: mfa_challenge::Model, passkey: serde_json::Value) -> Result<(), DbErr> {
+    let mut state = match challenge.state.clone() {
+        Some(serde_json::Value::Object(state)) => state,
+        _ => serde_json::Map::new(),
+    };
+    state.insert(STATE_PASSKEY.to_string(), passkey);
+    let mut challenge = challenge.into_active_model();
+    challenge.state = Set(Some(serde_json::Value::Object(state)));
+    challenge.update(db).await?;
+    Ok(())
+}
+
+pub fn passkey_state(challenge: &mfa_challenge::Model) -> Option<serde_json::Value> {
+    challenge.state.as_ref().and_then(|state| state.get(STATE_PASSKEY)).cloned()
+}
+
+/// Comptabilise un échec ; le challenge devient inutilisable au-delà du maximum.
+pub async fn record_challenge_failure(db: &DatabaseConnection, challenge: mfa_challenge::Model) -> Result<(), DbErr> {
+    let attempts = challenge.attempts + 1;
+    let mut challenge = challenge.into_active_model();
+    challenge.attempts = Set(attempts);
+    challenge.update(db).await?;
+    Ok(())
+}
+
+pub async fn consume_challenge(db: &DatabaseConnection, challenge: mfa_challenge::Model) -> Result<(), DbErr> {
+    MfaChallengeEntity::delete_by_id(challenge.id).exec(db).await?;
+    Ok(())
+}
+
+// ---- Politique des entités ----
+
+/// Vrai si la session courante a été ouverte avec un second facteur.
+pub fn session_has_mfa(req: &HttpRequest) -> bool {
+    req.extensions().get::<Claims>().is_some_and(|c| c.is_mfa())
+}
+
+/// Refuse l'accès à une entité qui impose la MFA si la session n'en a pas fait.
+pub async fn enforce_entity_mfa(db: &DatabaseConnection, req: &HttpRequest, entity_id: Uuid) -> Result<(), ApiError> {
+    if session_has_mfa(req) {
+        return Ok(());
+    }
+    let entity = EntityEntity::find_by_id(entity_id).one(db).await?;
+    if entity.is_some_and(|e| e.require_mfa) {
+        return Err(ApiError::MfaRequired);
+    }
+    Ok(())
+}
+
+/// Retire d'une liste d'entités celles qui imposent la MFA quand la session n'en a pas fait.
+pub async fn filter_mfa_entities(db: &DatabaseConnection, req: &HttpRequest, entity_ids: Vec<Uuid>) -> Result<Vec<Uuid>, DbErr> {
+    if session_has_mfa(req) || entity_ids.is_empty() {
+        return Ok(entity_ids);
+    }
+    let restricted: Vec<Uuid> = EntityEntity::find()
+        .filter(EntityColumn::Id.is_in(entity_ids.clone()))
+        .filter(EntityColumn::RequireMfa.eq(true))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
+    Ok(entity_ids.into_iter().filter(|id| !restricted.contains(id)).collect())
+}
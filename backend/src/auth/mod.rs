@@ -2,4 +2,11 @@ pub mod handlers;
 pub mod models;
 pub mod jwt;
 pub mod oidc;
+pub mod password;
+pub mod throttle;
+pub mod mfa;
+pub mod tokens;
+pub mod saml;
+pub mod sso;
 
+pub mod api_tokens;
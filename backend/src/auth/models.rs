@@ -1,47 +1,44 @@
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use validator::{Validate, ValidationError};
+use webauthn_rs::prelude::{CreationChallengeResponse, PublicKeyCredential, RegisterPublicKeyCredential};
+use crate::auth::api_tokens::SCOPES;
+use crate::auth::mfa::{METHOD_TOTP, METHOD_RECOVERY_CODE};
+use crate::validation::{not_blank, one_of};
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
-pub struct User {
-    pub id: Uuid,
-    pub email: String,
-    pub password_hash: String,
-    pub first_name: Option<String>,
-    pub last_name: Option<String>,
-    pub is_active: bool,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UserEntity {
-    pub id: Uuid,
-    pub user_id: Uuid,
-    pub entity_id: Uuid,
-    pub role: String,
-    pub created_at: DateTime<Utc>,
-}
-
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct LoginRequest {
+    #[validate(email)]
     pub email: String,
+    #[validate(length(min = 1))]
     pub password: String,
 }
 
-#[derive(Debug, Deserialize)]
+// La robustesse du mot de passe est vérifiée par la `PasswordPolicy` configurée
+#[derive(Debug, Deserialize, Validate)]
 pub struct RegisterRequest {
+    #[validate(email, length(max = 255))]
     pub email: String,
     pub password: String,
+    #[validate(length(min = 1, max = 100))]
     pub first_name: Option<String>,
+    #[validate(length(min = 1, max = 100))]
     pub last_name: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct RefreshTokenRequest {
+    #[validate(length(min = 1))]
+    pub refresh_token: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub token: String,
     pub refresh_token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_entity_id: Option<Uuid>,
     pub user: UserInfo,
 }
 
@@ -53,3 +50,226 @@ pub struct UserInfo {
     pub last_name: Option<String>,
 }
 
+
+#[derive(Debug, Serialize)]
+pub struct EntityMembership {
+    pub entity_id: Uuid,
+    pub role: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inherited_from: Option<Uuid>, // Rôle hérité d'une entité parente
+}
+
+#[derive(Debug, Serialize)]
+pub struct CurrentUserResponse {
+    #[serde(flatten)]
+    pub user: UserInfo,
+    pub active_entity_id: Option<Uuid>,
+    pub memberships: Vec<EntityMembership>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SwitchEntityRequest {
+    pub entity_id: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SwitchEntityResponse {
+    #[serde(flatten)]
+    pub auth: AuthResponse,
+    pub role: String, // Rôle dans l'entité active, à titre indicatif
+}
+
+/// Réponse du login quand un second facteur est exigé : aucun jeton d'accès
+/// n'est émis avant la vérification de `mfa_token`.
+#[derive(Debug, Serialize)]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+    pub methods: Vec<String>,
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct MfaVerifyRequest {
+    #[validate(length(min = 1))]
+    pub mfa_token: String,
+    #[validate(custom(function = "validate_mfa_code_method"))]
+    pub method: String, // "totp" or "recovery_code"
+    #[validate(length(min = 1, max = 64))]
+    pub code: String,
+}
+
+fn validate_mfa_code_method(method: &str) -> Result<(), validator::ValidationError> {
+    one_of(method, &[METHOD_TOTP, METHOD_RECOVERY_CODE])
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct MfaTokenRequest {
+    #[validate(length(min = 1))]
+    pub mfa_token: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct WebauthnLoginFinishRequest {
+    #[validate(length(min = 1))]
+    pub mfa_token: String,
+    pub credential: PublicKeyCredential,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaStatus {
+    pub totp_enabled: bool,
+    pub webauthn_credentials: Vec<WebauthnCredentialInfo>,
+    pub recovery_codes_remaining: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebauthnCredentialInfo {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TotpEnrollResponse {
+    pub secret: String,
+    pub otpauth_url: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct TotpCodeRequest {
+    #[validate(length(min = 6, max = 8))]
+    pub code: String,
+}
+
+/// Codes de secours en clair, affichés une seule fois. Vide si l'utilisateur
+/// en possédait déjà.
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct WebauthnRegisterStartRequest {
+    #[validate(length(min = 1, max = 100), custom(function = "not_blank"))]
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebauthnRegisterStartResponse {
+    pub registration_token: String,
+    pub options: CreationChallengeResponse,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct WebauthnRegisterFinishRequest {
+    #[validate(length(min = 1))]
+    pub registration_token: String,
+    pub credential: RegisterPublicKeyCredential,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebauthnRegisterFinishResponse {
+    pub credential: WebauthnCredentialInfo,
+    pub recovery_codes: Vec<String>,
+}
+
+/// Réponse de l'inscription quand l'adresse email doit d'abord être vérifiée.
+#[derive(Debug, Serialize)]
+pub struct RegisterResponse {
+    pub user: UserInfo,
+    pub email_verification_required: bool,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct TokenRequest {
+    #[validate(length(min = 1))]
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct EmailRequest {
+    #[validate(email)]
+    pub email: String,
+}
+
+// La robustesse du nouveau mot de passe est vérifiée par la `PasswordPolicy` configurée
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    #[validate(length(min = 1))]
+    pub token: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 1))]
+    pub current_password: String,
+    pub new_password: String,
+}
+
+// Paramètres renvoyés par le fournisseur OIDC sur le callback
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct OidcExchangeRequest {
+    #[validate(length(min = 1))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SsoDiscoveryResponse {
+    pub provider_id: Uuid,
+    pub name: String,
+    pub protocol: String,
+    pub authorize_url: String, // Relatif à la base de l'API
+}
+
+// Binding HTTP-POST : champs du formulaire renvoyé par l'IdP
+#[derive(Debug, Deserialize)]
+pub struct SamlAcsForm {
+    #[serde(rename = "SAMLResponse")]
+    pub saml_response: String,
+}
+
+fn validate_scopes(scopes: &[String]) -> Result<(), ValidationError> {
+    scopes.iter().try_for_each(|scope| one_of(scope, SCOPES))
+}
+
+// Jeton d'API (personnel ou de compte de service) ; l'échéance est obligatoire
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiTokenRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub name: String,
+    #[validate(length(min = 1, max = 20), custom(function = "validate_scopes"))]
+    pub scopes: Vec<String>,
+    pub entity_id: Option<Uuid>, // Limite le jeton à une entité (et à ses filiales)
+    pub expires_at: DateTime<Utc>,
+}
+
+// Le jeton lui-même n'est jamais renvoyé, seul son préfixe l'identifie
+#[derive(Debug, Serialize)]
+pub struct ApiTokenResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub token_prefix: String,
+    pub scopes: Vec<String>,
+    pub entity_id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedApiTokenResponse {
+    #[serde(flatten)]
+    pub api_token: ApiTokenResponse,
+    pub token: String, // Affiché une seule fois
+}
@@ -1,24 +1,52 @@
-use serde::{Deserialize, Serialize};
-use reqwest::Client;
+use std::collections::HashMap;
+use std::time::{Duration as StdDuration, Instant};
+use actix_web::cookie::{time::Duration as CookieDuration, Cookie, SameSite};
+use actix_web::HttpRequest;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use jsonwebtoken::jwk::JwkSet;
+use rand::{RngCore, rngs::OsRng};
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait, ColumnTrait, QueryFilter, Set};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+use uuid::Uuid;
 use crate::config::Config;
+use crate::entities_orm::identity_provider;
+use crate::outbound::OutboundClient;
+use crate::entities_orm::oidc_login_state::{self, Entity as OidcLoginStateEntity, Column as OidcLoginStateColumn, ActiveModel as OidcLoginStateActiveModel};
 use anyhow::{Result, Context};
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct OidcConfig {
-    pub issuer: String,
-    pub client_id: String,
-    pub client_secret: String,
-    pub redirect_uri: String,
-}
+// Durée laissée à l'utilisateur pour s'authentifier auprès du fournisseur
+const LOGIN_STATE_TTL_SECONDS: i64 = 600;
+/// Cookie qui lie le `state` au navigateur qui a lancé la connexion.
+pub const LOGIN_STATE_COOKIE: &str = "hypercyber_oidc_state";
+const LOGIN_STATE_COOKIE_PATH: &str = "/api/auth";
+const JWKS_TTL: StdDuration = StdDuration::from_secs(3600);
+// Délai minimal entre deux rechargements forcés (clé inconnue)
+const JWKS_MIN_REFRESH: StdDuration = StdDuration::from_secs(60);
+// Tolérance d'horloge sur exp / iat
+const ID_TOKEN_LEEWAY_SECONDS: u64 = 60;
 
-#[derive(Debug, Deserialize)]
+// Seuls les algorithmes asymétriques sont acceptés pour l'id_token
+const ID_TOKEN_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256, Algorithm::RS384, Algorithm::RS512,
+    Algorithm::PS256, Algorithm::PS384, Algorithm::PS512,
+    Algorithm::ES256, Algorithm::ES384, Algorithm::EdDSA,
+];
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct OidcDiscovery {
     pub authorization_endpoint: String,
     pub token_endpoint: String,
-    pub userinfo_endpoint: String,
+    pub userinfo_endpoint: Option<String>,
+    pub jwks_uri: String,
     pub issuer: String,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 pub struct TokenResponse {
     pub access_token: String,
@@ -27,54 +55,256 @@ pub struct TokenResponse {
     pub expires_in: Option<u64>,
 }
 
+/// Client OIDC : fournisseur global (`Config`) ou fournisseur d'une entité.
+pub struct OidcClient {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+}
+
+impl OidcClient {
+    pub fn from_config(config: &Config) -> Option<Self> {
+        Some(Self {
+            issuer: config.oidc_issuer.clone()?,
+            client_id: config.oidc_client_id.clone()?,
+            client_secret: config.oidc_client_secret.clone(),
+            redirect_uri: config.oidc_callback_url(),
+        })
+    }
+
+    pub fn from_provider(provider: &identity_provider::Model, config: &Config) -> Option<Self> {
+        Some(Self {
+            issuer: provider.issuer.clone(),
+            client_id: provider.client_id.clone()?,
+            client_secret: provider.client_secret.clone(),
+            redirect_uri: config.oidc_callback_url(),
+        })
+    }
+}
+
+/// Claims de l'id_token utilisés pour identifier l'utilisateur.
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub azp: Option<String>,
+    pub email: Option<String>,
+    // Certains fournisseurs envoient "true" sous forme de chaîne
+    pub email_verified: Option<serde_json::Value>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    // Méthodes (RFC 8176) et niveau d'authentification déclarés par le fournisseur
+    #[serde(default)]
+    pub amr: Vec<String>,
+    pub acr: Option<String>,
+    // Autres claims (groupes, rôles) lus selon la configuration du fournisseur
+    #[serde(flatten)]
+    pub additional: serde_json::Map<String, serde_json::Value>,
+}
+
+impl IdTokenClaims {
+    pub fn email_verified(&self) -> bool {
+        is_verified(&self.email_verified)
+    }
+}
+
+/// Réponse du endpoint userinfo, utilisée quand l'id_token ne porte pas l'email.
 #[derive(Debug, Deserialize)]
 pub struct UserInfo {
     pub sub: String,
     pub email: Option<String>,
-    pub email_verified: Option<bool>,
-    pub name: Option<String>,
+    pub email_verified: Option<serde_json::Value>,
     pub given_name: Option<String>,
     pub family_name: Option<String>,
+    #[serde(flatten)]
+    pub additional: serde_json::Map<String, serde_json::Value>,
 }
 
-pub async fn discover_oidc_config(issuer: &str) -> Result<OidcDiscovery> {
-    let discovery_url = format!("{}/.well-known/openid-configuration", issuer.trim_end_matches('/'));
-    let client = Client::new();
-    let response = client
-        .get(&discovery_url)
+fn is_verified(value: &Option<serde_json::Value>) -> bool {
+    match value {
+        Some(serde_json::Value::Bool(b)) => *b,
+        Some(serde_json::Value::String(s)) => s.eq_ignore_ascii_case("true"),
+        _ => false,
+    }
+}
+
+impl UserInfo {
+    pub fn email_verified(&self) -> bool {
+        is_verified(&self.email_verified)
+    }
+}
+
+/// Paramètres d'une tentative de connexion, conservés côté serveur entre
+/// la redirection vers le fournisseur et le retour sur le callback.
+pub struct LoginState {
+    pub state: String,
+    pub nonce: String,
+    pub code_challenge: String,
+}
+
+fn random_urlsafe(bytes: usize) -> String {
+    let mut buffer = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buffer);
+    URL_SAFE_NO_PAD.encode(buffer)
+}
+
+fn sha256_urlsafe(value: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(value.as_bytes()))
+}
+
+fn normalize_issuer(issuer: &str) -> &str {
+    issuer.trim_end_matches('/')
+}
+
+/// Crée et enregistre `state`, `nonce` et le vérificateur PKCE (S256).
+pub async fn create_login_state(db: &DatabaseConnection, provider_id: Option<Uuid>) -> Result<LoginState, DbErr> {
+    let state = random_urlsafe(32);
+    let nonce = random_urlsafe(32);
+    let code_verifier = random_urlsafe(48);
+
+    // Purge opportuniste des tentatives abandonnées
+    OidcLoginStateEntity::delete_many()
+        .filter(OidcLoginStateColumn::ExpiresAt.lt(Utc::now()))
+        .exec(db)
+        .await?;
+
+    let now = Utc::now();
+    let login_state = OidcLoginStateActiveModel {
+        id: Set(Uuid::new_v4()),
+        state_hash: Set(sha256_urlsafe(&state)),
+        nonce: Set(nonce.clone()),
+        code_verifier: Set(code_verifier.clone()),
+        provider_id: Set(provider_id),
+        expires_at: Set(now + Duration::seconds(LOGIN_STATE_TTL_SECONDS)),
+        created_at: Set(now),
+    };
+    OidcLoginStateEntity::insert(login_state).exec(db).await?;
+
+    Ok(LoginState {
+        state,
+        nonce,
+        code_challenge: sha256_urlsafe(&code_verifier),
+    })
+}
+
+/// Cookie posé avec la redirection vers le fournisseur : il porte l'empreinte
+/// du `state`, si bien qu'un callback ouvert dans un autre navigateur (CSRF de
+/// connexion : la victime connectée au compte de l'attaquant) est refusé.
+pub fn login_state_cookie(login_state: &LoginState) -> Cookie<'static> {
+    Cookie::build(LOGIN_STATE_COOKIE, sha256_urlsafe(&login_state.state))
+        .path(LOGIN_STATE_COOKIE_PATH)
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::Lax)
+        .max_age(CookieDuration::seconds(LOGIN_STATE_TTL_SECONDS))
+        .finish()
+}
+
+/// Cookie expiré qui remplace celui du `state` une fois le callback traité.
+pub fn clear_login_state_cookie() -> Cookie<'static> {
+    let mut cookie = Cookie::build(LOGIN_STATE_COOKIE, "")
+        .path(LOGIN_STATE_COOKIE_PATH)
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::Lax)
+        .finish();
+    cookie.make_removal();
+    cookie
+}
+
+/// Vrai si le navigateur présente le cookie posé pour ce `state`.
+pub fn state_matches_cookie(req: &HttpRequest, state: &str) -> bool {
+    let expected = sha256_urlsafe(state);
+    req.cookie(LOGIN_STATE_COOKIE).is_some_and(|cookie| {
+        let value = cookie.value().as_bytes();
+        value.len() == expected.len() && openssl::memcmp::eq(value, expected.as_bytes())
+    })
+}
+
+/// Récupère et supprime l'état associé à `state` (usage unique). Renvoie
+/// `None` si l'état est inconnu ou expiré.
+pub async fn take_login_state(db: &DatabaseConnection, state: &str) -> Result<Option<oidc_login_state::Model>, DbErr> {
+    let stored = OidcLoginStateEntity::find()
+        .filter(OidcLoginStateColumn::StateHash.eq(sha256_urlsafe(state)))
+        .one(db)
+        .await?;
+
+    let Some(stored) = stored else {
+        return Ok(None);
+    };
+    let result = OidcLoginStateEntity::delete_by_id(stored.id).exec(db).await?;
+    // Un autre callback a déjà consommé cet état
+    if result.rows_affected != 1 || stored.expires_at <= Utc::now() {
+        return Ok(None);
+    }
+    Ok(Some(stored))
+}
+
+/// Document de découverte de l'émetteur. Les URL qu'il annonce sont
+/// appelées par le serveur : elles passent par le même client sortant.
+pub async fn discover_oidc_config(http: &OutboundClient, issuer: &str) -> Result<OidcDiscovery> {
+    let discovery_url = format!("{}/.well-known/openid-configuration", normalize_issuer(issuer));
+    let response = http
+        .get(&discovery_url)?
         .send()
         .await
         .context("Failed to fetch OIDC discovery document")?;
-    
-    response
+
+    let discovery = response
         .json::<OidcDiscovery>()
         .await
-        .context("Failed to parse OIDC discovery document")
+        .context("Failed to parse OIDC discovery document")?;
+
+    // L'émetteur annoncé doit être celui qui est configuré (OIDC Discovery §4.3)
+    if normalize_issuer(&discovery.issuer) != normalize_issuer(issuer) {
+        return Err(anyhow::anyhow!("OIDC discovery issuer mismatch: {}", discovery.issuer));
+    }
+    http.check(&discovery.token_endpoint).context("Invalid OIDC token endpoint")?;
+    http.check(&discovery.jwks_uri).context("Invalid OIDC JWKS URI")?;
+    if let Some(userinfo_endpoint) = &discovery.userinfo_endpoint {
+        http.check(userinfo_endpoint).context("Invalid OIDC userinfo endpoint")?;
+    }
+    Ok(discovery)
+}
+
+pub fn build_authorization_url(client: &OidcClient, discovery: &OidcDiscovery, login_state: &LoginState) -> Result<String> {
+    let mut url = reqwest::Url::parse(&discovery.authorization_endpoint)
+        .context("Invalid OIDC authorization endpoint")?;
+    url.query_pairs_mut()
+        .append_pair("client_id", &client.client_id)
+        .append_pair("redirect_uri", &client.redirect_uri)
+        .append_pair("response_type", "code")
+        .append_pair("scope", "openid email profile")
+        .append_pair("state", &login_state.state)
+        .append_pair("nonce", &login_state.nonce)
+        .append_pair("code_challenge", &login_state.code_challenge)
+        .append_pair("code_challenge_method", "S256");
+    Ok(url.into())
 }
 
 pub async fn exchange_code_for_token(
+    http: &OutboundClient,
     code: &str,
-    config: &Config,
+    code_verifier: &str,
+    client: &OidcClient,
     discovery: &OidcDiscovery,
 ) -> Result<TokenResponse> {
-    let client = Client::new();
-    let client_id = config.oidc_client_id.as_ref()
-        .ok_or_else(|| anyhow::anyhow!("OIDC client ID not configured"))?;
-    let client_secret = config.oidc_client_secret.as_ref()
-        .ok_or_else(|| anyhow::anyhow!("OIDC client secret not configured"))?;
-    let redirect_uri = config.oidc_redirect_uri.as_ref()
-        .ok_or_else(|| anyhow::anyhow!("OIDC redirect URI not configured"))?;
-
-    let params = [
+    let mut params = vec![
         ("grant_type", "authorization_code"),
         ("code", code),
-        ("redirect_uri", redirect_uri),
-        ("client_id", client_id),
-        ("client_secret", client_secret),
+        ("redirect_uri", client.redirect_uri.as_str()),
+        ("client_id", client.client_id.as_str()),
+        ("code_verifier", code_verifier),
     ];
+    // Un client public (sans secret) s'appuie uniquement sur PKCE
+    if let Some(client_secret) = &client.client_secret {
+        params.push(("client_secret", client_secret));
+    }
 
-    let response = client
-        .post(&discovery.token_endpoint)
+    let response = http
+        .post(&discovery.token_endpoint)?
         .form(&params)
         .send()
         .await
@@ -91,10 +321,11 @@ pub async fn exchange_code_for_token(
         .context("Failed to parse token response")
 }
 
-pub async fn get_user_info(access_token: &str, discovery: &OidcDiscovery) -> Result<UserInfo> {
-    let client = Client::new();
-    let response = client
-        .get(&discovery.userinfo_endpoint)
+pub async fn get_user_info(http: &OutboundClient, access_token: &str, discovery: &OidcDiscovery) -> Result<UserInfo> {
+    let userinfo_endpoint = discovery.userinfo_endpoint.as_ref()
+        .ok_or_else(|| anyhow::anyhow!("OIDC provider has no userinfo endpoint"))?;
+    let response = http
+        .get(userinfo_endpoint)?
         .bearer_auth(access_token)
         .send()
         .await
@@ -111,3 +342,263 @@ pub async fn get_user_info(access_token: &str, discovery: &OidcDiscovery) -> Res
         .context("Failed to parse user info")
 }
 
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// Cache des clés publiques (JWKS) des fournisseurs, par URL. Les clés sont
+/// rechargées après expiration ou quand un `kid` inconnu apparaît (rotation).
+pub struct JwksCache {
+    http: OutboundClient,
+    entries: RwLock<HashMap<String, CachedJwks>>,
+}
+
+impl JwksCache {
+    pub fn new(http: OutboundClient) -> Self {
+        Self {
+            http,
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    async fn fetch(&self, jwks_uri: &str) -> Result<JwkSet> {
+        let keys = self.http
+            .get(jwks_uri)?
+            .send()
+            .await
+            .context("Failed to fetch JWKS")?
+            .error_for_status()
+            .context("Failed to fetch JWKS")?
+            .json::<JwkSet>()
+            .await
+            .context("Failed to parse JWKS")?;
+
+        self.entries.write().await.insert(jwks_uri.to_string(), CachedJwks {
+            keys: keys.clone(),
+            fetched_at: Instant::now(),
+        });
+        Ok(keys)
+    }
+
+    /// Renvoie la clé `kid` (ou l'unique clé si l'en-tête n'a pas de `kid`).
+    pub async fn decoding_key(&self, jwks_uri: &str, kid: Option<&str>) -> Result<DecodingKey> {
+        let cached = {
+            let entries = self.entries.read().await;
+            entries.get(jwks_uri).map(|c| (c.keys.clone(), c.fetched_at.elapsed()))
+        };
+
+        let find = |keys: &JwkSet| match kid {
+            Some(kid) => keys.find(kid).cloned(),
+            None if keys.keys.len() == 1 => keys.keys.first().cloned(),
+            None => None,
+        };
+
+        let jwk = match cached {
+            Some((keys, age)) if age < JWKS_TTL => match find(&keys) {
+                Some(jwk) => Some(jwk),
+                None if age >= JWKS_MIN_REFRESH => find(&self.fetch(jwks_uri).await?),
+                None => None,
+            },
+            _ => find(&self.fetch(jwks_uri).await?),
+        };
+
+        let jwk = jwk.ok_or_else(|| anyhow::anyhow!("No matching JWKS key for kid {:?}", kid))?;
+        DecodingKey::from_jwk(&jwk).context("Unsupported JWKS key")
+    }
+}
+
+/// Vérifie la signature de l'id_token avec le JWKS du fournisseur, puis
+/// `iss`, `aud`, `exp`, `azp` et le `nonce` de la tentative de connexion.
+pub async fn verify_id_token(
+    id_token: &str,
+    expected_nonce: &str,
+    client: &OidcClient,
+    discovery: &OidcDiscovery,
+    jwks: &JwksCache,
+) -> Result<IdTokenClaims> {
+    let client_id = &client.client_id;
+
+    let header = decode_header(id_token).context("Malformed id_token")?;
+    if !ID_TOKEN_ALGORITHMS.contains(&header.alg) {
+        return Err(anyhow::anyhow!("Unsupported id_token algorithm {:?}", header.alg));
+    }
+    let key = jwks.decoding_key(&discovery.jwks_uri, header.kid.as_deref()).await?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[&discovery.issuer]);
+    validation.set_audience(&[client_id]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+    validation.leeway = ID_TOKEN_LEEWAY_SECONDS;
+
+    let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+        .context("Invalid id_token")?
+        .claims;
+
+    if claims.nonce.as_deref() != Some(expected_nonce) {
+        return Err(anyhow::anyhow!("id_token nonce mismatch"));
+    }
+    if let Some(azp) = &claims.azp
+        && azp != client_id
+    {
+        return Err(anyhow::anyhow!("id_token authorized party mismatch"));
+    }
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use openssl::rsa::Rsa;
+    use serde_json::{json, Value};
+    use crate::test_support::{MockIssuer, MOCK_ISSUER_KEY_ID};
+
+    const CLIENT_ID: &str = "hypercyber-test";
+    const NONCE: &str = "expected-nonce";
+    const EMAIL: &str = "alice@example.com";
+
+    struct Provider {
+        mock: MockIssuer,
+        http: OutboundClient,
+        client: OidcClient,
+        discovery: OidcDiscovery,
+    }
+
+    impl Provider {
+        async fn start() -> Self {
+            let mock = MockIssuer::start(CLIENT_ID).await;
+            let http = OutboundClient::new(true).unwrap();
+            let discovery = discover_oidc_config(&http, &mock.issuer).await.unwrap();
+            let client = OidcClient {
+                issuer: mock.issuer.clone(),
+                client_id: CLIENT_ID.to_string(),
+                client_secret: None,
+                redirect_uri: "http://localhost:8080/api/auth/oidc/callback".to_string(),
+            };
+            Self { mock, http, client, discovery }
+        }
+
+        async fn verify(&self, id_token: &str) -> Result<IdTokenClaims> {
+            let jwks = JwksCache::new(self.http.clone());
+            verify_id_token(id_token, NONCE, &self.client, &self.discovery, &jwks).await
+        }
+
+        fn login_state(&self, code_verifier: &str) -> LoginState {
+            LoginState {
+                state: random_urlsafe(32),
+                nonce: NONCE.to_string(),
+                code_challenge: sha256_urlsafe(code_verifier),
+            }
+        }
+
+        async fn authorization_code(&self, login_state: &LoginState) -> String {
+            let url = build_authorization_url(&self.client, &self.discovery, login_state).unwrap();
+            let location = self.mock.authorize(&url, EMAIL).await;
+            let location = reqwest::Url::parse(&location).unwrap();
+            let param = |name: &str| location.query_pairs()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.into_owned())
+                .unwrap();
+            assert_eq!(param("state"), login_state.state);
+            param("code")
+        }
+    }
+
+    #[actix_web::test]
+    async fn accepts_a_valid_id_token() {
+        let provider = Provider::start().await;
+        let id_token = provider.mock.id_token(&provider.mock.claims(EMAIL, NONCE));
+
+        let claims = provider.verify(&id_token).await.unwrap();
+        assert_eq!(claims.iss, provider.mock.issuer);
+        assert_eq!(claims.email.as_deref(), Some(EMAIL));
+        assert!(claims.email_verified());
+    }
+
+    #[actix_web::test]
+    async fn rejects_a_nonce_mismatch() {
+        let provider = Provider::start().await;
+        let id_token = provider.mock.id_token(&provider.mock.claims(EMAIL, "another-nonce"));
+        assert!(provider.verify(&id_token).await.is_err());
+
+        let mut claims = provider.mock.claims(EMAIL, NONCE);
+        claims.as_object_mut().unwrap().remove("nonce");
+        assert!(provider.verify(&provider.mock.id_token(&claims)).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn rejects_foreign_issuer_audience_and_authorized_party_and_expired_tokens() {
+        let provider = Provider::start().await;
+        let expired = Utc::now().timestamp() - 3600;
+        for (claim, value) in [
+            ("iss", json!("https://idp.example.com")),
+            ("aud", json!("another-client")),
+            ("aud", json!(["another-client"])),
+            ("azp", json!("another-client")),
+            ("exp", json!(expired)),
+        ] {
+            let mut claims = provider.mock.claims(EMAIL, NONCE);
+            claims[claim] = value.clone();
+            let result = provider.verify(&provider.mock.id_token(&claims)).await;
+            assert!(result.is_err(), "{} = {} should be rejected", claim, value);
+        }
+    }
+
+    #[actix_web::test]
+    async fn rejects_unsigned_symmetric_and_foreign_signatures() {
+        let provider = Provider::start().await;
+        let claims = provider.mock.claims(EMAIL, NONCE);
+
+        let encode_part = |value: &Value| URL_SAFE_NO_PAD.encode(value.to_string());
+        let unsigned = format!("{}.{}.", encode_part(&json!({ "alg": "none", "typ": "JWT" })), encode_part(&claims));
+        assert!(provider.verify(&unsigned).await.is_err());
+
+        // Secret partagé : la clé publique du fournisseur ne doit pas servir de clé HMAC
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some(MOCK_ISSUER_KEY_ID.to_string());
+        let symmetric = encode(&header, &claims, &EncodingKey::from_secret(provider.mock.issuer.as_bytes())).unwrap();
+        assert!(provider.verify(&symmetric).await.is_err());
+
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(MOCK_ISSUER_KEY_ID.to_string());
+        let other_key = Rsa::generate(2048).unwrap().private_key_to_pem().unwrap();
+        let forged = encode(&header, &claims, &EncodingKey::from_rsa_pem(&other_key).unwrap()).unwrap();
+        assert!(provider.verify(&forged).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn authorization_requests_carry_an_s256_challenge() {
+        let provider = Provider::start().await;
+        let login_state = provider.login_state("verifier-for-the-challenge");
+        let url = build_authorization_url(&provider.client, &provider.discovery, &login_state).unwrap();
+        let url = reqwest::Url::parse(&url).unwrap();
+        let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+
+        assert_eq!(params["code_challenge_method"], "S256");
+        assert_eq!(params["code_challenge"], sha256_urlsafe("verifier-for-the-challenge"));
+        assert_eq!(params["state"], login_state.state);
+        assert_eq!(params["nonce"], NONCE);
+        assert!(!params.contains_key("code_verifier"));
+    }
+
+    #[actix_web::test]
+    async fn code_exchange_requires_the_pkce_verifier() {
+        let provider = Provider::start().await;
+        let code_verifier = random_urlsafe(48);
+        let login_state = provider.login_state(&code_verifier);
+
+        let code = provider.authorization_code(&login_state).await;
+        let result = exchange_code_for_token(&provider.http, &code, "another-verifier", &provider.client, &provider.discovery).await;
+        assert!(result.is_err());
+
+        let code = provider.authorization_code(&login_state).await;
+        let tokens = exchange_code_for_token(&provider.http, &code, &code_verifier, &provider.client, &provider.discovery).await.unwrap();
+        let claims = provider.verify(tokens.id_token.as_deref().unwrap()).await.unwrap();
+        assert_eq!(claims.email.as_deref(), Some(EMAIL));
+
+        // Le code est à usage unique
+        let replay = exchange_code_for_token(&provider.http, &code, &code_verifier, &provider.client, &provider.discovery).await;
+        assert!(replay.is_err());
+    }
+}
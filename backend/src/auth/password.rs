@@ -0,0 +1,86 @@
+use std::env;
+use crate::errors::{ApiError, FieldError};
+
+// bcrypt ignore tout ce qui dépasse 72 octets
+const BCRYPT_MAX_BYTES: usize = 72;
+
+/// Politique de robustesse des mots de passe, configurable par variables d'environnement.
+#[derive(Clone, Debug)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 12,
+            require_uppercase: true,
+            require_lowercase: true,
+            require_digit: true,
+            require_symbol: false,
+        }
+    }
+}
+
+fn env_flag(name: &str, default: bool) -> bool {
+    env::var(name)
+        .ok()
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(default)
+}
+
+impl PasswordPolicy {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            min_length: env::var("PASSWORD_MIN_LENGTH")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.min_length),
+            require_uppercase: env_flag("PASSWORD_REQUIRE_UPPERCASE", default.require_uppercase),
+            require_lowercase: env_flag("PASSWORD_REQUIRE_LOWERCASE", default.require_lowercase),
+            require_digit: env_flag("PASSWORD_REQUIRE_DIGIT", default.require_digit),
+            require_symbol: env_flag("PASSWORD_REQUIRE_SYMBOL", default.require_symbol),
+        }
+    }
+
+    /// Vérifie un mot de passe et renvoie une erreur de validation par règle non respectée.
+    pub fn check(&self, field: &str, password: &str) -> Result<(), ApiError> {
+        let mut errors = Vec::new();
+
+        if password.chars().count() < self.min_length {
+            errors.push(FieldError::new(field, "password_too_short",
+                format!("Password must contain at least {} characters", self.min_length)));
+        }
+        if password.len() > BCRYPT_MAX_BYTES {
+            errors.push(FieldError::new(field, "password_too_long",
+                format!("Password must not exceed {} bytes", BCRYPT_MAX_BYTES)));
+        }
+        if self.require_uppercase && !password.chars().any(|c| c.is_uppercase()) {
+            errors.push(FieldError::new(field, "password_missing_uppercase",
+                "Password must contain an uppercase letter"));
+        }
+        if self.require_lowercase && !password.chars().any(|c| c.is_lowercase()) {
+            errors.push(FieldError::new(field, "password_missing_lowercase",
+                "Password must contain a lowercase letter"));
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            errors.push(FieldError::new(field, "password_missing_digit",
+                "Password must contain a digit"));
+        }
+        if self.require_symbol && !password.chars().any(|c| !c.is_alphanumeric()) {
+            errors.push(FieldError::new(field, "password_missing_symbol",
+                "Password must contain a symbol"));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ApiError::Validation(errors))
+        }
+    }
+}
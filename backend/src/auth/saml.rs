@@ -0,0 +1,879 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+use actix_web::cookie::{time::Duration as CookieDuration, Cookie, SameSite};
+use actix_web::HttpRequest;
+use anyhow::{Result, Context, bail};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use flate2::{write::DeflateEncoder, Compression};
+use openssl::hash::{hash, MessageDigest};
+use openssl::sign::Verifier;
+use openssl::x509::X509;
+use rand::{RngCore, rngs::OsRng};
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait, ColumnTrait, QueryFilter, Set};
+use uuid::Uuid;
+use xml::attribute::OwnedAttribute;
+use xml::reader::{ParserConfig, XmlEvent};
+use crate::entities_orm::saml_request::{self, Entity as SamlRequestEntity, Column as SamlRequestColumn, ActiveModel as SamlRequestActiveModel};
+
+const NS_PROTOCOL: &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+const NS_ASSERTION: &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+const NS_DSIG: &str = "http://www.w3.org/2000/09/xmldsig#";
+
+const STATUS_SUCCESS: &str = "urn:oasis:names:tc:SAML:2.0:status:Success";
+const CONFIRMATION_BEARER: &str = "urn:oasis:names:tc:SAML:2.0:cm:bearer";
+const BINDING_HTTP_POST: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST";
+
+const TRANSFORM_ENVELOPED: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+const C14N_EXCLUSIVE: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+
+// Durée laissée à l'utilisateur pour s'authentifier auprès de l'IdP
+const REQUEST_TTL_SECONDS: i64 = 600;
+/// Cookie qui lie l'AuthnRequest au navigateur qui a lancé la connexion.
+pub const REQUEST_COOKIE: &str = "hypercyber_saml_request";
+const REQUEST_COOKIE_PATH: &str = "/api/auth/saml";
+// Tolérance d'horloge sur NotBefore / NotOnOrAfter
+const CLOCK_SKEW_SECONDS: i64 = 60;
+
+// Seuls RSA et les empreintes SHA-2 sont acceptés (SHA-1 est refusé)
+fn signature_digest(algorithm: &str) -> Option<MessageDigest> {
+    match algorithm {
+        "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256" => Some(MessageDigest::sha256()),
+        "http://www.w3.org/2001/04/xmldsig-more#rsa-sha384" => Some(MessageDigest::sha384()),
+        "http://www.w3.org/2001/04/xmldsig-more#rsa-sha512" => Some(MessageDigest::sha512()),
+        _ => None,
+    }
+}
+
+fn reference_digest(algorithm: &str) -> Option<MessageDigest> {
+    match algorithm {
+        "http://www.w3.org/2001/04/xmlenc#sha256" => Some(MessageDigest::sha256()),
+        "http://www.w3.org/2001/04/xmldsig-more#sha384" => Some(MessageDigest::sha384()),
+        "http://www.w3.org/2001/04/xmlenc#sha512" => Some(MessageDigest::sha512()),
+        _ => None,
+    }
+}
+
+// Arbre XML minimal qui conserve préfixes et espaces de noms, nécessaires à
+// la canonicalisation exclusive (xml-exc-c14n) des éléments signés.
+
+#[derive(Debug)]
+enum XmlNode {
+    Element(XmlElement),
+    Text(String),
+    ProcessingInstruction(String, Option<String>),
+}
+
+#[derive(Debug)]
+struct XmlElement {
+    prefix: Option<String>,
+    namespace: Option<String>,
+    name: String,
+    attributes: Vec<OwnedAttribute>,
+    // Espaces de noms visibles sur l'élément ("" = espace par défaut)
+    namespaces: BTreeMap<String, String>,
+    children: Vec<XmlNode>,
+}
+
+impl XmlElement {
+    fn is(&self, namespace: &str, name: &str) -> bool {
+        self.namespace.as_deref() == Some(namespace) && self.name == name
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &XmlElement> {
+        self.children.iter().filter_map(|c| match c {
+            XmlNode::Element(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    fn children_named<'a>(&'a self, namespace: &'a str, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.elements().filter(move |e| e.is(namespace, name))
+    }
+
+    fn child(&self, namespace: &str, name: &str) -> Option<&XmlElement> {
+        self.elements().find(|e| e.is(namespace, name))
+    }
+
+    fn required_child(&self, namespace: &str, name: &str) -> Result<&XmlElement> {
+        self.child(namespace, name)
+            .ok_or_else(|| anyhow::anyhow!("Missing {} element in {}", name, self.name))
+    }
+
+    // Attribut sans espace de noms (ID, Algorithm, URI...)
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|a| a.name.namespace.is_none() && a.name.local_name == name)
+            .map(|a| a.value.as_str())
+    }
+
+    fn text(&self) -> String {
+        self.children.iter().filter_map(|c| match c {
+            XmlNode::Text(t) => Some(t.as_str()),
+            _ => None,
+        }).collect()
+    }
+
+    fn collect_ids<'a>(&'a self, ids: &mut Vec<&'a str>) {
+        if let Some(id) = self.attr("ID") {
+            ids.push(id);
+        }
+        for child in self.elements() {
+            child.collect_ids(ids);
+        }
+    }
+}
+
+fn parse_xml(xml: &str) -> Result<XmlElement> {
+    // Les réponses SAML n'ont pas de DTD : on refuse toute déclaration (entités, XXE)
+    if xml.contains("<!DOCTYPE") || xml.contains("<!ENTITY") {
+        bail!("DTD declarations are not allowed");
+    }
+
+    let reader = ParserConfig::new()
+        .trim_whitespace(false)
+        .whitespace_to_characters(true)
+        .cdata_to_characters(true)
+        .ignore_comments(true)
+        .coalesce_characters(true)
+        .create_reader(xml.as_bytes());
+
+    let mut stack: Vec<XmlElement> = Vec::new();
+    let mut root = None;
+    for event in reader {
+        match event.context("Malformed XML")? {
+            XmlEvent::StartElement { name, attributes, namespace } => {
+                stack.push(XmlElement {
+                    prefix: name.prefix,
+                    namespace: name.namespace,
+                    name: name.local_name,
+                    attributes,
+                    namespaces: namespace.0.into_iter()
+                        .filter(|(prefix, _)| prefix != "xml" && prefix != "xmlns")
+                        .collect(),
+                    children: Vec::new(),
+                });
+            }
+            XmlEvent::EndElement { .. } => {
+                let element = stack.pop().context("Malformed XML")?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(XmlNode::Element(element)),
+                    None => root = Some(element),
+                }
+            }
+            XmlEvent::Characters(text) => {
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(XmlNode::Text(text));
+                }
+            }
+            XmlEvent::ProcessingInstruction { name, data } => {
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(XmlNode::ProcessingInstruction(name, data));
+                }
+            }
+            _ => {}
+        }
+    }
+    root.context("Empty XML document")
+}
+
+fn qualified_name(prefix: &Option<String>, name: &str) -> String {
+    match prefix {
+        Some(p) if !p.is_empty() => format!("{}:{}", p, name),
+        _ => name.to_string(),
+    }
+}
+
+fn escape_text(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('\r', "&#xD;")
+}
+
+fn escape_attribute(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('"', "&quot;")
+        .replace('\t', "&#x9;").replace('\n', "&#xA;").replace('\r', "&#xD;")
+}
+
+/// Canonicalisation exclusive (sans commentaires) de `element`, en omettant
+/// `excluded` (transformation enveloped-signature).
+fn canonicalize(element: &XmlElement, excluded: Option<&XmlElement>, inclusive_prefixes: &[String]) -> String {
+    let mut out = String::new();
+    write_canonical(element, excluded, inclusive_prefixes, &BTreeMap::new(), &mut out);
+    out
+}
+
+fn write_canonical(
+    element: &XmlElement,
+    excluded: Option<&XmlElement>,
+    inclusive_prefixes: &[String],
+    rendered: &BTreeMap<String, String>,
+    out: &mut String,
+) {
+    // Préfixes visiblement utilisés par l'élément et ses attributs
+    let mut utilized = BTreeSet::new();
+    utilized.insert(element.prefix.clone().unwrap_or_default());
+    for attribute in &element.attributes {
+        if let Some(prefix) = &attribute.name.prefix
+            && prefix != "xml"
+        {
+            utilized.insert(prefix.clone());
+        }
+    }
+    for prefix in inclusive_prefixes {
+        let prefix = if prefix == "#default" { "" } else { prefix.as_str() };
+        if element.namespaces.contains_key(prefix) {
+            utilized.insert(prefix.to_string());
+        }
+    }
+
+    let name = qualified_name(&element.prefix, &element.name);
+    out.push('<');
+    out.push_str(&name);
+
+    let mut in_scope = rendered.clone();
+    for prefix in utilized {
+        let uri = element.namespaces.get(&prefix).cloned().unwrap_or_default();
+        let previous = rendered.get(&prefix).cloned().unwrap_or_default();
+        if uri != previous && (prefix.is_empty() || !uri.is_empty()) {
+            if prefix.is_empty() {
+                out.push_str(&format!(" xmlns=\"{}\"", escape_attribute(&uri)));
+            } else {
+                out.push_str(&format!(" xmlns:{}=\"{}\"", prefix, escape_attribute(&uri)));
+            }
+        }
+        in_scope.insert(prefix, uri);
+    }
+
+    let mut attributes: Vec<&OwnedAttribute> = element.attributes.iter().collect();
+    attributes.sort_by(|a, b| {
+        (a.name.namespace.as_deref().unwrap_or(""), &a.name.local_name)
+            .cmp(&(b.name.namespace.as_deref().unwrap_or(""), &b.name.local_name))
+    });
+    for attribute in attributes {
+        out.push_str(&format!(
+            " {}=\"{}\"",
+            qualified_name(&attribute.name.prefix, &attribute.name.local_name),
+            escape_attribute(&attribute.value)
+        ));
+    }
+    out.push('>');
+
+    for child in &element.children {
+        match child {
+            XmlNode::Element(e) => {
+                if excluded.is_some_and(|x| std::ptr::eq(x, e)) {
+                    continue;
+                }
+                write_canonical(e, excluded, inclusive_prefixes, &in_scope, out);
+            }
+            XmlNode::Text(t) => out.push_str(&escape_text(t)),
+            XmlNode::ProcessingInstruction(target, data) => match data {
+                Some(data) => out.push_str(&format!("<?{} {}?>", target, data)),
+                None => out.push_str(&format!("<?{}?>", target)),
+            },
+        }
+    }
+
+    out.push_str(&format!("</{}>", name));
+}
+
+fn inclusive_prefixes(transform: &XmlElement) -> Vec<String> {
+    transform.child(C14N_EXCLUSIVE, "InclusiveNamespaces")
+        .and_then(|e| e.attr("PrefixList"))
+        .map(|list| list.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn decode_base64_text(element: &XmlElement) -> Result<Vec<u8>> {
+    let text: String = element.text().chars().filter(|c| !c.is_whitespace()).collect();
+    STANDARD.decode(text).context("Invalid base64 value")
+}
+
+/// Vérifie la signature enveloppée de `signed` avec le certificat de l'IdP.
+/// La référence doit désigner l'élément lui-même (`URI="#ID"`).
+fn verify_enveloped_signature(signed: &XmlElement, certificate: &X509) -> Result<()> {
+    let signature = signed.required_child(NS_DSIG, "Signature")?;
+    let signed_info = signature.required_child(NS_DSIG, "SignedInfo")?;
+
+    let c14n_method = signed_info.required_child(NS_DSIG, "CanonicalizationMethod")?;
+    if c14n_method.attr("Algorithm") != Some(C14N_EXCLUSIVE) {
+        bail!("Unsupported canonicalization method");
+    }
+    let signature_method = signed_info.required_child(NS_DSIG, "SignatureMethod")?
+        .attr("Algorithm")
+        .and_then(signature_digest)
+        .context("Unsupported signature method")?;
+
+    let mut references = signed_info.children_named(NS_DSIG, "Reference");
+    let reference = references.next().context("Missing signature reference")?;
+    if references.next().is_some() {
+        bail!("Multiple signature references are not supported");
+    }
+    let id = signed.attr("ID").context("Signed element has no ID")?;
+    if reference.attr("URI") != Some(format!("#{}", id).as_str()) {
+        bail!("Signature does not reference the signed element");
+    }
+
+    let mut reference_prefixes = Vec::new();
+    let mut canonicalized = false;
+    if let Some(transforms) = reference.child(NS_DSIG, "Transforms") {
+        for transform in transforms.children_named(NS_DSIG, "Transform") {
+            match transform.attr("Algorithm") {
+                Some(TRANSFORM_ENVELOPED) => {}
+                Some(C14N_EXCLUSIVE) => {
+                    reference_prefixes = inclusive_prefixes(transform);
+                    canonicalized = true;
+                }
+                other => bail!("Unsupported signature transform {:?}", other),
+            }
+        }
+    }
+    if !canonicalized {
+        bail!("Signature reference must use exclusive canonicalization");
+    }
+
+    let digest_method = reference.required_child(NS_DSIG, "DigestMethod")?
+        .attr("Algorithm")
+        .and_then(reference_digest)
+        .context("Unsupported digest method")?;
+    let expected_digest = decode_base64_text(reference.required_child(NS_DSIG, "DigestValue")?)?;
+    let digest = hash(digest_method, canonicalize(signed, Some(signature), &reference_prefixes).as_bytes())?;
+    if digest.len() != expected_digest.len() || !openssl::memcmp::eq(&digest, &expected_digest) {
+        bail!("Signed content digest mismatch");
+    }
+
+    let signature_value = decode_base64_text(signature.required_child(NS_DSIG, "SignatureValue")?)?;
+    let public_key = certificate.public_key()?;
+    let mut verifier = Verifier::new(signature_method, &public_key)?;
+    verifier.update(canonicalize(signed_info, None, &inclusive_prefixes(c14n_method)).as_bytes())?;
+    if !verifier.verify(&signature_value)? {
+        bail!("Invalid signature");
+    }
+    Ok(())
+}
+
+fn parse_instant(value: Option<&str>) -> Result<Option<DateTime<Utc>>> {
+    value
+        .map(|v| DateTime::parse_from_rfc3339(v).map(|d| d.with_timezone(&Utc)))
+        .transpose()
+        .context("Invalid SAML timestamp")
+}
+
+/// Réponse SAML décodée, pas encore vérifiée.
+pub struct SamlResponse {
+    root: XmlElement,
+}
+
+/// Attendus côté SP pour valider une réponse.
+pub struct SamlExpectations<'a> {
+    pub request_id: &'a str,
+    pub idp_issuer: &'a str,
+    pub idp_certificate: &'a str,
+    pub sp_entity_id: &'a str,
+    pub acs_url: &'a str,
+}
+
+/// Contenu d'une assertion dont la signature et les conditions ont été vérifiées.
+#[derive(Debug)]
+pub struct SamlAssertion {
+    pub issuer: String,
+    pub name_id: String,
+    pub attributes: HashMap<String, Vec<String>>,
+    // AuthnContextClassRef de l'AuthnStatement : méthode d'authentification chez l'IdP
+    pub authn_context_class_ref: Option<String>,
+}
+
+impl SamlAssertion {
+    pub fn first_attribute(&self, names: &[&str]) -> Option<String> {
+        names.iter()
+            .filter_map(|n| self.attributes.get(*n))
+            .flat_map(|values| values.iter())
+            .find(|v| !v.trim().is_empty())
+            .cloned()
+    }
+}
+
+impl SamlResponse {
+    /// Décode le paramètre `SAMLResponse` du binding HTTP-POST.
+    pub fn decode(encoded: &str) -> Result<Self> {
+        let compact: String = encoded.chars().filter(|c| !c.is_whitespace()).collect();
+        let bytes = STANDARD.decode(compact).context("Invalid SAMLResponse encoding")?;
+        let xml = String::from_utf8(bytes).context("Invalid SAMLResponse encoding")?;
+        let root = parse_xml(&xml)?;
+        if !root.is(NS_PROTOCOL, "Response") {
+            bail!("Not a SAML Response");
+        }
+        Ok(Self { root })
+    }
+
+    /// Identifiant de l'AuthnRequest d'origine (non authentifié à ce stade).
+    pub fn in_response_to(&self) -> Option<&str> {
+        self.root.attr("InResponseTo")
+    }
+
+    /// Vérifie signature, émetteur, destinataire, audience et validité
+    /// temporelle, puis renvoie le sujet et les attributs de l'assertion.
+    pub fn validate(&self, expected: &SamlExpectations) -> Result<SamlAssertion> {
+        let root = &self.root;
+        let now = Utc::now();
+        let skew = Duration::seconds(CLOCK_SKEW_SECONDS);
+
+        let status = root.required_child(NS_PROTOCOL, "Status")?
+            .required_child(NS_PROTOCOL, "StatusCode")?
+            .attr("Value")
+            .unwrap_or_default();
+        if status != STATUS_SUCCESS {
+            bail!("SAML authentication failed with status {}", status);
+        }
+        if self.in_response_to() != Some(expected.request_id) {
+            bail!("InResponseTo does not match the authentication request");
+        }
+        if let Some(destination) = root.attr("Destination")
+            && destination != expected.acs_url
+        {
+            bail!("Unexpected response destination {}", destination);
+        }
+
+        // Un identifiant dupliqué permettrait de faire vérifier un autre élément que celui lu
+        let mut ids = Vec::new();
+        root.collect_ids(&mut ids);
+        let unique: BTreeSet<&str> = ids.iter().copied().collect();
+        if unique.len() != ids.len() {
+            bail!("Duplicate ID attributes in SAML response");
+        }
+
+        if root.child(NS_ASSERTION, "EncryptedAssertion").is_some() {
+            bail!("Encrypted assertions are not supported");
+        }
+        let mut assertions = root.children_named(NS_ASSERTION, "Assertion");
+        let assertion = assertions.next().context("SAML response has no assertion")?;
+        if assertions.next().is_some() {
+            bail!("SAML response has several assertions");
+        }
+
+        let certificate = X509::from_pem(expected.idp_certificate.as_bytes())
+            .context("Invalid identity provider certificate")?;
+        let response_signed = root.child(NS_DSIG, "Signature").is_some();
+        let assertion_signed = assertion.child(NS_DSIG, "Signature").is_some();
+        if !response_signed && !assertion_signed {
+            bail!("SAML response is not signed");
+        }
+        if response_signed {
+            verify_enveloped_signature(root, &certificate)?;
+        }
+        if assertion_signed {
+            verify_enveloped_signature(assertion, &certificate)?;
+        }
+
+        let issuer = assertion.required_child(NS_ASSERTION, "Issuer")?.text();
+        if issuer.trim() != expected.idp_issuer {
+            bail!("Unexpected assertion issuer {}", issuer);
+        }
+
+        let conditions = assertion.required_child(NS_ASSERTION, "Conditions")?;
+        if let Some(not_before) = parse_instant(conditions.attr("NotBefore"))?
+            && now + skew < not_before
+        {
+            bail!("SAML assertion is not yet valid");
+        }
+        if let Some(not_on_or_after) = parse_instant(conditions.attr("NotOnOrAfter"))?
+            && now - skew >= not_on_or_after
+        {
+            bail!("SAML assertion has expired");
+        }
+        let mut restrictions = conditions.children_named(NS_ASSERTION, "AudienceRestriction").peekable();
+        if restrictions.peek().is_none() {
+            bail!("SAML assertion has no audience restriction");
+        }
+        for restriction in restrictions {
+            if !restriction.children_named(NS_ASSERTION, "Audience").any(|a| a.text().trim() == expected.sp_entity_id) {
+                bail!("SAML assertion is not intended for this service provider");
+            }
+        }
+
+        let subject = assertion.required_child(NS_ASSERTION, "Subject")?;
+        let name_id = subject.required_child(NS_ASSERTION, "NameID")?.text().trim().to_string();
+        if name_id.is_empty() {
+            bail!("SAML assertion has an empty NameID");
+        }
+        let confirmed = subject.children_named(NS_ASSERTION, "SubjectConfirmation")
+            .filter(|c| c.attr("Method") == Some(CONFIRMATION_BEARER))
+            .filter_map(|c| c.child(NS_ASSERTION, "SubjectConfirmationData"))
+            .any(|data| {
+                let in_time = matches!(
+                    parse_instant(data.attr("NotOnOrAfter")),
+                    Ok(Some(limit)) if now - skew < limit
+                );
+                in_time
+                    && data.attr("Recipient") == Some(expected.acs_url)
+                    && data.attr("InResponseTo").is_none_or(|id| id == expected.request_id)
+            });
+        if !confirmed {
+            bail!("SAML subject confirmation failed");
+        }
+
+        let mut attributes: HashMap<String, Vec<String>> = HashMap::new();
+        for statement in assertion.children_named(NS_ASSERTION, "AttributeStatement") {
+            for attribute in statement.children_named(NS_ASSERTION, "Attribute") {
+                let Some(name) = attribute.attr("Name") else { continue };
+                attributes.entry(name.to_string()).or_default().extend(
+                    attribute.children_named(NS_ASSERTION, "AttributeValue").map(|v| v.text().trim().to_string())
+                );
+            }
+        }
+
+        let authn_context_class_ref = assertion.children_named(NS_ASSERTION, "AuthnStatement")
+            .filter_map(|statement| statement.child(NS_ASSERTION, "AuthnContext"))
+            .filter_map(|context| context.child(NS_ASSERTION, "AuthnContextClassRef"))
+            .map(|class_ref| class_ref.text().trim().to_string())
+            .find(|class_ref| !class_ref.is_empty());
+
+        Ok(SamlAssertion {
+            issuer: issuer.trim().to_string(),
+            name_id,
+            attributes,
+            authn_context_class_ref,
+        })
+    }
+}
+
+/// Enregistre une AuthnRequest et renvoie son identifiant.
+pub async fn create_request(db: &DatabaseConnection, provider_id: Uuid) -> Result<String, DbErr> {
+    // Purge opportuniste des requêtes abandonnées
+    SamlRequestEntity::delete_many()
+        .filter(SamlRequestColumn::ExpiresAt.lt(Utc::now()))
+        .exec(db)
+        .await?;
+
+    // Un identifiant SAML (xs:ID) ne peut pas commencer par un chiffre
+    let mut random = [0u8; 20];
+    OsRng.fill_bytes(&mut random);
+    let request_id = format!("_{}", random.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+
+    let now = Utc::now();
+    let request = SamlRequestActiveModel {
+        id: Set(Uuid::new_v4()),
+        request_id: Set(request_id.clone()),
+        provider_id: Set(provider_id),
+        expires_at: Set(now + Duration::seconds(REQUEST_TTL_SECONDS)),
+        created_at: Set(now),
+    };
+    SamlRequestEntity::insert(request).exec(db).await?;
+    Ok(request_id)
+}
+
+fn request_binding(request_id: &str) -> String {
+    hash(MessageDigest::sha256(), request_id.as_bytes())
+        .map(|digest| digest.iter().map(|b| format!("{:02x}", b)).collect())
+        .unwrap_or_default()
+}
+
+// L'IdP renvoie la réponse par un POST intersite : un cookie `SameSite=Lax`
+// ne l'accompagnerait pas
+fn request_cookie_builder(value: String) -> Cookie<'static> {
+    Cookie::build(REQUEST_COOKIE, value)
+        .path(REQUEST_COOKIE_PATH)
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::None)
+        .finish()
+}
+
+/// Cookie posé avec la redirection vers l'IdP : il porte l'empreinte de
+/// l'identifiant de l'AuthnRequest, que la réponse reprend dans `InResponseTo`.
+/// Une réponse postée depuis un autre navigateur (CSRF de connexion) est refusée.
+pub fn request_cookie(request_id: &str) -> Cookie<'static> {
+    let mut cookie = request_cookie_builder(request_binding(request_id));
+    cookie.set_max_age(CookieDuration::seconds(REQUEST_TTL_SECONDS));
+    cookie
+}
+
+/// Cookie expiré qui remplace celui de la requête une fois la réponse traitée.
+pub fn clear_request_cookie() -> Cookie<'static> {
+    let mut cookie = request_cookie_builder(String::new());
+    cookie.make_removal();
+    cookie
+}
+
+/// Vrai si le navigateur présente le cookie posé pour cette AuthnRequest.
+pub fn request_matches_cookie(req: &HttpRequest, request_id: &str) -> bool {
+    let expected = request_binding(request_id);
+    req.cookie(REQUEST_COOKIE).is_some_and(|cookie| {
+        let value = cookie.value().as_bytes();
+        !expected.is_empty() && value.len() == expected.len() && openssl::memcmp::eq(value, expected.as_bytes())
+    })
+}
+
+/// Récupère et supprime la requête `request_id` (usage unique, non expirée).
+pub async fn take_request(db: &DatabaseConnection, request_id: &str) -> Result<Option<saml_request::Model>, DbErr> {
+    let stored = SamlRequestEntity::find()
+        .filter(SamlRequestColumn::RequestId.eq(request_id))
+        .one(db)
+        .await?;
+
+    let Some(stored) = stored else {
+        return Ok(None);
+    };
+    let result = SamlRequestEntity::delete_by_id(stored.id).exec(db).await?;
+    if result.rows_affected != 1 || stored.expires_at <= Utc::now() {
+        return Ok(None);
+    }
+    Ok(Some(stored))
+}
+
+fn escape_xml(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// URL de redirection vers l'IdP portant l'AuthnRequest (binding HTTP-Redirect).
+pub fn build_authn_request_url(sso_url: &str, request_id: &str, sp_entity_id: &str, acs_url: &str) -> Result<String> {
+    let request = format!(
+        concat!(
+            r#"<samlp:AuthnRequest xmlns:samlp="{}" xmlns:saml="{}" ID="{}" Version="2.0" IssueInstant="{}" "#,
+            r#"Destination="{}" AssertionConsumerServiceURL="{}" ProtocolBinding="{}">"#,
+            r#"<saml:Issuer>{}</saml:Issuer><samlp:NameIDPolicy AllowCreate="true"/></samlp:AuthnRequest>"#
+        ),
+        NS_PROTOCOL,
+        NS_ASSERTION,
+        request_id,
+        Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+        escape_xml(sso_url),
+        escape_xml(acs_url),
+        BINDING_HTTP_POST,
+        escape_xml(sp_entity_id),
+    );
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(request.as_bytes())?;
+    let deflated = encoder.finish()?;
+
+    let mut url = reqwest::Url::parse(sso_url).context("Invalid SAML SSO URL")?;
+    url.query_pairs_mut().append_pair("SAMLRequest", &STANDARD.encode(deflated));
+    Ok(url.into())
+}
+
+/// Métadonnées du fournisseur de services, à importer dans l'IdP.
+pub fn sp_metadata(sp_entity_id: &str, acs_url: &str) -> String {
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            r#"<md:EntityDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" entityID="{}">"#,
+            r#"<md:SPSSODescriptor AuthnRequestsSigned="false" WantAssertionsSigned="true" protocolSupportEnumeration="{}">"#,
+            r#"<md:NameIDFormat>urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress</md:NameIDFormat>"#,
+            r#"<md:AssertionConsumerService Binding="{}" Location="{}" index="0" isDefault="true"/>"#,
+            r#"</md:SPSSODescriptor></md:EntityDescriptor>"#
+        ),
+        escape_xml(sp_entity_id),
+        NS_PROTOCOL,
+        BINDING_HTTP_POST,
+        escape_xml(acs_url),
+    )
+}
+
+/// Vérifie qu'un certificat PEM de l'IdP est lisible.
+pub fn is_valid_certificate(pem: &str) -> bool {
+    X509::from_pem(pem.as_bytes()).is_ok()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+    use actix_web::test::TestRequest;
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::BigNum;
+    use openssl::pkey::{PKey, Private};
+    use openssl::rsa::Rsa;
+    use openssl::sign::Signer;
+    use openssl::x509::{X509Builder, X509NameBuilder};
+    use super::*;
+
+    const REQUEST_ID: &str = "_request";
+    const IDP: &str = "https://idp.test";
+    const SP: &str = "https://sp.test";
+    const ACS: &str = "https://sp.test/acs";
+
+    // Clé et certificat auto-signé de l'IdP de test, générés une seule fois
+    fn idp_key() -> &'static (PKey<Private>, String) {
+        static KEY: OnceLock<(PKey<Private>, String)> = OnceLock::new();
+        KEY.get_or_init(|| {
+            let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+            let mut name = X509NameBuilder::new().unwrap();
+            name.append_entry_by_text("CN", "idp.test").unwrap();
+            let name = name.build();
+            let mut builder = X509Builder::new().unwrap();
+            builder.set_version(2).unwrap();
+            builder.set_serial_number(&BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap()).unwrap();
+            builder.set_subject_name(&name).unwrap();
+            builder.set_issuer_name(&name).unwrap();
+            builder.set_pubkey(&key).unwrap();
+            builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+            builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+            builder.sign(&key, MessageDigest::sha256()).unwrap();
+            let pem = String::from_utf8(builder.build().to_pem().unwrap()).unwrap();
+            (key, pem)
+        })
+    }
+
+    fn instant(offset: Duration) -> String {
+        (Utc::now() + offset).to_rfc3339()
+    }
+
+    fn assertion(id: &str, name_id: &str, audience: &str, not_on_or_after: &str) -> String {
+        format!(
+            r#"<saml:Assertion xmlns:saml="{NS_ASSERTION}" ID="{id}" Version="2.0" IssueInstant="{now}"><saml:Issuer>{IDP}</saml:Issuer><saml:Subject><saml:NameID>{name_id}</saml:NameID><saml:SubjectConfirmation Method="{CONFIRMATION_BEARER}"><saml:SubjectConfirmationData InResponseTo="{REQUEST_ID}" Recipient="{ACS}" NotOnOrAfter="{later}"/></saml:SubjectConfirmation></saml:Subject><saml:Conditions NotBefore="{earlier}" NotOnOrAfter="{not_on_or_after}"><saml:AudienceRestriction><saml:Audience>{audience}</saml:Audience></saml:AudienceRestriction></saml:Conditions><saml:AuthnStatement AuthnInstant="{now}"><saml:AuthnContext><saml:AuthnContextClassRef>urn:oasis:names:tc:SAML:2.0:ac:classes:PasswordProtectedTransport</saml:AuthnContextClassRef></saml:AuthnContext></saml:AuthnStatement><saml:AttributeStatement><saml:Attribute Name="email"><saml:AttributeValue>{name_id}</saml:AttributeValue></saml:Attribute></saml:AttributeStatement></saml:Assertion>"#,
+            now = instant(Duration::zero()),
+            earlier = instant(Duration::minutes(-5)),
+            later = instant(Duration::minutes(5)),
+        )
+    }
+
+    fn valid_assertion(id: &str) -> String {
+        assertion(id, "alice@example.com", SP, &instant(Duration::minutes(5)))
+    }
+
+    /// Signe `unsigned` (signature enveloppée référençant `#reference_id`) et
+    /// insère la signature juste après le premier `saml:Issuer`.
+    fn sign(unsigned: &str, reference_id: &str) -> String {
+        let digest = hash(MessageDigest::sha256(), canonicalize(&parse_xml(unsigned).unwrap(), None, &[]).as_bytes()).unwrap();
+        let signed_info = format!(
+            r##"<ds:SignedInfo xmlns:ds="{NS_DSIG}"><ds:CanonicalizationMethod Algorithm="{C14N_EXCLUSIVE}"/><ds:SignatureMethod Algorithm="http://www.w3.org/2001/04/xmldsig-more#rsa-sha256"/><ds:Reference URI="#{reference_id}"><ds:Transforms><ds:Transform Algorithm="{TRANSFORM_ENVELOPED}"/><ds:Transform Algorithm="{C14N_EXCLUSIVE}"/></ds:Transforms><ds:DigestMethod Algorithm="http://www.w3.org/2001/04/xmlenc#sha256"/><ds:DigestValue>{}</ds:DigestValue></ds:Reference></ds:SignedInfo>"##,
+            STANDARD.encode(digest),
+        );
+        let mut signer = Signer::new(MessageDigest::sha256(), &idp_key().0).unwrap();
+        signer.update(canonicalize(&parse_xml(&signed_info).unwrap(), None, &[]).as_bytes()).unwrap();
+        let signature = format!(
+            r#"<ds:Signature xmlns:ds="{NS_DSIG}">{}<ds:SignatureValue>{}</ds:SignatureValue></ds:Signature>"#,
+            signed_info.replacen(&format!(r#" xmlns:ds="{NS_DSIG}""#), "", 1),
+            STANDARD.encode(signer.sign_to_vec().unwrap()),
+        );
+        let end = unsigned.find("</saml:Issuer>").unwrap() + "</saml:Issuer>".len();
+        format!("{}{}{}", &unsigned[..end], signature, &unsigned[end..])
+    }
+
+    fn response_xml(body: &str) -> String {
+        format!(
+            r#"<samlp:Response xmlns:samlp="{NS_PROTOCOL}" xmlns:saml="{NS_ASSERTION}" ID="_response" Version="2.0" IssueInstant="{}" InResponseTo="{REQUEST_ID}" Destination="{ACS}"><saml:Issuer>{IDP}</saml:Issuer><samlp:Status><samlp:StatusCode Value="{STATUS_SUCCESS}"/></samlp:Status>{body}</samlp:Response>"#,
+            instant(Duration::zero()),
+        )
+    }
+
+    fn validate(xml: &str) -> Result<SamlAssertion> {
+        let certificate = &idp_key().1;
+        SamlResponse::decode(&STANDARD.encode(xml))?.validate(&SamlExpectations {
+            request_id: REQUEST_ID,
+            idp_issuer: IDP,
+            idp_certificate: certificate,
+            sp_entity_id: SP,
+            acs_url: ACS,
+        })
+    }
+
+    fn rejection(xml: &str) -> String {
+        format!("{:#}", validate(xml).expect_err("response must be rejected"))
+    }
+
+    #[test]
+    fn accepts_signed_assertion() {
+        let signed = sign(&valid_assertion("_assertion"), "_assertion");
+        let assertion = validate(&response_xml(&signed)).unwrap();
+        assert_eq!(assertion.issuer, IDP);
+        assert_eq!(assertion.name_id, "alice@example.com");
+        assert_eq!(assertion.first_attribute(&["mail", "email"]).as_deref(), Some("alice@example.com"));
+        assert_eq!(
+            assertion.authn_context_class_ref.as_deref(),
+            Some("urn:oasis:names:tc:SAML:2.0:ac:classes:PasswordProtectedTransport")
+        );
+    }
+
+    #[test]
+    fn accepts_signed_response() {
+        let signed = sign(&response_xml(&valid_assertion("_assertion")), "_response");
+        assert_eq!(validate(&signed).unwrap().name_id, "alice@example.com");
+    }
+
+    #[test]
+    fn rejects_tampered_assertion() {
+        let signed = sign(&valid_assertion("_assertion"), "_assertion")
+            .replace("<saml:NameID>alice@example.com", "<saml:NameID>admin@example.com");
+        assert!(rejection(&response_xml(&signed)).contains("Signed content digest mismatch"));
+    }
+
+    #[test]
+    fn rejects_tampered_signed_info() {
+        let signed = sign(&valid_assertion("_assertion"), "_assertion")
+            .replacen(&format!(r#"<ds:Transform Algorithm="{TRANSFORM_ENVELOPED}"/>"#), "", 1);
+        assert!(rejection(&response_xml(&signed)).contains("Invalid signature"));
+    }
+
+    #[test]
+    fn rejects_unsigned_response() {
+        assert!(rejection(&response_xml(&valid_assertion("_assertion"))).contains("SAML response is not signed"));
+    }
+
+    #[test]
+    fn rejects_second_assertion() {
+        let signed = sign(&valid_assertion("_assertion"), "_assertion");
+        let evil = assertion("_evil", "admin@example.com", SP, &instant(Duration::minutes(5)));
+        assert!(rejection(&response_xml(&format!("{signed}{evil}"))).contains("SAML response has several assertions"));
+        assert!(rejection(&response_xml(&format!("{evil}{signed}"))).contains("SAML response has several assertions"));
+    }
+
+    #[test]
+    fn rejects_assertion_wrapping_signed_assertion() {
+        // L'assertion signée est cachée dans une assertion non signée (XSW)
+        let signed = sign(&valid_assertion("_assertion"), "_assertion");
+        let wrapper = assertion("_evil", "admin@example.com", SP, &instant(Duration::minutes(5)))
+            .replace("<saml:Subject>", &format!("<saml:Advice>{signed}</saml:Advice><saml:Subject>"));
+        assert!(rejection(&response_xml(&wrapper)).contains("SAML response is not signed"));
+    }
+
+    #[test]
+    fn rejects_duplicate_ids() {
+        // L'original signé est déplacé dans les extensions, une copie modifiée garde son ID et sa signature
+        let signed = sign(&valid_assertion("_assertion"), "_assertion");
+        let evil = signed.replace("<saml:NameID>alice@example.com", "<saml:NameID>admin@example.com");
+        let body = format!("<samlp:Extensions>{signed}</samlp:Extensions>{evil}");
+        assert!(rejection(&response_xml(&body)).contains("Duplicate ID attributes in SAML response"));
+    }
+
+    #[test]
+    fn rejects_expired_assertion() {
+        let expired = assertion("_assertion", "alice@example.com", SP, &instant(Duration::minutes(-5)));
+        let signed = sign(&expired, "_assertion");
+        assert!(rejection(&response_xml(&signed)).contains("SAML assertion has expired"));
+    }
+
+    #[test]
+    fn rejects_wrong_audience() {
+        let foreign = assertion("_assertion", "alice@example.com", "https://other-sp.test", &instant(Duration::minutes(5)));
+        let signed = sign(&foreign, "_assertion");
+        assert!(rejection(&response_xml(&signed)).contains("SAML assertion is not intended for this service provider"));
+    }
+
+    #[test]
+    fn rejects_reference_to_another_element() {
+        let signed = sign(&valid_assertion("_assertion"), "_other");
+        assert!(rejection(&response_xml(&signed)).contains("Signature does not reference the signed element"));
+    }
+
+    #[test]
+    fn rejects_doctype() {
+        let signed = sign(&valid_assertion("_assertion"), "_assertion");
+        let xml = format!(r#"<!DOCTYPE samlp:Response [<!ENTITY name "admin@example.com">]>{}"#, response_xml(&signed));
+        assert!(rejection(&xml).contains("DTD declarations are not allowed"));
+    }
+
+    #[test]
+    fn request_cookie_binds_request_to_browser() {
+        let cookie = request_cookie("_request");
+        assert!(cookie.http_only().unwrap_or(false));
+        assert!(cookie.secure().unwrap_or(false));
+        assert_eq!(cookie.same_site(), Some(SameSite::None));
+        assert_eq!(cookie.path(), Some(REQUEST_COOKIE_PATH));
+
+        let request = TestRequest::default().cookie(cookie).to_http_request();
+        assert!(request_matches_cookie(&request, "_request"));
+        assert!(!request_matches_cookie(&request, "_other"));
+        assert!(!request_matches_cookie(&TestRequest::default().to_http_request(), "_request"));
+    }
+}
@@ -0,0 +1,349 @@
+use bcrypt::{hash, DEFAULT_COST};
+use chrono::Utc;
+use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, Set, IntoActiveModel, ActiveModelTrait};
+use serde_json::Value;
+use uuid::Uuid;
+use crate::auth::jwt::{AMR_FEDERATED, AMR_MFA};
+use crate::config::Config;
+use crate::errors::ApiError;
+use crate::entities::email_domains;
+use crate::entities::identity_providers::models::RoleMappingRule;
+use crate::entities_orm::email_domain::{Entity as EmailDomainEntity, Column as EmailDomainColumn};
+use crate::entities_orm::identity_provider::{self, Entity as IdentityProviderEntity, Column as IdentityProviderColumn};
+use crate::entities_orm::user::{Entity as UserEntity, Column as UserColumn, ActiveModel as UserActiveModel, Model as UserModel};
+use crate::entities_orm::user_entity::{Entity as UserEntityEntity, Column as UserEntityColumn, ActiveModel as UserEntityActiveModel};
+use crate::entities_orm::user_identity::{Entity as UserIdentityEntity, Column as UserIdentityColumn, ActiveModel as UserIdentityActiveModel};
+
+pub const PROTOCOL_OIDC: &str = "oidc";
+pub const PROTOCOL_SAML: &str = "saml";
+
+// Attributs SAML usuels (noms courts, claims ADFS / Azure AD, OID LDAP)
+pub const SAML_EMAIL_ATTRIBUTES: &[&str] = &[
+    "email", "mail",
+    "http://schemas.xmlsoap.org/ws/2005/05/identity/claims/emailaddress",
+    "urn:oid:0.9.2342.19200300.100.1.3",
+];
+pub const SAML_GIVEN_NAME_ATTRIBUTES: &[&str] = &[
+    "given_name", "givenName",
+    "http://schemas.xmlsoap.org/ws/2005/05/identity/claims/givenname",
+    "urn:oid:2.5.4.42",
+];
+pub const SAML_FAMILY_NAME_ATTRIBUTES: &[&str] = &[
+    "family_name", "sn", "surname",
+    "http://schemas.xmlsoap.org/ws/2005/05/identity/claims/surname",
+    "urn:oid:2.5.4.4",
+];
+
+/// Identité renvoyée par un fournisseur externe (OIDC ou SAML), après vérification.
+pub struct FederatedIdentity {
+    pub issuer: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub groups: Vec<String>,
+}
+
+pub fn email_domain(email: &str) -> Option<String> {
+    email.rsplit_once('@')
+        .map(|(_, domain)| domain.trim().to_lowercase())
+        .filter(|d| !d.is_empty())
+}
+
+pub fn provider_domains(provider: &identity_provider::Model) -> Vec<String> {
+    serde_json::from_value(provider.email_domains.clone()).unwrap_or_default()
+}
+
+pub fn provider_role_mapping(provider: &identity_provider::Model) -> Vec<RoleMappingRule> {
+    serde_json::from_value(provider.role_mapping.clone()).unwrap_or_default()
+}
+
+// Méthodes d'authentification définies par la RFC 8176
+const RFC8176_METHODS: &[&str] = &[
+    "face", "fpt", "geo", "hwk", "iris", "kba", "mca", "mfa", "otp", "pin", "pop",
+    "pwd", "rba", "retina", "sc", "sms", "swk", "tel", "user", "vbm", "wia",
+];
+
+/// Confiance accordée au second facteur vérifié par le fournisseur : celui de
+/// l'entité, sinon le fournisseur OIDC global configuré par l'environnement.
+pub fn mfa_trust(provider: Option<&identity_provider::Model>, config: &Config) -> (bool, Vec<String>) {
+    match provider {
+        Some(p) => (p.mfa_trusted, serde_json::from_value(p.mfa_acr_values.clone()).unwrap_or_default()),
+        None => (config.oidc_trust_mfa, config.oidc_mfa_acr_values.clone()),
+    }
+}
+
+/// Claim `amr` d'une session fédérée. Les méthodes déclarées par le fournisseur
+/// ne sont reprises que s'il est reconnu, et `mfa` seulement s'il l'affirme
+/// (amr `mfa` ou acr attendu) : sinon le second facteur reste à faire ici.
+pub fn federated_amr(idp_amr: &[String], acr: Option<&str>, trusted: bool, mfa_acr_values: &[String]) -> Vec<String> {
+    let mut amr = vec![AMR_FEDERATED.to_string()];
+    if !trusted {
+        return amr;
+    }
+    for method in idp_amr {
+        if RFC8176_METHODS.contains(&method.as_str()) && method != AMR_MFA && !amr.contains(method) {
+            amr.push(method.clone());
+        }
+    }
+    let asserted = idp_amr.iter().any(|m| m == AMR_MFA)
+        || acr.is_some_and(|acr| mfa_acr_values.iter().any(|v| v == acr));
+    if asserted {
+        amr.push(AMR_MFA.to_string());
+    }
+    amr
+}
+
+/// Valeurs d'un claim de groupes ou de rôles (chaîne ou tableau de chaînes).
+pub fn claim_values(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Array(items)) => items.iter()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Découverte du domaine d'origine : fournisseur actif qui revendique le
+/// domaine de l'adresse email, pour l'entité qui a vérifié ce domaine.
+pub async fn find_provider_for_email(
+    db: &DatabaseConnection,
+    email: &str,
+) -> Result<Option<identity_provider::Model>, ApiError> {
+    let Some(domain) = email_domain(email) else {
+        return Ok(None);
+    };
+    let Some(verified) = EmailDomainEntity::find()
+        .filter(EmailDomainColumn::Domain.eq(&domain))
+        .filter(EmailDomainColumn::VerifiedAt.is_not_null())
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let providers = IdentityProviderEntity::find()
+        .filter(IdentityProviderColumn::EntityId.eq(verified.entity_id))
+        .filter(IdentityProviderColumn::IsEnabled.eq(true))
+        .all(db)
+        .await?;
+    Ok(providers.into_iter().find(|p| provider_domains(p).contains(&domain)))
+}
+
+pub async fn find_enabled_provider(
+    db: &DatabaseConnection,
+    provider_id: Uuid,
+) -> Result<identity_provider::Model, ApiError> {
+    IdentityProviderEntity::find_by_id(provider_id)
+        .filter(IdentityProviderColumn::IsEnabled.eq(true))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Identity provider not found".to_string()))
+}
+
+// La première règle dont le groupe est présent l'emporte
+fn mapped_role(provider: &identity_provider::Model, groups: &[String]) -> Option<String> {
+    provider_role_mapping(provider)
+        .into_iter()
+        .find(|rule| groups.contains(&rule.group))
+        .map(|rule| rule.role)
+}
+
+async fn is_member(db: &DatabaseConnection, user_id: Uuid, entity_id: Uuid) -> Result<bool, ApiError> {
+    let membership = UserEntityEntity::find()
+        .filter(UserEntityColumn::UserId.eq(user_id))
+        .filter(UserEntityColumn::EntityId.eq(entity_id))
+        .one(db)
+        .await?;
+    Ok(membership.is_some())
+}
+
+/// Provisioning juste-à-temps dans `user_entities`. Le rôle issu des groupes
+/// de l'IdP fait foi à chaque connexion ; le rôle par défaut ne s'applique
+/// qu'à la création du rattachement.
+async fn provision_membership(
+    db: &DatabaseConnection,
+    provider: &identity_provider::Model,
+    user_id: Uuid,
+    groups: &[String],
+) -> Result<(), ApiError> {
+    let membership = UserEntityEntity::find()
+        .filter(UserEntityColumn::UserId.eq(user_id))
+        .filter(UserEntityColumn::EntityId.eq(provider.entity_id))
+        .one(db)
+        .await?;
+    let role = mapped_role(provider, groups);
+
+    match membership {
+        Some(membership) => {
+            if let Some(role) = role
+                && membership.role != role
+            {
+                let mut membership = membership.into_active_model();
+                membership.role = Set(role);
+                membership.update(db).await?;
+            }
+        }
+        None => {
+            let role = role.or_else(|| provider.default_role.clone())
+                .ok_or_else(|| ApiError::Forbidden("Identity provider did not grant access to this entity".to_string()))?;
+            let membership = UserEntityActiveModel {
+                id: Set(Uuid::new_v4()),
+                user_id: Set(user_id),
+                entity_id: Set(provider.entity_id),
+                role: Set(role),
+                created_at: Set(Utc::now()),
+            };
+            UserEntityEntity::insert(membership).exec(db).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Retrouve le compte lié à l'identité (émetteur, subject), sinon lie ou crée
+/// un compte à partir de l'email. Pour un fournisseur d'entité, l'utilisateur
+/// est ensuite rattaché à l'entité avec le rôle déduit de ses groupes.
+pub async fn resolve_user(
+    db: &DatabaseConnection,
+    identity: FederatedIdentity,
+    provider: Option<&identity_provider::Model>,
+) -> Result<UserModel, ApiError> {
+    let now = Utc::now();
+
+    let linked = UserIdentityEntity::find()
+        .filter(UserIdentityColumn::Issuer.eq(&identity.issuer))
+        .filter(UserIdentityColumn::Subject.eq(&identity.subject))
+        .one(db)
+        .await?;
+
+    if let Some(linked) = linked {
+        let user = UserEntity::find_by_id(linked.user_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| ApiError::Unauthorized("Account not found".to_string()))?;
+        if !user.is_active {
+            return Err(ApiError::Unauthorized("Account disabled".to_string()));
+        }
+        if let Some(provider) = provider {
+            provision_membership(db, provider, user.id, &identity.groups).await?;
+        }
+        let mut linked = linked.into_active_model();
+        linked.last_login_at = Set(Some(now));
+        linked.update(db).await?;
+        return Ok(user);
+    }
+
+    let email = identity.email
+        .as_deref()
+        .map(|e| e.trim().to_lowercase())
+        .filter(|e| !e.is_empty())
+        .ok_or_else(|| ApiError::Forbidden("Email not provided by identity provider".to_string()))?;
+
+    // Un fournisseur d'entité fait autorité sur les domaines qu'il revendique
+    // et que l'entité a vérifiés, et sur eux seuls
+    let vouched = match provider {
+        Some(p) => {
+            let domains = provider_domains(p);
+            let domain = email_domain(&email).filter(|d| domains.contains(d));
+            if !domains.is_empty() && domain.is_none() {
+                return Err(ApiError::Forbidden("Email domain not allowed for this identity provider".to_string()));
+            }
+            match domain {
+                Some(domain) => email_domains::is_verified(db, p.entity_id, &domain).await?,
+                None => false,
+            }
+        }
+        None => false,
+    };
+    if !identity.email_verified && !vouched {
+        return Err(ApiError::Forbidden("Email not verified by identity provider".to_string()));
+    }
+
+    let existing = UserEntity::find()
+        .filter(UserColumn::Email.eq(&email))
+        .one(db)
+        .await?;
+
+    let user = match existing {
+        Some(user) => {
+            let member = match provider {
+                Some(p) => is_member(db, user.id, p.entity_id).await?,
+                None => false,
+            };
+            // Pas de rattachement à un compte local dont l'email n'a jamais été prouvé,
+            // sauf membre de l'entité (provisionné par SCIM) sur un domaine revendiqué
+            if user.email_verified_at.is_none() && !(vouched && member) {
+                return Err(ApiError::Conflict(
+                    "A local account with this email exists but its email is not verified".to_string(),
+                ));
+            }
+            // Le fournisseur d'une entité ne peut pas s'approprier le compte d'un non-membre
+            if provider.is_some() && !member {
+                return Err(ApiError::Conflict(
+                    "An account with this email already exists; an entity administrator must add it before it can sign in with this identity provider".to_string(),
+                ));
+            }
+            if !user.is_active {
+                return Err(ApiError::Unauthorized("Account disabled".to_string()));
+            }
+            if user.email_verified_at.is_none() {
+                let mut user = user.into_active_model();
+                user.email_verified_at = Set(Some(now));
+                user.updated_at = Set(now);
+                user.update(db).await?
+            } else {
+                user
+            }
+        }
+        None => {
+            if let Some(p) = provider
+                && mapped_role(p, &identity.groups).is_none()
+                && p.default_role.is_none()
+            {
+                return Err(ApiError::Forbidden("Identity provider did not grant access to this entity".to_string()));
+            }
+
+            // Le fournisseur d'une entité n'atteste l'adresse que sur un domaine vérifié
+            let email_verified_at = (provider.is_none() || vouched).then_some(now);
+
+            // Mot de passe aléatoire inutilisable : le compte ne se connecte que via le fournisseur
+            let password_hash = hash(Uuid::new_v4().to_string(), DEFAULT_COST)
+                .map_err(ApiError::internal("Password hashing failed"))?;
+
+            let new_user = UserActiveModel {
+                id: Set(Uuid::new_v4()),
+                email: Set(email.clone()),
+                password_hash: Set(password_hash),
+                first_name: Set(identity.given_name),
+                last_name: Set(identity.family_name),
+                is_active: Set(true),
+                email_verified_at: Set(email_verified_at),
+                sessions_valid_after: Set(None),
+                created_at: Set(now),
+                updated_at: Set(now),
+            };
+
+            UserEntity::insert(new_user)
+                .exec_with_returning(db)
+                .await?
+        }
+    };
+
+    if let Some(provider) = provider {
+        provision_membership(db, provider, user.id, &identity.groups).await?;
+    }
+
+    let linked = UserIdentityActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user.id),
+        issuer: Set(identity.issuer),
+        subject: Set(identity.subject),
+        email: Set(Some(email)),
+        created_at: Set(now),
+        last_login_at: Set(Some(now)),
+    };
+    UserIdentityEntity::insert(linked).exec(db).await?;
+
+    Ok(user)
+}
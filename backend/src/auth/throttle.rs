@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use sea_orm::sea_query::{Expr, OnConflict};
+use crate::entities_orm::login_throttle::{Entity as LoginThrottleEntity, Column as LoginThrottleColumn, ActiveModel as LoginThrottleActiveModel};
+
+/// Paramètres du limiteur : délai exponentiel après chaque échec, puis
+/// verrouillage temporaire une fois le seuil atteint.
+#[derive(Clone, Debug)]
+pub struct ThrottlePolicy {
+    pub account_max_failures: u32,
+    pub ip_max_failures: u32,
+    pub backoff_base_seconds: i64,
+    pub backoff_max_seconds: i64,
+    pub lockout_seconds: i64,
+}
+
+impl ThrottlePolicy {
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str, default: T) -> T {
+            env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        }
+        Self {
+            account_max_failures: var("LOGIN_MAX_FAILURES", 5),
+            ip_max_failures: var("LOGIN_IP_MAX_FAILURES", 20),
+            backoff_base_seconds: var("LOGIN_BACKOFF_BASE_SECONDS", 1),
+            backoff_max_seconds: var("LOGIN_BACKOFF_MAX_SECONDS", 60),
+            lockout_seconds: var("LOGIN_LOCKOUT_SECONDS", 900),
+        }
+    }
+
+    /// Calcule la date jusqu'à laquelle la clé est bloquée après `failures` échecs.
+    fn blocked_until(&self, failures: u32, max_failures: u32, now: DateTime<Utc>) -> DateTime<Utc> {
+        if failures >= max_failures {
+            return now + Duration::seconds(self.lockout_seconds);
+        }
+        let exponent = failures.saturating_sub(1).min(30);
+        let delay = self.backoff_base_seconds
+            .saturating_mul(1_i64 << exponent)
+            .min(self.backoff_max_seconds);
+        now + Duration::seconds(delay)
+    }
+}
+
+/// Clé de limitation : une adresse IP ou un compte (email normalisé).
+#[derive(Clone, Debug)]
+pub enum ThrottleKey {
+    Ip(String),
+    Account(String),
+}
+
+impl ThrottleKey {
+    pub fn account(email: &str) -> Self {
+        ThrottleKey::Account(email.trim().to_lowercase())
+    }
+
+    fn as_key(&self) -> String {
+        match self {
+            ThrottleKey::Ip(ip) => format!("ip:{}", ip),
+            ThrottleKey::Account(email) => format!("account:{}", email),
+        }
+    }
+
+    fn max_failures(&self, policy: &ThrottlePolicy) -> u32 {
+        match self {
+            ThrottleKey::Ip(_) => policy.ip_max_failures,
+            ThrottleKey::Account(_) => policy.account_max_failures,
+        }
+    }
+}
+
+/// Limiteur de tentatives de connexion. Deux implémentations : en mémoire
+/// (une seule instance du serveur) et PostgreSQL (partagé entre instances).
+///
+/// Chaque tentative est comptée avant la vérification du secret : des
+/// requêtes simultanées ne peuvent donc pas toutes passer le contrôle.
+#[async_trait]
+pub trait LoginThrottle: Send + Sync {
+    /// Comptabilise une tentative et renvoie le nombre de secondes à attendre
+    /// si la clé est bloquée ou si la tentative dépasse le seuil.
+    async fn record_attempt(&self, key: &ThrottleKey) -> anyhow::Result<Option<i64>>;
+
+    /// Applique le délai correspondant aux tentatives comptées après un échec.
+    async fn record_failure(&self, key: &ThrottleKey) -> anyhow::Result<()>;
+
+    /// La tentative a abouti : elle cesse de compter contre la clé.
+    async fn release(&self, key: &ThrottleKey) -> anyhow::Result<()>;
+
+    async fn reset(&self, key: &ThrottleKey) -> anyhow::Result<()>;
+}
+
+struct AttemptState {
+    failures: u32,
+    last_failure_at: DateTime<Utc>,
+    blocked_until: Option<DateTime<Utc>>,
+}
+
+// Délai restant (en secondes) si la clé est encore bloquée
+fn remaining(blocked_until: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Option<i64> {
+    blocked_until
+        .filter(|until| *until > now)
+        .map(|until| (until - now).num_seconds().max(1))
+}
+
+// Les échecs plus anciens que la fenêtre de verrouillage sont oubliés
+fn is_expired(policy: &ThrottlePolicy, last_failure_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    now - last_failure_at > Duration::seconds(policy.lockout_seconds)
+}
+
+pub struct InMemoryLoginThrottle {
+    policy: ThrottlePolicy,
+    attempts: Mutex<HashMap<String, AttemptState>>,
+}
+
+impl InMemoryLoginThrottle {
+    pub fn new(policy: ThrottlePolicy) -> Self {
+        Self { policy, attempts: Mutex::new(HashMap::new()) }
+    }
+
+    fn lock(&self) -> anyhow::Result<std::sync::MutexGuard<'_, HashMap<String, AttemptState>>> {
+        self.attempts.lock().map_err(|_| anyhow::anyhow!("throttle lock poisoned"))
+    }
+}
+
+#[async_trait]
+impl LoginThrottle for InMemoryLoginThrottle {
+    async fn record_attempt(&self, key: &ThrottleKey) -> anyhow::Result<Option<i64>> {
+        let now = Utc::now();
+        let mut attempts = self.lock()?;
+        // Purge opportuniste des entrées expirées pour borner la mémoire
+        attempts.retain(|_, state| !is_expired(&self.policy, state.last_failure_at, now));
+
+        let state = attempts.entry(key.as_key()).or_insert(AttemptState {
+            failures: 0,
+            last_failure_at: now,
+            blocked_until: None,
+        });
+        if let Some(retry_after) = remaining(state.blocked_until, now) {
+            return Ok(Some(retry_after));
+        }
+        state.failures += 1;
+        state.last_failure_at = now;
+        if state.failures > key.max_failures(&self.policy) {
+            state.blocked_until = Some(self.policy.blocked_until(state.failures, key.max_failures(&self.policy), now));
+            return Ok(remaining(state.blocked_until, now));
+        }
+        Ok(None)
+    }
+
+    async fn record_failure(&self, key: &ThrottleKey) -> anyhow::Result<()> {
+        let now = Utc::now();
+        let mut attempts = self.lock()?;
+        if let Some(state) = attempts.get_mut(&key.as_key()) {
+            let until = self.policy.blocked_until(state.failures, key.max_failures(&self.policy), now);
+            state.blocked_until = state.blocked_until.max(Some(until));
+        }
+        Ok(())
+    }
+
+    async fn release(&self, key: &ThrottleKey) -> anyhow::Result<()> {
+        let mut attempts = self.lock()?;
+        if let Some(state) = attempts.get_mut(&key.as_key()) {
+            state.failures = state.failures.saturating_sub(1);
+        }
+        Ok(())
+    }
+
+    async fn reset(&self, key: &ThrottleKey) -> anyhow::Result<()> {
+        self.lock()?.remove(&key.as_key());
+        Ok(())
+    }
+}
+
+pub struct PostgresLoginThrottle {
+    policy: ThrottlePolicy,
+    db: DatabaseConnection,
+}
+
+impl PostgresLoginThrottle {
+    pub fn new(policy: ThrottlePolicy, db: DatabaseConnection) -> Self {
+        Self { policy, db }
+    }
+
+    // Le blocage ne fait que s'allonger, quel que soit l'ordre des requêtes concurrentes
+    async fn block(&self, key: &ThrottleKey, until: DateTime<Utc>) -> anyhow::Result<()> {
+        LoginThrottleEntity::update_many()
+            .col_expr(LoginThrottleColumn::BlockedUntil, Expr::value(until))
+            .filter(LoginThrottleColumn::Key.eq(key.as_key()))
+            .filter(
+                Condition::any()
+                    .add(LoginThrottleColumn::BlockedUntil.is_null())
+                    .add(LoginThrottleColumn::BlockedUntil.lt(until)),
+            )
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LoginThrottle for PostgresLoginThrottle {
+    async fn record_attempt(&self, key: &ThrottleKey) -> anyhow::Result<Option<i64>> {
+        let now = Utc::now();
+        let window_start = now - Duration::seconds(self.policy.lockout_seconds);
+        let failures = Expr::col((LoginThrottleEntity, LoginThrottleColumn::Failures));
+        let last_failure_at = Expr::col((LoginThrottleEntity, LoginThrottleColumn::LastFailureAt));
+        let blocked = Expr::col((LoginThrottleEntity, LoginThrottleColumn::BlockedUntil)).gt(now);
+
+        // Incrément atomique : une clé bloquée n'est pas incrémentée, une
+        // fenêtre expirée repart de un
+        let state = LoginThrottleActiveModel {
+            key: Set(key.as_key()),
+            failures: Set(1),
+            last_failure_at: Set(now),
+            blocked_until: Set(None),
+        };
+        let state = LoginThrottleEntity::insert(state)
+            .on_conflict(
+                OnConflict::column(LoginThrottleColumn::Key)
+                    .value(
+                        LoginThrottleColumn::Failures,
+                        Expr::case(blocked.clone(), failures.clone())
+                            .case(last_failure_at.clone().lt(window_start), 1)
+                            .finally(failures.add(1)),
+                    )
+                    .value(
+                        LoginThrottleColumn::LastFailureAt,
+                        Expr::case(blocked, last_failure_at).finally(now),
+                    )
+                    .to_owned(),
+            )
+            .exec_with_returning(&self.db)
+            .await?;
+
+        if let Some(retry_after) = remaining(state.blocked_until, now) {
+            return Ok(Some(retry_after));
+        }
+        let failures = state.failures.max(0) as u32;
+        if failures > key.max_failures(&self.policy) {
+            let until = self.policy.blocked_until(failures, key.max_failures(&self.policy), now);
+            self.block(key, until).await?;
+            return Ok(remaining(Some(until), now));
+        }
+        Ok(None)
+    }
+
+    async fn record_failure(&self, key: &ThrottleKey) -> anyhow::Result<()> {
+        let Some(state) = LoginThrottleEntity::find_by_id(key.as_key()).one(&self.db).await? else {
+            return Ok(());
+        };
+        let failures = state.failures.max(0) as u32;
+        let until = self.policy.blocked_until(failures, key.max_failures(&self.policy), Utc::now());
+        self.block(key, until).await
+    }
+
+    async fn release(&self, key: &ThrottleKey) -> anyhow::Result<()> {
+        LoginThrottleEntity::update_many()
+            .col_expr(LoginThrottleColumn::Failures, Expr::col(LoginThrottleColumn::Failures).sub(1))
+            .filter(LoginThrottleColumn::Key.eq(key.as_key()))
+            .filter(LoginThrottleColumn::Failures.gt(0))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn reset(&self, key: &ThrottleKey) -> anyhow::Result<()> {
+        LoginThrottleEntity::delete_by_id(key.as_key()).exec(&self.db).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(account_max_failures: u32, backoff_base_seconds: i64) -> ThrottlePolicy {
+        ThrottlePolicy {
+            account_max_failures,
+            ip_max_failures: 10,
+            backoff_base_seconds,
+            backoff_max_seconds: 60,
+            lockout_seconds: 900,
+        }
+    }
+
+    fn account() -> ThrottleKey {
+        ThrottleKey::account("Alice@Example.com ")
+    }
+
+    // Recule l'horloge d'une clé, pour simuler le temps écoulé
+    fn rewind(throttle: &InMemoryLoginThrottle, key: &ThrottleKey, seconds: i64) {
+        let mut attempts = throttle.lock().unwrap();
+        let state = attempts.get_mut(&key.as_key()).unwrap();
+        state.last_failure_at -= Duration::seconds(seconds);
+        state.blocked_until = state.blocked_until.map(|until| until - Duration::seconds(seconds));
+    }
+
+    async fn fail(throttle: &InMemoryLoginThrottle, key: &ThrottleKey) -> Option<i64> {
+        let blocked = throttle.record_attempt(key).await.unwrap();
+        if blocked.is_none() {
+            throttle.record_failure(key).await.unwrap();
+        }
+        blocked
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_its_cap_then_locks_out() {
+        let policy = policy(100, 1);
+        let now = Utc::now();
+        let delay = |failures| (policy.blocked_until(failures, 100, now) - now).num_seconds();
+
+        assert_eq!(delay(0), 1);
+        assert_eq!(delay(1), 1);
+        assert_eq!(delay(2), 2);
+        assert_eq!(delay(3), 4);
+        assert_eq!(delay(6), 32);
+        assert_eq!(delay(7), 60);
+        assert_eq!(delay(99), 60);
+        assert_eq!(delay(100), 900);
+        assert_eq!((policy.blocked_until(u32::MAX - 1, u32::MAX, now) - now).num_seconds(), 60);
+    }
+
+    #[tokio::test]
+    async fn locks_out_once_the_threshold_is_crossed() {
+        let throttle = InMemoryLoginThrottle::new(policy(3, 0));
+        let key = account();
+
+        for _ in 0..3 {
+            assert_eq!(fail(&throttle, &key).await, None);
+        }
+        let retry_after = throttle.record_attempt(&key).await.unwrap().unwrap();
+        assert!((899..=900).contains(&retry_after), "{}", retry_after);
+        // La même adresse, autrement écrite, reste bloquée
+        assert!(throttle.record_attempt(&ThrottleKey::account("alice@example.com")).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn failures_delay_the_next_attempt() {
+        let throttle = InMemoryLoginThrottle::new(policy(5, 1));
+        let key = account();
+
+        let blocked_for = |throttle: &InMemoryLoginThrottle| {
+            let until = throttle.lock().unwrap()[&key.as_key()].blocked_until.unwrap();
+            (until - Utc::now()).num_milliseconds()
+        };
+
+        assert_eq!(fail(&throttle, &key).await, None);
+        assert_eq!(throttle.record_attempt(&key).await.unwrap(), Some(1));
+        assert!((900..=1000).contains(&blocked_for(&throttle)));
+        rewind(&throttle, &key, 1);
+        assert_eq!(fail(&throttle, &key).await, None);
+        assert!(throttle.record_attempt(&key).await.unwrap().is_some());
+        assert!((1900..=2000).contains(&blocked_for(&throttle)));
+    }
+
+    #[tokio::test]
+    async fn failures_are_forgotten_after_the_lockout_window() {
+        let throttle = InMemoryLoginThrottle::new(policy(2, 0));
+        let key = account();
+
+        for _ in 0..2 {
+            fail(&throttle, &key).await;
+        }
+        assert!(throttle.record_attempt(&key).await.unwrap().is_some());
+
+        rewind(&throttle, &key, 901);
+        assert_eq!(throttle.record_attempt(&key).await.unwrap(), None);
+        assert_eq!(throttle.lock().unwrap()[&key.as_key()].failures, 1);
+    }
+
+    #[tokio::test]
+    async fn released_attempts_do_not_count() {
+        let throttle = InMemoryLoginThrottle::new(policy(2, 0));
+        let key = account();
+
+        for _ in 0..5 {
+            assert_eq!(throttle.record_attempt(&key).await.unwrap(), None);
+            throttle.release(&key).await.unwrap();
+        }
+        for _ in 0..2 {
+            assert_eq!(fail(&throttle, &key).await, None);
+        }
+        assert!(throttle.record_attempt(&key).await.unwrap().is_some());
+
+        throttle.reset(&key).await.unwrap();
+        assert_eq!(throttle.record_attempt(&key).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn account_and_ip_keys_are_counted_independently() {
+        let throttle = InMemoryLoginThrottle::new(policy(2, 0));
+        let key = account();
+        let ip = ThrottleKey::Ip("203.0.113.7".to_string());
+
+        for _ in 0..2 {
+            fail(&throttle, &key).await;
+            fail(&throttle, &ip).await;
+        }
+        assert!(throttle.record_attempt(&key).await.unwrap().is_some());
+        // Le seuil de l'adresse IP (10) n'est pas atteint, ni celui d'un autre compte
+        assert_eq!(throttle.record_attempt(&ip).await.unwrap(), None);
+        assert_eq!(throttle.record_attempt(&ThrottleKey::account("bob@example.com")).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,212 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait, ColumnTrait, QueryFilter, Set};
+use sea_orm::sea_query::Expr;
+use sha2::Sha256;
+use uuid::Uuid;
+use crate::errors::ApiError;
+use crate::entities_orm::user_token::{Entity as UserTokenEntity, Column as UserTokenColumn, ActiveModel as UserTokenActiveModel};
+
+pub const PURPOSE_EMAIL_VERIFICATION: &str = "email_verification";
+pub const PURPOSE_PASSWORD_RESET: &str = "password_reset";
+// Code à usage unique remis au frontend après le callback OIDC
+pub const PURPOSE_OIDC_LOGIN: &str = "oidc_login";
+
+pub const EMAIL_VERIFICATION_TTL_SECONDS: i64 = 24 * 60 * 60;
+pub const PASSWORD_RESET_TTL_SECONDS: i64 = 60 * 60;
+pub const OIDC_LOGIN_CODE_TTL_SECONDS: i64 = 60;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Le jeton lie l'identifiant de la ligne, l'utilisateur, l'usage et l'expiration
+fn payload(id: Uuid, user_id: Uuid, purpose: &str, expires_at: DateTime<Utc>) -> String {
+    format!("{}:{}:{}:{}", id, user_id, purpose, expires_at.timestamp())
+}
+
+fn mac(secret: &str, payload: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+fn invalid_token() -> ApiError {
+    ApiError::validation("token", "invalid_token", "Invalid or expired token")
+}
+
+/// Émet un jeton signé à usage unique, de la forme `<id>.<signature>`.
+/// Les jetons encore valides du même usage sont révoqués.
+pub async fn issue(
+    db: &DatabaseConnection,
+    secret: &str,
+    user_id: Uuid,
+    purpose: &str,
+    ttl_seconds: i64,
+) -> Result<String, DbErr> {
+    issue_with_context(db, secret, user_id, purpose, ttl_seconds, None).await
+}
+
+/// Comme `issue`, en conservant côté serveur un contexte restitué à la consommation.
+pub async fn issue_with_context(
+    db: &DatabaseConnection,
+    secret: &str,
+    user_id: Uuid,
+    purpose: &str,
+    ttl_seconds: i64,
+    context: Option<serde_json::Value>,
+) -> Result<String, DbErr> {
+    UserTokenEntity::delete_many()
+        .filter(UserTokenColumn::UserId.eq(user_id))
+        .filter(UserTokenColumn::Purpose.eq(purpose))
+        .filter(UserTokenColumn::UsedAt.is_null())
+        .exec(db)
+        .await?;
+
+    let now = Utc::now();
+    let id = Uuid::new_v4();
+    let expires_at = now + Duration::seconds(ttl_seconds);
+    let token = UserTokenActiveModel {
+        id: Set(id),
+        user_id: Set(user_id),
+        purpose: Set(purpose.to_string()),
+        expires_at: Set(expires_at),
+        used_at: Set(None),
+        created_at: Set(now),
+        context: Set(context),
+    };
+    UserTokenEntity::insert(token).exec(db).await?;
+
+    let signature = mac(secret, &payload(id, user_id, purpose, expires_at)).finalize().into_bytes();
+    Ok(format!("{}.{}", id.simple(), URL_SAFE_NO_PAD.encode(signature)))
+}
+
+/// Vérifie la signature, l'expiration et l'usage du jeton, le marque comme
+/// consommé et renvoie l'utilisateur concerné.
+pub async fn consume(
+    db: &DatabaseConnection,
+    secret: &str,
+    token: &str,
+    purpose: &str,
+) -> Result<Uuid, ApiError> {
+    consume_with_context(db, secret, token, purpose).await.map(|(user_id, _)| user_id)
+}
+
+/// Comme `consume`, en renvoyant aussi le contexte enregistré à l'émission.
+pub async fn consume_with_context(
+    db: &DatabaseConnection,
+    secret: &str,
+    token: &str,
+    purpose: &str,
+) -> Result<(Uuid, Option<serde_json::Value>), ApiError> {
+    let (id, signature) = token.trim().split_once('.').ok_or_else(invalid_token)?;
+    let id = Uuid::parse_str(id).map_err(|_| invalid_token())?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid_token())?;
+
+    let stored = UserTokenEntity::find_by_id(id)
+        .filter(UserTokenColumn::Purpose.eq(purpose))
+        .one(db)
+        .await?
+        .ok_or_else(invalid_token)?;
+
+    mac(secret, &payload(stored.id, stored.user_id, &stored.purpose, stored.expires_at))
+        .verify_slice(&signature)
+        .map_err(|_| invalid_token())?;
+
+    if stored.used_at.is_some() || stored.expires_at <= Utc::now() {
+        return Err(invalid_token());
+    }
+
+    // Mise à jour conditionnelle : deux requêtes concurrentes ne peuvent pas consommer le même jeton
+    let result = UserTokenEntity::update_many()
+        .col_expr(UserTokenColumn::UsedAt, Expr::value(Utc::now()))
+        .filter(UserTokenColumn::Id.eq(stored.id))
+        .filter(UserTokenColumn::UsedAt.is_null())
+        .exec(db)
+        .await?;
+    if result.rows_affected != 1 {
+        return Err(invalid_token());
+    }
+
+    Ok((stored.user_id, stored.context))
+}
+
+/// Vrai si un jeton de cet usage a été émis récemment (limite les renvois d'emails).
+pub async fn recently_issued(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    purpose: &str,
+    within_seconds: i64,
+) -> Result<bool, DbErr> {
+    let recent = UserTokenEntity::find()
+        .filter(UserTokenColumn::UserId.eq(user_id))
+        .filter(UserTokenColumn::Purpose.eq(purpose))
+        .filter(UserTokenColumn::CreatedAt.gt(Utc::now() - Duration::seconds(within_seconds)))
+        .one(db)
+        .await?;
+    Ok(recent.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    const SECRET: &str = "test-token-secret";
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn tokens_are_single_use() {
+        let db = test_support::database().await;
+        let user = test_support::create_user(&db, false).await;
+
+        let token = issue(&db, SECRET, user.id, PURPOSE_EMAIL_VERIFICATION, 60).await.unwrap();
+        assert_eq!(consume(&db, SECRET, &token, PURPOSE_EMAIL_VERIFICATION).await.unwrap(), user.id);
+        assert!(consume(&db, SECRET, &token, PURPOSE_EMAIL_VERIFICATION).await.is_err());
+
+        test_support::delete_user(&db, user.id).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn expired_tokens_are_rejected() {
+        let db = test_support::database().await;
+        let user = test_support::create_user(&db, true).await;
+
+        let token = issue(&db, SECRET, user.id, PURPOSE_PASSWORD_RESET, -1).await.unwrap();
+        assert!(consume(&db, SECRET, &token, PURPOSE_PASSWORD_RESET).await.is_err());
+
+        test_support::delete_user(&db, user.id).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn tokens_are_bound_to_their_purpose_and_secret() {
+        let db = test_support::database().await;
+        let user = test_support::create_user(&db, true).await;
+
+        let token = issue(&db, SECRET, user.id, PURPOSE_PASSWORD_RESET, 60).await.unwrap();
+        assert!(consume(&db, SECRET, &token, PURPOSE_EMAIL_VERIFICATION).await.is_err());
+        assert!(consume(&db, "another-secret", &token, PURPOSE_PASSWORD_RESET).await.is_err());
+        let (id, _) = token.split_once('.').unwrap();
+        assert!(consume(&db, SECRET, &format!("{}.AAAA", id), PURPOSE_PASSWORD_RESET).await.is_err());
+        // Les tentatives refusées n'ont pas consommé le jeton
+        assert_eq!(consume(&db, SECRET, &token, PURPOSE_PASSWORD_RESET).await.unwrap(), user.id);
+
+        test_support::delete_user(&db, user.id).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn issuing_a_token_revokes_the_previous_one() {
+        let db = test_support::database().await;
+        let user = test_support::create_user(&db, true).await;
+
+        let first = issue(&db, SECRET, user.id, PURPOSE_PASSWORD_RESET, 60).await.unwrap();
+        let second = issue(&db, SECRET, user.id, PURPOSE_PASSWORD_RESET, 60).await.unwrap();
+        assert!(consume(&db, SECRET, &first, PURPOSE_PASSWORD_RESET).await.is_err());
+        assert_eq!(consume(&db, SECRET, &second, PURPOSE_PASSWORD_RESET).await.unwrap(), user.id);
+
+        test_support::delete_user(&db, user.id).await;
+    }
+}
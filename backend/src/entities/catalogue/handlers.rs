@@ -1,39 +1,57 @@
 use actix_web::{web, HttpResponse, HttpRequest};
 use actix_multipart::Multipart;
-use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, Set, IntoActiveModel, QueryOrder, Order, ActiveModelTrait};
+use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, Condition, QueryFilter, Set, IntoActiveModel, QueryOrder, Order, ActiveModelTrait};
 use futures_util::TryStreamExt;
 use crate::entities::catalogue::models::*;
-use crate::entities::catalogue::storage::{Storage, LocalStorage, S3Storage};
+use crate::entities::catalogue::storage;
+use crate::entities::lifecycle;
+use crate::auth::mfa;
 use crate::config::Config;
-use crate::middleware::get_current_user_id;
+use crate::errors::ApiError;
+use crate::validation::ValidatedJson;
+use crate::middleware::{get_current_user_id, get_entity_role, get_member_entity_ids};
 use crate::entities_orm::{
-    endpoint::{Entity as EndpointEntity, ActiveModel as EndpointActiveModel},
-    license_key::{Entity as LicenseKeyEntity, ActiveModel as LicenseKeyActiveModel},
-    software_version::{Entity as SoftwareVersionEntity, ActiveModel as SoftwareVersionActiveModel},
-    encryption_algorithm::{Entity as EncryptionAlgorithmEntity, ActiveModel as EncryptionAlgorithmActiveModel},
+    endpoint::{Entity as EndpointEntity, ActiveModel as EndpointActiveModel, Column as EndpointColumn},
+    license_key::{Entity as LicenseKeyEntity, ActiveModel as LicenseKeyActiveModel, Column as LicenseKeyColumn},
+    software_version::{Entity as SoftwareVersionEntity, ActiveModel as SoftwareVersionActiveModel, Column as SoftwareVersionColumn},
+    encryption_algorithm::{Entity as EncryptionAlgorithmEntity, ActiveModel as EncryptionAlgorithmActiveModel, Column as EncryptionAlgorithmColumn},
     catalogue_relation::{Entity as CatalogueRelationEntity, ActiveModel as CatalogueRelationActiveModel, Column as CatalogueRelationColumn},
 };
 use uuid::Uuid;
 use chrono::Utc;
 
-// Helper function to check access (simplified - catalogue items are not entity-specific by default)
-// But we can add entity_id later if needed
+// Les éléments sans entité forment le référentiel partagé ; les autres ne sont
+// accessibles qu'aux membres de leur entité, et en lecture seule une fois
+// celle-ci archivée
 async fn check_entity_access(
     db: &DatabaseConnection,
-    user_id: Uuid,
+    req: &HttpRequest,
     entity_id: Option<Uuid>,
-) -> Result<bool, sea_orm::DbErr> {
-    if let Some(eid) = entity_id {
-        use crate::entities_orm::user_entity::{Entity as UserEntityEntity, Column as UserEntityColumn};
-        let has_access = UserEntityEntity::find()
-            .filter(UserEntityColumn::UserId.eq(user_id))
-            .filter(UserEntityColumn::EntityId.eq(eid))
-            .one(db)
-            .await?;
-        Ok(has_access.is_some())
-    } else {
-        Ok(true) // Pas de restriction d'entité
+    write: bool,
+) -> Result<(), ApiError> {
+    let Some(entity_id) = entity_id else {
+        return Ok(());
+    };
+    if get_entity_role(req, entity_id).is_none() {
+        return Err(ApiError::Forbidden("Access denied".to_string()));
+    }
+    mfa::enforce_entity_mfa(db, req, entity_id).await?;
+    if write {
+        lifecycle::ensure_writable(db, entity_id).await?;
     }
+    Ok(())
+}
+
+/// Référentiel partagé et éléments des entités accessibles.
+async fn visible_items<C: ColumnTrait>(
+    db: &DatabaseConnection,
+    req: &HttpRequest,
+    column: C,
+) -> Result<Condition, ApiError> {
+    let entity_ids = mfa::filter_mfa_entities(db, req, get_member_entity_ids(req)).await?;
+    Ok(Condition::any()
+        .add(column.is_null())
+        .add(column.is_in(entity_ids)))
 }
 
 // ========== Endpoints ==========
@@ -42,31 +60,28 @@ pub async fn list_endpoints(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
     query: web::Query<serde_json::Value>,
-) -> Result<HttpResponse, actix_web::Error> {
+) -> Result<HttpResponse, ApiError> {
     let _user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
 
     let endpoint_type: Option<String> = query.get("endpoint_type")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
 
-    let mut query = EndpointEntity::find();
+    let mut query = EndpointEntity::find()
+        .filter(visible_items(db.get_ref(), &req, EndpointColumn::EntityId).await?);
     if let Some(et) = endpoint_type {
-        use crate::entities_orm::endpoint::Column as EndpointColumn;
         query = query.filter(EndpointColumn::EndpointType.eq(et));
     }
 
     let endpoints = query
         .order_by(crate::entities_orm::endpoint::Column::CreatedAt, Order::Desc)
         .all(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     let endpoints: Vec<Endpoint> = endpoints.into_iter().map(|e| Endpoint {
         id: e.id,
+        entity_id: e.entity_id,
         name: e.name,
         endpoint_type: e.endpoint_type,
         description: e.description,
@@ -82,14 +97,17 @@ pub async fn list_endpoints(
 pub async fn create_endpoint(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
-    body: web::Json<CreateEndpointRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
+    body: ValidatedJson<CreateEndpointRequest>,
+) -> Result<HttpResponse, ApiError> {
     let _user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+
+    check_entity_access(db.get_ref(), &req, body.entity_id, true).await?;
 
     let now = Utc::now();
     let endpoint = EndpointActiveModel {
         id: Set(Uuid::new_v4()),
+        entity_id: Set(body.entity_id),
         name: Set(body.name.clone()),
         endpoint_type: Set(body.endpoint_type.clone()),
         description: Set(body.description.clone()),
@@ -101,14 +119,11 @@ pub async fn create_endpoint(
 
     let endpoint = EndpointEntity::insert(endpoint)
         .exec_with_returning(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     Ok(HttpResponse::Created().json(Endpoint {
         id: endpoint.id,
+        entity_id: endpoint.entity_id,
         name: endpoint.name,
         endpoint_type: endpoint.endpoint_type,
         description: endpoint.description,
@@ -123,59 +138,51 @@ pub async fn get_endpoint(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
     path: web::Path<Uuid>,
-) -> Result<HttpResponse, actix_web::Error> {
+) -> Result<HttpResponse, ApiError> {
     let _user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
     let endpoint_id = path.into_inner();
 
     let endpoint = EndpointEntity::find_by_id(endpoint_id)
         .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    match endpoint {
-        Some(e) => Ok(HttpResponse::Ok().json(Endpoint {
-            id: e.id,
-            name: e.name,
-            endpoint_type: e.endpoint_type,
-            description: e.description,
-            address: e.address,
-            metadata: e.metadata,
-            created_at: e.created_at,
-            updated_at: e.updated_at,
-        })),
-        None => Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Endpoint not found"
-        }))),
-    }
+        .await?;
+
+    let endpoint = endpoint.ok_or_else(|| ApiError::NotFound("Endpoint not found".to_string()))?;
+    check_entity_access(db.get_ref(), &req, endpoint.entity_id, false).await?;
+
+    Ok(HttpResponse::Ok().json(Endpoint {
+        id: endpoint.id,
+        entity_id: endpoint.entity_id,
+        name: endpoint.name,
+        endpoint_type: endpoint.endpoint_type,
+        description: endpoint.description,
+        address: endpoint.address,
+        metadata: endpoint.metadata,
+        created_at: endpoint.created_at,
+        updated_at: endpoint.updated_at,
+    }))
 }
 
 pub async fn update_endpoint(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
     path: web::Path<Uuid>,
-    body: web::Json<UpdateEndpointRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
+    body: ValidatedJson<UpdateEndpointRequest>,
+) -> Result<HttpResponse, ApiError> {
     let _user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
     let endpoint_id = path.into_inner();
 
     let endpoint = EndpointEntity::find_by_id(endpoint_id)
         .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     let mut endpoint: EndpointActiveModel = match endpoint {
-        Some(e) => e.into_active_model(),
-        None => return Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Endpoint not found"
-        }))),
+        Some(e) => {
+            check_entity_access(db.get_ref(), &req, e.entity_id, true).await?;
+            e.into_active_model()
+        }
+        None => return Err(ApiError::NotFound("Endpoint not found".to_string())),
     };
 
     if let Some(name) = &body.name {
@@ -196,14 +203,11 @@ pub async fn update_endpoint(
     endpoint.updated_at = Set(Utc::now());
 
     let endpoint = endpoint.update(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     Ok(HttpResponse::Ok().json(Endpoint {
         id: endpoint.id,
+        entity_id: endpoint.entity_id,
         name: endpoint.name,
         endpoint_type: endpoint.endpoint_type,
         description: endpoint.description,
@@ -219,21 +223,19 @@ pub async fn update_endpoint(
 pub async fn list_license_keys(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
-) -> Result<HttpResponse, actix_web::Error> {
+) -> Result<HttpResponse, ApiError> {
     let _user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
 
     let license_keys = LicenseKeyEntity::find()
+        .filter(visible_items(db.get_ref(), &req, LicenseKeyColumn::EntityId).await?)
         .order_by(crate::entities_orm::license_key::Column::CreatedAt, Order::Desc)
         .all(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     let license_keys: Vec<LicenseKey> = license_keys.into_iter().map(|k| LicenseKey {
         id: k.id,
+        entity_id: k.entity_id,
         name: k.name,
         license_type: k.license_type,
         key_value: k.key_value,
@@ -253,14 +255,17 @@ pub async fn list_license_keys(
 pub async fn create_license_key(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
-    body: web::Json<CreateLicenseKeyRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
+    body: ValidatedJson<CreateLicenseKeyRequest>,
+) -> Result<HttpResponse, ApiError> {
     let _user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+
+    check_entity_access(db.get_ref(), &req, body.entity_id, true).await?;
 
     let now = Utc::now();
     let license_key = LicenseKeyActiveModel {
         id: Set(Uuid::new_v4()),
+        entity_id: Set(body.entity_id),
         name: Set(body.name.clone()),
         license_type: Set(body.license_type.clone()),
         key_value: Set(body.key_value.clone()),
@@ -276,14 +281,11 @@ pub async fn create_license_key(
 
     let license_key = LicenseKeyEntity::insert(license_key)
         .exec_with_returning(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     Ok(HttpResponse::Created().json(LicenseKey {
         id: license_key.id,
+        entity_id: license_key.entity_id,
         name: license_key.name,
         license_type: license_key.license_type,
         key_value: license_key.key_value,
@@ -304,47 +306,29 @@ pub async fn upload_license_key_file(
     req: HttpRequest,
     path: web::Path<Uuid>,
     mut payload: Multipart,
-) -> Result<HttpResponse, actix_web::Error> {
+) -> Result<HttpResponse, ApiError> {
     let _user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
     let license_key_id = path.into_inner();
 
     // Vérifier que la clé de licence existe
     let license_key = LicenseKeyEntity::find_by_id(license_key_id)
         .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     let license_key = match license_key {
         Some(k) => k,
-        None => return Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "License key not found"
-        }))),
+        None => return Err(ApiError::NotFound("License key not found".to_string())),
     };
+    check_entity_access(db.get_ref(), &req, license_key.entity_id, true).await?;
 
     // Vérifier que le type est "file"
     if license_key.license_type != "file" {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "License key type must be 'file'"
-        })));
+        return Err(ApiError::BadRequest("License key type must be 'file'".to_string()));
     }
 
     // Créer le storage selon la configuration
-    let storage: Box<dyn Storage> = if config.storage_type == "s3" {
-        let s3_storage = S3Storage::new(
-            config.s3_bucket.clone().unwrap_or_else(|| "default".to_string()),
-            config.s3_region.clone(),
-            config.s3_endpoint.clone(),
-            config.s3_access_key_id.clone(),
-            config.s3_secret_access_key.clone(),
-        ).await;
-        Box::new(s3_storage)
-    } else {
-        Box::new(LocalStorage::new(config.storage_local_path.clone()))
-    };
+    let storage = storage::for_type(&config, &config.storage_type).await;
 
     // Traiter le multipart
     let mut file_data: Option<Vec<u8>> = None;
@@ -352,14 +336,11 @@ pub async fn upload_license_key_file(
 
     while let Some(mut field) = payload.try_next().await.map_err(|e| {
         log::error!("Multipart error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid multipart data")
+        ApiError::BadRequest("Invalid multipart data".to_string())
     })? {
         if field.name() == "file" {
             let mut bytes = Vec::new();
-            while let Some(chunk) = field.try_next().await.map_err(|e| {
-                log::error!("Chunk error: {}", e);
-                actix_web::error::ErrorInternalServerError("Error reading file")
-            })? {
+            while let Some(chunk) = field.try_next().await.map_err(ApiError::internal("Error reading file"))? {
                 bytes.extend_from_slice(&chunk);
             }
             file_data = Some(bytes);
@@ -368,7 +349,7 @@ pub async fn upload_license_key_file(
     }
 
     let file_data = file_data.ok_or_else(|| {
-        actix_web::error::ErrorBadRequest("No file provided")
+        ApiError::BadRequest("No file provided".to_string())
     })?;
 
     let file_name = file_name.unwrap_or_else(|| "license.key".to_string());
@@ -376,10 +357,7 @@ pub async fn upload_license_key_file(
 
     // Sauvegarder le fichier
     let file_path = storage.save_file(&file_data, &file_name, license_key_id).await
-        .map_err(|e| {
-            log::error!("Storage error: {}", e);
-            actix_web::error::ErrorInternalServerError("Failed to save file")
-        })?;
+        .map_err(ApiError::internal("Failed to save file"))?;
 
     // Mettre à jour la clé de licence
     let mut license_key: LicenseKeyActiveModel = license_key.into_active_model();
@@ -390,14 +368,11 @@ pub async fn upload_license_key_file(
     license_key.updated_at = Set(Utc::now());
 
     let license_key = license_key.update(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     Ok(HttpResponse::Ok().json(LicenseKey {
         id: license_key.id,
+        entity_id: license_key.entity_id,
         name: license_key.name,
         license_type: license_key.license_type,
         key_value: license_key.key_value,
@@ -417,21 +392,19 @@ pub async fn upload_license_key_file(
 pub async fn list_software_versions(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
-) -> Result<HttpResponse, actix_web::Error> {
+) -> Result<HttpResponse, ApiError> {
     let _user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
 
     let versions = SoftwareVersionEntity::find()
+        .filter(visible_items(db.get_ref(), &req, SoftwareVersionColumn::EntityId).await?)
         .order_by(crate::entities_orm::software_version::Column::CreatedAt, Order::Desc)
         .all(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     let versions: Vec<SoftwareVersion> = versions.into_iter().map(|v| SoftwareVersion {
         id: v.id,
+        entity_id: v.entity_id,
         name: v.name,
         version: v.version,
         description: v.description,
@@ -448,14 +421,17 @@ pub async fn list_software_versions(
 pub async fn create_software_version(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
-    body: web::Json<CreateSoftwareVersionRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
+    body: ValidatedJson<CreateSoftwareVersionRequest>,
+) -> Result<HttpResponse, ApiError> {
     let _user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+
+    check_entity_access(db.get_ref(), &req, body.entity_id, true).await?;
 
     let now = Utc::now();
     let version = SoftwareVersionActiveModel {
         id: Set(Uuid::new_v4()),
+        entity_id: Set(body.entity_id),
         name: Set(body.name.clone()),
         version: Set(body.version.clone()),
         description: Set(body.description.clone()),
@@ -468,14 +444,11 @@ pub async fn create_software_version(
 
     let version = SoftwareVersionEntity::insert(version)
         .exec_with_returning(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     Ok(HttpResponse::Created().json(SoftwareVersion {
         id: version.id,
+        entity_id: version.entity_id,
         name: version.name,
         version: version.version,
         description: version.description,
@@ -492,21 +465,19 @@ pub async fn create_software_version(
 pub async fn list_encryption_algorithms(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
-) -> Result<HttpResponse, actix_web::Error> {
+) -> Result<HttpResponse, ApiError> {
     let _user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
 
     let algorithms = EncryptionAlgorithmEntity::find()
+        .filter(visible_items(db.get_ref(), &req, EncryptionAlgorithmColumn::EntityId).await?)
         .order_by(crate::entities_orm::encryption_algorithm::Column::CreatedAt, Order::Desc)
         .all(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     let algorithms: Vec<EncryptionAlgorithm> = algorithms.into_iter().map(|a| EncryptionAlgorithm {
         id: a.id,
+        entity_id: a.entity_id,
         name: a.name,
         algorithm_type: a.algorithm_type,
         key_size: a.key_size,
@@ -523,14 +494,17 @@ pub async fn list_encryption_algorithms(
 pub async fn create_encryption_algorithm(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
-    body: web::Json<CreateEncryptionAlgorithmRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
+    body: ValidatedJson<CreateEncryptionAlgorithmRequest>,
+) -> Result<HttpResponse, ApiError> {
     let _user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+
+    check_entity_access(db.get_ref(), &req, body.entity_id, true).await?;
 
     let now = Utc::now();
     let algorithm = EncryptionAlgorithmActiveModel {
         id: Set(Uuid::new_v4()),
+        entity_id: Set(body.entity_id),
         name: Set(body.name.clone()),
         algorithm_type: Set(body.algorithm_type.clone()),
         key_size: Set(body.key_size),
@@ -543,14 +517,11 @@ pub async fn create_encryption_algorithm(
 
     let algorithm = EncryptionAlgorithmEntity::insert(algorithm)
         .exec_with_returning(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     Ok(HttpResponse::Created().json(EncryptionAlgorithm {
         id: algorithm.id,
+        entity_id: algorithm.entity_id,
         name: algorithm.name,
         algorithm_type: algorithm.algorithm_type,
         key_size: algorithm.key_size,
@@ -567,10 +538,12 @@ pub async fn create_encryption_algorithm(
 pub async fn create_catalogue_relation(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
-    body: web::Json<CreateCatalogueRelationRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
+    body: ValidatedJson<CreateCatalogueRelationRequest>,
+) -> Result<HttpResponse, ApiError> {
     let _user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+
+    check_entity_access(db.get_ref(), &req, body.entity_id, true).await?;
 
     // Vérifier que la relation n'existe pas déjà
     let existing = CatalogueRelationEntity::find()
@@ -580,21 +553,16 @@ pub async fn create_catalogue_relation(
         .filter(CatalogueRelationColumn::TargetId.eq(body.target_id))
         .filter(CatalogueRelationColumn::RelationType.eq(&body.relation_type))
         .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     if existing.is_some() {
-        return Ok(HttpResponse::Conflict().json(serde_json::json!({
-            "error": "Relation already exists"
-        })));
+        return Err(ApiError::Conflict("Relation already exists".to_string()));
     }
 
     let now = Utc::now();
     let relation = CatalogueRelationActiveModel {
         id: Set(Uuid::new_v4()),
+        entity_id: Set(body.entity_id),
         source_type: Set(body.source_type.clone()),
         source_id: Set(body.source_id),
         target_type: Set(body.target_type.clone()),
@@ -606,14 +574,11 @@ pub async fn create_catalogue_relation(
 
     let relation = CatalogueRelationEntity::insert(relation)
         .exec_with_returning(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     Ok(HttpResponse::Created().json(CatalogueRelation {
         id: relation.id,
+        entity_id: relation.entity_id,
         source_type: relation.source_type,
         source_id: relation.source_id,
         target_type: relation.target_type,
@@ -628,9 +593,9 @@ pub async fn list_catalogue_relations(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
     query: web::Query<serde_json::Value>,
-) -> Result<HttpResponse, actix_web::Error> {
+) -> Result<HttpResponse, ApiError> {
     let _user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
 
     let source_type: Option<String> = query.get("source_type")
         .and_then(|v| v.as_str())
@@ -645,36 +610,34 @@ pub async fn list_catalogue_relations(
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
 
-    let mut query_builder = CatalogueRelationEntity::find();
+    let mut query_builder = CatalogueRelationEntity::find()
+        .filter(visible_items(db.get_ref(), &req, CatalogueRelationColumn::EntityId).await?);
 
     if let Some(st) = source_type {
         query_builder = query_builder.filter(CatalogueRelationColumn::SourceType.eq(st));
     }
-    if let Some(sid) = source_id {
-        if let Ok(uuid) = Uuid::parse_str(&sid) {
-            query_builder = query_builder.filter(CatalogueRelationColumn::SourceId.eq(uuid));
-        }
+    if let Some(sid) = source_id
+        && let Ok(uuid) = Uuid::parse_str(&sid)
+    {
+        query_builder = query_builder.filter(CatalogueRelationColumn::SourceId.eq(uuid));
     }
     if let Some(tt) = target_type {
         query_builder = query_builder.filter(CatalogueRelationColumn::TargetType.eq(tt));
     }
-    if let Some(tid) = target_id {
-        if let Ok(uuid) = Uuid::parse_str(&tid) {
-            query_builder = query_builder.filter(CatalogueRelationColumn::TargetId.eq(uuid));
-        }
+    if let Some(tid) = target_id
+        && let Ok(uuid) = Uuid::parse_str(&tid)
+    {
+        query_builder = query_builder.filter(CatalogueRelationColumn::TargetId.eq(uuid));
     }
 
     let relations = query_builder
         .order_by(CatalogueRelationColumn::CreatedAt, Order::Desc)
         .all(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     let relations: Vec<CatalogueRelation> = relations.into_iter().map(|r| CatalogueRelation {
         id: r.id,
+        entity_id: r.entity_id,
         source_type: r.source_type,
         source_id: r.source_id,
         target_type: r.target_type,
@@ -691,32 +654,21 @@ pub async fn delete_catalogue_relation(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
     path: web::Path<Uuid>,
-) -> Result<HttpResponse, actix_web::Error> {
+) -> Result<HttpResponse, ApiError> {
     let _user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
     let relation_id = path.into_inner();
 
     let relation = CatalogueRelationEntity::find_by_id(relation_id)
         .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    if relation.is_none() {
-        return Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Relation not found"
-        })));
-    }
+        .await?;
+
+    let relation = relation.ok_or_else(|| ApiError::NotFound("Relation not found".to_string()))?;
+    check_entity_access(db.get_ref(), &req, relation.entity_id, true).await?;
 
     CatalogueRelationEntity::delete_by_id(relation_id)
         .exec(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
@@ -1,11 +1,54 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use validator::{Validate, ValidationError};
+use crate::validation::{not_blank, one_of, validation_error};
+
+pub const LICENSE_TYPES: &[&str] = &["string", "file"];
+
+fn validate_license_type(value: &str) -> Result<(), ValidationError> {
+    one_of(value, LICENSE_TYPES)
+}
+
+fn validate_create_license_key(req: &CreateLicenseKeyRequest) -> Result<(), ValidationError> {
+    if req.license_type == "string" && req.key_value.as_deref().is_none_or(|v| v.trim().is_empty()) {
+        return Err(validation_error("key_value_required", "key_value is required for 'string' license keys"));
+    }
+    if req.license_type == "file" && req.key_value.is_some() {
+        return Err(validation_error("key_value_not_allowed", "key_value must be empty for 'file' license keys; upload the file instead"));
+    }
+    Ok(())
+}
+
+fn check_lifecycle_dates(release_date: Option<DateTime<Utc>>, end_of_life: Option<DateTime<Utc>>) -> Result<(), ValidationError> {
+    if let (Some(release), Some(eol)) = (release_date, end_of_life)
+        && eol < release
+    {
+        return Err(validation_error("end_of_life_before_release", "end_of_life must not be before release_date"));
+    }
+    Ok(())
+}
+
+fn validate_create_software_version(req: &CreateSoftwareVersionRequest) -> Result<(), ValidationError> {
+    check_lifecycle_dates(req.release_date, req.end_of_life)
+}
+
+fn validate_update_software_version(req: &UpdateSoftwareVersionRequest) -> Result<(), ValidationError> {
+    check_lifecycle_dates(req.release_date, req.end_of_life)
+}
+
+fn validate_create_relation(req: &CreateCatalogueRelationRequest) -> Result<(), ValidationError> {
+    if req.source_type == req.target_type && req.source_id == req.target_id {
+        return Err(validation_error("self_relation", "An item cannot be related to itself"));
+    }
+    Ok(())
+}
 
 // Endpoint - peut être une machine, un programme, une URL, etc.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Endpoint {
     pub id: Uuid,
+    pub entity_id: Option<Uuid>, // `None` = référentiel partagé
     pub name: String,
     pub endpoint_type: String, // "machine", "program", "url", "api", etc.
     pub description: Option<String>,
@@ -15,20 +58,29 @@ pub struct Endpoint {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateEndpointRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
     pub name: String,
+    #[validate(length(min = 1, max = 50), custom(function = "not_blank"))]
     pub endpoint_type: String,
+    #[validate(length(max = 2000))]
     pub description: Option<String>,
+    #[validate(length(max = 255))]
     pub address: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    pub entity_id: Option<Uuid>, // Élément propre à une entité (membre requis)
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdateEndpointRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
     pub name: Option<String>,
+    #[validate(length(min = 1, max = 50), custom(function = "not_blank"))]
     pub endpoint_type: Option<String>,
+    #[validate(length(max = 2000))]
     pub description: Option<String>,
+    #[validate(length(max = 255))]
     pub address: Option<String>,
     pub metadata: Option<serde_json::Value>,
 }
@@ -37,6 +89,7 @@ pub struct UpdateEndpointRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LicenseKey {
     pub id: Uuid,
+    pub entity_id: Option<Uuid>, // `None` = référentiel partagé
     pub name: String,
     pub license_type: String, // "string", "file"
     pub key_value: Option<String>, // Pour les clés string
@@ -50,20 +103,31 @@ pub struct LicenseKey {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_create_license_key"))]
 pub struct CreateLicenseKeyRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
     pub name: String,
+    #[validate(custom(function = "validate_license_type"))]
     pub license_type: String,
+    #[validate(length(max = 10000))]
     pub key_value: Option<String>,
+    #[validate(length(max = 2000))]
     pub description: Option<String>,
     pub expires_at: Option<DateTime<Utc>>,
+    pub entity_id: Option<Uuid>, // Élément propre à une entité (membre requis)
 }
 
-#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdateLicenseKeyRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
     pub name: Option<String>,
+    #[validate(custom(function = "validate_license_type"))]
     pub license_type: Option<String>,
+    #[validate(length(max = 10000))]
     pub key_value: Option<String>,
+    #[validate(length(max = 2000))]
     pub description: Option<String>,
     pub expires_at: Option<DateTime<Utc>>,
 }
@@ -72,6 +136,7 @@ pub struct UpdateLicenseKeyRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SoftwareVersion {
     pub id: Uuid,
+    pub entity_id: Option<Uuid>, // `None` = référentiel partagé
     pub name: String,
     pub version: String, // Version semver ou autre format
     pub description: Option<String>,
@@ -82,20 +147,30 @@ pub struct SoftwareVersion {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_create_software_version"))]
 pub struct CreateSoftwareVersionRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
     pub name: String,
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
     pub version: String,
+    #[validate(length(max = 2000))]
     pub description: Option<String>,
     pub release_date: Option<DateTime<Utc>>,
     pub end_of_life: Option<DateTime<Utc>>,
     pub metadata: Option<serde_json::Value>,
+    pub entity_id: Option<Uuid>, // Élément propre à une entité (membre requis)
 }
 
-#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_update_software_version"))]
 pub struct UpdateSoftwareVersionRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
     pub name: Option<String>,
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
     pub version: Option<String>,
+    #[validate(length(max = 2000))]
     pub description: Option<String>,
     pub release_date: Option<DateTime<Utc>>,
     pub end_of_life: Option<DateTime<Utc>>,
@@ -106,6 +181,7 @@ pub struct UpdateSoftwareVersionRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EncryptionAlgorithm {
     pub id: Uuid,
+    pub entity_id: Option<Uuid>, // `None` = référentiel partagé
     pub name: String,
     pub algorithm_type: String, // "symmetric", "asymmetric", "hashing", etc.
     pub key_size: Option<i32>, // Taille de la clé en bits
@@ -116,22 +192,34 @@ pub struct EncryptionAlgorithm {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateEncryptionAlgorithmRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
     pub name: String,
+    #[validate(length(min = 1, max = 50), custom(function = "not_blank"))]
     pub algorithm_type: String,
+    #[validate(range(min = 1, max = 65536))]
     pub key_size: Option<i32>,
+    #[validate(length(max = 2000))]
     pub description: Option<String>,
+    #[validate(length(max = 255))]
     pub standard: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    pub entity_id: Option<Uuid>, // Élément propre à une entité (membre requis)
 }
 
-#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdateEncryptionAlgorithmRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
     pub name: Option<String>,
+    #[validate(length(min = 1, max = 50), custom(function = "not_blank"))]
     pub algorithm_type: Option<String>,
+    #[validate(range(min = 1, max = 65536))]
     pub key_size: Option<i32>,
+    #[validate(length(max = 2000))]
     pub description: Option<String>,
+    #[validate(length(max = 255))]
     pub standard: Option<String>,
     pub metadata: Option<serde_json::Value>,
 }
@@ -141,6 +229,7 @@ pub struct UpdateEncryptionAlgorithmRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CatalogueRelation {
     pub id: Uuid,
+    pub entity_id: Option<Uuid>, // `None` = référentiel partagé
     pub source_type: String, // "endpoint", "license_key", "software_version", "encryption_algorithm", "entity", etc.
     pub source_id: Uuid,
     pub target_type: String,
@@ -150,13 +239,19 @@ pub struct CatalogueRelation {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_create_relation"))]
 pub struct CreateCatalogueRelationRequest {
+    #[validate(length(min = 1, max = 50), custom(function = "not_blank"))]
     pub source_type: String,
     pub source_id: Uuid,
+    #[validate(length(min = 1, max = 50), custom(function = "not_blank"))]
     pub target_type: String,
     pub target_id: Uuid,
+    #[validate(length(min = 1, max = 50), custom(function = "not_blank"))]
     pub relation_type: String,
+    #[validate(length(max = 2000))]
     pub description: Option<String>,
+    pub entity_id: Option<Uuid>, // Élément propre à une entité (membre requis)
 }
 
@@ -2,7 +2,9 @@ use async_trait::async_trait;
 use uuid::Uuid;
 use std::io::Result as IoResult;
 use aws_sdk_s3::primitives::ByteStream;
+use crate::config::Config;
 
+#[allow(dead_code)]
 #[async_trait]
 pub trait Storage: Send + Sync {
     /// Sauvegarde un fichier et retourne le chemin/URL
@@ -18,6 +20,22 @@ pub trait Storage: Send + Sync {
     async fn get_file_size(&self, file_path: &str) -> IoResult<u64>;
 }
 
+/// Stockage correspondant au type enregistré avec le fichier ("local" ou "s3").
+pub async fn for_type(config: &Config, storage_type: &str) -> Box<dyn Storage> {
+    if storage_type == "s3" {
+        let s3_storage = S3Storage::new(
+            config.s3_bucket.clone().unwrap_or_else(|| "default".to_string()),
+            config.s3_region.clone(),
+            config.s3_endpoint.clone(),
+            config.s3_access_key_id.clone(),
+            config.s3_secret_access_key.clone(),
+        ).await;
+        Box::new(s3_storage)
+    } else {
+        Box::new(LocalStorage::new(config.storage_local_path.clone()))
+    }
+}
+
 // Implémentation pour le stockage local
 pub struct LocalStorage {
     base_path: String,
@@ -124,10 +142,7 @@ impl Storage for S3Storage {
             Ok(_) => Ok(key),
             Err(e) => {
                 log::error!("S3 upload error: {}", e);
-                Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("S3 upload failed: {}", e)
-                ))
+                Err(std::io::Error::other(format!("S3 upload failed: {}", e)))
             }
         }
     }
@@ -149,10 +164,7 @@ impl Storage for S3Storage {
                         Ok(bytes) => data.extend_from_slice(&bytes),
                         Err(e) => {
                             log::error!("S3 read error: {}", e);
-                            return Err(std::io::Error::new(
-                                std::io::ErrorKind::Other,
-                                format!("S3 read failed: {}", e)
-                            ));
+                            return Err(std::io::Error::other(format!("S3 read failed: {}", e)));
                         }
                     }
                 }
@@ -160,10 +172,7 @@ impl Storage for S3Storage {
             }
             Err(e) => {
                 log::error!("S3 get error: {}", e);
-                Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("S3 get failed: {}", e)
-                ))
+                Err(std::io::Error::other(format!("S3 get failed: {}", e)))
             }
         }
     }
@@ -180,10 +189,7 @@ impl Storage for S3Storage {
             Ok(_) => Ok(()),
             Err(e) => {
                 log::error!("S3 delete error: {}", e);
-                Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("S3 delete failed: {}", e)
-                ))
+                Err(std::io::Error::other(format!("S3 delete failed: {}", e)))
             }
         }
     }
@@ -202,10 +208,7 @@ impl Storage for S3Storage {
             }
             Err(e) => {
                 log::error!("S3 head error: {}", e);
-                Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("S3 head failed: {}", e)
-                ))
+                Err(std::io::Error::other(format!("S3 head failed: {}", e)))
             }
         }
     }
@@ -0,0 +1,178 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::{RngCore, rngs::OsRng};
+use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, QueryOrder, Set, IntoActiveModel, ActiveModelTrait};
+use crate::entities::email_domains::{self as domains, models::*};
+use crate::errors::ApiError;
+use crate::validation::ValidatedJson;
+use crate::middleware::get_entity_role;
+use crate::auth::mfa;
+use crate::outbound::OutboundClient;
+use crate::entities_orm::email_domain::{self, Entity as EmailDomainEntity, Column as EmailDomainColumn, ActiveModel as EmailDomainActiveModel};
+use uuid::Uuid;
+use chrono::Utc;
+
+// Taille maximale lue du fichier de preuve
+const MAX_PROOF_BYTES: usize = 4096;
+
+async fn require_entity_admin(
+    db: &DatabaseConnection,
+    req: &HttpRequest,
+    entity_id: Uuid,
+) -> Result<(), ApiError> {
+    if get_entity_role(req, entity_id).as_deref() != Some("admin") {
+        return Err(ApiError::Forbidden("Admin access required".to_string()));
+    }
+    mfa::enforce_entity_mfa(db, req, entity_id).await
+}
+
+fn verification_url(domain: &str) -> String {
+    format!("https://{}{}", domain, domains::VERIFICATION_PATH)
+}
+
+fn to_dto(d: email_domain::Model) -> EmailDomain {
+    EmailDomain {
+        verification_url: verification_url(&d.domain),
+        verification_record: domains::verification_record(&d.verification_token),
+        id: d.id,
+        entity_id: d.entity_id,
+        domain: d.domain,
+        verified_at: d.verified_at,
+        created_at: d.created_at,
+    }
+}
+
+async fn find_domain(
+    db: &DatabaseConnection,
+    entity_id: Uuid,
+    domain_id: Uuid,
+) -> Result<email_domain::Model, ApiError> {
+    EmailDomainEntity::find_by_id(domain_id)
+        .filter(EmailDomainColumn::EntityId.eq(entity_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Email domain not found".to_string()))
+}
+
+// Le fichier publié à la racine HTTPS du domaine doit contenir la ligne attendue
+async fn fetch_proof(http: &OutboundClient, domain: &email_domain::Model) -> anyhow::Result<bool> {
+    let response = http.get(&verification_url(&domain.domain))?
+        .send()
+        .await?
+        .error_for_status()?;
+    let body = response.bytes().await?;
+    let body = String::from_utf8_lossy(&body[..body.len().min(MAX_PROOF_BYTES)]).into_owned();
+    let expected = domains::verification_record(&domain.verification_token);
+    Ok(body.lines().any(|line| line.trim() == expected))
+}
+
+pub async fn list_email_domains(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let domains: Vec<EmailDomain> = EmailDomainEntity::find()
+        .filter(EmailDomainColumn::EntityId.eq(entity_id))
+        .order_by_asc(EmailDomainColumn::Domain)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(to_dto)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(domains))
+}
+
+pub async fn create_email_domain(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: ValidatedJson<CreateEmailDomainRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let domain = domains::normalize(&body.domain);
+    let existing = EmailDomainEntity::find()
+        .filter(EmailDomainColumn::EntityId.eq(entity_id))
+        .filter(EmailDomainColumn::Domain.eq(&domain))
+        .one(db.get_ref())
+        .await?;
+    if existing.is_some() {
+        return Err(ApiError::Conflict("Email domain already declared for this entity".to_string()));
+    }
+
+    let mut token = [0u8; 24];
+    OsRng.fill_bytes(&mut token);
+    let email_domain = EmailDomainActiveModel {
+        id: Set(Uuid::new_v4()),
+        entity_id: Set(entity_id),
+        domain: Set(domain),
+        verification_token: Set(URL_SAFE_NO_PAD.encode(token)),
+        verified_at: Set(None),
+        created_at: Set(Utc::now()),
+    };
+    let email_domain = EmailDomainEntity::insert(email_domain)
+        .exec_with_returning(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Created().json(to_dto(email_domain)))
+}
+
+pub async fn verify_email_domain(
+    db: web::Data<DatabaseConnection>,
+    http: web::Data<OutboundClient>,
+    req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, ApiError> {
+    let (entity_id, domain_id) = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let email_domain = find_domain(db.get_ref(), entity_id, domain_id).await?;
+    if email_domain.verified_at.is_some() {
+        return Ok(HttpResponse::Ok().json(to_dto(email_domain)));
+    }
+
+    let claimed = EmailDomainEntity::find()
+        .filter(EmailDomainColumn::Domain.eq(&email_domain.domain))
+        .filter(EmailDomainColumn::VerifiedAt.is_not_null())
+        .one(db.get_ref())
+        .await?;
+    if claimed.is_some() {
+        return Err(ApiError::Conflict("Email domain is already verified by another entity".to_string()));
+    }
+
+    match fetch_proof(&http, &email_domain).await {
+        Ok(true) => {}
+        Ok(false) => return Err(ApiError::validation("domain", "proof_mismatch", "The verification file does not contain the expected record")),
+        Err(e) => {
+            log::info!("Domain verification of {} failed: {:#}", email_domain.domain, e);
+            return Err(ApiError::validation("domain", "proof_unreachable", "The verification file could not be fetched over https"));
+        }
+    }
+
+    let mut email_domain = email_domain.into_active_model();
+    email_domain.verified_at = Set(Some(Utc::now()));
+    let email_domain = email_domain.update(db.get_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(to_dto(email_domain)))
+}
+
+pub async fn delete_email_domain(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, ApiError> {
+    let (entity_id, domain_id) = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let email_domain = find_domain(db.get_ref(), entity_id, domain_id).await?;
+    EmailDomainEntity::delete_by_id(email_domain.id)
+        .exec(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
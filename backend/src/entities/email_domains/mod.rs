@@ -0,0 +1,30 @@
+pub mod handlers;
+pub mod models;
+
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use uuid::Uuid;
+use crate::entities_orm::email_domain::{Entity as EmailDomainEntity, Column as EmailDomainColumn};
+
+/// Fichier de preuve publié par l'entité à la racine HTTPS du domaine.
+pub const VERIFICATION_PATH: &str = "/.well-known/hypercyber-domain-verification.txt";
+const VERIFICATION_PREFIX: &str = "hypercyber-domain-verification=";
+
+pub fn normalize(domain: &str) -> String {
+    domain.trim().trim_start_matches('@').trim_end_matches('.').to_lowercase()
+}
+
+/// Ligne attendue dans le fichier de preuve.
+pub fn verification_record(token: &str) -> String {
+    format!("{}{}", VERIFICATION_PREFIX, token)
+}
+
+/// Vrai si l'entité a prouvé la maîtrise du domaine.
+pub async fn is_verified(db: &DatabaseConnection, entity_id: Uuid, domain: &str) -> Result<bool, DbErr> {
+    Ok(EmailDomainEntity::find()
+        .filter(EmailDomainColumn::EntityId.eq(entity_id))
+        .filter(EmailDomainColumn::Domain.eq(normalize(domain)))
+        .filter(EmailDomainColumn::VerifiedAt.is_not_null())
+        .one(db)
+        .await?
+        .is_some())
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::{Validate, ValidationError};
+use crate::validation::validation_error;
+
+fn validate_domain(value: &str) -> Result<(), ValidationError> {
+    let domain = super::normalize(value);
+    let valid_label = |label: &str| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+    if domain.len() > 253 || !domain.contains('.') || !domain.split('.').all(valid_label) {
+        return Err(validation_error("invalid_domain", "Must be a domain name such as example.com"));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmailDomain {
+    pub id: Uuid,
+    pub entity_id: Uuid,
+    pub domain: String,
+    pub verified_at: Option<DateTime<Utc>>,
+    // Preuve à publier tant que le domaine n'est pas vérifié
+    pub verification_url: String,
+    pub verification_record: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateEmailDomainRequest {
+    #[validate(length(min = 1, max = 253), custom(function = "validate_domain"))]
+    pub domain: String,
+}
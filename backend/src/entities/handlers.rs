@@ -1,41 +1,41 @@
 use actix_web::{web, HttpResponse, HttpRequest};
-use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, Set, IntoActiveModel, ActiveModelTrait};
+use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, QueryOrder, QuerySelect, Order, Set, IntoActiveModel, ActiveModelTrait};
 use crate::entities::models::*;
-use crate::middleware::get_current_user_id;
-use crate::entities_orm::entity::{Entity as EntityEntity, ActiveModel as EntityActiveModel};
+use crate::errors::ApiError;
+use crate::validation::ValidatedJson;
+use crate::middleware::{get_current_user_id, get_entity_role, get_member_entity_ids};
+use crate::auth::mfa;
+use crate::entities::hierarchy::EntityTree;
+use crate::entities::lifecycle;
+use crate::entities_orm::entity::{Entity as EntityEntity, Column as EntityColumn, ActiveModel as EntityActiveModel};
 use crate::entities_orm::user_entity::{Entity as UserEntityEntity, Column as UserEntityColumn, ActiveModel as UserEntityActiveModel};
 use crate::entities_orm::user::Entity as UserEntity;
+use crate::entities_orm::login_failure::{Entity as LoginFailureEntity, Column as LoginFailureColumn};
 use uuid::Uuid;
 use chrono::Utc;
 
+async fn require_entity_admin(
+    db: &DatabaseConnection,
+    req: &HttpRequest,
+    entity_id: Uuid,
+) -> Result<(), ApiError> {
+    if get_entity_role(req, entity_id).as_deref() != Some("admin") {
+        return Err(ApiError::Forbidden("Admin access required".to_string()));
+    }
+    mfa::enforce_entity_mfa(db, req, entity_id).await
+}
+
 pub async fn list_entities(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
-
-    // Find entities through user_entities relationship
-    let user_entities = UserEntityEntity::find()
-        .filter(UserEntityColumn::UserId.eq(user_id))
-        .find_also_related(EntityEntity)
+) -> Result<HttpResponse, ApiError> {
+    let entities: Vec<Entity> = EntityEntity::find()
+        .filter(EntityColumn::Id.is_in(get_member_entity_ids(&req)))
+        .order_by_asc(EntityColumn::Name)
         .all(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    let entities: Vec<Entity> = user_entities
+        .await?
         .into_iter()
-        .filter_map(|(_, entity)| entity)
-        .map(|e| Entity {
-            id: e.id,
-            name: e.name,
-            description: e.description,
-            created_at: e.created_at,
-            updated_at: e.updated_at,
-        })
+        .map(Entity::from)
         .collect();
 
     Ok(HttpResponse::Ok().json(entities))
@@ -44,27 +44,40 @@ pub async fn list_entities(
 pub async fn create_entity(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
-    body: web::Json<CreateEntityRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
+    body: ValidatedJson<CreateEntityRequest>,
+) -> Result<HttpResponse, ApiError> {
     let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+
+    let require_mfa = body.require_mfa.unwrap_or(false);
+    // Évite que le créateur se retrouve exclu de sa propre entité
+    if require_mfa && !mfa::session_has_mfa(&req) {
+        return Err(ApiError::MfaRequired);
+    }
+
+    // Une filiale ne peut être créée que par un administrateur du groupe
+    if let Some(parent_id) = body.parent_id {
+        require_entity_admin(db.get_ref(), &req, parent_id).await?;
+        lifecycle::ensure_writable(db.get_ref(), parent_id).await?;
+    }
 
     let now = Utc::now();
     let entity = EntityActiveModel {
         id: Set(Uuid::new_v4()),
         name: Set(body.name.clone()),
         description: Set(body.description.clone()),
+        require_mfa: Set(require_mfa),
+        parent_id: Set(body.parent_id),
+        archived_at: Set(None),
+        deletion_scheduled_at: Set(None),
+        deletion_requested_by: Set(None),
         created_at: Set(now),
         updated_at: Set(now),
     };
 
     let entity = EntityEntity::insert(entity)
         .exec_with_returning(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     // Add creator as admin
     let user_entity = UserEntityActiveModel {
@@ -77,66 +90,31 @@ pub async fn create_entity(
 
     UserEntityEntity::insert(user_entity)
         .exec(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    Ok(HttpResponse::Created().json(Entity {
-        id: entity.id,
-        name: entity.name,
-        description: entity.description,
-        created_at: entity.created_at,
-        updated_at: entity.updated_at,
-    }))
+        .await?;
+
+    Ok(HttpResponse::Created().json(Entity::from(entity)))
 }
 
 pub async fn get_entity(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
     path: web::Path<Uuid>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+) -> Result<HttpResponse, ApiError> {
     let entity_id = path.into_inner();
 
-    // Check if user has access to this entity
-    let has_access = UserEntityEntity::find()
-        .filter(UserEntityColumn::UserId.eq(user_id))
-        .filter(UserEntityColumn::EntityId.eq(entity_id))
-        .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    if has_access.is_none() {
-        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
-            "error": "Access denied"
-        })));
+    // Appartenance lue dans le contexte chargé par le middleware
+    if get_entity_role(&req, entity_id).is_none() {
+        return Err(ApiError::Forbidden("Access denied".to_string()));
     }
+    mfa::enforce_entity_mfa(db.get_ref(), &req, entity_id).await?;
 
     let entity = EntityEntity::find_by_id(entity_id)
         .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     match entity {
-        Some(e) => Ok(HttpResponse::Ok().json(Entity {
-            id: e.id,
-            name: e.name,
-            description: e.description,
-            created_at: e.created_at,
-            updated_at: e.updated_at,
-        })),
-        None => Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Entity not found"
-        }))),
+        Some(e) => Ok(HttpResponse::Ok().json(Entity::from(e))),
+        None => Err(ApiError::NotFound("Entity not found".to_string())),
     }
 }
 
@@ -144,43 +122,23 @@ pub async fn update_entity(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
     path: web::Path<Uuid>,
-    body: web::Json<UpdateEntityRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+    body: ValidatedJson<UpdateEntityRequest>,
+) -> Result<HttpResponse, ApiError> {
     let entity_id = path.into_inner();
 
     // Check if user is admin of this entity
-    let is_admin = UserEntityEntity::find()
-        .filter(UserEntityColumn::UserId.eq(user_id))
-        .filter(UserEntityColumn::EntityId.eq(entity_id))
-        .filter(UserEntityColumn::Role.eq("admin"))
-        .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    if is_admin.is_none() {
-        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
-            "error": "Admin access required"
-        })));
+    if get_entity_role(&req, entity_id).as_deref() != Some("admin") {
+        return Err(ApiError::Forbidden("Admin access required".to_string()));
     }
+    mfa::enforce_entity_mfa(db.get_ref(), &req, entity_id).await?;
 
     let entity = EntityEntity::find_by_id(entity_id)
         .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     let mut entity: EntityActiveModel = match entity {
         Some(e) => e.into_active_model(),
-        None => return Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Entity not found"
-        }))),
+        None => return Err(ApiError::NotFound("Entity not found".to_string())),
     };
 
     if let Some(name) = &body.name {
@@ -189,60 +147,39 @@ pub async fn update_entity(
     if let Some(description) = &body.description {
         entity.description = Set(Some(description.clone()));
     }
+    if let Some(require_mfa) = body.require_mfa {
+        if require_mfa && !mfa::session_has_mfa(&req) {
+            return Err(ApiError::MfaRequired);
+        }
+        entity.require_mfa = Set(require_mfa);
+    }
     entity.updated_at = Set(Utc::now());
 
     let entity = entity.update(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    Ok(HttpResponse::Ok().json(Entity {
-        id: entity.id,
-        name: entity.name,
-        description: entity.description,
-        created_at: entity.created_at,
-        updated_at: entity.updated_at,
-    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(Entity::from(entity)))
 }
 
 pub async fn get_entity_users(
     db: web::Data<DatabaseConnection>,
     req: HttpRequest,
     path: web::Path<Uuid>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
+) -> Result<HttpResponse, ApiError> {
     let entity_id = path.into_inner();
 
-    // Check if user has access to this entity
-    let has_access = UserEntityEntity::find()
-        .filter(UserEntityColumn::UserId.eq(user_id))
-        .filter(UserEntityColumn::EntityId.eq(entity_id))
-        .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    if has_access.is_none() {
-        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
-            "error": "Access denied"
-        })));
+    // Appartenance lue dans le contexte chargé par le middleware
+    if get_entity_role(&req, entity_id).is_none() {
+        return Err(ApiError::Forbidden("Access denied".to_string()));
     }
+    mfa::enforce_entity_mfa(db.get_ref(), &req, entity_id).await?;
 
     // Get all user_entities for this entity with user details
     let user_entities: Vec<(crate::entities_orm::user_entity::Model, Option<crate::entities_orm::user::Model>)> = UserEntityEntity::find()
         .filter(UserEntityColumn::EntityId.eq(entity_id))
         .find_also_related(UserEntity)
         .all(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+        .await?;
 
     let users: Vec<EntityUser> = user_entities
         .into_iter()
@@ -261,3 +198,115 @@ pub async fn get_entity_users(
 
     Ok(HttpResponse::Ok().json(users))
 }
+
+pub async fn list_login_failures(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    query: web::Query<LoginFailureQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+
+    // Check if user is admin of this entity
+    if get_entity_role(&req, entity_id).as_deref() != Some("admin") {
+        return Err(ApiError::Forbidden("Admin access required".to_string()));
+    }
+    mfa::enforce_entity_mfa(db.get_ref(), &req, entity_id).await?;
+
+    // Seuls les échecs rattachés aux membres de l'entité sont visibles
+    let member_ids: Vec<Uuid> = UserEntityEntity::find()
+        .filter(UserEntityColumn::EntityId.eq(entity_id))
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(|ue| ue.user_id)
+        .collect();
+
+    let mut failures = LoginFailureEntity::find()
+        .filter(LoginFailureColumn::UserId.is_in(member_ids));
+    if let Some(since) = query.since {
+        failures = failures.filter(LoginFailureColumn::CreatedAt.gte(since));
+    }
+
+    let failures: Vec<LoginFailure> = failures
+        .order_by(LoginFailureColumn::CreatedAt, Order::Desc)
+        .limit(query.limit.unwrap_or(100).min(500))
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(|f| LoginFailure {
+            id: f.id,
+            email: f.email,
+            user_id: f.user_id,
+            ip_address: f.ip_address,
+            reason: f.reason,
+            created_at: f.created_at,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(failures))
+}
+
+/// Rattache l'entité à un groupe ou l'en détache. Il faut être administrateur
+/// de l'entité, de son groupe actuel et du nouveau groupe.
+pub async fn set_entity_parent(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: ValidatedJson<SetParentRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let entity = EntityEntity::find_by_id(entity_id)
+        .one(db.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Entity not found".to_string()))?;
+
+    if let Some(current) = entity.parent_id {
+        require_entity_admin(db.get_ref(), &req, current).await?;
+    }
+    if let Some(parent_id) = body.parent_id {
+        require_entity_admin(db.get_ref(), &req, parent_id).await?;
+        lifecycle::ensure_writable(db.get_ref(), parent_id).await?;
+        // Pas de cycle : le nouveau groupe ne peut pas être l'entité ni l'une de ses filiales
+        let tree = EntityTree::load(db.get_ref()).await?;
+        if tree.subtree(entity_id).contains(&parent_id) {
+            return Err(ApiError::validation("parent_id", "cycle", "An entity cannot be attached to itself or to one of its subsidiaries"));
+        }
+    }
+
+    let mut entity: EntityActiveModel = entity.into_active_model();
+    entity.parent_id = Set(body.parent_id);
+    entity.updated_at = Set(Utc::now());
+    let entity = entity.update(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(Entity::from(entity)))
+}
+
+/// Filiales directes et indirectes ; `parent_id` permet de reconstruire l'arbre.
+pub async fn list_subsidiaries(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+
+    if get_entity_role(&req, entity_id).is_none() {
+        return Err(ApiError::Forbidden("Access denied".to_string()));
+    }
+    mfa::enforce_entity_mfa(db.get_ref(), &req, entity_id).await?;
+
+    let tree = EntityTree::load(db.get_ref()).await?;
+    let subsidiaries: Vec<Entity> = EntityEntity::find()
+        .filter(EntityColumn::Id.is_in(tree.descendants(entity_id)))
+        .order_by_asc(EntityColumn::Name)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(Entity::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(subsidiaries))
+}
@@ -0,0 +1,89 @@
+use std::collections::{HashMap, HashSet};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, DbErr, EntityTrait, QuerySelect, Statement};
+use uuid::Uuid;
+use crate::entities_orm::entity::{Entity as EntityEntity, Column as EntityColumn};
+
+/// Arborescence groupe / filiales, chargée en une requête (identifiant et
+/// parent de chaque entité).
+#[derive(Debug, Clone, Default)]
+pub struct EntityTree {
+    parents: HashMap<Uuid, Option<Uuid>>,
+}
+
+impl EntityTree {
+    pub async fn load(db: &DatabaseConnection) -> Result<Self, DbErr> {
+        let parents = EntityEntity::find()
+            .select_only()
+            .columns([EntityColumn::Id, EntityColumn::ParentId])
+            .into_tuple::<(Uuid, Option<Uuid>)>()
+            .all(db)
+            .await?
+            .into_iter()
+            .collect();
+        Ok(Self { parents })
+    }
+
+    /// Ancêtres de l'entité, du parent direct jusqu'à la racine.
+    pub fn ancestors(&self, entity_id: Uuid) -> Vec<Uuid> {
+        let mut ancestors = Vec::new();
+        let mut seen = HashSet::from([entity_id]);
+        let mut current = self.parents.get(&entity_id).copied().flatten();
+        // `seen` protège d'un cycle introduit hors de l'API
+        while let Some(parent) = current.filter(|p| seen.insert(*p)) {
+            ancestors.push(parent);
+            current = self.parents.get(&parent).copied().flatten();
+        }
+        ancestors
+    }
+
+    /// Filiales directes et indirectes de l'entité (sans l'entité elle-même).
+    pub fn descendants(&self, entity_id: Uuid) -> Vec<Uuid> {
+        let mut children: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for (id, parent) in &self.parents {
+            if let Some(parent) = parent {
+                children.entry(*parent).or_default().push(*id);
+            }
+        }
+
+        let mut descendants = Vec::new();
+        let mut seen = HashSet::from([entity_id]);
+        let mut queue = vec![entity_id];
+        while let Some(id) = queue.pop() {
+            for child in children.get(&id).into_iter().flatten() {
+                if seen.insert(*child) {
+                    descendants.push(*child);
+                    queue.push(*child);
+                }
+            }
+        }
+        descendants
+    }
+
+    /// L'entité et toutes ses filiales.
+    pub fn subtree(&self, entity_id: Uuid) -> Vec<Uuid> {
+        let mut ids = vec![entity_id];
+        ids.extend(self.descendants(entity_id));
+        ids
+    }
+}
+
+/// L'entité et toutes ses filiales, par une requête récursive limitée au
+/// sous-arbre (sans charger l'arborescence complète).
+pub async fn subtree(db: &DatabaseConnection, entity_id: Uuid) -> Result<Vec<Uuid>, DbErr> {
+    // `UNION` écarte les doublons et arrête la récursion sur un cycle
+    let statement = Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"WITH RECURSIVE subtree(id) AS (
+               SELECT id FROM entities WHERE id = $1
+               UNION
+               SELECT e.id FROM entities e JOIN subtree s ON e.parent_id = s.id
+           )
+           SELECT id FROM subtree"#,
+        [entity_id.into()],
+    );
+    db.query_all(statement)
+        .await?
+        .iter()
+        .map(|row| row.try_get::<Uuid>("", "id"))
+        .collect()
+}
@@ -0,0 +1,288 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, QueryOrder, Set, IntoActiveModel, ActiveModelTrait};
+use crate::entities::identity_providers::models::*;
+use crate::errors::ApiError;
+use crate::validation::ValidatedJson;
+use crate::middleware::get_entity_role;
+use crate::auth::{mfa, saml, sso};
+use crate::outbound::OutboundClient;
+use crate::entities_orm::identity_provider::{self, Entity as IdentityProviderEntity, Column as IdentityProviderColumn, ActiveModel as IdentityProviderActiveModel};
+use uuid::Uuid;
+use chrono::Utc;
+
+async fn require_entity_admin(
+    db: &DatabaseConnection,
+    req: &HttpRequest,
+    entity_id: Uuid,
+) -> Result<(), ApiError> {
+    if get_entity_role(req, entity_id).as_deref() != Some("admin") {
+        return Err(ApiError::Forbidden("Admin access required".to_string()));
+    }
+    mfa::enforce_entity_mfa(db, req, entity_id).await
+}
+
+fn to_dto(p: identity_provider::Model) -> IdentityProvider {
+    IdentityProvider {
+        email_domains: sso::provider_domains(&p),
+        role_mapping: sso::provider_role_mapping(&p),
+        mfa_acr_values: serde_json::from_value(p.mfa_acr_values.clone()).unwrap_or_default(),
+        has_client_secret: p.client_secret.is_some(),
+        id: p.id,
+        entity_id: p.entity_id,
+        name: p.name,
+        protocol: p.protocol,
+        issuer: p.issuer,
+        client_id: p.client_id,
+        sso_url: p.sso_url,
+        certificate: p.certificate,
+        groups_claim: p.groups_claim,
+        default_role: p.default_role,
+        is_enabled: p.is_enabled,
+        mfa_trusted: p.mfa_trusted,
+        created_at: p.created_at,
+        updated_at: p.updated_at,
+    }
+}
+
+fn normalize_domains(domains: &[String]) -> Vec<String> {
+    let mut domains: Vec<String> = domains.iter()
+        .map(|d| d.trim().trim_start_matches('@').to_lowercase())
+        .collect();
+    domains.sort();
+    domains.dedup();
+    domains
+}
+
+// Les valeurs acr sont des URI comparées à l'identique : seuls les blancs sont retirés
+fn normalize_acr_values(values: &[String]) -> Vec<String> {
+    let mut values: Vec<String> = values.iter().map(|v| v.trim().to_string()).collect();
+    values.sort();
+    values.dedup();
+    values
+}
+
+// Règles propres au protocole, vérifiées sur la configuration finale
+fn check_protocol_settings(http: &OutboundClient, provider: &IdentityProviderActiveModel) -> Result<(), ApiError> {
+    let protocol = provider.protocol.as_ref();
+    if protocol == sso::PROTOCOL_OIDC {
+        if provider.client_id.as_ref().is_none() {
+            return Err(ApiError::validation("client_id", "required", "client_id is required for OIDC providers"));
+        }
+        // L'émetteur OIDC est appelé par le serveur (découverte, jetons, JWKS)
+        if http.check(provider.issuer.as_ref()).is_err() {
+            return Err(ApiError::validation("issuer", "insecure_url", "The issuer must be a public https URL"));
+        }
+    }
+    if protocol == sso::PROTOCOL_SAML {
+        match provider.sso_url.as_ref() {
+            Some(sso_url) if http.check(sso_url).is_ok() => {}
+            Some(_) => return Err(ApiError::validation("sso_url", "insecure_url", "sso_url must be a public https URL")),
+            None => return Err(ApiError::validation("sso_url", "required", "sso_url is required for SAML providers")),
+        }
+        match provider.certificate.as_ref() {
+            Some(pem) if saml::is_valid_certificate(pem) => {}
+            _ => return Err(ApiError::validation("certificate", "invalid_certificate", "A PEM encoded X.509 certificate is required for SAML providers")),
+        }
+        // Sans email vérifié par SAML, seuls les domaines revendiqués permettent de se connecter
+        if serde_json::from_value::<Vec<String>>(provider.email_domains.as_ref().clone()).unwrap_or_default().is_empty() {
+            return Err(ApiError::validation("email_domains", "required", "SAML providers must claim at least one email domain"));
+        }
+    }
+    Ok(())
+}
+
+// Un domaine ne peut être revendiqué que par un seul fournisseur actif de
+// l'entité. Les autres entités ne peuvent pas l'utiliser : un domaine n'est
+// vérifié que pour une seule d'entre elles.
+async fn check_domain_conflicts(
+    db: &DatabaseConnection,
+    entity_id: Uuid,
+    provider_id: Uuid,
+    domains: &[String],
+) -> Result<(), ApiError> {
+    let others = IdentityProviderEntity::find()
+        .filter(IdentityProviderColumn::EntityId.eq(entity_id))
+        .filter(IdentityProviderColumn::IsEnabled.eq(true))
+        .filter(IdentityProviderColumn::Id.ne(provider_id))
+        .all(db)
+        .await?;
+
+    for other in others {
+        if let Some(domain) = sso::provider_domains(&other).into_iter().find(|d| domains.contains(d)) {
+            return Err(ApiError::Conflict(format!("Email domain {} is already claimed by another identity provider", domain)));
+        }
+    }
+    Ok(())
+}
+
+async fn find_provider(
+    db: &DatabaseConnection,
+    entity_id: Uuid,
+    provider_id: Uuid,
+) -> Result<identity_provider::Model, ApiError> {
+    IdentityProviderEntity::find_by_id(provider_id)
+        .filter(IdentityProviderColumn::EntityId.eq(entity_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Identity provider not found".to_string()))
+}
+
+pub async fn list_identity_providers(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let providers: Vec<IdentityProvider> = IdentityProviderEntity::find()
+        .filter(IdentityProviderColumn::EntityId.eq(entity_id))
+        .order_by_asc(IdentityProviderColumn::Name)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(to_dto)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(providers))
+}
+
+pub async fn create_identity_provider(
+    db: web::Data<DatabaseConnection>,
+    http: web::Data<OutboundClient>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: ValidatedJson<CreateIdentityProviderRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let id = Uuid::new_v4();
+    let domains = normalize_domains(body.email_domains.as_deref().unwrap_or_default());
+    let is_enabled = body.is_enabled.unwrap_or(true);
+    if is_enabled {
+        check_domain_conflicts(db.get_ref(), entity_id, id, &domains).await?;
+    }
+
+    let now = Utc::now();
+    let provider = IdentityProviderActiveModel {
+        id: Set(id),
+        entity_id: Set(entity_id),
+        name: Set(body.name.trim().to_string()),
+        protocol: Set(body.protocol.clone()),
+        issuer: Set(body.issuer.trim().to_string()),
+        client_id: Set(body.client_id.clone()),
+        client_secret: Set(body.client_secret.clone()),
+        sso_url: Set(body.sso_url.clone()),
+        certificate: Set(body.certificate.clone()),
+        email_domains: Set(serde_json::json!(domains)),
+        groups_claim: Set(body.groups_claim.clone().unwrap_or_else(|| "groups".to_string())),
+        role_mapping: Set(serde_json::json!(body.role_mapping.clone().unwrap_or_default())),
+        default_role: Set(body.default_role.clone()),
+        is_enabled: Set(is_enabled),
+        mfa_trusted: Set(body.mfa_trusted.unwrap_or(false)),
+        mfa_acr_values: Set(serde_json::json!(normalize_acr_values(body.mfa_acr_values.as_deref().unwrap_or_default()))),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+    check_protocol_settings(&http, &provider)?;
+
+    let provider = IdentityProviderEntity::insert(provider)
+        .exec_with_returning(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Created().json(to_dto(provider)))
+}
+
+pub async fn get_identity_provider(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, ApiError> {
+    let (entity_id, provider_id) = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let provider = find_provider(db.get_ref(), entity_id, provider_id).await?;
+    Ok(HttpResponse::Ok().json(to_dto(provider)))
+}
+
+pub async fn update_identity_provider(
+    db: web::Data<DatabaseConnection>,
+    http: web::Data<OutboundClient>,
+    req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+    body: ValidatedJson<UpdateIdentityProviderRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let (entity_id, provider_id) = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let existing = find_provider(db.get_ref(), entity_id, provider_id).await?;
+    let mut domains = sso::provider_domains(&existing);
+    let mut provider = existing.into_active_model();
+
+    if let Some(name) = &body.name {
+        provider.name = Set(name.trim().to_string());
+    }
+    if let Some(issuer) = &body.issuer {
+        provider.issuer = Set(issuer.trim().to_string());
+    }
+    if let Some(client_id) = &body.client_id {
+        provider.client_id = Set(Some(client_id.clone()));
+    }
+    if let Some(client_secret) = &body.client_secret {
+        provider.client_secret = Set(Some(client_secret.clone()));
+    }
+    if let Some(sso_url) = &body.sso_url {
+        provider.sso_url = Set(Some(sso_url.clone()));
+    }
+    if let Some(certificate) = &body.certificate {
+        provider.certificate = Set(Some(certificate.clone()));
+    }
+    if let Some(email_domains) = &body.email_domains {
+        domains = normalize_domains(email_domains);
+        provider.email_domains = Set(serde_json::json!(domains));
+    }
+    if let Some(groups_claim) = &body.groups_claim {
+        provider.groups_claim = Set(groups_claim.clone());
+    }
+    if let Some(role_mapping) = &body.role_mapping {
+        provider.role_mapping = Set(serde_json::json!(role_mapping));
+    }
+    if let Some(default_role) = &body.default_role {
+        let default_role = default_role.trim();
+        provider.default_role = Set((!default_role.is_empty()).then(|| default_role.to_string()));
+    }
+    if let Some(is_enabled) = body.is_enabled {
+        provider.is_enabled = Set(is_enabled);
+    }
+    if let Some(mfa_trusted) = body.mfa_trusted {
+        provider.mfa_trusted = Set(mfa_trusted);
+    }
+    if let Some(mfa_acr_values) = &body.mfa_acr_values {
+        provider.mfa_acr_values = Set(serde_json::json!(normalize_acr_values(mfa_acr_values)));
+    }
+    check_protocol_settings(&http, &provider)?;
+    if *provider.is_enabled.as_ref() {
+        check_domain_conflicts(db.get_ref(), entity_id, provider_id, &domains).await?;
+    }
+    provider.updated_at = Set(Utc::now());
+
+    let provider = provider.update(db.get_ref()).await?;
+    Ok(HttpResponse::Ok().json(to_dto(provider)))
+}
+
+pub async fn delete_identity_provider(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, ApiError> {
+    let (entity_id, provider_id) = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let provider = find_provider(db.get_ref(), entity_id, provider_id).await?;
+    IdentityProviderEntity::delete_by_id(provider.id)
+        .exec(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::{Validate, ValidationError};
+use crate::auth::sso::{PROTOCOL_OIDC, PROTOCOL_SAML};
+use crate::entities::models::validate_entity_role;
+use crate::validation::{non_blank_items, not_blank, one_of};
+
+pub const PROTOCOLS: &[&str] = &[PROTOCOL_OIDC, PROTOCOL_SAML];
+
+fn validate_protocol(value: &str) -> Result<(), ValidationError> {
+    one_of(value, PROTOCOLS)
+}
+
+// Chaîne vide acceptée : retire le rôle par défaut
+fn validate_optional_role(value: &str) -> Result<(), ValidationError> {
+    match value.trim() {
+        "" => Ok(()),
+        role => validate_entity_role(role),
+    }
+}
+
+// Association groupe (ou rôle) de l'IdP -> rôle dans l'entité
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct RoleMappingRule {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub group: String,
+    #[validate(custom(function = "validate_entity_role"))]
+    pub role: String,
+}
+
+// Le secret client n'est jamais renvoyé
+#[derive(Debug, Serialize)]
+pub struct IdentityProvider {
+    pub id: Uuid,
+    pub entity_id: Uuid,
+    pub name: String,
+    pub protocol: String,
+    pub issuer: String,
+    pub client_id: Option<String>,
+    pub has_client_secret: bool,
+    pub sso_url: Option<String>,
+    pub certificate: Option<String>,
+    pub email_domains: Vec<String>,
+    pub groups_claim: String,
+    pub role_mapping: Vec<RoleMappingRule>,
+    pub default_role: Option<String>,
+    pub is_enabled: bool,
+    pub mfa_trusted: bool,
+    pub mfa_acr_values: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateIdentityProviderRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub name: String,
+    #[validate(custom(function = "validate_protocol"))]
+    pub protocol: String,
+    #[validate(url)]
+    pub issuer: String,
+    #[validate(length(min = 1, max = 255))]
+    pub client_id: Option<String>,
+    #[validate(length(min = 1, max = 1000))]
+    pub client_secret: Option<String>,
+    #[validate(url)]
+    pub sso_url: Option<String>,
+    #[validate(length(max = 20000))]
+    pub certificate: Option<String>,
+    #[validate(length(max = 50), custom(function = "non_blank_items"))]
+    pub email_domains: Option<Vec<String>>,
+    #[validate(length(min = 1, max = 255))]
+    pub groups_claim: Option<String>,
+    #[validate(length(max = 100), nested)]
+    pub role_mapping: Option<Vec<RoleMappingRule>>,
+    #[validate(custom(function = "validate_entity_role"))]
+    pub default_role: Option<String>,
+    pub is_enabled: Option<bool>,
+    // Second facteur vérifié par le fournisseur : amr "mfa" (OIDC) ou acr /
+    // AuthnContextClassRef listé dans mfa_acr_values
+    pub mfa_trusted: Option<bool>,
+    #[validate(length(max = 20), custom(function = "non_blank_items"))]
+    pub mfa_acr_values: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateIdentityProviderRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub name: Option<String>,
+    #[validate(url)]
+    pub issuer: Option<String>,
+    #[validate(length(min = 1, max = 255))]
+    pub client_id: Option<String>,
+    #[validate(length(min = 1, max = 1000))]
+    pub client_secret: Option<String>,
+    #[validate(url)]
+    pub sso_url: Option<String>,
+    #[validate(length(max = 20000))]
+    pub certificate: Option<String>,
+    #[validate(length(max = 50), custom(function = "non_blank_items"))]
+    pub email_domains: Option<Vec<String>>,
+    #[validate(length(min = 1, max = 255))]
+    pub groups_claim: Option<String>,
+    #[validate(length(max = 100), nested)]
+    pub role_mapping: Option<Vec<RoleMappingRule>>,
+    // Chaîne vide : supprime le rôle par défaut (plus de provisioning sans groupe reconnu)
+    #[validate(custom(function = "validate_optional_role"))]
+    pub default_role: Option<String>,
+    pub is_enabled: Option<bool>,
+    pub mfa_trusted: Option<bool>,
+    #[validate(length(max = 20), custom(function = "non_blank_items"))]
+    pub mfa_acr_values: Option<Vec<String>>,
+}
@@ -0,0 +1,411 @@
+//! Export complet d'une entité (réversibilité) : un fichier JSON par table,
+//! les fichiers stockés, et un `manifest.json` récapitulatif.
+use std::collections::BTreeMap;
+use std::io::{Cursor, Write};
+use actix_web::web;
+use chrono::Utc;
+use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter};
+use serde::Serialize;
+use serde_json::json;
+use uuid::Uuid;
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+use crate::config::Config;
+use crate::entities::catalogue::storage;
+use crate::errors::ApiError;
+use crate::entities_orm::entity;
+use crate::entities_orm::user_entity::{Entity as UserEntityEntity, Column as UserEntityColumn};
+use crate::entities_orm::user::Entity as UserEntity;
+use crate::entities_orm::register_entry::{Entity as RegisterEntryEntity, Column as RegisterEntryColumn};
+use crate::entities_orm::rgpd_data_category::{Entity as DataCategoryEntity, Column as DataCategoryColumn};
+use crate::entities_orm::rgpd_data_subject::{Entity as DataSubjectEntity, Column as DataSubjectColumn};
+use crate::entities_orm::rgpd_party::{Entity as PartyEntity, Column as PartyColumn};
+use crate::entities_orm::rgpd_security_measure::{Entity as SecurityMeasureEntity, Column as SecurityMeasureColumn};
+use crate::entities_orm::register_data_category::{Entity as RegisterDataCategoryEntity, Column as RegisterDataCategoryColumn};
+use crate::entities_orm::register_data_subject::{Entity as RegisterDataSubjectEntity, Column as RegisterDataSubjectColumn};
+use crate::entities_orm::register_party::{Entity as RegisterPartyEntity, Column as RegisterPartyColumn};
+use crate::entities_orm::register_security_measure::{Entity as RegisterSecurityMeasureEntity, Column as RegisterSecurityMeasureColumn};
+use crate::entities_orm::rgpd_transfer::{Entity as TransferEntity, Column as TransferColumn};
+use crate::entities_orm::rgpd_register_export::{Entity as RegisterExportEntity, Column as RegisterExportColumn};
+use crate::entities_orm::rgpd_dpia::{Entity as DpiaEntity, Column as DpiaColumn};
+use crate::entities_orm::rgpd_dpia_risk::{Entity as DpiaRiskEntity, Column as DpiaRiskColumn};
+use crate::entities_orm::rgpd_dpia_measure::{Entity as DpiaMeasureEntity, Column as DpiaMeasureColumn};
+use crate::entities_orm::rgpd_dpia_version::{Entity as DpiaVersionEntity, Column as DpiaVersionColumn};
+use crate::entities_orm::rgpd_dpia_review::{Entity as DpiaReviewEntity, Column as DpiaReviewColumn};
+use crate::entities_orm::rgpd_processor::{Entity as ProcessorEntity, Column as ProcessorColumn};
+use crate::entities_orm::rgpd_sub_processor::{Entity as SubProcessorEntity, Column as SubProcessorColumn};
+use crate::entities_orm::rgpd_processor_endpoint::{Entity as ProcessorEndpointEntity, Column as ProcessorEndpointColumn};
+use crate::entities_orm::rgpd_consent_purpose::{Entity as ConsentPurposeEntity, Column as ConsentPurposeColumn};
+use crate::entities_orm::rgpd_consent::{Entity as ConsentEntity, Column as ConsentColumn};
+use crate::entities_orm::access_request::{Entity as AccessRequestEntity, Column as AccessRequestColumn};
+use crate::entities_orm::breach::{Entity as BreachEntity, Column as BreachColumn};
+use crate::entities_orm::rgpd_breach_attachment::{Entity as BreachAttachmentEntity, Column as BreachAttachmentColumn};
+use crate::entities_orm::rgpd_breach_template::{Entity as BreachTemplateEntity, Column as BreachTemplateColumn};
+use crate::entities_orm::rgpd_retention_policy::{Entity as RetentionPolicyEntity, Column as RetentionPolicyColumn};
+use crate::entities_orm::rgpd_retention_run::{Entity as RetentionRunEntity, Column as RetentionRunColumn};
+use crate::entities_orm::endpoint::{Entity as EndpointEntity, Column as EndpointColumn};
+use crate::entities_orm::license_key::{Entity as LicenseKeyEntity, Column as LicenseKeyColumn};
+use crate::entities_orm::software_version::{Entity as SoftwareVersionEntity, Column as SoftwareVersionColumn};
+use crate::entities_orm::encryption_algorithm::{Entity as EncryptionAlgorithmEntity, Column as EncryptionAlgorithmColumn};
+use crate::entities_orm::catalogue_relation::{Entity as CatalogueRelationEntity, Column as CatalogueRelationColumn};
+use crate::entities_orm::identity_provider::{Entity as IdentityProviderEntity, Column as IdentityProviderColumn};
+use crate::entities_orm::scim_token::{Entity as ScimTokenEntity, Column as ScimTokenColumn};
+use crate::entities_orm::scim_user::{Entity as ScimUserEntity, Column as ScimUserColumn};
+use crate::entities_orm::scim_group::{Entity as ScimGroupEntity, Column as ScimGroupColumn};
+use crate::entities_orm::scim_group_member::{Entity as ScimGroupMemberEntity, Column as ScimGroupMemberColumn};
+use crate::entities_orm::service_account::{Entity as ServiceAccountEntity, Column as ServiceAccountColumn};
+use crate::entities_orm::api_token::{Entity as ApiTokenEntity, Column as ApiTokenColumn};
+use crate::entities_orm::entity_lifecycle_event::{Entity as LifecycleEventEntity, Column as LifecycleEventColumn};
+
+pub const FORMAT: &str = "hypercyber-entity-export";
+pub const FORMAT_VERSION: u32 = 1;
+
+pub struct EntityArchive {
+    pub bytes: Vec<u8>,
+    pub file_count: usize,
+    pub missing_files: Vec<String>, // Fichiers référencés mais illisibles dans le stockage
+}
+
+#[derive(Default)]
+struct Entries {
+    files: Vec<(String, Vec<u8>)>,
+    tables: BTreeMap<String, usize>, // Nombre de lignes par table, repris dans le manifeste
+}
+
+impl Entries {
+    fn json(&mut self, name: &str, value: &impl Serialize) -> Result<(), ApiError> {
+        let data = serde_json::to_vec_pretty(value)
+            .map_err(ApiError::internal("Failed to serialize export"))?;
+        self.files.push((name.to_string(), data));
+        Ok(())
+    }
+
+    // Les secrets (empreintes de jetons, secrets clients) ne sont jamais exportés
+    fn table<T: Serialize>(&mut self, name: &str, rows: &[T], secrets: &[&str]) -> Result<(), ApiError> {
+        let mut value = serde_json::to_value(rows)
+            .map_err(ApiError::internal("Failed to serialize export"))?;
+        for row in value.as_array_mut().into_iter().flatten() {
+            if let Some(row) = row.as_object_mut() {
+                for field in secrets {
+                    row.remove(*field);
+                }
+            }
+        }
+        self.json(&format!("{}.json", name), &value)?;
+        self.tables.insert(name.to_string(), rows.len());
+        Ok(())
+    }
+}
+
+pub async fn build(
+    db: &DatabaseConnection,
+    config: &Config,
+    entity: &entity::Model,
+    exported_by: Uuid,
+) -> Result<EntityArchive, ApiError> {
+    let entity_id = entity.id;
+    let mut entries = Entries::default();
+    entries.json("entity.json", entity)?;
+
+    let members: Vec<serde_json::Value> = UserEntityEntity::find()
+        .filter(UserEntityColumn::EntityId.eq(entity_id))
+        .find_also_related(UserEntity)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|(membership, user)| json!({
+            "user_id": membership.user_id,
+            "email": user.as_ref().map(|u| &u.email),
+            "first_name": user.as_ref().and_then(|u| u.first_name.as_ref()),
+            "last_name": user.as_ref().and_then(|u| u.last_name.as_ref()),
+            "role": membership.role,
+            "created_at": membership.created_at,
+        }))
+        .collect();
+    entries.table("members", &members, &[])?;
+
+    // Registre RGPD (sans les traitements hérités du groupe)
+    let register = RegisterEntryEntity::find()
+        .filter(RegisterEntryColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    let entry_ids: Vec<Uuid> = register.iter().map(|e| e.id).collect();
+    entries.table("rgpd/register", &register, &[])?;
+    let data_categories = DataCategoryEntity::find()
+        .filter(DataCategoryColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("rgpd/data_categories", &data_categories, &[])?;
+    let data_subjects = DataSubjectEntity::find()
+        .filter(DataSubjectColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("rgpd/data_subjects", &data_subjects, &[])?;
+    let parties = PartyEntity::find()
+        .filter(PartyColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("rgpd/parties", &parties, &[])?;
+    let security_measures = SecurityMeasureEntity::find()
+        .filter(SecurityMeasureColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("rgpd/security_measures", &security_measures, &[])?;
+    let register_data_categories = RegisterDataCategoryEntity::find()
+        .filter(RegisterDataCategoryColumn::RegisterEntryId.is_in(entry_ids.clone()))
+        .all(db)
+        .await?;
+    entries.table("rgpd/register_data_categories", &register_data_categories, &[])?;
+    let register_data_subjects = RegisterDataSubjectEntity::find()
+        .filter(RegisterDataSubjectColumn::RegisterEntryId.is_in(entry_ids.clone()))
+        .all(db)
+        .await?;
+    entries.table("rgpd/register_data_subjects", &register_data_subjects, &[])?;
+    let register_parties = RegisterPartyEntity::find()
+        .filter(RegisterPartyColumn::RegisterEntryId.is_in(entry_ids.clone()))
+        .all(db)
+        .await?;
+    entries.table("rgpd/register_parties", &register_parties, &[])?;
+    let register_security_measures = RegisterSecurityMeasureEntity::find()
+        .filter(RegisterSecurityMeasureColumn::RegisterEntryId.is_in(entry_ids.clone()))
+        .all(db)
+        .await?;
+    entries.table("rgpd/register_security_measures", &register_security_measures, &[])?;
+    let transfers = TransferEntity::find()
+        .filter(TransferColumn::RegisterEntryId.is_in(entry_ids))
+        .all(db)
+        .await?;
+    entries.table("rgpd/transfers", &transfers, &[])?;
+    let register_exports = RegisterExportEntity::find()
+        .filter(RegisterExportColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("rgpd/register_exports", &register_exports, &[])?;
+    let dpias = DpiaEntity::find()
+        .filter(DpiaColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    let dpia_ids: Vec<Uuid> = dpias.iter().map(|d| d.id).collect();
+    entries.table("rgpd/dpias", &dpias, &[])?;
+    let dpia_risks = DpiaRiskEntity::find()
+        .filter(DpiaRiskColumn::DpiaId.is_in(dpia_ids.clone()))
+        .all(db)
+        .await?;
+    entries.table("rgpd/dpia_risks", &dpia_risks, &[])?;
+    let dpia_measures = DpiaMeasureEntity::find()
+        .filter(DpiaMeasureColumn::DpiaId.is_in(dpia_ids.clone()))
+        .all(db)
+        .await?;
+    entries.table("rgpd/dpia_measures", &dpia_measures, &[])?;
+    let dpia_versions = DpiaVersionEntity::find()
+        .filter(DpiaVersionColumn::DpiaId.is_in(dpia_ids.clone()))
+        .all(db)
+        .await?;
+    entries.table("rgpd/dpia_versions", &dpia_versions, &[])?;
+    let dpia_reviews = DpiaReviewEntity::find()
+        .filter(DpiaReviewColumn::DpiaId.is_in(dpia_ids))
+        .all(db)
+        .await?;
+    entries.table("rgpd/dpia_reviews", &dpia_reviews, &[])?;
+    let processors = ProcessorEntity::find()
+        .filter(ProcessorColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    let processor_ids: Vec<Uuid> = processors.iter().map(|p| p.id).collect();
+    entries.table("rgpd/processors", &processors, &[])?;
+    let sub_processors = SubProcessorEntity::find()
+        .filter(SubProcessorColumn::ProcessorId.is_in(processor_ids.clone()))
+        .all(db)
+        .await?;
+    entries.table("rgpd/sub_processors", &sub_processors, &[])?;
+    let processor_transfers = TransferEntity::find()
+        .filter(TransferColumn::ProcessorId.is_in(processor_ids.clone()))
+        .all(db)
+        .await?;
+    entries.table("rgpd/processor_transfers", &processor_transfers, &[])?;
+    let processor_endpoints = ProcessorEndpointEntity::find()
+        .filter(ProcessorEndpointColumn::ProcessorId.is_in(processor_ids))
+        .all(db)
+        .await?;
+    entries.table("rgpd/processor_endpoints", &processor_endpoints, &[])?;
+    let consent_purposes = ConsentPurposeEntity::find()
+        .filter(ConsentPurposeColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("rgpd/consent_purposes", &consent_purposes, &[])?;
+    let consents = ConsentEntity::find()
+        .filter(ConsentColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("rgpd/consents", &consents, &[])?;
+    let access_requests = AccessRequestEntity::find()
+        .filter(AccessRequestColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("rgpd/access_requests", &access_requests, &[])?;
+    let breaches = BreachEntity::find()
+        .filter(BreachColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("rgpd/breaches", &breaches, &[])?;
+    let breach_attachments = BreachAttachmentEntity::find()
+        .filter(BreachAttachmentColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("rgpd/breach_attachments", &breach_attachments, &[])?;
+    let breach_templates = BreachTemplateEntity::find()
+        .filter(BreachTemplateColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("rgpd/breach_templates", &breach_templates, &[])?;
+    let retention_policies = RetentionPolicyEntity::find()
+        .filter(RetentionPolicyColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("rgpd/retention_policies", &retention_policies, &[])?;
+    let retention_runs = RetentionRunEntity::find()
+        .filter(RetentionRunColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("rgpd/retention_runs", &retention_runs, &[])?;
+
+    // Catalogue propre à l'entité
+    let endpoints = EndpointEntity::find()
+        .filter(EndpointColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("catalogue/endpoints", &endpoints, &[])?;
+    let license_keys = LicenseKeyEntity::find()
+        .filter(LicenseKeyColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("catalogue/license_keys", &license_keys, &[])?;
+    let software_versions = SoftwareVersionEntity::find()
+        .filter(SoftwareVersionColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("catalogue/software_versions", &software_versions, &[])?;
+    let encryption_algorithms = EncryptionAlgorithmEntity::find()
+        .filter(EncryptionAlgorithmColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("catalogue/encryption_algorithms", &encryption_algorithms, &[])?;
+    let relations = CatalogueRelationEntity::find()
+        .filter(CatalogueRelationColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("catalogue/relations", &relations, &[])?;
+
+    // Annuaire : fournisseurs d'identité, provisionnement SCIM, comptes de service
+    let identity_providers = IdentityProviderEntity::find()
+        .filter(IdentityProviderColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("identity_providers", &identity_providers, &["client_secret"])?;
+    let scim_tokens = ScimTokenEntity::find()
+        .filter(ScimTokenColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("scim/tokens", &scim_tokens, &["token_hash"])?;
+    let scim_users = ScimUserEntity::find()
+        .filter(ScimUserColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("scim/users", &scim_users, &[])?;
+    let scim_groups = ScimGroupEntity::find()
+        .filter(ScimGroupColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    let scim_group_members = ScimGroupMemberEntity::find()
+        .filter(ScimGroupMemberColumn::GroupId.is_in(scim_groups.iter().map(|g| g.id)))
+        .all(db)
+        .await?;
+    entries.table("scim/groups", &scim_groups, &[])?;
+    entries.table("scim/group_members", &scim_group_members, &[])?;
+    let service_accounts = ServiceAccountEntity::find()
+        .filter(ServiceAccountColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    let service_account_tokens = ApiTokenEntity::find()
+        .filter(ApiTokenColumn::UserId.is_in(service_accounts.iter().map(|a| a.user_id)))
+        .all(db)
+        .await?;
+    entries.table("service_accounts", &service_accounts, &[])?;
+    entries.table("service_account_tokens", &service_account_tokens, &["token_hash"])?;
+
+    let events = LifecycleEventEntity::find()
+        .filter(LifecycleEventColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    entries.table("lifecycle_events", &events, &[])?;
+
+    // Fichiers des clés de licence et pièces jointes des violations, lus dans le
+    // stockage où ils ont été déposés
+    let file_name = |name: Option<&str>, default: &'static str| -> String {
+        name.and_then(|n| std::path::Path::new(n).file_name())
+            .and_then(|n| n.to_str())
+            .unwrap_or(default)
+            .to_string()
+    };
+    let mut stored: Vec<(String, &str, &str)> = license_keys.iter()
+        .filter_map(|key| key.file_path.as_deref().map(|path| (
+            format!("files/license_keys/{}/{}", key.id, file_name(key.file_name.as_deref(), "license.key")),
+            key.storage_type.as_str(),
+            path,
+        )))
+        .collect();
+    stored.extend(breach_attachments.iter().map(|a| (
+        format!("files/breach_attachments/{}/{}", a.id, file_name(Some(&a.file_name), "attachment")),
+        a.storage_type.as_str(),
+        a.file_path.as_str(),
+    )));
+
+    let mut file_count = 0;
+    let mut missing_files = Vec::new();
+    for (name, storage_type, file_path) in stored {
+        let storage = storage::for_type(config, storage_type).await;
+        match storage.get_file(file_path).await {
+            Ok(data) => {
+                entries.files.push((name, data));
+                file_count += 1;
+            }
+            Err(e) => {
+                log::warn!("Export of entity {}: cannot read {}: {}", entity_id, file_path, e);
+                missing_files.push(file_path.to_string());
+            }
+        }
+    }
+
+    let manifest = json!({
+        "format": FORMAT,
+        "version": FORMAT_VERSION,
+        "entity_id": entity_id,
+        "entity_name": entity.name,
+        "exported_at": Utc::now(),
+        "exported_by": exported_by,
+        "tables": entries.tables,
+        "files": file_count,
+        "missing_files": missing_files,
+    });
+    let manifest = serde_json::to_vec_pretty(&manifest)
+        .map_err(ApiError::internal("Failed to serialize export"))?;
+    entries.files.insert(0, ("manifest.json".to_string(), manifest));
+
+    // Compression hors de la boucle d'événements
+    let files = entries.files;
+    let bytes = web::block(move || write_zip(files))
+        .await
+        .map_err(ApiError::internal("Export task failed"))?
+        .map_err(ApiError::internal("Failed to build export archive"))?;
+
+    Ok(EntityArchive { bytes, file_count, missing_files })
+}
+
+fn write_zip(files: Vec<(String, Vec<u8>)>) -> zip::result::ZipResult<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, data) in files {
+        zip.start_file(name, options)?;
+        zip.write_all(&data)?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
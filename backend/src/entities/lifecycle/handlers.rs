@@ -0,0 +1,209 @@
+use actix_web::{web, HttpResponse, HttpRequest, http::header};
+use chrono::{Duration, Utc};
+use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, QueryOrder, PaginatorTrait, Set, IntoActiveModel, ActiveModelTrait, TransactionTrait};
+use serde_json::json;
+use crate::auth::mfa;
+use crate::config::Config;
+use crate::entities::lifecycle::{export, models::*, record_event};
+use crate::entities::models::Entity;
+use crate::errors::ApiError;
+use crate::validation::ValidatedJson;
+use crate::middleware::{get_current_user_id, get_entity_role};
+use crate::entities_orm::entity::{self, Entity as EntityEntity, Column as EntityColumn};
+use crate::entities_orm::entity_lifecycle_event::{Entity as LifecycleEventEntity, Column as LifecycleEventColumn};
+use uuid::Uuid;
+
+async fn require_entity_admin(
+    db: &DatabaseConnection,
+    req: &HttpRequest,
+    entity_id: Uuid,
+) -> Result<Uuid, ApiError> {
+    let user_id = get_current_user_id(req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+
+    if get_entity_role(req, entity_id).as_deref() != Some("admin") {
+        return Err(ApiError::Forbidden("Admin access required".to_string()));
+    }
+    mfa::enforce_entity_mfa(db, req, entity_id).await?;
+    Ok(user_id)
+}
+
+async fn find_entity(db: &DatabaseConnection, entity_id: Uuid) -> Result<entity::Model, ApiError> {
+    EntityEntity::find_by_id(entity_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Entity not found".to_string()))
+}
+
+// Applique le changement d'état et trace l'événement dans la même transaction
+async fn transition(
+    db: &DatabaseConnection,
+    entity: entity::Model,
+    action: &str,
+    actor_id: Uuid,
+    details: Option<serde_json::Value>,
+    apply: impl FnOnce(&mut entity::ActiveModel),
+) -> Result<entity::Model, ApiError> {
+    let txn = db.begin().await?;
+    let mut active = entity.into_active_model();
+    apply(&mut active);
+    active.updated_at = Set(Utc::now());
+    let entity = active.update(&txn).await?;
+    record_event(&txn, &entity, action, Some(actor_id), details).await?;
+    txn.commit().await?;
+    Ok(entity)
+}
+
+/// Passe l'entité en lecture seule ; ses données restent consultables et exportables.
+pub async fn archive_entity(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    let user_id = require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let entity = find_entity(db.get_ref(), entity_id).await?;
+    if entity.archived_at.is_some() {
+        return Err(ApiError::Conflict("Entity is already archived".to_string()));
+    }
+
+    let entity = transition(db.get_ref(), entity, ACTION_ARCHIVED, user_id, None, |e| {
+        e.archived_at = Set(Some(Utc::now()));
+    }).await?;
+
+    Ok(HttpResponse::Ok().json(Entity::from(entity)))
+}
+
+pub async fn unarchive_entity(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    let user_id = require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let entity = find_entity(db.get_ref(), entity_id).await?;
+    if entity.archived_at.is_none() {
+        return Err(ApiError::Conflict("Entity is not archived".to_string()));
+    }
+    if entity.deletion_scheduled_at.is_some() {
+        return Err(ApiError::Conflict("Cancel the scheduled deletion before unarchiving".to_string()));
+    }
+
+    let entity = transition(db.get_ref(), entity, ACTION_UNARCHIVED, user_id, None, |e| {
+        e.archived_at = Set(None);
+    }).await?;
+
+    Ok(HttpResponse::Ok().json(Entity::from(entity)))
+}
+
+/// Programme la suppression définitive à l'issue du délai de grâce. L'entité est
+/// archivée dans l'intervalle ; les filiales doivent être détachées au préalable.
+pub async fn schedule_deletion(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: ValidatedJson<ScheduleDeletionRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    let user_id = require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let entity = find_entity(db.get_ref(), entity_id).await?;
+    if body.confirm_name != entity.name {
+        return Err(ApiError::validation("confirm_name", "mismatch", "confirm_name must match the entity name"));
+    }
+    if entity.deletion_scheduled_at.is_some() {
+        return Err(ApiError::Conflict("Deletion is already scheduled".to_string()));
+    }
+    let subsidiaries = EntityEntity::find()
+        .filter(EntityColumn::ParentId.eq(entity_id))
+        .count(db.get_ref())
+        .await?;
+    if subsidiaries > 0 {
+        return Err(ApiError::Conflict("Detach or delete the subsidiaries first".to_string()));
+    }
+
+    let now = Utc::now();
+    let scheduled_for = now + Duration::days(config.entity_deletion_grace_days.max(0));
+    let details = json!({ "scheduled_for": scheduled_for });
+    let entity = transition(db.get_ref(), entity, ACTION_DELETION_SCHEDULED, user_id, Some(details), |e| {
+        if e.archived_at.as_ref().is_none() {
+            e.archived_at = Set(Some(now));
+        }
+        e.deletion_scheduled_at = Set(Some(scheduled_for));
+        e.deletion_requested_by = Set(Some(user_id));
+    }).await?;
+
+    Ok(HttpResponse::Ok().json(Entity::from(entity)))
+}
+
+/// Annule la suppression programmée ; l'entité reste archivée.
+pub async fn cancel_deletion(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    let user_id = require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let entity = find_entity(db.get_ref(), entity_id).await?;
+    if entity.deletion_scheduled_at.is_none() {
+        return Err(ApiError::Conflict("No deletion is scheduled".to_string()));
+    }
+
+    let entity = transition(db.get_ref(), entity, ACTION_DELETION_CANCELLED, user_id, None, |e| {
+        e.deletion_scheduled_at = Set(None);
+        e.deletion_requested_by = Set(None);
+    }).await?;
+
+    Ok(HttpResponse::Ok().json(Entity::from(entity)))
+}
+
+/// Archive zip de toutes les données de l'entité (JSON par table et fichiers stockés).
+pub async fn export_entity(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    let user_id = require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let entity = find_entity(db.get_ref(), entity_id).await?;
+    let archive = export::build(db.get_ref(), &config, &entity, user_id).await?;
+
+    let details = json!({
+        "size": archive.bytes.len(),
+        "files": archive.file_count,
+        "missing_files": archive.missing_files,
+    });
+    record_event(db.get_ref(), &entity, ACTION_EXPORTED, Some(user_id), Some(details)).await?;
+
+    let file_name = format!("entity-{}-{}.zip", entity.id, Utc::now().format("%Y%m%d%H%M%S"));
+    Ok(HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file_name)))
+        .body(archive.bytes))
+}
+
+pub async fn list_lifecycle_events(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let events: Vec<LifecycleEvent> = LifecycleEventEntity::find()
+        .filter(LifecycleEventColumn::EntityId.eq(entity_id))
+        .order_by_desc(LifecycleEventColumn::CreatedAt)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(LifecycleEvent::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(events))
+}
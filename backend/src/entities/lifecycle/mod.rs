@@ -0,0 +1,51 @@
+pub mod export;
+pub mod handlers;
+pub mod models;
+pub mod purge;
+
+use chrono::Utc;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, Set};
+use uuid::Uuid;
+use crate::errors::ApiError;
+use crate::entities_orm::entity::{self, Entity as EntityEntity};
+use crate::entities_orm::entity_lifecycle_event::{Entity as LifecycleEventEntity, ActiveModel as LifecycleEventActiveModel};
+
+/// Vrai si l'entité est archivée, y compris en attente de suppression.
+pub async fn is_archived(db: &DatabaseConnection, entity_id: Uuid) -> Result<bool, DbErr> {
+    Ok(EntityEntity::find_by_id(entity_id)
+        .one(db)
+        .await?
+        .is_some_and(|e| e.archived_at.is_some()))
+}
+
+/// Refuse toute modification des données d'une entité archivée.
+pub async fn ensure_writable(db: &DatabaseConnection, entity_id: Uuid) -> Result<(), ApiError> {
+    if is_archived(db, entity_id).await? {
+        return Err(ApiError::Conflict("Entity is archived and read-only".to_string()));
+    }
+    Ok(())
+}
+
+/// Trace une étape du cycle de vie. Le nom de l'entité est recopié : le
+/// journal reste lisible après la purge.
+pub async fn record_event<C: ConnectionTrait>(
+    db: &C,
+    entity: &entity::Model,
+    action: &str,
+    actor_id: Option<Uuid>,
+    details: Option<serde_json::Value>,
+) -> Result<(), DbErr> {
+    let event = LifecycleEventActiveModel {
+        id: Set(Uuid::new_v4()),
+        entity_id: Set(entity.id),
+        entity_name: Set(entity.name.clone()),
+        action: Set(action.to_string()),
+        actor_id: Set(actor_id),
+        details: Set(details),
+        created_at: Set(Utc::now()),
+    };
+    LifecycleEventEntity::insert(event)
+        .exec(db)
+        .await?;
+    Ok(())
+}
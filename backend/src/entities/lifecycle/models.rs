@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+use crate::entities_orm::entity_lifecycle_event;
+
+pub const ACTION_ARCHIVED: &str = "archived";
+pub const ACTION_UNARCHIVED: &str = "unarchived";
+pub const ACTION_DELETION_SCHEDULED: &str = "deletion_scheduled";
+pub const ACTION_DELETION_CANCELLED: &str = "deletion_cancelled";
+pub const ACTION_EXPORTED: &str = "exported";
+pub const ACTION_DELETED: &str = "deleted";
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ScheduleDeletionRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub confirm_name: String, // Nom exact de l'entité, contre les suppressions par erreur
+}
+
+#[derive(Debug, Serialize)]
+pub struct LifecycleEvent {
+    pub id: Uuid,
+    pub entity_id: Uuid,
+    pub entity_name: String,
+    pub action: String, // "archived", "unarchived", "deletion_scheduled", "deletion_cancelled", "exported", "deleted"
+    pub actor_id: Option<Uuid>, // `None` pour la purge automatique
+    pub details: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<entity_lifecycle_event::Model> for LifecycleEvent {
+    fn from(e: entity_lifecycle_event::Model) -> Self {
+        Self {
+            id: e.id,
+            entity_id: e.entity_id,
+            entity_name: e.entity_name,
+            action: e.action,
+            actor_id: e.actor_id,
+            details: e.details,
+            created_at: e.created_at,
+        }
+    }
+}
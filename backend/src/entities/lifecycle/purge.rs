@@ -0,0 +1,190 @@
+//! Purge des entités dont le délai de grâce est écoulé : données RGPD,
+//! catalogue, annuaire et fichiers stockés. Les comptes utilisateurs, communs
+//! à plusieurs entités, sont conservés ; seuls leurs rattachements disparaissent.
+use std::time::Duration;
+use chrono::Utc;
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait, ColumnTrait, Condition, QueryFilter, QuerySelect, TransactionTrait};
+use serde_json::json;
+use uuid::Uuid;
+use crate::config::Config;
+use crate::entities::catalogue::storage;
+use crate::entities::lifecycle::{models::ACTION_DELETED, record_event};
+use crate::entities_orm::entity::{self, Entity as EntityEntity, Column as EntityColumn};
+use crate::entities_orm::user::{Entity as UserEntity, Column as UserColumn};
+use crate::entities_orm::register_entry::{Entity as RegisterEntryEntity, Column as RegisterEntryColumn};
+use crate::entities_orm::access_request::{Entity as AccessRequestEntity, Column as AccessRequestColumn};
+use crate::entities_orm::breach::{Entity as BreachEntity, Column as BreachColumn};
+use crate::entities_orm::rgpd_breach_attachment::{Entity as BreachAttachmentEntity, Column as BreachAttachmentColumn};
+use crate::entities_orm::endpoint::{Entity as EndpointEntity, Column as EndpointColumn};
+use crate::entities_orm::license_key::{Entity as LicenseKeyEntity, Column as LicenseKeyColumn};
+use crate::entities_orm::software_version::{Entity as SoftwareVersionEntity, Column as SoftwareVersionColumn};
+use crate::entities_orm::encryption_algorithm::{Entity as EncryptionAlgorithmEntity, Column as EncryptionAlgorithmColumn};
+use crate::entities_orm::catalogue_relation::{Entity as CatalogueRelationEntity, Column as CatalogueRelationColumn};
+use crate::entities_orm::service_account::{Entity as ServiceAccountEntity, Column as ServiceAccountColumn};
+
+/// Lance la purge périodique en tâche de fond.
+pub fn spawn(db: DatabaseConnection, config: Config) {
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(Duration::from_secs(config.entity_purge_interval_minutes * 60));
+        loop {
+            interval.tick().await;
+            if let Err(e) = purge_due_entities(&db, &config).await {
+                log::error!("Entity purge failed: {}", e);
+            }
+        }
+    });
+}
+
+async fn purge_due_entities(db: &DatabaseConnection, config: &Config) -> Result<(), DbErr> {
+    let due = EntityEntity::find()
+        .filter(EntityColumn::DeletionScheduledAt.lte(Utc::now()))
+        .all(db)
+        .await?;
+
+    for entity in due {
+        match purge_entity(db, config, &entity).await {
+            Ok(()) => log::info!("Entity {} ({}) purged", entity.id, entity.name),
+            Err(e) => log::error!("Failed to purge entity {}: {}", entity.id, e),
+        }
+    }
+    Ok(())
+}
+
+async fn item_ids<E: EntityTrait>(db: &impl sea_orm::ConnectionTrait, entity_id: Uuid, id: E::Column, owner: E::Column) -> Result<Vec<Uuid>, DbErr> {
+    E::find()
+        .select_only()
+        .column(id)
+        .filter(owner.eq(entity_id))
+        .into_tuple()
+        .all(db)
+        .await
+}
+
+pub async fn purge_entity(db: &DatabaseConnection, config: &Config, entity: &entity::Model) -> Result<(), DbErr> {
+    let entity_id = entity.id;
+
+    // Fichiers supprimés après la transaction : une purge annulée n'en perd aucun
+    let mut files: Vec<(String, String)> = LicenseKeyEntity::find()
+        .filter(LicenseKeyColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|k| k.file_path.map(|path| (k.storage_type, path)))
+        .collect();
+    files.extend(BreachAttachmentEntity::find()
+        .filter(BreachAttachmentColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|a| (a.storage_type, a.file_path)));
+
+    let txn = db.begin().await?;
+
+    let mut items = item_ids::<EndpointEntity>(&txn, entity_id, EndpointColumn::Id, EndpointColumn::EntityId).await?;
+    items.extend(item_ids::<LicenseKeyEntity>(&txn, entity_id, LicenseKeyColumn::Id, LicenseKeyColumn::EntityId).await?);
+    items.extend(item_ids::<SoftwareVersionEntity>(&txn, entity_id, SoftwareVersionColumn::Id, SoftwareVersionColumn::EntityId).await?);
+    items.extend(item_ids::<EncryptionAlgorithmEntity>(&txn, entity_id, EncryptionAlgorithmColumn::Id, EncryptionAlgorithmColumn::EntityId).await?);
+
+    // Relations de l'entité, et relations partagées pointant vers ses éléments
+    let relations = CatalogueRelationEntity::delete_many()
+        .filter(
+            Condition::any()
+                .add(CatalogueRelationColumn::EntityId.eq(entity_id))
+                .add(CatalogueRelationColumn::SourceId.is_in(items.clone()))
+                .add(CatalogueRelationColumn::TargetId.is_in(items.clone()))
+                .add(Condition::all()
+                    .add(CatalogueRelationColumn::SourceType.eq("entity"))
+                    .add(CatalogueRelationColumn::SourceId.eq(entity_id)))
+                .add(Condition::all()
+                    .add(CatalogueRelationColumn::TargetType.eq("entity"))
+                    .add(CatalogueRelationColumn::TargetId.eq(entity_id))),
+        )
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    let endpoints = EndpointEntity::delete_many()
+        .filter(EndpointColumn::EntityId.eq(entity_id))
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    let license_keys = LicenseKeyEntity::delete_many()
+        .filter(LicenseKeyColumn::EntityId.eq(entity_id))
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    let software_versions = SoftwareVersionEntity::delete_many()
+        .filter(SoftwareVersionColumn::EntityId.eq(entity_id))
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    let encryption_algorithms = EncryptionAlgorithmEntity::delete_many()
+        .filter(EncryptionAlgorithmColumn::EntityId.eq(entity_id))
+        .exec(&txn)
+        .await?
+        .rows_affected;
+
+    let access_requests = AccessRequestEntity::delete_many()
+        .filter(AccessRequestColumn::EntityId.eq(entity_id))
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    let breaches = BreachEntity::delete_many()
+        .filter(BreachColumn::EntityId.eq(entity_id))
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    let register_entries = RegisterEntryEntity::delete_many()
+        .filter(RegisterEntryColumn::EntityId.eq(entity_id))
+        .exec(&txn)
+        .await?
+        .rows_affected;
+
+    // Les comptes de service n'existent que pour l'entité : leur utilisateur est supprimé
+    let service_users: Vec<Uuid> = ServiceAccountEntity::find()
+        .select_only()
+        .column(ServiceAccountColumn::UserId)
+        .filter(ServiceAccountColumn::EntityId.eq(entity_id))
+        .into_tuple()
+        .all(&txn)
+        .await?;
+    let service_accounts = UserEntity::delete_many()
+        .filter(UserColumn::Id.is_in(service_users))
+        .exec(&txn)
+        .await?
+        .rows_affected;
+
+    // Rattachements, référentiels du registre, fournisseurs d'identité et annuaire
+    // SCIM suivent par cascade
+    EntityEntity::delete_by_id(entity_id)
+        .exec(&txn)
+        .await?;
+
+    let details = json!({
+        "requested_by": entity.deletion_requested_by,
+        "scheduled_for": entity.deletion_scheduled_at,
+        "rgpd": {
+            "register_entries": register_entries,
+            "access_requests": access_requests,
+            "breaches": breaches,
+        },
+        "catalogue": {
+            "endpoints": endpoints,
+            "license_keys": license_keys,
+            "software_versions": software_versions,
+            "encryption_algorithms": encryption_algorithms,
+            "relations": relations,
+        },
+        "service_accounts": service_accounts,
+        "files": files.len(),
+    });
+    record_event(&txn, entity, ACTION_DELETED, None, Some(details)).await?;
+    txn.commit().await?;
+
+    for (storage_type, path) in files {
+        let storage = storage::for_type(config, &storage_type).await;
+        if let Err(e) = storage.delete_file(&path).await {
+            log::warn!("Purge of entity {}: cannot delete {}: {}", entity_id, path, e);
+        }
+    }
+    Ok(())
+}
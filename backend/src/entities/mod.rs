@@ -1,5 +1,12 @@
 pub mod handlers;
+pub mod hierarchy;
+pub mod lifecycle;
 pub mod models;
 pub mod rgpd;
 pub mod catalogue;
+pub mod email_domains;
+pub mod identity_providers;
+pub mod scope;
 
+pub mod scim_provisioning;
+pub mod service_accounts;
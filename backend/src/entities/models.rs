@@ -2,26 +2,86 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use validator::{Validate, ValidationError};
+use crate::validation::{not_blank, one_of};
+use crate::entities_orm::entity;
+
+/// Rôles d'un membre dans une entité ; `admin` administre l'entité, `dpo`
+/// désigne le délégué à la protection des données (avis sur les AIPD).
+pub const ENTITY_ROLES: &[&str] = &["admin", "dpo", "member"];
+
+pub fn validate_entity_role(value: &str) -> Result<(), ValidationError> {
+    one_of(value, ENTITY_ROLES)
+}
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Entity {
     pub id: Uuid,
     pub name: String,
     pub description: Option<String>,
+    pub require_mfa: bool, // Les membres doivent s'authentifier avec un second facteur
+    pub parent_id: Option<Uuid>, // Groupe dont l'entité est une filiale
+    pub archived_at: Option<DateTime<Utc>>, // Entité en lecture seule
+    pub deletion_scheduled_at: Option<DateTime<Utc>>, // Date de purge définitive
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+impl From<entity::Model> for Entity {
+    fn from(e: entity::Model) -> Self {
+        Self {
+            id: e.id,
+            name: e.name,
+            description: e.description,
+            require_mfa: e.require_mfa,
+            parent_id: e.parent_id,
+            archived_at: e.archived_at,
+            deletion_scheduled_at: e.deletion_scheduled_at,
+            created_at: e.created_at,
+            updated_at: e.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateEntityRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
     pub name: String,
+    #[validate(length(max = 2000))]
     pub description: Option<String>,
+    pub require_mfa: Option<bool>,
+    pub parent_id: Option<Uuid>, // Création directe comme filiale (admin du groupe requis)
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdateEntityRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
     pub name: Option<String>,
+    #[validate(length(max = 2000))]
     pub description: Option<String>,
+    pub require_mfa: Option<bool>,
+}
+
+/// Rattachement à un groupe ; `null` détache l'entité.
+#[derive(Debug, Deserialize, Validate)]
+pub struct SetParentRequest {
+    pub parent_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoginFailure {
+    pub id: Uuid,
+    pub email: String,
+    pub user_id: Option<Uuid>,
+    pub ip_address: Option<String>,
+    pub reason: String, // "invalid_password", "unknown_account", "throttled"
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginFailureQuery {
+    pub since: Option<DateTime<Utc>>,
+    pub limit: Option<u64>,
 }
 
 #[derive(Debug, Serialize, FromRow)]
@@ -0,0 +1,181 @@
+//! Pièces jointes des violations de données (constats, échanges, notifications).
+//! Les fichiers suivent le stockage configuré et disparaissent avec la violation
+//! ou lors de son anonymisation.
+use actix_multipart::Multipart;
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, Order, QueryFilter, QueryOrder, Set};
+use uuid::Uuid;
+use crate::config::Config;
+use crate::entities::catalogue::storage;
+use crate::entities::rgpd::handlers::find_breach;
+use crate::entities::rgpd::models::{BreachAttachment, BreachAttachmentPath};
+use crate::entities::scope::{EntityScope, ScopedId};
+use crate::errors::ApiError;
+use crate::middleware::get_current_user_id;
+use crate::entities_orm::breach;
+use crate::entities_orm::rgpd_breach_attachment::{self, Entity as AttachmentEntity, Column as AttachmentColumn, ActiveModel as AttachmentActiveModel};
+
+// Même plafond que les corps de requête JSON
+const MAX_ATTACHMENT_SIZE: usize = 10_000_000;
+
+// Nom conservé pour le téléchargement, réduit à des caractères sûrs pour le stockage
+fn storage_name(file_name: &str) -> String {
+    let name: String = file_name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+    match name.trim_matches('.') {
+        "" => "attachment".to_string(),
+        name => name.to_string(),
+    }
+}
+
+async fn find_attachment(db: &DatabaseConnection, breach: &breach::Model, id: Uuid) -> Result<rgpd_breach_attachment::Model, ApiError> {
+    AttachmentEntity::find_by_id(id)
+        .filter(AttachmentColumn::BreachId.eq(breach.id))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Attachment not found".to_string()))
+}
+
+pub async fn list_attachments(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+) -> Result<HttpResponse, ApiError> {
+    let breach = find_breach(db.get_ref(), &scope, path.id).await?;
+    let attachments: Vec<BreachAttachment> = AttachmentEntity::find()
+        .filter(AttachmentColumn::BreachId.eq(breach.id))
+        .order_by(AttachmentColumn::CreatedAt, Order::Asc)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(BreachAttachment::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(attachments))
+}
+
+pub async fn upload_attachment(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    mut payload: Multipart,
+) -> Result<HttpResponse, ApiError> {
+    let breach = find_breach(db.get_ref(), &scope, path.id).await?;
+    if breach.anonymized_at.is_some() {
+        return Err(ApiError::Conflict("Breach has been anonymized".to_string()));
+    }
+
+    let mut file: Option<(Vec<u8>, Option<String>, Option<String>)> = None;
+    while let Some(mut field) = payload.try_next().await.map_err(|e| {
+        log::error!("Multipart error: {}", e);
+        ApiError::BadRequest("Invalid multipart data".to_string())
+    })? {
+        if field.name() == "file" {
+            let mut bytes = Vec::new();
+            while let Some(chunk) = field.try_next().await.map_err(ApiError::internal("Error reading file"))? {
+                if bytes.len() + chunk.len() > MAX_ATTACHMENT_SIZE {
+                    return Err(ApiError::validation("file", "too_large", format!("Attachments are limited to {} bytes", MAX_ATTACHMENT_SIZE)));
+                }
+                bytes.extend_from_slice(&chunk);
+            }
+            let file_name = field.content_disposition().get_filename().map(|s| s.to_string());
+            let content_type = field.content_type().map(|m| m.to_string());
+            file = Some((bytes, file_name, content_type));
+        }
+    }
+    let (data, file_name, content_type) = file.ok_or_else(|| ApiError::validation("file", "required", "No file provided"))?;
+    let file_name = file_name.filter(|n| !n.trim().is_empty()).unwrap_or_else(|| "attachment".to_string());
+
+    let attachment = store(db.get_ref(), &config, &breach, file_name, content_type, &data, get_current_user_id(&req)).await?;
+    Ok(HttpResponse::Created().json(BreachAttachment::from(attachment)))
+}
+
+/// Enregistre un fichier en pièce jointe de la violation (envoi ou document généré).
+pub(crate) async fn store(
+    db: &DatabaseConnection,
+    config: &Config,
+    breach: &breach::Model,
+    file_name: String,
+    content_type: Option<String>,
+    data: &[u8],
+    uploaded_by: Option<Uuid>,
+) -> Result<rgpd_breach_attachment::Model, ApiError> {
+    let id = Uuid::new_v4();
+    let storage = storage::for_type(config, &config.storage_type).await;
+    let file_path = storage.save_file(data, &storage_name(&file_name), id).await
+        .map_err(ApiError::internal("Failed to save file"))?;
+
+    let attachment = AttachmentActiveModel {
+        id: Set(id),
+        entity_id: Set(breach.entity_id),
+        breach_id: Set(breach.id),
+        file_name: Set(file_name),
+        content_type: Set(content_type),
+        file_size: Set(data.len() as i64),
+        storage_type: Set(config.storage_type.clone()),
+        file_path: Set(file_path.clone()),
+        uploaded_by: Set(uploaded_by),
+        created_at: Set(Utc::now()),
+    }
+    .insert(db)
+    .await;
+
+    // Un fichier sans enregistrement ne serait jamais supprimé
+    match attachment {
+        Ok(attachment) => Ok(attachment),
+        Err(e) => {
+            if let Err(e) = storage.delete_file(&file_path).await {
+                log::warn!("Cannot delete orphan attachment {}: {}", file_path, e);
+            }
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn download_attachment(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    scope: EntityScope,
+    path: web::Path<BreachAttachmentPath>,
+) -> Result<HttpResponse, ApiError> {
+    let breach = find_breach(db.get_ref(), &scope, path.id).await?;
+    let attachment = find_attachment(db.get_ref(), &breach, path.item_id).await?;
+
+    let storage = storage::for_type(&config, &attachment.storage_type).await;
+    let bytes = storage.get_file(&attachment.file_path).await
+        .map_err(ApiError::internal("Failed to read file"))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(attachment.content_type.unwrap_or_else(|| "application/octet-stream".to_string()))
+        .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", storage_name(&attachment.file_name))))
+        .body(bytes))
+}
+
+pub async fn delete_attachment(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    scope: EntityScope,
+    path: web::Path<BreachAttachmentPath>,
+) -> Result<HttpResponse, ApiError> {
+    let breach = find_breach(db.get_ref(), &scope, path.id).await?;
+    let attachment = find_attachment(db.get_ref(), &breach, path.item_id).await?;
+
+    AttachmentEntity::delete_by_id(attachment.id)
+        .exec(db.get_ref())
+        .await?;
+    let storage = storage::for_type(&config, &attachment.storage_type).await;
+    if let Err(e) = storage.delete_file(&attachment.file_path).await {
+        log::warn!("Cannot delete attachment file {}: {}", attachment.file_path, e);
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,47 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, IntoActiveModel, Set};
+use serde_json::json;
+use uuid::Uuid;
+use crate::entities::rgpd::breach_assessment::assess;
+use crate::entities::rgpd::breach_assessment::models::AssessmentRequest;
+use crate::entities::rgpd::handlers::find_breach;
+use crate::entities::rgpd::models::Breach;
+use crate::entities::scope::{EntityScope, ScopedId};
+use crate::errors::ApiError;
+use crate::middleware::get_current_user_id;
+use crate::validation::ValidatedJson;
+use crate::entities_orm::breach::ActiveModel as BreachActiveModel;
+
+fn current_user(req: &HttpRequest) -> Result<Uuid, ApiError> {
+    get_current_user_id(req).ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))
+}
+
+/// Évalue (ou réévalue) la gravité de la violation : le niveau retenu remplace
+/// la gravité saisie et l'évaluation précédente.
+pub async fn assess_breach(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<AssessmentRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = current_user(&req)?;
+    let breach = find_breach(db.get_ref(), &scope, path.id).await?;
+    if breach.anonymized_at.is_some() {
+        return Err(ApiError::Conflict("Breach has been anonymized".to_string()));
+    }
+
+    let assessment = assess(&body);
+    let now = Utc::now();
+    let mut breach: BreachActiveModel = breach.into_active_model();
+    breach.severity = Set(assessment.severity.clone());
+    breach.assessment = Set(Some(json!(assessment)));
+    breach.assessment_justification = Set(body.justification.clone().filter(|j| !j.trim().is_empty()));
+    breach.assessed_by = Set(Some(user_id));
+    breach.assessed_at = Set(Some(now));
+    breach.updated_at = Set(now);
+    let breach = breach.update(db.get_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(Breach::from(breach)))
+}
@@ -0,0 +1,360 @@
+//! Évaluation de la gravité d'une violation selon la méthode de l'ENISA
+//! (Recommendations for a methodology of the assessment of severity of
+//! personal data breaches, 2013) : SE = DPC × EI + CB. Le niveau retenu
+//! détermine la notification à l'autorité (Art. 33) et la communication aux
+//! personnes concernées (Art. 34).
+pub mod handlers;
+pub mod models;
+
+use serde::Serialize;
+use crate::entities::rgpd::taxonomy::Choice;
+use models::{Assessment, AssessmentRequest};
+
+#[derive(Debug, Serialize)]
+pub struct Scale {
+    pub key: &'static str,
+    pub label: &'static str,
+    pub score: f64,
+}
+
+/// Contexte du traitement (DPC) : score de base selon la nature des données,
+/// ajusté ensuite d'au plus trois points.
+pub const DATA_TYPES: &[Scale] = &[
+    Scale { key: "simple", label: "Données simples (identité, coordonnées, parcours scolaire…)", score: 1.0 },
+    Scale { key: "behavioural", label: "Données comportementales (localisation, habitudes, préférences…)", score: 2.0 },
+    Scale { key: "financial", label: "Données financières (revenus, transactions, relevés…)", score: 3.0 },
+    Scale { key: "sensitive", label: "Données sensibles (santé, opinions, vie sexuelle…)", score: 4.0 },
+];
+
+/// Facilité d'identification des personnes (EI), facteur multiplicatif.
+pub const IDENTIFICATION_LEVELS: &[Scale] = &[
+    Scale { key: "negligible", label: "Négligeable", score: 0.25 },
+    Scale { key: "limited", label: "Limitée", score: 0.5 },
+    Scale { key: "significant", label: "Importante", score: 0.75 },
+    Scale { key: "maximum", label: "Maximale", score: 1.0 },
+];
+
+// Circonstances de la violation (CB), additives
+pub const CONFIDENTIALITY_LOSSES: &[Scale] = &[
+    Scale { key: "none", label: "Aucune perte de confidentialité", score: 0.0 },
+    Scale { key: "known_recipients", label: "Données exposées à des destinataires identifiés", score: 0.25 },
+    Scale { key: "unknown_recipients", label: "Données exposées à des destinataires inconnus ou au public", score: 0.5 },
+];
+
+pub const INTEGRITY_LOSSES: &[Scale] = &[
+    Scale { key: "none", label: "Aucune perte d'intégrité", score: 0.0 },
+    Scale { key: "recoverable", label: "Données altérées, pouvant être rétablies", score: 0.25 },
+    Scale { key: "unrecoverable", label: "Données altérées sans possibilité de rétablissement", score: 0.5 },
+];
+
+pub const AVAILABILITY_LOSSES: &[Scale] = &[
+    Scale { key: "none", label: "Aucune perte de disponibilité", score: 0.0 },
+    Scale { key: "temporary", label: "Indisponibilité temporaire", score: 0.25 },
+    Scale { key: "permanent", label: "Perte définitive", score: 0.5 },
+];
+
+/// Majoration en cas d'intention malveillante.
+pub const MALICIOUS_INTENT_SCORE: f64 = 0.5;
+/// Ajustement maximal du contexte du traitement, dans un sens ou dans l'autre.
+pub const MAX_CONTEXT_ADJUSTMENT: i32 = 3;
+/// Délai de notification à l'autorité à compter de la découverte (Art. 33(1)).
+pub const AUTHORITY_DEADLINE_HOURS: i64 = 72;
+
+/// Exceptions à la communication aux personnes (Art. 34(3)).
+pub const SUBJECT_EXEMPTIONS: &[Choice] = &[
+    Choice { key: "unintelligible_data", label: "Données rendues incompréhensibles, par exemple chiffrées (Art. 34(3)(a))" },
+    Choice { key: "risk_no_longer_likely", label: "Mesures ultérieures écartant le risque élevé (Art. 34(3)(b))" },
+    Choice { key: "disproportionate_effort", label: "Efforts disproportionnés : communication publique (Art. 34(3)(c))" },
+];
+pub const EXEMPTION_PUBLIC_COMMUNICATION: &str = "disproportionate_effort";
+
+// Communication aux personnes concernées
+pub const SUBJECTS_NOT_REQUIRED: &str = "not_required";
+pub const SUBJECTS_REQUIRED: &str = "required";
+pub const SUBJECTS_EXEMPTED: &str = "exempted";
+pub const SUBJECTS_PUBLIC_COMMUNICATION: &str = "public_communication";
+
+pub const METHOD: &str = "enisa_2013";
+
+/// Barèmes présentés au formulaire d'évaluation.
+#[derive(Debug, Serialize)]
+pub struct Scales {
+    pub data_types: &'static [Scale],
+    pub identification_levels: &'static [Scale],
+    pub confidentiality_losses: &'static [Scale],
+    pub integrity_losses: &'static [Scale],
+    pub availability_losses: &'static [Scale],
+    pub malicious_intent_score: f64,
+    pub max_context_adjustment: i32,
+    pub subject_exemptions: &'static [Choice],
+}
+
+pub const SCALES: Scales = Scales {
+    data_types: DATA_TYPES,
+    identification_levels: IDENTIFICATION_LEVELS,
+    confidentiality_losses: CONFIDENTIALITY_LOSSES,
+    integrity_losses: INTEGRITY_LOSSES,
+    availability_losses: AVAILABILITY_LOSSES,
+    malicious_intent_score: MALICIOUS_INTENT_SCORE,
+    max_context_adjustment: MAX_CONTEXT_ADJUSTMENT,
+    subject_exemptions: SUBJECT_EXEMPTIONS,
+};
+
+pub fn scale_keys(scales: &[Scale]) -> Vec<&'static str> {
+    scales.iter().map(|s| s.key).collect()
+}
+
+fn scale<'a>(scales: &'a [Scale], key: &str) -> &'a Scale {
+    // Les clés sont validées à la réception de la requête
+    scales.iter().find(|s| s.key == key).unwrap_or(&scales[0])
+}
+
+/// Niveau de gravité correspondant au score : faible sous 2, moyen sous 3,
+/// élevé sous 4, très élevé au-delà.
+pub fn severity_for(score: f64) -> &'static str {
+    if score < 2.0 {
+        "low"
+    } else if score < 3.0 {
+        "medium"
+    } else if score < 4.0 {
+        "high"
+    } else {
+        "critical"
+    }
+}
+
+/// Notification à l'autorité, sauf violation peu susceptible d'engendrer un risque.
+pub fn authority_notification_required(severity: &str) -> bool {
+    severity != "low"
+}
+
+/// Communication aux personnes en cas de risque élevé, sauf exception de l'article 34(3).
+pub fn subjects_notification(severity: &str, exemption: Option<&str>) -> &'static str {
+    if !matches!(severity, "high" | "critical") {
+        SUBJECTS_NOT_REQUIRED
+    } else {
+        match exemption {
+            None => SUBJECTS_REQUIRED,
+            Some(EXEMPTION_PUBLIC_COMMUNICATION) => SUBJECTS_PUBLIC_COMMUNICATION,
+            Some(_) => SUBJECTS_EXEMPTED,
+        }
+    }
+}
+
+/// Calcule l'évaluation et ses motifs. Le niveau retenu est celui du score,
+/// sauf décision contraire motivée dans la requête.
+pub fn assess(req: &AssessmentRequest) -> Assessment {
+    let data_type = scale(DATA_TYPES, &req.data_type);
+    let identification = scale(IDENTIFICATION_LEVELS, &req.ease_of_identification);
+    let confidentiality = scale(CONFIDENTIALITY_LOSSES, &req.confidentiality);
+    let integrity = scale(INTEGRITY_LOSSES, &req.integrity);
+    let availability = scale(AVAILABILITY_LOSSES, &req.availability);
+
+    let dpc = (data_type.score + f64::from(req.context_adjustment)).clamp(1.0, 4.0);
+    let malicious = if req.malicious_intent { MALICIOUS_INTENT_SCORE } else { 0.0 };
+    let cb = confidentiality.score + integrity.score + availability.score + malicious;
+    let score = dpc * identification.score + cb;
+    let computed_severity = severity_for(score);
+    let severity = req.severity.as_deref().unwrap_or(computed_severity);
+    let authority = authority_notification_required(severity);
+    let subjects = subjects_notification(severity, req.subject_exemption.as_deref());
+
+    let mut rationale = vec![format!("Data processing context: {} ({})", data_type.key, data_type.score)];
+    if req.context_adjustment != 0 {
+        rationale.push(format!("Context adjusted by {:+}, DPC = {}", req.context_adjustment, dpc));
+    }
+    rationale.push(format!("Ease of identification: {} (EI = {})", identification.key, identification.score));
+    let mut circumstances: Vec<String> = [confidentiality, integrity, availability].iter()
+        .zip(["confidentiality", "integrity", "availability"])
+        .filter(|(s, _)| s.score > 0.0)
+        .map(|(s, loss)| format!("loss of {} ({}, +{})", loss, s.key, s.score))
+        .collect();
+    if req.malicious_intent {
+        circumstances.push(format!("malicious intent (+{})", MALICIOUS_INTENT_SCORE));
+    }
+    rationale.push(if circumstances.is_empty() {
+        "Circumstances of breach: none (CB = 0)".to_string()
+    } else {
+        format!("Circumstances of breach: {} (CB = {})", circumstances.join(", "), cb)
+    });
+    rationale.push(format!("Severity score: {} × {} + {} = {} ({})", dpc, identification.score, cb, score, computed_severity));
+    if severity != computed_severity {
+        rationale.push(format!("Severity set to {} instead of {}; see the justification", severity, computed_severity));
+    }
+    rationale.push(if authority {
+        format!("Art. 33: the supervisory authority must be notified within {} hours of discovery", AUTHORITY_DEADLINE_HOURS)
+    } else {
+        "Art. 33: notification not required, the breach is unlikely to result in a risk; it must still be documented (Art. 33(5))".to_string()
+    });
+    rationale.push(match (subjects, req.subject_exemption.as_deref()) {
+        (SUBJECTS_REQUIRED, _) => "Art. 34: high risk, the data subjects must be informed without undue delay",
+        (SUBJECTS_PUBLIC_COMMUNICATION, _) => "Art. 34: high risk, informing each data subject would involve disproportionate effort; a public communication is required (Art. 34(3)(c))",
+        (SUBJECTS_EXEMPTED, Some("unintelligible_data")) => "Art. 34: high risk, but the data is unintelligible to any unauthorised person (Art. 34(3)(a))",
+        (SUBJECTS_EXEMPTED, _) => "Art. 34: high risk, but subsequent measures ensure it is no longer likely to materialise (Art. 34(3)(b))",
+        _ => "Art. 34: communication to data subjects not required below a high risk",
+    }.to_string());
+
+    Assessment {
+        method: METHOD.to_string(),
+        data_type: req.data_type.clone(),
+        context_adjustment: req.context_adjustment,
+        ease_of_identification: req.ease_of_identification.clone(),
+        confidentiality: req.confidentiality.clone(),
+        integrity: req.integrity.clone(),
+        availability: req.availability.clone(),
+        malicious_intent: req.malicious_intent,
+        subject_exemption: req.subject_exemption.clone(),
+        data_processing_context: dpc,
+        ease_of_identification_score: identification.score,
+        circumstances_score: cb,
+        score,
+        computed_severity: computed_severity.to_string(),
+        severity: severity.to_string(),
+        authority_notification_required: authority,
+        subjects_notification: subjects.to_string(),
+        rationale,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use validator::Validate;
+    use super::*;
+
+    // Violation sans circonstance aggravante, complétée par `overrides`
+    fn request(overrides: serde_json::Value) -> AssessmentRequest {
+        let mut body = json!({
+            "data_type": "simple",
+            "ease_of_identification": "maximum",
+            "confidentiality": "none",
+            "integrity": "none",
+            "availability": "none",
+        });
+        body.as_object_mut().unwrap().extend(overrides.as_object().unwrap().clone());
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn simple_data_without_circumstances_is_low() {
+        let assessment = assess(&request(json!({})));
+        assert_eq!(assessment.data_processing_context, 1.0);
+        assert_eq!(assessment.circumstances_score, 0.0);
+        assert_eq!(assessment.score, 1.0);
+        assert_eq!(assessment.severity, "low");
+        assert!(!assessment.authority_notification_required);
+        assert_eq!(assessment.subjects_notification, SUBJECTS_NOT_REQUIRED);
+    }
+
+    #[test]
+    fn sensitive_identifiable_data_is_critical() {
+        let assessment = assess(&request(json!({ "data_type": "sensitive" })));
+        assert!(assessment.score >= 4.0);
+        assert_eq!(assessment.severity, "critical");
+        assert!(assessment.authority_notification_required);
+        assert_eq!(assessment.subjects_notification, SUBJECTS_REQUIRED);
+
+        // Les circonstances s'ajoutent au produit DPC × EI
+        let assessment = assess(&request(json!({
+            "data_type": "sensitive",
+            "ease_of_identification": "negligible",
+            "confidentiality": "unknown_recipients",
+            "integrity": "unrecoverable",
+            "availability": "permanent",
+            "malicious_intent": true,
+        })));
+        assert_eq!(assessment.circumstances_score, 2.0);
+        assert_eq!(assessment.score, 3.0);
+        assert_eq!(assessment.severity, "high");
+    }
+
+    #[test]
+    fn severity_thresholds_are_inclusive_lower_bounds() {
+        let cases = [
+            (0.25, "low"), (1.99, "low"),
+            (2.0, "medium"), (2.99, "medium"),
+            (3.0, "high"), (3.99, "high"),
+            (4.0, "critical"), (5.5, "critical"),
+        ];
+        for (score, expected) in cases {
+            assert_eq!(severity_for(score), expected, "{}", score);
+        }
+        // Mêmes seuils atteints par le calcul : DPC de 2, 3 et 4 avec une identification maximale
+        for (data_type, expected) in [("behavioural", "medium"), ("financial", "high"), ("sensitive", "critical")] {
+            let assessment = assess(&request(json!({ "data_type": data_type })));
+            assert_eq!(assessment.computed_severity, expected, "{}", data_type);
+        }
+        assert!(authority_notification_required("medium"));
+        assert!(!authority_notification_required("low"));
+    }
+
+    #[test]
+    fn context_adjustment_is_clamped() {
+        let cases = [
+            ("simple", MAX_CONTEXT_ADJUSTMENT, 4.0),
+            ("simple", -MAX_CONTEXT_ADJUSTMENT, 1.0),
+            ("sensitive", MAX_CONTEXT_ADJUSTMENT, 4.0),
+            ("sensitive", -MAX_CONTEXT_ADJUSTMENT, 1.0),
+            ("behavioural", 1, 3.0),
+            ("financial", -1, 2.0),
+        ];
+        for (data_type, adjustment, expected) in cases {
+            let assessment = assess(&request(json!({ "data_type": data_type, "context_adjustment": adjustment })));
+            assert_eq!(assessment.data_processing_context, expected, "{} {:+}", data_type, adjustment);
+        }
+
+        // Au-delà de l'ajustement maximal, la requête est refusée
+        let justified = |adjustment: i32| request(json!({ "context_adjustment": adjustment, "justification": "Volume" }));
+        assert!(justified(MAX_CONTEXT_ADJUSTMENT).validate().is_ok());
+        assert!(justified(-MAX_CONTEXT_ADJUSTMENT).validate().is_ok());
+        assert!(justified(MAX_CONTEXT_ADJUSTMENT + 1).validate().is_err());
+        assert!(justified(-MAX_CONTEXT_ADJUSTMENT - 1).validate().is_err());
+        // Un ajustement doit être motivé
+        assert!(request(json!({ "context_adjustment": 1 })).validate().is_err());
+    }
+
+    #[test]
+    fn subject_exemptions_apply_only_to_high_risk() {
+        let cases = [
+            ("high", None, SUBJECTS_REQUIRED),
+            ("critical", None, SUBJECTS_REQUIRED),
+            ("high", Some("unintelligible_data"), SUBJECTS_EXEMPTED),
+            ("critical", Some("risk_no_longer_likely"), SUBJECTS_EXEMPTED),
+            ("high", Some("disproportionate_effort"), SUBJECTS_PUBLIC_COMMUNICATION),
+            ("medium", None, SUBJECTS_NOT_REQUIRED),
+            ("medium", Some("disproportionate_effort"), SUBJECTS_NOT_REQUIRED),
+            ("low", Some("unintelligible_data"), SUBJECTS_NOT_REQUIRED),
+        ];
+        for (severity, exemption, expected) in cases {
+            assert_eq!(subjects_notification(severity, exemption), expected, "{} {:?}", severity, exemption);
+        }
+
+        // Chaque exception figure au barème et dans les motifs de l'évaluation
+        for exemption in SUBJECT_EXEMPTIONS {
+            let assessment = assess(&request(json!({ "data_type": "sensitive", "subject_exemption": exemption.key })));
+            assert_ne!(assessment.subjects_notification, SUBJECTS_REQUIRED, "{}", exemption.key);
+            assert!(assessment.rationale.last().unwrap().contains("Art. 34(3)"), "{}", exemption.key);
+        }
+    }
+
+    #[test]
+    fn manual_override_keeps_computed_severity() {
+        let overridden = request(json!({
+            "data_type": "sensitive",
+            "severity": "medium",
+            "justification": "Données chiffrées, clé non compromise",
+        }));
+        assert!(overridden.validate().is_ok());
+        let assessment = assess(&overridden);
+        assert_eq!(assessment.computed_severity, "critical");
+        assert_eq!(assessment.severity, "medium");
+        // Les obligations suivent le niveau retenu
+        assert!(assessment.authority_notification_required);
+        assert_eq!(assessment.subjects_notification, SUBJECTS_NOT_REQUIRED);
+        assert!(assessment.rationale.iter().any(|r| r.contains("Severity set to medium instead of critical")));
+
+        // S'écarter du niveau calculé sans motif est refusé, le confirmer ne l'exige pas
+        assert!(request(json!({ "data_type": "sensitive", "severity": "low" })).validate().is_err());
+        assert!(request(json!({ "data_type": "sensitive", "severity": "critical" })).validate().is_ok());
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Duration, Utc};
+use validator::{Validate, ValidationError};
+use crate::entities::rgpd::breach_assessment::{
+    self as enisa, AVAILABILITY_LOSSES, CONFIDENTIALITY_LOSSES, DATA_TYPES, IDENTIFICATION_LEVELS, INTEGRITY_LOSSES, SUBJECT_EXEMPTIONS,
+};
+use crate::entities::rgpd::models::BREACH_SEVERITIES;
+use crate::entities::rgpd::taxonomy;
+use crate::validation::{one_of, validation_error};
+use crate::entities_orm::breach;
+
+fn validate_data_type(value: &str) -> Result<(), ValidationError> {
+    one_of(value, &enisa::scale_keys(DATA_TYPES))
+}
+
+fn validate_identification(value: &str) -> Result<(), ValidationError> {
+    one_of(value, &enisa::scale_keys(IDENTIFICATION_LEVELS))
+}
+
+fn validate_confidentiality(value: &str) -> Result<(), ValidationError> {
+    one_of(value, &enisa::scale_keys(CONFIDENTIALITY_LOSSES))
+}
+
+fn validate_integrity(value: &str) -> Result<(), ValidationError> {
+    one_of(value, &enisa::scale_keys(INTEGRITY_LOSSES))
+}
+
+fn validate_availability(value: &str) -> Result<(), ValidationError> {
+    one_of(value, &enisa::scale_keys(AVAILABILITY_LOSSES))
+}
+
+fn validate_exemption(value: &str) -> Result<(), ValidationError> {
+    one_of(value, &taxonomy::choice_keys(SUBJECT_EXEMPTIONS))
+}
+
+fn validate_severity(value: &str) -> Result<(), ValidationError> {
+    one_of(value, BREACH_SEVERITIES)
+}
+
+// Ajuster le contexte ou s'écarter du niveau calculé doit être motivé
+fn validate_assessment(req: &AssessmentRequest) -> Result<(), ValidationError> {
+    let computed = enisa::assess(req).computed_severity;
+    let departs = req.severity.as_ref().is_some_and(|s| *s != computed);
+    let blank = req.justification.as_deref().is_none_or(|j| j.trim().is_empty());
+    if (req.context_adjustment != 0 || departs) && blank {
+        return Err(validation_error(
+            "justification_required",
+            "A justification is required when the context is adjusted or the severity departs from the computed score",
+        ));
+    }
+    Ok(())
+}
+
+/// Évaluation enregistrée avec la violation : critères, scores intermédiaires,
+/// niveau et obligations qui en découlent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Assessment {
+    pub method: String, // "enisa_2013"
+    pub data_type: String,
+    pub context_adjustment: i32,
+    pub ease_of_identification: String,
+    pub confidentiality: String,
+    pub integrity: String,
+    pub availability: String,
+    pub malicious_intent: bool,
+    pub subject_exemption: Option<String>,
+    pub data_processing_context: f64, // DPC après ajustement
+    pub ease_of_identification_score: f64,
+    pub circumstances_score: f64,
+    pub score: f64,
+    pub computed_severity: String,
+    pub severity: String, // Niveau retenu, repris dans la gravité de la violation
+    pub authority_notification_required: bool,
+    pub subjects_notification: String, // "not_required", "required", "exempted", "public_communication"
+    pub rationale: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BreachAssessment {
+    #[serde(flatten)]
+    pub assessment: Assessment,
+    pub justification: Option<String>,
+    pub assessed_by: Option<Uuid>,
+    pub assessed_at: DateTime<Utc>,
+    pub authority_deadline: Option<DateTime<Utc>>, // 72 heures après la découverte
+}
+
+impl BreachAssessment {
+    pub fn from_breach(b: &breach::Model) -> Option<Self> {
+        let assessment: Assessment = serde_json::from_value(b.assessment.clone()?).ok()?;
+        let authority_deadline = assessment.authority_notification_required
+            .then(|| b.discovery_date + Duration::hours(enisa::AUTHORITY_DEADLINE_HOURS));
+        Some(Self {
+            assessment,
+            justification: b.assessment_justification.clone(),
+            assessed_by: b.assessed_by,
+            assessed_at: b.assessed_at?,
+            authority_deadline,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_assessment"))]
+pub struct AssessmentRequest {
+    #[validate(custom(function = "validate_data_type"))]
+    pub data_type: String,
+    #[serde(default)]
+    #[validate(range(min = -3, max = 3))]
+    pub context_adjustment: i32,
+    #[validate(custom(function = "validate_identification"))]
+    pub ease_of_identification: String,
+    #[validate(custom(function = "validate_confidentiality"))]
+    pub confidentiality: String,
+    #[validate(custom(function = "validate_integrity"))]
+    pub integrity: String,
+    #[validate(custom(function = "validate_availability"))]
+    pub availability: String,
+    #[serde(default)]
+    pub malicious_intent: bool,
+    #[validate(custom(function = "validate_exemption"))]
+    pub subject_exemption: Option<String>,
+    #[validate(custom(function = "validate_severity"))]
+    pub severity: Option<String>, // Niveau calculé par défaut
+    #[validate(length(max = 10000))]
+    pub justification: Option<String>,
+}
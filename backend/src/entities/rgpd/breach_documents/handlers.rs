@@ -0,0 +1,197 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter, Set};
+use uuid::Uuid;
+use crate::config::Config;
+use crate::entities::rgpd::attachments;
+use crate::entities::rgpd::breach_documents::{self, models::*, Inputs, KIND_AUTHORITY};
+use crate::entities::rgpd::export::pdf::{self, Letter};
+use crate::entities::rgpd::handlers::find_breach;
+use crate::entities::rgpd::models::BreachAttachment;
+use crate::entities::scope::{EntityScope, ScopedId};
+use crate::errors::ApiError;
+use crate::middleware::{get_current_user_id, get_entity_role};
+use crate::validation::ValidatedJson;
+use crate::entities_orm::entity::Entity as EntityEntity;
+use crate::entities_orm::user::Entity as UserEntity;
+use crate::entities_orm::rgpd_breach_template::{Entity as TemplateEntity, Column as TemplateColumn, ActiveModel as TemplateActiveModel};
+
+// Les modèles relèvent de l'administrateur de l'entité
+fn require_admin(req: &HttpRequest, scope: &EntityScope) -> Result<Uuid, ApiError> {
+    let user_id = get_current_user_id(req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+    if get_entity_role(req, scope.entity_id).as_deref() != Some("admin") {
+        return Err(ApiError::Forbidden("Admin access required".to_string()));
+    }
+    Ok(user_id)
+}
+
+fn check_path(path: &TemplatePath) -> Result<(), ApiError> {
+    validate_kind(&path.kind)
+        .map_err(|e| ApiError::validation("kind", e.code, e.message.unwrap_or_default()))?;
+    validate_language(&path.language)
+        .map_err(|e| ApiError::validation("language", e.code, e.message.unwrap_or_default()))
+}
+
+pub async fn list_templates(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(breach_documents::effective_templates(db.get_ref(), scope.entity_id).await?))
+}
+
+pub async fn update_template(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+    path: web::Path<TemplatePath>,
+    body: ValidatedJson<TemplateRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = require_admin(&req, &scope)?;
+    let path = path.into_inner();
+    check_path(&path)?;
+
+    let existing = breach_documents::find_template(db.get_ref(), scope.entity_id, &path.kind, &path.language).await?;
+    let now = Utc::now();
+    let template = match existing {
+        Some(template) => {
+            let mut template: TemplateActiveModel = template.into_active_model();
+            template.title = Set(body.title.trim().to_string());
+            template.body = Set(body.body.clone());
+            template.updated_by = Set(Some(user_id));
+            template.updated_at = Set(now);
+            template.update(db.get_ref()).await?
+        }
+        None => {
+            TemplateActiveModel {
+                id: Set(Uuid::new_v4()),
+                entity_id: Set(scope.entity_id),
+                kind: Set(path.kind),
+                language: Set(path.language),
+                title: Set(body.title.trim().to_string()),
+                body: Set(body.body.clone()),
+                updated_by: Set(Some(user_id)),
+                created_at: Set(now),
+                updated_at: Set(now),
+            }
+            .insert(db.get_ref())
+            .await?
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(BreachTemplate::from(template)))
+}
+
+/// Retour au modèle fourni, s'il en existe un dans cette langue.
+pub async fn reset_template(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+    path: web::Path<TemplatePath>,
+) -> Result<HttpResponse, ApiError> {
+    require_admin(&req, &scope)?;
+    let path = path.into_inner();
+    check_path(&path)?;
+
+    TemplateEntity::delete_many()
+        .filter(TemplateColumn::EntityId.eq(scope.entity_id))
+        .filter(TemplateColumn::Kind.eq(&path.kind))
+        .filter(TemplateColumn::Language.eq(&path.language))
+        .exec(db.get_ref())
+        .await?;
+
+    Ok(match breach_documents::default_template(&path.kind, &path.language) {
+        Some(template) => HttpResponse::Ok().json(template),
+        None => HttpResponse::NoContent().finish(),
+    })
+}
+
+/// Produit la notification ou la communication à partir du modèle de l'entité
+/// et la conserve en pièce jointe de la violation.
+pub async fn generate_document(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<GenerateDocumentRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = get_current_user_id(&req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+    let breach = find_breach(db.get_ref(), &scope, path.id).await?;
+    if breach.anonymized_at.is_some() {
+        return Err(ApiError::Conflict("Breach has been anonymized".to_string()));
+    }
+
+    let language = body.language.as_deref().unwrap_or(DEFAULT_LANGUAGE);
+    let template = match breach_documents::find_template(db.get_ref(), scope.entity_id, &body.kind, language).await? {
+        Some(template) => BreachTemplate::from(template),
+        None => breach_documents::default_template(&body.kind, language)
+            .ok_or_else(|| ApiError::NotFound("No template for this document and language".to_string()))?,
+    };
+
+    // Au-delà de 72 heures, la notification doit être accompagnée des motifs du retard
+    let generated_at = Utc::now();
+    let delay_reason = body.delay_reason.as_deref().filter(|r| !r.trim().is_empty());
+    if body.kind == KIND_AUTHORITY
+        && breach_documents::is_late(&breach, breach.notification_date.unwrap_or(generated_at))
+        && delay_reason.is_none()
+    {
+        return Err(ApiError::validation(
+            "delay_reason",
+            "delay_reason_required",
+            "The notification is made more than 72 hours after discovery; the reasons for the delay are required",
+        ));
+    }
+
+    let contact = match body.contact.as_deref().filter(|c| !c.trim().is_empty()) {
+        Some(contact) => contact.to_string(),
+        None => {
+            let dpos = breach_documents::dpo_contacts(db.get_ref(), scope.entity_id).await?;
+            if dpos.is_empty() { "—".to_string() } else { dpos.join("; ") }
+        }
+    };
+    let entity = EntityEntity::find_by_id(scope.entity_id)
+        .one(db.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Entity not found".to_string()))?;
+    let generated_by = UserEntity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await?
+        .map(|u| u.email)
+        .unwrap_or_else(|| user_id.to_string());
+
+    let values = breach_documents::values(&entity, &breach, &Inputs {
+        language,
+        contact,
+        consequences: body.consequences.as_deref(),
+        delay_reason,
+        generated_at,
+    });
+    let letter = Letter {
+        title: template.title,
+        organisation: entity.name.clone(),
+        footer: breach_documents::generated_on(language, generated_at),
+        generated_at,
+        generated_by,
+        body: breach_documents::fill(&template.body, &values),
+    };
+    // Mise en page hors de la boucle d'événements
+    let bytes = web::block(move || pdf::render_letter(&letter))
+        .await
+        .map_err(ApiError::internal("Document task failed"))?;
+
+    let file_name = format!("{}-{}-{}.pdf", body.kind, language, generated_at.format("%Y%m%d-%H%M%S"));
+    let attachment = attachments::store(
+        db.get_ref(),
+        &config,
+        &breach,
+        file_name,
+        Some("application/pdf".to_string()),
+        &bytes,
+        Some(user_id),
+    )
+    .await?;
+
+    Ok(HttpResponse::Created().json(BreachAttachment::from(attachment)))
+}
@@ -0,0 +1,398 @@
+//! Documents de notification d'une violation : notification à l'autorité de
+//! contrôle reprenant la structure du formulaire de la CNIL (Art. 33(3)) et
+//! communication aux personnes concernées (Art. 34). Les documents sont
+//! produits à partir de modèles propres à l'entité, par langue, et conservés
+//! en pièces jointes de la violation.
+pub mod handlers;
+pub mod models;
+
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QuerySelect};
+use uuid::Uuid;
+use crate::entities::rgpd::breach_assessment::{self, AUTHORITY_DEADLINE_HOURS};
+use crate::entities::rgpd::breach_assessment::models::BreachAssessment;
+use crate::entities::rgpd::export::document::contact;
+use crate::entities::rgpd::taxonomy::Choice;
+use crate::entities_orm::{breach, entity};
+use crate::entities_orm::register_entry::{Entity as RegisterEntryEntity, Column as RegisterEntryColumn};
+use crate::entities_orm::register_party::{Entity as RegisterPartyEntity, Column as RegisterPartyColumn};
+use crate::entities_orm::rgpd_party::{Entity as PartyEntity, Column as PartyColumn};
+use crate::entities_orm::rgpd_breach_template::{self, Entity as TemplateEntity, Column as TemplateColumn};
+use models::BreachTemplate;
+
+pub const KIND_AUTHORITY: &str = "authority_notification";
+pub const KIND_SUBJECTS: &str = "subject_notification";
+
+pub const DOCUMENT_KINDS: &[Choice] = &[
+    Choice { key: KIND_AUTHORITY, label: "Notification à l'autorité de contrôle (Art. 33)" },
+    Choice { key: KIND_SUBJECTS, label: "Communication aux personnes concernées (Art. 34)" },
+];
+
+/// Langues des modèles fournis ; l'entité peut en ajouter d'autres.
+pub const DEFAULT_LANGUAGES: &[&str] = &["fr", "en"];
+
+/// Champs disponibles dans les modèles, sous la forme `{{cle}}`.
+pub const PLACEHOLDERS: &[Choice] = &[
+    Choice { key: "organisation", label: "Nom de l'organisme" },
+    Choice { key: "contact", label: "DPO ou point de contact" },
+    Choice { key: "reference", label: "Référence interne de la violation" },
+    Choice { key: "breach_date", label: "Date de début de la violation" },
+    Choice { key: "discovery_date", label: "Date de découverte" },
+    Choice { key: "description", label: "Circonstances de la violation" },
+    Choice { key: "nature", label: "Nature de la violation (confidentialité, intégrité, disponibilité)" },
+    Choice { key: "data_categories", label: "Catégories de données concernées" },
+    Choice { key: "number_of_subjects", label: "Nombre approximatif de personnes concernées" },
+    Choice { key: "severity", label: "Gravité évaluée" },
+    Choice { key: "consequences", label: "Conséquences probables" },
+    Choice { key: "measures", label: "Mesures prises ou envisagées" },
+    Choice { key: "subjects_notification", label: "Information des personnes concernées" },
+    Choice { key: "delay_reason", label: "Motif d'une notification au-delà de 72 heures" },
+    Choice { key: "generated_at", label: "Date du document" },
+];
+
+const AUTHORITY_FR: &str = "Notification à la CNIL au titre de l'article 33 du RGPD
+Référence interne : {{reference}}
+Date : {{generated_at}}
+
+# 1. Responsable du traitement
+Organisme : {{organisation}}
+Personne à contacter (DPO ou autre point de contact) : {{contact}}
+
+# 2. Nature de la violation
+Date de début de la violation : {{breach_date}}
+Date de découverte : {{discovery_date}}
+Circonstances : {{description}}
+Nature : {{nature}}
+
+# 3. Données et personnes concernées
+Catégories de données : {{data_categories}}
+Nombre approximatif de personnes concernées : {{number_of_subjects}}
+
+# 4. Conséquences probables
+{{consequences}}
+Gravité évaluée : {{severity}}
+
+# 5. Mesures prises ou envisagées
+{{measures}}
+
+# 6. Information des personnes concernées
+{{subjects_notification}}
+
+# 7. Notification au-delà de 72 heures
+{{delay_reason}}";
+
+const AUTHORITY_EN: &str = "Notification to the supervisory authority under Article 33 GDPR
+Internal reference: {{reference}}
+Date: {{generated_at}}
+
+# 1. Controller
+Organisation: {{organisation}}
+Contact point (DPO or other): {{contact}}
+
+# 2. Nature of the breach
+Start of the breach: {{breach_date}}
+Discovery: {{discovery_date}}
+Circumstances: {{description}}
+Nature: {{nature}}
+
+# 3. Data and data subjects concerned
+Categories of data: {{data_categories}}
+Approximate number of data subjects: {{number_of_subjects}}
+
+# 4. Likely consequences
+{{consequences}}
+Assessed severity: {{severity}}
+
+# 5. Measures taken or proposed
+{{measures}}
+
+# 6. Communication to data subjects
+{{subjects_notification}}
+
+# 7. Notification after 72 hours
+{{delay_reason}}";
+
+const SUBJECTS_FR: &str = "Madame, Monsieur,
+
+Conformément à l'article 34 du RGPD, {{organisation}} vous informe d'une violation de données personnelles vous concernant, découverte le {{discovery_date}}.
+
+# Ce qui s'est passé
+{{description}}
+
+# Données concernées
+{{data_categories}}
+
+# Conséquences possibles
+{{consequences}}
+
+# Mesures prises
+{{measures}}
+
+# Contact
+Pour toute question, vous pouvez contacter : {{contact}}
+
+Vous disposez également du droit d'introduire une réclamation auprès de la CNIL.";
+
+const SUBJECTS_EN: &str = "Dear Sir or Madam,
+
+In accordance with Article 34 GDPR, {{organisation}} informs you of a personal data breach concerning you, discovered on {{discovery_date}}.
+
+# What happened
+{{description}}
+
+# Data concerned
+{{data_categories}}
+
+# Likely consequences
+{{consequences}}
+
+# Measures taken
+{{measures}}
+
+# Contact
+For any question, please contact: {{contact}}
+
+You also have the right to lodge a complaint with a supervisory authority.";
+
+/// Modèle fourni pour un type de document et une langue, s'il existe.
+pub fn default_template(kind: &str, language: &str) -> Option<BreachTemplate> {
+    let (title, body) = match (kind, language) {
+        (KIND_AUTHORITY, "fr") => ("Notification d'une violation de données personnelles", AUTHORITY_FR),
+        (KIND_AUTHORITY, "en") => ("Personal data breach notification", AUTHORITY_EN),
+        (KIND_SUBJECTS, "fr") => ("Information relative à une violation de vos données personnelles", SUBJECTS_FR),
+        (KIND_SUBJECTS, "en") => ("Information about a breach of your personal data", SUBJECTS_EN),
+        _ => return None,
+    };
+    Some(BreachTemplate {
+        kind: kind.to_string(),
+        language: language.to_string(),
+        title: title.to_string(),
+        body: body.to_string(),
+        is_default: true,
+        updated_by: None,
+        updated_at: None,
+    })
+}
+
+/// Modèles en vigueur : ceux de l'entité, complétés des modèles fournis.
+pub async fn effective_templates<C: ConnectionTrait>(db: &C, entity_id: Uuid) -> Result<Vec<BreachTemplate>, DbErr> {
+    let mut templates: Vec<BreachTemplate> = TemplateEntity::find()
+        .filter(TemplateColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(BreachTemplate::from)
+        .collect();
+    for kind in DOCUMENT_KINDS {
+        for language in DEFAULT_LANGUAGES {
+            if !templates.iter().any(|t| t.kind == kind.key && t.language == *language) {
+                templates.extend(default_template(kind.key, language));
+            }
+        }
+    }
+    templates.sort_by(|a, b| (&a.kind, &a.language).cmp(&(&b.kind, &b.language)));
+    Ok(templates)
+}
+
+pub async fn find_template<C: ConnectionTrait>(db: &C, entity_id: Uuid, kind: &str, language: &str) -> Result<Option<rgpd_breach_template::Model>, DbErr> {
+    TemplateEntity::find()
+        .filter(TemplateColumn::EntityId.eq(entity_id))
+        .filter(TemplateColumn::Kind.eq(kind))
+        .filter(TemplateColumn::Language.eq(language))
+        .one(db)
+        .await
+}
+
+/// Noms des champs `{{...}}` cités par un modèle, dans l'ordre.
+pub fn placeholders_in(body: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        names.push(rest[start + 2..start + end].trim());
+        rest = &rest[start + end + 2..];
+    }
+    names
+}
+
+/// Remplace les champs du modèle par leur valeur.
+pub fn fill(body: &str, values: &HashMap<&str, String>) -> String {
+    let mut filled = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        filled.push_str(&rest[..start]);
+        let name = rest[start + 2..start + end].trim();
+        filled.push_str(values.get(name).map_or("", |v| v.as_str()));
+        rest = &rest[start + end + 2..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// Notification à l'autorité intervenant après le délai de 72 heures.
+pub fn is_late(breach: &breach::Model, at: DateTime<Utc>) -> bool {
+    at > breach.discovery_date + Duration::hours(AUTHORITY_DEADLINE_HOURS)
+}
+
+// Texte généré dans la langue du document, l'anglais à défaut du français
+fn text(language: &str, fr: &str, en: &str) -> String {
+    if language == "fr" { fr } else { en }.to_string()
+}
+
+fn format_date(language: &str, date: DateTime<Utc>) -> String {
+    if language == "fr" {
+        date.format("%d/%m/%Y %H:%M UTC").to_string()
+    } else {
+        date.format("%Y-%m-%d %H:%M UTC").to_string()
+    }
+}
+
+/// Mention de génération portée en pied de page.
+pub fn generated_on(language: &str, at: DateTime<Utc>) -> String {
+    format!("{} {}", text(language, "Document généré le", "Generated on"), format_date(language, at))
+}
+
+fn or_dash(value: Option<&str>) -> String {
+    value.filter(|v| !v.trim().is_empty()).unwrap_or("—").to_string()
+}
+
+fn severity_label(language: &str, severity: &str) -> String {
+    match severity {
+        "low" => text(language, "faible", "low"),
+        "medium" => text(language, "moyenne", "medium"),
+        "high" => text(language, "élevée", "high"),
+        _ => text(language, "très élevée", "very high"),
+    }
+}
+
+// Pertes de confidentialité, d'intégrité et de disponibilité retenues par l'évaluation
+fn nature(language: &str, assessment: Option<&BreachAssessment>) -> String {
+    let Some(a) = assessment.map(|a| &a.assessment) else {
+        return "—".to_string();
+    };
+    let losses: Vec<String> = [
+        (a.confidentiality.as_str(), text(language, "perte de confidentialité", "loss of confidentiality")),
+        (a.integrity.as_str(), text(language, "perte d'intégrité", "loss of integrity")),
+        (a.availability.as_str(), text(language, "perte de disponibilité", "loss of availability")),
+    ]
+    .into_iter()
+    .filter(|(key, _)| *key != "none")
+    .map(|(_, label)| label)
+    .collect();
+    if losses.is_empty() { "—".to_string() } else { losses.join(", ") }
+}
+
+fn subjects_notification(language: &str, breach: &breach::Model, assessment: Option<&BreachAssessment>) -> String {
+    if breach.subjects_notified {
+        return text(language, "Les personnes concernées ont été informées.", "The data subjects have been informed.");
+    }
+    match assessment.map(|a| a.assessment.subjects_notification.as_str()) {
+        Some(breach_assessment::SUBJECTS_REQUIRED) => text(
+            language,
+            "Les personnes concernées vont être informées (risque élevé).",
+            "The data subjects will be informed (high risk).",
+        ),
+        Some(breach_assessment::SUBJECTS_PUBLIC_COMMUNICATION) => text(
+            language,
+            "Une communication publique est prévue, l'information individuelle exigeant des efforts disproportionnés (Art. 34(3)(c)).",
+            "A public communication is planned, as informing each data subject would involve disproportionate effort (Art. 34(3)(c)).",
+        ),
+        Some(breach_assessment::SUBJECTS_EXEMPTED) => text(
+            language,
+            "Les personnes concernées ne seront pas informées, une exception de l'article 34(3) s'appliquant.",
+            "The data subjects will not be informed, as an exception of Article 34(3) applies.",
+        ),
+        Some(_) => text(
+            language,
+            "Les personnes concernées ne seront pas informées, la violation n'engendrant pas de risque élevé.",
+            "The data subjects will not be informed, as the breach is not likely to result in a high risk.",
+        ),
+        None => "—".to_string(),
+    }
+}
+
+/// DPO désignés dans les fiches du registre de l'entité.
+pub async fn dpo_contacts<C: ConnectionTrait>(db: &C, entity_id: Uuid) -> Result<Vec<String>, DbErr> {
+    let entry_ids: Vec<Uuid> = RegisterEntryEntity::find()
+        .select_only()
+        .column(RegisterEntryColumn::Id)
+        .filter(RegisterEntryColumn::EntityId.eq(entity_id))
+        .into_tuple()
+        .all(db)
+        .await?;
+    let party_ids: Vec<Uuid> = RegisterPartyEntity::find()
+        .filter(RegisterPartyColumn::RegisterEntryId.is_in(entry_ids))
+        .filter(RegisterPartyColumn::Role.eq("dpo"))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|p| p.party_id)
+        .collect();
+    let mut contacts: Vec<String> = PartyEntity::find()
+        .filter(PartyColumn::Id.is_in(party_ids))
+        .all(db)
+        .await?
+        .iter()
+        .map(|p| contact(Some(p), &p.name))
+        .collect();
+    contacts.sort();
+    contacts.dedup();
+    Ok(contacts)
+}
+
+/// Informations saisies lors de la génération, en complément de la violation.
+pub struct Inputs<'a> {
+    pub language: &'a str,
+    pub contact: String,
+    pub consequences: Option<&'a str>,
+    pub delay_reason: Option<&'a str>,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Valeurs des champs des modèles pour la violation.
+pub fn values<'a>(entity: &entity::Model, breach: &breach::Model, inputs: &Inputs) -> HashMap<&'a str, String> {
+    let language = inputs.language;
+    let assessment = BreachAssessment::from_breach(breach);
+    let categories: Vec<String> = serde_json::from_value(breach.data_categories_affected.clone()).unwrap_or_default();
+    let severity = match &assessment {
+        Some(a) => format!(
+            "{} ({} {})",
+            severity_label(language, &breach.severity),
+            text(language, "score ENISA", "ENISA score"),
+            if language == "fr" { a.assessment.score.to_string().replace('.', ",") } else { a.assessment.score.to_string() },
+        ),
+        None => severity_label(language, &breach.severity),
+    };
+    let delay_reason = if is_late(breach, inputs.generated_at) {
+        or_dash(inputs.delay_reason)
+    } else {
+        text(language, "Sans objet : notification dans le délai de 72 heures.", "Not applicable: notified within 72 hours.")
+    };
+
+    HashMap::from([
+        ("organisation", entity.name.clone()),
+        ("contact", inputs.contact.clone()),
+        ("reference", breach.id.to_string()),
+        ("breach_date", format_date(language, breach.breach_date)),
+        ("discovery_date", format_date(language, breach.discovery_date)),
+        ("description", breach.description.clone()),
+        ("nature", nature(language, assessment.as_ref())),
+        ("data_categories", if categories.is_empty() { "—".to_string() } else { categories.join(", ") }),
+        ("number_of_subjects", breach.number_of_subjects.map_or_else(
+            || text(language, "inconnu", "unknown"),
+            |n| n.to_string(),
+        )),
+        ("severity", severity),
+        ("consequences", or_dash(inputs.consequences)),
+        ("measures", or_dash(breach.containment_measures.as_deref())),
+        ("subjects_notification", subjects_notification(language, breach, assessment.as_ref())),
+        ("delay_reason", delay_reason),
+        ("generated_at", format_date(language, inputs.generated_at)),
+    ])
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::{Validate, ValidationError};
+use crate::entities::rgpd::breach_documents::{placeholders_in, DOCUMENT_KINDS, PLACEHOLDERS};
+use crate::entities::rgpd::taxonomy;
+use crate::validation::{not_blank, one_of, validation_error};
+use crate::entities_orm::rgpd_breach_template;
+
+pub const DEFAULT_LANGUAGE: &str = "fr";
+
+pub fn validate_kind(value: &str) -> Result<(), ValidationError> {
+    one_of(value, &taxonomy::choice_keys(DOCUMENT_KINDS))
+}
+
+/// Code de langue ISO 639-1 en minuscules.
+pub fn validate_language(value: &str) -> Result<(), ValidationError> {
+    if value.len() == 2 && value.chars().all(|c| c.is_ascii_lowercase()) {
+        Ok(())
+    } else {
+        Err(validation_error("invalid_language", "Must be a two-letter ISO 639-1 code, such as fr or en"))
+    }
+}
+
+fn validate_placeholders(body: &str) -> Result<(), ValidationError> {
+    let known = taxonomy::choice_keys(PLACEHOLDERS);
+    let unknown: Vec<&str> = placeholders_in(body).into_iter()
+        .filter(|p| !known.contains(p))
+        .collect();
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(validation_error("unknown_placeholder", format!("Unknown placeholders: {}", unknown.join(", "))))
+    }
+}
+
+/// Modèle applicable à un type de document et une langue ; `is_default`
+/// signale le modèle fourni, faute de modèle propre à l'entité.
+#[derive(Debug, Clone, Serialize)]
+pub struct BreachTemplate {
+    pub kind: String, // "authority_notification", "subject_notification"
+    pub language: String,
+    pub title: String,
+    pub body: String,
+    pub is_default: bool,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl From<rgpd_breach_template::Model> for BreachTemplate {
+    fn from(model: rgpd_breach_template::Model) -> Self {
+        Self {
+            kind: model.kind,
+            language: model.language,
+            title: model.title,
+            body: model.body,
+            is_default: false,
+            updated_by: model.updated_by,
+            updated_at: Some(model.updated_at),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TemplatePath {
+    pub kind: String,
+    pub language: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct TemplateRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub title: String,
+    #[validate(length(min = 1, max = 50000), custom(function = "not_blank"), custom(function = "validate_placeholders"))]
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct GenerateDocumentRequest {
+    #[validate(custom(function = "validate_kind"))]
+    pub kind: String,
+    #[validate(custom(function = "validate_language"))]
+    pub language: Option<String>, // Français par défaut
+    #[validate(length(max = 1000))]
+    pub contact: Option<String>, // DPO désigné au registre par défaut
+    #[validate(length(max = 10000))]
+    pub consequences: Option<String>, // Conséquences probables (Art. 33(3)(c), 34(2))
+    #[validate(length(max = 10000))]
+    pub delay_reason: Option<String>, // Motif du retard au-delà de 72 heures (Art. 33(1))
+}
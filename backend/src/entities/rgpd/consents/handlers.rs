@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, Order, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait};
+use uuid::Uuid;
+use crate::config::Config;
+use crate::entities::rgpd::consents::{find_consent_entry, find_entry, subject_hash};
+use crate::entities::rgpd::consents::models::*;
+use crate::entities::scope::{EntityScope, ScopedId};
+use crate::errors::ApiError;
+use crate::middleware::get_current_user_id;
+use crate::validation::ValidatedJson;
+use crate::entities_orm::rgpd_consent::{self, Entity as ConsentEntity, Column as ConsentColumn};
+use crate::entities_orm::rgpd_consent_purpose::{Entity as PurposeEntity, Column as PurposeColumn, ActiveModel as PurposeActiveModel};
+use crate::entities_orm::register_entry::{Entity as RegisterEntryEntity, Column as RegisterEntryColumn};
+
+fn current_user(req: &HttpRequest) -> Result<Uuid, ApiError> {
+    get_current_user_id(req).ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))
+}
+
+// Les applications transmettent l'heure du recueil ; seule une légère avance est tolérée
+fn event_time(field: &str, value: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Result<DateTime<Utc>, ApiError> {
+    match value {
+        Some(at) if at > now + Duration::seconds(CLOCK_SKEW_SECONDS) => {
+            Err(ApiError::validation(field, "in_future", "Date cannot be in the future"))
+        }
+        Some(at) => Ok(at),
+        None => Ok(now),
+    }
+}
+
+/// Restitue les consentements avec le traitement et la version acceptée.
+async fn load(db: &DatabaseConnection, consents: Vec<rgpd_consent::Model>) -> Result<Vec<Consent>, ApiError> {
+    let purposes: HashMap<Uuid, (i32, String)> = PurposeEntity::find()
+        .filter(PurposeColumn::Id.is_in(consents.iter().map(|c| c.purpose_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|p| (p.id, (p.version, p.wording)))
+        .collect();
+    let entries: HashMap<Uuid, String> = RegisterEntryEntity::find()
+        .filter(RegisterEntryColumn::Id.is_in(consents.iter().map(|c| c.register_entry_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|e| (e.id, e.processing_name))
+        .collect();
+
+    Ok(consents.into_iter().map(|c| {
+        let (purpose_version, purpose_wording) = purposes.get(&c.purpose_id).cloned().unwrap_or_default();
+        Consent {
+            id: c.id,
+            processing_name: entries.get(&c.register_entry_id).cloned().unwrap_or_default(),
+            register_entry_id: c.register_entry_id,
+            purpose_version,
+            purpose_wording,
+            channel: c.channel,
+            source: c.source,
+            external_id: c.external_id,
+            consented_at: c.consented_at,
+            withdrawn_at: c.withdrawn_at,
+            withdrawal_channel: c.withdrawal_channel,
+            recorded_by: c.recorded_by,
+            created_at: c.created_at,
+        }
+    }).collect())
+}
+
+pub async fn list_purposes(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+) -> Result<HttpResponse, ApiError> {
+    let entry = find_entry(db.get_ref(), &scope, path.id, "id").await?;
+    let purposes: Vec<ConsentPurpose> = PurposeEntity::find()
+        .filter(PurposeColumn::RegisterEntryId.eq(entry.id))
+        .order_by(PurposeColumn::Version, Order::Desc)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(ConsentPurpose::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(purposes))
+}
+
+// Toute modification du texte crée une nouvelle version ; les précédentes restent
+// la référence des consentements déjà recueillis
+pub async fn create_purpose(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<CreateConsentPurposeRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = current_user(&req)?;
+    let entry = find_consent_entry(db.get_ref(), &scope, path.id, "id").await?;
+
+    // Le verrou sur la fiche sérialise la numérotation des versions
+    let txn = db.begin().await?;
+    RegisterEntryEntity::find_by_id(entry.id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?;
+    let last: Option<i32> = PurposeEntity::find()
+        .select_only()
+        .column_as(PurposeColumn::Version.max(), "version")
+        .filter(PurposeColumn::RegisterEntryId.eq(entry.id))
+        .into_tuple()
+        .one(&txn)
+        .await?
+        .flatten();
+    let purpose = PurposeActiveModel {
+        id: Set(Uuid::new_v4()),
+        entity_id: Set(scope.entity_id),
+        register_entry_id: Set(entry.id),
+        version: Set(last.unwrap_or(0) + 1),
+        wording: Set(body.wording.trim().to_string()),
+        created_by: Set(Some(user_id)),
+        created_at: Set(Utc::now()),
+    }
+    .insert(&txn)
+    .await?;
+    txn.commit().await?;
+
+    Ok(HttpResponse::Created().json(ConsentPurpose::from(purpose)))
+}
+
+// Consentement déjà enregistré sous la référence `external_id` : renvoyé tel
+// quel s'il porte sur la même personne et le même traitement
+async fn replay(
+    db: &DatabaseConnection,
+    entity_id: Uuid,
+    external_id: &str,
+    register_entry_id: Uuid,
+    hash: &str,
+) -> Result<Option<HttpResponse>, ApiError> {
+    let existing = ConsentEntity::find()
+        .filter(ConsentColumn::EntityId.eq(entity_id))
+        .filter(ConsentColumn::ExternalId.eq(external_id))
+        .one(db)
+        .await?;
+    let Some(existing) = existing else {
+        return Ok(None);
+    };
+    if existing.register_entry_id != register_entry_id || existing.subject_hash != hash {
+        return Err(ApiError::Conflict("external_id is already used by another consent".to_string()));
+    }
+    let mut consents = load(db, vec![existing]).await?;
+    Ok(Some(HttpResponse::Ok().json(consents.pop())))
+}
+
+/// Enregistre un consentement transmis par une application. Une référence
+/// `external_id` déjà connue renvoie le consentement existant, ce qui rend
+/// les nouvelles tentatives sans effet.
+pub async fn record_consent(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    scope: EntityScope,
+    body: ValidatedJson<ConsentRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = current_user(&req)?;
+    let now = Utc::now();
+    let consented_at = event_time("consented_at", body.consented_at, now)?;
+    let entry = find_consent_entry(db.get_ref(), &scope, body.register_entry_id, "register_entry_id").await?;
+    let hash = subject_hash(&config.consent_subject_key, scope.entity_id, &body.subject_id);
+
+    if let Some(external_id) = body.external_id.as_deref()
+        && let Some(response) = replay(db.get_ref(), scope.entity_id, external_id, entry.id, &hash).await?
+    {
+        return Ok(response);
+    }
+
+    let mut purposes = PurposeEntity::find()
+        .filter(PurposeColumn::RegisterEntryId.eq(entry.id));
+    purposes = match body.purpose_version {
+        Some(version) => purposes.filter(PurposeColumn::Version.eq(version)),
+        None => purposes.order_by(PurposeColumn::Version, Order::Desc),
+    };
+    let purpose = match purposes.one(db.get_ref()).await? {
+        Some(purpose) => purpose,
+        None if body.purpose_version.is_some() => {
+            return Err(ApiError::validation("purpose_version", "unknown_reference", "No consent wording has this version"));
+        }
+        None => {
+            return Err(ApiError::validation("purpose_version", "consent_wording_missing", "No consent wording is recorded for this processing"));
+        }
+    };
+
+    let consent = rgpd_consent::Model {
+        id: Uuid::new_v4(),
+        entity_id: scope.entity_id,
+        register_entry_id: entry.id,
+        purpose_id: purpose.id,
+        subject_hash: hash,
+        channel: body.channel.clone(),
+        source: body.source.clone(),
+        external_id: body.external_id.clone(),
+        consented_at,
+        withdrawn_at: None,
+        withdrawal_channel: None,
+        recorded_by: Some(user_id),
+        created_at: now,
+    };
+    // Deux envois simultanés de la même référence : le second n'insère rien et
+    // relit le consentement enregistré par le premier
+    let inserted = ConsentEntity::insert(consent.clone().into_active_model())
+        .on_conflict(
+            OnConflict::columns([ConsentColumn::EntityId, ConsentColumn::ExternalId])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db.get_ref())
+        .await?;
+    if inserted == 0
+        && let Some(external_id) = consent.external_id.as_deref()
+    {
+        return replay(db.get_ref(), scope.entity_id, external_id, entry.id, &consent.subject_hash)
+            .await?
+            .ok_or_else(|| ApiError::Conflict("external_id is already used by another consent".to_string()));
+    }
+
+    let mut consents = load(db.get_ref(), vec![consent]).await?;
+    Ok(HttpResponse::Created().json(consents.pop()))
+}
+
+/// Retire les consentements de la personne au traitement recueillis avant la
+/// date du retrait (Art. 7(3)) ; un consentement ultérieur reste acquis.
+pub async fn withdraw_consent(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    scope: EntityScope,
+    body: ValidatedJson<WithdrawConsentRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let withdrawn_at = event_time("withdrawn_at", body.withdrawn_at, Utc::now())?;
+    let entry = find_consent_entry(db.get_ref(), &scope, body.register_entry_id, "register_entry_id").await?;
+    let hash = subject_hash(&config.consent_subject_key, scope.entity_id, &body.subject_id);
+
+    let result = ConsentEntity::update_many()
+        .col_expr(ConsentColumn::WithdrawnAt, Expr::value(withdrawn_at))
+        .col_expr(ConsentColumn::WithdrawalChannel, Expr::value(body.channel.clone()))
+        .filter(ConsentColumn::RegisterEntryId.eq(entry.id))
+        .filter(ConsentColumn::SubjectHash.eq(hash))
+        .filter(ConsentColumn::WithdrawnAt.is_null())
+        .filter(ConsentColumn::ConsentedAt.lte(withdrawn_at))
+        .exec(db.get_ref())
+        .await?;
+    if result.rows_affected == 0 {
+        return Err(ApiError::NotFound("No active consent to withdraw".to_string()));
+    }
+
+    Ok(HttpResponse::Ok().json(WithdrawalResult { withdrawn: result.rows_affected, withdrawn_at }))
+}
+
+/// Preuve du consentement d'une personne. L'identifiant est transmis dans le
+/// corps de la requête pour ne pas figurer dans les journaux d'accès.
+pub async fn consent_proof(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    scope: EntityScope,
+    body: ValidatedJson<ConsentProofRequest>,
+) -> Result<HttpResponse, ApiError> {
+    if let Some(id) = body.register_entry_id {
+        find_entry(db.get_ref(), &scope, id, "register_entry_id").await?;
+    }
+    let hash = subject_hash(&config.consent_subject_key, scope.entity_id, &body.subject_id);
+
+    let mut query = ConsentEntity::find()
+        .filter(ConsentColumn::EntityId.eq(scope.entity_id))
+        .filter(ConsentColumn::SubjectHash.eq(hash.clone()));
+    if let Some(id) = body.register_entry_id {
+        query = query.filter(ConsentColumn::RegisterEntryId.eq(id));
+    }
+    let consents = load(db.get_ref(), query
+        .order_by(ConsentColumn::ConsentedAt, Order::Desc)
+        .all(db.get_ref())
+        .await?).await?;
+
+    let status = if consents.is_empty() {
+        STATUS_NONE
+    } else if consents.iter().any(|c| c.withdrawn_at.is_none()) {
+        STATUS_GIVEN
+    } else {
+        STATUS_WITHDRAWN
+    };
+
+    Ok(HttpResponse::Ok().json(ConsentProof {
+        subject_hash: hash,
+        register_entry_id: body.register_entry_id,
+        status: status.to_string(),
+        consents,
+        generated_at: Utc::now(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test::{init_service, try_call_service, TestRequest}, App};
+    use futures::future::join_all;
+    use serde_json::json;
+    use crate::auth::api_tokens;
+    use crate::entities_orm::register_entry::ActiveModel as RegisterEntryActiveModel;
+    use crate::middleware::AuthMiddleware;
+    use crate::test_support;
+    use super::*;
+
+    #[actix_web::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn concurrent_retries_record_one_consent() {
+        let db = test_support::database().await;
+        let config = test_support::config();
+        let user = test_support::create_user(&db, true).await;
+        let entity = test_support::create_entity(&db, None).await;
+        test_support::add_member(&db, user.id, entity.id, "admin").await;
+        let now = Utc::now();
+        let entry = RegisterEntryActiveModel {
+            id: Set(Uuid::new_v4()),
+            entity_id: Set(entity.id),
+            processing_name: Set("Newsletter".to_string()),
+            purpose: Set("Envoi de la lettre d'information".to_string()),
+            legal_basis: Set(Some("consent".to_string())),
+            record_type: Set("controller".to_string()),
+            shared_with_subsidiaries: Set(false),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        PurposeActiveModel {
+            id: Set(Uuid::new_v4()),
+            entity_id: Set(entity.id),
+            register_entry_id: Set(entry.id),
+            version: Set(1),
+            wording: Set("J'accepte de recevoir la lettre d'information".to_string()),
+            created_by: Set(Some(user.id)),
+            created_at: Set(now),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let (_, token) = api_tokens::issue(
+            &db, user.id, Some(entity.id), "crm", &["consents:write".to_string()], false, now + Duration::days(1),
+        ).await.unwrap();
+
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(web::Data::new(config.clone()))
+                .service(
+                    web::resource("/api/entities/{entity_id}/rgpd/consents")
+                        .wrap(AuthMiddleware)
+                        .route(web::post().to(record_consent))
+                )
+        ).await;
+        let send = |subject_id: &str| {
+            let request = TestRequest::post()
+                .uri(&format!("/api/entities/{}/rgpd/consents", entity.id))
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .set_json(json!({
+                    "register_entry_id": entry.id,
+                    "subject_id": subject_id,
+                    "channel": "web_form",
+                    "external_id": "crm-42",
+                }))
+                .to_request();
+            let app = &app;
+            async move {
+                match try_call_service(app, request).await {
+                    Ok(response) => response.status(),
+                    Err(error) => error.as_response_error().status_code(),
+                }
+            }
+        };
+
+        // Nouvelles tentatives simultanées d'un même envoi
+        let statuses = join_all((0..8).map(|_| send("alice@example.com"))).await;
+        assert_eq!(statuses.iter().filter(|s| **s == StatusCode::CREATED).count(), 1, "{:?}", statuses);
+        assert!(statuses.iter().all(|s| *s == StatusCode::CREATED || *s == StatusCode::OK), "{:?}", statuses);
+        let recorded = ConsentEntity::find()
+            .filter(ConsentColumn::EntityId.eq(entity.id))
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(recorded.len(), 1);
+
+        // La même référence pour une autre personne reste refusée
+        assert_eq!(send("bob@example.com").await, StatusCode::CONFLICT);
+
+        test_support::delete_user(&db, user.id).await;
+        test_support::delete_entities(&db, &[entity.id]).await;
+    }
+}
@@ -0,0 +1,114 @@
+//! Registre des consentements (Art. 7(1)) : versions du texte présenté aux
+//! personnes, recueils et retraits transmis par les applications de l'entité,
+//! preuve du consentement d'une personne et statistiques reprises au registre.
+pub mod handlers;
+pub mod models;
+
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QuerySelect};
+use sha2::Sha256;
+use uuid::Uuid;
+use crate::entities::rgpd::models::ConsentStats;
+use crate::entities::rgpd::taxonomy;
+use crate::entities::scope::EntityScope;
+use crate::errors::ApiError;
+use crate::entities_orm::register_entry::{self, Entity as RegisterEntryEntity, Column as RegisterEntryColumn};
+use crate::entities_orm::rgpd_consent::{Entity as ConsentEntity, Column as ConsentColumn};
+use crate::entities_orm::rgpd_consent_purpose::{Entity as PurposeEntity, Column as PurposeColumn};
+
+type HmacSha256 = Hmac<Sha256>;
+// Fiche, personnes, actives, actives sur la dernière version, derniers recueil et retrait
+type StatsRow = (Uuid, i64, i64, i64, Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+/// Empreinte d'un identifiant de personne : HMAC-SHA256 de l'identifiant
+/// normalisé (espaces de bord retirés, minuscules), propre à l'entité pour
+/// qu'une même personne ne puisse être rapprochée d'une entité à l'autre.
+pub fn subject_hash(key: &str, entity_id: Uuid, subject_id: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(key.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(format!("{}:{}", entity_id, subject_id.trim().to_lowercase()).as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Fiche du registre de l'entité ; `field` nomme le champ de la requête en erreur.
+pub async fn find_entry(db: &DatabaseConnection, scope: &EntityScope, id: Uuid, field: &str) -> Result<register_entry::Model, ApiError> {
+    RegisterEntryEntity::find_by_id(id)
+        .filter(RegisterEntryColumn::EntityId.eq(scope.entity_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::validation(field, "unknown_reference", "References an item that does not exist in this entity"))
+}
+
+/// Fiche de l'entité dont la base légale est le consentement.
+pub async fn find_consent_entry(db: &DatabaseConnection, scope: &EntityScope, id: Uuid, field: &str) -> Result<register_entry::Model, ApiError> {
+    let entry = find_entry(db, scope, id, field).await?;
+    if !taxonomy::is_consent_based(entry.legal_basis.as_deref(), entry.special_category_exception.as_deref()) {
+        return Err(ApiError::validation(field, "consent_not_applicable", "The processing is not based on consent"));
+    }
+    Ok(entry)
+}
+
+/// Statistiques des fiches fondées sur le consentement, calculées à la lecture.
+pub async fn stats_by_entry<C: ConnectionTrait>(db: &C, entries: &[register_entry::Model]) -> Result<HashMap<Uuid, ConsentStats>, DbErr> {
+    let ids: Vec<Uuid> = entries.iter()
+        .filter(|e| taxonomy::is_consent_based(e.legal_basis.as_deref(), e.special_category_exception.as_deref()))
+        .map(|e| e.id)
+        .collect();
+    let mut stats: HashMap<Uuid, ConsentStats> = ids.iter().map(|id| (*id, ConsentStats::default())).collect();
+    if ids.is_empty() {
+        return Ok(stats);
+    }
+
+    let versions: Vec<(Uuid, Option<i32>)> = PurposeEntity::find()
+        .select_only()
+        .column(PurposeColumn::RegisterEntryId)
+        .column_as(PurposeColumn::Version.max(), "version")
+        .filter(PurposeColumn::RegisterEntryId.is_in(ids.clone()))
+        .group_by(PurposeColumn::RegisterEntryId)
+        .into_tuple()
+        .all(db)
+        .await?;
+    for (entry_id, version) in versions {
+        if let Some(s) = stats.get_mut(&entry_id) {
+            s.current_version = version;
+        }
+    }
+
+    // Une personne est active tant qu'un de ses consentements n'est pas retiré
+    let rows: Vec<StatsRow> = ConsentEntity::find()
+        .select_only()
+        .column(ConsentColumn::RegisterEntryId)
+        .column_as(Expr::cust("count(DISTINCT subject_hash)"), "subjects")
+        .column_as(Expr::cust("count(DISTINCT subject_hash) FILTER (WHERE withdrawn_at IS NULL)"), "active")
+        .column_as(Expr::cust(
+            "count(DISTINCT subject_hash) FILTER (WHERE withdrawn_at IS NULL AND purpose_id = (
+                SELECT p.id FROM rgpd_consent_purposes p
+                WHERE p.register_entry_id = rgpd_consents.register_entry_id
+                ORDER BY p.version DESC LIMIT 1))",
+        ), "active_on_current_version")
+        .column_as(ConsentColumn::ConsentedAt.max(), "last_consented_at")
+        .column_as(ConsentColumn::WithdrawnAt.max(), "last_withdrawn_at")
+        .filter(ConsentColumn::RegisterEntryId.is_in(ids))
+        .group_by(ConsentColumn::RegisterEntryId)
+        .into_tuple()
+        .all(db)
+        .await?;
+    for (entry_id, subjects, active, active_on_current_version, last_consented_at, last_withdrawn_at) in rows {
+        if let Some(s) = stats.get_mut(&entry_id) {
+            s.subjects = subjects;
+            s.active = active;
+            s.withdrawn = subjects - active;
+            s.active_on_current_version = active_on_current_version;
+            s.last_consented_at = last_consented_at;
+            s.last_withdrawn_at = last_withdrawn_at;
+        }
+    }
+    Ok(stats)
+}
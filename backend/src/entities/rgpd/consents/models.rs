@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::{Validate, ValidationError};
+use crate::entities::rgpd::taxonomy::{self, CONSENT_CHANNELS};
+use crate::validation::{not_blank, one_of};
+use crate::entities_orm::rgpd_consent_purpose;
+
+/// Décalage d'horloge toléré sur les dates transmises par les applications.
+pub const CLOCK_SKEW_SECONDS: i64 = 300;
+
+pub const STATUS_GIVEN: &str = "given";
+pub const STATUS_WITHDRAWN: &str = "withdrawn";
+pub const STATUS_NONE: &str = "none";
+
+fn validate_channel(value: &str) -> Result<(), ValidationError> {
+    one_of(value, &taxonomy::choice_keys(CONSENT_CHANNELS))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsentPurpose {
+    pub id: Uuid,
+    pub register_entry_id: Uuid,
+    pub version: i32,
+    pub wording: String, // Texte présenté à la personne lors du recueil
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<rgpd_consent_purpose::Model> for ConsentPurpose {
+    fn from(model: rgpd_consent_purpose::Model) -> Self {
+        Self {
+            id: model.id,
+            register_entry_id: model.register_entry_id,
+            version: model.version,
+            wording: model.wording,
+            created_by: model.created_by,
+            created_at: model.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Consent {
+    pub id: Uuid,
+    pub register_entry_id: Uuid,
+    pub processing_name: String,
+    pub purpose_version: i32,
+    pub purpose_wording: String,
+    pub channel: String,
+    pub source: Option<String>, // Formulaire, écran ou version de l'application
+    pub external_id: Option<String>, // Référence du recueil dans l'application émettrice
+    pub consented_at: DateTime<Utc>,
+    pub withdrawn_at: Option<DateTime<Utc>>,
+    pub withdrawal_channel: Option<String>,
+    pub recorded_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Preuve du consentement d'une personne : historique complet de ses recueils
+/// et retraits, avec le texte de chaque version acceptée.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsentProof {
+    pub subject_hash: String,
+    pub register_entry_id: Option<Uuid>,
+    pub status: String, // "given", "withdrawn", "none"
+    pub consents: Vec<Consent>,
+    pub generated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WithdrawalResult {
+    pub withdrawn: u64, // Consentements retirés par la demande
+    pub withdrawn_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateConsentPurposeRequest {
+    #[validate(length(min = 1, max = 20000), custom(function = "not_blank"))]
+    pub wording: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ConsentRequest {
+    pub register_entry_id: Uuid,
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub subject_id: String, // Identifiant de la personne dans l'application, jamais conservé
+    #[validate(range(min = 1))]
+    pub purpose_version: Option<i32>, // Dernière version par défaut
+    #[validate(custom(function = "validate_channel"))]
+    pub channel: String,
+    pub consented_at: Option<DateTime<Utc>>, // Date de la requête par défaut
+    #[validate(length(max = 500))]
+    pub source: Option<String>,
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub external_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct WithdrawConsentRequest {
+    pub register_entry_id: Uuid,
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub subject_id: String,
+    #[validate(custom(function = "validate_channel"))]
+    pub channel: String,
+    pub withdrawn_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ConsentProofRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub subject_id: String,
+    pub register_entry_id: Option<Uuid>, // Tous les traitements de l'entité sinon
+}
@@ -0,0 +1,569 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, Order, PaginatorTrait, QueryFilter, QueryOrder, Set, TransactionTrait};
+use serde_json::json;
+use uuid::Uuid;
+use crate::entities::rgpd::dpia::{ensure_status, find_dpia, load, load_one};
+use crate::entities::rgpd::dpia::models::*;
+use crate::entities::scope::{EntityScope, ScopedId};
+use crate::errors::{ApiError, FieldError};
+use crate::middleware::{get_current_user_id, get_entity_role};
+use crate::validation::ValidatedJson;
+use crate::entities_orm::register_entry::{Entity as RegisterEntryEntity, Column as RegisterEntryColumn};
+use crate::entities_orm::rgpd_security_measure::{Entity as SecurityMeasureEntity, Column as SecurityMeasureColumn};
+use crate::entities_orm::rgpd_dpia::{Entity as DpiaEntity, Column as DpiaColumn, ActiveModel as DpiaActiveModel};
+use crate::entities_orm::rgpd_dpia_risk::{self, Entity as RiskEntity, Column as RiskColumn, ActiveModel as RiskActiveModel};
+use crate::entities_orm::rgpd_dpia_measure::{self, Entity as MeasureEntity, Column as MeasureColumn, ActiveModel as MeasureActiveModel};
+use crate::entities_orm::rgpd_dpia_version::{Entity as VersionEntity, Column as VersionColumn, ActiveModel as VersionActiveModel};
+use crate::entities_orm::rgpd_dpia_review::ActiveModel as ReviewActiveModel;
+
+// Le contenu de l'analyse ne se modifie qu'entre la pré-évaluation et la validation
+const EDITABLE: &[&str] = &[STATUS_SCREENING, STATUS_NOT_REQUIRED, STATUS_IN_PROGRESS];
+
+fn current_user(req: &HttpRequest) -> Result<Uuid, ApiError> {
+    get_current_user_id(req).ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))
+}
+
+fn unknown_reference(field: &str) -> ApiError {
+    ApiError::validation(field, "unknown_reference", "References an item that does not exist in this entity")
+}
+
+async fn touch(db: &impl sea_orm::ConnectionTrait, dpia_id: Uuid) -> Result<(), ApiError> {
+    DpiaActiveModel {
+        id: Set(dpia_id),
+        updated_at: Set(Utc::now()),
+        ..Default::default()
+    }
+    .update(db)
+    .await?;
+    Ok(())
+}
+
+pub async fn list_dpias(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    let dpias = DpiaEntity::find()
+        .filter(DpiaColumn::EntityId.eq(scope.entity_id))
+        .order_by(DpiaColumn::CreatedAt, Order::Desc)
+        .all(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(load(db.get_ref(), dpias).await?))
+}
+
+// Une analyse par fiche du registre ; ses versions en gardent l'historique
+pub async fn create_dpia(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+    body: ValidatedJson<CreateDpiaRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = current_user(&req)?;
+    RegisterEntryEntity::find_by_id(body.register_entry_id)
+        .filter(RegisterEntryColumn::EntityId.eq(scope.entity_id))
+        .one(db.get_ref())
+        .await?
+        .ok_or_else(|| unknown_reference("register_entry_id"))?;
+    let existing = DpiaEntity::find()
+        .filter(DpiaColumn::RegisterEntryId.eq(body.register_entry_id))
+        .count(db.get_ref())
+        .await?;
+    if existing > 0 {
+        return Err(ApiError::Conflict("A DPIA already exists for this register entry".to_string()));
+    }
+
+    let now = Utc::now();
+    let dpia = DpiaActiveModel {
+        id: Set(Uuid::new_v4()),
+        entity_id: Set(scope.entity_id),
+        register_entry_id: Set(body.register_entry_id),
+        title: Set(body.title.trim().to_string()),
+        status: Set(STATUS_SCREENING.to_string()),
+        screening_criteria: Set(json!([])),
+        screening_justification: Set(None),
+        screened_by: Set(None),
+        screened_at: Set(None),
+        context: Set(None),
+        necessity: Set(None),
+        proportionality: Set(None),
+        data_subject_rights: Set(None),
+        dpo_recommendation: Set(None),
+        dpo_opinion: Set(None),
+        dpo_opinion_by: Set(None),
+        dpo_opinion_at: Set(None),
+        version: Set(0),
+        next_review_at: Set(None),
+        last_reviewed_at: Set(None),
+        created_by: Set(Some(user_id)),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(db.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Created().json(load_one(db.get_ref(), dpia).await?))
+}
+
+pub async fn get_dpia(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+) -> Result<HttpResponse, ApiError> {
+    let dpia = find_dpia(db.get_ref(), &scope, path.id).await?;
+    Ok(HttpResponse::Ok().json(load_one(db.get_ref(), dpia).await?))
+}
+
+/// Sections de l'analyse : contexte, nécessité et proportionnalité, droits
+/// des personnes.
+pub async fn update_dpia(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<UpdateDpiaRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let dpia = find_dpia(db.get_ref(), &scope, path.id).await?;
+    ensure_status(&dpia, EDITABLE)?;
+
+    let mut dpia: DpiaActiveModel = dpia.into_active_model();
+    if let Some(title) = &body.title {
+        dpia.title = Set(title.trim().to_string());
+    }
+    if let Some(context) = &body.context {
+        dpia.context = Set(Some(context.clone()));
+    }
+    if let Some(necessity) = &body.necessity {
+        dpia.necessity = Set(Some(necessity.clone()));
+    }
+    if let Some(proportionality) = &body.proportionality {
+        dpia.proportionality = Set(Some(proportionality.clone()));
+    }
+    if let Some(rights) = &body.data_subject_rights {
+        dpia.data_subject_rights = Set(Some(rights.clone()));
+    }
+    if let Some(date) = &body.next_review_at {
+        dpia.next_review_at = Set(Some(*date));
+    }
+    dpia.updated_at = Set(Utc::now());
+    let dpia = dpia.update(db.get_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(load_one(db.get_ref(), dpia).await?))
+}
+
+// Une analyse déjà validée fait partie de la documentation de conformité
+pub async fn delete_dpia(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+) -> Result<HttpResponse, ApiError> {
+    let dpia = find_dpia(db.get_ref(), &scope, path.id).await?;
+    if dpia.version > 0 {
+        return Err(ApiError::Conflict("A signed-off DPIA cannot be deleted".to_string()));
+    }
+
+    DpiaEntity::delete_by_id(dpia.id)
+        .exec(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Questionnaire de pré-évaluation : l'analyse est requise à partir de deux
+/// critères, sauf décision contraire motivée.
+pub async fn screen_dpia(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<ScreeningRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = current_user(&req)?;
+    let dpia = find_dpia(db.get_ref(), &scope, path.id).await?;
+    ensure_status(&dpia, EDITABLE)?;
+
+    let decision = body.decision.clone().unwrap_or_else(|| screening_result(body.criteria.len()).to_string());
+    let now = Utc::now();
+    let mut dpia: DpiaActiveModel = dpia.into_active_model();
+    dpia.screening_criteria = Set(json!(body.criteria));
+    dpia.screening_justification = Set(body.justification.clone().filter(|j| !j.trim().is_empty()));
+    dpia.screened_by = Set(Some(user_id));
+    dpia.screened_at = Set(Some(now));
+    dpia.status = Set(if decision == "required" { STATUS_IN_PROGRESS } else { STATUS_NOT_REQUIRED }.to_string());
+    dpia.updated_at = Set(now);
+    let dpia = dpia.update(db.get_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(load_one(db.get_ref(), dpia).await?))
+}
+
+// ========== Risques ==========
+
+async fn find_risk(db: &DatabaseConnection, dpia_id: Uuid, id: Uuid) -> Result<rgpd_dpia_risk::Model, ApiError> {
+    RiskEntity::find_by_id(id)
+        .filter(RiskColumn::DpiaId.eq(dpia_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Risk not found".to_string()))
+}
+
+fn apply_risk(risk: &mut RiskActiveModel, body: &RiskRequest) {
+    risk.risk_type = Set(body.risk_type.clone());
+    risk.description = Set(body.description.clone());
+    risk.impacts = Set(body.impacts.clone());
+    risk.threats = Set(body.threats.clone());
+    risk.likelihood = Set(body.likelihood);
+    risk.severity = Set(body.severity);
+    risk.residual_likelihood = Set(body.residual_likelihood);
+    risk.residual_severity = Set(body.residual_severity);
+    risk.updated_at = Set(Utc::now());
+}
+
+pub async fn create_risk(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<RiskRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let dpia = find_dpia(db.get_ref(), &scope, path.id).await?;
+    ensure_status(&dpia, &[STATUS_IN_PROGRESS])?;
+
+    let mut risk = RiskActiveModel {
+        id: Set(Uuid::new_v4()),
+        dpia_id: Set(dpia.id),
+        created_at: Set(Utc::now()),
+        ..Default::default()
+    };
+    apply_risk(&mut risk, &body);
+    let txn = db.begin().await?;
+    let risk = risk.insert(&txn).await?;
+    touch(&txn, dpia.id).await?;
+    txn.commit().await?;
+
+    Ok(HttpResponse::Created().json(Risk::from(risk)))
+}
+
+pub async fn update_risk(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<DpiaItemPath>,
+    body: ValidatedJson<RiskRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let dpia = find_dpia(db.get_ref(), &scope, path.id).await?;
+    ensure_status(&dpia, &[STATUS_IN_PROGRESS])?;
+    let risk = find_risk(db.get_ref(), dpia.id, path.item_id).await?;
+
+    let mut risk: RiskActiveModel = risk.into_active_model();
+    apply_risk(&mut risk, &body);
+    let txn = db.begin().await?;
+    let risk = risk.update(&txn).await?;
+    touch(&txn, dpia.id).await?;
+    txn.commit().await?;
+
+    Ok(HttpResponse::Ok().json(Risk::from(risk)))
+}
+
+// Les mesures rattachées au risque sont conservées, sans risque associé
+pub async fn delete_risk(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<DpiaItemPath>,
+) -> Result<HttpResponse, ApiError> {
+    let dpia = find_dpia(db.get_ref(), &scope, path.id).await?;
+    ensure_status(&dpia, &[STATUS_IN_PROGRESS])?;
+    let risk = find_risk(db.get_ref(), dpia.id, path.item_id).await?;
+
+    let txn = db.begin().await?;
+    RiskEntity::delete_by_id(risk.id)
+        .exec(&txn)
+        .await?;
+    touch(&txn, dpia.id).await?;
+    txn.commit().await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// ========== Mesures ==========
+
+async fn find_measure(db: &DatabaseConnection, dpia_id: Uuid, id: Uuid) -> Result<rgpd_dpia_measure::Model, ApiError> {
+    MeasureEntity::find_by_id(id)
+        .filter(MeasureColumn::DpiaId.eq(dpia_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Measure not found".to_string()))
+}
+
+// Le risque traité appartient à l'analyse, la mesure du référentiel à l'entité
+async fn check_measure_references(db: &DatabaseConnection, scope: &EntityScope, dpia_id: Uuid, body: &MeasureRequest) -> Result<(), ApiError> {
+    if let Some(risk_id) = body.risk_id {
+        let found = RiskEntity::find_by_id(risk_id)
+            .filter(RiskColumn::DpiaId.eq(dpia_id))
+            .count(db)
+            .await?;
+        if found == 0 {
+            return Err(ApiError::validation("risk_id", "unknown_reference", "References a risk that does not belong to this DPIA"));
+        }
+    }
+    if let Some(measure_id) = body.security_measure_id {
+        let found = SecurityMeasureEntity::find_by_id(measure_id)
+            .filter(SecurityMeasureColumn::EntityId.eq(scope.entity_id))
+            .count(db)
+            .await?;
+        if found == 0 {
+            return Err(unknown_reference("security_measure_id"));
+        }
+    }
+    Ok(())
+}
+
+fn apply_measure(measure: &mut MeasureActiveModel, body: &MeasureRequest) {
+    measure.risk_id = Set(body.risk_id);
+    measure.security_measure_id = Set(body.security_measure_id);
+    measure.description = Set(body.description.clone());
+    measure.measure_type = Set(body.measure_type.clone());
+    measure.status = Set(body.status.clone().unwrap_or_else(|| "planned".to_string()));
+    measure.owner = Set(body.owner.clone());
+    measure.due_date = Set(body.due_date);
+    measure.updated_at = Set(Utc::now());
+}
+
+pub async fn create_measure(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<MeasureRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let dpia = find_dpia(db.get_ref(), &scope, path.id).await?;
+    ensure_status(&dpia, &[STATUS_IN_PROGRESS])?;
+    check_measure_references(db.get_ref(), &scope, dpia.id, &body).await?;
+
+    let mut measure = MeasureActiveModel {
+        id: Set(Uuid::new_v4()),
+        dpia_id: Set(dpia.id),
+        created_at: Set(Utc::now()),
+        ..Default::default()
+    };
+    apply_measure(&mut measure, &body);
+    let txn = db.begin().await?;
+    let measure = measure.insert(&txn).await?;
+    touch(&txn, dpia.id).await?;
+    txn.commit().await?;
+
+    Ok(HttpResponse::Created().json(Measure::from(measure)))
+}
+
+pub async fn update_measure(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<DpiaItemPath>,
+    body: ValidatedJson<MeasureRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let dpia = find_dpia(db.get_ref(), &scope, path.id).await?;
+    ensure_status(&dpia, &[STATUS_IN_PROGRESS])?;
+    let measure = find_measure(db.get_ref(), dpia.id, path.item_id).await?;
+    check_measure_references(db.get_ref(), &scope, dpia.id, &body).await?;
+
+    let mut measure: MeasureActiveModel = measure.into_active_model();
+    apply_measure(&mut measure, &body);
+    let txn = db.begin().await?;
+    let measure = measure.update(&txn).await?;
+    touch(&txn, dpia.id).await?;
+    txn.commit().await?;
+
+    Ok(HttpResponse::Ok().json(Measure::from(measure)))
+}
+
+pub async fn delete_measure(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<DpiaItemPath>,
+) -> Result<HttpResponse, ApiError> {
+    let dpia = find_dpia(db.get_ref(), &scope, path.id).await?;
+    ensure_status(&dpia, &[STATUS_IN_PROGRESS])?;
+    let measure = find_measure(db.get_ref(), dpia.id, path.item_id).await?;
+
+    let txn = db.begin().await?;
+    MeasureEntity::delete_by_id(measure.id)
+        .exec(&txn)
+        .await?;
+    touch(&txn, dpia.id).await?;
+    txn.commit().await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// ========== Avis du DPO, validation et revues ==========
+
+/// Avis du délégué à la protection des données (Art. 35(2)), préalable à la
+/// validation. Réservé au DPO désigné de l'entité (rôle `dpo`, hérité du groupe le cas échéant).
+pub async fn give_dpo_opinion(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<DpoOpinionRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = current_user(&req)?;
+    if get_entity_role(&req, scope.entity_id).as_deref() != Some("dpo") {
+        return Err(ApiError::Forbidden("DPO access required".to_string()));
+    }
+    let dpia = find_dpia(db.get_ref(), &scope, path.id).await?;
+    ensure_status(&dpia, &[STATUS_IN_PROGRESS])?;
+
+    let now = Utc::now();
+    let mut dpia: DpiaActiveModel = dpia.into_active_model();
+    dpia.dpo_recommendation = Set(Some(body.recommendation.clone()));
+    dpia.dpo_opinion = Set(Some(body.opinion.clone()));
+    dpia.dpo_opinion_by = Set(Some(user_id));
+    dpia.dpo_opinion_at = Set(Some(now));
+    dpia.updated_at = Set(now);
+    let dpia = dpia.update(db.get_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(load_one(db.get_ref(), dpia).await?))
+}
+
+// Éléments manquants pour présenter l'analyse à la validation
+fn sign_off_errors(dpia: &Dpia, body: &SignOffRequest) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    for (field, value) in [("necessity", &dpia.necessity), ("proportionality", &dpia.proportionality)] {
+        if value.as_deref().is_none_or(|v| v.trim().is_empty()) {
+            errors.push(FieldError::new(field, "required", format!("{} must be assessed before sign-off", field)));
+        }
+    }
+    if dpia.risks.is_empty() {
+        errors.push(FieldError::new("risks", "required", "At least one risk must be assessed before sign-off"));
+    }
+    for (i, risk) in dpia.risks.iter().enumerate() {
+        if risk.residual_level.is_none() {
+            errors.push(FieldError::new(format!("risks[{}]", i), "residual_risk_required", "The residual risk must be assessed before sign-off"));
+        }
+    }
+    if dpia.dpo_opinion.is_none() {
+        errors.push(FieldError::new("dpo_opinion", "required", "The DPO must give an opinion before sign-off"));
+    }
+    // Passer outre un avis défavorable du DPO doit être motivé
+    if body.decision == STATUS_APPROVED
+        && dpia.dpo_recommendation.as_deref() == Some("unfavourable")
+        && body.comment.as_deref().is_none_or(|c| c.trim().is_empty())
+    {
+        errors.push(FieldError::new("comment", "justification_required", "Approving against an unfavourable DPO opinion requires a comment"));
+    }
+    errors
+}
+
+/// Validation par le responsable de traitement (administrateur de l'entité) :
+/// fige une nouvelle version de l'analyse.
+pub async fn sign_off_dpia(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<SignOffRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = current_user(&req)?;
+    if get_entity_role(&req, scope.entity_id).as_deref() != Some("admin") {
+        return Err(ApiError::Forbidden("Admin access required".to_string()));
+    }
+    let dpia = find_dpia(db.get_ref(), &scope, path.id).await?;
+    ensure_status(&dpia, &[STATUS_IN_PROGRESS])?;
+    // L'avis du DPO et la décision du responsable doivent venir de deux personnes
+    if dpia.dpo_opinion_by == Some(user_id) {
+        return Err(ApiError::Forbidden("The DPO who gave the opinion cannot sign off the DPIA".to_string()));
+    }
+    let errors = sign_off_errors(&load_one(db.get_ref(), dpia.clone()).await?, &body);
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
+
+    let now = Utc::now();
+    let version = dpia.version + 1;
+    let txn = db.begin().await?;
+    let mut dpia: DpiaActiveModel = dpia.into_active_model();
+    dpia.status = Set(body.decision.clone());
+    dpia.version = Set(version);
+    if let Some(date) = &body.next_review_at {
+        dpia.next_review_at = Set(Some(*date));
+    }
+    dpia.updated_at = Set(now);
+    let dpia = dpia.update(&txn).await?;
+    let snapshot = load_one(&txn, dpia.clone()).await?;
+
+    VersionActiveModel {
+        id: Set(Uuid::new_v4()),
+        dpia_id: Set(dpia.id),
+        version: Set(version),
+        decision: Set(body.decision.clone()),
+        comment: Set(body.comment.clone()),
+        snapshot: Set(serde_json::to_value(&snapshot).map_err(ApiError::internal("Failed to serialize DPIA"))?),
+        signed_off_by: Set(Some(user_id)),
+        signed_off_at: Set(now),
+    }
+    .insert(&txn)
+    .await?;
+    txn.commit().await?;
+
+    Ok(HttpResponse::Ok().json(snapshot))
+}
+
+/// Revue d'une analyse validée par le responsable de traitement : confirmée
+/// en l'état, ou rouverte pour une nouvelle version (un nouvel avis du DPO sera requis).
+pub async fn review_dpia(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<ReviewRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = current_user(&req)?;
+    if get_entity_role(&req, scope.entity_id).as_deref() != Some("admin") {
+        return Err(ApiError::Forbidden("Admin access required".to_string()));
+    }
+    let dpia = find_dpia(db.get_ref(), &scope, path.id).await?;
+    ensure_status(&dpia, &[STATUS_APPROVED, STATUS_REJECTED])?;
+
+    let now = Utc::now();
+    let txn = db.begin().await?;
+    ReviewActiveModel {
+        id: Set(Uuid::new_v4()),
+        dpia_id: Set(dpia.id),
+        version: Set(dpia.version),
+        outcome: Set(body.outcome.clone()),
+        comment: Set(body.comment.clone()),
+        reviewed_by: Set(Some(user_id)),
+        reviewed_at: Set(now),
+    }
+    .insert(&txn)
+    .await?;
+
+    let mut dpia: DpiaActiveModel = dpia.into_active_model();
+    dpia.last_reviewed_at = Set(Some(now));
+    dpia.next_review_at = Set(body.next_review_at);
+    if body.outcome == "revision_required" {
+        dpia.status = Set(STATUS_IN_PROGRESS.to_string());
+        dpia.dpo_recommendation = Set(None);
+        dpia.dpo_opinion = Set(None);
+        dpia.dpo_opinion_by = Set(None);
+        dpia.dpo_opinion_at = Set(None);
+    }
+    dpia.updated_at = Set(now);
+    let dpia = dpia.update(&txn).await?;
+    txn.commit().await?;
+
+    Ok(HttpResponse::Ok().json(load_one(db.get_ref(), dpia).await?))
+}
+
+pub async fn list_dpia_versions(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+) -> Result<HttpResponse, ApiError> {
+    let dpia = find_dpia(db.get_ref(), &scope, path.id).await?;
+    let versions: Vec<DpiaVersion> = VersionEntity::find()
+        .filter(VersionColumn::DpiaId.eq(dpia.id))
+        .order_by(VersionColumn::Version, Order::Desc)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(DpiaVersion::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(versions))
+}
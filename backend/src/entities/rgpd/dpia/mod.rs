@@ -0,0 +1,73 @@
+//! Analyses d'impact relatives à la protection des données (Art. 35) :
+//! pré-évaluation sur les neuf critères du CEPD, analyse, avis du DPO,
+//! validation versionnée et revues périodiques.
+pub mod handlers;
+pub mod models;
+
+use sea_orm::{ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, Order, QueryFilter, QueryOrder};
+use uuid::Uuid;
+use crate::entities::scope::EntityScope;
+use crate::errors::ApiError;
+use crate::entities_orm::rgpd_dpia::{self, Entity as DpiaEntity, Column as DpiaColumn};
+use crate::entities_orm::rgpd_dpia_risk::{Entity as RiskEntity, Column as RiskColumn};
+use crate::entities_orm::rgpd_dpia_measure::{Entity as MeasureEntity, Column as MeasureColumn};
+use crate::entities_orm::rgpd_dpia_review::{Entity as ReviewEntity, Column as ReviewColumn};
+use models::{Dpia, Measure, Review, Risk};
+
+// Une analyse d'une autre entité est traitée comme inexistante
+pub async fn find_dpia(db: &DatabaseConnection, scope: &EntityScope, id: Uuid) -> Result<rgpd_dpia::Model, ApiError> {
+    DpiaEntity::find_by_id(id)
+        .filter(DpiaColumn::EntityId.eq(scope.entity_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("DPIA not found".to_string()))
+}
+
+/// Refuse l'opération si l'analyse n'est pas dans l'un des statuts attendus.
+pub fn ensure_status(dpia: &rgpd_dpia::Model, allowed: &[&str]) -> Result<(), ApiError> {
+    if allowed.contains(&dpia.status.as_str()) {
+        Ok(())
+    } else {
+        Err(ApiError::Conflict(format!(
+            "DPIA is {}; this operation requires status {}",
+            dpia.status,
+            allowed.join(" or "),
+        )))
+    }
+}
+
+/// Construit les analyses complètes (risques, mesures, revues) en quelques requêtes.
+pub async fn load<C: ConnectionTrait>(db: &C, dpias: Vec<rgpd_dpia::Model>) -> Result<Vec<Dpia>, DbErr> {
+    let ids: Vec<Uuid> = dpias.iter().map(|d| d.id).collect();
+
+    let risks = RiskEntity::find()
+        .filter(RiskColumn::DpiaId.is_in(ids.clone()))
+        .order_by(RiskColumn::CreatedAt, Order::Asc)
+        .all(db)
+        .await?;
+    let measures = MeasureEntity::find()
+        .filter(MeasureColumn::DpiaId.is_in(ids.clone()))
+        .order_by(MeasureColumn::CreatedAt, Order::Asc)
+        .all(db)
+        .await?;
+    let reviews = ReviewEntity::find()
+        .filter(ReviewColumn::DpiaId.is_in(ids))
+        .order_by(ReviewColumn::ReviewedAt, Order::Desc)
+        .all(db)
+        .await?;
+
+    Ok(dpias.into_iter().map(|d| {
+        let id = d.id;
+        Dpia::from_parts(
+            d,
+            risks.iter().filter(|r| r.dpia_id == id).cloned().map(Risk::from).collect(),
+            measures.iter().filter(|m| m.dpia_id == id).cloned().map(Measure::from).collect(),
+            reviews.iter().filter(|r| r.dpia_id == id).cloned().map(Review::from).collect(),
+        )
+    }).collect())
+}
+
+pub async fn load_one<C: ConnectionTrait>(db: &C, dpia: rgpd_dpia::Model) -> Result<Dpia, DbErr> {
+    let mut dpias = load(db, vec![dpia]).await?;
+    dpias.pop().ok_or_else(|| DbErr::RecordNotFound("DPIA".to_string()))
+}
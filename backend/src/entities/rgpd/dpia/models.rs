@@ -0,0 +1,399 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::{Validate, ValidationError};
+use crate::validation::{not_blank, one_of, validation_error};
+use crate::entities_orm::{rgpd_dpia, rgpd_dpia_measure, rgpd_dpia_review, rgpd_dpia_risk, rgpd_dpia_version};
+
+/// Les neuf critères des lignes directrices du CEPD (WP248), repris par la CNIL.
+pub const SCREENING_CRITERIA: &[&str] = &[
+    "evaluation_scoring",
+    "automated_decision",
+    "systematic_monitoring",
+    "sensitive_data",
+    "large_scale",
+    "matching_datasets",
+    "vulnerable_subjects",
+    "innovative_technology",
+    "prevents_exercise_of_rights",
+];
+/// Nombre de critères à partir duquel une analyse est en principe requise.
+pub const CRITERIA_THRESHOLD: usize = 2;
+pub const SCREENING_DECISIONS: &[&str] = &["required", "not_required"];
+pub const RISK_TYPES: &[&str] = &["illegitimate_access", "unwanted_modification", "disappearance"];
+pub const MEASURE_TYPES: &[&str] = &["technical", "organisational", "legal"];
+pub const MEASURE_STATUSES: &[&str] = &["planned", "in_progress", "implemented"];
+pub const DPO_RECOMMENDATIONS: &[&str] = &["favourable", "favourable_with_reservations", "unfavourable"];
+pub const SIGN_OFF_DECISIONS: &[&str] = &["approved", "rejected"];
+pub const REVIEW_OUTCOMES: &[&str] = &["unchanged", "revision_required"];
+
+pub const STATUS_SCREENING: &str = "screening";
+pub const STATUS_NOT_REQUIRED: &str = "not_required";
+pub const STATUS_IN_PROGRESS: &str = "in_progress";
+pub const STATUS_APPROVED: &str = "approved";
+pub const STATUS_REJECTED: &str = "rejected";
+
+fn validate_criteria(items: &[String]) -> Result<(), ValidationError> {
+    for (i, item) in items.iter().enumerate() {
+        one_of(item, SCREENING_CRITERIA)?;
+        if items[..i].contains(item) {
+            return Err(validation_error("duplicate", "Each criterion may only be listed once"));
+        }
+    }
+    Ok(())
+}
+
+fn validate_screening_decision(value: &str) -> Result<(), ValidationError> {
+    one_of(value, SCREENING_DECISIONS)
+}
+
+fn validate_risk_type(value: &str) -> Result<(), ValidationError> {
+    one_of(value, RISK_TYPES)
+}
+
+fn validate_measure_type(value: &str) -> Result<(), ValidationError> {
+    one_of(value, MEASURE_TYPES)
+}
+
+fn validate_measure_status(value: &str) -> Result<(), ValidationError> {
+    one_of(value, MEASURE_STATUSES)
+}
+
+fn validate_recommendation(value: &str) -> Result<(), ValidationError> {
+    one_of(value, DPO_RECOMMENDATIONS)
+}
+
+fn validate_decision(value: &str) -> Result<(), ValidationError> {
+    one_of(value, SIGN_OFF_DECISIONS)
+}
+
+fn validate_review_outcome(value: &str) -> Result<(), ValidationError> {
+    one_of(value, REVIEW_OUTCOMES)
+}
+
+/// Décision issue du questionnaire : au moins deux critères rendent
+/// l'analyse obligatoire.
+pub fn screening_result(criteria: usize) -> &'static str {
+    if criteria >= CRITERIA_THRESHOLD { "required" } else { "not_required" }
+}
+
+// S'écarter du résultat du questionnaire doit être motivé
+fn validate_screening(req: &ScreeningRequest) -> Result<(), ValidationError> {
+    let computed = screening_result(req.criteria.len());
+    if req.decision.as_deref().is_some_and(|d| d != computed)
+        && req.justification.as_deref().is_none_or(|j| j.trim().is_empty())
+    {
+        return Err(validation_error("justification_required", "A justification is required when the decision departs from the screening result"));
+    }
+    Ok(())
+}
+
+// Le risque résiduel s'évalue sur les deux axes à la fois
+fn validate_risk(req: &RiskRequest) -> Result<(), ValidationError> {
+    if req.residual_likelihood.is_some() != req.residual_severity.is_some() {
+        return Err(validation_error("incomplete_residual_risk", "residual_likelihood and residual_severity go together"));
+    }
+    Ok(())
+}
+
+/// Niveau d'un risque sur l'échelle à quatre paliers de la CNIL (négligeable,
+/// limité, important, maximal) : élevé quand vraisemblance et gravité sont
+/// toutes deux importantes ou maximales.
+pub fn risk_level(likelihood: i32, severity: i32) -> &'static str {
+    match (likelihood >= 3, severity >= 3) {
+        (true, true) => "high",
+        (false, false) => "low",
+        _ => "medium",
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Dpia {
+    pub id: Uuid,
+    pub entity_id: Uuid,
+    pub register_entry_id: Uuid,
+    pub title: String,
+    pub status: String, // "screening", "not_required", "in_progress", "approved", "rejected"
+    pub screening_criteria: Vec<String>,
+    pub screening_result: Option<String>, // Résultat du questionnaire, une fois renseigné
+    pub screening_justification: Option<String>,
+    pub screened_by: Option<Uuid>,
+    pub screened_at: Option<DateTime<Utc>>,
+    pub context: Option<String>,
+    pub necessity: Option<String>,
+    pub proportionality: Option<String>,
+    pub data_subject_rights: Option<String>,
+    pub risks: Vec<Risk>,
+    pub measures: Vec<Measure>,
+    pub dpo_recommendation: Option<String>,
+    pub dpo_opinion: Option<String>,
+    pub dpo_opinion_by: Option<Uuid>,
+    pub dpo_opinion_at: Option<DateTime<Utc>>,
+    pub prior_consultation_required: bool, // Risque résiduel élevé : consultation de l'autorité (Art. 36)
+    pub version: i32, // Dernière version validée, 0 avant la première décision
+    pub reviews: Vec<Review>,
+    pub next_review_at: Option<DateTime<Utc>>,
+    pub last_reviewed_at: Option<DateTime<Utc>>,
+    pub review_overdue: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Dpia {
+    pub fn from_parts(model: rgpd_dpia::Model, risks: Vec<Risk>, measures: Vec<Measure>, reviews: Vec<Review>) -> Self {
+        let criteria: Vec<String> = serde_json::from_value(model.screening_criteria).unwrap_or_default();
+        let prior_consultation_required = risks.iter().any(|r| r.residual_level.as_deref() == Some("high"));
+        Self {
+            id: model.id,
+            entity_id: model.entity_id,
+            register_entry_id: model.register_entry_id,
+            title: model.title,
+            screening_result: model.screened_at.map(|_| screening_result(criteria.len()).to_string()),
+            screening_criteria: criteria,
+            screening_justification: model.screening_justification,
+            screened_by: model.screened_by,
+            screened_at: model.screened_at,
+            context: model.context,
+            necessity: model.necessity,
+            proportionality: model.proportionality,
+            data_subject_rights: model.data_subject_rights,
+            risks,
+            measures,
+            dpo_recommendation: model.dpo_recommendation,
+            dpo_opinion: model.dpo_opinion,
+            dpo_opinion_by: model.dpo_opinion_by,
+            dpo_opinion_at: model.dpo_opinion_at,
+            prior_consultation_required,
+            version: model.version,
+            reviews,
+            review_overdue: model.status == STATUS_APPROVED && model.next_review_at.is_some_and(|at| at < Utc::now()),
+            next_review_at: model.next_review_at,
+            last_reviewed_at: model.last_reviewed_at,
+            status: model.status,
+            created_by: model.created_by,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Risk {
+    pub id: Uuid,
+    pub risk_type: String, // "illegitimate_access", "unwanted_modification", "disappearance"
+    pub description: String,
+    pub impacts: Option<String>,
+    pub threats: Option<String>,
+    pub likelihood: i32, // 1 à 4
+    pub severity: i32,   // 1 à 4
+    pub level: String,
+    pub residual_likelihood: Option<i32>,
+    pub residual_severity: Option<i32>,
+    pub residual_level: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<rgpd_dpia_risk::Model> for Risk {
+    fn from(model: rgpd_dpia_risk::Model) -> Self {
+        Self {
+            id: model.id,
+            risk_type: model.risk_type,
+            description: model.description,
+            impacts: model.impacts,
+            threats: model.threats,
+            likelihood: model.likelihood,
+            severity: model.severity,
+            level: risk_level(model.likelihood, model.severity).to_string(),
+            residual_likelihood: model.residual_likelihood,
+            residual_severity: model.residual_severity,
+            residual_level: model.residual_likelihood
+                .zip(model.residual_severity)
+                .map(|(l, s)| risk_level(l, s).to_string()),
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Measure {
+    pub id: Uuid,
+    pub risk_id: Option<Uuid>,
+    pub security_measure_id: Option<Uuid>, // Mesure correspondante du référentiel
+    pub description: String,
+    pub measure_type: String, // "technical", "organisational", "legal"
+    pub status: String,       // "planned", "in_progress", "implemented"
+    pub owner: Option<String>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<rgpd_dpia_measure::Model> for Measure {
+    fn from(model: rgpd_dpia_measure::Model) -> Self {
+        Self {
+            id: model.id,
+            risk_id: model.risk_id,
+            security_measure_id: model.security_measure_id,
+            description: model.description,
+            measure_type: model.measure_type,
+            status: model.status,
+            owner: model.owner,
+            due_date: model.due_date,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Review {
+    pub id: Uuid,
+    pub version: i32,
+    pub outcome: String, // "unchanged", "revision_required"
+    pub comment: Option<String>,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: DateTime<Utc>,
+}
+
+impl From<rgpd_dpia_review::Model> for Review {
+    fn from(model: rgpd_dpia_review::Model) -> Self {
+        Self {
+            id: model.id,
+            version: model.version,
+            outcome: model.outcome,
+            comment: model.comment,
+            reviewed_by: model.reviewed_by,
+            reviewed_at: model.reviewed_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DpiaVersion {
+    pub id: Uuid,
+    pub version: i32,
+    pub decision: String, // "approved", "rejected"
+    pub comment: Option<String>,
+    pub snapshot: serde_json::Value, // L'analyse telle que validée
+    pub signed_off_by: Option<Uuid>,
+    pub signed_off_at: DateTime<Utc>,
+}
+
+impl From<rgpd_dpia_version::Model> for DpiaVersion {
+    fn from(model: rgpd_dpia_version::Model) -> Self {
+        Self {
+            id: model.id,
+            version: model.version,
+            decision: model.decision,
+            comment: model.comment,
+            snapshot: model.snapshot,
+            signed_off_by: model.signed_off_by,
+            signed_off_at: model.signed_off_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateDpiaRequest {
+    pub register_entry_id: Uuid,
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub title: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateDpiaRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub title: Option<String>,
+    #[validate(length(max = 20000))]
+    pub context: Option<String>,
+    #[validate(length(max = 20000))]
+    pub necessity: Option<String>,
+    #[validate(length(max = 20000))]
+    pub proportionality: Option<String>,
+    #[validate(length(max = 20000))]
+    pub data_subject_rights: Option<String>,
+    pub next_review_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_screening"))]
+pub struct ScreeningRequest {
+    #[validate(custom(function = "validate_criteria"))]
+    pub criteria: Vec<String>,
+    #[validate(custom(function = "validate_screening_decision"))]
+    pub decision: Option<String>, // Par défaut, le résultat du questionnaire
+    #[validate(length(max = 5000))]
+    pub justification: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_risk"))]
+pub struct RiskRequest {
+    #[validate(custom(function = "validate_risk_type"))]
+    pub risk_type: String,
+    #[validate(length(min = 1, max = 5000), custom(function = "not_blank"))]
+    pub description: String,
+    #[validate(length(max = 5000))]
+    pub impacts: Option<String>,
+    #[validate(length(max = 5000))]
+    pub threats: Option<String>,
+    #[validate(range(min = 1, max = 4))]
+    pub likelihood: i32,
+    #[validate(range(min = 1, max = 4))]
+    pub severity: i32,
+    #[validate(range(min = 1, max = 4))]
+    pub residual_likelihood: Option<i32>,
+    #[validate(range(min = 1, max = 4))]
+    pub residual_severity: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct MeasureRequest {
+    pub risk_id: Option<Uuid>,
+    pub security_measure_id: Option<Uuid>,
+    #[validate(length(min = 1, max = 5000), custom(function = "not_blank"))]
+    pub description: String,
+    #[validate(custom(function = "validate_measure_type"))]
+    pub measure_type: String,
+    #[validate(custom(function = "validate_measure_status"))]
+    pub status: Option<String>, // "planned" par défaut
+    #[validate(length(max = 255))]
+    pub owner: Option<String>,
+    pub due_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct DpoOpinionRequest {
+    #[validate(custom(function = "validate_recommendation"))]
+    pub recommendation: String,
+    #[validate(length(min = 1, max = 20000), custom(function = "not_blank"))]
+    pub opinion: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SignOffRequest {
+    #[validate(custom(function = "validate_decision"))]
+    pub decision: String,
+    #[validate(length(max = 5000))]
+    pub comment: Option<String>,
+    pub next_review_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReviewRequest {
+    #[validate(custom(function = "validate_review_outcome"))]
+    pub outcome: String,
+    #[validate(length(max = 5000))]
+    pub comment: Option<String>,
+    pub next_review_at: Option<DateTime<Utc>>,
+}
+
+/// Chemin d'un risque ou d'une mesure d'une analyse.
+#[derive(Debug, Deserialize)]
+pub struct DpiaItemPath {
+    pub id: Uuid,
+    pub item_id: Uuid,
+}
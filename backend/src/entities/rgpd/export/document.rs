@@ -0,0 +1,313 @@
+//! Contenu du registre mis en forme selon le modèle de la CNIL : une page
+//! « Organisme », la liste des activités, puis une fiche par traitement.
+//! Les rendus PDF et tableur partent de cette même structure.
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::entities::rgpd::models::RegisterEntry;
+use crate::entities::rgpd::taxonomy::{self, Choice, LEGAL_BASES, RETENTION_ACTIONS, RETENTION_TRIGGERS, SPECIAL_CATEGORY_EXCEPTIONS};
+use crate::entities_orm::{entity, rgpd_party};
+
+pub const TITLE: &str = "Registre des activités de traitement";
+
+pub struct Field {
+    pub label: &'static str,
+    pub value: String,
+}
+
+pub struct Section {
+    pub title: &'static str,
+    pub fields: Vec<Field>,
+}
+
+pub struct Record {
+    pub reference: String,
+    pub name: String,
+    pub record_type: &'static str,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub sections: Vec<Section>,
+}
+
+pub struct RegisterDocument {
+    pub organisation: String,
+    pub version: i32,
+    pub generated_at: DateTime<Utc>,
+    pub generated_by: String,
+    pub header: Vec<Field>,
+    pub records: Vec<Record>,
+}
+
+impl RegisterDocument {
+    /// Mention portée en tête et en pied de chaque rendu.
+    pub fn edition(&self) -> String {
+        format!("Version {} du {}", self.version, format_date_time(self.generated_at))
+    }
+}
+
+pub fn format_date(date: DateTime<Utc>) -> String {
+    date.format("%d/%m/%Y").to_string()
+}
+
+pub fn format_date_time(date: DateTime<Utc>) -> String {
+    date.format("%d/%m/%Y %H:%M UTC").to_string()
+}
+
+pub fn record_type_label(record_type: &str) -> &'static str {
+    match record_type {
+        "processor" => "Sous-traitant (art. 30.2)",
+        _ => "Responsable de traitement (art. 30.1)",
+    }
+}
+
+pub fn safeguard_label(safeguard: &str) -> &'static str {
+    match safeguard {
+        "adequacy" => "Décision d'adéquation",
+        "scc" => "Clauses contractuelles types",
+        "bcr" => "Règles d'entreprise contraignantes",
+        "derogation" => "Dérogation (art. 49)",
+        _ => "Autre garantie",
+    }
+}
+
+pub fn dpia_status_label(status: &str) -> &'static str {
+    match status {
+        "screening" => "Pré-évaluation en cours",
+        "not_required" => "Non requise",
+        "in_progress" => "En cours",
+        "approved" => "Validée",
+        _ => "Refusée",
+    }
+}
+
+// Nom et coordonnées d'un tiers, sur une ligne
+pub fn contact(party: Option<&rgpd_party::Model>, name: &str) -> String {
+    let Some(party) = party else {
+        return name.to_string();
+    };
+    let details: Vec<&str> = [&party.contact_name, &party.email, &party.phone, &party.address, &party.country]
+        .into_iter()
+        .filter_map(|v| v.as_deref())
+        .filter(|v| !v.trim().is_empty())
+        .collect();
+    if details.is_empty() {
+        party.name.clone()
+    } else {
+        format!("{} ({})", party.name, details.join(", "))
+    }
+}
+
+fn join_lines(values: impl IntoIterator<Item = String>) -> String {
+    let values: Vec<String> = values.into_iter().collect();
+    if values.is_empty() {
+        "—".to_string()
+    } else {
+        values.join("\n")
+    }
+}
+
+fn retention_choice(choices: &[Choice], key: Option<&str>) -> String {
+    key.map_or_else(|| "—".to_string(), |key| taxonomy::choice_label(choices, key).unwrap_or(key).to_string())
+}
+
+fn parties_with_role(entry: &RegisterEntry, role: &str, parties: &HashMap<Uuid, rgpd_party::Model>) -> String {
+    join_lines(entry.parties.iter()
+        .filter(|p| p.role == role)
+        .map(|p| contact(parties.get(&p.party_id), &p.name)))
+}
+
+fn record(index: usize, entry: &RegisterEntry, organisation: &str, parties: &HashMap<Uuid, rgpd_party::Model>) -> Record {
+    let processor = entry.record_type == "processor";
+    let mut description = vec![
+        Field { label: "Nom du traitement", value: entry.processing_name.clone() },
+        Field { label: "Référence", value: format!("T-{:03}", index + 1) },
+        Field { label: "Registre", value: record_type_label(&entry.record_type).to_string() },
+        Field { label: "Date de création", value: format_date(entry.created_at) },
+        Field { label: "Dernière mise à jour", value: format_date(entry.updated_at) },
+    ];
+    description.push(Field {
+        label: "Analyse d'impact (AIPD)",
+        value: match &entry.dpia {
+            Some(dpia) if dpia.version > 0 => format!("{} (version {})", dpia_status_label(&dpia.status), dpia.version),
+            Some(dpia) => dpia_status_label(&dpia.status).to_string(),
+            None => "—".to_string(),
+        },
+    });
+    if !processor {
+        let mut legal_basis = entry.legal_basis.as_deref()
+            .and_then(|key| taxonomy::label(LEGAL_BASES, key))
+            .unwrap_or_else(|| "—".to_string());
+        if let Some(details) = entry.legal_basis_details.as_deref().filter(|d| !d.trim().is_empty()) {
+            legal_basis.push_str(&format!("\n{}", details));
+        }
+        description.push(Field { label: "Base légale", value: legal_basis });
+        if let Some(exception) = entry.special_category_exception.as_deref() {
+            description.push(Field {
+                label: "Exception (données sensibles)",
+                value: taxonomy::label(SPECIAL_CATEGORY_EXCEPTIONS, exception).unwrap_or_else(|| exception.to_string()),
+            });
+        }
+        if let Some(basis) = entry.criminal_data_basis.as_deref() {
+            description.push(Field { label: "Fondement (infractions, art. 10)", value: basis.to_string() });
+        }
+        if let Some(mechanism) = entry.consent_mechanism.as_deref() {
+            description.push(Field { label: "Recueil du consentement", value: mechanism.to_string() });
+        }
+    }
+
+    // L'organisme est responsable de ses traitements, ou sous-traitant pour le compte des responsables cités
+    let (controllers, processors) = if processor {
+        (parties_with_role(entry, "controller", parties), join_lines([organisation.to_string()]))
+    } else {
+        (join_lines([organisation.to_string()]), parties_with_role(entry, "processor", parties))
+    };
+    let actors = vec![
+        Field { label: "Responsable(s) de traitement", value: controllers },
+        Field { label: "Co-responsable(s)", value: parties_with_role(entry, "joint_controller", parties) },
+        Field { label: "Représentant", value: parties_with_role(entry, "representative", parties) },
+        Field { label: "Délégué à la protection des données", value: parties_with_role(entry, "dpo", parties) },
+        Field { label: "Sous-traitant(s)", value: processors },
+    ];
+
+    let transfers = join_lines(entry.transfers.iter().map(|t| {
+        let mut line = format!(
+            "{} — {} — {}",
+            t.party_id.map(|id| contact(parties.get(&id), t.party_name.as_deref().unwrap_or_default()))
+                .unwrap_or_else(|| "Destinataire non précisé".to_string()),
+            t.country,
+            safeguard_label(&t.safeguard),
+        );
+        if let Some(details) = t.safeguard_details.as_deref().filter(|d| !d.trim().is_empty()) {
+            line.push_str(&format!(" ({})", details));
+        }
+        if let Some(module) = t.scc_module.as_deref().and_then(|m| taxonomy::choice_label(taxonomy::SCC_MODULES, m)) {
+            line.push_str(&format!(" — {}", module));
+        }
+        if let Some(outcome) = t.tia_outcome.as_deref().and_then(|o| taxonomy::choice_label(taxonomy::TIA_OUTCOMES, o)) {
+            line.push_str(&format!(" — Évaluation d'impact : {}", outcome));
+        }
+        line
+    }));
+
+    let mut measures: Vec<String> = entry.measures.iter()
+        .map(|m| format!("{} ({})", m.name, m.category))
+        .collect();
+    if let Some(text) = entry.security_measures.as_deref().filter(|t| !t.trim().is_empty()) {
+        measures.push(text.to_string());
+    }
+
+    Record {
+        reference: format!("T-{:03}", index + 1),
+        name: entry.processing_name.clone(),
+        record_type: record_type_label(&entry.record_type),
+        created_at: entry.created_at,
+        updated_at: entry.updated_at,
+        sections: vec![
+            Section { title: "Description du traitement", fields: description },
+            Section { title: "Acteurs", fields: actors },
+            Section {
+                title: "Finalité(s) du traitement",
+                fields: vec![Field { label: "Finalité principale", value: entry.purpose.clone() }],
+            },
+            Section {
+                title: "Catégories de données personnelles",
+                fields: vec![Field {
+                    label: "Données traitées",
+                    value: join_lines(entry.data_categories.iter().map(|c| match c.special.as_deref() {
+                        Some("art9") => format!("{} (donnée sensible, art. 9)", c.name),
+                        Some(_) => format!("{} (infractions, art. 10)", c.name),
+                        None => c.name.clone(),
+                    })),
+                }],
+            },
+            Section {
+                title: "Catégories de personnes concernées",
+                fields: vec![Field {
+                    label: "Personnes concernées",
+                    value: join_lines(entry.data_subjects.iter().map(|c| c.name.clone())),
+                }],
+            },
+            Section {
+                title: "Destinataires",
+                fields: vec![Field { label: "Destinataires", value: parties_with_role(entry, "recipient", parties) }],
+            },
+            Section {
+                title: "Transferts de données hors UE",
+                fields: vec![Field { label: "Destinataire — pays — garantie", value: transfers }],
+            },
+            Section {
+                title: "Durée de conservation",
+                fields: vec![
+                    Field {
+                        label: "Durée",
+                        value: entry.retention_period.clone().unwrap_or_else(|| "—".to_string()),
+                    },
+                    Field {
+                        label: "Durée maximale",
+                        value: entry.retention_months.map_or_else(|| "—".to_string(), |months| format!("{} mois", months)),
+                    },
+                    Field {
+                        label: "Point de départ",
+                        value: retention_choice(RETENTION_TRIGGERS, entry.retention_trigger.as_deref()),
+                    },
+                    Field {
+                        label: "Sort à l'échéance",
+                        value: retention_choice(RETENTION_ACTIONS, entry.retention_action.as_deref()),
+                    },
+                ],
+            },
+            Section {
+                title: "Mesures de sécurité",
+                fields: vec![Field { label: "Mesures techniques et organisationnelles", value: join_lines(measures) }],
+            },
+        ],
+    }
+}
+
+/// Les fiches sont numérotées par ordre de création.
+pub fn build(
+    entity: &entity::Model,
+    entries: &[RegisterEntry],
+    parties: &HashMap<Uuid, rgpd_party::Model>,
+    version: i32,
+    generated_at: DateTime<Utc>,
+    generated_by: String,
+) -> RegisterDocument {
+    // Le DPO et le représentant de l'organisme sont ceux désignés dans ses fiches
+    let mut dpos: Vec<String> = Vec::new();
+    let mut representatives: Vec<String> = Vec::new();
+    for party in entries.iter().flat_map(|e| &e.parties) {
+        let target = match party.role.as_str() {
+            "dpo" => &mut dpos,
+            "representative" => &mut representatives,
+            _ => continue,
+        };
+        let line = contact(parties.get(&party.party_id), &party.name);
+        if !target.contains(&line) {
+            target.push(line);
+        }
+    }
+
+    let header = vec![
+        Field { label: "Organisme", value: entity.name.clone() },
+        Field { label: "Description", value: entity.description.clone().unwrap_or_else(|| "—".to_string()) },
+        Field { label: "Délégué à la protection des données", value: join_lines(dpos) },
+        Field { label: "Représentant", value: join_lines(representatives) },
+        Field { label: "Nombre de traitements", value: entries.len().to_string() },
+        Field { label: "Version", value: version.to_string() },
+        Field { label: "Date de génération", value: format_date_time(generated_at) },
+        Field { label: "Généré par", value: generated_by.clone() },
+    ];
+
+    RegisterDocument {
+        organisation: entity.name.clone(),
+        version,
+        generated_at,
+        generated_by,
+        header,
+        records: entries.iter()
+            .enumerate()
+            .map(|(i, e)| record(i, e, &entity.name, parties))
+            .collect(),
+    }
+}
@@ -0,0 +1,199 @@
+//! Rendu JSON-LD du registre avec le vocabulaire W3C DPV, pour l'échange
+//! avec d'autres outils de conformité.
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+use crate::entities::rgpd::models::RegisterEntry;
+use crate::entities::rgpd::taxonomy::{self, Term, LEGAL_BASES, RETENTION_TRIGGERS, SPECIAL_CATEGORY_EXCEPTIONS};
+use crate::entities_orm::entity;
+
+fn node(id: Uuid) -> String {
+    format!("urn:uuid:{}", id)
+}
+
+// Article du RGPD dans le vocabulaire DPV : 6(1)(a) devient eu-gdpr:A6-1-a
+fn article(terms: &[Term], key: &str, details: Option<&str>) -> Value {
+    match terms.iter().find(|t| t.key == key) {
+        Some(term) => json!({
+            "@type": format!("eu-gdpr:A{}", term.article.replace('(', "-").replace(')', "")),
+            "dct:description": details,
+        }),
+        None => json!({ "@type": "dpv:LegalBasis", "dct:description": details.unwrap_or(key) }),
+    }
+}
+
+fn role(role: &str) -> (&'static str, &'static str) {
+    match role {
+        "controller" => ("dpv:hasDataController", "dpv:DataController"),
+        "joint_controller" => ("dpv:hasJointDataControllers", "dpv:JointDataControllers"),
+        "representative" => ("dpv:hasRepresentative", "dpv:Representative"),
+        "dpo" => ("dpv:hasDataProtectionOfficer", "dpv:DataProtectionOfficer"),
+        "processor" => ("dpv:hasDataProcessor", "dpv:DataProcessor"),
+        _ => ("dpv:hasRecipient", "dpv:Recipient"),
+    }
+}
+
+fn measure_type(category: &str) -> &'static str {
+    match category {
+        "access_control" => "dpv:AccessControlMethod",
+        "encryption" => "dpv:Encryption",
+        "pseudonymisation" => "dpv:Pseudonymisation",
+        "backup" => "dpv:DataBackupProtocols",
+        "logging" => "dpv:LoggingPolicies",
+        "network" => "dpv:NetworkSecurityProtocols",
+        "physical" => "dpv:PhysicalMeasure",
+        "organisational" => "dpv:OrganisationalMeasure",
+        _ => "dpv:TechnicalOrganisationalMeasure",
+    }
+}
+
+fn transfer_basis(safeguard: &str) -> &'static str {
+    match safeguard {
+        "adequacy" => "eu-gdpr:A45-3",
+        "scc" => "eu-gdpr:A46-2-c",
+        "bcr" => "eu-gdpr:A46-2-b",
+        "derogation" => "eu-gdpr:A49-1",
+        _ => "eu-gdpr:A46",
+    }
+}
+
+fn push(process: &mut Map<String, Value>, property: &str, value: Value) {
+    match process.get_mut(property) {
+        Some(Value::Array(values)) => values.push(value),
+        _ => {
+            process.insert(property.to_string(), Value::Array(vec![value]));
+        }
+    }
+}
+
+fn process(entry: &RegisterEntry, organisation: &Value) -> Value {
+    let mut process = Map::new();
+    process.insert("@id".to_string(), json!(node(entry.id)));
+    process.insert("@type".to_string(), json!("dpv:Process"));
+    process.insert("dct:title".to_string(), json!(entry.processing_name));
+    process.insert("dct:created".to_string(), json!(entry.created_at));
+    process.insert("dct:modified".to_string(), json!(entry.updated_at));
+    process.insert("dpv:hasPurpose".to_string(), json!({ "@type": "dpv:Purpose", "dct:description": entry.purpose }));
+
+    // L'organisme est responsable de ses traitements, sous-traitant sinon
+    if entry.record_type == "processor" {
+        push(&mut process, "dpv:hasDataProcessor", organisation.clone());
+    } else {
+        push(&mut process, "dpv:hasDataController", organisation.clone());
+    }
+    if let Some(basis) = entry.legal_basis.as_deref() {
+        push(&mut process, "dpv:hasLegalBasis", article(LEGAL_BASES, basis, entry.legal_basis_details.as_deref()));
+    }
+    if let Some(exception) = entry.special_category_exception.as_deref() {
+        push(&mut process, "dpv:hasLegalBasis", article(SPECIAL_CATEGORY_EXCEPTIONS, exception, None));
+    }
+    if let Some(basis) = entry.criminal_data_basis.as_deref() {
+        push(&mut process, "dpv:hasLegalBasis", json!({ "@type": "eu-gdpr:A10", "dct:description": basis }));
+    }
+    for category in &entry.data_categories {
+        let class = match category.special.as_deref() {
+            Some("art9") => json!(["dpv:PersonalData", "dpv:SpecialCategoryPersonalData"]),
+            Some(_) => json!(["dpv:PersonalData", "dpv:SensitivePersonalData"]),
+            None => json!("dpv:PersonalData"),
+        };
+        push(&mut process, "dpv:hasPersonalData", json!({
+            "@id": node(category.id),
+            "@type": class,
+            "dct:title": category.name,
+        }));
+    }
+    for subject in &entry.data_subjects {
+        push(&mut process, "dpv:hasDataSubject", json!({
+            "@id": node(subject.id),
+            "@type": "dpv:DataSubject",
+            "dct:title": subject.name,
+        }));
+    }
+    for party in &entry.parties {
+        let (property, class) = role(&party.role);
+        push(&mut process, property, json!({
+            "@id": node(party.party_id),
+            "@type": class,
+            "dpv:hasName": party.name,
+        }));
+    }
+    for transfer in &entry.transfers {
+        let mut recipient = json!({
+            "@type": "dpv:Recipient",
+            "dpv:hasCountry": { "@id": format!("loc:{}", transfer.country) },
+            "dpv:hasLegalBasis": {
+                "@type": transfer_basis(&transfer.safeguard),
+                "dct:description": transfer.safeguard_details,
+            },
+        });
+        if let Some(measures) = transfer.supplementary_measures.as_deref() {
+            recipient["dpv:hasTechnicalOrganisationalMeasure"] = json!({
+                "@type": "dpv:TechnicalOrganisationalMeasure",
+                "dct:description": measures,
+            });
+        }
+        if let Some(party_id) = transfer.party_id {
+            recipient["@id"] = json!(node(party_id));
+            recipient["dpv:hasName"] = json!(transfer.party_name);
+        }
+        push(&mut process, "dpv:hasRecipient", recipient);
+    }
+    for measure in &entry.measures {
+        push(&mut process, "dpv:hasTechnicalOrganisationalMeasure", json!({
+            "@id": node(measure.id),
+            "@type": measure_type(&measure.category),
+            "dct:title": measure.name,
+        }));
+    }
+    if let Some(text) = entry.security_measures.as_deref() {
+        push(&mut process, "dpv:hasTechnicalOrganisationalMeasure", json!({
+            "@type": "dpv:TechnicalOrganisationalMeasure",
+            "dct:description": text,
+        }));
+    }
+    if entry.retention_period.is_some() || entry.retention_months.is_some() || entry.retention_trigger.is_some() {
+        process.insert("dpv:hasStorageCondition".to_string(), json!({
+            "@type": "dpv:StorageDuration",
+            "dct:description": entry.retention_period,
+            "rdf:value": entry.retention_months.map(|months| format!("P{}M", months)),
+            "dct:temporal": entry.retention_trigger.as_deref().and_then(|key| taxonomy::choice_label(RETENTION_TRIGGERS, key)),
+            "dpv:hasProcessing": entry.retention_action.as_deref().map(|action| match action {
+                "delete" => "dpv:Erase",
+                "anonymize" => "dpv:Anonymise",
+                _ => "dpv:Store",
+            }),
+        }));
+    }
+    Value::Object(process)
+}
+
+pub fn render(
+    export_id: Uuid,
+    entity: &entity::Model,
+    entries: &[RegisterEntry],
+    version: i32,
+    generated_at: DateTime<Utc>,
+) -> Value {
+    let organisation = json!({
+        "@id": node(entity.id),
+        "@type": "dpv:Organisation",
+        "dpv:hasName": entity.name,
+    });
+    json!({
+        "@context": {
+            "dpv": "https://w3id.org/dpv#",
+            "eu-gdpr": "https://w3id.org/dpv/legal/eu/gdpr#",
+            "loc": "https://w3id.org/dpv/loc#",
+            "dct": "http://purl.org/dc/terms/",
+            "rdf": "http://www.w3.org/1999/02/22-rdf-syntax-ns#",
+            "owl": "http://www.w3.org/2002/07/owl#",
+        },
+        "@id": node(export_id),
+        "@type": "dpv:ROPA",
+        "dct:title": format!("{} — {}", super::document::TITLE, entity.name),
+        "dct:issued": generated_at,
+        "owl:versionInfo": version.to_string(),
+        "dpv:hasDataController": organisation,
+        "dct:hasPart": entries.iter().map(|e| process(e, &organisation)).collect::<Vec<_>>(),
+    })
+}
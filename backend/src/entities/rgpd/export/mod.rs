@@ -0,0 +1,170 @@
+//! Éditions du registre des traitements (PDF, traitement de texte, tableur,
+//! JSON-LD). Chaque
+//! export est daté, numéroté et son empreinte conservée dans
+//! `rgpd_register_exports`.
+pub mod document;
+pub mod jsonld;
+pub mod pdf;
+pub mod spreadsheet;
+pub mod text;
+
+use std::collections::{HashMap, HashSet};
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, Order, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use crate::entities::hierarchy::EntityTree;
+use crate::entities::rgpd::models::{RegisterExport, RegisterExportQuery, REGISTER_EXPORT_FORMATS};
+use crate::entities::rgpd::register;
+use crate::entities::scope::EntityScope;
+use crate::errors::ApiError;
+use crate::middleware::get_current_user_id;
+use crate::entities_orm::entity::Entity as EntityEntity;
+use crate::entities_orm::user::Entity as UserEntity;
+use crate::entities_orm::register_entry::{Entity as RegisterEntryEntity, Column as RegisterEntryColumn};
+use crate::entities_orm::rgpd_party::{Entity as PartyEntity, Column as PartyColumn};
+use crate::entities_orm::rgpd_register_export::{Entity as RegisterExportEntity, Column as RegisterExportColumn, ActiveModel as RegisterExportActiveModel};
+
+fn content_type(format: &str) -> &'static str {
+    match format {
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "ods" => "application/vnd.oasis.opendocument.spreadsheet",
+        "odt" => "application/vnd.oasis.opendocument.text",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "jsonld" => "application/ld+json",
+        _ => "application/pdf",
+    }
+}
+
+/// Génère une nouvelle édition du registre de l'entité, traitements partagés
+/// par ses groupes compris, et la renvoie en pièce jointe.
+pub async fn export_register(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+    query: web::Query<RegisterExportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let format = query.format.clone().unwrap_or_else(|| "pdf".to_string());
+    if !REGISTER_EXPORT_FORMATS.contains(&format.as_str()) {
+        return Err(ApiError::validation(
+            "format",
+            "one_of",
+            format!("Format must be one of: {}", REGISTER_EXPORT_FORMATS.join(", ")),
+        ));
+    }
+    let user_id = get_current_user_id(&req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+    let generated_by = UserEntity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await?
+        .map(|u| u.email)
+        .unwrap_or_else(|| user_id.to_string());
+    let entity = EntityEntity::find_by_id(scope.entity_id)
+        .one(db.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Entity not found".to_string()))?;
+
+    let ancestors = EntityTree::load(db.get_ref()).await?.ancestors(scope.entity_id);
+    let entries = RegisterEntryEntity::find()
+        .filter(
+            Condition::any()
+                .add(RegisterEntryColumn::EntityId.eq(scope.entity_id))
+                .add(
+                    Condition::all()
+                        .add(RegisterEntryColumn::EntityId.is_in(ancestors))
+                        .add(RegisterEntryColumn::SharedWithSubsidiaries.eq(true)),
+                ),
+        )
+        .order_by(RegisterEntryColumn::CreatedAt, Order::Asc)
+        .all(db.get_ref())
+        .await?;
+    let entries = register::load(db.get_ref(), entries).await?;
+
+    // Coordonnées complètes des tiers cités dans les fiches
+    let party_ids: HashSet<Uuid> = entries.iter()
+        .flat_map(|e| e.parties.iter().map(|p| p.party_id).chain(e.transfers.iter().filter_map(|t| t.party_id)))
+        .collect();
+    let parties: HashMap<_, _> = PartyEntity::find()
+        .filter(PartyColumn::Id.is_in(party_ids))
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(|p| (p.id, p))
+        .collect();
+
+    // Le verrou sur l'entité sérialise la numérotation des versions
+    let txn = db.begin().await?;
+    EntityEntity::find_by_id(scope.entity_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?;
+    let last: Option<i32> = RegisterExportEntity::find()
+        .select_only()
+        .column_as(RegisterExportColumn::Version.max(), "version")
+        .filter(RegisterExportColumn::EntityId.eq(scope.entity_id))
+        .into_tuple()
+        .one(&txn)
+        .await?
+        .flatten();
+    let version = last.unwrap_or(0) + 1;
+    let export_id = Uuid::new_v4();
+    let generated_at = Utc::now();
+
+    let bytes = match format.as_str() {
+        "jsonld" => serde_json::to_vec_pretty(&jsonld::render(export_id, &entity, &entries, version, generated_at))
+            .map_err(ApiError::internal("Failed to serialize register"))?,
+        _ => {
+            let doc = document::build(&entity, &entries, &parties, version, generated_at, generated_by);
+            let format = format.clone();
+            // Mise en page hors de la boucle d'événements
+            web::block(move || match format.as_str() {
+                "xlsx" => spreadsheet::render_xlsx(&doc).map_err(|e| e.to_string()),
+                "ods" => spreadsheet::render_ods(&doc).map_err(|e| e.to_string()),
+                "odt" => text::render_odt(&doc).map_err(|e| e.to_string()),
+                "docx" => text::render_docx(&doc).map_err(|e| e.to_string()),
+                _ => Ok(pdf::render(&doc)),
+            })
+            .await
+            .map_err(ApiError::internal("Export task failed"))?
+            .map_err(ApiError::internal("Failed to render register"))?
+        }
+    };
+
+    RegisterExportActiveModel {
+        id: Set(export_id),
+        entity_id: Set(scope.entity_id),
+        version: Set(version),
+        format: Set(format.clone()),
+        entry_count: Set(entries.len() as i32),
+        sha256: Set(Sha256::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect()),
+        generated_by: Set(Some(user_id)),
+        generated_at: Set(generated_at),
+    }
+    .insert(&txn)
+    .await?;
+    txn.commit().await?;
+
+    let file_name = format!("registre-{}-v{}-{}.{}", entity.id, version, generated_at.format("%Y%m%d"), format);
+    Ok(HttpResponse::Ok()
+        .content_type(content_type(&format))
+        .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file_name)))
+        .body(bytes))
+}
+
+/// Historique des éditions du registre, la plus récente en premier.
+pub async fn list_register_exports(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    let exports: Vec<RegisterExport> = RegisterExportEntity::find()
+        .filter(RegisterExportColumn::EntityId.eq(scope.entity_id))
+        .order_by(RegisterExportColumn::Version, Order::Desc)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(RegisterExport::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(exports))
+}
@@ -0,0 +1,379 @@
+//! Rendu PDF du registre et des courriers, sans dépendance système : polices
+//! standard Helvetica en WinAnsiEncoding, mise en page A4 calculée ici.
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use pdf_writer::{Content, Date, Name, Pdf, Rect, Ref, Str, TextStr};
+use super::document::{self, Field, RegisterDocument};
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+const BOTTOM: f32 = 70.0;
+const LABEL_WIDTH: f32 = 170.0;
+const GAP: f32 = 10.0;
+
+const REGULAR: Name = Name(b"F1");
+const BOLD: Name = Name(b"F2");
+
+// Chasses Helvetica / Helvetica-Bold (AFM Adobe) des caractères 32 à 126
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+// Code WinAnsi d'un caractère ; les caractères hors de ce jeu deviennent « ? »
+fn win_ansi(c: char) -> u8 {
+    match c {
+        ' '..='~' | '\u{A0}'..='\u{FF}' => c as u8,
+        '€' => 0x80,
+        '…' => 0x85,
+        'Œ' => 0x8C,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        'œ' => 0x9C,
+        '\t' => b' ',
+        _ => b'?',
+    }
+}
+
+fn char_width(c: char, bold: bool, size: f32) -> f32 {
+    let units = match c {
+        ' '..='~' => {
+            let table = if bold { &HELVETICA_BOLD } else { &HELVETICA };
+            table[c as usize - 32]
+        }
+        '—' | 'Œ' | 'œ' => 1000,
+        _ => 600,
+    };
+    units as f32 * size / 1000.0
+}
+
+fn text_width(text: &str, bold: bool, size: f32) -> f32 {
+    text.chars().map(|c| char_width(c, bold, size)).sum()
+}
+
+// Découpe en lignes tenant dans la largeur ; un mot trop long est coupé
+fn wrap(text: &str, bold: bool, size: f32, width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+            if text_width(&candidate, bold, size) <= width {
+                line = candidate;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            for c in word.chars() {
+                if !line.is_empty() && text_width(&line, bold, size) + char_width(c, bold, size) > width {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.push(c);
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+enum Op {
+    Text { bold: bool, size: f32, x: f32, y: f32, text: String },
+    Rule { y: f32 },
+}
+
+struct Layout {
+    pages: Vec<Vec<Op>>,
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Self { pages: vec![Vec::new()], y: PAGE_HEIGHT - MARGIN }
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Vec::new());
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn ensure(&mut self, height: f32) {
+        if self.y - height < BOTTOM {
+            self.new_page();
+        }
+    }
+
+    fn push(&mut self, op: Op) {
+        self.pages.last_mut().expect("layout has a page").push(op);
+    }
+
+    fn line(&mut self, bold: bool, size: f32, x: f32, text: &str) {
+        self.ensure(size * 1.4);
+        self.y -= size * 1.4;
+        let y = self.y;
+        self.push(Op::Text { bold, size, x, y, text: text.to_string() });
+    }
+
+    fn paragraph(&mut self, bold: bool, size: f32, text: &str) {
+        for line in wrap(text, bold, size, PAGE_WIDTH - 2.0 * MARGIN) {
+            self.line(bold, size, MARGIN, &line);
+        }
+    }
+
+    fn rule(&mut self) {
+        self.y -= 4.0;
+        let y = self.y;
+        self.push(Op::Rule { y });
+    }
+
+    fn space(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    // Libellé en gras à gauche, valeur à droite ; une ligne trop longue pour
+    // la page continue sur la suivante
+    fn field(&mut self, field: &Field) {
+        let size = 9.0;
+        let leading = size * 1.4;
+        let value_x = MARGIN + LABEL_WIDTH + GAP;
+        let labels = wrap(field.label, true, size, LABEL_WIDTH);
+        let values = wrap(&field.value, false, size, PAGE_WIDTH - MARGIN - value_x);
+        for i in 0..labels.len().max(values.len()) {
+            self.ensure(leading);
+            self.y -= leading;
+            let y = self.y;
+            if let Some(label) = labels.get(i) {
+                self.push(Op::Text { bold: true, size, x: MARGIN, y, text: label.clone() });
+            }
+            if let Some(value) = values.get(i) {
+                self.push(Op::Text { bold: false, size, x: value_x, y, text: value.clone() });
+            }
+        }
+        self.space(3.0);
+    }
+}
+
+fn layout(doc: &RegisterDocument) -> Vec<Vec<Op>> {
+    let mut layout = Layout::new();
+    layout.paragraph(true, 18.0, document::TITLE);
+    layout.paragraph(false, 11.0, &doc.organisation);
+    layout.paragraph(false, 9.0, &doc.edition());
+    layout.rule();
+    layout.space(6.0);
+
+    layout.paragraph(true, 13.0, "Organisme");
+    layout.space(4.0);
+    for field in &doc.header {
+        layout.field(field);
+    }
+
+    layout.space(10.0);
+    layout.paragraph(true, 13.0, "Liste des activités de traitement");
+    layout.space(4.0);
+    if doc.records.is_empty() {
+        layout.paragraph(false, 9.0, "Aucun traitement n'est inscrit au registre.");
+    }
+    for record in &doc.records {
+        layout.paragraph(false, 9.0, &format!("{} — {} — {} — mis à jour le {}",
+            record.reference,
+            record.name,
+            record.record_type,
+            document::format_date(record.updated_at)));
+    }
+
+    for record in &doc.records {
+        layout.new_page();
+        layout.paragraph(true, 14.0, &format!("Fiche {} — {}", record.reference, record.name));
+        layout.rule();
+        for section in &record.sections {
+            layout.ensure(40.0);
+            layout.space(8.0);
+            layout.paragraph(true, 11.0, section.title);
+            layout.space(2.0);
+            for field in &section.fields {
+                layout.field(field);
+            }
+        }
+    }
+    layout.pages
+}
+
+/// Courrier ou formulaire rédigé en texte libre : une ligne commençant par
+/// « # » ouvre une rubrique, une ligne vide sépare les paragraphes.
+pub struct Letter {
+    pub title: String,
+    pub organisation: String,
+    pub footer: String, // Mention de génération, dans la langue du courrier
+    pub generated_at: DateTime<Utc>,
+    pub generated_by: String,
+    pub body: String,
+}
+
+fn layout_letter(letter: &Letter) -> Vec<Vec<Op>> {
+    let mut layout = Layout::new();
+    layout.paragraph(true, 16.0, &letter.title);
+    layout.paragraph(false, 11.0, &letter.organisation);
+    layout.rule();
+    layout.space(6.0);
+    for line in letter.body.lines() {
+        match line.strip_prefix('#') {
+            Some(heading) => {
+                layout.ensure(40.0);
+                layout.space(8.0);
+                layout.paragraph(true, 11.0, heading.trim());
+                layout.space(2.0);
+            }
+            None if line.trim().is_empty() => layout.space(6.0),
+            None => layout.paragraph(false, 10.0, line),
+        }
+    }
+    layout.pages
+}
+
+fn encode(text: &str) -> Vec<u8> {
+    text.chars().map(win_ansi).collect()
+}
+
+// Métadonnées du document et pied de page
+struct Info<'a> {
+    title: String,
+    author: &'a str,
+    subject: String,
+    footer: String,
+    created_at: DateTime<Utc>,
+}
+
+pub fn render(doc: &RegisterDocument) -> Vec<u8> {
+    write(layout(doc), Info {
+        title: format!("{} — {}", document::TITLE, doc.organisation),
+        author: &doc.generated_by,
+        subject: doc.edition(),
+        footer: format!("{} — {}", doc.organisation, doc.edition()),
+        created_at: doc.generated_at,
+    })
+}
+
+pub fn render_letter(letter: &Letter) -> Vec<u8> {
+    write(layout_letter(letter), Info {
+        title: format!("{} — {}", letter.title, letter.organisation),
+        author: &letter.generated_by,
+        subject: letter.footer.clone(),
+        footer: format!("{} — {}", letter.organisation, letter.footer),
+        created_at: letter.generated_at,
+    })
+}
+
+fn write(pages: Vec<Vec<Op>>, info: Info) -> Vec<u8> {
+    let count = pages.len();
+
+    let mut next = 1;
+    let mut alloc = || {
+        let id = Ref::new(next);
+        next += 1;
+        id
+    };
+    let catalog_id = alloc();
+    let tree_id = alloc();
+    let regular_id = alloc();
+    let bold_id = alloc();
+    let info_id = alloc();
+    let page_ids: Vec<(Ref, Ref)> = (0..count).map(|_| (alloc(), alloc())).collect();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(tree_id);
+    pdf.pages(tree_id)
+        .kids(page_ids.iter().map(|(page, _)| *page))
+        .count(count as i32);
+    pdf.type1_font(regular_id)
+        .base_font(Name(b"Helvetica"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.type1_font(bold_id)
+        .base_font(Name(b"Helvetica-Bold"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+
+    let at = info.created_at;
+    pdf.document_info(info_id)
+        .title(TextStr(&info.title))
+        .author(TextStr(info.author))
+        .subject(TextStr(&info.subject))
+        .creator(TextStr("HyperCyber"))
+        .creation_date(Date::new(at.year() as u16)
+            .month(at.month() as u8)
+            .day(at.day() as u8)
+            .hour(at.hour() as u8)
+            .minute(at.minute() as u8)
+            .second(at.second() as u8));
+
+    let footer = info.footer;
+    for (index, (ops, (page_id, content_id))) in pages.into_iter().zip(&page_ids).enumerate() {
+        let mut content = Content::new();
+        for op in ops {
+            match op {
+                Op::Text { bold, size, x, y, text } => {
+                    content.begin_text()
+                        .set_font(if bold { BOLD } else { REGULAR }, size)
+                        .next_line(x, y)
+                        .show(Str(&encode(&text)))
+                        .end_text();
+                }
+                Op::Rule { y } => {
+                    content.set_line_width(0.5)
+                        .move_to(MARGIN, y)
+                        .line_to(PAGE_WIDTH - MARGIN, y)
+                        .stroke();
+                }
+            }
+        }
+
+        // Pied de page : organisme, version et pagination
+        let page_label = format!("Page {}/{}", index + 1, count);
+        let footer = wrap(&footer, false, 8.0, PAGE_WIDTH - 2.0 * MARGIN - 60.0)
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        content.set_line_width(0.5)
+            .move_to(MARGIN, BOTTOM - 20.0)
+            .line_to(PAGE_WIDTH - MARGIN, BOTTOM - 20.0)
+            .stroke();
+        content.begin_text()
+            .set_font(REGULAR, 8.0)
+            .next_line(MARGIN, BOTTOM - 32.0)
+            .show(Str(&encode(&footer)))
+            .end_text();
+        content.begin_text()
+            .set_font(REGULAR, 8.0)
+            .next_line(PAGE_WIDTH - MARGIN - text_width(&page_label, false, 8.0), BOTTOM - 32.0)
+            .show(Str(&encode(&page_label)))
+            .end_text();
+
+        let mut page = pdf.page(*page_id);
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+            .parent(tree_id)
+            .contents(*content_id);
+        page.resources()
+            .fonts()
+            .pair(REGULAR, regular_id)
+            .pair(BOLD, bold_id);
+        drop(page);
+        pdf.stream(*content_id, &content.finish());
+    }
+
+    pdf.finish()
+}
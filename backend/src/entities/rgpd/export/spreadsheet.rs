@@ -0,0 +1,192 @@
+//! Rendu tableur du registre (XLSX et ODS), feuille par feuille selon le
+//! modèle de la CNIL : « Organisme », « Activités » puis une feuille par fiche.
+use std::io::{Cursor, Write};
+use rust_xlsxwriter::{DocProperties, Format, FormatAlign, Workbook, XlsxError};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+use super::document::{self, RegisterDocument};
+
+struct Cell {
+    text: String,
+    bold: bool,
+}
+
+struct Sheet {
+    name: String,
+    widths: Vec<f64>,
+    rows: Vec<Vec<Cell>>,
+}
+
+fn bold(text: impl Into<String>) -> Cell {
+    Cell { text: text.into(), bold: true }
+}
+
+fn plain(text: impl Into<String>) -> Cell {
+    Cell { text: text.into(), bold: false }
+}
+
+fn sheets(doc: &RegisterDocument) -> Vec<Sheet> {
+    let mut organisation = vec![
+        vec![bold(document::TITLE)],
+        vec![plain(doc.edition())],
+        vec![],
+    ];
+    organisation.extend(doc.header.iter().map(|f| vec![bold(f.label), plain(f.value.clone())]));
+
+    let mut activities = vec![vec![
+        bold("Référence"),
+        bold("Nom du traitement"),
+        bold("Registre"),
+        bold("Date de création"),
+        bold("Dernière mise à jour"),
+    ]];
+    activities.extend(doc.records.iter().map(|r| vec![
+        plain(r.reference.clone()),
+        plain(r.name.clone()),
+        plain(r.record_type),
+        plain(document::format_date(r.created_at)),
+        plain(document::format_date(r.updated_at)),
+    ]));
+
+    let mut sheets = vec![
+        Sheet { name: "Organisme".to_string(), widths: vec![40.0, 80.0], rows: organisation },
+        Sheet { name: "Activités".to_string(), widths: vec![12.0, 50.0, 36.0, 18.0, 20.0], rows: activities },
+    ];
+    for record in &doc.records {
+        let mut rows = vec![vec![bold(format!("{} — {}", record.reference, record.name))]];
+        for section in &record.sections {
+            rows.push(vec![]);
+            rows.push(vec![bold(section.title)]);
+            rows.extend(section.fields.iter().map(|f| vec![plain(f.label), plain(f.value.clone())]));
+        }
+        sheets.push(Sheet { name: format!("Fiche {}", record.reference), widths: vec![40.0, 80.0], rows });
+    }
+    sheets
+}
+
+pub fn render_xlsx(doc: &RegisterDocument) -> Result<Vec<u8>, XlsxError> {
+    let mut workbook = Workbook::new();
+    workbook.set_properties(&DocProperties::new()
+        .set_title(format!("{} — {}", document::TITLE, doc.organisation))
+        .set_subject(doc.edition())
+        .set_author(doc.generated_by.clone()));
+
+    let regular = Format::new().set_text_wrap().set_align(FormatAlign::Top);
+    let strong = regular.clone().set_bold();
+    for sheet in sheets(doc) {
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(&sheet.name)?;
+        for (col, width) in sheet.widths.iter().enumerate() {
+            worksheet.set_column_width(col as u16, *width)?;
+        }
+        for (row, cells) in sheet.rows.iter().enumerate() {
+            for (col, cell) in cells.iter().enumerate() {
+                let format = if cell.bold { &strong } else { &regular };
+                worksheet.write_string_with_format(row as u32, col as u16, &cell.text, format)?;
+            }
+        }
+    }
+    workbook.save_to_buffer()
+}
+
+pub(super) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const ODS_MIMETYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+const ODS_MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+ <manifest:file-entry manifest:full-path="/" manifest:version="1.2" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/>
+ <manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+ <manifest:file-entry manifest:full-path="meta.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>
+"#;
+
+fn ods_meta(doc: &RegisterDocument) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-meta xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:meta="urn:oasis:names:tc:opendocument:xmlns:meta:1.0" xmlns:dc="http://purl.org/dc/elements/1.1/" office:version="1.2">
+ <office:meta>
+  <dc:title>{}</dc:title>
+  <dc:subject>{}</dc:subject>
+  <meta:initial-creator>{}</meta:initial-creator>
+  <meta:creation-date>{}</meta:creation-date>
+  <meta:generator>HyperCyber</meta:generator>
+ </office:meta>
+</office:document-meta>
+"#,
+        escape(&format!("{} — {}", document::TITLE, doc.organisation)),
+        escape(&doc.edition()),
+        escape(&doc.generated_by),
+        doc.generated_at.format("%Y-%m-%dT%H:%M:%S"),
+    )
+}
+
+fn ods_content(sheets: &[Sheet]) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" office:version="1.2">
+ <office:automatic-styles>
+  <style:style style:name="ce1" style:family="table-cell"><style:table-cell-properties fo:wrap-option="wrap" style:vertical-align="top"/></style:style>
+  <style:style style:name="ce2" style:family="table-cell"><style:table-cell-properties fo:wrap-option="wrap" style:vertical-align="top"/><style:text-properties fo:font-weight="bold"/></style:style>
+"#);
+    // Un style de colonne par largeur utilisée (largeurs XLSX en caractères, ~0,2 cm)
+    let mut widths: Vec<f64> = sheets.iter().flat_map(|s| s.widths.iter().copied()).collect();
+    widths.sort_by(f64::total_cmp);
+    widths.dedup();
+    for (i, width) in widths.iter().enumerate() {
+        xml.push_str(&format!(
+            "  <style:style style:name=\"co{}\" style:family=\"table-column\"><style:table-column-properties style:column-width=\"{:.2}cm\"/></style:style>\n",
+            i + 1,
+            width * 0.2,
+        ));
+    }
+    xml.push_str(" </office:automatic-styles>\n <office:body>\n  <office:spreadsheet>\n");
+
+    for sheet in sheets {
+        xml.push_str(&format!("   <table:table table:name=\"{}\">\n", escape(&sheet.name)));
+        for width in &sheet.widths {
+            let style = widths.iter().position(|w| w == width).unwrap_or_default() + 1;
+            xml.push_str(&format!("    <table:table-column table:style-name=\"co{}\"/>\n", style));
+        }
+        for row in &sheet.rows {
+            xml.push_str("    <table:table-row>");
+            if row.is_empty() {
+                xml.push_str("<table:table-cell/>");
+            }
+            for cell in row {
+                xml.push_str(&format!(
+                    "<table:table-cell table:style-name=\"{}\" office:value-type=\"string\">",
+                    if cell.bold { "ce2" } else { "ce1" },
+                ));
+                for line in cell.text.split('\n') {
+                    xml.push_str(&format!("<text:p>{}</text:p>", escape(line)));
+                }
+                xml.push_str("</table:table-cell>");
+            }
+            xml.push_str("</table:table-row>\n");
+        }
+        xml.push_str("   </table:table>\n");
+    }
+    xml.push_str("  </office:spreadsheet>\n </office:body>\n</office:document-content>\n");
+    xml
+}
+
+// Le type MIME est la première entrée de l'archive, non compressée
+pub fn render_ods(doc: &RegisterDocument) -> zip::result::ZipResult<Vec<u8>> {
+    let sheets = sheets(doc);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file("mimetype", SimpleFileOptions::default().compression_method(CompressionMethod::Stored))?;
+    zip.write_all(ODS_MIMETYPE.as_bytes())?;
+
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file("content.xml", options)?;
+    zip.write_all(ods_content(&sheets).as_bytes())?;
+    zip.start_file("meta.xml", options)?;
+    zip.write_all(ods_meta(doc).as_bytes())?;
+    zip.start_file("META-INF/manifest.xml", options)?;
+    zip.write_all(ODS_MANIFEST.as_bytes())?;
+    Ok(zip.finish()?.into_inner())
+}
@@ -0,0 +1,442 @@
+//! Rendu traitement de texte du registre (ODT et DOCX), dans l'ordre du
+//! modèle de la CNIL : « Organisme », liste des activités, puis une fiche par
+//! traitement, chacune sur une nouvelle page. L'édition figure en pied de page.
+use std::io::{Cursor, Write};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+use super::document::{self, RegisterDocument};
+use super::spreadsheet::escape;
+
+// Largeur utile d'une page A4 avec des marges de 2 cm
+const TEXT_WIDTH_CM: f64 = 17.0;
+const TWIPS_PER_CM: f64 = 567.0;
+
+struct Cell {
+    text: String,
+    bold: bool,
+}
+
+enum Block {
+    Title(String),
+    // Titre de niveau 2 ; `page_break` ouvre une nouvelle page
+    Heading { text: String, page_break: bool },
+    Subheading(String),
+    Paragraph(String),
+    // Largeurs relatives des colonnes
+    Table { widths: Vec<f64>, rows: Vec<Vec<Cell>> },
+}
+
+fn bold(text: impl Into<String>) -> Cell {
+    Cell { text: text.into(), bold: true }
+}
+
+fn plain(text: impl Into<String>) -> Cell {
+    Cell { text: text.into(), bold: false }
+}
+
+fn blocks(doc: &RegisterDocument) -> Vec<Block> {
+    let mut blocks = vec![
+        Block::Title(document::TITLE.to_string()),
+        Block::Paragraph(doc.edition()),
+        Block::Heading { text: "Organisme".to_string(), page_break: false },
+        Block::Table {
+            widths: vec![1.0, 2.0],
+            rows: doc.header.iter().map(|f| vec![bold(f.label), plain(f.value.clone())]).collect(),
+        },
+        Block::Heading { text: "Activités".to_string(), page_break: true },
+    ];
+
+    let mut activities = vec![vec![
+        bold("Référence"),
+        bold("Nom du traitement"),
+        bold("Registre"),
+        bold("Date de création"),
+        bold("Dernière mise à jour"),
+    ]];
+    activities.extend(doc.records.iter().map(|r| vec![
+        plain(r.reference.clone()),
+        plain(r.name.clone()),
+        plain(r.record_type),
+        plain(document::format_date(r.created_at)),
+        plain(document::format_date(r.updated_at)),
+    ]));
+    blocks.push(Block::Table { widths: vec![1.2, 4.0, 3.4, 1.8, 1.8], rows: activities });
+
+    for record in &doc.records {
+        blocks.push(Block::Heading { text: format!("{} — {}", record.reference, record.name), page_break: true });
+        for section in &record.sections {
+            blocks.push(Block::Subheading(section.title.to_string()));
+            blocks.push(Block::Table {
+                widths: vec![1.0, 2.0],
+                rows: section.fields.iter().map(|f| vec![bold(f.label), plain(f.value.clone())]).collect(),
+            });
+        }
+    }
+    blocks
+}
+
+fn column_widths(widths: &[f64], total: f64) -> Vec<f64> {
+    let sum: f64 = widths.iter().sum();
+    widths.iter().map(|w| w / sum * total).collect()
+}
+
+fn document_title(doc: &RegisterDocument) -> String {
+    format!("{} — {}", document::TITLE, doc.organisation)
+}
+
+fn footer(doc: &RegisterDocument) -> String {
+    format!("{} — {}", doc.organisation, doc.edition())
+}
+
+const ODT_MIMETYPE: &str = "application/vnd.oasis.opendocument.text";
+
+const ODT_MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+ <manifest:file-entry manifest:full-path="/" manifest:version="1.2" manifest:media-type="application/vnd.oasis.opendocument.text"/>
+ <manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+ <manifest:file-entry manifest:full-path="styles.xml" manifest:media-type="text/xml"/>
+ <manifest:file-entry manifest:full-path="meta.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>
+"#;
+
+const ODF_NAMESPACES: &str = r#"xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" office:version="1.2""#;
+
+fn odt_meta(doc: &RegisterDocument) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-meta xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:meta="urn:oasis:names:tc:opendocument:xmlns:meta:1.0" xmlns:dc="http://purl.org/dc/elements/1.1/" office:version="1.2">
+ <office:meta>
+  <dc:title>{}</dc:title>
+  <dc:subject>{}</dc:subject>
+  <meta:initial-creator>{}</meta:initial-creator>
+  <meta:creation-date>{}</meta:creation-date>
+  <meta:generator>HyperCyber</meta:generator>
+ </office:meta>
+</office:document-meta>
+"#,
+        escape(&document_title(doc)),
+        escape(&doc.edition()),
+        escape(&doc.generated_by),
+        doc.generated_at.format("%Y-%m-%dT%H:%M:%S"),
+    )
+}
+
+// Styles nommés, format de page A4 et pied de page
+fn odt_styles(doc: &RegisterDocument) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-styles {ns}>
+ <office:styles>
+  <style:default-style style:family="paragraph"><style:text-properties style:font-name="Liberation Sans" fo:font-family="'Liberation Sans'" fo:font-size="10pt"/></style:default-style>
+  <style:style style:name="Title" style:family="paragraph"><style:paragraph-properties fo:margin-bottom="0.2cm"/><style:text-properties fo:font-size="18pt" fo:font-weight="bold"/></style:style>
+  <style:style style:name="Heading_20_2" style:display-name="Heading 2" style:family="paragraph" style:default-outline-level="2"><style:paragraph-properties fo:margin-top="0.4cm" fo:margin-bottom="0.2cm"/><style:text-properties fo:font-size="14pt" fo:font-weight="bold"/></style:style>
+  <style:style style:name="Heading_20_3" style:display-name="Heading 3" style:family="paragraph" style:default-outline-level="3"><style:paragraph-properties fo:margin-top="0.3cm" fo:margin-bottom="0.1cm"/><style:text-properties fo:font-size="11pt" fo:font-weight="bold"/></style:style>
+  <style:style style:name="Footer" style:family="paragraph"><style:text-properties fo:font-size="8pt"/></style:style>
+ </office:styles>
+ <office:automatic-styles>
+  <style:page-layout style:name="pm1"><style:page-layout-properties fo:page-width="21cm" fo:page-height="29.7cm" style:print-orientation="portrait" fo:margin-top="2cm" fo:margin-bottom="1.5cm" fo:margin-left="2cm" fo:margin-right="2cm"/></style:page-layout>
+ </office:automatic-styles>
+ <office:master-styles>
+  <style:master-page style:name="Standard" style:page-layout-name="pm1">
+   <style:footer><text:p text:style-name="Footer">{footer}</text:p></style:footer>
+  </style:master-page>
+ </office:master-styles>
+</office:document-styles>
+"#,
+        ns = ODF_NAMESPACES,
+        footer = escape(&footer(doc)),
+    )
+}
+
+fn odt_content(blocks: &[Block]) -> String {
+    let mut xml = format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content {}>
+ <office:automatic-styles>
+  <style:style style:name="P1" style:family="paragraph" style:parent-style-name="Heading_20_2"><style:paragraph-properties fo:break-before="page"/></style:style>
+  <style:style style:name="T1" style:family="table-cell"><style:table-cell-properties fo:padding="0.1cm" fo:border="0.5pt solid #808080"/></style:style>
+  <style:style style:name="P2" style:family="paragraph"><style:text-properties fo:font-weight="bold"/></style:style>
+"#, ODF_NAMESPACES);
+
+    // Un style de table et de colonnes par tableau
+    let tables = blocks.iter().filter_map(|b| match b {
+        Block::Table { widths, .. } => Some(widths),
+        _ => None,
+    });
+    for (t, widths) in tables.enumerate() {
+        xml.push_str(&format!(
+            "  <style:style style:name=\"Tab{}\" style:family=\"table\"><style:table-properties style:width=\"{:.2}cm\" table:align=\"left\"/></style:style>\n",
+            t + 1,
+            TEXT_WIDTH_CM,
+        ));
+        for (c, width) in column_widths(widths, TEXT_WIDTH_CM).iter().enumerate() {
+            xml.push_str(&format!(
+                "  <style:style style:name=\"Tab{}.C{}\" style:family=\"table-column\"><style:table-column-properties style:column-width=\"{:.2}cm\"/></style:style>\n",
+                t + 1,
+                c + 1,
+                width,
+            ));
+        }
+    }
+    xml.push_str(" </office:automatic-styles>\n <office:body>\n  <office:text>\n");
+
+    let mut table_index = 0;
+    for block in blocks {
+        match block {
+            Block::Title(text) => {
+                xml.push_str(&format!("   <text:p text:style-name=\"Title\">{}</text:p>\n", escape(text)));
+            }
+            Block::Heading { text, page_break } => {
+                xml.push_str(&format!(
+                    "   <text:h text:style-name=\"{}\" text:outline-level=\"2\">{}</text:h>\n",
+                    if *page_break { "P1" } else { "Heading_20_2" },
+                    escape(text),
+                ));
+            }
+            Block::Subheading(text) => {
+                xml.push_str(&format!("   <text:h text:style-name=\"Heading_20_3\" text:outline-level=\"3\">{}</text:h>\n", escape(text)));
+            }
+            Block::Paragraph(text) => {
+                xml.push_str(&format!("   <text:p>{}</text:p>\n", escape(text)));
+            }
+            Block::Table { widths, rows } => {
+                table_index += 1;
+                xml.push_str(&format!("   <table:table table:name=\"Tableau{0}\" table:style-name=\"Tab{0}\">\n", table_index));
+                for c in 0..widths.len() {
+                    xml.push_str(&format!("    <table:table-column table:style-name=\"Tab{}.C{}\"/>\n", table_index, c + 1));
+                }
+                for row in rows {
+                    xml.push_str("    <table:table-row>");
+                    for c in 0..widths.len() {
+                        xml.push_str("<table:table-cell table:style-name=\"T1\" office:value-type=\"string\">");
+                        let cell = row.get(c);
+                        let style = if cell.is_some_and(|c| c.bold) { " text:style-name=\"P2\"" } else { "" };
+                        for line in cell.map_or("", |c| c.text.as_str()).split('\n') {
+                            xml.push_str(&format!("<text:p{}>{}</text:p>", style, escape(line)));
+                        }
+                        xml.push_str("</table:table-cell>");
+                    }
+                    xml.push_str("</table:table-row>\n");
+                }
+                xml.push_str("   </table:table>\n");
+            }
+        }
+    }
+    xml.push_str("  </office:text>\n </office:body>\n</office:document-content>\n");
+    xml
+}
+
+// Le type MIME est la première entrée de l'archive, non compressée
+pub fn render_odt(doc: &RegisterDocument) -> zip::result::ZipResult<Vec<u8>> {
+    let blocks = blocks(doc);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file("mimetype", SimpleFileOptions::default().compression_method(CompressionMethod::Stored))?;
+    zip.write_all(ODT_MIMETYPE.as_bytes())?;
+
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file("content.xml", options)?;
+    zip.write_all(odt_content(&blocks).as_bytes())?;
+    zip.start_file("styles.xml", options)?;
+    zip.write_all(odt_styles(doc).as_bytes())?;
+    zip.start_file("meta.xml", options)?;
+    zip.write_all(odt_meta(doc).as_bytes())?;
+    zip.start_file("META-INF/manifest.xml", options)?;
+    zip.write_all(ODT_MANIFEST.as_bytes())?;
+    Ok(zip.finish()?.into_inner())
+}
+
+const DOCX_CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+ <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+ <Default Extension="xml" ContentType="application/xml"/>
+ <Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/>
+ <Override PartName="/word/footer1.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.footer+xml"/>
+ <Override PartName="/docProps/core.xml" ContentType="application/vnd.openxmlformats-package.core-properties+xml"/>
+</Types>
+"#;
+
+const DOCX_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+ <Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/>
+ <Relationship Id="rId2" Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties" Target="docProps/core.xml"/>
+</Relationships>
+"#;
+
+const DOCX_DOCUMENT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+ <Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/footer" Target="footer1.xml"/>
+</Relationships>
+"#;
+
+const WORDML_NAMESPACES: &str = r#"xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships""#;
+
+fn docx_core(doc: &RegisterDocument) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+ <dc:title>{}</dc:title>
+ <dc:subject>{}</dc:subject>
+ <dc:creator>{}</dc:creator>
+ <dcterms:created xsi:type="dcterms:W3CDTF">{}</dcterms:created>
+</cp:coreProperties>
+"#,
+        escape(&document_title(doc)),
+        escape(&doc.edition()),
+        escape(&doc.generated_by),
+        doc.generated_at.format("%Y-%m-%dT%H:%M:%SZ"),
+    )
+}
+
+// Paragraphe en mise en forme directe (pas de feuille de styles dans l'archive)
+fn docx_paragraph(text: &str, size_pt: u32, bold: bool, page_break: bool) -> String {
+    let mut properties = String::from("<w:pPr>");
+    if page_break {
+        properties.push_str("<w:pageBreakBefore/>");
+    }
+    properties.push_str("<w:spacing w:after=\"80\"/></w:pPr>");
+    let run_properties = format!("<w:rPr>{}<w:sz w:val=\"{}\"/></w:rPr>", if bold { "<w:b/>" } else { "" }, size_pt * 2);
+    let runs: Vec<String> = text.split('\n')
+        .map(|line| format!("<w:t xml:space=\"preserve\">{}</w:t>", escape(line)))
+        .collect();
+    format!("<w:p>{}<w:r>{}{}</w:r></w:p>", properties, run_properties, runs.join("<w:br/>"))
+}
+
+fn docx_document(blocks: &[Block]) -> String {
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<w:document {}>\n<w:body>\n",
+        WORDML_NAMESPACES,
+    );
+    let text_width = TEXT_WIDTH_CM * TWIPS_PER_CM;
+    for block in blocks {
+        match block {
+            Block::Title(text) => xml.push_str(&docx_paragraph(text, 18, true, false)),
+            Block::Heading { text, page_break } => xml.push_str(&docx_paragraph(text, 14, true, *page_break)),
+            Block::Subheading(text) => xml.push_str(&docx_paragraph(text, 11, true, false)),
+            Block::Paragraph(text) => xml.push_str(&docx_paragraph(text, 10, false, false)),
+            Block::Table { widths, rows } => {
+                let widths: Vec<u32> = column_widths(widths, text_width).iter().map(|w| w.round() as u32).collect();
+                xml.push_str(&format!(
+                    "<w:tbl><w:tblPr><w:tblW w:w=\"{}\" w:type=\"dxa\"/><w:tblLayout w:type=\"fixed\"/><w:tblBorders>",
+                    text_width.round() as u32,
+                ));
+                for side in ["top", "left", "bottom", "right", "insideH", "insideV"] {
+                    xml.push_str(&format!("<w:{} w:val=\"single\" w:sz=\"4\" w:color=\"808080\"/>", side));
+                }
+                xml.push_str("</w:tblBorders></w:tblPr><w:tblGrid>");
+                for width in &widths {
+                    xml.push_str(&format!("<w:gridCol w:w=\"{}\"/>", width));
+                }
+                xml.push_str("</w:tblGrid>");
+                for row in rows {
+                    xml.push_str("<w:tr>");
+                    for (c, width) in widths.iter().enumerate() {
+                        let cell = row.get(c);
+                        xml.push_str(&format!("<w:tc><w:tcPr><w:tcW w:w=\"{}\" w:type=\"dxa\"/></w:tcPr>", width));
+                        xml.push_str(&docx_paragraph(cell.map_or("", |c| c.text.as_str()), 10, cell.is_some_and(|c| c.bold), false));
+                        xml.push_str("</w:tc>");
+                    }
+                    xml.push_str("</w:tr>");
+                }
+                // Un paragraphe sépare deux tableaux consécutifs
+                xml.push_str("</w:tbl><w:p/>");
+            }
+        }
+        xml.push('\n');
+    }
+    // Page A4 (11906 × 16838 twips), marges de 2 cm
+    xml.push_str(concat!(
+        "<w:sectPr><w:footerReference w:type=\"default\" r:id=\"rId1\"/>",
+        "<w:pgSz w:w=\"11906\" w:h=\"16838\"/>",
+        "<w:pgMar w:top=\"1134\" w:right=\"1134\" w:bottom=\"1134\" w:left=\"1134\" w:header=\"567\" w:footer=\"567\" w:gutter=\"0\"/>",
+        "</w:sectPr>\n</w:body>\n</w:document>\n",
+    ));
+    xml
+}
+
+fn docx_footer(doc: &RegisterDocument) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<w:ftr {}>{}</w:ftr>\n",
+        WORDML_NAMESPACES,
+        docx_paragraph(&footer(doc), 8, false, false),
+    )
+}
+
+pub fn render_docx(doc: &RegisterDocument) -> zip::result::ZipResult<Vec<u8>> {
+    let blocks = blocks(doc);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file("[Content_Types].xml", options)?;
+    zip.write_all(DOCX_CONTENT_TYPES.as_bytes())?;
+    zip.start_file("_rels/.rels", options)?;
+    zip.write_all(DOCX_RELS.as_bytes())?;
+    zip.start_file("docProps/core.xml", options)?;
+    zip.write_all(docx_core(doc).as_bytes())?;
+    zip.start_file("word/document.xml", options)?;
+    zip.write_all(docx_document(&blocks).as_bytes())?;
+    zip.start_file("word/_rels/document.xml.rels", options)?;
+    zip.write_all(DOCX_DOCUMENT_RELS.as_bytes())?;
+    zip.start_file("word/footer1.xml", options)?;
+    zip.write_all(docx_footer(doc).as_bytes())?;
+    Ok(zip.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use chrono::Utc;
+    use super::*;
+    use super::document::{Field, Record, Section};
+
+    fn sample() -> RegisterDocument {
+        RegisterDocument {
+            organisation: "ACME & Fils".to_string(),
+            version: 3,
+            generated_at: Utc::now(),
+            generated_by: "dpo@acme.test".to_string(),
+            header: vec![Field { label: "Organisme", value: "ACME & Fils".to_string() }],
+            records: vec![Record {
+                reference: "T-001".to_string(),
+                name: "Paie <salariés>".to_string(),
+                record_type: document::record_type_label("controller"),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                sections: vec![Section {
+                    title: "Durée de conservation",
+                    fields: vec![Field { label: "Durée", value: "5 ans\naprès le départ".to_string() }],
+                }],
+            }],
+        }
+    }
+
+    // Chaque partie XML de l'archive doit être bien formée
+    fn assert_well_formed(archive: Vec<u8>, parts: &[&str]) -> String {
+        let mut zip = zip::ZipArchive::new(Cursor::new(archive)).unwrap();
+        let mut main = String::new();
+        for (i, part) in parts.iter().enumerate() {
+            let mut xml = String::new();
+            zip.by_name(part).unwrap().read_to_string(&mut xml).unwrap();
+            for event in xml::reader::EventReader::new(xml.as_bytes()) {
+                event.unwrap_or_else(|e| panic!("{}: {}", part, e));
+            }
+            if i == 0 {
+                main = xml;
+            }
+        }
+        main
+    }
+
+    #[test]
+    fn renders_well_formed_odt() {
+        let archive = render_odt(&sample()).unwrap();
+        assert_eq!(&archive[30..38], b"mimetype");
+        let content = assert_well_formed(archive, &["content.xml", "styles.xml", "meta.xml", "META-INF/manifest.xml"]);
+        assert!(content.contains("Paie &lt;salariés&gt;"));
+        assert!(content.contains("<text:p>5 ans</text:p><text:p>après le départ</text:p>"));
+    }
+
+    #[test]
+    fn renders_well_formed_docx() {
+        let archive = render_docx(&sample()).unwrap();
+        let document = assert_well_formed(archive, &["word/document.xml", "word/footer1.xml", "[Content_Types].xml", "_rels/.rels", "docProps/core.xml"]);
+        assert!(document.contains("T-001 — Paie &lt;salariés&gt;"));
+        assert!(document.contains("<w:pageBreakBefore/>"));
+    }
+}
@@ -1,344 +1,229 @@
-use actix_web::{web, HttpResponse, HttpRequest};
-use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, Set, IntoActiveModel, QueryOrder, Order, ActiveModelTrait};
+use actix_web::{web, HttpRequest, HttpResponse};
+use sea_orm::{Condition, DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, Set, IntoActiveModel, QueryOrder, Order, ActiveModelTrait, TransactionTrait};
+use crate::auth::mfa;
+use crate::entities::hierarchy::EntityTree;
 use crate::entities::rgpd::models::*;
-use crate::middleware::get_current_user_id;
-use crate::entities_orm::user_entity::{Entity as UserEntityEntity, Column as UserEntityColumn};
-use crate::entities_orm::register_entry::{Entity as RegisterEntryEntity, Column as RegisterEntryColumn, ActiveModel as RegisterEntryActiveModel};
-use crate::entities_orm::access_request::{Entity as AccessRequestEntity, Column as AccessRequestColumn, ActiveModel as AccessRequestActiveModel};
-use crate::entities_orm::breach::{Entity as BreachEntity, Column as BreachColumn, ActiveModel as BreachActiveModel};
+use crate::entities::rgpd::register::{self, RegisterLinks};
+use crate::entities::rgpd::taxonomy;
+use crate::entities::scope::{EntityScope, ScopedId};
+use crate::errors::ApiError;
+use crate::validation::ValidatedJson;
+use crate::entities_orm::register_entry::{self, Entity as RegisterEntryEntity, Column as RegisterEntryColumn, ActiveModel as RegisterEntryActiveModel};
+use crate::entities_orm::access_request::{self, Entity as AccessRequestEntity, Column as AccessRequestColumn, ActiveModel as AccessRequestActiveModel};
+use crate::entities_orm::breach::{self, Entity as BreachEntity, Column as BreachColumn, ActiveModel as BreachActiveModel};
 use uuid::Uuid;
 use chrono::Utc;
 
-// Helper function to check access
-async fn check_entity_access(
-    db: &DatabaseConnection,
-    user_id: Uuid,
-    entity_id: Uuid,
-) -> Result<bool, sea_orm::DbErr> {
-    let has_access = UserEntityEntity::find()
-        .filter(UserEntityColumn::UserId.eq(user_id))
-        .filter(UserEntityColumn::EntityId.eq(entity_id))
+// Un enregistrement d'une autre entité est traité comme inexistant
+async fn find_register_entry(db: &DatabaseConnection, scope: &EntityScope, id: Uuid) -> Result<register_entry::Model, ApiError> {
+    RegisterEntryEntity::find_by_id(id)
+        .filter(RegisterEntryColumn::EntityId.eq(scope.entity_id))
         .one(db)
-        .await?;
-    Ok(has_access.is_some())
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Entry not found".to_string()))
+}
+
+// Les dossiers archivés ne sont consultables que par l'archive de conservation
+async fn find_access_request(db: &DatabaseConnection, scope: &EntityScope, id: Uuid) -> Result<access_request::Model, ApiError> {
+    AccessRequestEntity::find_by_id(id)
+        .filter(AccessRequestColumn::EntityId.eq(scope.entity_id))
+        .filter(AccessRequestColumn::ArchivedAt.is_null())
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Request not found".to_string()))
+}
+
+pub(crate) async fn find_breach(db: &DatabaseConnection, scope: &EntityScope, id: Uuid) -> Result<breach::Model, ApiError> {
+    BreachEntity::find_by_id(id)
+        .filter(BreachColumn::EntityId.eq(scope.entity_id))
+        .filter(BreachColumn::ArchivedAt.is_null())
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Breach not found".to_string()))
 }
 
-// Registre léger
+// Registre : entrées de l'entité et traitements partagés par ses groupes
 pub async fn get_register(
     db: web::Data<DatabaseConnection>,
-    req: HttpRequest,
-    query: web::Query<serde_json::Value>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
-
-    let entity_id: Option<Uuid> = query.get("entity_id")
-        .and_then(|v| v.as_str())
-        .and_then(|s| Uuid::parse_str(s).ok());
-
-    let entries: Vec<crate::entities_orm::register_entry::Model> = if let Some(eid) = entity_id {
-        // Check access
-        if !check_entity_access(db.get_ref(), user_id, eid).await.map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })? {
-            return Ok(HttpResponse::Forbidden().json(serde_json::json!({
-                "error": "Access denied"
-            })));
-        }
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    let ancestors = EntityTree::load(db.get_ref()).await?.ancestors(scope.entity_id);
+    let entries = RegisterEntryEntity::find()
+        .filter(
+            Condition::any()
+                .add(RegisterEntryColumn::EntityId.eq(scope.entity_id))
+                .add(
+                    Condition::all()
+                        .add(RegisterEntryColumn::EntityId.is_in(ancestors))
+                        .add(RegisterEntryColumn::SharedWithSubsidiaries.eq(true)),
+                ),
+        )
+        .order_by(RegisterEntryColumn::CreatedAt, Order::Desc)
+        .all(db.get_ref())
+        .await?;
 
-        RegisterEntryEntity::find()
-            .filter(RegisterEntryColumn::EntityId.eq(eid))
-            .order_by(RegisterEntryColumn::CreatedAt, Order::Desc)
-            .all(db.get_ref())
-            .await
-    } else {
-        // Get all entities user has access to through user_entities
-        let user_entities = UserEntityEntity::find()
-            .filter(UserEntityColumn::UserId.eq(user_id))
-            .all(db.get_ref())
-            .await
-            .map_err(|e| {
-                log::error!("Database error: {}", e);
-                actix_web::error::ErrorInternalServerError("Database error")
-            })?;
-        
-        let entity_ids: Vec<Uuid> = user_entities.iter().map(|ue| ue.entity_id).collect();
-        
-        RegisterEntryEntity::find()
-            .filter(RegisterEntryColumn::EntityId.is_in(entity_ids))
-            .order_by(RegisterEntryColumn::CreatedAt, Order::Desc)
-            .all(db.get_ref())
-            .await
-    }
-    .map_err(|e| {
-        log::error!("Database error: {}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })?;
-
-    let entries: Vec<RegisterEntry> = entries.into_iter().map(|e| RegisterEntry {
-        id: e.id,
-        entity_id: e.entity_id,
-        processing_name: e.processing_name,
-        purpose: e.purpose,
-        legal_basis: e.legal_basis,
-        data_categories: e.data_categories,
-        data_subjects: e.data_subjects,
-        recipients: e.recipients,
-        retention_period: e.retention_period,
-        security_measures: e.security_measures,
-        created_at: e.created_at,
-        updated_at: e.updated_at,
-    }).collect();
-
-    Ok(HttpResponse::Ok().json(entries))
+    Ok(HttpResponse::Ok().json(register::load(db.get_ref(), entries).await?))
 }
 
 pub async fn add_to_register(
     db: web::Data<DatabaseConnection>,
-    req: HttpRequest,
-    query: web::Query<serde_json::Value>,
-    body: web::Json<CreateRegisterEntryRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
-
-    let entity_id: Uuid = query.get("entity_id")
-        .and_then(|v| v.as_str())
-        .and_then(|s| Uuid::parse_str(s).ok())
-        .ok_or_else(|| actix_web::error::ErrorBadRequest("Missing entity_id"))?;
-
-    // Check access
-    if !check_entity_access(db.get_ref(), user_id, entity_id).await.map_err(|e| {
-        log::error!("Database error: {}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })? {
-        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
-            "error": "Access denied"
-        })));
-    }
+    scope: EntityScope,
+    body: ValidatedJson<CreateRegisterEntryRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let links = RegisterLinks::from_create(&body);
+    register::check_references(db.get_ref(), scope.entity_id, &links).await?;
 
     let now = Utc::now();
     let entry = RegisterEntryActiveModel {
         id: Set(Uuid::new_v4()),
-        entity_id: Set(entity_id),
+        entity_id: Set(scope.entity_id),
+        record_type: Set(body.record_type.clone().unwrap_or_else(|| "controller".to_string())),
         processing_name: Set(body.processing_name.clone()),
         purpose: Set(body.purpose.clone()),
         legal_basis: Set(body.legal_basis.clone()),
-        data_categories: Set(body.data_categories.clone()),
-        data_subjects: Set(body.data_subjects.clone()),
-        recipients: Set(body.recipients.clone()),
+        legal_basis_details: Set(body.legal_basis_details.clone()),
+        special_category_exception: Set(body.special_category_exception.clone()),
+        criminal_data_basis: Set(body.criminal_data_basis.clone()),
+        consent_mechanism: Set(body.consent_mechanism.clone()),
         retention_period: Set(body.retention_period.clone()),
+        retention_months: Set(body.retention_months),
+        retention_trigger: Set(body.retention_trigger.clone()),
+        retention_action: Set(body.retention_action.clone()),
         security_measures: Set(body.security_measures.clone()),
+        shared_with_subsidiaries: Set(body.shared_with_subsidiaries.unwrap_or(false)),
         created_at: Set(now),
         updated_at: Set(now),
     };
 
+    let txn = db.begin().await?;
     let entry = RegisterEntryEntity::insert(entry)
-        .exec_with_returning(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    Ok(HttpResponse::Created().json(RegisterEntry {
-        id: entry.id,
-        entity_id: entry.entity_id,
-        processing_name: entry.processing_name,
-        purpose: entry.purpose,
-        legal_basis: entry.legal_basis,
-        data_categories: entry.data_categories,
-        data_subjects: entry.data_subjects,
-        recipients: entry.recipients,
-        retention_period: entry.retention_period,
-        security_measures: entry.security_measures,
-        created_at: entry.created_at,
-        updated_at: entry.updated_at,
-    }))
+        .exec_with_returning(&txn)
+        .await?;
+    register::save_links(&txn, entry.id, &links).await?;
+    let entry = register::load_one(&txn, entry).await?;
+    // Règles de cohérence évaluées sur la fiche complète, avant validation
+    taxonomy::ensure_consistent(&entry)?;
+    txn.commit().await?;
+
+    Ok(HttpResponse::Created().json(entry))
 }
 
 pub async fn update_register_entry(
     db: web::Data<DatabaseConnection>,
-    req: HttpRequest,
-    path: web::Path<Uuid>,
-    body: web::Json<UpdateRegisterEntryRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
-    let entry_id = path.into_inner();
-
-    // Check access through entry's entity
-    let entry = RegisterEntryEntity::find_by_id(entry_id)
-        .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    let entry = match entry {
-        Some(e) => e,
-        None => return Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Entry not found"
-        }))),
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<UpdateRegisterEntryRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let entry = find_register_entry(db.get_ref(), &scope, path.id).await?;
+    let links = RegisterLinks::from_update(&body);
+    register::check_references(db.get_ref(), scope.entity_id, &links).await?;
+
+    // Mentions obligatoires vérifiées après fusion avec la fiche existante ;
+    // passer en registre sous-traitant retire la base légale
+    let current = register::load_one(db.get_ref(), entry.clone()).await?;
+    let record_type = body.record_type.clone().unwrap_or(current.record_type);
+    let legal_basis = match (&body.legal_basis, record_type.as_str()) {
+        (Some(legal_basis), _) => Some(legal_basis.clone()),
+        (None, "processor") => None,
+        (None, _) => current.legal_basis,
     };
-
-    if !check_entity_access(db.get_ref(), user_id, entry.entity_id).await.map_err(|e| {
-        log::error!("Database error: {}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })? {
-        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
-            "error": "Access denied"
-        })));
-    }
-
+    let parties: Vec<RegisterPartyInput> = match &body.parties {
+        Some(parties) => parties.clone(),
+        None => current.parties.iter()
+            .map(|p| RegisterPartyInput { party_id: p.party_id, role: p.role.clone() })
+            .collect(),
+    };
+    check_record(
+        &record_type,
+        legal_basis.as_deref(),
+        body.data_category_ids.as_ref().map_or(current.data_categories.len(), Vec::len),
+        body.data_subject_ids.as_ref().map_or(current.data_subjects.len(), Vec::len),
+        &parties,
+    )
+    .map_err(|e| ApiError::validation("body", e.code, e.message.unwrap_or_default()))?;
+
+    let processor = record_type == "processor";
     let mut entry: RegisterEntryActiveModel = entry.into_active_model();
+    entry.record_type = Set(record_type);
+    entry.legal_basis = Set(legal_basis);
+    if let Some(details) = &body.legal_basis_details {
+        entry.legal_basis_details = Set(Some(details.clone()));
+    } else if processor {
+        entry.legal_basis_details = Set(None);
+    }
+    if let Some(exception) = &body.special_category_exception {
+        entry.special_category_exception = Set(Some(exception.clone()));
+    }
+    if let Some(basis) = &body.criminal_data_basis {
+        entry.criminal_data_basis = Set(Some(basis.clone()));
+    }
+    if let Some(mechanism) = &body.consent_mechanism {
+        entry.consent_mechanism = Set(Some(mechanism.clone()));
+    }
     if let Some(name) = &body.processing_name {
         entry.processing_name = Set(name.clone());
     }
     if let Some(purpose) = &body.purpose {
         entry.purpose = Set(purpose.clone());
     }
-    if let Some(legal_basis) = &body.legal_basis {
-        entry.legal_basis = Set(legal_basis.clone());
-    }
-    if let Some(categories) = &body.data_categories {
-        entry.data_categories = Set(categories.clone());
+    if let Some(retention) = &body.retention_period {
+        entry.retention_period = Set(Some(retention.clone()));
     }
-    if let Some(subjects) = &body.data_subjects {
-        entry.data_subjects = Set(subjects.clone());
+    if let Some(months) = body.retention_months {
+        entry.retention_months = Set(Some(months));
     }
-    if let Some(recipients) = &body.recipients {
-        entry.recipients = Set(recipients.clone());
+    if let Some(trigger) = &body.retention_trigger {
+        entry.retention_trigger = Set(Some(trigger.clone()));
     }
-    if let Some(retention) = &body.retention_period {
-        entry.retention_period = Set(Some(retention.clone()));
+    if let Some(action) = &body.retention_action {
+        entry.retention_action = Set(Some(action.clone()));
     }
     if let Some(security) = &body.security_measures {
         entry.security_measures = Set(Some(security.clone()));
     }
+    if let Some(shared) = body.shared_with_subsidiaries {
+        entry.shared_with_subsidiaries = Set(shared);
+    }
     entry.updated_at = Set(Utc::now());
 
-    let entry = entry.update(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    Ok(HttpResponse::Ok().json(RegisterEntry {
-        id: entry.id,
-        entity_id: entry.entity_id,
-        processing_name: entry.processing_name,
-        purpose: entry.purpose,
-        legal_basis: entry.legal_basis,
-        data_categories: entry.data_categories,
-        data_subjects: entry.data_subjects,
-        recipients: entry.recipients,
-        retention_period: entry.retention_period,
-        security_measures: entry.security_measures,
-        created_at: entry.created_at,
-        updated_at: entry.updated_at,
-    }))
+    let txn = db.begin().await?;
+    let entry = entry.update(&txn)
+        .await?;
+    register::save_links(&txn, entry.id, &links).await?;
+    let entry = register::load_one(&txn, entry).await?;
+    taxonomy::ensure_consistent(&entry)?;
+    txn.commit().await?;
+
+    Ok(HttpResponse::Ok().json(entry))
 }
 
 // Demandes d'accès
 pub async fn list_access_requests(
     db: web::Data<DatabaseConnection>,
-    req: HttpRequest,
-    query: web::Query<serde_json::Value>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
-
-    let entity_id: Option<Uuid> = query.get("entity_id")
-        .and_then(|v| v.as_str())
-        .and_then(|s| Uuid::parse_str(s).ok());
-
-    let requests: Vec<crate::entities_orm::access_request::Model> = if let Some(eid) = entity_id {
-        if !check_entity_access(db.get_ref(), user_id, eid).await.map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })? {
-            return Ok(HttpResponse::Forbidden().json(serde_json::json!({
-                "error": "Access denied"
-            })));
-        }
-
-        AccessRequestEntity::find()
-            .filter(AccessRequestColumn::EntityId.eq(eid))
-            .order_by(AccessRequestColumn::CreatedAt, Order::Desc)
-            .all(db.get_ref())
-            .await
-    } else {
-        let user_entities = UserEntityEntity::find()
-            .filter(UserEntityColumn::UserId.eq(user_id))
-            .all(db.get_ref())
-            .await
-            .map_err(|e| {
-                log::error!("Database error: {}", e);
-                actix_web::error::ErrorInternalServerError("Database error")
-            })?;
-        
-        let entity_ids: Vec<Uuid> = user_entities.iter().map(|ue| ue.entity_id).collect();
-        
-        AccessRequestEntity::find()
-            .filter(AccessRequestColumn::EntityId.is_in(entity_ids))
-            .order_by(AccessRequestColumn::CreatedAt, Order::Desc)
-            .all(db.get_ref())
-            .await
-    }
-    .map_err(|e| {
-        log::error!("Database error: {}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })?;
-
-    let requests: Vec<AccessRequest> = requests.into_iter().map(|r| AccessRequest {
-        id: r.id,
-        entity_id: r.entity_id,
-        requester_name: r.requester_name,
-        requester_email: r.requester_email,
-        request_type: r.request_type,
-        description: r.description,
-        status: r.status,
-        response: r.response,
-        created_at: r.created_at,
-        updated_at: r.updated_at,
-        completed_at: r.completed_at,
-    }).collect();
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    let requests: Vec<AccessRequest> = AccessRequestEntity::find()
+        .filter(AccessRequestColumn::EntityId.eq(scope.entity_id))
+        .filter(AccessRequestColumn::ArchivedAt.is_null())
+        .order_by(AccessRequestColumn::CreatedAt, Order::Desc)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(AccessRequest::from)
+        .collect();
 
     Ok(HttpResponse::Ok().json(requests))
 }
 
 pub async fn create_access_request(
     db: web::Data<DatabaseConnection>,
-    req: HttpRequest,
-    query: web::Query<serde_json::Value>,
-    body: web::Json<CreateAccessRequestRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
-
-    let entity_id: Uuid = query.get("entity_id")
-        .and_then(|v| v.as_str())
-        .and_then(|s| Uuid::parse_str(s).ok())
-        .ok_or_else(|| actix_web::error::ErrorBadRequest("Missing entity_id"))?;
-
-    // Check access
-    if !check_entity_access(db.get_ref(), user_id, entity_id).await.map_err(|e| {
-        log::error!("Database error: {}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })? {
-        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
-            "error": "Access denied"
-        })));
-    }
-
+    scope: EntityScope,
+    body: ValidatedJson<CreateAccessRequestRequest>,
+) -> Result<HttpResponse, ApiError> {
     let now = Utc::now();
     let access_request = AccessRequestActiveModel {
         id: Set(Uuid::new_v4()),
-        entity_id: Set(entity_id),
+        entity_id: Set(scope.entity_id),
         requester_name: Set(body.requester_name.clone()),
         requester_email: Set(body.requester_email.clone()),
         request_type: Set(body.request_type.clone()),
@@ -348,247 +233,86 @@ pub async fn create_access_request(
         created_at: Set(now),
         updated_at: Set(now),
         completed_at: Set(None),
+        anonymized_at: Set(None),
+        archived_at: Set(None),
     };
 
     let access_request = AccessRequestEntity::insert(access_request)
         .exec_with_returning(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    Ok(HttpResponse::Created().json(AccessRequest {
-        id: access_request.id,
-        entity_id: access_request.entity_id,
-        requester_name: access_request.requester_name,
-        requester_email: access_request.requester_email,
-        request_type: access_request.request_type,
-        description: access_request.description,
-        status: access_request.status,
-        response: access_request.response,
-        created_at: access_request.created_at,
-        updated_at: access_request.updated_at,
-        completed_at: access_request.completed_at,
-    }))
+        .await?;
+
+    Ok(HttpResponse::Created().json(AccessRequest::from(access_request)))
 }
 
 pub async fn get_access_request(
     db: web::Data<DatabaseConnection>,
-    req: HttpRequest,
-    path: web::Path<Uuid>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
-    let request_id = path.into_inner();
-
-    let access_request = AccessRequestEntity::find_by_id(request_id)
-        .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    let access_request = match access_request {
-        Some(r) => r,
-        None => return Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Request not found"
-        }))),
-    };
-
-    if !check_entity_access(db.get_ref(), user_id, access_request.entity_id).await.map_err(|e| {
-        log::error!("Database error: {}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })? {
-        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
-            "error": "Access denied"
-        })));
-    }
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+) -> Result<HttpResponse, ApiError> {
+    let access_request = find_access_request(db.get_ref(), &scope, path.id).await?;
 
-    Ok(HttpResponse::Ok().json(AccessRequest {
-        id: access_request.id,
-        entity_id: access_request.entity_id,
-        requester_name: access_request.requester_name,
-        requester_email: access_request.requester_email,
-        request_type: access_request.request_type,
-        description: access_request.description,
-        status: access_request.status,
-        response: access_request.response,
-        created_at: access_request.created_at,
-        updated_at: access_request.updated_at,
-        completed_at: access_request.completed_at,
-    }))
+    Ok(HttpResponse::Ok().json(AccessRequest::from(access_request)))
 }
 
 pub async fn respond_to_request(
     db: web::Data<DatabaseConnection>,
-    req: HttpRequest,
-    path: web::Path<Uuid>,
-    body: web::Json<RespondToRequestRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
-    let request_id = path.into_inner();
-
-    let access_request = AccessRequestEntity::find_by_id(request_id)
-        .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    let access_request = match access_request {
-        Some(r) => r,
-        None => return Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Request not found"
-        }))),
-    };
-
-    if !check_entity_access(db.get_ref(), user_id, access_request.entity_id).await.map_err(|e| {
-        log::error!("Database error: {}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })? {
-        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
-            "error": "Access denied"
-        })));
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<RespondToRequestRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let access_request = find_access_request(db.get_ref(), &scope, path.id).await?;
+    if access_request.anonymized_at.is_some() {
+        return Err(ApiError::Conflict("Access request has been anonymized".to_string()));
     }
 
     let mut access_request: AccessRequestActiveModel = access_request.into_active_model();
     access_request.status = Set(body.status.clone());
     access_request.response = Set(body.response.clone());
-    if body.status == "completed" {
+    // La clôture, acceptée ou refusée, fait courir la durée de conservation
+    if ACCESS_REQUEST_CLOSED_STATUSES.contains(&body.status.as_str()) {
         access_request.completed_at = Set(Some(Utc::now()));
+    } else {
+        access_request.completed_at = Set(None);
     }
     access_request.updated_at = Set(Utc::now());
 
     let access_request = access_request.update(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    Ok(HttpResponse::Ok().json(AccessRequest {
-        id: access_request.id,
-        entity_id: access_request.entity_id,
-        requester_name: access_request.requester_name,
-        requester_email: access_request.requester_email,
-        request_type: access_request.request_type,
-        description: access_request.description,
-        status: access_request.status,
-        response: access_request.response,
-        created_at: access_request.created_at,
-        updated_at: access_request.updated_at,
-        completed_at: access_request.completed_at,
-    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(AccessRequest::from(access_request)))
 }
 
 // Gestion des écarts
 pub async fn list_breaches(
     db: web::Data<DatabaseConnection>,
-    req: HttpRequest,
-    query: web::Query<serde_json::Value>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
-
-    let entity_id: Option<Uuid> = query.get("entity_id")
-        .and_then(|v| v.as_str())
-        .and_then(|s| Uuid::parse_str(s).ok());
-
-    let breaches: Vec<crate::entities_orm::breach::Model> = if let Some(eid) = entity_id {
-        if !check_entity_access(db.get_ref(), user_id, eid).await.map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })? {
-            return Ok(HttpResponse::Forbidden().json(serde_json::json!({
-                "error": "Access denied"
-            })));
-        }
-
-        BreachEntity::find()
-            .filter(BreachColumn::EntityId.eq(eid))
-            .order_by(BreachColumn::DiscoveryDate, Order::Desc)
-            .all(db.get_ref())
-            .await
-    } else {
-        let user_entities = UserEntityEntity::find()
-            .filter(UserEntityColumn::UserId.eq(user_id))
-            .all(db.get_ref())
-            .await
-            .map_err(|e| {
-                log::error!("Database error: {}", e);
-                actix_web::error::ErrorInternalServerError("Database error")
-            })?;
-        
-        let entity_ids: Vec<Uuid> = user_entities.iter().map(|ue| ue.entity_id).collect();
-        
-        BreachEntity::find()
-            .filter(BreachColumn::EntityId.is_in(entity_ids))
-            .order_by(BreachColumn::DiscoveryDate, Order::Desc)
-            .all(db.get_ref())
-            .await
-    }
-    .map_err(|e| {
-        log::error!("Database error: {}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })?;
-
-    let breaches: Vec<Breach> = breaches.into_iter().map(|b| Breach {
-        id: b.id,
-        entity_id: b.entity_id,
-        breach_date: b.breach_date,
-        discovery_date: b.discovery_date,
-        description: b.description,
-        data_categories_affected: b.data_categories_affected,
-        number_of_subjects: b.number_of_subjects,
-        severity: b.severity,
-        status: b.status,
-        containment_measures: b.containment_measures,
-        notification_date: b.notification_date,
-        authority_notified: b.authority_notified,
-        subjects_notified: b.subjects_notified,
-        created_at: b.created_at,
-        updated_at: b.updated_at,
-    }).collect();
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    let breaches: Vec<Breach> = BreachEntity::find()
+        .filter(BreachColumn::EntityId.eq(scope.entity_id))
+        .filter(BreachColumn::ArchivedAt.is_null())
+        .order_by(BreachColumn::DiscoveryDate, Order::Desc)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(Breach::from)
+        .collect();
 
     Ok(HttpResponse::Ok().json(breaches))
 }
 
 pub async fn create_breach(
     db: web::Data<DatabaseConnection>,
-    req: HttpRequest,
-    query: web::Query<serde_json::Value>,
-    body: web::Json<CreateBreachRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
-
-    let entity_id: Uuid = query.get("entity_id")
-        .and_then(|v| v.as_str())
-        .and_then(|s| Uuid::parse_str(s).ok())
-        .ok_or_else(|| actix_web::error::ErrorBadRequest("Missing entity_id"))?;
-
-    // Check access
-    if !check_entity_access(db.get_ref(), user_id, entity_id).await.map_err(|e| {
-        log::error!("Database error: {}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })? {
-        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
-            "error": "Access denied"
-        })));
-    }
-
+    scope: EntityScope,
+    body: ValidatedJson<CreateBreachRequest>,
+) -> Result<HttpResponse, ApiError> {
     let now = Utc::now();
     let breach = BreachActiveModel {
         id: Set(Uuid::new_v4()),
-        entity_id: Set(entity_id),
+        entity_id: Set(scope.entity_id),
         breach_date: Set(body.breach_date),
         discovery_date: Set(body.discovery_date),
         description: Set(body.description.clone()),
-        data_categories_affected: Set(body.data_categories_affected.clone()),
+        data_categories_affected: Set(serde_json::json!(body.data_categories_affected)),
         number_of_subjects: Set(body.number_of_subjects),
         severity: Set(body.severity.clone()),
         status: Set("detected".to_string()),
@@ -598,121 +322,56 @@ pub async fn create_breach(
         subjects_notified: Set(false),
         created_at: Set(now),
         updated_at: Set(now),
+        closed_at: Set(None),
+        anonymized_at: Set(None),
+        assessment: Set(None),
+        assessment_justification: Set(None),
+        assessed_by: Set(None),
+        assessed_at: Set(None),
+        archived_at: Set(None),
     };
 
     let breach = BreachEntity::insert(breach)
         .exec_with_returning(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    Ok(HttpResponse::Created().json(Breach {
-        id: breach.id,
-        entity_id: breach.entity_id,
-        breach_date: breach.breach_date,
-        discovery_date: breach.discovery_date,
-        description: breach.description,
-        data_categories_affected: breach.data_categories_affected,
-        number_of_subjects: breach.number_of_subjects,
-        severity: breach.severity,
-        status: breach.status,
-        containment_measures: breach.containment_measures,
-        notification_date: breach.notification_date,
-        authority_notified: breach.authority_notified,
-        subjects_notified: breach.subjects_notified,
-        created_at: breach.created_at,
-        updated_at: breach.updated_at,
-    }))
+        .await?;
+
+    Ok(HttpResponse::Created().json(Breach::from(breach)))
 }
 
 pub async fn get_breach(
     db: web::Data<DatabaseConnection>,
-    req: HttpRequest,
-    path: web::Path<Uuid>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
-    let breach_id = path.into_inner();
-
-    let breach = BreachEntity::find_by_id(breach_id)
-        .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    let breach = match breach {
-        Some(b) => b,
-        None => return Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Breach not found"
-        }))),
-    };
-
-    if !check_entity_access(db.get_ref(), user_id, breach.entity_id).await.map_err(|e| {
-        log::error!("Database error: {}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })? {
-        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
-            "error": "Access denied"
-        })));
-    }
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+) -> Result<HttpResponse, ApiError> {
+    let breach = find_breach(db.get_ref(), &scope, path.id).await?;
 
-    Ok(HttpResponse::Ok().json(Breach {
-        id: breach.id,
-        entity_id: breach.entity_id,
-        breach_date: breach.breach_date,
-        discovery_date: breach.discovery_date,
-        description: breach.description,
-        data_categories_affected: breach.data_categories_affected,
-        number_of_subjects: breach.number_of_subjects,
-        severity: breach.severity,
-        status: breach.status,
-        containment_measures: breach.containment_measures,
-        notification_date: breach.notification_date,
-        authority_notified: breach.authority_notified,
-        subjects_notified: breach.subjects_notified,
-        created_at: breach.created_at,
-        updated_at: breach.updated_at,
-    }))
+    Ok(HttpResponse::Ok().json(Breach::from(breach)))
 }
 
 pub async fn update_breach(
     db: web::Data<DatabaseConnection>,
-    req: HttpRequest,
-    path: web::Path<Uuid>,
-    body: web::Json<UpdateBreachRequest>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = get_current_user_id(&req)
-        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Not authenticated"))?;
-    let breach_id = path.into_inner();
-
-    let breach = BreachEntity::find_by_id(breach_id)
-        .one(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    let breach = match breach {
-        Some(b) => b,
-        None => return Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Breach not found"
-        }))),
-    };
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<UpdateBreachRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let breach = find_breach(db.get_ref(), &scope, path.id).await?;
+    if breach.anonymized_at.is_some() {
+        return Err(ApiError::Conflict("Breach has been anonymized".to_string()));
+    }
 
-    if !check_entity_access(db.get_ref(), user_id, breach.entity_id).await.map_err(|e| {
-        log::error!("Database error: {}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })? {
-        return Ok(HttpResponse::Forbidden().json(serde_json::json!({
-            "error": "Access denied"
-        })));
+    // Une fois évaluée, la gravité suit l'évaluation
+    if breach.assessment.is_some() && body.severity.as_ref().is_some_and(|s| *s != breach.severity) {
+        return Err(ApiError::validation("severity", "assessed", "Severity follows the breach assessment; update the assessment instead"));
     }
 
+    // Cohérence des dates après fusion avec les valeurs existantes
+    check_breach_dates(
+        Some(body.breach_date.unwrap_or(breach.breach_date)),
+        Some(body.discovery_date.unwrap_or(breach.discovery_date)),
+        body.notification_date.or(breach.notification_date),
+    )
+    .map_err(|e| ApiError::validation("body", e.code, e.message.unwrap_or_default()))?;
+
     let mut breach: BreachActiveModel = breach.into_active_model();
     if let Some(date) = &body.breach_date {
         breach.breach_date = Set(*date);
@@ -724,7 +383,7 @@ pub async fn update_breach(
         breach.description = Set(desc.clone());
     }
     if let Some(categories) = &body.data_categories_affected {
-        breach.data_categories_affected = Set(categories.clone());
+        breach.data_categories_affected = Set(serde_json::json!(categories));
     }
     if let Some(number) = &body.number_of_subjects {
         breach.number_of_subjects = Set(Some(*number));
@@ -733,6 +392,12 @@ pub async fn update_breach(
         breach.severity = Set(severity.clone());
     }
     if let Some(status) = &body.status {
+        let closed = BREACH_CLOSED_STATUSES.contains(&status.as_str());
+        match breach.closed_at.as_ref() {
+            None if closed => breach.closed_at = Set(Some(Utc::now())),
+            Some(_) if !closed => breach.closed_at = Set(None),
+            _ => {}
+        }
         breach.status = Set(status.clone());
     }
     if let Some(measures) = &body.containment_measures {
@@ -750,28 +415,64 @@ pub async fn update_breach(
     breach.updated_at = Set(Utc::now());
 
     let breach = breach.update(db.get_ref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    Ok(HttpResponse::Ok().json(Breach {
-        id: breach.id,
-        entity_id: breach.entity_id,
-        breach_date: breach.breach_date,
-        discovery_date: breach.discovery_date,
-        description: breach.description,
-        data_categories_affected: breach.data_categories_affected,
-        number_of_subjects: breach.number_of_subjects,
-        severity: breach.severity,
-        status: breach.status,
-        containment_measures: breach.containment_measures,
-        notification_date: breach.notification_date,
-        authority_notified: breach.authority_notified,
-        subjects_notified: breach.subjects_notified,
-        created_at: breach.created_at,
-        updated_at: breach.updated_at,
-    }))
+        .await?;
+
+    Ok(HttpResponse::Ok().json(Breach::from(breach)))
+}
+
+// Vues consolidées : l'entité et toutes ses filiales. Le rôle étant hérité,
+// seule la politique MFA propre à chaque filiale peut en exclure une.
+async fn consolidated_entities(db: &DatabaseConnection, req: &HttpRequest, scope: &EntityScope) -> Result<Vec<Uuid>, ApiError> {
+    let subtree = EntityTree::load(db).await?.subtree(scope.entity_id);
+    Ok(mfa::filter_mfa_entities(db, req, subtree).await?)
+}
+
+pub async fn consolidated_register(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    let entries = RegisterEntryEntity::find()
+        .filter(RegisterEntryColumn::EntityId.is_in(consolidated_entities(db.get_ref(), &req, &scope).await?))
+        .order_by(RegisterEntryColumn::CreatedAt, Order::Desc)
+        .all(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(register::load(db.get_ref(), entries).await?))
 }
 
+pub async fn consolidated_access_requests(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    let requests: Vec<AccessRequest> = AccessRequestEntity::find()
+        .filter(AccessRequestColumn::EntityId.is_in(consolidated_entities(db.get_ref(), &req, &scope).await?))
+        .filter(AccessRequestColumn::ArchivedAt.is_null())
+        .order_by(AccessRequestColumn::CreatedAt, Order::Desc)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(AccessRequest::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(requests))
+}
+
+pub async fn consolidated_breaches(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    let breaches: Vec<Breach> = BreachEntity::find()
+        .filter(BreachColumn::EntityId.is_in(consolidated_entities(db.get_ref(), &req, &scope).await?))
+        .filter(BreachColumn::ArchivedAt.is_null())
+        .order_by(BreachColumn::DiscoveryDate, Order::Desc)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(Breach::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(breaches))
+}
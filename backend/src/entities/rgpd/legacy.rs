@@ -0,0 +1,225 @@
+//! Anciennes routes `/rgpd?entity_id=...`, dépréciées au profit de
+//! `/entities/{entity_id}/rgpd`. Chaque adaptateur retrouve l'entité (paramètre
+//! de requête ou entité de l'enregistrement) puis délègue au handler imbriqué.
+use actix_web::{web, HttpRequest, HttpResponse};
+use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, QueryOrder, Order};
+use serde::Deserialize;
+use uuid::Uuid;
+use crate::auth::mfa;
+use crate::entities::lifecycle;
+use crate::entities::rgpd::{handlers, register};
+use crate::entities::rgpd::models::*;
+use crate::entities::scope::{EntityScope, ScopedId};
+use crate::errors::ApiError;
+use crate::middleware::get_member_entity_ids;
+use crate::validation::ValidatedJson;
+use crate::entities_orm::register_entry::{Entity as RegisterEntryEntity, Column as RegisterEntryColumn};
+use crate::entities_orm::access_request::{Entity as AccessRequestEntity, Column as AccessRequestColumn};
+use crate::entities_orm::breach::{Entity as BreachEntity, Column as BreachColumn};
+
+/// Date de retrait des routes `/rgpd` (format HTTP-date, en-tête `Sunset`).
+pub const SUNSET: &str = "Thu, 01 Apr 2027 00:00:00 GMT";
+pub const SUCCESSOR_LINK: &str = "</api/entities/{entity_id}/rgpd>; rel=\"successor-version\"";
+
+#[derive(Debug, Deserialize)]
+pub struct LegacyScopeQuery {
+    pub entity_id: Option<Uuid>,
+}
+
+fn warn_legacy(req: &HttpRequest) {
+    log::warn!("Deprecated RGPD route used: {} {}", req.method(), req.path());
+}
+
+async fn scope_from_query(db: &DatabaseConnection, req: &HttpRequest, query: &LegacyScopeQuery) -> Result<EntityScope, ApiError> {
+    let entity_id = query.entity_id
+        .ok_or_else(|| ApiError::validation("entity_id", "required", "Missing entity_id"))?;
+    EntityScope::resolve(db, req, entity_id).await
+}
+
+// Sans `entity_id`, les listes couvrent toutes les entités de l'utilisateur
+async fn all_entities(db: &DatabaseConnection, req: &HttpRequest) -> Result<Vec<Uuid>, ApiError> {
+    Ok(mfa::filter_mfa_entities(db, req, get_member_entity_ids(req)).await?)
+}
+
+pub async fn get_register(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    query: web::Query<LegacyScopeQuery>,
+) -> Result<HttpResponse, ApiError> {
+    warn_legacy(&req);
+    if query.entity_id.is_some() {
+        let scope = scope_from_query(db.get_ref(), &req, &query).await?;
+        return handlers::get_register(db, scope).await;
+    }
+
+    let entries = RegisterEntryEntity::find()
+        .filter(RegisterEntryColumn::EntityId.is_in(all_entities(db.get_ref(), &req).await?))
+        .order_by(RegisterEntryColumn::CreatedAt, Order::Desc)
+        .all(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(register::load(db.get_ref(), entries).await?))
+}
+
+pub async fn add_to_register(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    query: web::Query<LegacyScopeQuery>,
+    body: ValidatedJson<CreateRegisterEntryRequest>,
+) -> Result<HttpResponse, ApiError> {
+    warn_legacy(&req);
+    let scope = scope_from_query(db.get_ref(), &req, &query).await?;
+    handlers::add_to_register(db, scope, body).await
+}
+
+pub async fn update_register_entry(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: ValidatedJson<UpdateRegisterEntryRequest>,
+) -> Result<HttpResponse, ApiError> {
+    warn_legacy(&req);
+    let id = path.into_inner();
+    let entry = RegisterEntryEntity::find_by_id(id)
+        .one(db.get_ref())
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Entry not found".to_string()))?;
+    let scope = EntityScope::resolve(db.get_ref(), &req, entry.entity_id).await?;
+    // L'entité n'est pas dans l'URL : le middleware ne peut pas la vérifier
+    lifecycle::ensure_writable(db.get_ref(), scope.entity_id).await?;
+    handlers::update_register_entry(db, scope, web::Path::from(ScopedId { id }), body).await
+}
+
+pub async fn list_access_requests(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    query: web::Query<LegacyScopeQuery>,
+) -> Result<HttpResponse, ApiError> {
+    warn_legacy(&req);
+    if query.entity_id.is_some() {
+        let scope = scope_from_query(db.get_ref(), &req, &query).await?;
+        return handlers::list_access_requests(db, scope).await;
+    }
+
+    let requests: Vec<AccessRequest> = AccessRequestEntity::find()
+        .filter(AccessRequestColumn::EntityId.is_in(all_entities(db.get_ref(), &req).await?))
+        .filter(AccessRequestColumn::ArchivedAt.is_null())
+        .order_by(AccessRequestColumn::CreatedAt, Order::Desc)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(AccessRequest::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(requests))
+}
+
+pub async fn create_access_request(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    query: web::Query<LegacyScopeQuery>,
+    body: ValidatedJson<CreateAccessRequestRequest>,
+) -> Result<HttpResponse, ApiError> {
+    warn_legacy(&req);
+    let scope = scope_from_query(db.get_ref(), &req, &query).await?;
+    handlers::create_access_request(db, scope, body).await
+}
+
+async fn access_request_scope(db: &DatabaseConnection, req: &HttpRequest, id: Uuid) -> Result<EntityScope, ApiError> {
+    let access_request = AccessRequestEntity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Request not found".to_string()))?;
+    EntityScope::resolve(db, req, access_request.entity_id).await
+}
+
+pub async fn get_access_request(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    warn_legacy(&req);
+    let id = path.into_inner();
+    let scope = access_request_scope(db.get_ref(), &req, id).await?;
+    handlers::get_access_request(db, scope, web::Path::from(ScopedId { id })).await
+}
+
+pub async fn respond_to_request(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: ValidatedJson<RespondToRequestRequest>,
+) -> Result<HttpResponse, ApiError> {
+    warn_legacy(&req);
+    let id = path.into_inner();
+    let scope = access_request_scope(db.get_ref(), &req, id).await?;
+    lifecycle::ensure_writable(db.get_ref(), scope.entity_id).await?;
+    handlers::respond_to_request(db, scope, web::Path::from(ScopedId { id }), body).await
+}
+
+pub async fn list_breaches(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    query: web::Query<LegacyScopeQuery>,
+) -> Result<HttpResponse, ApiError> {
+    warn_legacy(&req);
+    if query.entity_id.is_some() {
+        let scope = scope_from_query(db.get_ref(), &req, &query).await?;
+        return handlers::list_breaches(db, scope).await;
+    }
+
+    let breaches: Vec<Breach> = BreachEntity::find()
+        .filter(BreachColumn::EntityId.is_in(all_entities(db.get_ref(), &req).await?))
+        .filter(BreachColumn::ArchivedAt.is_null())
+        .order_by(BreachColumn::DiscoveryDate, Order::Desc)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(Breach::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(breaches))
+}
+
+pub async fn create_breach(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    query: web::Query<LegacyScopeQuery>,
+    body: ValidatedJson<CreateBreachRequest>,
+) -> Result<HttpResponse, ApiError> {
+    warn_legacy(&req);
+    let scope = scope_from_query(db.get_ref(), &req, &query).await?;
+    handlers::create_breach(db, scope, body).await
+}
+
+async fn breach_scope(db: &DatabaseConnection, req: &HttpRequest, id: Uuid) -> Result<EntityScope, ApiError> {
+    let breach = BreachEntity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Breach not found".to_string()))?;
+    EntityScope::resolve(db, req, breach.entity_id).await
+}
+
+pub async fn get_breach(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    warn_legacy(&req);
+    let id = path.into_inner();
+    let scope = breach_scope(db.get_ref(), &req, id).await?;
+    handlers::get_breach(db, scope, web::Path::from(ScopedId { id })).await
+}
+
+pub async fn update_breach(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: ValidatedJson<UpdateBreachRequest>,
+) -> Result<HttpResponse, ApiError> {
+    warn_legacy(&req);
+    let id = path.into_inner();
+    let scope = breach_scope(db.get_ref(), &req, id).await?;
+    lifecycle::ensure_writable(db.get_ref(), scope.entity_id).await?;
+    handlers::update_breach(db, scope, web::Path::from(ScopedId { id }), body).await
+}
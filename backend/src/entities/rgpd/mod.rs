@@ -1,3 +1,14 @@
+pub mod attachments;
+pub mod breach_assessment;
+pub mod breach_documents;
+pub mod consents;
+pub mod dpia;
+pub mod export;
 pub mod handlers;
+pub mod legacy;
 pub mod models;
-
+pub mod processors;
+pub mod referentials;
+pub mod register;
+pub mod retention;
+pub mod taxonomy;
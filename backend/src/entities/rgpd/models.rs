@@ -1,47 +1,662 @@
 use serde::{Deserialize, Serialize};
+use sea_orm::Set;
 use sqlx::FromRow;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use validator::{Validate, ValidationError};
+use crate::validation::{non_blank_items, not_blank, one_of, validation_error};
+use crate::entities::rgpd::breach_assessment::models::BreachAssessment;
+use crate::entities::rgpd::taxonomy::{self, LEGAL_BASES, RETENTION_ACTIONS, RETENTION_TRIGGERS, SCC_MODULES, SPECIAL_CATEGORY_EXCEPTIONS, TIA_OUTCOMES};
+use crate::entities_orm::{access_request, breach, rgpd_breach_attachment, rgpd_data_category, rgpd_data_subject, rgpd_party, rgpd_register_export, rgpd_security_measure, rgpd_transfer};
 
-// Registre léger RGPD
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub const ACCESS_REQUEST_TYPES: &[&str] = &["access", "rectification", "erasure", "portability", "objection"];
+pub const ACCESS_REQUEST_STATUSES: &[&str] = &["pending", "in_progress", "completed", "rejected"];
+pub const BREACH_SEVERITIES: &[&str] = &["low", "medium", "high", "critical"];
+pub const BREACH_STATUSES: &[&str] = &["detected", "contained", "investigating", "resolved", "reported"];
+// Statuts de clôture, point de départ des durées de conservation
+pub const ACCESS_REQUEST_CLOSED_STATUSES: &[&str] = &["completed", "rejected"];
+pub const BREACH_CLOSED_STATUSES: &[&str] = &["resolved", "reported"];
+pub const RECORD_TYPES: &[&str] = &["controller", "processor"];
+pub const PARTY_CATEGORIES: &[&str] = &["internal", "organisation", "public_authority", "individual"];
+pub const PARTY_ROLES: &[&str] = &["controller", "joint_controller", "representative", "dpo", "processor", "recipient"];
+pub const REGISTER_EXPORT_FORMATS: &[&str] = &["pdf", "odt", "docx", "xlsx", "ods", "jsonld"];
+pub const TRANSFER_SAFEGUARDS: &[&str] = &["adequacy", "scc", "bcr", "derogation", "other"];
+pub const SECURITY_MEASURE_CATEGORIES: &[&str] = &[
+    "access_control", "encryption", "pseudonymisation", "backup", "logging", "network", "physical", "organisational", "other",
+];
+pub const CATALOGUE_ITEM_TYPES: &[&str] = &["endpoint", "license_key", "software_version", "encryption_algorithm"];
+
+fn validate_request_type(value: &str) -> Result<(), ValidationError> {
+    one_of(value, ACCESS_REQUEST_TYPES)
+}
+
+fn validate_request_status(value: &str) -> Result<(), ValidationError> {
+    one_of(value, ACCESS_REQUEST_STATUSES)
+}
+
+fn validate_severity(value: &str) -> Result<(), ValidationError> {
+    one_of(value, BREACH_SEVERITIES)
+}
+
+fn validate_breach_status(value: &str) -> Result<(), ValidationError> {
+    one_of(value, BREACH_STATUSES)
+}
+
+fn validate_record_type(value: &str) -> Result<(), ValidationError> {
+    one_of(value, RECORD_TYPES)
+}
+
+fn validate_party_category(value: &str) -> Result<(), ValidationError> {
+    one_of(value, PARTY_CATEGORIES)
+}
+
+fn validate_party_role(value: &str) -> Result<(), ValidationError> {
+    one_of(value, PARTY_ROLES)
+}
+
+fn validate_safeguard(value: &str) -> Result<(), ValidationError> {
+    one_of(value, TRANSFER_SAFEGUARDS)
+}
+
+fn validate_measure_category(value: &str) -> Result<(), ValidationError> {
+    one_of(value, SECURITY_MEASURE_CATEGORIES)
+}
+
+fn validate_catalogue_item_type(value: &str) -> Result<(), ValidationError> {
+    one_of(value, CATALOGUE_ITEM_TYPES)
+}
+
+fn validate_legal_basis(value: &str) -> Result<(), ValidationError> {
+    one_of(value, &taxonomy::keys(LEGAL_BASES))
+}
+
+fn validate_special_category_exception(value: &str) -> Result<(), ValidationError> {
+    one_of(value, &taxonomy::keys(SPECIAL_CATEGORY_EXCEPTIONS))
+}
+
+fn validate_retention_trigger(value: &str) -> Result<(), ValidationError> {
+    one_of(value, &taxonomy::choice_keys(RETENTION_TRIGGERS))
+}
+
+fn validate_retention_action(value: &str) -> Result<(), ValidationError> {
+    one_of(value, &taxonomy::choice_keys(RETENTION_ACTIONS))
+}
+
+fn validate_scc_module(value: &str) -> Result<(), ValidationError> {
+    one_of(value, &taxonomy::choice_keys(SCC_MODULES))
+}
+
+fn validate_tia_outcome(value: &str) -> Result<(), ValidationError> {
+    one_of(value, &taxonomy::choice_keys(TIA_OUTCOMES))
+}
+
+fn validate_data_category_kind(value: &str) -> Result<(), ValidationError> {
+    let kinds: Vec<&str> = taxonomy::DATA_CATEGORY_KINDS.iter().map(|k| k.key).collect();
+    one_of(value, &kinds)
+}
+
+/// Code pays ISO 3166-1 alpha-2, en majuscules.
+pub fn validate_country(value: &str) -> Result<(), ValidationError> {
+    if value.len() == 2 && value.bytes().all(|b| b.is_ascii_uppercase()) {
+        Ok(())
+    } else {
+        Err(validation_error("invalid_country", "Must be an ISO 3166-1 alpha-2 country code"))
+    }
+}
+
+/// Mentions obligatoires d'une fiche selon le registre tenu : base légale,
+/// catégories de données et de personnes pour le responsable (Art. 30(1)),
+/// responsable pour le compte duquel agit le sous-traitant (Art. 30(2)).
+pub fn check_record(
+    record_type: &str,
+    legal_basis: Option<&str>,
+    data_categories: usize,
+    data_subjects: usize,
+    parties: &[RegisterPartyInput],
+) -> Result<(), ValidationError> {
+    if record_type == "processor" {
+        if legal_basis.is_some() {
+            return Err(validation_error("legal_basis_not_applicable", "legal_basis only applies to controller records"));
+        }
+        if !parties.iter().any(|p| p.role == "controller") {
+            return Err(validation_error("controller_required", "A processor record must name at least one controller"));
+        }
+        return Ok(());
+    }
+    if legal_basis.is_none() {
+        return Err(validation_error("legal_basis_required", "legal_basis is required for controller records"));
+    }
+    if data_categories == 0 {
+        return Err(validation_error("data_categories_required", "At least one data category is required"));
+    }
+    if data_subjects == 0 {
+        return Err(validation_error("data_subjects_required", "At least one data subject category is required"));
+    }
+    Ok(())
+}
+
+fn validate_create_register_entry(req: &CreateRegisterEntryRequest) -> Result<(), ValidationError> {
+    check_record(
+        req.record_type.as_deref().unwrap_or("controller"),
+        req.legal_basis.as_deref(),
+        req.data_category_ids.len(),
+        req.data_subject_ids.len(),
+        &req.parties,
+    )
+}
+
+// Les dérogations (Art. 49) et garanties atypiques doivent être documentées
+// Seul un pays tiers donne lieu à transfert ; l'adéquation doit exister, le
+// module des clauses types accompagne les clauses types et elles seules
+pub fn validate_transfer(req: &TransferInput) -> Result<(), ValidationError> {
+    if !taxonomy::is_third_country(&req.country) {
+        return Err(validation_error("not_a_third_country", "Transfers within the EEA are not third-country transfers"));
+    }
+    if req.safeguard == "adequacy" && taxonomy::find_adequacy(&req.country).is_none() {
+        return Err(validation_error("no_adequacy_decision", "No adequacy decision covers this country"));
+    }
+    if matches!(req.safeguard.as_str(), "derogation" | "other")
+        && req.safeguard_details.as_deref().is_none_or(|d| d.trim().is_empty())
+    {
+        return Err(validation_error("safeguard_details_required", "safeguard_details is required for derogations and other safeguards"));
+    }
+    match (req.safeguard.as_str(), &req.scc_module) {
+        ("scc", None) => return Err(validation_error("scc_module_required", "scc_module is required for standard contractual clauses")),
+        (safeguard, Some(_)) if safeguard != "scc" => {
+            return Err(validation_error("scc_module_not_applicable", "scc_module only applies to standard contractual clauses"));
+        }
+        _ => {}
+    }
+    if req.tia_outcome.as_deref() == Some("adequate_with_measures")
+        && req.supplementary_measures.as_deref().is_none_or(|m| m.trim().is_empty())
+    {
+        return Err(validation_error("supplementary_measures_required", "supplementary_measures is required when the assessment relies on them"));
+    }
+    Ok(())
+}
+
+fn validate_security_measure(req: &SecurityMeasureRequest) -> Result<(), ValidationError> {
+    if req.catalogue_item_type.is_some() != req.catalogue_item_id.is_some() {
+        return Err(validation_error("incomplete_catalogue_item", "catalogue_item_type and catalogue_item_id go together"));
+    }
+    Ok(())
+}
+
+pub fn check_breach_dates(
+    breach_date: Option<DateTime<Utc>>,
+    discovery_date: Option<DateTime<Utc>>,
+    notification_date: Option<DateTime<Utc>>,
+) -> Result<(), ValidationError> {
+    if let (Some(breach), Some(discovery)) = (breach_date, discovery_date)
+        && breach > discovery
+    {
+        return Err(validation_error("breach_after_discovery", "breach_date must not be after discovery_date"));
+    }
+    if let Some(discovery) = discovery_date
+        && discovery > Utc::now()
+    {
+        return Err(validation_error("discovery_in_future", "discovery_date must not be in the future"));
+    }
+    if let (Some(discovery), Some(notification)) = (discovery_date, notification_date)
+        && notification < discovery
+    {
+        return Err(validation_error("notification_before_discovery", "notification_date must not be before discovery_date"));
+    }
+    Ok(())
+}
+
+fn validate_create_breach(req: &CreateBreachRequest) -> Result<(), ValidationError> {
+    check_breach_dates(Some(req.breach_date), Some(req.discovery_date), None)
+}
+
+// Pour une mise à jour partielle, seules les dates fournies sont comparées ;
+// la cohérence avec les valeurs stockées est vérifiée par le handler.
+fn validate_update_breach(req: &UpdateBreachRequest) -> Result<(), ValidationError> {
+    check_breach_dates(req.breach_date, req.discovery_date, req.notification_date)
+}
+
+// Registre des activités de traitement (Art. 30), tenu en qualité de
+// responsable (30(1)) ou de sous-traitant (30(2))
+#[derive(Debug, Serialize, Deserialize)]
 pub struct RegisterEntry {
     pub id: Uuid,
     pub entity_id: Uuid,
+    pub record_type: String, // "controller", "processor"
     pub processing_name: String,
     pub purpose: String,
-    pub legal_basis: String,
-    pub data_categories: Vec<String>,
-    pub data_subjects: Vec<String>,
-    pub recipients: Vec<String>,
+    pub legal_basis: Option<String>, // Article 6, registre du responsable uniquement
+    pub legal_basis_details: Option<String>, // Précision, par exemple l'intérêt légitime poursuivi
+    pub special_category_exception: Option<String>, // Exception de l'article 9(2)
+    pub criminal_data_basis: Option<String>, // Texte autorisant le traitement (Art. 10)
+    pub consent_mechanism: Option<String>, // Recueil, preuve et retrait du consentement
+    pub data_categories: Vec<DataCategoryRef>,
+    pub data_subjects: Vec<ReferentialRef>,
+    pub parties: Vec<RegisterPartyRef>, // Responsables, DPO, sous-traitants, destinataires...
+    pub transfers: Vec<Transfer>,
+    pub measures: Vec<SecurityMeasureRef>,
     pub retention_period: Option<String>,
-    pub security_measures: Option<String>,
+    pub retention_months: Option<i32>, // Durée comparée aux durées recommandées des catégories
+    pub retention_trigger: Option<String>, // Événement à partir duquel la durée court
+    pub retention_action: Option<String>, // Sort des données à l'échéance : "delete", "anonymize", "archive"
+    pub security_measures: Option<String>, // Description générale (Art. 30(1)(g))
+    pub shared_with_subsidiaries: bool, // Repris en lecture seule dans le registre des filiales
+    pub dpia: Option<DpiaRef>, // Analyse d'impact de la fiche, le cas échéant
+    pub consents: Option<ConsentStats>, // Registre des consentements, traitements fondés sur le consentement
+    pub checks: Vec<RegisterCheck>, // Règles de cohérence non respectées
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DpiaRef {
+    pub id: Uuid,
+    pub status: String,
+    pub version: i32,
+}
+
+/// Statistiques du registre des consentements d'une fiche, en nombre de
+/// personnes (empreintes distinctes).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConsentStats {
+    pub current_version: Option<i32>, // Dernière version du texte de consentement
+    pub subjects: i64,
+    pub active: i64, // Au moins un consentement non retiré
+    pub withdrawn: i64, // Tous les consentements retirés
+    pub active_on_current_version: i64,
+    pub last_consented_at: Option<DateTime<Utc>>,
+    pub last_withdrawn_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DataCategoryRef {
+    pub id: Uuid,
+    pub name: String,
+    pub kind: String,
+    pub special: Option<String>, // "art9", "art10"
+    pub retention_ceiling_months: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterCheck {
+    pub rule: String,
+    pub severity: String, // "error", "warning"
+    pub field: Option<String>,
+    pub data_category_id: Option<Uuid>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReferentialRef {
+    pub id: Uuid,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterPartyRef {
+    pub party_id: Uuid,
+    pub name: String,
+    pub role: String,
+    pub country: Option<String>,
+    pub processor: Option<ProcessorRef>, // Fiche fournisseur du tiers, le cas échéant
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessorRef {
+    pub id: Uuid,
+    pub dpa_status: String, // "valid", "expiring", "expired", "missing"
+    pub transfer_countries: Vec<String>, // Pays couverts par les transferts de la fiche fournisseur
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Transfer {
+    pub id: Uuid,
+    pub party_id: Option<Uuid>,
+    pub party_name: Option<String>,
+    pub country: String,
+    pub safeguard: String, // "adequacy", "scc", "bcr", "derogation", "other"
+    pub safeguard_details: Option<String>,
+    pub scc_module: Option<String>, // "module_1" à "module_4"
+    pub supplementary_measures: Option<String>,
+    pub tia_outcome: Option<String>, // "adequate", "adequate_with_measures", "not_adequate"
+    pub tia_notes: Option<String>,
+    pub tia_assessed_at: Option<DateTime<Utc>>,
+}
+
+impl Transfer {
+    pub fn from_model(t: &rgpd_transfer::Model, party_name: Option<String>) -> Self {
+        Self {
+            id: t.id,
+            party_id: t.party_id,
+            party_name,
+            country: t.country.clone(),
+            safeguard: t.safeguard.clone(),
+            safeguard_details: t.safeguard_details.clone(),
+            scc_module: t.scc_module.clone(),
+            supplementary_measures: t.supplementary_measures.clone(),
+            tia_outcome: t.tia_outcome.clone(),
+            tia_notes: t.tia_notes.clone(),
+            tia_assessed_at: t.tia_assessed_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityMeasureRef {
+    pub id: Uuid,
+    pub name: String,
+    pub category: String,
+    pub catalogue_item_type: Option<String>,
+    pub catalogue_item_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct RegisterPartyInput {
+    pub party_id: Uuid,
+    #[validate(custom(function = "validate_party_role"))]
+    pub role: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[validate(schema(function = "validate_transfer"))]
+pub struct TransferInput {
+    pub party_id: Option<Uuid>,
+    #[validate(custom(function = "validate_country"))]
+    pub country: String,
+    #[validate(custom(function = "validate_safeguard"))]
+    pub safeguard: String,
+    #[validate(length(max = 5000))]
+    pub safeguard_details: Option<String>,
+    #[validate(custom(function = "validate_scc_module"))]
+    pub scc_module: Option<String>,
+    #[validate(length(max = 5000))]
+    pub supplementary_measures: Option<String>,
+    #[validate(custom(function = "validate_tia_outcome"))]
+    pub tia_outcome: Option<String>,
+    #[validate(length(max = 5000))]
+    pub tia_notes: Option<String>,
+    pub tia_assessed_at: Option<DateTime<Utc>>,
+}
+
+impl TransferInput {
+    /// Ligne à insérer ; le transfert relève d'une fiche du registre ou d'une fiche fournisseur.
+    pub fn to_active_model(&self, register_entry_id: Option<Uuid>, processor_id: Option<Uuid>, now: DateTime<Utc>) -> rgpd_transfer::ActiveModel {
+        rgpd_transfer::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            register_entry_id: Set(register_entry_id),
+            processor_id: Set(processor_id),
+            party_id: Set(self.party_id),
+            country: Set(self.country.clone()),
+            safeguard: Set(self.safeguard.clone()),
+            safeguard_details: Set(self.safeguard_details.clone()),
+            scc_module: Set(self.scc_module.clone()),
+            supplementary_measures: Set(self.supplementary_measures.clone()),
+            tia_outcome: Set(self.tia_outcome.clone()),
+            tia_notes: Set(self.tia_notes.clone()),
+            tia_assessed_at: Set(self.tia_assessed_at),
+            created_at: Set(now),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_create_register_entry"))]
 pub struct CreateRegisterEntryRequest {
+    #[validate(custom(function = "validate_record_type"))]
+    pub record_type: Option<String>,
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
     pub processing_name: String,
+    #[validate(length(min = 1, max = 2000), custom(function = "not_blank"))]
     pub purpose: String,
-    pub legal_basis: String,
-    pub data_categories: Vec<String>,
-    pub data_subjects: Vec<String>,
-    pub recipients: Vec<String>,
+    #[validate(custom(function = "validate_legal_basis"))]
+    pub legal_basis: Option<String>,
+    #[validate(length(max = 2000))]
+    pub legal_basis_details: Option<String>,
+    #[validate(custom(function = "validate_special_category_exception"))]
+    pub special_category_exception: Option<String>,
+    #[validate(length(max = 2000))]
+    pub criminal_data_basis: Option<String>,
+    #[validate(length(max = 2000))]
+    pub consent_mechanism: Option<String>,
+    #[serde(default)]
+    pub data_category_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub data_subject_ids: Vec<Uuid>,
+    #[serde(default)]
+    #[validate(nested)]
+    pub parties: Vec<RegisterPartyInput>,
+    #[serde(default)]
+    #[validate(nested)]
+    pub transfers: Vec<TransferInput>,
+    #[serde(default)]
+    pub measure_ids: Vec<Uuid>,
+    #[validate(length(max = 255))]
     pub retention_period: Option<String>,
+    #[validate(range(min = 1, max = 1200))]
+    pub retention_months: Option<i32>,
+    #[validate(custom(function = "validate_retention_trigger"))]
+    pub retention_trigger: Option<String>,
+    #[validate(custom(function = "validate_retention_action"))]
+    pub retention_action: Option<String>,
+    #[validate(length(max = 5000))]
     pub security_measures: Option<String>,
+    pub shared_with_subsidiaries: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdateRegisterEntryRequest {
+    #[validate(custom(function = "validate_record_type"))]
+    pub record_type: Option<String>,
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
     pub processing_name: Option<String>,
+    #[validate(length(min = 1, max = 2000), custom(function = "not_blank"))]
     pub purpose: Option<String>,
+    #[validate(custom(function = "validate_legal_basis"))]
     pub legal_basis: Option<String>,
-    pub data_categories: Option<Vec<String>>,
-    pub data_subjects: Option<Vec<String>>,
-    pub recipients: Option<Vec<String>>,
+    #[validate(length(max = 2000))]
+    pub legal_basis_details: Option<String>,
+    #[validate(custom(function = "validate_special_category_exception"))]
+    pub special_category_exception: Option<String>,
+    #[validate(length(max = 2000))]
+    pub criminal_data_basis: Option<String>,
+    #[validate(length(max = 2000))]
+    pub consent_mechanism: Option<String>,
+    pub data_category_ids: Option<Vec<Uuid>>,
+    pub data_subject_ids: Option<Vec<Uuid>>,
+    #[validate(nested)]
+    pub parties: Option<Vec<RegisterPartyInput>>,
+    #[validate(nested)]
+    pub transfers: Option<Vec<TransferInput>>,
+    pub measure_ids: Option<Vec<Uuid>>,
+    #[validate(length(max = 255))]
     pub retention_period: Option<String>,
+    #[validate(range(min = 1, max = 1200))]
+    pub retention_months: Option<i32>,
+    #[validate(custom(function = "validate_retention_trigger"))]
+    pub retention_trigger: Option<String>,
+    #[validate(custom(function = "validate_retention_action"))]
+    pub retention_action: Option<String>,
+    #[validate(length(max = 5000))]
     pub security_measures: Option<String>,
+    pub shared_with_subsidiaries: Option<bool>,
+}
+
+// Référentiel des catégories de personnes concernées
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Category {
+    pub id: Uuid,
+    pub entity_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Catégorie de données rattachée à la typologie ; sans durée propre, la
+// durée recommandée pour son type s'applique
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DataCategory {
+    pub id: Uuid,
+    pub entity_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub kind: String,
+    pub special: Option<String>, // "art9", "art10"
+    pub retention_ceiling_months: Option<i32>, // Durée propre à la catégorie
+    pub recommended_retention_months: Option<i32>, // Durée recommandée pour le type
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<rgpd_data_category::Model> for DataCategory {
+    fn from(c: rgpd_data_category::Model) -> Self {
+        let kind = taxonomy::find_kind(&c.kind);
+        Self {
+            id: c.id,
+            entity_id: c.entity_id,
+            name: c.name,
+            description: c.description,
+            special: kind.and_then(|k| k.special).map(str::to_string),
+            recommended_retention_months: kind.and_then(|k| k.retention_ceiling_months),
+            kind: c.kind,
+            retention_ceiling_months: c.retention_ceiling_months,
+            created_at: c.created_at,
+            updated_at: c.updated_at,
+        }
+    }
+}
+
+impl From<rgpd_data_subject::Model> for Category {
+    fn from(c: rgpd_data_subject::Model) -> Self {
+        Self {
+            id: c.id,
+            entity_id: c.entity_id,
+            name: c.name,
+            description: c.description,
+            created_at: c.created_at,
+            updated_at: c.updated_at,
+        }
+    }
+}
+
+// Création et remplacement (PUT) des référentiels
+#[derive(Debug, Deserialize, Validate)]
+pub struct CategoryRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub name: String,
+    #[validate(length(max = 2000))]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct DataCategoryRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub name: String,
+    #[validate(length(max = 2000))]
+    pub description: Option<String>,
+    #[validate(custom(function = "validate_data_category_kind"))]
+    pub kind: Option<String>, // "other" par défaut
+    #[validate(range(min = 1, max = 1200))]
+    pub retention_ceiling_months: Option<i32>,
+}
+
+// Tiers : responsable, co-responsable, représentant, DPO, sous-traitant ou destinataire
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Party {
+    pub id: Uuid,
+    pub entity_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub category: String, // "internal", "organisation", "public_authority", "individual"
+    pub contact_name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    pub country: Option<String>, // ISO 3166-1 alpha-2
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<rgpd_party::Model> for Party {
+    fn from(p: rgpd_party::Model) -> Self {
+        Self {
+            id: p.id,
+            entity_id: p.entity_id,
+            name: p.name,
+            description: p.description,
+            category: p.category,
+            contact_name: p.contact_name,
+            email: p.email,
+            phone: p.phone,
+            address: p.address,
+            country: p.country,
+            created_at: p.created_at,
+            updated_at: p.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct PartyRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub name: String,
+    #[validate(length(max = 2000))]
+    pub description: Option<String>,
+    #[validate(custom(function = "validate_party_category"))]
+    pub category: String,
+    #[validate(length(max = 255))]
+    pub contact_name: Option<String>,
+    #[validate(email, length(max = 255))]
+    pub email: Option<String>,
+    #[validate(length(max = 50))]
+    pub phone: Option<String>,
+    #[validate(length(max = 2000))]
+    pub address: Option<String>,
+    #[validate(custom(function = "validate_country"))]
+    pub country: Option<String>,
+}
+
+// Mesure de sécurité, éventuellement adossée à un élément du catalogue
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SecurityMeasure {
+    pub id: Uuid,
+    pub entity_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub category: String,
+    pub catalogue_item_type: Option<String>, // "endpoint", "license_key", "software_version", "encryption_algorithm"
+    pub catalogue_item_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<rgpd_security_measure::Model> for SecurityMeasure {
+    fn from(m: rgpd_security_measure::Model) -> Self {
+        Self {
+            id: m.id,
+            entity_id: m.entity_id,
+            name: m.name,
+            description: m.description,
+            category: m.category,
+            catalogue_item_type: m.catalogue_item_type,
+            catalogue_item_id: m.catalogue_item_id,
+            created_at: m.created_at,
+            updated_at: m.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_security_measure"))]
+pub struct SecurityMeasureRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub name: String,
+    #[validate(length(max = 5000))]
+    pub description: Option<String>,
+    #[validate(custom(function = "validate_measure_category"))]
+    pub category: String,
+    #[validate(custom(function = "validate_catalogue_item_type"))]
+    pub catalogue_item_type: Option<String>,
+    pub catalogue_item_id: Option<Uuid>,
 }
 
 // Demandes d'accès RGPD
@@ -57,20 +672,48 @@ pub struct AccessRequest {
     pub response: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
-    pub completed_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>, // Clôture, y compris par un refus
+    pub anonymized_at: Option<DateTime<Utc>>,
+    pub archived_at: Option<DateTime<Utc>>, // Archivage intermédiaire (conservation)
 }
 
-#[derive(Debug, Deserialize)]
+impl From<access_request::Model> for AccessRequest {
+    fn from(r: access_request::Model) -> Self {
+        Self {
+            id: r.id,
+            entity_id: r.entity_id,
+            requester_name: r.requester_name,
+            requester_email: r.requester_email,
+            request_type: r.request_type,
+            description: r.description,
+            status: r.status,
+            response: r.response,
+            created_at: r.created_at,
+            updated_at: r.updated_at,
+            completed_at: r.completed_at,
+            anonymized_at: r.anonymized_at,
+            archived_at: r.archived_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateAccessRequestRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
     pub requester_name: String,
+    #[validate(email, length(max = 255))]
     pub requester_email: String,
+    #[validate(custom(function = "validate_request_type"))]
     pub request_type: String,
+    #[validate(length(max = 5000))]
     pub description: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct RespondToRequestRequest {
+    #[validate(custom(function = "validate_request_status"))]
     pub status: String,
+    #[validate(length(max = 10000))]
     pub response: Option<String>,
 }
 
@@ -92,31 +735,142 @@ pub struct Breach {
     pub subjects_notified: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub anonymized_at: Option<DateTime<Utc>>,
+    pub archived_at: Option<DateTime<Utc>>, // Archivage intermédiaire (conservation)
+    #[sqlx(skip)]
+    pub assessment: Option<BreachAssessment>, // Évaluation ENISA, si elle a été menée
 }
 
-#[derive(Debug, Deserialize)]
+// Listes stockées en jsonb
+fn string_list(value: serde_json::Value) -> Vec<String> {
+    serde_json::from_value(value).unwrap_or_default()
+}
+
+impl From<breach::Model> for Breach {
+    fn from(b: breach::Model) -> Self {
+        Self {
+            assessment: BreachAssessment::from_breach(&b),
+            id: b.id,
+            entity_id: b.entity_id,
+            breach_date: b.breach_date,
+            discovery_date: b.discovery_date,
+            description: b.description,
+            data_categories_affected: string_list(b.data_categories_affected),
+            number_of_subjects: b.number_of_subjects,
+            severity: b.severity,
+            status: b.status,
+            containment_measures: b.containment_measures,
+            notification_date: b.notification_date,
+            authority_notified: b.authority_notified,
+            subjects_notified: b.subjects_notified,
+            created_at: b.created_at,
+            updated_at: b.updated_at,
+            closed_at: b.closed_at,
+            anonymized_at: b.anonymized_at,
+            archived_at: b.archived_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_create_breach"))]
 pub struct CreateBreachRequest {
     pub breach_date: DateTime<Utc>,
     pub discovery_date: DateTime<Utc>,
+    #[validate(length(min = 1, max = 10000), custom(function = "not_blank"))]
     pub description: String,
+    #[validate(custom(function = "non_blank_items"))]
     pub data_categories_affected: Vec<String>,
+    #[validate(range(min = 0))]
     pub number_of_subjects: Option<i32>,
+    #[validate(custom(function = "validate_severity"))]
     pub severity: String,
+    #[validate(length(max = 10000))]
     pub containment_measures: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_update_breach"))]
 pub struct UpdateBreachRequest {
     pub breach_date: Option<DateTime<Utc>>,
     pub discovery_date: Option<DateTime<Utc>>,
+    #[validate(length(min = 1, max = 10000), custom(function = "not_blank"))]
     pub description: Option<String>,
+    #[validate(custom(function = "non_blank_items"))]
     pub data_categories_affected: Option<Vec<String>>,
+    #[validate(range(min = 0))]
     pub number_of_subjects: Option<i32>,
+    #[validate(custom(function = "validate_severity"))]
     pub severity: Option<String>,
+    #[validate(custom(function = "validate_breach_status"))]
     pub status: Option<String>,
+    #[validate(length(max = 10000))]
     pub containment_measures: Option<String>,
     pub notification_date: Option<DateTime<Utc>>,
     pub authority_notified: Option<bool>,
     pub subjects_notified: Option<bool>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct BreachAttachment {
+    pub id: Uuid,
+    pub breach_id: Uuid,
+    pub file_name: String,
+    pub content_type: Option<String>,
+    pub file_size: i64,
+    pub uploaded_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<rgpd_breach_attachment::Model> for BreachAttachment {
+    fn from(model: rgpd_breach_attachment::Model) -> Self {
+        Self {
+            id: model.id,
+            breach_id: model.breach_id,
+            file_name: model.file_name,
+            content_type: model.content_type,
+            file_size: model.file_size,
+            uploaded_by: model.uploaded_by,
+            created_at: model.created_at,
+        }
+    }
+}
+
+/// Chemin d'une pièce jointe d'une violation.
+#[derive(Debug, Deserialize)]
+pub struct BreachAttachmentPath {
+    pub id: Uuid,
+    pub item_id: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterExportQuery {
+    pub format: Option<String>, // "pdf" par défaut
+}
+
+/// Édition du registre : chaque export reçoit le numéro de version suivant.
+#[derive(Debug, Serialize)]
+pub struct RegisterExport {
+    pub id: Uuid,
+    pub version: i32,
+    pub format: String,
+    pub entry_count: i32,
+    pub sha256: String,
+    pub generated_by: Option<Uuid>,
+    pub generated_at: DateTime<Utc>,
+}
+
+impl From<rgpd_register_export::Model> for RegisterExport {
+    fn from(model: rgpd_register_export::Model) -> Self {
+        Self {
+            id: model.id,
+            version: model.version,
+            format: model.format,
+            entry_count: model.entry_count,
+            sha256: model.sha256,
+            generated_by: model.generated_by,
+            generated_at: model.generated_at,
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter, Set, TransactionTrait};
+use uuid::Uuid;
+use crate::entities::rgpd::processors::{entity_alerts, find_processor, load, load_one};
+use crate::entities::rgpd::processors::models::*;
+use crate::entities::scope::{EntityScope, ScopedId};
+use crate::errors::ApiError;
+use crate::validation::ValidatedJson;
+use crate::entities_orm::rgpd_processor::{Entity as ProcessorEntity, Column as ProcessorColumn, ActiveModel as ProcessorActiveModel};
+use crate::entities_orm::rgpd_sub_processor::{self, Entity as SubProcessorEntity, Column as SubProcessorColumn};
+use crate::entities_orm::rgpd_processor_endpoint::{self, Entity as ProcessorEndpointEntity, Column as ProcessorEndpointColumn};
+use crate::entities_orm::rgpd_transfer::{Entity as TransferEntity, Column as TransferColumn};
+use crate::entities_orm::rgpd_party::{Entity as PartyEntity, Column as PartyColumn};
+use crate::entities_orm::endpoint::{Entity as EndpointEntity, Column as EndpointColumn};
+
+fn unknown_reference(field: &str) -> ApiError {
+    ApiError::validation(field, "unknown_reference", "References an item that does not exist in this entity")
+}
+
+/// Vérifie les tiers (fournisseur et sous-traitants ultérieurs) et les
+/// équipements cités, et l'unicité de la fiche fournisseur par tiers.
+async fn check_references(db: &DatabaseConnection, scope: &EntityScope, body: &ProcessorRequest, except: Option<Uuid>) -> Result<(), ApiError> {
+    let found = PartyEntity::find_by_id(body.party_id)
+        .filter(PartyColumn::EntityId.eq(scope.entity_id))
+        .count(db)
+        .await?;
+    if found == 0 {
+        return Err(unknown_reference("party_id"));
+    }
+    let mut existing = ProcessorEntity::find()
+        .filter(ProcessorColumn::PartyId.eq(body.party_id));
+    if let Some(id) = except {
+        existing = existing.filter(ProcessorColumn::Id.ne(id));
+    }
+    if existing.count(db).await? > 0 {
+        return Err(ApiError::Conflict("A processor record already exists for this party".to_string()));
+    }
+
+    let sub_ids: Vec<Uuid> = body.sub_processors.iter().map(|s| s.party_id).collect();
+    if !sub_ids.is_empty() {
+        let found = PartyEntity::find()
+            .filter(PartyColumn::EntityId.eq(scope.entity_id))
+            .filter(PartyColumn::Id.is_in(sub_ids.clone()))
+            .count(db)
+            .await?;
+        if found as usize != sub_ids.len() {
+            return Err(unknown_reference("sub_processors"));
+        }
+    }
+
+    // Équipements partagés ou propres à l'entité, comme pour les mesures de sécurité
+    let endpoint_ids: HashSet<Uuid> = body.endpoint_ids.iter().copied().collect();
+    if !endpoint_ids.is_empty() {
+        let found = EndpointEntity::find()
+            .filter(EndpointColumn::Id.is_in(endpoint_ids.iter().copied()))
+            .filter(EndpointColumn::EntityId.is_null().or(EndpointColumn::EntityId.eq(scope.entity_id)))
+            .count(db)
+            .await?;
+        if found as usize != endpoint_ids.len() {
+            return Err(unknown_reference("endpoint_ids"));
+        }
+    }
+    Ok(())
+}
+
+/// Remplace les sous-traitants ultérieurs, les transferts et les équipements de la fiche.
+async fn save_links<C: ConnectionTrait>(db: &C, processor_id: Uuid, body: &ProcessorRequest) -> Result<(), DbErr> {
+    SubProcessorEntity::delete_many()
+        .filter(SubProcessorColumn::ProcessorId.eq(processor_id))
+        .exec(db)
+        .await?;
+    let now = Utc::now();
+    let subs: Vec<_> = body.sub_processors.iter()
+        .map(|s| rgpd_sub_processor::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            processor_id: Set(processor_id),
+            party_id: Set(s.party_id),
+            service: Set(s.service.clone()),
+            authorised_at: Set(s.authorised_at),
+            created_at: Set(now),
+        })
+        .collect();
+    if !subs.is_empty() {
+        SubProcessorEntity::insert_many(subs).exec(db).await?;
+    }
+
+    TransferEntity::delete_many()
+        .filter(TransferColumn::ProcessorId.eq(processor_id))
+        .exec(db)
+        .await?;
+    let transfers: Vec<_> = body.transfers.iter()
+        .map(|t| t.to_active_model(None, Some(processor_id), now))
+        .collect();
+    if !transfers.is_empty() {
+        TransferEntity::insert_many(transfers).exec(db).await?;
+    }
+
+    ProcessorEndpointEntity::delete_many()
+        .filter(ProcessorEndpointColumn::ProcessorId.eq(processor_id))
+        .exec(db)
+        .await?;
+    let mut seen = HashSet::new();
+    let endpoints: Vec<_> = body.endpoint_ids.iter()
+        .filter(|id| seen.insert(**id))
+        .map(|id| rgpd_processor_endpoint::ActiveModel {
+            processor_id: Set(processor_id),
+            endpoint_id: Set(*id),
+        })
+        .collect();
+    if !endpoints.is_empty() {
+        ProcessorEndpointEntity::insert_many(endpoints).exec(db).await?;
+    }
+    Ok(())
+}
+
+pub async fn list_processors(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    let processors = ProcessorEntity::find()
+        .filter(ProcessorColumn::EntityId.eq(scope.entity_id))
+        .all(db.get_ref())
+        .await?;
+    let mut processors = load(db.get_ref(), processors).await?;
+    processors.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(HttpResponse::Ok().json(processors))
+}
+
+pub async fn create_processor(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    body: ValidatedJson<ProcessorRequest>,
+) -> Result<HttpResponse, ApiError> {
+    check_references(db.get_ref(), &scope, &body, None).await?;
+
+    let now = Utc::now();
+    let txn = db.begin().await?;
+    let processor = ProcessorActiveModel {
+        id: Set(Uuid::new_v4()),
+        entity_id: Set(scope.entity_id),
+        party_id: Set(body.party_id),
+        services: Set(body.services.clone()),
+        dpa_reference: Set(body.dpa_reference.clone()),
+        dpa_signed_at: Set(body.dpa_signed_at),
+        dpa_expires_at: Set(body.dpa_expires_at),
+        security_score: Set(body.security_score),
+        security_assessed_at: Set(body.security_assessed_at),
+        notes: Set(body.notes.clone()),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(&txn)
+    .await?;
+    save_links(&txn, processor.id, &body).await?;
+    txn.commit().await?;
+
+    Ok(HttpResponse::Created().json(load_one(db.get_ref(), processor).await?))
+}
+
+pub async fn get_processor(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+) -> Result<HttpResponse, ApiError> {
+    let processor = find_processor(db.get_ref(), &scope, path.id).await?;
+    Ok(HttpResponse::Ok().json(load_one(db.get_ref(), processor).await?))
+}
+
+/// Remplace la fiche, sous-traitants ultérieurs, transferts et équipements compris.
+pub async fn update_processor(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<ProcessorRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let processor = find_processor(db.get_ref(), &scope, path.id).await?;
+    check_references(db.get_ref(), &scope, &body, Some(processor.id)).await?;
+
+    let txn = db.begin().await?;
+    let mut processor = processor.into_active_model();
+    processor.party_id = Set(body.party_id);
+    processor.services = Set(body.services.clone());
+    processor.dpa_reference = Set(body.dpa_reference.clone());
+    processor.dpa_signed_at = Set(body.dpa_signed_at);
+    processor.dpa_expires_at = Set(body.dpa_expires_at);
+    processor.security_score = Set(body.security_score);
+    processor.security_assessed_at = Set(body.security_assessed_at);
+    processor.notes = Set(body.notes.clone());
+    processor.updated_at = Set(Utc::now());
+    let processor = processor.update(&txn).await?;
+    save_links(&txn, processor.id, &body).await?;
+    txn.commit().await?;
+
+    Ok(HttpResponse::Ok().json(load_one(db.get_ref(), processor).await?))
+}
+
+// Le tiers reste au référentiel ; seule la fiche fournisseur disparaît
+pub async fn delete_processor(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+) -> Result<HttpResponse, ApiError> {
+    let processor = find_processor(db.get_ref(), &scope, path.id).await?;
+    ProcessorEntity::delete_by_id(processor.id)
+        .exec(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn list_alerts(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(entity_alerts(db.get_ref(), scope.entity_id).await?))
+}
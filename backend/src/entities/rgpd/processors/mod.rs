@@ -0,0 +1,277 @@
+//! Registre des sous-traitants (Art. 28) : fiche fournisseur d'un tiers avec
+//! contrat de sous-traitance, encadrement des transferts, évaluation de
+//! sécurité, sous-traitants ultérieurs et équipements du catalogue hébergés.
+//! Les fiches du registre y renvoient par leurs tiers de rôle `processor`.
+pub mod handlers;
+pub mod models;
+
+use std::collections::{HashMap, HashSet};
+use chrono::Utc;
+use sea_orm::{ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder};
+use uuid::Uuid;
+use crate::entities::rgpd::models::{ProcessorRef, Transfer};
+use crate::entities::rgpd::taxonomy;
+use crate::entities::scope::EntityScope;
+use crate::errors::ApiError;
+use crate::entities_orm::rgpd_processor::{self, Entity as ProcessorEntity, Column as ProcessorColumn};
+use crate::entities_orm::rgpd_sub_processor::{Entity as SubProcessorEntity, Column as SubProcessorColumn};
+use crate::entities_orm::rgpd_processor_endpoint::{Entity as ProcessorEndpointEntity, Column as ProcessorEndpointColumn};
+use crate::entities_orm::rgpd_transfer::{Entity as TransferEntity, Column as TransferColumn};
+use crate::entities_orm::rgpd_party::{self, Entity as PartyEntity, Column as PartyColumn};
+use crate::entities_orm::endpoint::{Entity as EndpointEntity, Column as EndpointColumn};
+use crate::entities_orm::register_entry::{Entity as RegisterEntryEntity, Column as RegisterEntryColumn};
+use crate::entities_orm::register_party::{Entity as RegisterPartyEntity, Column as RegisterPartyColumn};
+use models::*;
+
+pub const PROCESSOR_ROLE: &str = "processor";
+
+// Une fiche d'une autre entité est traitée comme inexistante
+pub async fn find_processor(db: &DatabaseConnection, scope: &EntityScope, id: Uuid) -> Result<rgpd_processor::Model, ApiError> {
+    ProcessorEntity::find_by_id(id)
+        .filter(ProcessorColumn::EntityId.eq(scope.entity_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Processor not found".to_string()))
+}
+
+/// Fiches fournisseur des tiers donnés, pour les fiches du registre.
+pub async fn refs_by_party<C: ConnectionTrait>(db: &C, party_ids: impl IntoIterator<Item = Uuid>) -> Result<HashMap<Uuid, ProcessorRef>, DbErr> {
+    let now = Utc::now();
+    let processors = ProcessorEntity::find()
+        .filter(ProcessorColumn::PartyId.is_in(party_ids))
+        .all(db)
+        .await?;
+    let transfers = TransferEntity::find()
+        .filter(TransferColumn::ProcessorId.is_in(processors.iter().map(|p| p.id)))
+        .all(db)
+        .await?;
+    Ok(processors.into_iter()
+        .map(|p| (p.party_id, ProcessorRef {
+            id: p.id,
+            dpa_status: dpa_status(p.dpa_signed_at, p.dpa_expires_at, now).to_string(),
+            transfer_countries: transfers.iter()
+                .filter(|t| t.processor_id == Some(p.id))
+                .map(|t| t.country.clone())
+                .collect(),
+        }))
+        .collect())
+}
+
+fn alert(code: &str, severity: &str, processor: &Processor, message: String) -> ProcessorAlert {
+    ProcessorAlert {
+        code: code.to_string(),
+        severity: severity.to_string(),
+        processor_id: Some(processor.id),
+        party_id: processor.party_id,
+        party_name: processor.name.clone(),
+        message,
+    }
+}
+
+/// Points d'attention d'une fiche fournisseur ; l'absence ou l'expiration du
+/// contrat est bloquante au regard de l'article 28(3).
+pub fn processor_alerts(processor: &Processor) -> Vec<ProcessorAlert> {
+    let mut alerts = Vec::new();
+    match processor.dpa_status.as_str() {
+        DPA_MISSING => alerts.push(alert("dpa_missing", "error", processor,
+            "No signed data processing agreement is recorded".to_string())),
+        DPA_EXPIRED => alerts.push(alert("dpa_expired", "error", processor,
+            "The data processing agreement has expired".to_string())),
+        DPA_EXPIRING => alerts.push(alert("dpa_expiring", "warning", processor, format!(
+            "The data processing agreement expires within {} days", DPA_EXPIRY_WARNING_DAYS,
+        ))),
+        _ => {}
+    }
+    // Fournisseur et sous-traitants ultérieurs situés dans un pays sans adéquation
+    let recipients = std::iter::once((processor.party_id, processor.name.as_str(), processor.country.as_deref()))
+        .chain(processor.sub_processors.iter().map(|s| (s.party_id, s.name.as_str(), s.country.as_deref())));
+    for (party_id, name, country) in recipients {
+        if let Some(country) = country.filter(|c| taxonomy::needs_transfer_tool(c))
+            && !taxonomy::transfer_covers(&processor.transfers, party_id, country)
+        {
+            alerts.push(alert("transfer_mechanism_missing", "warning", processor, format!(
+                "\"{}\" is located in {}, which has no adequacy decision, and no transfer mechanism is recorded", name, country,
+            )));
+        }
+    }
+    for transfer in &processor.transfers {
+        for (code, message) in taxonomy::transfer_issues(transfer) {
+            alerts.push(alert(code, "warning", processor, message));
+        }
+    }
+    if processor.security_score.is_none() {
+        alerts.push(alert("security_assessment_missing", "warning", processor,
+            "No security assessment is recorded".to_string()));
+    }
+    for sub in processor.sub_processors.iter().filter(|s| s.authorised_at.is_none()) {
+        alerts.push(alert("sub_processor_not_authorised", "warning", processor, format!(
+            "No written authorisation is recorded for sub-processor \"{}\"", sub.name,
+        )));
+    }
+    alerts
+}
+
+/// Construit les fiches complètes en quelques requêtes, quel que soit leur nombre.
+pub async fn load<C: ConnectionTrait>(db: &C, processors: Vec<rgpd_processor::Model>) -> Result<Vec<Processor>, DbErr> {
+    let now = Utc::now();
+    let ids: Vec<Uuid> = processors.iter().map(|p| p.id).collect();
+    let party_ids: Vec<Uuid> = processors.iter().map(|p| p.party_id).collect();
+
+    let subs = SubProcessorEntity::find()
+        .filter(SubProcessorColumn::ProcessorId.is_in(ids.clone()))
+        .order_by_asc(SubProcessorColumn::CreatedAt)
+        .all(db)
+        .await?;
+    let transfers = TransferEntity::find()
+        .filter(TransferColumn::ProcessorId.is_in(ids.clone()))
+        .order_by_asc(TransferColumn::CreatedAt)
+        .all(db)
+        .await?;
+    let endpoint_links = ProcessorEndpointEntity::find()
+        .filter(ProcessorEndpointColumn::ProcessorId.is_in(ids))
+        .all(db)
+        .await?;
+    let parties: HashMap<Uuid, rgpd_party::Model> = PartyEntity::find()
+        .filter(PartyColumn::Id.is_in(party_ids.iter().copied().chain(subs.iter().map(|s| s.party_id)).chain(transfers.iter().filter_map(|t| t.party_id))))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|p| (p.id, p))
+        .collect();
+    let endpoints: HashMap<Uuid, EndpointRef> = EndpointEntity::find()
+        .filter(EndpointColumn::Id.is_in(endpoint_links.iter().map(|l| l.endpoint_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|e| (e.id, EndpointRef { id: e.id, name: e.name, endpoint_type: e.endpoint_type, address: e.address }))
+        .collect();
+
+    // Fiches du registre citant ces tiers comme sous-traitants
+    let register_links = RegisterPartyEntity::find()
+        .filter(RegisterPartyColumn::PartyId.is_in(party_ids))
+        .filter(RegisterPartyColumn::Role.eq(PROCESSOR_ROLE))
+        .all(db)
+        .await?;
+    let entries: HashMap<Uuid, (Uuid, String)> = RegisterEntryEntity::find()
+        .filter(RegisterEntryColumn::Id.is_in(register_links.iter().map(|l| l.register_entry_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|e| (e.id, (e.entity_id, e.processing_name)))
+        .collect();
+
+    Ok(processors.into_iter().map(|p| {
+        let party = parties.get(&p.party_id);
+        let sub_processors = subs.iter()
+            .filter(|s| s.processor_id == p.id)
+            .map(|s| {
+                let sub_party = parties.get(&s.party_id);
+                SubProcessor {
+                    id: s.id,
+                    party_id: s.party_id,
+                    name: sub_party.map(|sp| sp.name.clone()).unwrap_or_default(),
+                    country: sub_party.and_then(|sp| sp.country.clone()),
+                    service: s.service.clone(),
+                    authorised_at: s.authorised_at,
+                }
+            })
+            .collect();
+        let mut processor_endpoints: Vec<EndpointRef> = endpoint_links.iter()
+            .filter(|l| l.processor_id == p.id)
+            .filter_map(|l| endpoints.get(&l.endpoint_id).cloned())
+            .collect();
+        processor_endpoints.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut register_entries: Vec<RegisterEntryRef> = register_links.iter()
+            .filter(|l| l.party_id == p.party_id)
+            .filter_map(|l| entries.get(&l.register_entry_id).map(|e| (l.register_entry_id, e)))
+            .filter(|(_, (entity_id, _))| *entity_id == p.entity_id)
+            .map(|(id, (_, name))| RegisterEntryRef { id, processing_name: name.clone() })
+            .collect();
+        register_entries.sort_by(|a, b| a.processing_name.cmp(&b.processing_name));
+
+        let mut processor = Processor {
+            id: p.id,
+            entity_id: p.entity_id,
+            party_id: p.party_id,
+            name: party.map(|pa| pa.name.clone()).unwrap_or_default(),
+            contact_name: party.and_then(|pa| pa.contact_name.clone()),
+            email: party.and_then(|pa| pa.email.clone()),
+            phone: party.and_then(|pa| pa.phone.clone()),
+            address: party.and_then(|pa| pa.address.clone()),
+            country: party.and_then(|pa| pa.country.clone()),
+            services: p.services,
+            transfers: transfers.iter()
+                .filter(|t| t.processor_id == Some(p.id))
+                .map(|t| Transfer::from_model(t, t.party_id.and_then(|id| parties.get(&id)).map(|pa| pa.name.clone())))
+                .collect(),
+            dpa_reference: p.dpa_reference,
+            dpa_status: dpa_status(p.dpa_signed_at, p.dpa_expires_at, now).to_string(),
+            dpa_signed_at: p.dpa_signed_at,
+            dpa_expires_at: p.dpa_expires_at,
+            security_score: p.security_score,
+            security_assessed_at: p.security_assessed_at,
+            notes: p.notes,
+            sub_processors,
+            endpoints: processor_endpoints,
+            register_entries,
+            alerts: Vec::new(),
+            created_at: p.created_at,
+            updated_at: p.updated_at,
+        };
+        processor.alerts = processor_alerts(&processor);
+        processor
+    }).collect())
+}
+
+pub async fn load_one<C: ConnectionTrait>(db: &C, processor: rgpd_processor::Model) -> Result<Processor, DbErr> {
+    let mut processors = load(db, vec![processor]).await?;
+    processors.pop().ok_or_else(|| DbErr::RecordNotFound("Processor".to_string()))
+}
+
+/// Alertes de l'entité : celles des fiches fournisseur, et les tiers désignés
+/// comme sous-traitants dans le registre sans fiche fournisseur.
+pub async fn entity_alerts<C: ConnectionTrait>(db: &C, entity_id: Uuid) -> Result<Vec<ProcessorAlert>, DbErr> {
+    let processors = ProcessorEntity::find()
+        .filter(ProcessorColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    let registered: HashSet<Uuid> = processors.iter().map(|p| p.party_id).collect();
+    let mut alerts: Vec<ProcessorAlert> = load(db, processors).await?
+        .into_iter()
+        .flat_map(|p| p.alerts)
+        .collect();
+
+    let entry_ids: Vec<Uuid> = RegisterEntryEntity::find()
+        .filter(RegisterEntryColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
+    let unregistered: HashSet<Uuid> = RegisterPartyEntity::find()
+        .filter(RegisterPartyColumn::RegisterEntryId.is_in(entry_ids))
+        .filter(RegisterPartyColumn::Role.eq(PROCESSOR_ROLE))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|l| l.party_id)
+        .filter(|id| !registered.contains(id))
+        .collect();
+    let mut parties = PartyEntity::find()
+        .filter(PartyColumn::Id.is_in(unregistered))
+        .all(db)
+        .await?;
+    parties.sort_by(|a, b| a.name.cmp(&b.name));
+    alerts.extend(parties.into_iter().map(|party| ProcessorAlert {
+        code: "processor_not_registered".to_string(),
+        severity: "warning".to_string(),
+        processor_id: None,
+        party_id: party.id,
+        message: format!("\"{}\" acts as a processor in the register but has no processor record", party.name),
+        party_name: party.name,
+    }));
+
+    // Les alertes bloquantes d'abord
+    alerts.sort_by_key(|a| a.severity != "error");
+    Ok(alerts)
+}
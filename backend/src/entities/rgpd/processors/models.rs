@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::{Validate, ValidationError};
+use crate::validation::validation_error;
+use crate::entities::rgpd::models::{Transfer, TransferInput};
+
+pub const DPA_VALID: &str = "valid";
+pub const DPA_EXPIRING: &str = "expiring";
+pub const DPA_EXPIRED: &str = "expired";
+pub const DPA_MISSING: &str = "missing";
+/// Délai avant l'échéance du contrat à partir duquel une alerte est levée.
+pub const DPA_EXPIRY_WARNING_DAYS: i64 = 60;
+
+// Dates cohérentes, sous-traitants ultérieurs distincts du fournisseur,
+// transferts vers le fournisseur ou l'un de ses sous-traitants ultérieurs
+fn validate_processor(req: &ProcessorRequest) -> Result<(), ValidationError> {
+    if req.dpa_expires_at.is_some() && req.dpa_signed_at.is_none() {
+        return Err(validation_error("dpa_signed_at_required", "A DPA expiry date requires its signature date"));
+    }
+    if let (Some(signed), Some(expires)) = (req.dpa_signed_at, req.dpa_expires_at)
+        && expires < signed
+    {
+        return Err(validation_error("invalid_dpa_dates", "dpa_expires_at cannot precede dpa_signed_at"));
+    }
+    if req.security_score.is_some() && req.security_assessed_at.is_none() {
+        return Err(validation_error("security_assessed_at_required", "A security score requires its assessment date"));
+    }
+    for (i, sub) in req.sub_processors.iter().enumerate() {
+        if sub.party_id == req.party_id {
+            return Err(validation_error("invalid_sub_processor", "A processor cannot be its own sub-processor"));
+        }
+        if req.sub_processors[..i].iter().any(|s| s.party_id == sub.party_id) {
+            return Err(validation_error("duplicate", "Each sub-processor may only be listed once"));
+        }
+    }
+    let recipients: Vec<Uuid> = std::iter::once(req.party_id).chain(req.sub_processors.iter().map(|s| s.party_id)).collect();
+    if req.transfers.iter().filter_map(|t| t.party_id).any(|id| !recipients.contains(&id)) {
+        return Err(validation_error("invalid_transfer_party", "A transfer must go to the processor or one of its sub-processors"));
+    }
+    Ok(())
+}
+
+/// État du contrat de sous-traitance (Art. 28(3)) à une date donnée.
+pub fn dpa_status(signed_at: Option<DateTime<Utc>>, expires_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> &'static str {
+    match (signed_at, expires_at) {
+        (None, _) => DPA_MISSING,
+        (Some(_), Some(expires)) if expires <= now => DPA_EXPIRED,
+        (Some(_), Some(expires)) if expires <= now + chrono::Duration::days(DPA_EXPIRY_WARNING_DAYS) => DPA_EXPIRING,
+        (Some(_), _) => DPA_VALID,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Processor {
+    pub id: Uuid,
+    pub entity_id: Uuid,
+    pub party_id: Uuid,
+    pub name: String,
+    pub contact_name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    pub country: Option<String>, // Pays du tiers, ISO 3166-1 alpha-2
+    pub services: Option<String>,
+    pub transfers: Vec<Transfer>, // Transferts hors EEE vers le fournisseur ou ses sous-traitants ultérieurs
+    pub dpa_reference: Option<String>,
+    pub dpa_signed_at: Option<DateTime<Utc>>,
+    pub dpa_expires_at: Option<DateTime<Utc>>,
+    pub dpa_status: String, // "valid", "expiring", "expired", "missing"
+    pub security_score: Option<i32>, // 0 à 100
+    pub security_assessed_at: Option<DateTime<Utc>>,
+    pub notes: Option<String>,
+    pub sub_processors: Vec<SubProcessor>,
+    pub endpoints: Vec<EndpointRef>,
+    pub register_entries: Vec<RegisterEntryRef>, // Fiches citant le tiers comme sous-traitant
+    pub alerts: Vec<ProcessorAlert>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubProcessor {
+    pub id: Uuid,
+    pub party_id: Uuid,
+    pub name: String,
+    pub country: Option<String>,
+    pub service: Option<String>,
+    pub authorised_at: Option<DateTime<Utc>>, // Autorisation écrite du responsable (Art. 28(2))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointRef {
+    pub id: Uuid,
+    pub name: String,
+    pub endpoint_type: String,
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterEntryRef {
+    pub id: Uuid,
+    pub processing_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessorAlert {
+    pub code: String, // "dpa_missing", "dpa_expired", "transfer_mechanism_missing", "tia_missing"...
+    pub severity: String, // "error", "warning"
+    pub processor_id: Option<Uuid>,
+    pub party_id: Uuid,
+    pub party_name: String,
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_processor"))]
+pub struct ProcessorRequest {
+    pub party_id: Uuid,
+    #[validate(length(max = 2000))]
+    pub services: Option<String>,
+    #[serde(default)]
+    #[validate(nested)]
+    pub transfers: Vec<TransferInput>,
+    #[validate(length(max = 255))]
+    pub dpa_reference: Option<String>,
+    pub dpa_signed_at: Option<DateTime<Utc>>,
+    pub dpa_expires_at: Option<DateTime<Utc>>,
+    #[validate(range(min = 0, max = 100))]
+    pub security_score: Option<i32>,
+    pub security_assessed_at: Option<DateTime<Utc>>,
+    #[validate(length(max = 5000))]
+    pub notes: Option<String>,
+    #[serde(default)]
+    #[validate(nested)]
+    pub sub_processors: Vec<SubProcessorInput>,
+    #[serde(default)]
+    pub endpoint_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SubProcessorInput {
+    pub party_id: Uuid,
+    #[validate(length(max = 2000))]
+    pub service: Option<String>,
+    pub authorised_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,461 @@
+//! Référentiels du registre, propres à chaque entité : catégories de données,
+//! catégories de personnes concernées, tiers et mesures de sécurité. Un élément
+//! encore cité par une fiche ne peut pas être supprimé.
+use actix_web::{web, HttpResponse};
+use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, QueryOrder, PaginatorTrait, Set, IntoActiveModel, ActiveModelTrait};
+use uuid::Uuid;
+use chrono::Utc;
+use crate::entities::rgpd::models::*;
+use crate::entities::scope::{EntityScope, ScopedId};
+use crate::errors::ApiError;
+use crate::validation::ValidatedJson;
+use crate::entities_orm::rgpd_data_category::{self, Entity as DataCategoryEntity, Column as DataCategoryColumn};
+use crate::entities_orm::rgpd_data_subject::{self, Entity as DataSubjectEntity, Column as DataSubjectColumn};
+use crate::entities_orm::rgpd_party::{self, Entity as PartyEntity, Column as PartyColumn};
+use crate::entities_orm::rgpd_security_measure::{self, Entity as SecurityMeasureEntity, Column as SecurityMeasureColumn};
+use crate::entities_orm::register_data_category::{Entity as RegisterDataCategoryEntity, Column as RegisterDataCategoryColumn};
+use crate::entities_orm::register_data_subject::{Entity as RegisterDataSubjectEntity, Column as RegisterDataSubjectColumn};
+use crate::entities_orm::register_party::{Entity as RegisterPartyEntity, Column as RegisterPartyColumn};
+use crate::entities_orm::register_security_measure::{Entity as RegisterSecurityMeasureEntity, Column as RegisterSecurityMeasureColumn};
+use crate::entities_orm::rgpd_transfer::{Entity as TransferEntity, Column as TransferColumn};
+use crate::entities_orm::rgpd_processor::{Entity as ProcessorEntity, Column as ProcessorColumn};
+use crate::entities_orm::rgpd_sub_processor::{Entity as SubProcessorEntity, Column as SubProcessorColumn};
+use crate::entities_orm::endpoint::Entity as EndpointEntity;
+use crate::entities_orm::license_key::Entity as LicenseKeyEntity;
+use crate::entities_orm::software_version::Entity as SoftwareVersionEntity;
+use crate::entities_orm::encryption_algorithm::Entity as EncryptionAlgorithmEntity;
+
+fn in_use(what: &str) -> ApiError {
+    ApiError::Conflict(format!("{} is referenced by register entries", what))
+}
+
+// ========== Catégories de données ==========
+
+async fn find_data_category(db: &DatabaseConnection, scope: &EntityScope, id: Uuid) -> Result<rgpd_data_category::Model, ApiError> {
+    DataCategoryEntity::find_by_id(id)
+        .filter(DataCategoryColumn::EntityId.eq(scope.entity_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Data category not found".to_string()))
+}
+
+async fn ensure_data_category_name_free(db: &DatabaseConnection, scope: &EntityScope, name: &str, except: Option<Uuid>) -> Result<(), ApiError> {
+    let mut query = DataCategoryEntity::find()
+        .filter(DataCategoryColumn::EntityId.eq(scope.entity_id))
+        .filter(DataCategoryColumn::Name.eq(name));
+    if let Some(id) = except {
+        query = query.filter(DataCategoryColumn::Id.ne(id));
+    }
+    if query.count(db).await? > 0 {
+        return Err(ApiError::Conflict("A data category with this name already exists".to_string()));
+    }
+    Ok(())
+}
+
+pub async fn list_data_categories(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    let categories: Vec<DataCategory> = DataCategoryEntity::find()
+        .filter(DataCategoryColumn::EntityId.eq(scope.entity_id))
+        .order_by_asc(DataCategoryColumn::Name)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(DataCategory::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(categories))
+}
+
+pub async fn create_data_category(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    body: ValidatedJson<DataCategoryRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let name = body.name.trim().to_string();
+    ensure_data_category_name_free(db.get_ref(), &scope, &name, None).await?;
+
+    let now = Utc::now();
+    let category = DataCategoryEntity::insert(rgpd_data_category::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        entity_id: Set(scope.entity_id),
+        name: Set(name),
+        description: Set(body.description.clone()),
+        kind: Set(body.kind.clone().unwrap_or_else(|| "other".to_string())),
+        retention_ceiling_months: Set(body.retention_ceiling_months),
+        created_at: Set(now),
+        updated_at: Set(now),
+    })
+    .exec_with_returning(db.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Created().json(DataCategory::from(category)))
+}
+
+pub async fn update_data_category(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<DataCategoryRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let category = find_data_category(db.get_ref(), &scope, path.id).await?;
+    let name = body.name.trim().to_string();
+    ensure_data_category_name_free(db.get_ref(), &scope, &name, Some(category.id)).await?;
+
+    let mut category = category.into_active_model();
+    category.name = Set(name);
+    category.description = Set(body.description.clone());
+    category.kind = Set(body.kind.clone().unwrap_or_else(|| "other".to_string()));
+    category.retention_ceiling_months = Set(body.retention_ceiling_months);
+    category.updated_at = Set(Utc::now());
+    let category = category.update(db.get_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(DataCategory::from(category)))
+}
+
+pub async fn delete_data_category(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+) -> Result<HttpResponse, ApiError> {
+    let category = find_data_category(db.get_ref(), &scope, path.id).await?;
+    let uses = RegisterDataCategoryEntity::find()
+        .filter(RegisterDataCategoryColumn::DataCategoryId.eq(category.id))
+        .count(db.get_ref())
+        .await?;
+    if uses > 0 {
+        return Err(in_use("Data category"));
+    }
+
+    DataCategoryEntity::delete_by_id(category.id)
+        .exec(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// ========== Catégories de personnes concernées ==========
+
+async fn find_data_subject(db: &DatabaseConnection, scope: &EntityScope, id: Uuid) -> Result<rgpd_data_subject::Model, ApiError> {
+    DataSubjectEntity::find_by_id(id)
+        .filter(DataSubjectColumn::EntityId.eq(scope.entity_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Data subject category not found".to_string()))
+}
+
+async fn ensure_data_subject_name_free(db: &DatabaseConnection, scope: &EntityScope, name: &str, except: Option<Uuid>) -> Result<(), ApiError> {
+    let mut query = DataSubjectEntity::find()
+        .filter(DataSubjectColumn::EntityId.eq(scope.entity_id))
+        .filter(DataSubjectColumn::Name.eq(name));
+    if let Some(id) = except {
+        query = query.filter(DataSubjectColumn::Id.ne(id));
+    }
+    if query.count(db).await? > 0 {
+        return Err(ApiError::Conflict("A data subject category with this name already exists".to_string()));
+    }
+    Ok(())
+}
+
+pub async fn list_data_subjects(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    let subjects: Vec<Category> = DataSubjectEntity::find()
+        .filter(DataSubjectColumn::EntityId.eq(scope.entity_id))
+        .order_by_asc(DataSubjectColumn::Name)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(Category::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(subjects))
+}
+
+pub async fn create_data_subject(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    body: ValidatedJson<CategoryRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let name = body.name.trim().to_string();
+    ensure_data_subject_name_free(db.get_ref(), &scope, &name, None).await?;
+
+    let now = Utc::now();
+    let subject = DataSubjectEntity::insert(rgpd_data_subject::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        entity_id: Set(scope.entity_id),
+        name: Set(name),
+        description: Set(body.description.clone()),
+        created_at: Set(now),
+        updated_at: Set(now),
+    })
+    .exec_with_returning(db.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Created().json(Category::from(subject)))
+}
+
+pub async fn update_data_subject(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<CategoryRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let subject = find_data_subject(db.get_ref(), &scope, path.id).await?;
+    let name = body.name.trim().to_string();
+    ensure_data_subject_name_free(db.get_ref(), &scope, &name, Some(subject.id)).await?;
+
+    let mut subject = subject.into_active_model();
+    subject.name = Set(name);
+    subject.description = Set(body.description.clone());
+    subject.updated_at = Set(Utc::now());
+    let subject = subject.update(db.get_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(Category::from(subject)))
+}
+
+pub async fn delete_data_subject(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+) -> Result<HttpResponse, ApiError> {
+    let subject = find_data_subject(db.get_ref(), &scope, path.id).await?;
+    let uses = RegisterDataSubjectEntity::find()
+        .filter(RegisterDataSubjectColumn::DataSubjectId.eq(subject.id))
+        .count(db.get_ref())
+        .await?;
+    if uses > 0 {
+        return Err(in_use("Data subject category"));
+    }
+
+    DataSubjectEntity::delete_by_id(subject.id)
+        .exec(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// ========== Tiers ==========
+
+async fn find_party(db: &DatabaseConnection, scope: &EntityScope, id: Uuid) -> Result<rgpd_party::Model, ApiError> {
+    PartyEntity::find_by_id(id)
+        .filter(PartyColumn::EntityId.eq(scope.entity_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Party not found".to_string()))
+}
+
+pub async fn list_parties(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    let parties: Vec<Party> = PartyEntity::find()
+        .filter(PartyColumn::EntityId.eq(scope.entity_id))
+        .order_by_asc(PartyColumn::Name)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(Party::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(parties))
+}
+
+pub async fn create_party(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    body: ValidatedJson<PartyRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let now = Utc::now();
+    let party = PartyEntity::insert(rgpd_party::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        entity_id: Set(scope.entity_id),
+        name: Set(body.name.trim().to_string()),
+        description: Set(body.description.clone()),
+        category: Set(body.category.clone()),
+        contact_name: Set(body.contact_name.clone()),
+        email: Set(body.email.clone()),
+        phone: Set(body.phone.clone()),
+        address: Set(body.address.clone()),
+        country: Set(body.country.clone()),
+        created_at: Set(now),
+        updated_at: Set(now),
+    })
+    .exec_with_returning(db.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Created().json(Party::from(party)))
+}
+
+pub async fn update_party(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<PartyRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let party = find_party(db.get_ref(), &scope, path.id).await?;
+
+    let mut party = party.into_active_model();
+    party.name = Set(body.name.trim().to_string());
+    party.description = Set(body.description.clone());
+    party.category = Set(body.category.clone());
+    party.contact_name = Set(body.contact_name.clone());
+    party.email = Set(body.email.clone());
+    party.phone = Set(body.phone.clone());
+    party.address = Set(body.address.clone());
+    party.country = Set(body.country.clone());
+    party.updated_at = Set(Utc::now());
+    let party = party.update(db.get_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(Party::from(party)))
+}
+
+pub async fn delete_party(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+) -> Result<HttpResponse, ApiError> {
+    let party = find_party(db.get_ref(), &scope, path.id).await?;
+    let roles = RegisterPartyEntity::find()
+        .filter(RegisterPartyColumn::PartyId.eq(party.id))
+        .count(db.get_ref())
+        .await?;
+    let transfers = TransferEntity::find()
+        .filter(TransferColumn::PartyId.eq(party.id))
+        .count(db.get_ref())
+        .await?;
+    if roles + transfers > 0 {
+        return Err(in_use("Party"));
+    }
+    let processors = ProcessorEntity::find()
+        .filter(ProcessorColumn::PartyId.eq(party.id))
+        .count(db.get_ref())
+        .await?;
+    let sub_processors = SubProcessorEntity::find()
+        .filter(SubProcessorColumn::PartyId.eq(party.id))
+        .count(db.get_ref())
+        .await?;
+    if processors + sub_processors > 0 {
+        return Err(ApiError::Conflict("Party is referenced by the processor registry".to_string()));
+    }
+
+    PartyEntity::delete_by_id(party.id)
+        .exec(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// ========== Mesures de sécurité ==========
+
+async fn find_security_measure(db: &DatabaseConnection, scope: &EntityScope, id: Uuid) -> Result<rgpd_security_measure::Model, ApiError> {
+    SecurityMeasureEntity::find_by_id(id)
+        .filter(SecurityMeasureColumn::EntityId.eq(scope.entity_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Security measure not found".to_string()))
+}
+
+// L'élément du catalogue doit être partagé ou appartenir à l'entité
+async fn check_catalogue_item(db: &DatabaseConnection, scope: &EntityScope, body: &SecurityMeasureRequest) -> Result<(), ApiError> {
+    let (Some(item_type), Some(item_id)) = (&body.catalogue_item_type, body.catalogue_item_id) else {
+        return Ok(());
+    };
+    let owner = match item_type.as_str() {
+        "endpoint" => EndpointEntity::find_by_id(item_id).one(db).await?.map(|i| i.entity_id),
+        "license_key" => LicenseKeyEntity::find_by_id(item_id).one(db).await?.map(|i| i.entity_id),
+        "software_version" => SoftwareVersionEntity::find_by_id(item_id).one(db).await?.map(|i| i.entity_id),
+        "encryption_algorithm" => EncryptionAlgorithmEntity::find_by_id(item_id).one(db).await?.map(|i| i.entity_id),
+        _ => None,
+    };
+    match owner {
+        Some(None) => Ok(()),
+        Some(Some(entity_id)) if entity_id == scope.entity_id => Ok(()),
+        _ => Err(ApiError::validation("catalogue_item_id", "unknown_reference", "Catalogue item not found")),
+    }
+}
+
+pub async fn list_security_measures(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    let measures: Vec<SecurityMeasure> = SecurityMeasureEntity::find()
+        .filter(SecurityMeasureColumn::EntityId.eq(scope.entity_id))
+        .order_by_asc(SecurityMeasureColumn::Name)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(SecurityMeasure::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(measures))
+}
+
+pub async fn create_security_measure(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    body: ValidatedJson<SecurityMeasureRequest>,
+) -> Result<HttpResponse, ApiError> {
+    check_catalogue_item(db.get_ref(), &scope, &body).await?;
+
+    let now = Utc::now();
+    let measure = SecurityMeasureEntity::insert(rgpd_security_measure::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        entity_id: Set(scope.entity_id),
+        name: Set(body.name.trim().to_string()),
+        description: Set(body.description.clone()),
+        category: Set(body.category.clone()),
+        catalogue_item_type: Set(body.catalogue_item_type.clone()),
+        catalogue_item_id: Set(body.catalogue_item_id),
+        created_at: Set(now),
+        updated_at: Set(now),
+    })
+    .exec_with_returning(db.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Created().json(SecurityMeasure::from(measure)))
+}
+
+pub async fn update_security_measure(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+    body: ValidatedJson<SecurityMeasureRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let measure = find_security_measure(db.get_ref(), &scope, path.id).await?;
+    check_catalogue_item(db.get_ref(), &scope, &body).await?;
+
+    let mut measure = measure.into_active_model();
+    measure.name = Set(body.name.trim().to_string());
+    measure.description = Set(body.description.clone());
+    measure.category = Set(body.category.clone());
+    measure.catalogue_item_type = Set(body.catalogue_item_type.clone());
+    measure.catalogue_item_id = Set(body.catalogue_item_id);
+    measure.updated_at = Set(Utc::now());
+    let measure = measure.update(db.get_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(SecurityMeasure::from(measure)))
+}
+
+pub async fn delete_security_measure(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+    path: web::Path<ScopedId>,
+) -> Result<HttpResponse, ApiError> {
+    let measure = find_security_measure(db.get_ref(), &scope, path.id).await?;
+    let uses = RegisterSecurityMeasureEntity::find()
+        .filter(RegisterSecurityMeasureColumn::SecurityMeasureId.eq(measure.id))
+        .count(db.get_ref())
+        .await?;
+    if uses > 0 {
+        return Err(in_use("Security measure"));
+    }
+
+    SecurityMeasureEntity::delete_by_id(measure.id)
+        .exec(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,346 @@
+//! Fiches du registre et leurs liens vers les référentiels de l'entité :
+//! chargement groupé, contrôle des références et remplacement des liens.
+use std::collections::{HashMap, HashSet};
+use chrono::Utc;
+use sea_orm::{ConnectionTrait, DbErr, EntityTrait, ColumnTrait, QueryFilter, PaginatorTrait, Set};
+use uuid::Uuid;
+use crate::entities::rgpd::models::*;
+use crate::entities::rgpd::{consents, processors, taxonomy};
+use crate::errors::ApiError;
+use crate::entities_orm::register_entry;
+use crate::entities_orm::rgpd_data_category::{Entity as DataCategoryEntity, Column as DataCategoryColumn};
+use crate::entities_orm::rgpd_data_subject::{Entity as DataSubjectEntity, Column as DataSubjectColumn};
+use crate::entities_orm::rgpd_party::{Entity as PartyEntity, Column as PartyColumn};
+use crate::entities_orm::rgpd_security_measure::{Entity as SecurityMeasureEntity, Column as SecurityMeasureColumn};
+use crate::entities_orm::register_data_category::{self, Entity as RegisterDataCategoryEntity, Column as RegisterDataCategoryColumn};
+use crate::entities_orm::register_data_subject::{self, Entity as RegisterDataSubjectEntity, Column as RegisterDataSubjectColumn};
+use crate::entities_orm::register_party::{self, Entity as RegisterPartyEntity, Column as RegisterPartyColumn};
+use crate::entities_orm::register_security_measure::{self, Entity as RegisterSecurityMeasureEntity, Column as RegisterSecurityMeasureColumn};
+use crate::entities_orm::rgpd_transfer::{Entity as TransferEntity, Column as TransferColumn};
+use crate::entities_orm::rgpd_dpia::{Entity as DpiaEntity, Column as DpiaColumn};
+
+/// Liens d'une fiche ; `None` conserve les liens existants lors d'une mise à jour.
+#[derive(Default)]
+pub struct RegisterLinks<'a> {
+    pub data_category_ids: Option<&'a [Uuid]>,
+    pub data_subject_ids: Option<&'a [Uuid]>,
+    pub parties: Option<&'a [RegisterPartyInput]>,
+    pub transfers: Option<&'a [TransferInput]>,
+    pub measure_ids: Option<&'a [Uuid]>,
+}
+
+impl<'a> RegisterLinks<'a> {
+    pub fn from_create(req: &'a CreateRegisterEntryRequest) -> Self {
+        Self {
+            data_category_ids: Some(&req.data_category_ids),
+            data_subject_ids: Some(&req.data_subject_ids),
+            parties: Some(&req.parties),
+            transfers: Some(&req.transfers),
+            measure_ids: Some(&req.measure_ids),
+        }
+    }
+
+    pub fn from_update(req: &'a UpdateRegisterEntryRequest) -> Self {
+        Self {
+            data_category_ids: req.data_category_ids.as_deref(),
+            data_subject_ids: req.data_subject_ids.as_deref(),
+            parties: req.parties.as_deref(),
+            transfers: req.transfers.as_deref(),
+            measure_ids: req.measure_ids.as_deref(),
+        }
+    }
+}
+
+fn distinct(ids: &[Uuid]) -> Vec<Uuid> {
+    let mut seen = HashSet::new();
+    ids.iter().copied().filter(|id| seen.insert(*id)).collect()
+}
+
+fn unknown_reference(field: &'static str) -> ApiError {
+    ApiError::validation(field, "unknown_reference", "References an item that does not exist in this entity")
+}
+
+/// Vérifie que toutes les références désignent des éléments de l'entité.
+pub async fn check_references<C: ConnectionTrait>(db: &C, entity_id: Uuid, links: &RegisterLinks<'_>) -> Result<(), ApiError> {
+    if let Some(ids) = links.data_category_ids {
+        let ids = distinct(ids);
+        let found = DataCategoryEntity::find()
+            .filter(DataCategoryColumn::EntityId.eq(entity_id))
+            .filter(DataCategoryColumn::Id.is_in(ids.clone()))
+            .count(db)
+            .await?;
+        if found as usize != ids.len() {
+            return Err(unknown_reference("data_category_ids"));
+        }
+    }
+    if let Some(ids) = links.data_subject_ids {
+        let ids = distinct(ids);
+        let found = DataSubjectEntity::find()
+            .filter(DataSubjectColumn::EntityId.eq(entity_id))
+            .filter(DataSubjectColumn::Id.is_in(ids.clone()))
+            .count(db)
+            .await?;
+        if found as usize != ids.len() {
+            return Err(unknown_reference("data_subject_ids"));
+        }
+    }
+    let mut party_ids: Vec<Uuid> = links.parties.unwrap_or_default().iter().map(|p| p.party_id).collect();
+    party_ids.extend(links.transfers.unwrap_or_default().iter().filter_map(|t| t.party_id));
+    let party_ids = distinct(&party_ids);
+    if !party_ids.is_empty() {
+        let found = PartyEntity::find()
+            .filter(PartyColumn::EntityId.eq(entity_id))
+            .filter(PartyColumn::Id.is_in(party_ids.clone()))
+            .count(db)
+            .await?;
+        if found as usize != party_ids.len() {
+            return Err(unknown_reference("parties"));
+        }
+    }
+    if let Some(ids) = links.measure_ids {
+        let ids = distinct(ids);
+        let found = SecurityMeasureEntity::find()
+            .filter(SecurityMeasureColumn::EntityId.eq(entity_id))
+            .filter(SecurityMeasureColumn::Id.is_in(ids.clone()))
+            .count(db)
+            .await?;
+        if found as usize != ids.len() {
+            return Err(unknown_reference("measure_ids"));
+        }
+    }
+    Ok(())
+}
+
+/// Remplace les liens fournis ; à appeler dans la transaction de la fiche.
+pub async fn save_links<C: ConnectionTrait>(db: &C, entry_id: Uuid, links: &RegisterLinks<'_>) -> Result<(), DbErr> {
+    if let Some(ids) = links.data_category_ids {
+        RegisterDataCategoryEntity::delete_many()
+            .filter(RegisterDataCategoryColumn::RegisterEntryId.eq(entry_id))
+            .exec(db)
+            .await?;
+        let rows: Vec<_> = distinct(ids).into_iter()
+            .map(|id| register_data_category::ActiveModel {
+                register_entry_id: Set(entry_id),
+                data_category_id: Set(id),
+            })
+            .collect();
+        if !rows.is_empty() {
+            RegisterDataCategoryEntity::insert_many(rows).exec(db).await?;
+        }
+    }
+    if let Some(ids) = links.data_subject_ids {
+        RegisterDataSubjectEntity::delete_many()
+            .filter(RegisterDataSubjectColumn::RegisterEntryId.eq(entry_id))
+            .exec(db)
+            .await?;
+        let rows: Vec<_> = distinct(ids).into_iter()
+            .map(|id| register_data_subject::ActiveModel {
+                register_entry_id: Set(entry_id),
+                data_subject_id: Set(id),
+            })
+            .collect();
+        if !rows.is_empty() {
+            RegisterDataSubjectEntity::insert_many(rows).exec(db).await?;
+        }
+    }
+    if let Some(parties) = links.parties {
+        RegisterPartyEntity::delete_many()
+            .filter(RegisterPartyColumn::RegisterEntryId.eq(entry_id))
+            .exec(db)
+            .await?;
+        let mut seen = HashSet::new();
+        let rows: Vec<_> = parties.iter()
+            .filter(|p| seen.insert((p.party_id, p.role.clone())))
+            .map(|p| register_party::ActiveModel {
+                register_entry_id: Set(entry_id),
+                party_id: Set(p.party_id),
+                role: Set(p.role.clone()),
+            })
+            .collect();
+        if !rows.is_empty() {
+            RegisterPartyEntity::insert_many(rows).exec(db).await?;
+        }
+    }
+    if let Some(transfers) = links.transfers {
+        TransferEntity::delete_many()
+            .filter(TransferColumn::RegisterEntryId.eq(entry_id))
+            .exec(db)
+            .await?;
+        let now = Utc::now();
+        let rows: Vec<_> = transfers.iter()
+            .map(|t| t.to_active_model(Some(entry_id), None, now))
+            .collect();
+        if !rows.is_empty() {
+            TransferEntity::insert_many(rows).exec(db).await?;
+        }
+    }
+    if let Some(ids) = links.measure_ids {
+        RegisterSecurityMeasureEntity::delete_many()
+            .filter(RegisterSecurityMeasureColumn::RegisterEntryId.eq(entry_id))
+            .exec(db)
+            .await?;
+        let rows: Vec<_> = distinct(ids).into_iter()
+            .map(|id| register_security_measure::ActiveModel {
+                register_entry_id: Set(entry_id),
+                security_measure_id: Set(id),
+            })
+            .collect();
+        if !rows.is_empty() {
+            RegisterSecurityMeasureEntity::insert_many(rows).exec(db).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Construit les fiches complètes en quelques requêtes, quel que soit leur nombre.
+pub async fn load<C: ConnectionTrait>(db: &C, entries: Vec<register_entry::Model>) -> Result<Vec<RegisterEntry>, DbErr> {
+    let entry_ids: Vec<Uuid> = entries.iter().map(|e| e.id).collect();
+
+    let category_links = RegisterDataCategoryEntity::find()
+        .filter(RegisterDataCategoryColumn::RegisterEntryId.is_in(entry_ids.clone()))
+        .all(db)
+        .await?;
+    let subject_links = RegisterDataSubjectEntity::find()
+        .filter(RegisterDataSubjectColumn::RegisterEntryId.is_in(entry_ids.clone()))
+        .all(db)
+        .await?;
+    let party_links = RegisterPartyEntity::find()
+        .filter(RegisterPartyColumn::RegisterEntryId.is_in(entry_ids.clone()))
+        .all(db)
+        .await?;
+    let measure_links = RegisterSecurityMeasureEntity::find()
+        .filter(RegisterSecurityMeasureColumn::RegisterEntryId.is_in(entry_ids.clone()))
+        .all(db)
+        .await?;
+    let transfers = TransferEntity::find()
+        .filter(TransferColumn::RegisterEntryId.is_in(entry_ids.clone()))
+        .all(db)
+        .await?;
+    let mut dpias: HashMap<Uuid, DpiaRef> = DpiaEntity::find()
+        .filter(DpiaColumn::RegisterEntryId.is_in(entry_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|d| (d.register_entry_id, DpiaRef { id: d.id, status: d.status, version: d.version }))
+        .collect();
+
+    let categories: HashMap<Uuid, DataCategory> = DataCategoryEntity::find()
+        .filter(DataCategoryColumn::Id.is_in(category_links.iter().map(|l| l.data_category_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|c| (c.id, DataCategory::from(c)))
+        .collect();
+    let subjects: HashMap<Uuid, String> = DataSubjectEntity::find()
+        .filter(DataSubjectColumn::Id.is_in(subject_links.iter().map(|l| l.data_subject_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|s| (s.id, s.name))
+        .collect();
+    let party_ids = party_links.iter().map(|l| l.party_id).chain(transfers.iter().filter_map(|t| t.party_id));
+    let parties: HashMap<Uuid, (String, Option<String>)> = PartyEntity::find()
+        .filter(PartyColumn::Id.is_in(party_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|p| (p.id, (p.name, p.country)))
+        .collect();
+    let mut consent_stats = consents::stats_by_entry(db, &entries).await?;
+    let processor_refs = processors::refs_by_party(db, party_links.iter()
+        .filter(|l| l.role == processors::PROCESSOR_ROLE)
+        .map(|l| l.party_id)).await?;
+    let measures: HashMap<Uuid, SecurityMeasureRef> = SecurityMeasureEntity::find()
+        .filter(SecurityMeasureColumn::Id.is_in(measure_links.iter().map(|l| l.security_measure_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|m| (m.id, SecurityMeasureRef {
+            id: m.id,
+            name: m.name,
+            category: m.category,
+            catalogue_item_type: m.catalogue_item_type,
+            catalogue_item_id: m.catalogue_item_id,
+        }))
+        .collect();
+
+    let named = |names: &HashMap<Uuid, String>, id: Uuid| names.get(&id).map(|name| ReferentialRef { id, name: name.clone() });
+
+    Ok(entries.into_iter().map(|e| {
+        let mut data_categories: Vec<DataCategoryRef> = category_links.iter()
+            .filter(|l| l.register_entry_id == e.id)
+            .filter_map(|l| categories.get(&l.data_category_id))
+            .map(|c| DataCategoryRef {
+                id: c.id,
+                name: c.name.clone(),
+                kind: c.kind.clone(),
+                special: c.special.clone(),
+                retention_ceiling_months: c.retention_ceiling_months.or(c.recommended_retention_months),
+            })
+            .collect();
+        data_categories.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut data_subjects: Vec<ReferentialRef> = subject_links.iter()
+            .filter(|l| l.register_entry_id == e.id)
+            .filter_map(|l| named(&subjects, l.data_subject_id))
+            .collect();
+        data_subjects.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut entry_parties: Vec<RegisterPartyRef> = party_links.iter()
+            .filter(|l| l.register_entry_id == e.id)
+            .filter_map(|l| parties.get(&l.party_id).map(|(name, country)| RegisterPartyRef {
+                party_id: l.party_id,
+                name: name.clone(),
+                role: l.role.clone(),
+                country: country.clone(),
+                processor: (l.role == processors::PROCESSOR_ROLE)
+                    .then(|| processor_refs.get(&l.party_id).cloned())
+                    .flatten(),
+            }))
+            .collect();
+        entry_parties.sort_by(|a, b| (&a.role, &a.name).cmp(&(&b.role, &b.name)));
+        let entry_transfers: Vec<Transfer> = transfers.iter()
+            .filter(|t| t.register_entry_id == Some(e.id))
+            .map(|t| Transfer::from_model(t, t.party_id.and_then(|id| parties.get(&id)).map(|(name, _)| name.clone())))
+            .collect();
+        let mut entry_measures: Vec<SecurityMeasureRef> = measure_links.iter()
+            .filter(|l| l.register_entry_id == e.id)
+            .filter_map(|l| measures.get(&l.security_measure_id).cloned())
+            .collect();
+        entry_measures.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut entry = RegisterEntry {
+            dpia: dpias.remove(&e.id),
+            consents: consent_stats.remove(&e.id),
+            id: e.id,
+            entity_id: e.entity_id,
+            record_type: e.record_type,
+            processing_name: e.processing_name,
+            purpose: e.purpose,
+            legal_basis: e.legal_basis,
+            legal_basis_details: e.legal_basis_details,
+            special_category_exception: e.special_category_exception,
+            criminal_data_basis: e.criminal_data_basis,
+            consent_mechanism: e.consent_mechanism,
+            data_categories,
+            data_subjects,
+            parties: entry_parties,
+            transfers: entry_transfers,
+            measures: entry_measures,
+            retention_period: e.retention_period,
+            retention_months: e.retention_months,
+            retention_trigger: e.retention_trigger,
+            retention_action: e.retention_action,
+            security_measures: e.security_measures,
+            shared_with_subsidiaries: e.shared_with_subsidiaries,
+            checks: Vec::new(),
+            created_at: e.created_at,
+            updated_at: e.updated_at,
+        };
+        entry.checks = taxonomy::check_entry(&entry);
+        entry
+    }).collect())
+}
+
+pub async fn load_one<C: ConnectionTrait>(db: &C, entry: register_entry::Model) -> Result<RegisterEntry, DbErr> {
+    let mut entries = load(db, vec![entry]).await?;
+    entries.pop().ok_or_else(|| DbErr::RecordNotFound("Register entry".to_string()))
+}
@@ -0,0 +1,213 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, Order, QueryFilter, QueryOrder, QuerySelect, Set};
+use uuid::Uuid;
+use crate::config::Config;
+use crate::entities::rgpd::retention::{self, models::*};
+use crate::entities::scope::EntityScope;
+use crate::errors::ApiError;
+use crate::middleware::{get_current_user_id, get_entity_role};
+use crate::validation::ValidatedJson;
+use crate::entities_orm::rgpd_retention_policy::{Entity as PolicyEntity, Column as PolicyColumn, ActiveModel as PolicyActiveModel};
+use crate::entities_orm::rgpd_retention_run::{Entity as RunEntity, Column as RunColumn};
+use crate::entities::rgpd::models::{AccessRequest, Breach};
+use crate::entities_orm::access_request::{Entity as AccessRequestEntity, Column as AccessRequestColumn};
+use crate::entities_orm::breach::{Entity as BreachEntity, Column as BreachColumn};
+use crate::entities_orm::register_entry::{Entity as RegisterEntryEntity, Column as RegisterEntryColumn};
+
+// Rapports conservés en base ; seuls les plus récents sont listés
+const RUNS_LIMIT: u64 = 100;
+
+// Les politiques et les passages manuels relèvent de l'administrateur de l'entité
+fn require_admin(req: &HttpRequest, scope: &EntityScope) -> Result<Uuid, ApiError> {
+    let user_id = get_current_user_id(req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+    if get_entity_role(req, scope.entity_id).as_deref() != Some("admin") {
+        return Err(ApiError::Forbidden("Admin access required".to_string()));
+    }
+    Ok(user_id)
+}
+
+pub async fn list_policies(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(retention::effective_policies(db.get_ref(), scope.entity_id).await?))
+}
+
+pub async fn update_policy(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+    path: web::Path<PolicyPath>,
+    body: ValidatedJson<RetentionPolicyRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = require_admin(&req, &scope)?;
+    let record_type = path.into_inner().record_type;
+    validate_record_type(&record_type)
+        .map_err(|e| ApiError::validation("record_type", e.code, e.message.unwrap_or_default()))?;
+    body.validate_trigger(&record_type)
+        .map_err(|e| ApiError::validation("trigger", e.code, e.message.unwrap_or_default()))?;
+
+    // Règle reprise d'une fiche du registre de l'entité, ou saisie
+    let mut rule = RetentionPolicy {
+        record_type: record_type.clone(),
+        duration_months: body.duration_months.unwrap_or_default(),
+        trigger: body.trigger.clone().unwrap_or_default(),
+        action: body.action.clone().unwrap_or_default(),
+        enabled: body.enabled.unwrap_or(true),
+        is_default: false,
+        register_entry_id: body.register_entry_id,
+        updated_by: Some(user_id),
+        updated_at: None,
+    };
+    if let Some(entry_id) = body.register_entry_id {
+        let entry = RegisterEntryEntity::find_by_id(entry_id)
+            .filter(RegisterEntryColumn::EntityId.eq(scope.entity_id))
+            .one(db.get_ref())
+            .await?
+            .ok_or_else(|| ApiError::validation("register_entry_id", "not_found", "Register entry not found in this entity"))?;
+        if entry.retention_months.is_none() || entry.retention_trigger.is_none()
+            || !entry.retention_action.as_deref().is_some_and(|a| ACTIONS.contains(&a))
+        {
+            return Err(ApiError::validation(
+                "register_entry_id",
+                "retention_rule_incomplete",
+                "The register entry needs a retention duration, trigger and action",
+            ));
+        }
+        rule.apply_register_rule(&entry);
+    } else {
+        for (field, missing) in [
+            ("duration_months", body.duration_months.is_none()),
+            ("trigger", body.trigger.is_none()),
+            ("action", body.action.is_none()),
+        ] {
+            if missing {
+                return Err(ApiError::validation(field, "required", format!("{} is required without register_entry_id", field)));
+            }
+        }
+    }
+
+    let existing = PolicyEntity::find()
+        .filter(PolicyColumn::EntityId.eq(scope.entity_id))
+        .filter(PolicyColumn::RecordType.eq(&record_type))
+        .one(db.get_ref())
+        .await?;
+
+    let now = Utc::now();
+    let policy = match existing {
+        Some(policy) => {
+            let mut policy: PolicyActiveModel = policy.into_active_model();
+            policy.duration_months = Set(rule.duration_months);
+            policy.trigger = Set(rule.trigger);
+            policy.action = Set(rule.action);
+            policy.enabled = Set(rule.enabled);
+            policy.register_entry_id = Set(rule.register_entry_id);
+            policy.updated_by = Set(Some(user_id));
+            policy.updated_at = Set(now);
+            policy.update(db.get_ref()).await?
+        }
+        None => {
+            PolicyActiveModel {
+                id: Set(Uuid::new_v4()),
+                entity_id: Set(scope.entity_id),
+                record_type: Set(record_type),
+                duration_months: Set(rule.duration_months),
+                trigger: Set(rule.trigger),
+                action: Set(rule.action),
+                enabled: Set(rule.enabled),
+                updated_by: Set(Some(user_id)),
+                created_at: Set(now),
+                updated_at: Set(now),
+                register_entry_id: Set(rule.register_entry_id),
+            }
+            .insert(db.get_ref())
+            .await?
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(RetentionPolicy::from(policy)))
+}
+
+/// Retour à la politique par défaut.
+pub async fn reset_policy(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+    path: web::Path<PolicyPath>,
+) -> Result<HttpResponse, ApiError> {
+    require_admin(&req, &scope)?;
+    let record_type = path.into_inner().record_type;
+    validate_record_type(&record_type)
+        .map_err(|e| ApiError::validation("record_type", e.code, e.message.unwrap_or_default()))?;
+
+    PolicyEntity::delete_many()
+        .filter(PolicyColumn::EntityId.eq(scope.entity_id))
+        .filter(PolicyColumn::RecordType.eq(&record_type))
+        .exec(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(retention::default_policy(&record_type)))
+}
+
+/// Passage immédiat, éventuellement en simulation ; le rapport est renvoyé.
+pub async fn start_run(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    scope: EntityScope,
+    body: ValidatedJson<StartRunRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = require_admin(&req, &scope)?;
+    let run = retention::run(db.get_ref(), &config, scope.entity_id, Some(user_id), body.dry_run).await?;
+
+    Ok(HttpResponse::Created().json(RetentionRun::from(run)))
+}
+
+pub async fn list_runs(
+    db: web::Data<DatabaseConnection>,
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    let runs: Vec<RetentionRun> = RunEntity::find()
+        .filter(RunColumn::EntityId.eq(scope.entity_id))
+        .order_by(RunColumn::StartedAt, Order::Desc)
+        .limit(RUNS_LIMIT)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(RetentionRun::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(runs))
+}
+
+/// Archive intermédiaire : dossiers retirés des vues courantes par une
+/// politique `archive`, en lecture seule.
+pub async fn list_archive(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    scope: EntityScope,
+) -> Result<HttpResponse, ApiError> {
+    require_admin(&req, &scope)?;
+    let access_requests: Vec<AccessRequest> = AccessRequestEntity::find()
+        .filter(AccessRequestColumn::EntityId.eq(scope.entity_id))
+        .filter(AccessRequestColumn::ArchivedAt.is_not_null())
+        .order_by(AccessRequestColumn::ArchivedAt, Order::Desc)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(AccessRequest::from)
+        .collect();
+    let breaches: Vec<Breach> = BreachEntity::find()
+        .filter(BreachColumn::EntityId.eq(scope.entity_id))
+        .filter(BreachColumn::ArchivedAt.is_not_null())
+        .order_by(BreachColumn::ArchivedAt, Order::Desc)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(Breach::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(RetentionArchive { access_requests, breaches }))
+}
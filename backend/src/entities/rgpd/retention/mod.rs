@@ -0,0 +1,377 @@
+//! Conservation des données tenues par HyperCyber pour le compte des entités :
+//! demandes d'exercice de droits et violations de données. Chaque politique
+//! associe une durée, un événement déclencheur et une action (suppression,
+//! anonymisation ou archivage intermédiaire) ; un passage planifié les
+//! applique et laisse un rapport.
+pub mod handlers;
+pub mod models;
+
+use std::time::Duration;
+use actix_web::rt::time::{interval_at, Instant};
+use chrono::{DateTime, Months, Utc};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, DbErr, EntityTrait, IntoActiveModel, QueryFilter, QuerySelect, Set, Statement, TransactionTrait};
+use uuid::Uuid;
+use crate::config::Config;
+use crate::entities::catalogue::storage;
+use crate::entities::rgpd::models::{ACCESS_REQUEST_CLOSED_STATUSES, BREACH_CLOSED_STATUSES};
+use crate::entities_orm::entity::{Entity as EntityEntity, Column as EntityColumn};
+use crate::entities_orm::access_request::{Entity as AccessRequestEntity, Column as AccessRequestColumn};
+use crate::entities_orm::breach::{Entity as BreachEntity, Column as BreachColumn};
+use crate::entities_orm::rgpd_breach_attachment::{Entity as AttachmentEntity, Column as AttachmentColumn};
+use crate::entities_orm::rgpd_retention_policy::{self, Entity as PolicyEntity, Column as PolicyColumn};
+use crate::entities_orm::rgpd_retention_run::{self, Entity as RunEntity, ActiveModel as RunActiveModel};
+use crate::entities_orm::register_entry::{self, Entity as RegisterEntryEntity, Column as RegisterEntryColumn};
+use models::*;
+
+/// Valeur de remplacement des champs textuels obligatoires anonymisés.
+pub const ANONYMIZED: &str = "[anonymisé]";
+
+/// Politique proposée tant que l'entité n'en a pas défini : trois ans après
+/// la clôture pour les demandes, cinq ans pour les violations. Elle reste
+/// inactive (simulations seulement) jusqu'à sa confirmation par un `PUT`.
+pub fn default_policy(record_type: &str) -> RetentionPolicy {
+    let (duration_months, action) = if record_type == RECORD_TYPE_BREACH {
+        (60, ACTION_DELETE)
+    } else {
+        (36, ACTION_ANONYMIZE)
+    };
+    RetentionPolicy {
+        record_type: record_type.to_string(),
+        duration_months,
+        trigger: TRIGGER_CLOSED.to_string(),
+        action: action.to_string(),
+        enabled: false,
+        is_default: true,
+        register_entry_id: None,
+        updated_by: None,
+        updated_at: None,
+    }
+}
+
+impl From<rgpd_retention_policy::Model> for RetentionPolicy {
+    fn from(model: rgpd_retention_policy::Model) -> Self {
+        Self {
+            record_type: model.record_type,
+            duration_months: model.duration_months,
+            trigger: model.trigger,
+            action: model.action,
+            enabled: model.enabled,
+            is_default: false,
+            register_entry_id: model.register_entry_id,
+            updated_by: model.updated_by,
+            updated_at: Some(model.updated_at),
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Reprend la règle de la fiche du registre ; un élément absent de la fiche
+    /// garde la valeur recopiée lors de l'enregistrement de la politique.
+    pub fn apply_register_rule(&mut self, entry: &register_entry::Model) {
+        if let Some(months) = entry.retention_months {
+            self.duration_months = months;
+        }
+        if let Some(trigger) = entry.retention_trigger.as_deref() {
+            self.trigger = trigger_from_register(&self.record_type, trigger).to_string();
+        }
+        if let Some(action) = entry.retention_action.as_deref().filter(|a| ACTIONS.contains(a)) {
+            self.action = action.to_string();
+        }
+    }
+}
+
+/// Politiques en vigueur pour l'entité, une par type d'enregistrement, avec
+/// la règle à jour des fiches du registre auxquelles elles renvoient.
+pub async fn effective_policies<C: ConnectionTrait>(db: &C, entity_id: Uuid) -> Result<Vec<RetentionPolicy>, DbErr> {
+    let stored = PolicyEntity::find()
+        .filter(PolicyColumn::EntityId.eq(entity_id))
+        .all(db)
+        .await?;
+    let entries = RegisterEntryEntity::find()
+        .filter(RegisterEntryColumn::Id.is_in(stored.iter().filter_map(|p| p.register_entry_id)))
+        .all(db)
+        .await?;
+    Ok(RECORD_TYPES.iter().map(|record_type| {
+        let Some(policy) = stored.iter().find(|p| p.record_type == *record_type) else {
+            return default_policy(record_type);
+        };
+        let mut policy = RetentionPolicy::from(policy.clone());
+        if let Some(entry) = entries.iter().find(|e| Some(e.id) == policy.register_entry_id) {
+            policy.apply_register_rule(entry);
+        }
+        policy
+    }).collect())
+}
+
+/// Lance l'application périodique des politiques en tâche de fond. Le premier
+/// passage a lieu après un intervalle complet, pas au démarrage.
+pub fn spawn(db: DatabaseConnection, config: Config) {
+    actix_web::rt::spawn(async move {
+        let period = Duration::from_secs(config.retention_interval_minutes * 60);
+        let mut interval = interval_at(Instant::now() + period, period);
+        loop {
+            interval.tick().await;
+            if let Err(e) = run_all(&db, &config).await {
+                log::error!("Retention run failed: {}", e);
+            }
+        }
+    });
+}
+
+// Clé du verrou consultatif Postgres des passages planifiés
+const RUN_ALL_LOCK_KEY: i64 = 0x6879_7065_7263_7962;
+
+/// Passage planifié sur toutes les entités. Le verrou consultatif, tenu par
+/// une transaction ouverte le temps du passage, écarte les autres instances ;
+/// les rapports des passages sans dossier concerné ne sont pas conservés.
+async fn run_all(db: &DatabaseConnection, config: &Config) -> Result<(), DbErr> {
+    let lock = db.begin().await?;
+    let acquired = lock.query_one(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT pg_try_advisory_xact_lock($1) AS acquired",
+        [RUN_ALL_LOCK_KEY.into()],
+    ))
+    .await?
+    .map(|row| row.try_get::<bool>("", "acquired"))
+    .transpose()?
+    .unwrap_or(false);
+    if !acquired {
+        log::info!("Retention run skipped: another instance holds the lock");
+        return lock.rollback().await;
+    }
+
+    let entity_ids: Vec<Uuid> = EntityEntity::find()
+        .select_only()
+        .column(EntityColumn::Id)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    for entity_id in entity_ids {
+        let run = run(db, config, entity_id, None, false).await?;
+        if let Some(error) = &run.error {
+            log::error!("Retention run {} for entity {} failed: {}", run.id, entity_id, error);
+            continue;
+        }
+        let reports: Vec<PolicyReport> = serde_json::from_value(run.report.clone()).unwrap_or_default();
+        if reports.iter().all(|r| r.matched == 0) {
+            RunEntity::delete_by_id(run.id).exec(db).await?;
+        }
+    }
+    lock.commit().await
+}
+
+/// Applique les politiques de l'entité et enregistre le rapport du passage.
+/// En simulation, le rapport liste les dossiers concernés sans les modifier,
+/// politiques par défaut non confirmées comprises.
+pub async fn run(
+    db: &DatabaseConnection,
+    config: &Config,
+    entity_id: Uuid,
+    triggered_by: Option<Uuid>,
+    dry_run: bool,
+) -> Result<rgpd_retention_run::Model, DbErr> {
+    let started_at = Utc::now();
+    let run = RunActiveModel {
+        id: Set(Uuid::new_v4()),
+        entity_id: Set(entity_id),
+        triggered_by: Set(triggered_by),
+        dry_run: Set(dry_run),
+        status: Set(RUN_RUNNING.to_string()),
+        report: Set(serde_json::json!([])),
+        error: Set(None),
+        started_at: Set(started_at),
+        finished_at: Set(None),
+    }
+    .insert(db)
+    .await?;
+
+    let result = apply(db, entity_id, started_at, dry_run).await;
+
+    let mut run = run.into_active_model();
+    match result {
+        Ok((reports, files)) => {
+            // Fichiers supprimés après validation : un passage annulé n'en perd aucun
+            for (storage_type, path) in files {
+                let storage = storage::for_type(config, &storage_type).await;
+                if let Err(e) = storage.delete_file(&path).await {
+                    log::warn!("Retention for entity {}: cannot delete {}: {}", entity_id, path, e);
+                }
+            }
+            run.status = Set(RUN_COMPLETED.to_string());
+            run.report = Set(serde_json::to_value(reports).unwrap_or_default());
+        }
+        Err(e) => {
+            run.status = Set(RUN_FAILED.to_string());
+            run.error = Set(Some(e.to_string()));
+        }
+    }
+    run.finished_at = Set(Some(Utc::now()));
+    run.update(db).await
+}
+
+type Files = Vec<(String, String)>;
+
+async fn apply(
+    db: &DatabaseConnection,
+    entity_id: Uuid,
+    now: DateTime<Utc>,
+    dry_run: bool,
+) -> Result<(Vec<PolicyReport>, Files), DbErr> {
+    let txn = db.begin().await?;
+    let mut reports = Vec::new();
+    let mut files = Vec::new();
+
+    let policies = effective_policies(&txn, entity_id).await?.into_iter()
+        .filter(|p| p.enabled || (dry_run && p.is_default));
+    for policy in policies {
+        let cutoff = now.checked_sub_months(Months::new(policy.duration_months.max(0) as u32)).unwrap_or(now);
+        let (record_ids, deleted) = if policy.record_type == RECORD_TYPE_BREACH {
+            apply_to_breaches(&txn, entity_id, &policy, cutoff, dry_run).await?
+        } else {
+            (apply_to_access_requests(&txn, entity_id, &policy, cutoff, dry_run).await?, Vec::new())
+        };
+        reports.push(PolicyReport {
+            record_type: policy.record_type,
+            trigger: policy.trigger,
+            action: policy.action,
+            duration_months: policy.duration_months,
+            is_default: policy.is_default,
+            cutoff,
+            matched: record_ids.len(),
+            processed: if dry_run { 0 } else { record_ids.len() },
+            record_ids,
+            files_deleted: deleted.len(),
+        });
+        files.extend(deleted);
+    }
+
+    if dry_run {
+        txn.rollback().await?;
+        return Ok((reports, Vec::new()));
+    }
+    txn.commit().await?;
+    Ok((reports, files))
+}
+
+async fn apply_to_access_requests<C: ConnectionTrait>(
+    db: &C,
+    entity_id: Uuid,
+    policy: &RetentionPolicy,
+    cutoff: DateTime<Utc>,
+    dry_run: bool,
+) -> Result<Vec<Uuid>, DbErr> {
+    let since = if policy.trigger == TRIGGER_CLOSED { AccessRequestColumn::CompletedAt } else { AccessRequestColumn::CreatedAt };
+    let mut query = AccessRequestEntity::find()
+        .select_only()
+        .column(AccessRequestColumn::Id)
+        .filter(AccessRequestColumn::EntityId.eq(entity_id))
+        .filter(AccessRequestColumn::Status.is_in(ACCESS_REQUEST_CLOSED_STATUSES.iter().copied()))
+        .filter(AccessRequestColumn::CompletedAt.is_not_null())
+        .filter(since.lt(cutoff));
+    if policy.action == ACTION_ANONYMIZE {
+        query = query.filter(AccessRequestColumn::AnonymizedAt.is_null());
+    } else if policy.action == ACTION_ARCHIVE {
+        query = query.filter(AccessRequestColumn::ArchivedAt.is_null());
+    }
+    let ids: Vec<Uuid> = query.into_tuple().all(db).await?;
+    if dry_run || ids.is_empty() {
+        return Ok(ids);
+    }
+
+    if policy.action == ACTION_DELETE {
+        AccessRequestEntity::delete_many()
+            .filter(AccessRequestColumn::Id.is_in(ids.clone()))
+            .exec(db)
+            .await?;
+    } else if policy.action == ACTION_ARCHIVE {
+        // Le dossier est conservé intact, hors des vues courantes
+        AccessRequestEntity::update_many()
+            .col_expr(AccessRequestColumn::ArchivedAt, Expr::value(Utc::now()))
+            .filter(AccessRequestColumn::Id.is_in(ids.clone()))
+            .exec(db)
+            .await?;
+    } else {
+        // Seuls le type, le statut et les dates subsistent, pour les statistiques
+        AccessRequestEntity::update_many()
+            .col_expr(AccessRequestColumn::RequesterName, Expr::value(ANONYMIZED))
+            .col_expr(AccessRequestColumn::RequesterEmail, Expr::value(""))
+            .col_expr(AccessRequestColumn::Description, Expr::value(Option::<String>::None))
+            .col_expr(AccessRequestColumn::Response, Expr::value(Option::<String>::None))
+            .col_expr(AccessRequestColumn::AnonymizedAt, Expr::value(Utc::now()))
+            .filter(AccessRequestColumn::Id.is_in(ids.clone()))
+            .exec(db)
+            .await?;
+    }
+    Ok(ids)
+}
+
+async fn apply_to_breaches<C: ConnectionTrait>(
+    db: &C,
+    entity_id: Uuid,
+    policy: &RetentionPolicy,
+    cutoff: DateTime<Utc>,
+    dry_run: bool,
+) -> Result<(Vec<Uuid>, Files), DbErr> {
+    let since = if policy.trigger == TRIGGER_CLOSED { BreachColumn::ClosedAt } else { BreachColumn::DiscoveryDate };
+    let mut query = BreachEntity::find()
+        .select_only()
+        .column(BreachColumn::Id)
+        .filter(BreachColumn::EntityId.eq(entity_id))
+        .filter(BreachColumn::Status.is_in(BREACH_CLOSED_STATUSES.iter().copied()))
+        .filter(BreachColumn::ClosedAt.is_not_null())
+        .filter(since.lt(cutoff));
+    if policy.action == ACTION_ANONYMIZE {
+        query = query.filter(BreachColumn::AnonymizedAt.is_null());
+    } else if policy.action == ACTION_ARCHIVE {
+        query = query.filter(BreachColumn::ArchivedAt.is_null());
+    }
+    let ids: Vec<Uuid> = query.into_tuple().all(db).await?;
+
+    if policy.action == ACTION_ARCHIVE {
+        // Archivage intermédiaire : écart et pièces jointes conservés intacts
+        if !dry_run && !ids.is_empty() {
+            BreachEntity::update_many()
+                .col_expr(BreachColumn::ArchivedAt, Expr::value(Utc::now()))
+                .filter(BreachColumn::Id.is_in(ids.clone()))
+                .exec(db)
+                .await?;
+        }
+        return Ok((ids, Vec::new()));
+    }
+
+    // Les pièces jointes disparaissent à la suppression comme à l'anonymisation
+    let files: Files = AttachmentEntity::find()
+        .filter(AttachmentColumn::BreachId.is_in(ids.clone()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|a| (a.storage_type, a.file_path))
+        .collect();
+    if dry_run || ids.is_empty() {
+        return Ok((ids, files));
+    }
+
+    if policy.action == ACTION_DELETE {
+        BreachEntity::delete_many()
+            .filter(BreachColumn::Id.is_in(ids.clone()))
+            .exec(db)
+            .await?;
+    } else {
+        AttachmentEntity::delete_many()
+            .filter(AttachmentColumn::BreachId.is_in(ids.clone()))
+            .exec(db)
+            .await?;
+        // Dates, gravité, catégories et nombre de personnes restent pour le bilan
+        BreachEntity::update_many()
+            .col_expr(BreachColumn::Description, Expr::value(ANONYMIZED))
+            .col_expr(BreachColumn::ContainmentMeasures, Expr::value(Option::<String>::None))
+            .col_expr(BreachColumn::AssessmentJustification, Expr::value(Option::<String>::None))
+            .col_expr(BreachColumn::AnonymizedAt, Expr::value(Utc::now()))
+            .filter(BreachColumn::Id.is_in(ids.clone()))
+            .exec(db)
+            .await?;
+    }
+    Ok((ids, files))
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::{Validate, ValidationError};
+use crate::validation::one_of;
+use crate::entities::rgpd::models::{AccessRequest, Breach};
+use crate::entities_orm::rgpd_retention_run;
+
+pub const RECORD_TYPE_ACCESS_REQUEST: &str = "access_request";
+pub const RECORD_TYPE_BREACH: &str = "breach";
+pub const RECORD_TYPES: &[&str] = &[RECORD_TYPE_ACCESS_REQUEST, RECORD_TYPE_BREACH];
+
+/// Événements déclencheurs : la clôture, ou la création (réception d'une
+/// demande, découverte d'une violation). Seuls les dossiers clos sont traités.
+pub const TRIGGER_CLOSED: &str = "closed";
+pub const ACCESS_REQUEST_TRIGGERS: &[&str] = &[TRIGGER_CLOSED, "created"];
+pub const BREACH_TRIGGERS: &[&str] = &[TRIGGER_CLOSED, "discovered"];
+
+pub const ACTION_DELETE: &str = "delete";
+pub const ACTION_ANONYMIZE: &str = "anonymize";
+pub const ACTION_ARCHIVE: &str = "archive";
+// Mêmes sorts finals que `taxonomy::RETENTION_ACTIONS`
+pub const ACTIONS: &[&str] = &[ACTION_DELETE, ACTION_ANONYMIZE, ACTION_ARCHIVE];
+
+pub const RUN_RUNNING: &str = "running";
+pub const RUN_COMPLETED: &str = "completed";
+pub const RUN_FAILED: &str = "failed";
+
+pub fn triggers(record_type: &str) -> &'static [&'static str] {
+    if record_type == RECORD_TYPE_BREACH { BREACH_TRIGGERS } else { ACCESS_REQUEST_TRIGGERS }
+}
+
+fn validate_action(value: &str) -> Result<(), ValidationError> {
+    one_of(value, ACTIONS)
+}
+
+/// Politique applicable à un type d'enregistrement ; `is_default` signale
+/// qu'aucune politique propre à l'entité n'a été enregistrée.
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionPolicy {
+    pub record_type: String,
+    pub duration_months: i32,
+    pub trigger: String,
+    pub action: String,
+    pub enabled: bool,
+    pub is_default: bool,
+    pub register_entry_id: Option<Uuid>, // Fiche du registre dont la règle s'applique
+    pub updated_by: Option<Uuid>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Sans `register_entry_id`, la durée, le déclencheur et l'action sont
+/// obligatoires ; avec, ils sont repris de la fiche du registre.
+#[derive(Debug, Deserialize, Validate)]
+pub struct RetentionPolicyRequest {
+    #[validate(range(min = 1, max = 1200))]
+    pub duration_months: Option<i32>,
+    pub trigger: Option<String>, // Contrôlé selon le type d'enregistrement
+    #[validate(custom(function = "validate_action"))]
+    pub action: Option<String>,
+    pub enabled: Option<bool>,
+    pub register_entry_id: Option<Uuid>,
+}
+
+impl RetentionPolicyRequest {
+    pub fn validate_trigger(&self, record_type: &str) -> Result<(), ValidationError> {
+        self.trigger.as_deref().map_or(Ok(()), |trigger| one_of(trigger, triggers(record_type)))
+    }
+}
+
+/// Déclencheur de la politique correspondant au point de départ d'une fiche
+/// du registre : la collecte correspond à la création du dossier, les autres
+/// événements (fin de la relation, dernier contact…) à sa clôture.
+pub fn trigger_from_register(record_type: &str, register_trigger: &str) -> &'static str {
+    if register_trigger == "collection" {
+        triggers(record_type)[1]
+    } else {
+        TRIGGER_CLOSED
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PolicyPath {
+    pub record_type: String,
+}
+
+pub fn validate_record_type(value: &str) -> Result<(), ValidationError> {
+    one_of(value, RECORD_TYPES)
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct StartRunRequest {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Résultat d'une politique lors d'un passage.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PolicyReport {
+    pub record_type: String,
+    pub trigger: String,
+    pub action: String,
+    pub duration_months: i32,
+    #[serde(default)]
+    pub is_default: bool, // Politique par défaut non confirmée, simulée seulement
+    pub cutoff: DateTime<Utc>, // Dossiers dont l'événement déclencheur est antérieur
+    pub matched: usize,
+    pub processed: usize, // Nul en simulation
+    pub record_ids: Vec<Uuid>,
+    pub files_deleted: usize, // En simulation, fichiers qui seraient supprimés
+}
+
+#[derive(Debug, Serialize)]
+pub struct RetentionRun {
+    pub id: Uuid,
+    pub entity_id: Uuid,
+    pub triggered_by: Option<Uuid>, // Absent pour les passages planifiés
+    pub dry_run: bool,
+    pub status: String, // "running", "completed", "failed"
+    pub report: Vec<PolicyReport>,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl From<rgpd_retention_run::Model> for RetentionRun {
+    fn from(model: rgpd_retention_run::Model) -> Self {
+        Self {
+            id: model.id,
+            entity_id: model.entity_id,
+            triggered_by: model.triggered_by,
+            dry_run: model.dry_run,
+            status: model.status,
+            report: serde_json::from_value(model.report).unwrap_or_default(),
+            error: model.error,
+            started_at: model.started_at,
+            finished_at: model.finished_at,
+        }
+    }
+}
+
+/// Dossiers placés en archivage intermédiaire, consultables par les seuls
+/// administrateurs de l'entité.
+#[derive(Debug, Serialize)]
+pub struct RetentionArchive {
+    pub access_requests: Vec<AccessRequest>,
+    pub breaches: Vec<Breach>,
+}
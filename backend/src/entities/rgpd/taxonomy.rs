@@ -0,0 +1,430 @@
+//! Nomenclatures du registre : bases légales de l'article 6, exceptions de
+//! l'article 9(2), typologie des catégories de données (données sensibles et
+//! infractions) avec leur durée de conservation recommandée, et règles de
+//! cohérence appliquées aux fiches. Les règles de conservation se composent
+//! d'une durée, d'un événement déclencheur et d'un sort final des données.
+//! Les transferts hors EEE s'apprécient au regard des décisions d'adéquation.
+use actix_web::HttpResponse;
+use serde::Serialize;
+use uuid::Uuid;
+use crate::entities::rgpd::{breach_assessment, breach_documents};
+use crate::entities::rgpd::models::{RegisterCheck, RegisterEntry, Transfer};
+use crate::entities::rgpd::processors::models::{DPA_EXPIRED, DPA_MISSING};
+use crate::errors::{ApiError, FieldError};
+
+#[derive(Debug, Serialize)]
+pub struct Term {
+    pub key: &'static str,
+    pub article: &'static str,
+    pub label: &'static str,
+}
+
+pub const LEGAL_BASES: &[Term] = &[
+    Term { key: "consent", article: "6(1)(a)", label: "Consentement" },
+    Term { key: "contract", article: "6(1)(b)", label: "Exécution d'un contrat" },
+    Term { key: "legal_obligation", article: "6(1)(c)", label: "Obligation légale" },
+    Term { key: "vital_interests", article: "6(1)(d)", label: "Sauvegarde des intérêts vitaux" },
+    Term { key: "public_task", article: "6(1)(e)", label: "Mission d'intérêt public" },
+    Term { key: "legitimate_interests", article: "6(1)(f)", label: "Intérêt légitime" },
+];
+
+pub const SPECIAL_CATEGORY_EXCEPTIONS: &[Term] = &[
+    Term { key: "explicit_consent", article: "9(2)(a)", label: "Consentement explicite" },
+    Term { key: "employment_social_protection", article: "9(2)(b)", label: "Droit du travail et protection sociale" },
+    Term { key: "vital_interests", article: "9(2)(c)", label: "Sauvegarde des intérêts vitaux" },
+    Term { key: "non_profit_body", article: "9(2)(d)", label: "Organisme à but non lucratif, auprès de ses membres" },
+    Term { key: "manifestly_made_public", article: "9(2)(e)", label: "Données rendues publiques par la personne" },
+    Term { key: "legal_claims", article: "9(2)(f)", label: "Droits en justice" },
+    Term { key: "substantial_public_interest", article: "9(2)(g)", label: "Intérêt public important" },
+    Term { key: "health_social_care", article: "9(2)(h)", label: "Médecine, soins et prise en charge sociale" },
+    Term { key: "public_health", article: "9(2)(i)", label: "Santé publique" },
+    Term { key: "archiving_research", article: "9(2)(j)", label: "Archives, recherche scientifique ou historique, statistiques" },
+];
+
+#[derive(Debug, Serialize)]
+pub struct Choice {
+    pub key: &'static str,
+    pub label: &'static str,
+}
+
+pub const RETENTION_TRIGGERS: &[Choice] = &[
+    Choice { key: "collection", label: "Collecte des données" },
+    Choice { key: "last_contact", label: "Dernier contact avec la personne" },
+    Choice { key: "end_of_contract", label: "Fin de la relation contractuelle" },
+    Choice { key: "account_closure", label: "Clôture du compte" },
+    Choice { key: "other", label: "Autre événement, précisé dans la durée de conservation" },
+];
+
+pub const RETENTION_ACTIONS: &[Choice] = &[
+    Choice { key: "delete", label: "Suppression" },
+    Choice { key: "anonymize", label: "Anonymisation" },
+    Choice { key: "archive", label: "Archivage intermédiaire" },
+];
+
+/// États membres de l'Espace économique européen : les transferts vers un
+/// pays tiers doivent être encadrés (Art. 44 et suivants).
+pub const EEA_COUNTRIES: &[&str] = &[
+    "AT", "BE", "BG", "CY", "CZ", "DE", "DK", "EE", "ES", "FI", "FR", "GR", "HR", "HU", "IE",
+    "IS", "IT", "LI", "LT", "LU", "LV", "MT", "NL", "NO", "PL", "PT", "RO", "SE", "SI", "SK",
+];
+
+/// Décision d'adéquation de la Commission (Art. 45) ; `scope` précise une
+/// adéquation partielle, limitée à certains destinataires.
+#[derive(Debug, Serialize)]
+pub struct AdequacyDecision {
+    pub country: &'static str,
+    pub name: &'static str,
+    pub decision: &'static str,
+    pub scope: Option<&'static str>,
+}
+
+const fn adequacy(country: &'static str, name: &'static str, decision: &'static str, scope: Option<&'static str>) -> AdequacyDecision {
+    AdequacyDecision { country, name, decision, scope }
+}
+
+/// Liste tenue à jour dans le dépôt, à revoir à chaque décision de la
+/// Commission (adoption, modification ou invalidation).
+pub const ADEQUACY_LIST_UPDATED: &str = "2026-10-01";
+
+pub const ADEQUACY_DECISIONS: &[AdequacyDecision] = &[
+    adequacy("AD", "Andorre", "2010/625/UE", None),
+    adequacy("AR", "Argentine", "2003/490/CE", None),
+    adequacy("CA", "Canada", "2002/2/CE", Some("Organisations commerciales soumises à la PIPEDA")),
+    adequacy("CH", "Suisse", "2000/518/CE", None),
+    adequacy("FO", "Îles Féroé", "2010/146/UE", None),
+    adequacy("GB", "Royaume-Uni", "2021/1772", None),
+    adequacy("GG", "Guernesey", "2003/821/CE", None),
+    adequacy("IL", "Israël", "2011/61/UE", None),
+    adequacy("IM", "Île de Man", "2004/411/CE", None),
+    adequacy("JE", "Jersey", "2008/393/CE", None),
+    adequacy("JP", "Japon", "2019/419", Some("Secteur privé")),
+    adequacy("KR", "République de Corée", "2022/254", None),
+    adequacy("NZ", "Nouvelle-Zélande", "2013/65/UE", None),
+    adequacy("US", "États-Unis", "2023/1795", Some("Organisations certifiées au Data Privacy Framework UE-États-Unis")),
+    adequacy("UY", "Uruguay", "2012/484/UE", None),
+];
+
+pub fn is_third_country(country: &str) -> bool {
+    !EEA_COUNTRIES.contains(&country)
+}
+
+pub fn find_adequacy(country: &str) -> Option<&'static AdequacyDecision> {
+    ADEQUACY_DECISIONS.iter().find(|d| d.country == country)
+}
+
+/// Pays tiers sans décision d'adéquation : tout transfert y exige une
+/// garantie de l'article 46 ou une dérogation de l'article 49.
+pub fn needs_transfer_tool(country: &str) -> bool {
+    is_third_country(country) && find_adequacy(country).is_none()
+}
+
+/// Modules des clauses contractuelles types (décision 2021/914).
+pub const SCC_MODULES: &[Choice] = &[
+    Choice { key: "module_1", label: "Module 1 : responsable vers responsable" },
+    Choice { key: "module_2", label: "Module 2 : responsable vers sous-traitant" },
+    Choice { key: "module_3", label: "Module 3 : sous-traitant vers sous-traitant" },
+    Choice { key: "module_4", label: "Module 4 : sous-traitant vers responsable" },
+];
+
+/// Conclusion de l'évaluation d'impact du transfert (TIA).
+pub const TIA_OUTCOMES: &[Choice] = &[
+    Choice { key: "adequate", label: "Protection équivalente sans mesure supplémentaire" },
+    Choice { key: "adequate_with_measures", label: "Protection équivalente grâce aux mesures supplémentaires" },
+    Choice { key: "not_adequate", label: "Protection insuffisante : transfert à suspendre" },
+];
+
+/// Canaux de recueil et de retrait du consentement.
+pub const CONSENT_CHANNELS: &[Choice] = &[
+    Choice { key: "web_form", label: "Formulaire web" },
+    Choice { key: "mobile_app", label: "Application mobile" },
+    Choice { key: "email", label: "Courrier électronique" },
+    Choice { key: "phone", label: "Téléphone" },
+    Choice { key: "paper", label: "Formulaire papier" },
+    Choice { key: "in_person", label: "En présentiel" },
+    Choice { key: "other", label: "Autre canal" },
+];
+
+/// Traitement fondé sur le consentement (Art. 6(1)(a)) ou sur le consentement
+/// explicite de l'article 9(2)(a).
+pub fn is_consent_based(legal_basis: Option<&str>, special_category_exception: Option<&str>) -> bool {
+    legal_basis == Some("consent") || special_category_exception == Some("explicit_consent")
+}
+
+// Les outils de l'article 46 supposent une évaluation du droit du pays de destination
+const TIA_SAFEGUARDS: &[&str] = &["scc", "bcr", "other"];
+
+/// Points d'attention d'un transfert, communs aux fiches du registre et aux
+/// fiches fournisseur : (règle, message).
+pub fn transfer_issues(transfer: &Transfer) -> Vec<(&'static str, String)> {
+    let mut issues = Vec::new();
+    if transfer.safeguard == "adequacy" {
+        match find_adequacy(&transfer.country) {
+            None => issues.push(("adequacy_decision_missing", format!(
+                "No adequacy decision covers {}; another transfer tool is required", transfer.country,
+            ))),
+            Some(AdequacyDecision { scope: Some(scope), .. }) => issues.push(("adequacy_partial", format!(
+                "The adequacy decision for {} only covers: {}; check that the recipient is within its scope", transfer.country, scope,
+            ))),
+            Some(_) => {}
+        }
+    }
+    if TIA_SAFEGUARDS.contains(&transfer.safeguard.as_str()) && transfer.tia_outcome.is_none() {
+        issues.push(("tia_missing", format!(
+            "The transfer to {} relies on an Article 46 safeguard but no transfer impact assessment is recorded", transfer.country,
+        )));
+    }
+    if transfer.tia_outcome.as_deref() == Some("not_adequate") {
+        issues.push(("tia_unfavourable", format!(
+            "The transfer impact assessment for {} concluded that protection is not adequate", transfer.country,
+        )));
+    }
+    issues
+}
+
+/// Un destinataire situé dans un pays tiers non adéquat est couvert par un
+/// transfert qui le désigne, ou par un transfert vers son pays sans tiers précisé.
+pub fn transfer_covers(transfers: &[Transfer], party_id: Uuid, country: &str) -> bool {
+    transfers.iter().any(|t| t.party_id == Some(party_id) || (t.party_id.is_none() && t.country == country))
+}
+
+/// Type de catégorie de données ; `special` vaut `art9` pour les catégories
+/// particulières et `art10` pour les condamnations et infractions.
+#[derive(Debug, Serialize)]
+pub struct DataCategoryKind {
+    pub key: &'static str,
+    pub label: &'static str,
+    pub special: Option<&'static str>,
+    pub retention_ceiling_months: Option<i32>, // Durée recommandée, modifiable par catégorie
+}
+
+const fn kind(key: &'static str, label: &'static str, special: Option<&'static str>, ceiling: Option<i32>) -> DataCategoryKind {
+    DataCategoryKind { key, label, special, retention_ceiling_months: ceiling }
+}
+
+pub const DATA_CATEGORY_KINDS: &[DataCategoryKind] = &[
+    kind("identification", "État civil, identité, données d'identification", None, Some(36)),
+    kind("personal_life", "Vie personnelle", None, Some(36)),
+    kind("professional_life", "Vie professionnelle", None, Some(60)),
+    kind("economic_financial", "Informations d'ordre économique et financier", None, Some(120)),
+    kind("connection_data", "Données de connexion", None, Some(12)),
+    kind("location_data", "Données de localisation", None, Some(12)),
+    kind("online_identifiers", "Identifiants en ligne et traceurs", None, Some(13)),
+    kind("national_identifier", "Numéro d'inscription au répertoire (NIR)", None, None),
+    kind("racial_ethnic_origin", "Origine raciale ou ethnique", Some("art9"), Some(12)),
+    kind("political_opinions", "Opinions politiques", Some("art9"), Some(36)),
+    kind("religious_beliefs", "Convictions religieuses ou philosophiques", Some("art9"), Some(36)),
+    kind("trade_union_membership", "Appartenance syndicale", Some("art9"), Some(36)),
+    kind("genetic", "Données génétiques", Some("art9"), Some(240)),
+    kind("biometric", "Données biométriques aux fins d'identification", Some("art9"), Some(12)),
+    kind("health", "Données de santé", Some("art9"), Some(240)),
+    kind("sex_life_orientation", "Vie sexuelle ou orientation sexuelle", Some("art9"), Some(12)),
+    kind("criminal_offences", "Condamnations pénales et infractions", Some("art10"), Some(60)),
+    kind("other", "Autres données", None, None),
+];
+
+pub fn find_kind(key: &str) -> Option<&'static DataCategoryKind> {
+    DATA_CATEGORY_KINDS.iter().find(|k| k.key == key)
+}
+
+pub fn keys(terms: &[Term]) -> Vec<&'static str> {
+    terms.iter().map(|t| t.key).collect()
+}
+
+pub fn choice_keys(choices: &[Choice]) -> Vec<&'static str> {
+    choices.iter().map(|c| c.key).collect()
+}
+
+pub fn choice_label(choices: &[Choice], key: &str) -> Option<&'static str> {
+    choices.iter().find(|c| c.key == key).map(|c| c.label)
+}
+
+pub fn label(terms: &[Term], key: &str) -> Option<String> {
+    terms.iter().find(|t| t.key == key).map(|t| format!("{} (Art. {})", t.label, t.article))
+}
+
+fn is_blank(value: Option<&str>) -> bool {
+    value.is_none_or(|v| v.trim().is_empty())
+}
+
+fn error(field: &str, rule: &str, message: &str) -> RegisterCheck {
+    RegisterCheck {
+        rule: rule.to_string(),
+        severity: "error".to_string(),
+        field: Some(field.to_string()),
+        data_category_id: None,
+        message: message.to_string(),
+    }
+}
+
+fn warning(rule: &str, message: String) -> RegisterCheck {
+    RegisterCheck {
+        rule: rule.to_string(),
+        severity: "warning".to_string(),
+        field: None,
+        data_category_id: None,
+        message,
+    }
+}
+
+// Tiers destinataires des données ; le responsable, le DPO et le représentant n'en reçoivent pas
+const TRANSFER_ROLES: &[&str] = &["joint_controller", "processor", "recipient"];
+
+/// Règles de cohérence d'une fiche. Les erreurs bloquent l'enregistrement ;
+/// les avertissements sont seulement signalés.
+pub fn check_entry(entry: &RegisterEntry) -> Vec<RegisterCheck> {
+    let mut checks = Vec::new();
+    let art9 = entry.data_categories.iter().any(|c| c.special.as_deref() == Some("art9"));
+    let art10 = entry.data_categories.iter().any(|c| c.special.as_deref() == Some("art10"));
+
+    // Base légale, données sensibles et consentement relèvent du responsable
+    if entry.record_type == "controller" {
+        if entry.legal_basis.is_none() {
+            checks.push(error("legal_basis", "legal_basis_required",
+                "Controller records need one of the Article 6 legal bases"));
+        }
+        if art9 && entry.special_category_exception.is_none() {
+            checks.push(error("special_category_exception", "special_category_exception_required",
+                "Special category data (Art. 9) requires an Article 9(2) exception"));
+        }
+        if !art9 && entry.special_category_exception.is_some() {
+            checks.push(warning("special_category_exception_not_applicable",
+                "An Article 9(2) exception is recorded but no special category data is processed".to_string()));
+        }
+        if art10 && is_blank(entry.criminal_data_basis.as_deref()) {
+            checks.push(error("criminal_data_basis", "criminal_data_basis_required",
+                "Criminal offence data (Art. 10) requires the legal provision authorising the processing"));
+        }
+        let consent = is_consent_based(entry.legal_basis.as_deref(), entry.special_category_exception.as_deref());
+        if consent && is_blank(entry.consent_mechanism.as_deref()) {
+            checks.push(error("consent_mechanism", "consent_mechanism_required",
+                "Consent-based processing must describe how consent is collected, recorded and withdrawn"));
+        }
+        // Registre des consentements : texte de référence et personnes à solliciter à nouveau
+        if let Some(stats) = entry.consents.as_ref() {
+            if stats.current_version.is_none() {
+                checks.push(warning("consent_wording_missing",
+                    "No consent wording is recorded; the consent ledger cannot show what people agreed to".to_string()));
+            }
+            let outdated = stats.active - stats.active_on_current_version;
+            if stats.current_version.is_some() && outdated > 0 {
+                checks.push(warning("consent_wording_outdated", format!(
+                    "{} data subject(s) consented to an earlier wording and have not renewed their consent", outdated,
+                )));
+            }
+        }
+    }
+
+    // Durées recommandées : la plus courte des catégories traitées s'applique
+    let ceilings: Vec<_> = entry.data_categories.iter()
+        .filter_map(|c| c.retention_ceiling_months.map(|ceiling| (c, ceiling)))
+        .collect();
+    match entry.retention_months {
+        None if !ceilings.is_empty() => checks.push(warning("retention_months_missing",
+            "No retention duration is recorded; the processed data categories have recommended maximums".to_string())),
+        Some(months) => {
+            for (category, ceiling) in ceilings.into_iter().filter(|(_, ceiling)| months > *ceiling) {
+                let mut check = warning("retention_exceeds_ceiling", format!(
+                    "Retention of {} months exceeds the recommended {} months for \"{}\"",
+                    months, ceiling, category.name,
+                ));
+                check.data_category_id = Some(category.id);
+                checks.push(check);
+            }
+        }
+        None => {}
+    }
+
+    // Une règle de conservation n'est exploitable que complète
+    let rule = [entry.retention_months.is_some(), entry.retention_trigger.is_some(), entry.retention_action.is_some()];
+    if rule.contains(&true) && rule.contains(&false) {
+        checks.push(warning("retention_rule_incomplete",
+            "A retention rule needs a duration, a trigger event and an end-of-retention action".to_string()));
+    }
+
+    // Transferts hors EEE : outil de transfert et évaluation d'impact
+    for transfer in &entry.transfers {
+        for (rule, message) in transfer_issues(transfer) {
+            checks.push(warning(rule, message));
+        }
+    }
+    for party in entry.parties.iter().filter(|p| TRANSFER_ROLES.contains(&p.role.as_str())) {
+        let Some(country) = party.country.as_deref().filter(|c| needs_transfer_tool(c)) else {
+            continue;
+        };
+        let covered = transfer_covers(&entry.transfers, party.party_id, country)
+            || party.processor.as_ref().is_some_and(|p| p.transfer_countries.iter().any(|c| c == country));
+        if !covered {
+            let mut check = warning("transfer_mechanism_missing", format!(
+                "\"{}\" is located in {}, which has no adequacy decision, and no transfer mechanism is recorded", party.name, country,
+            ));
+            check.field = Some("transfers".to_string());
+            checks.push(check);
+        }
+    }
+
+    // Sous-traitants : fiche fournisseur et contrat en vigueur (Art. 28)
+    for party in entry.parties.iter().filter(|p| p.role == "processor") {
+        match &party.processor {
+            None => checks.push(warning("processor_not_registered", format!(
+                "Processor \"{}\" has no processor record", party.name,
+            ))),
+            Some(processor) if [DPA_MISSING, DPA_EXPIRED].contains(&processor.dpa_status.as_str()) => checks.push(warning(
+                "processor_dpa_invalid",
+                format!("Processor \"{}\" has no data processing agreement in force", party.name),
+            )),
+            Some(_) => {}
+        }
+    }
+
+    checks
+}
+
+/// Refuse une fiche présentant des erreurs de cohérence (422, une erreur par règle).
+pub fn ensure_consistent(entry: &RegisterEntry) -> Result<(), ApiError> {
+    let errors: Vec<FieldError> = entry.checks.iter()
+        .filter(|c| c.severity == "error")
+        .map(|c| FieldError::new(c.field.clone().unwrap_or_else(|| "body".to_string()), c.rule.clone(), c.message.clone()))
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::Validation(errors))
+    }
+}
+
+#[derive(Serialize)]
+struct Taxonomy {
+    legal_bases: &'static [Term],
+    special_category_exceptions: &'static [Term],
+    data_category_kinds: &'static [DataCategoryKind],
+    retention_triggers: &'static [Choice],
+    retention_actions: &'static [Choice],
+    eea_countries: &'static [&'static str],
+    adequacy_decisions: &'static [AdequacyDecision],
+    adequacy_list_updated: &'static str,
+    scc_modules: &'static [Choice],
+    tia_outcomes: &'static [Choice],
+    consent_channels: &'static [Choice],
+    breach_assessment: &'static breach_assessment::Scales,
+    breach_document_kinds: &'static [Choice],
+    breach_template_placeholders: &'static [Choice],
+}
+
+pub async fn get_taxonomy() -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(Taxonomy {
+        legal_bases: LEGAL_BASES,
+        special_category_exceptions: SPECIAL_CATEGORY_EXCEPTIONS,
+        data_category_kinds: DATA_CATEGORY_KINDS,
+        retention_triggers: RETENTION_TRIGGERS,
+        retention_actions: RETENTION_ACTIONS,
+        eea_countries: EEA_COUNTRIES,
+        adequacy_decisions: ADEQUACY_DECISIONS,
+        adequacy_list_updated: ADEQUACY_LIST_UPDATED,
+        scc_modules: SCC_MODULES,
+        tia_outcomes: TIA_OUTCOMES,
+        consent_channels: CONSENT_CHANNELS,
+        breach_assessment: &breach_assessment::SCALES,
+        breach_document_kinds: breach_documents::DOCUMENT_KINDS,
+        breach_template_placeholders: breach_documents::PLACEHOLDERS,
+    }))
+}
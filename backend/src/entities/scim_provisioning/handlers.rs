@@ -0,0 +1,180 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, Set, IntoActiveModel, ActiveModelTrait};
+use crate::config::Config;
+use crate::entities::scim_provisioning::models::*;
+use crate::errors::ApiError;
+use crate::validation::ValidatedJson;
+use crate::middleware::get_entity_role;
+use crate::auth::mfa;
+use crate::entities_orm::scim_token::{self, Entity as ScimTokenEntity, Column as ScimTokenColumn, ActiveModel as ScimTokenActiveModel};
+use crate::entities_orm::scim_user::{Entity as ScimUserEntity, Column as ScimUserColumn};
+use crate::scim::{auth as scim_auth, models as scim_models, provisioning};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+async fn require_entity_admin(
+    db: &DatabaseConnection,
+    req: &HttpRequest,
+    entity_id: Uuid,
+) -> Result<(), ApiError> {
+    if get_entity_role(req, entity_id).as_deref() != Some("admin") {
+        return Err(ApiError::Forbidden("Admin access required".to_string()));
+    }
+    mfa::enforce_entity_mfa(db, req, entity_id).await
+}
+
+fn to_dto(config: &Config, client: &scim_token::Model) -> ScimClient {
+    ScimClient {
+        entity_id: client.entity_id,
+        base_url: scim_models::base_url(config),
+        role_mapping: provisioning::role_mapping(client),
+        default_role: client.default_role.clone(),
+        created_at: client.created_at,
+        last_used_at: client.last_used_at,
+        expires_at: client.expires_at,
+    }
+}
+
+fn check_expiry(expires_at: Option<DateTime<Utc>>) -> Result<(), ApiError> {
+    if expires_at.is_some_and(|t| t <= Utc::now()) {
+        return Err(ApiError::validation("expires_at", "in_past", "Expiration date must be in the future"));
+    }
+    Ok(())
+}
+
+async fn find_client(db: &DatabaseConnection, entity_id: Uuid) -> Result<Option<scim_token::Model>, ApiError> {
+    Ok(ScimTokenEntity::find()
+        .filter(ScimTokenColumn::EntityId.eq(entity_id))
+        .one(db)
+        .await?)
+}
+
+// Nouvelle correspondance groupes -> rôles appliquée aux comptes déjà provisionnés
+async fn resync_memberships(db: &DatabaseConnection, client: &scim_token::Model) -> Result<(), ApiError> {
+    let users = ScimUserEntity::find()
+        .filter(ScimUserColumn::EntityId.eq(client.entity_id))
+        .all(db)
+        .await?;
+    for scim_user in users {
+        provisioning::sync_membership(db, client, &scim_user).await?;
+    }
+    Ok(())
+}
+
+pub async fn get_scim_client(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let client = find_client(db.get_ref(), entity_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("SCIM provisioning is not configured for this entity".to_string()))?;
+    Ok(HttpResponse::Ok().json(to_dto(&config, &client)))
+}
+
+/// Active le provisioning SCIM de l'entité (le jeton est renvoyé une seule
+/// fois) ou met à jour sa configuration.
+pub async fn upsert_scim_client(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: ValidatedJson<UpdateScimClientRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+    check_expiry(body.expires_at)?;
+
+    match find_client(db.get_ref(), entity_id).await? {
+        Some(existing) => {
+            let mut client = existing.into_active_model();
+            if let Some(role_mapping) = &body.role_mapping {
+                client.role_mapping = Set(serde_json::json!(role_mapping));
+            }
+            if let Some(default_role) = &body.default_role {
+                client.default_role = Set(default_role.trim().to_string());
+            }
+            if let Some(expires_at) = body.expires_at {
+                client.expires_at = Set(Some(expires_at));
+            }
+            let client = client.update(db.get_ref()).await?;
+            resync_memberships(db.get_ref(), &client).await?;
+
+            Ok(HttpResponse::Ok().json(to_dto(&config, &client)))
+        }
+        None => {
+            let (token, token_hash) = scim_auth::generate_token();
+            let client = ScimTokenActiveModel {
+                id: Set(Uuid::new_v4()),
+                entity_id: Set(entity_id),
+                token_hash: Set(token_hash),
+                role_mapping: Set(serde_json::json!(body.role_mapping.clone().unwrap_or_default())),
+                default_role: Set(body.default_role.as_deref().map(str::trim).unwrap_or("member").to_string()),
+                created_at: Set(Utc::now()),
+                last_used_at: Set(None),
+                expires_at: Set(body.expires_at),
+            };
+            let client = ScimTokenEntity::insert(client)
+                .exec_with_returning(db.get_ref())
+                .await?;
+
+            Ok(HttpResponse::Created().json(ScimClientWithToken {
+                client: to_dto(&config, &client),
+                token,
+            }))
+        }
+    }
+}
+
+/// Remplace le jeton SCIM ; l'ancien est refusé immédiatement.
+pub async fn rotate_scim_token(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: ValidatedJson<RotateScimTokenRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+    check_expiry(body.expires_at)?;
+
+    let existing = find_client(db.get_ref(), entity_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("SCIM provisioning is not configured for this entity".to_string()))?;
+
+    let (token, token_hash) = scim_auth::generate_token();
+    let mut client = existing.into_active_model();
+    client.token_hash = Set(token_hash);
+    client.expires_at = Set(body.expires_at);
+    client.last_used_at = Set(None);
+    let client = client.update(db.get_ref()).await?;
+
+    Ok(HttpResponse::Ok().json(ScimClientWithToken {
+        client: to_dto(&config, &client),
+        token,
+    }))
+}
+
+/// Désactive le provisioning : le jeton est supprimé, les comptes et
+/// rattachements déjà provisionnés sont conservés.
+pub async fn delete_scim_client(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let client = find_client(db.get_ref(), entity_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("SCIM provisioning is not configured for this entity".to_string()))?;
+    ScimTokenEntity::delete_by_id(client.id)
+        .exec(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
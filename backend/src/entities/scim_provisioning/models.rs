@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+use crate::entities::identity_providers::models::RoleMappingRule;
+use crate::entities::models::validate_entity_role;
+
+// Le jeton n'est jamais renvoyé, sauf à sa création
+#[derive(Debug, Serialize)]
+pub struct ScimClient {
+    pub entity_id: Uuid,
+    pub base_url: String,
+    pub role_mapping: Vec<RoleMappingRule>,
+    pub default_role: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScimClientWithToken {
+    #[serde(flatten)]
+    pub client: ScimClient,
+    pub token: String,
+}
+
+// role_mapping : groupe SCIM (displayName) -> rôle dans l'entité
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateScimClientRequest {
+    #[validate(length(max = 100), nested)]
+    pub role_mapping: Option<Vec<RoleMappingRule>>,
+    #[validate(custom(function = "validate_entity_role"))]
+    pub default_role: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RotateScimTokenRequest {
+    pub expires_at: Option<DateTime<Utc>>,
+}
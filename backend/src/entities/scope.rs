@@ -0,0 +1,51 @@
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use futures::future::LocalBoxFuture;
+use sea_orm::DatabaseConnection;
+use serde::Deserialize;
+use uuid::Uuid;
+use crate::auth::mfa;
+use crate::errors::ApiError;
+use crate::middleware::get_entity_role;
+
+/// Entité désignée par le segment `{entity_id}` du chemin, après contrôle de
+/// l'appartenance de l'utilisateur et de la politique MFA de l'entité. Seule
+/// source de l'entité pour les routes imbriquées `/entities/{entity_id}/...`.
+#[derive(Debug, Clone)]
+pub struct EntityScope {
+    pub entity_id: Uuid,
+}
+
+impl EntityScope {
+    pub async fn resolve(db: &DatabaseConnection, req: &HttpRequest, entity_id: Uuid) -> Result<Self, ApiError> {
+        if get_entity_role(req, entity_id).is_none() {
+            return Err(ApiError::Forbidden("Access denied".to_string()));
+        }
+        mfa::enforce_entity_mfa(db, req, entity_id).await?;
+        Ok(Self { entity_id })
+    }
+}
+
+impl FromRequest for EntityScope {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            let entity_id = req.match_info().get("entity_id")
+                .ok_or_else(|| ApiError::Internal("Route has no {entity_id} segment".to_string()))?;
+            let entity_id = Uuid::parse_str(entity_id)
+                .map_err(|_| ApiError::BadRequest("Invalid path parameter: entity_id".to_string()))?;
+            let db = req.app_data::<web::Data<DatabaseConnection>>()
+                .ok_or_else(|| ApiError::Internal("Database not configured".to_string()))?;
+            Ok(Self::resolve(db.get_ref(), &req, entity_id).await?)
+        })
+    }
+}
+
+/// Identifiant d'un enregistrement sous une route d'entité (`.../{id}`) ; le
+/// segment `entity_id` est lu par [`EntityScope`].
+#[derive(Debug, Deserialize)]
+pub struct ScopedId {
+    pub id: Uuid,
+}
@@ -0,0 +1,242 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use bcrypt::{hash, DEFAULT_COST};
+use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, QueryOrder, Set, TransactionTrait};
+use crate::auth::models::{ApiTokenResponse, CreateApiTokenRequest, CreatedApiTokenResponse};
+use crate::auth::{api_tokens, mfa};
+use crate::config::Config;
+use crate::entities::service_accounts::models::*;
+use crate::errors::ApiError;
+use crate::validation::ValidatedJson;
+use crate::middleware::{get_current_user_id, get_entity_role};
+use crate::entities_orm::api_token::{Entity as ApiTokenEntity, Column as ApiTokenColumn};
+use crate::entities_orm::service_account::{self, Entity as ServiceAccountEntity, Column as ServiceAccountColumn, ActiveModel as ServiceAccountActiveModel};
+use crate::entities_orm::user::{Entity as UserEntity, ActiveModel as UserActiveModel};
+use crate::entities_orm::user_entity::{Entity as UserEntityEntity, Column as UserEntityColumn, ActiveModel as UserEntityActiveModel};
+use uuid::Uuid;
+use chrono::Utc;
+
+// Domaine réservé (RFC 2606) : aucun email ne peut être envoyé à un compte de service
+const SERVICE_ACCOUNT_EMAIL_DOMAIN: &str = "service-accounts.invalid";
+
+async fn require_entity_admin(
+    db: &DatabaseConnection,
+    req: &HttpRequest,
+    entity_id: Uuid,
+) -> Result<Uuid, ApiError> {
+    let user_id = get_current_user_id(req)
+        .ok_or_else(|| ApiError::Unauthorized("Not authenticated".to_string()))?;
+
+    if get_entity_role(req, entity_id).as_deref() != Some("admin") {
+        return Err(ApiError::Forbidden("Admin access required".to_string()));
+    }
+    mfa::enforce_entity_mfa(db, req, entity_id).await?;
+    Ok(user_id)
+}
+
+async fn find_account(
+    db: &DatabaseConnection,
+    entity_id: Uuid,
+    account_id: Uuid,
+) -> Result<service_account::Model, ApiError> {
+    ServiceAccountEntity::find_by_id(account_id)
+        .filter(ServiceAccountColumn::EntityId.eq(entity_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Service account not found".to_string()))
+}
+
+async fn to_dto(db: &DatabaseConnection, account: service_account::Model) -> Result<ServiceAccount, ApiError> {
+    let role = UserEntityEntity::find()
+        .filter(UserEntityColumn::UserId.eq(account.user_id))
+        .filter(UserEntityColumn::EntityId.eq(account.entity_id))
+        .one(db)
+        .await?
+        .map(|m| m.role)
+        .unwrap_or_default();
+
+    Ok(ServiceAccount {
+        id: account.id,
+        entity_id: account.entity_id,
+        user_id: account.user_id,
+        name: account.name,
+        description: account.description,
+        role,
+        created_by: account.created_by,
+        created_at: account.created_at,
+    })
+}
+
+pub async fn list_service_accounts(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let accounts = ServiceAccountEntity::find()
+        .filter(ServiceAccountColumn::EntityId.eq(entity_id))
+        .order_by_asc(ServiceAccountColumn::Name)
+        .all(db.get_ref())
+        .await?;
+
+    let mut result = Vec::with_capacity(accounts.len());
+    for account in accounts {
+        result.push(to_dto(db.get_ref(), account).await?);
+    }
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// Crée un compte de service : un utilisateur technique, sans mot de passe
+/// utilisable ni email joignable, rattaché à la seule entité.
+pub async fn create_service_account(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: ValidatedJson<CreateServiceAccountRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let entity_id = path.into_inner();
+    let admin_id = require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let now = Utc::now();
+    let user_id = Uuid::new_v4();
+    let password_hash = hash(Uuid::new_v4().to_string(), DEFAULT_COST)
+        .map_err(ApiError::internal("Password hashing failed"))?;
+
+    let txn = db.begin().await?;
+
+    let user = UserActiveModel {
+        id: Set(user_id),
+        email: Set(format!("svc-{}@{}", user_id, SERVICE_ACCOUNT_EMAIL_DOMAIN)),
+        password_hash: Set(password_hash),
+        first_name: Set(None),
+        last_name: Set(Some(body.name.trim().to_string())),
+        is_active: Set(true),
+        email_verified_at: Set(Some(now)),
+        sessions_valid_after: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+    UserEntity::insert(user).exec(&txn).await?;
+
+    let membership = UserEntityActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        entity_id: Set(entity_id),
+        role: Set(body.role.clone().unwrap_or_else(|| "member".to_string())),
+        created_at: Set(now),
+    };
+    UserEntityEntity::insert(membership).exec(&txn).await?;
+
+    let account = ServiceAccountActiveModel {
+        id: Set(Uuid::new_v4()),
+        entity_id: Set(entity_id),
+        user_id: Set(user_id),
+        name: Set(body.name.trim().to_string()),
+        description: Set(body.description.clone()),
+        created_by: Set(Some(admin_id)),
+        created_at: Set(now),
+    };
+    let account = ServiceAccountEntity::insert(account)
+        .exec_with_returning(&txn)
+        .await?;
+
+    txn.commit().await?;
+
+    Ok(HttpResponse::Created().json(to_dto(db.get_ref(), account).await?))
+}
+
+/// Supprime le compte de service ; ses jetons sont supprimés avec lui.
+pub async fn delete_service_account(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, ApiError> {
+    let (entity_id, account_id) = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let account = find_account(db.get_ref(), entity_id, account_id).await?;
+    UserEntity::delete_by_id(account.user_id)
+        .exec(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn list_service_account_tokens(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, ApiError> {
+    let (entity_id, account_id) = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let account = find_account(db.get_ref(), entity_id, account_id).await?;
+    let tokens: Vec<ApiTokenResponse> = ApiTokenEntity::find()
+        .filter(ApiTokenColumn::UserId.eq(account.user_id))
+        .order_by_desc(ApiTokenColumn::CreatedAt)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .map(api_tokens::to_response)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+/// Émet un jeton pour le compte de service, limité à son entité (et à ses
+/// filiales). Il satisfait l'exigence de MFA de
+/// l'entité : le compte n'a pas de connexion interactive, et l'administrateur
+/// qui l'émet y est lui-même soumis.
+pub async fn create_service_account_token(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+    body: ValidatedJson<CreateApiTokenRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let (entity_id, account_id) = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+    api_tokens::check_expiry(&config, body.expires_at)?;
+    if body.entity_id.is_some_and(|id| id != entity_id) {
+        return Err(ApiError::validation("entity_id", "invalid_entity", "Service account tokens are limited to the account's entity"));
+    }
+
+    let account = find_account(db.get_ref(), entity_id, account_id).await?;
+    // Toujours limité à l'entité du compte, même si le compte y reçoit un autre rattachement
+    let (token, secret) = api_tokens::issue(
+        db.get_ref(),
+        account.user_id,
+        Some(account.entity_id),
+        &body.name,
+        &body.scopes,
+        true,
+        body.expires_at,
+    ).await?;
+
+    Ok(HttpResponse::Created().json(CreatedApiTokenResponse {
+        api_token: api_tokens::to_response(token),
+        token: secret,
+    }))
+}
+
+pub async fn delete_service_account_token(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<(Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, ApiError> {
+    let (entity_id, account_id, token_id) = path.into_inner();
+    require_entity_admin(db.get_ref(), &req, entity_id).await?;
+
+    let account = find_account(db.get_ref(), entity_id, account_id).await?;
+    let result = ApiTokenEntity::delete_many()
+        .filter(ApiTokenColumn::Id.eq(token_id))
+        .filter(ApiTokenColumn::UserId.eq(account.user_id))
+        .exec(db.get_ref())
+        .await?;
+    if result.rows_affected == 0 {
+        return Err(ApiError::NotFound("API token not found".to_string()));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
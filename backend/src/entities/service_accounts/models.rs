@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::{Validate, ValidationError};
+use crate::validation::{not_blank, one_of};
+
+// L'avis du DPO engage une personne : un compte de service ne tient pas ce rôle
+const SERVICE_ACCOUNT_ROLES: &[&str] = &["admin", "member"];
+
+fn validate_service_account_role(value: &str) -> Result<(), ValidationError> {
+    one_of(value, SERVICE_ACCOUNT_ROLES)
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServiceAccount {
+    pub id: Uuid,
+    pub entity_id: Uuid,
+    pub user_id: Uuid, // Compte technique portant le rattachement et les jetons
+    pub name: String,
+    pub description: Option<String>,
+    pub role: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateServiceAccountRequest {
+    #[validate(length(min = 1, max = 255), custom(function = "not_blank"))]
+    pub name: String,
+    #[validate(length(max = 2000))]
+    pub description: Option<String>,
+    #[validate(custom(function = "validate_service_account_role"))]
+    pub role: Option<String>, // "member" par défaut
+}
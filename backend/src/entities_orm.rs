@@ -1,6 +1,5 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
-use serde_json;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
@@ -18,6 +17,8 @@ pub mod user {
         pub first_name: Option<String>,
         pub last_name: Option<String>,
         pub is_active: bool,
+        pub email_verified_at: Option<DateTime<Utc>>,
+        pub sessions_valid_after: Option<DateTime<Utc>>, // Jetons émis avant cette date refusés
         pub created_at: DateTime<Utc>,
         pub updated_at: DateTime<Utc>,
     }
@@ -48,14 +49,21 @@ pub mod entity {
         pub id: Uuid,
         pub name: String,
         pub description: Option<String>,
+        pub require_mfa: bool,
         pub created_at: DateTime<Utc>,
         pub updated_at: DateTime<Utc>,
+        pub parent_id: Option<Uuid>,
+        pub archived_at: Option<DateTime<Utc>>,
+        pub deletion_scheduled_at: Option<DateTime<Utc>>,
+        pub deletion_requested_by: Option<Uuid>,
     }
 
     #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
     pub enum Relation {
         #[sea_orm(has_many = "super::user_entity::Entity")]
         UserEntities,
+        #[sea_orm(belongs_to = "Entity", from = "Column::ParentId", to = "Column::Id")]
+        Parent,
     }
 
     impl Related<super::user_entity::Entity> for Entity {
@@ -125,14 +133,20 @@ pub mod register_entry {
         pub entity_id: Uuid,
         pub processing_name: String,
         pub purpose: String,
-        pub legal_basis: String,
-        pub data_categories: Vec<String>,
-        pub data_subjects: Vec<String>,
-        pub recipients: Vec<String>,
+        pub legal_basis: Option<String>,
         pub retention_period: Option<String>,
         pub security_measures: Option<String>,
         pub created_at: DateTime<Utc>,
         pub updated_at: DateTime<Utc>,
+        pub shared_with_subsidiaries: bool,
+        pub record_type: String,
+        pub legal_basis_details: Option<String>,
+        pub special_category_exception: Option<String>,
+        pub criminal_data_basis: Option<String>,
+        pub consent_mechanism: Option<String>,
+        pub retention_months: Option<i32>,
+        pub retention_trigger: Option<String>,
+        pub retention_action: Option<String>,
     }
 
     #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -173,6 +187,8 @@ pub mod access_request {
         pub created_at: DateTime<Utc>,
         pub updated_at: DateTime<Utc>,
         pub completed_at: Option<DateTime<Utc>>,
+        pub anonymized_at: Option<DateTime<Utc>>,
+        pub archived_at: Option<DateTime<Utc>>,
     }
 
     #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -207,7 +223,7 @@ pub mod breach {
         pub breach_date: DateTime<Utc>,
         pub discovery_date: DateTime<Utc>,
         pub description: String,
-        pub data_categories_affected: Vec<String>,
+        pub data_categories_affected: serde_json::Value,
         pub number_of_subjects: Option<i32>,
         pub severity: String,
         pub status: String,
@@ -217,6 +233,13 @@ pub mod breach {
         pub subjects_notified: bool,
         pub created_at: DateTime<Utc>,
         pub updated_at: DateTime<Utc>,
+        pub closed_at: Option<DateTime<Utc>>,
+        pub anonymized_at: Option<DateTime<Utc>>,
+        pub assessment: Option<serde_json::Value>,
+        pub assessment_justification: Option<String>,
+        pub assessed_by: Option<Uuid>,
+        pub assessed_at: Option<DateTime<Utc>>,
+        pub archived_at: Option<DateTime<Utc>>,
     }
 
     #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -254,6 +277,7 @@ pub mod endpoint {
         pub metadata: Option<serde_json::Value>,
         pub created_at: DateTime<Utc>,
         pub updated_at: DateTime<Utc>,
+        pub entity_id: Option<Uuid>,
     }
 
     #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -282,6 +306,7 @@ pub mod license_key {
         pub expires_at: Option<DateTime<Utc>>,
         pub created_at: DateTime<Utc>,
         pub updated_at: DateTime<Utc>,
+        pub entity_id: Option<Uuid>,
     }
 
     #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -307,6 +332,7 @@ pub mod software_version {
         pub metadata: Option<serde_json::Value>,
         pub created_at: DateTime<Utc>,
         pub updated_at: DateTime<Utc>,
+        pub entity_id: Option<Uuid>,
     }
 
     #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -332,6 +358,7 @@ pub mod encryption_algorithm {
         pub metadata: Option<serde_json::Value>,
         pub created_at: DateTime<Utc>,
         pub updated_at: DateTime<Utc>,
+        pub entity_id: Option<Uuid>,
     }
 
     #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -356,6 +383,1067 @@ pub mod catalogue_relation {
         pub relation_type: String,
         pub description: Option<String>,
         pub created_at: DateTime<Utc>,
+        pub entity_id: Option<Uuid>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// LoginFailure entity (journal des échecs de connexion)
+pub mod login_failure {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "login_failures")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub email: String,
+        pub user_id: Option<Uuid>,
+        pub ip_address: Option<String>,
+        pub reason: String,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// LoginThrottle entity (compteurs du limiteur de connexion persistant)
+pub mod login_throttle {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "login_throttle")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub key: String,
+        pub failures: i32,
+        pub last_failure_at: DateTime<Utc>,
+        pub blocked_until: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// UserTotp entity (secret TOTP d'un utilisateur, actif une fois confirmé)
+pub mod user_totp {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "user_totp")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub user_id: Uuid,
+        pub secret: String,
+        pub confirmed_at: Option<DateTime<Utc>>,
+        pub last_used_step: Option<i64>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RecoveryCode entity (codes de secours à usage unique, stockés hachés)
+pub mod recovery_code {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "user_recovery_codes")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub user_id: Uuid,
+        pub code_hash: String,
+        pub used_at: Option<DateTime<Utc>>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// WebauthnCredential entity (passkeys / clés de sécurité enregistrées)
+pub mod webauthn_credential {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "webauthn_credentials")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub user_id: Uuid,
+        pub name: String,
+        pub passkey: serde_json::Value,
+        pub created_at: DateTime<Utc>,
+        pub last_used_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// MfaChallenge entity (jeton intermédiaire entre mot de passe et second facteur)
+pub mod mfa_challenge {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "mfa_challenges")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub user_id: Uuid,
+        pub token_hash: String,
+        pub purpose: String, // "login" or "webauthn_registration"
+        pub state: Option<serde_json::Value>,
+        pub attempts: i32,
+        pub expires_at: DateTime<Utc>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// UserToken entity (jetons signés à usage unique : vérification d'email, réinitialisation)
+pub mod user_token {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "user_tokens")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub user_id: Uuid,
+        pub purpose: String, // "email_verification" or "password_reset"
+        pub expires_at: DateTime<Utc>,
+        pub used_at: Option<DateTime<Utc>>,
+        pub created_at: DateTime<Utc>,
+        pub context: Option<serde_json::Value>, // Authentification déclarée par le fournisseur (code de connexion fédérée)
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// OidcLoginState entity (state, nonce et vérificateur PKCE d'une connexion OIDC en cours)
+pub mod oidc_login_state {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "oidc_login_states")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub state_hash: String,
+        pub nonce: String,
+        pub code_verifier: String,
+        pub provider_id: Option<Uuid>, // Fournisseur d'une entité, `None` pour le fournisseur global
+        pub expires_at: DateTime<Utc>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// UserIdentity entity (compte externe lié : couple émetteur / subject OIDC)
+pub mod user_identity {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "user_identities")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub user_id: Uuid,
+        pub issuer: String,
+        pub subject: String,
+        pub email: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub last_login_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// IdentityProvider entity (fournisseur OIDC ou SAML propre à une entité)
+pub mod identity_provider {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "identity_providers")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub name: String,
+        pub protocol: String, // "oidc" or "saml"
+        pub issuer: String, // Émetteur OIDC ou entityID de l'IdP SAML
+        pub client_id: Option<String>,
+        pub client_secret: Option<String>,
+        pub sso_url: Option<String>,
+        pub certificate: Option<String>, // Certificat de signature de l'IdP SAML (PEM)
+        pub email_domains: serde_json::Value,
+        pub groups_claim: String,
+        pub role_mapping: serde_json::Value,
+        pub default_role: Option<String>,
+        pub is_enabled: bool,
+        pub mfa_trusted: bool, // Second facteur vérifié par le fournisseur reconnu
+        pub mfa_acr_values: serde_json::Value, // Valeurs acr / AuthnContextClassRef qui attestent ce second facteur
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {
+        #[sea_orm(
+            belongs_to = "super::entity::Entity",
+            from = "Column::EntityId",
+            to = "super::entity::Column::Id"
+        )]
+        Entity,
+    }
+
+    impl Related<super::entity::Entity> for Entity {
+        fn to() -> RelationDef {
+            Relation::Entity.def()
+        }
+    }
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// SamlRequest entity (AuthnRequest émise, attendue en InResponseTo)
+pub mod saml_request {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "saml_requests")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub request_id: String,
+        pub provider_id: Uuid,
+        pub expires_at: DateTime<Utc>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// ScimToken entity (jeton bearer du client SCIM d'une entité et correspondance groupes -> rôles)
+pub mod scim_token {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "scim_tokens")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub token_hash: String,
+        pub role_mapping: serde_json::Value,
+        pub default_role: String,
+        pub created_at: DateTime<Utc>,
+        pub last_used_at: Option<DateTime<Utc>>,
+        pub expires_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// ScimUser entity (ressource User SCIM : compte rattaché à une entité)
+pub mod scim_user {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "scim_users")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub user_id: Uuid,
+        pub external_id: Option<String>,
+        pub active: bool,
+        pub version: i32, // Incrémentée à chaque modification (ETag)
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {
+        #[sea_orm(
+            belongs_to = "super::user::Entity",
+            from = "Column::UserId",
+            to = "super::user::Column::Id"
+        )]
+        User,
+    }
+
+    impl Related<super::user::Entity> for Entity {
+        fn to() -> RelationDef {
+            Relation::User.def()
+        }
+    }
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// ScimGroup entity
+pub mod scim_group {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "scim_groups")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub display_name: String,
+        pub external_id: Option<String>,
+        pub version: i32,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// ScimGroupMember entity
+pub mod scim_group_member {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "scim_group_members")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub group_id: Uuid,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub scim_user_id: Uuid,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// ServiceAccount entity (compte non humain : utilisateur technique membre d'une seule entité)
+pub mod service_account {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "service_accounts")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub user_id: Uuid,
+        pub name: String,
+        pub description: Option<String>,
+        pub created_by: Option<Uuid>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// ApiToken entity (jeton d'API personnel ou de compte de service)
+pub mod api_token {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "api_tokens")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub user_id: Uuid,
+        pub entity_id: Option<Uuid>, // Seule entité accessible au jeton
+        pub name: String,
+        pub token_prefix: String, // Début du jeton, pour l'identifier dans les listes
+        pub token_hash: String,
+        pub scopes: serde_json::Value,
+        pub mfa: bool, // Émis depuis une session avec second facteur
+        pub expires_at: DateTime<Utc>,
+        pub last_used_at: Option<DateTime<Utc>>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// EntityLifecycleEvent entity (journal d'archivage, d'export et de suppression)
+pub mod entity_lifecycle_event {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "entity_lifecycle_events")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub entity_name: String,
+        pub action: String,
+        pub actor_id: Option<Uuid>,
+        pub details: Option<serde_json::Value>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdDataCategory entity (référentiel des catégories de données de l'entité)
+pub mod rgpd_data_category {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_data_categories")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub name: String,
+        pub description: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+        pub kind: String,
+        pub retention_ceiling_months: Option<i32>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdDataSubject entity (référentiel des catégories de personnes concernées)
+pub mod rgpd_data_subject {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_data_subjects")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub name: String,
+        pub description: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdParty entity (responsables, représentants, DPO, sous-traitants et destinataires)
+pub mod rgpd_party {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_parties")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub name: String,
+        pub description: Option<String>,
+        pub category: String,
+        pub contact_name: Option<String>,
+        pub email: Option<String>,
+        pub phone: Option<String>,
+        pub address: Option<String>,
+        pub country: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdSecurityMeasure entity (mesure de sécurité, éventuellement rattachée à un élément du catalogue)
+pub mod rgpd_security_measure {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_security_measures")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub name: String,
+        pub description: Option<String>,
+        pub category: String,
+        pub catalogue_item_type: Option<String>,
+        pub catalogue_item_id: Option<Uuid>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RegisterDataCategory entity (lien traitement / catégorie de données)
+pub mod register_data_category {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_register_data_categories")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub register_entry_id: Uuid,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub data_category_id: Uuid,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RegisterDataSubject entity (lien traitement / personnes concernées)
+pub mod register_data_subject {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_register_data_subjects")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub register_entry_id: Uuid,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub data_subject_id: Uuid,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RegisterParty entity (rôle d'un tiers dans un traitement)
+pub mod register_party {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_register_parties")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub register_entry_id: Uuid,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub party_id: Uuid,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub role: String,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RegisterSecurityMeasure entity (lien traitement / mesure de sécurité)
+pub mod register_security_measure {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_register_security_measures")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub register_entry_id: Uuid,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub security_measure_id: Uuid,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdTransfer entity (transfert hors EEE d'un traitement ou d'un sous-traitant, garanties et évaluation d'impact)
+pub mod rgpd_transfer {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_transfers")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub register_entry_id: Option<Uuid>,
+        pub processor_id: Option<Uuid>,
+        pub party_id: Option<Uuid>,
+        pub country: String,
+        pub safeguard: String,
+        pub safeguard_details: Option<String>,
+        pub scc_module: Option<String>,
+        pub supplementary_measures: Option<String>,
+        pub tia_outcome: Option<String>,
+        pub tia_notes: Option<String>,
+        pub tia_assessed_at: Option<DateTime<Utc>>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdRegisterExport entity (version datée d'un export du registre)
+pub mod rgpd_register_export {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_register_exports")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub version: i32,
+        pub format: String,
+        pub entry_count: i32,
+        pub sha256: String,
+        pub generated_by: Option<Uuid>,
+        pub generated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdDpia entity (analyse d'impact d'une fiche du registre)
+pub mod rgpd_dpia {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_dpias")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub register_entry_id: Uuid,
+        pub title: String,
+        pub status: String,
+        pub screening_criteria: serde_json::Value,
+        pub screening_justification: Option<String>,
+        pub screened_by: Option<Uuid>,
+        pub screened_at: Option<DateTime<Utc>>,
+        pub context: Option<String>,
+        pub necessity: Option<String>,
+        pub proportionality: Option<String>,
+        pub data_subject_rights: Option<String>,
+        pub dpo_recommendation: Option<String>,
+        pub dpo_opinion: Option<String>,
+        pub dpo_opinion_by: Option<Uuid>,
+        pub dpo_opinion_at: Option<DateTime<Utc>>,
+        pub version: i32,
+        pub next_review_at: Option<DateTime<Utc>>,
+        pub last_reviewed_at: Option<DateTime<Utc>>,
+        pub created_by: Option<Uuid>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdDpiaRisk entity (risque pour les droits et libertés)
+pub mod rgpd_dpia_risk {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_dpia_risks")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub dpia_id: Uuid,
+        pub risk_type: String,
+        pub description: String,
+        pub impacts: Option<String>,
+        pub threats: Option<String>,
+        pub likelihood: i32,
+        pub severity: i32,
+        pub residual_likelihood: Option<i32>,
+        pub residual_severity: Option<i32>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdDpiaMeasure entity (mesure prévue par l'analyse d'impact)
+pub mod rgpd_dpia_measure {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_dpia_measures")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub dpia_id: Uuid,
+        pub risk_id: Option<Uuid>,
+        pub security_measure_id: Option<Uuid>,
+        pub description: String,
+        pub measure_type: String,
+        pub status: String,
+        pub owner: Option<String>,
+        pub due_date: Option<DateTime<Utc>>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdDpiaVersion entity (copie figée d'une analyse validée)
+pub mod rgpd_dpia_version {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_dpia_versions")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub dpia_id: Uuid,
+        pub version: i32,
+        pub decision: String,
+        pub comment: Option<String>,
+        pub snapshot: serde_json::Value,
+        pub signed_off_by: Option<Uuid>,
+        pub signed_off_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdDpiaReview entity (revue périodique d'une analyse validée)
+pub mod rgpd_dpia_review {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_dpia_reviews")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub dpia_id: Uuid,
+        pub version: i32,
+        pub outcome: String,
+        pub comment: Option<String>,
+        pub reviewed_by: Option<Uuid>,
+        pub reviewed_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdBreachAttachment entity (pièce jointe d'une violation)
+pub mod rgpd_breach_attachment {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_breach_attachments")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub breach_id: Uuid,
+        pub file_name: String,
+        pub content_type: Option<String>,
+        pub file_size: i64,
+        pub storage_type: String,
+        pub file_path: String,
+        pub uploaded_by: Option<Uuid>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdRetentionPolicy entity (conservation des données tenues par HyperCyber)
+pub mod rgpd_retention_policy {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_retention_policies")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub record_type: String,
+        pub duration_months: i32,
+        pub trigger: String,
+        pub action: String,
+        pub enabled: bool,
+        pub updated_by: Option<Uuid>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+        pub register_entry_id: Option<Uuid>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdRetentionRun entity (rapport d'exécution de la conservation)
+pub mod rgpd_retention_run {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_retention_runs")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub triggered_by: Option<Uuid>,
+        pub dry_run: bool,
+        pub status: String,
+        pub report: serde_json::Value,
+        pub error: Option<String>,
+        pub started_at: DateTime<Utc>,
+        pub finished_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdProcessor entity (fiche fournisseur d'un sous-traitant : contrat, transferts, sécurité)
+pub mod rgpd_processor {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_processors")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub party_id: Uuid,
+        pub services: Option<String>,
+        pub dpa_reference: Option<String>,
+        pub dpa_signed_at: Option<DateTime<Utc>>,
+        pub dpa_expires_at: Option<DateTime<Utc>>,
+        pub security_score: Option<i32>,
+        pub security_assessed_at: Option<DateTime<Utc>>,
+        pub notes: Option<String>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdSubProcessor entity (sous-traitant ultérieur autorisé)
+pub mod rgpd_sub_processor {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_sub_processors")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub processor_id: Uuid,
+        pub party_id: Uuid,
+        pub service: Option<String>,
+        pub authorised_at: Option<DateTime<Utc>>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdProcessorEndpoint entity (équipement du catalogue hébergé par le fournisseur)
+pub mod rgpd_processor_endpoint {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_processor_endpoints")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub processor_id: Uuid,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub endpoint_id: Uuid,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdConsentPurpose entity (version du texte de consentement d'un traitement, figée une fois créée)
+pub mod rgpd_consent_purpose {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_consent_purposes")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub register_entry_id: Uuid,
+        pub version: i32,
+        pub wording: String,
+        pub created_by: Option<Uuid>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdConsent entity (recueil d'un consentement ; seule l'empreinte de l'identifiant est conservée)
+pub mod rgpd_consent {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_consents")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub register_entry_id: Uuid,
+        pub purpose_id: Uuid,
+        pub subject_hash: String,
+        pub channel: String,
+        pub source: Option<String>,
+        pub external_id: Option<String>,
+        pub consented_at: DateTime<Utc>,
+        pub withdrawn_at: Option<DateTime<Utc>>,
+        pub withdrawal_channel: Option<String>,
+        pub recorded_by: Option<Uuid>,
+        pub created_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// RgpdBreachTemplate entity (modèle de notification d'une violation propre à l'entité, par type et langue)
+pub mod rgpd_breach_template {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "rgpd_breach_templates")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub kind: String,
+        pub language: String,
+        pub title: String,
+        pub body: String,
+        pub updated_by: Option<Uuid>,
+        pub created_at: DateTime<Utc>,
+        pub updated_at: DateTime<Utc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+// EmailDomain entity (domaine email d'une entité, vérifié ou en attente)
+pub mod email_domain {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+    #[sea_orm(table_name = "email_domains")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: Uuid,
+        pub entity_id: Uuid,
+        pub domain: String,
+        pub verification_token: String,
+        pub verified_at: Option<DateTime<Utc>>,
+        pub created_at: DateTime<Utc>,
     }
 
     #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -365,14 +1453,61 @@ pub mod catalogue_relation {
 }
 
 // Type aliases for easier use
-pub use user::Entity as User;
-pub use entity::Entity as EntityModel;
-pub use user_entity::Entity as UserEntity;
-pub use register_entry::Entity as RegisterEntry;
-pub use access_request::Entity as AccessRequest;
-pub use breach::Entity as Breach;
-pub use endpoint::Entity as Endpoint;
-pub use license_key::Entity as LicenseKey;
-pub use software_version::Entity as SoftwareVersion;
-pub use encryption_algorithm::Entity as EncryptionAlgorithm;
-pub use catalogue_relation::Entity as CatalogueRelation;
+#[allow(unused_imports)]
+pub use self::{
+    user::Entity as User,
+    entity::Entity as EntityModel,
+    user_entity::Entity as UserEntity,
+    register_entry::Entity as RegisterEntry,
+    access_request::Entity as AccessRequest,
+    breach::Entity as Breach,
+    endpoint::Entity as Endpoint,
+    license_key::Entity as LicenseKey,
+    software_version::Entity as SoftwareVersion,
+    encryption_algorithm::Entity as EncryptionAlgorithm,
+    catalogue_relation::Entity as CatalogueRelation,
+    login_failure::Entity as LoginFailure,
+    login_throttle::Entity as LoginThrottle,
+    user_totp::Entity as UserTotp,
+    recovery_code::Entity as RecoveryCode,
+    webauthn_credential::Entity as WebauthnCredential,
+    mfa_challenge::Entity as MfaChallenge,
+    user_token::Entity as UserToken,
+    oidc_login_state::Entity as OidcLoginState,
+    user_identity::Entity as UserIdentity,
+    identity_provider::Entity as IdentityProvider,
+    saml_request::Entity as SamlRequest,
+    scim_token::Entity as ScimToken,
+    scim_user::Entity as ScimUser,
+    scim_group::Entity as ScimGroup,
+    scim_group_member::Entity as ScimGroupMember,
+    service_account::Entity as ServiceAccount,
+    api_token::Entity as ApiToken,
+    entity_lifecycle_event::Entity as EntityLifecycleEvent,
+    rgpd_data_category::Entity as RgpdDataCategory,
+    rgpd_data_subject::Entity as RgpdDataSubject,
+    rgpd_party::Entity as RgpdParty,
+    rgpd_security_measure::Entity as RgpdSecurityMeasure,
+    register_data_category::Entity as RegisterDataCategory,
+    register_data_subject::Entity as RegisterDataSubject,
+    register_party::Entity as RegisterParty,
+    register_security_measure::Entity as RegisterSecurityMeasure,
+    rgpd_transfer::Entity as RgpdTransfer,
+    rgpd_register_export::Entity as RgpdRegisterExport,
+    rgpd_dpia::Entity as RgpdDpia,
+    rgpd_dpia_risk::Entity as RgpdDpiaRisk,
+    rgpd_dpia_measure::Entity as RgpdDpiaMeasure,
+    rgpd_dpia_version::Entity as RgpdDpiaVersion,
+    rgpd_dpia_review::Entity as RgpdDpiaReview,
+    rgpd_breach_attachment::Entity as RgpdBreachAttachment,
+    rgpd_retention_policy::Entity as RgpdRetentionPolicy,
+    rgpd_retention_run::Entity as RgpdRetentionRun,
+    rgpd_processor::Entity as RgpdProcessor,
+    rgpd_sub_processor::Entity as RgpdSubProcessor,
+    rgpd_processor_endpoint::Entity as RgpdProcessorEndpoint,
+    rgpd_consent_purpose::Entity as RgpdConsentPurpose,
+    rgpd_consent::Entity as RgpdConsent,
+    rgpd_breach_template::Entity as RgpdBreachTemplate,
+    email_domain::Entity as EmailDomain,
+};
+
@@ -0,0 +1,157 @@
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use serde::Serialize;
+use crate::middleware::current_request_id;
+
+/// Erreur applicative unique renvoyée par tous les handlers.
+///
+/// Chaque variante est rendue sous la forme d'un document
+/// `application/problem+json` (RFC 7807) contenant un `code` stable,
+/// destiné aux clients qui doivent traiter les erreurs par programme.
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("Multi-factor authentication required")]
+    MfaRequired,
+    #[error("Email address not verified")]
+    EmailNotVerified,
+    #[error("Too many attempts, retry in {retry_after} seconds")]
+    TooManyRequests { retry_after: i64 },
+    #[error("Validation failed")]
+    Validation(Vec<FieldError>),
+    #[error("Database error")]
+    Database(#[from] sea_orm::DbErr),
+    #[error("{0}")]
+    Internal(String),
+}
+
+/// Erreur portant sur un champ précis de la requête.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            code: code.into(),
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ProblemDetails<'a> {
+    #[serde(rename = "type")]
+    problem_type: String,
+    title: &'a str,
+    status: u16,
+    detail: String,
+    code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    errors: Option<&'a [FieldError]>,
+}
+
+impl ApiError {
+    /// Code machine stable, exposé dans le champ `code` du corps de la réponse.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::MfaRequired => "mfa_required",
+            ApiError::EmailNotVerified => "email_not_verified",
+            ApiError::TooManyRequests { .. } => "too_many_requests",
+            ApiError::Validation(_) => "validation_failed",
+            ApiError::Database(_) => "database_error",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+
+    /// Construit un `map_err` qui journalise la cause et renvoie une erreur 500
+    /// avec un message générique, sans exposer le détail au client.
+    pub fn internal<E: std::fmt::Display>(message: &'static str) -> impl FnOnce(E) -> ApiError {
+        move |e| {
+            log::error!("{}: {}", message, e);
+            ApiError::Internal(message.to_string())
+        }
+    }
+
+    pub fn validation(field: impl Into<String>, code: impl Into<String>, message: impl Into<String>) -> Self {
+        ApiError::Validation(vec![FieldError::new(field, code, message)])
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) | ApiError::MfaRequired | ApiError::EmailNotVerified => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Database(_) | ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let request_id = current_request_id();
+
+        if let ApiError::Database(e) = self {
+            log::error!("Database error (request {}): {}", request_id.as_deref().unwrap_or("-"), e);
+        }
+
+        let problem = ProblemDetails {
+            problem_type: format!("urn:hypercyber:problem:{}", self.code()),
+            title: status.canonical_reason().unwrap_or("Error"),
+            status: status.as_u16(),
+            detail: self.to_string(),
+            code: self.code(),
+            request_id,
+            errors: match self {
+                ApiError::Validation(errors) => Some(errors),
+                _ => None,
+            },
+        };
+
+        let mut response = HttpResponse::build(status);
+        if let ApiError::TooManyRequests { retry_after } = self {
+            response.insert_header((actix_web::http::header::RETRY_AFTER, retry_after.to_string()));
+        }
+        response
+            .content_type("application/problem+json")
+            .json(problem)
+    }
+}
+
+/// Gestionnaires d'erreurs des extracteurs actix (`Json`, `Query`, `Path`),
+/// pour que les corps ou paramètres mal formés suivent le même format.
+pub fn json_error_handler(err: actix_web::error::JsonPayloadError, _req: &actix_web::HttpRequest) -> actix_web::Error {
+    ApiError::BadRequest(format!("Invalid JSON body: {}", err)).into()
+}
+
+pub fn query_error_handler(err: actix_web::error::QueryPayloadError, _req: &actix_web::HttpRequest) -> actix_web::Error {
+    ApiError::BadRequest(format!("Invalid query string: {}", err)).into()
+}
+
+pub fn path_error_handler(err: actix_web::error::PathError, _req: &actix_web::HttpRequest) -> actix_web::Error {
+    ApiError::BadRequest(format!("Invalid path parameter: {}", err)).into()
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use lettre::message::{Mailbox, Message};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use chrono::Utc;
+use uuid::Uuid;
+use crate::config::Config;
+
+/// Message texte à envoyer à un destinataire.
+#[derive(Clone, Debug)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Envoi des emails transactionnels. Trois implémentations : SMTP,
+/// fichiers (un `.eml` par message, pour les tests) et journal applicatif.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &Email) -> anyhow::Result<()>;
+}
+
+pub struct SmtpMailer {
+    from: Mailbox,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        let host = config.smtp_host.as_deref()
+            .ok_or_else(|| anyhow::anyhow!("SMTP_HOST is required for the smtp mail backend"))?;
+        let mut transport = if config.smtp_starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+        };
+        if let Some(port) = config.smtp_port {
+            transport = transport.port(port);
+        }
+        if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password) {
+            transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        Ok(Self {
+            from: config.mail_from.parse()?,
+            transport: transport.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &Email) -> anyhow::Result<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(email.to.parse()?)
+            .subject(email.subject.clone())
+            .body(email.body.clone())?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+pub struct FileMailer {
+    from: String,
+    directory: String,
+}
+
+impl FileMailer {
+    pub fn new(from: String, directory: String) -> Self {
+        // Créer le répertoire s'il n'existe pas
+        std::fs::create_dir_all(&directory).ok();
+        Self { from, directory }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: &Email) -> anyhow::Result<()> {
+        let file_path = format!(
+            "{}/{}-{}.eml",
+            self.directory,
+            Utc::now().format("%Y%m%dT%H%M%S"),
+            Uuid::new_v4()
+        );
+        let content = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\n\r\n{}\r\n",
+            self.from, email.to, email.subject, email.body
+        );
+        tokio::fs::write(&file_path, content).await?;
+        Ok(())
+    }
+}
+
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: &Email) -> anyhow::Result<()> {
+        log::info!("Email to {} - {}\n{}", email.to, email.subject, email.body);
+        Ok(())
+    }
+}
+
+/// Construit le mailer selon `MAIL_BACKEND` ("smtp", "file" ou "log").
+pub fn build_mailer(config: &Config) -> anyhow::Result<Arc<dyn Mailer>> {
+    Ok(match config.mail_backend.as_str() {
+        "smtp" => Arc::new(SmtpMailer::new(config)?),
+        "file" => Arc::new(FileMailer::new(config.mail_from.clone(), config.mail_file_dir.clone())),
+        _ => Arc::new(LogMailer),
+    })
+}
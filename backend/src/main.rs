@@ -1,14 +1,23 @@
 use actix_web::{web, App, HttpServer, middleware::Logger, http::header};
+use std::sync::Arc;
 
 mod auth;
 mod entities;
 mod database;
 mod config;
+mod errors;
+mod validation;
 mod middleware;
+mod mailer;
 mod entities_orm;
+mod scim;
+mod outbound;
+#[cfg(test)]
+mod test_support;
 
 use database::get_connection;
 use config::Config;
+use auth::throttle::{LoginThrottle, InMemoryLoginThrottle, PostgresLoginThrottle};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -16,12 +25,51 @@ async fn main() -> std::io::Result<()> {
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
     let config = Config::from_env();
+    config.check_mfa_encryption_key().expect("Invalid MFA configuration");
+    config.check_consent_subject_key().expect("Invalid consent ledger configuration");
     let db = get_connection(&config.database_url).await
         .expect("Failed to create database connection");
 
-    // Run migrations (still using sqlx for migrations)
-    // Note: SeaORM has its own migration system, but we'll keep sqlx for now
-    // You can migrate to SeaORM migrations later if needed
+    // Secrets TOTP enregistrés en clair par une version antérieure
+    let encrypted = auth::mfa::encrypt_legacy_secrets(&db, &config).await
+        .expect("Failed to encrypt stored TOTP secrets");
+    if encrypted > 0 {
+        log::info!("Encrypted {} stored TOTP secrets", encrypted);
+    }
+
+    let login_throttle: Arc<dyn LoginThrottle> = if config.login_throttle_backend == "postgres" {
+        Arc::new(PostgresLoginThrottle::new(config.login_throttle.clone(), db.clone()))
+    } else {
+        Arc::new(InMemoryLoginThrottle::new(config.login_throttle.clone()))
+    };
+    let login_throttle = web::Data::from(login_throttle);
+
+    let mailer = web::Data::from(
+        mailer::build_mailer(&config).expect("Invalid mail configuration")
+    );
+
+    let webauthn = web::Data::new(
+        auth::mfa::build_webauthn(&config).expect("Invalid WebAuthn configuration")
+    );
+
+    let jwt_keys = web::Data::new(
+        auth::jwt::JwtKeys::from_config(&config).expect("Invalid JWT signing configuration")
+    );
+
+    let outbound = outbound::OutboundClient::from_config(&config)
+        .expect("Invalid outbound HTTP configuration");
+    if config.outbound_allow_insecure {
+        log::warn!("OUTBOUND_ALLOW_INSECURE is set: outbound requests may target http URLs and private addresses");
+    }
+
+    // Clés publiques du fournisseur OIDC, partagées entre les workers
+    let oidc_jwks = web::Data::new(auth::oidc::JwksCache::new(outbound.clone()));
+    let outbound = web::Data::new(outbound);
+
+    // Suppression définitive des entités à l'issue du délai de grâce
+    entities::lifecycle::purge::spawn(db.clone(), config.clone());
+    // Application des durées de conservation RGPD
+    entities::rgpd::retention::spawn(db.clone(), config.clone());
 
     let server_address = format!("{}:{}", config.host, config.port);
     log::info!("Starting server on {}", server_address);
@@ -39,16 +87,31 @@ async fn main() -> std::io::Result<()> {
                 header::ACCEPT_LANGUAGE,
                 header::CONTENT_LANGUAGE,
             ])
-            .expose_headers(vec![header::AUTHORIZATION, header::CONTENT_TYPE])
+            .expose_headers(vec![
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+                header::HeaderName::from_static(middleware::REQUEST_ID_HEADER),
+            ])
             .supports_credentials()
             .max_age(3600);
 
         App::new()
             .app_data(web::Data::new(db.clone()))
             .app_data(web::Data::new(config.clone()))
+            .app_data(login_throttle.clone())
+            .app_data(jwt_keys.clone())
+            .app_data(webauthn.clone())
+            .app_data(oidc_jwks.clone())
+            .app_data(outbound.clone())
+            .app_data(mailer.clone())
             .app_data(web::PayloadConfig::new(10_000_000)) // 10MB max payload
+            .app_data(web::JsonConfig::default().error_handler(errors::json_error_handler))
+            .app_data(web::QueryConfig::default().error_handler(errors::query_error_handler))
+            .app_data(web::PathConfig::default().error_handler(errors::path_error_handler))
+            .wrap(middleware::RequestIdMiddleware)
             .wrap(cors)
-            .wrap(Logger::default())
+            .wrap(Logger::new(r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T request_id=%{x-request-id}o"#))
+            .route("/.well-known/jwks.json", web::get().to(auth::handlers::get_jwks))
             .service(
                 web::scope("/api")
                     .service(
@@ -57,24 +120,108 @@ async fn main() -> std::io::Result<()> {
                             .route("/register", web::post().to(auth::handlers::register))
                             .route("/oidc/callback", web::get().to(auth::handlers::oidc_callback))
                             .route("/oidc/authorize", web::get().to(auth::handlers::oidc_authorize))
+                            .route("/oidc/exchange", web::post().to(auth::handlers::oidc_exchange))
+                            .route("/sso/discover", web::post().to(auth::handlers::sso_discover))
+                            .route("/sso/{provider_id}/authorize", web::get().to(auth::handlers::sso_authorize))
+                            .route("/saml/acs", web::post().to(auth::handlers::saml_acs))
+                            .route("/saml/metadata", web::get().to(auth::handlers::saml_metadata))
                             .route("/refresh", web::post().to(auth::handlers::refresh_token))
-                            .route("/me", web::get().to(auth::handlers::get_current_user))
-                    )
-                    .service(
-                        web::scope("/entities")
-                            .wrap(middleware::AuthMiddleware)
-                            .route("", web::get().to(entities::handlers::list_entities))
-                            .route("", web::post().to(entities::handlers::create_entity))
-                            .route("/{id}", web::get().to(entities::handlers::get_entity))
-                            .route("/{id}", web::put().to(entities::handlers::update_entity))
-                            .route("/{id}/users", web::get().to(entities::handlers::get_entity_users))
+                            .service(
+                                web::resource("/me")
+                                    .wrap(middleware::AuthMiddleware)
+                                    .route(web::get().to(auth::handlers::get_current_user))
+                            )
+                            .service(
+                                web::resource("/switch-entity")
+                                    .wrap(middleware::AuthMiddleware)
+                                    .route(web::post().to(auth::handlers::switch_entity))
+                            )
+                            .route("/verify-email", web::post().to(auth::handlers::verify_email))
+                            .route("/verify-email/resend", web::post().to(auth::handlers::resend_verification_email))
+                            .route("/password/forgot", web::post().to(auth::handlers::forgot_password))
+                            .route("/password/reset", web::post().to(auth::handlers::reset_password))
+                            .service(
+                                web::resource("/password/change")
+                                    .wrap(middleware::AuthMiddleware)
+                                    .route(web::post().to(auth::handlers::change_password))
+                            )
+                            .service(
+                                web::scope("/tokens")
+                                    .wrap(middleware::AuthMiddleware)
+                                    .route("", web::get().to(auth::handlers::list_api_tokens))
+                                    .route("", web::post().to(auth::handlers::create_api_token))
+                                    .route("/{id}", web::delete().to(auth::handlers::delete_api_token))
+                            )
+                            .route("/login/mfa", web::post().to(auth::handlers::login_mfa))
+                            .route("/login/webauthn/start", web::post().to(auth::handlers::login_webauthn_start))
+                            .route("/login/webauthn/finish", web::post().to(auth::handlers::login_webauthn_finish))
+                            .service(
+                                web::scope("/mfa")
+                                    .wrap(middleware::AuthMiddleware)
+                                    .route("", web::get().to(auth::handlers::mfa_status))
+                                    .route("/step-up", web::post().to(auth::handlers::mfa_step_up))
+                                    .route("/totp/enroll", web::post().to(auth::handlers::totp_enroll))
+                                    .route("/totp/confirm", web::post().to(auth::handlers::totp_confirm))
+                                    .route("/totp/disable", web::post().to(auth::handlers::totp_disable))
+                                    .route("/recovery-codes", web::post().to(auth::handlers::regenerate_recovery_codes))
+                                    .route("/webauthn/register/start", web::post().to(auth::handlers::webauthn_register_start))
+                                    .route("/webauthn/register/finish", web::post().to(auth::handlers::webauthn_register_finish))
+                                    .route("/webauthn/{id}", web::delete().to(auth::handlers::delete_webauthn_credential))
+                            )
                     )
+                    // Déclaré avant `/entities`, dont le préfixe masquerait ces routes
                     .service(
                         web::scope("/entities/{entity_id}/rgpd")
                             .wrap(middleware::AuthMiddleware)
                             .route("/register", web::get().to(entities::rgpd::handlers::get_register))
                             .route("/register", web::post().to(entities::rgpd::handlers::add_to_register))
+                            .route("/register/export", web::get().to(entities::rgpd::export::export_register))
+                            .route("/register/exports", web::get().to(entities::rgpd::export::list_register_exports))
                             .route("/register/{id}", web::put().to(entities::rgpd::handlers::update_register_entry))
+                            .route("/register/{id}/consent-purposes", web::get().to(entities::rgpd::consents::handlers::list_purposes))
+                            .route("/register/{id}/consent-purposes", web::post().to(entities::rgpd::consents::handlers::create_purpose))
+                            .route("/consents", web::post().to(entities::rgpd::consents::handlers::record_consent))
+                            .route("/consents/withdraw", web::post().to(entities::rgpd::consents::handlers::withdraw_consent))
+                            .route("/consents/proof", web::post().to(entities::rgpd::consents::handlers::consent_proof))
+                            .route("/taxonomy", web::get().to(entities::rgpd::taxonomy::get_taxonomy))
+                            .route("/data-categories", web::get().to(entities::rgpd::referentials::list_data_categories))
+                            .route("/data-categories", web::post().to(entities::rgpd::referentials::create_data_category))
+                            .route("/data-categories/{id}", web::put().to(entities::rgpd::referentials::update_data_category))
+                            .route("/data-categories/{id}", web::delete().to(entities::rgpd::referentials::delete_data_category))
+                            .route("/data-subjects", web::get().to(entities::rgpd::referentials::list_data_subjects))
+                            .route("/data-subjects", web::post().to(entities::rgpd::referentials::create_data_subject))
+                            .route("/data-subjects/{id}", web::put().to(entities::rgpd::referentials::update_data_subject))
+                            .route("/data-subjects/{id}", web::delete().to(entities::rgpd::referentials::delete_data_subject))
+                            .route("/parties", web::get().to(entities::rgpd::referentials::list_parties))
+                            .route("/parties", web::post().to(entities::rgpd::referentials::create_party))
+                            .route("/parties/{id}", web::put().to(entities::rgpd::referentials::update_party))
+                            .route("/parties/{id}", web::delete().to(entities::rgpd::referentials::delete_party))
+                            .route("/security-measures", web::get().to(entities::rgpd::referentials::list_security_measures))
+                            .route("/security-measures", web::post().to(entities::rgpd::referentials::create_security_measure))
+                            .route("/security-measures/{id}", web::put().to(entities::rgpd::referentials::update_security_measure))
+                            .route("/security-measures/{id}", web::delete().to(entities::rgpd::referentials::delete_security_measure))
+                            .route("/processors", web::get().to(entities::rgpd::processors::handlers::list_processors))
+                            .route("/processors", web::post().to(entities::rgpd::processors::handlers::create_processor))
+                            .route("/processors/alerts", web::get().to(entities::rgpd::processors::handlers::list_alerts))
+                            .route("/processors/{id}", web::get().to(entities::rgpd::processors::handlers::get_processor))
+                            .route("/processors/{id}", web::put().to(entities::rgpd::processors::handlers::update_processor))
+                            .route("/processors/{id}", web::delete().to(entities::rgpd::processors::handlers::delete_processor))
+                            .route("/dpias", web::get().to(entities::rgpd::dpia::handlers::list_dpias))
+                            .route("/dpias", web::post().to(entities::rgpd::dpia::handlers::create_dpia))
+                            .route("/dpias/{id}", web::get().to(entities::rgpd::dpia::handlers::get_dpia))
+                            .route("/dpias/{id}", web::put().to(entities::rgpd::dpia::handlers::update_dpia))
+                            .route("/dpias/{id}", web::delete().to(entities::rgpd::dpia::handlers::delete_dpia))
+                            .route("/dpias/{id}/screening", web::put().to(entities::rgpd::dpia::handlers::screen_dpia))
+                            .route("/dpias/{id}/risks", web::post().to(entities::rgpd::dpia::handlers::create_risk))
+                            .route("/dpias/{id}/risks/{item_id}", web::put().to(entities::rgpd::dpia::handlers::update_risk))
+                            .route("/dpias/{id}/risks/{item_id}", web::delete().to(entities::rgpd::dpia::handlers::delete_risk))
+                            .route("/dpias/{id}/measures", web::post().to(entities::rgpd::dpia::handlers::create_measure))
+                            .route("/dpias/{id}/measures/{item_id}", web::put().to(entities::rgpd::dpia::handlers::update_measure))
+                            .route("/dpias/{id}/measures/{item_id}", web::delete().to(entities::rgpd::dpia::handlers::delete_measure))
+                            .route("/dpias/{id}/dpo-opinion", web::post().to(entities::rgpd::dpia::handlers::give_dpo_opinion))
+                            .route("/dpias/{id}/sign-off", web::post().to(entities::rgpd::dpia::handlers::sign_off_dpia))
+                            .route("/dpias/{id}/reviews", web::post().to(entities::rgpd::dpia::handlers::review_dpia))
+                            .route("/dpias/{id}/versions", web::get().to(entities::rgpd::dpia::handlers::list_dpia_versions))
                             .route("/access-requests", web::get().to(entities::rgpd::handlers::list_access_requests))
                             .route("/access-requests", web::post().to(entities::rgpd::handlers::create_access_request))
                             .route("/access-requests/{id}", web::get().to(entities::rgpd::handlers::get_access_request))
@@ -83,22 +230,102 @@ async fn main() -> std::io::Result<()> {
                             .route("/breaches", web::post().to(entities::rgpd::handlers::create_breach))
                             .route("/breaches/{id}", web::get().to(entities::rgpd::handlers::get_breach))
                             .route("/breaches/{id}", web::put().to(entities::rgpd::handlers::update_breach))
+                            .route("/breaches/{id}/assessment", web::put().to(entities::rgpd::breach_assessment::handlers::assess_breach))
+                            .route("/breaches/{id}/documents", web::post().to(entities::rgpd::breach_documents::handlers::generate_document))
+                            .route("/breaches/{id}/attachments", web::get().to(entities::rgpd::attachments::list_attachments))
+                            .route("/breaches/{id}/attachments", web::post().to(entities::rgpd::attachments::upload_attachment))
+                            .route("/breaches/{id}/attachments/{item_id}", web::get().to(entities::rgpd::attachments::download_attachment))
+                            .route("/breaches/{id}/attachments/{item_id}", web::delete().to(entities::rgpd::attachments::delete_attachment))
+                            .route("/breach-templates", web::get().to(entities::rgpd::breach_documents::handlers::list_templates))
+                            .route("/breach-templates/{kind}/{language}", web::put().to(entities::rgpd::breach_documents::handlers::update_template))
+                            .route("/breach-templates/{kind}/{language}", web::delete().to(entities::rgpd::breach_documents::handlers::reset_template))
+                            .route("/retention/policies", web::get().to(entities::rgpd::retention::handlers::list_policies))
+                            .route("/retention/policies/{record_type}", web::put().to(entities::rgpd::retention::handlers::update_policy))
+                            .route("/retention/policies/{record_type}", web::delete().to(entities::rgpd::retention::handlers::reset_policy))
+                            .route("/retention/runs", web::get().to(entities::rgpd::retention::handlers::list_runs))
+                            .route("/retention/runs", web::post().to(entities::rgpd::retention::handlers::start_run))
+                            .route("/retention/archive", web::get().to(entities::rgpd::retention::handlers::list_archive))
+                            .route("/consolidated/register", web::get().to(entities::rgpd::handlers::consolidated_register))
+                            .route("/consolidated/access-requests", web::get().to(entities::rgpd::handlers::consolidated_access_requests))
+                            .route("/consolidated/breaches", web::get().to(entities::rgpd::handlers::consolidated_breaches))
                     )
-                    // Keep old RGPD routes for backward compatibility (using query params)
+                    .service(
+                        web::scope("/entities")
+                            .wrap(middleware::AuthMiddleware)
+                            .route("", web::get().to(entities::handlers::list_entities))
+                            .route("", web::post().to(entities::handlers::create_entity))
+                            .route("/{id}", web::get().to(entities::handlers::get_entity))
+                            .route("/{id}", web::put().to(entities::handlers::update_entity))
+                            .route("/{id}/users", web::get().to(entities::handlers::get_entity_users))
+                            .route("/{id}/parent", web::put().to(entities::handlers::set_entity_parent))
+                            .route("/{id}/subsidiaries", web::get().to(entities::handlers::list_subsidiaries))
+                            .route("/{id}/archive", web::post().to(entities::lifecycle::handlers::archive_entity))
+                            .route("/{id}/archive", web::delete().to(entities::lifecycle::handlers::unarchive_entity))
+                            .route("/{id}/deletion", web::post().to(entities::lifecycle::handlers::schedule_deletion))
+                            .route("/{id}/deletion", web::delete().to(entities::lifecycle::handlers::cancel_deletion))
+                            .route("/{id}/export", web::get().to(entities::lifecycle::handlers::export_entity))
+                            .route("/{id}/lifecycle-events", web::get().to(entities::lifecycle::handlers::list_lifecycle_events))
+                            .route("/{id}/login-failures", web::get().to(entities::handlers::list_login_failures))
+                            .route("/{id}/email-domains", web::get().to(entities::email_domains::handlers::list_email_domains))
+                            .route("/{id}/email-domains", web::post().to(entities::email_domains::handlers::create_email_domain))
+                            .route("/{id}/email-domains/{domain_id}/verify", web::post().to(entities::email_domains::handlers::verify_email_domain))
+                            .route("/{id}/email-domains/{domain_id}", web::delete().to(entities::email_domains::handlers::delete_email_domain))
+                            .route("/{id}/identity-providers", web::get().to(entities::identity_providers::handlers::list_identity_providers))
+                            .route("/{id}/identity-providers", web::post().to(entities::identity_providers::handlers::create_identity_provider))
+                            .route("/{id}/identity-providers/{provider_id}", web::get().to(entities::identity_providers::handlers::get_identity_provider))
+                            .route("/{id}/identity-providers/{provider_id}", web::put().to(entities::identity_providers::handlers::update_identity_provider))
+                            .route("/{id}/identity-providers/{provider_id}", web::delete().to(entities::identity_providers::handlers::delete_identity_provider))
+                            .route("/{id}/scim", web::get().to(entities::scim_provisioning::handlers::get_scim_client))
+                            .route("/{id}/scim", web::put().to(entities::scim_provisioning::handlers::upsert_scim_client))
+                            .route("/{id}/scim", web::delete().to(entities::scim_provisioning::handlers::delete_scim_client))
+                            .route("/{id}/scim/token", web::post().to(entities::scim_provisioning::handlers::rotate_scim_token))
+                            .route("/{id}/service-accounts", web::get().to(entities::service_accounts::handlers::list_service_accounts))
+                            .route("/{id}/service-accounts", web::post().to(entities::service_accounts::handlers::create_service_account))
+                            .route("/{id}/service-accounts/{account_id}", web::delete().to(entities::service_accounts::handlers::delete_service_account))
+                            .route("/{id}/service-accounts/{account_id}/tokens", web::get().to(entities::service_accounts::handlers::list_service_account_tokens))
+                            .route("/{id}/service-accounts/{account_id}/tokens", web::post().to(entities::service_accounts::handlers::create_service_account_token))
+                            .route("/{id}/service-accounts/{account_id}/tokens/{token_id}", web::delete().to(entities::service_accounts::handlers::delete_service_account_token))
+                    )
+                    // Provisioning SCIM 2.0 : authentifié par le jeton de l'entité (extracteur ScimAuth)
+                    .service(
+                        web::scope("/scim/v2")
+                            .route("/ServiceProviderConfig", web::get().to(scim::handlers::get_service_provider_config))
+                            .route("/ResourceTypes", web::get().to(scim::handlers::list_resource_types))
+                            .route("/Schemas", web::get().to(scim::handlers::list_schemas))
+                            .route("/Users", web::get().to(scim::handlers::list_users))
+                            .route("/Users", web::post().to(scim::handlers::create_user))
+                            .route("/Users/{id}", web::get().to(scim::handlers::get_user))
+                            .route("/Users/{id}", web::put().to(scim::handlers::replace_user))
+                            .route("/Users/{id}", web::patch().to(scim::handlers::patch_user))
+                            .route("/Users/{id}", web::delete().to(scim::handlers::delete_user))
+                            .route("/Groups", web::get().to(scim::handlers::list_groups))
+                            .route("/Groups", web::post().to(scim::handlers::create_group))
+                            .route("/Groups/{id}", web::get().to(scim::handlers::get_group))
+                            .route("/Groups/{id}", web::put().to(scim::handlers::replace_group))
+                            .route("/Groups/{id}", web::patch().to(scim::handlers::patch_group))
+                            .route("/Groups/{id}", web::delete().to(scim::handlers::delete_group))
+                    )
+                    // Anciennes routes RGPD (entité en paramètre de requête), dépréciées
                     .service(
                         web::scope("/rgpd")
                             .wrap(middleware::AuthMiddleware)
-                            .route("/register", web::get().to(entities::rgpd::handlers::get_register))
-                            .route("/register", web::post().to(entities::rgpd::handlers::add_to_register))
-                            .route("/register/{id}", web::put().to(entities::rgpd::handlers::update_register_entry))
-                            .route("/access-requests", web::get().to(entities::rgpd::handlers::list_access_requests))
-                            .route("/access-requests", web::post().to(entities::rgpd::handlers::create_access_request))
-                            .route("/access-requests/{id}", web::get().to(entities::rgpd::handlers::get_access_request))
-                            .route("/access-requests/{id}/respond", web::post().to(entities::rgpd::handlers::respond_to_request))
-                            .route("/breaches", web::get().to(entities::rgpd::handlers::list_breaches))
-                            .route("/breaches", web::post().to(entities::rgpd::handlers::create_breach))
-                            .route("/breaches/{id}", web::get().to(entities::rgpd::handlers::get_breach))
-                            .route("/breaches/{id}", web::put().to(entities::rgpd::handlers::update_breach))
+                            .wrap(
+                                actix_web::middleware::DefaultHeaders::new()
+                                    .add(("Deprecation", "true"))
+                                    .add(("Sunset", entities::rgpd::legacy::SUNSET))
+                                    .add((header::LINK, entities::rgpd::legacy::SUCCESSOR_LINK))
+                            )
+                            .route("/register", web::get().to(entities::rgpd::legacy::get_register))
+                            .route("/register", web::post().to(entities::rgpd::legacy::add_to_register))
+                            .route("/register/{id}", web::put().to(entities::rgpd::legacy::update_register_entry))
+                            .route("/access-requests", web::get().to(entities::rgpd::legacy::list_access_requests))
+                            .route("/access-requests", web::post().to(entities::rgpd::legacy::create_access_request))
+                            .route("/access-requests/{id}", web::get().to(entities::rgpd::legacy::get_access_request))
+                            .route("/access-requests/{id}/respond", web::post().to(entities::rgpd::legacy::respond_to_request))
+                            .route("/breaches", web::get().to(entities::rgpd::legacy::list_breaches))
+                            .route("/breaches", web::post().to(entities::rgpd::legacy::create_breach))
+                            .route("/breaches/{id}", web::get().to(entities::rgpd::legacy::get_breach))
+                            .route("/breaches/{id}", web::put().to(entities::rgpd::legacy::update_breach))
                     )
                     .service(
                         web::scope("/catalogue")
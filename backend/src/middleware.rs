@@ -1,10 +1,101 @@
 use actix_web::{dev::ServiceRequest, Error, HttpMessage, HttpRequest};
+use actix_web::error::InternalError;
 use actix_web::dev::{Service, ServiceResponse, Transform};
-use actix_web::web::Data;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::web::{Data, Query};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 use futures::future::{ok, Ready, LocalBoxFuture};
-use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
-use crate::config::Config;
-use crate::auth::jwt::Claims;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, Statement};
+use uuid::Uuid;
+use crate::auth::api_tokens;
+use crate::auth::jwt::{Claims, JwtKeys, validate_session, TOKEN_TYPE_ACCESS};
+use crate::entities::hierarchy;
+use crate::entities::lifecycle;
+use crate::errors::ApiError;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Identifiant de corrélation de la requête en cours, s'il y en a une.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Attribue à chaque requête un identifiant de corrélation : celui fourni par
+/// le client dans `X-Request-Id` s'il est exploitable, sinon un UUID généré.
+/// L'identifiant est renvoyé dans l'en-tête de réponse et dans le corps des erreurs.
+pub struct RequestIdMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestIdMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestIdMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestIdMiddlewareService { service })
+    }
+}
+
+pub struct RequestIdMiddlewareService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id = req.headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| !v.is_empty() && v.len() <= 128 && v.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .map(|v| v.to_string())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+        // Pas de clone de la requête ici : le routeur exige d'en être l'unique détenteur
+        let fut = REQUEST_ID.sync_scope(request_id.clone(), || self.service.call(req));
+
+        Box::pin(REQUEST_ID.scope(request_id.clone(), async move {
+            let header_value = HeaderValue::from_str(&request_id).ok();
+            match fut.await {
+                Ok(mut res) => {
+                    if let Some(value) = header_value {
+                        res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                    }
+                    Ok(res)
+                }
+                // Les erreurs remontées par les middlewares internes sont rendues
+                // ici, tant que l'identifiant de corrélation est encore accessible.
+                Err(e) => {
+                    let mut response = e.error_response();
+                    if let Some(value) = header_value {
+                        response.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                    }
+                    Err(InternalError::from_response(e, response).into())
+                }
+            }
+        }))
+    }
+}
 
 pub struct AuthMiddleware;
 
@@ -21,12 +112,12 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ok(AuthMiddlewareService { service })
+        ok(AuthMiddlewareService { service: Rc::new(service) })
     }
 }
 
 pub struct AuthMiddlewareService<S> {
-    service: S,
+    service: Rc<S>,
 }
 
 impl<S, B> Service<ServiceRequest> for AuthMiddlewareService<S>
@@ -42,33 +133,193 @@ where
     actix_web::dev::forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        let config = req.app_data::<Data<Config>>().cloned();
-        let auth_header = req.headers().get("Authorization");
-
-        if let Some(header_value) = auth_header {
-            if let Ok(header_str) = header_value.to_str() {
-                if header_str.starts_with("Bearer ") {
-                    let token = &header_str[7..];
-                    if let Some(cfg) = config {
-                        let decoding_key = DecodingKey::from_secret(cfg.jwt_secret.as_ref());
-                        let validation = Validation::new(Algorithm::HS256);
-
-                        if let Ok(token_data) = decode::<Claims>(token, &decoding_key, &validation) {
-                            req.extensions_mut().insert(token_data.claims);
-                            return Box::pin(self.service.call(req));
-                        }
-                    }
-                }
-            }
+        let jwt_keys = req.app_data::<Data<JwtKeys>>().cloned();
+        let db = req.app_data::<Data<DatabaseConnection>>().cloned();
+        let token = req.headers()
+            .get("Authorization")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .map(|t| t.trim().to_string());
+
+        // Jeton d'API (`hct_...`) ou JWT de session
+        let api_token = token.clone().filter(|t| t.starts_with(api_tokens::TOKEN_PREFIX));
+        let mut claims = None;
+        if api_token.is_none()
+            && let Some(token) = &token
+            && let Some(jwt_keys) = &jwt_keys
+        {
+            claims = jwt_keys.verify(token, TOKEN_TYPE_ACCESS).ok();
         }
 
+        let service = self.service.clone();
         Box::pin(async move {
-            Err(actix_web::error::ErrorUnauthorized("Invalid or missing token"))
+            let Some(db) = db else {
+                return Err(ApiError::Unauthorized("Invalid or missing token".to_string()).into());
+            };
+            let mut token_entity_id = None;
+            if let Some(token) = api_token {
+                let auth = api_tokens::authenticate(db.get_ref(), &token).await?;
+                api_tokens::check_scope(req.method(), req.path(), &auth.scopes)?;
+                token_entity_id = auth.entity_id;
+                claims = Some(auth.claims);
+            }
+            let Some(claims) = claims else {
+                return Err(ApiError::Unauthorized("Invalid or missing token".to_string()).into());
+            };
+            validate_session(db.get_ref(), &claims).await?;
+
+            // Rattachements chargés une fois par requête ; l'entité visée par le
+            // chemin est vérifiée avant le handler
+            let mut memberships = EntityMemberships::load(db.get_ref(), claims.user_id).await?;
+            if let Some(entity_id) = token_entity_id {
+                memberships.restrict_to(db.get_ref(), entity_id).await?;
+            }
+            if let Some(entity_id) = path_entity_id(req.path(), req.query_string()) {
+                if memberships.role(entity_id).is_none() {
+                    return Err(ApiError::Forbidden("Access denied".to_string()).into());
+                }
+                // Une entité archivée est en lecture seule, hors archivage et suppression
+                if !req.method().is_safe() && !is_lifecycle_route(req.path()) {
+                    lifecycle::ensure_writable(db.get_ref(), entity_id).await?;
+                }
+            }
+
+            req.extensions_mut().insert(claims);
+            req.extensions_mut().insert(memberships);
+            service.call(req).await
         })
     }
 }
 
+/// Rôles de l'utilisateur courant dans ses entités, relus à chaque requête :
+/// un retrait ou un changement de rôle s'applique sans attendre l'expiration
+/// du jeton. Un rôle s'hérite vers les filiales ; le rattachement le plus
+/// proche (l'entité elle-même, puis ses ancêtres) l'emporte.
+#[derive(Debug, Clone, Default)]
+pub struct EntityMemberships {
+    roles: HashMap<Uuid, Membership>,
+}
+
+#[derive(Debug, Clone)]
+struct Membership {
+    role: String,
+    inherited_from: Option<Uuid>,
+}
+
+impl EntityMemberships {
+    /// Requête récursive partant des rattachements directs de l'utilisateur :
+    /// seules ses entités et leurs filiales sont lues. Une filiale rattachée
+    /// directement n'hérite pas, sa ligne de départ l'emporte.
+    pub async fn load(db: &DatabaseConnection, user_id: Uuid) -> Result<Self, ApiError> {
+        // `UNION` écarte les doublons et arrête la récursion sur un cycle
+        let statement = Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"WITH RECURSIVE memberships(entity_id, role, source_id) AS (
+                   SELECT entity_id, role, entity_id FROM user_entities WHERE user_id = $1
+                   UNION
+                   SELECT e.id, m.role, m.source_id
+                   FROM entities e
+                   JOIN memberships m ON e.parent_id = m.entity_id
+                   WHERE NOT EXISTS (
+                       SELECT 1 FROM user_entities d WHERE d.user_id = $1 AND d.entity_id = e.id
+                   )
+               )
+               SELECT entity_id, role, source_id FROM memberships"#,
+            [user_id.into()],
+        );
+
+        let mut roles = HashMap::new();
+        for row in db.query_all(statement).await? {
+            let entity_id: Uuid = row.try_get("", "entity_id")?;
+            let source_id: Uuid = row.try_get("", "source_id")?;
+            let role: String = row.try_get("", "role")?;
+            let inherited_from = (source_id != entity_id).then_some(source_id);
+            roles.insert(entity_id, Membership { role, inherited_from });
+        }
+        Ok(Self { roles })
+    }
+
+    /// Limite les rattachements à l'entité et à ses filiales (jeton d'API
+    /// limité à une entité).
+    pub async fn restrict_to(&mut self, db: &DatabaseConnection, entity_id: Uuid) -> Result<(), ApiError> {
+        let subtree: HashSet<Uuid> = hierarchy::subtree(db, entity_id).await?.into_iter().collect();
+        self.roles.retain(|id, _| subtree.contains(id));
+        Ok(())
+    }
+
+    pub fn role(&self, entity_id: Uuid) -> Option<&str> {
+        self.roles.get(&entity_id).map(|m| m.role.as_str())
+    }
+
+    /// Entité dont le rôle est hérité, `None` pour un rattachement direct.
+    pub fn inherited_from(&self, entity_id: Uuid) -> Option<Uuid> {
+        self.roles.get(&entity_id).and_then(|m| m.inherited_from)
+    }
+
+    pub fn entity_ids(&self) -> Vec<Uuid> {
+        self.roles.keys().copied().collect()
+    }
+}
+
+// Entité désignée par la requête : `/entities/{id}/...`, ou `?entity_id=` sur
+// les anciennes routes `/rgpd`
+fn path_entity_id(path: &str, query: &str) -> Option<Uuid> {
+    let segments: Vec<&str> = path.trim_start_matches("/api")
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    match segments.as_slice() {
+        ["entities", id, ..] => Uuid::parse_str(id).ok(),
+        ["rgpd", ..] => Query::<HashMap<String, String>>::from_query(query).ok()
+            .and_then(|q| q.get("entity_id").and_then(|id| Uuid::parse_str(id).ok())),
+        _ => None,
+    }
+}
+
+fn is_lifecycle_route(path: &str) -> bool {
+    let segments: Vec<&str> = path.trim_start_matches("/api")
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    matches!(segments.as_slice(), ["entities", _, "archive" | "deletion"])
+}
+
+/// Adresse IP du client. Les en-têtes `Forwarded` / `X-Forwarded-For` ne sont
+/// pris en compte que si le serveur est déclaré derrière un reverse proxy.
+pub fn client_ip(req: &HttpRequest, behind_proxy: bool) -> Option<String> {
+    let info = req.connection_info();
+    let addr = if behind_proxy { info.realip_remote_addr() } else { info.peer_addr() };
+    addr.map(|a| a.to_string())
+}
+
 pub fn get_current_user_id(req: &HttpRequest) -> Option<uuid::Uuid> {
     req.extensions().get::<Claims>().map(|c| c.user_id)
 }
 
+/// Rôle de l'utilisateur courant dans l'entité, `None` s'il n'en est pas membre.
+pub fn get_entity_role(req: &HttpRequest, entity_id: Uuid) -> Option<String> {
+    req.extensions().get::<EntityMemberships>()
+        .and_then(|m| m.role(entity_id).map(str::to_string))
+}
+
+/// Entité d'où l'utilisateur courant hérite son rôle dans `entity_id`.
+pub fn get_inherited_from(req: &HttpRequest, entity_id: Uuid) -> Option<Uuid> {
+    req.extensions().get::<EntityMemberships>()
+        .and_then(|m| m.inherited_from(entity_id))
+}
+
+/// Entités dont l'utilisateur courant est membre (directement ou par héritage).
+pub fn get_member_entity_ids(req: &HttpRequest) -> Vec<Uuid> {
+    req.extensions().get::<EntityMemberships>()
+        .map(EntityMemberships::entity_ids)
+        .unwrap_or_default()
+}
+
+/// Entité active du jeton, si l'utilisateur en est toujours membre.
+pub fn get_active_entity_id(req: &HttpRequest) -> Option<Uuid> {
+    let extensions = req.extensions();
+    let entity_id = extensions.get::<Claims>()?.active_entity_id?;
+    extensions.get::<EntityMemberships>()?.role(entity_id)?;
+    Some(entity_id)
+}
+
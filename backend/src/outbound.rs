@@ -0,0 +1,181 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{Context, Result};
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::{redirect, Client, RequestBuilder, Url};
+use crate::config::Config;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REDIRECTS: usize = 5;
+
+/// Client HTTP des requêtes émises vers des URL saisies par les
+/// administrateurs (fournisseurs d'identité, vérification de domaines).
+///
+/// Seul HTTPS est accepté et les adresses privées, locales ou réservées sont
+/// refusées : pour un hôte littéral à l'envoi, pour un nom après résolution
+/// DNS, et à chaque redirection. `OUTBOUND_ALLOW_INSECURE` lève ces
+/// restrictions (développement, fournisseur de test local).
+#[derive(Clone)]
+pub struct OutboundClient {
+    client: Client,
+    allow_insecure: bool,
+}
+
+impl OutboundClient {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Self::new(config.outbound_allow_insecure)
+    }
+
+    pub fn new(allow_insecure: bool) -> Result<Self> {
+        let mut builder = Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    return attempt.error("too many redirects");
+                }
+                match check_url(attempt.url(), allow_insecure) {
+                    Ok(()) => attempt.follow(),
+                    Err(e) => attempt.error(e.to_string()),
+                }
+            }));
+        if !allow_insecure {
+            // Un proxy résoudrait lui-même les noms, hors du contrôle des adresses
+            builder = builder
+                .https_only(true)
+                .no_proxy()
+                .dns_resolver(Arc::new(PublicResolver));
+        }
+        Ok(Self {
+            client: builder.build().context("Failed to build outbound HTTP client")?,
+            allow_insecure,
+        })
+    }
+
+    /// Contrôle syntaxique d'une URL avant enregistrement (schéma, hôte littéral).
+    pub fn check(&self, url: &str) -> Result<Url> {
+        let url = Url::parse(url).context("Invalid URL")?;
+        check_url(&url, self.allow_insecure)?;
+        Ok(url)
+    }
+
+    pub fn get(&self, url: &str) -> Result<RequestBuilder> {
+        Ok(self.client.get(self.check(url)?))
+    }
+
+    pub fn post(&self, url: &str) -> Result<RequestBuilder> {
+        Ok(self.client.post(self.check(url)?))
+    }
+}
+
+fn check_url(url: &Url, allow_insecure: bool) -> Result<()> {
+    if allow_insecure {
+        return Ok(());
+    }
+    if url.scheme() != "https" {
+        return Err(anyhow::anyhow!("Only https URLs are allowed: {}", url));
+    }
+    // Les hôtes littéraux ne passent pas par le résolveur
+    let host = url.host_str()
+        .ok_or_else(|| anyhow::anyhow!("URL has no host: {}", url))?;
+    if let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>()
+        && !is_public(ip)
+    {
+        return Err(anyhow::anyhow!("Address {} is not allowed", ip));
+    }
+    Ok(())
+}
+
+/// Résolveur système qui écarte les adresses non publiques.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if let Some(addr) = addrs.iter().find(|a| !is_public(a.ip())) {
+                return Err(format!("{} resolves to a disallowed address {}", host, addr.ip()).into());
+            }
+            if addrs.is_empty() {
+                return Err(format!("{} did not resolve", host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => is_public_v4(v4),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        || (a == 100 && (64..128).contains(&b)) // CGNAT
+        || (a == 192 && b == 0 && c == 0) // IETF
+        || (a == 198 && (18..20).contains(&b)) // tests de performance
+        || a >= 240) // réservé
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        || (segments[0] & 0xfe00) == 0xfc00 // adresses locales uniques
+        || (segments[0] & 0xffc0) == 0xfe80 // lien local
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8) // documentation
+        || (segments[0] == 0x0064 && segments[1] == 0xff9b) // NAT64
+        || segments[0] == 0x2002 // 6to4
+        || segments[..6] == [0; 6]) // IPv4 compatible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_non_public_addresses() {
+        for ip in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0", "::1", "fd00::1", "fe80::1", "::ffff:127.0.0.1"] {
+            assert!(!is_public(ip.parse().unwrap()), "{} should be rejected", ip);
+        }
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public(ip.parse().unwrap()), "{} should be allowed", ip);
+        }
+    }
+
+    #[test]
+    fn requires_https_and_public_literal_hosts() {
+        let http = OutboundClient::new(false).unwrap();
+        assert!(http.check("https://idp.example.com/realms/a").is_ok());
+        assert!(http.check("http://idp.example.com").is_err());
+        assert!(http.check("https://127.0.0.1/").is_err());
+        assert!(http.check("https://[::1]:8443/").is_err());
+        assert!(http.check("https://169.254.169.254/latest/meta-data").is_err());
+
+        let insecure = OutboundClient::new(true).unwrap();
+        assert!(insecure.check("http://127.0.0.1:8080").is_ok());
+    }
+
+    #[tokio::test]
+    async fn refuses_hostnames_resolving_to_private_addresses() {
+        let http = OutboundClient::new(false).unwrap();
+        let error = http.get("https://localhost/").unwrap().send().await.unwrap_err();
+        assert!(format!("{:?}", error).contains("disallowed address"), "{:?}", error);
+    }
+}
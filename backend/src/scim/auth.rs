@@ -0,0 +1,78 @@
+use actix_web::{dev::Payload, http::{header, StatusCode}, web, FromRequest, HttpRequest};
+use chrono::{Duration, Utc};
+use futures::future::LocalBoxFuture;
+use rand::{RngCore, rngs::OsRng};
+use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, Set, IntoActiveModel, ActiveModelTrait};
+use crate::auth::mfa::sha256_hex;
+use crate::entities::lifecycle;
+use crate::entities_orm::scim_token::{self, Entity as ScimTokenEntity, Column as ScimTokenColumn};
+use crate::scim::error::ScimError;
+
+pub const TOKEN_PREFIX: &str = "scim_";
+
+// Évite une écriture en base à chaque requête d'une synchronisation massive
+const LAST_USED_PRECISION_SECONDS: i64 = 60;
+
+/// Génère un jeton SCIM ; seule son empreinte SHA-256 est conservée.
+pub fn generate_token() -> (String, String) {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token = format!("{}{}", TOKEN_PREFIX, bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+    let hash = sha256_hex(&token);
+    (token, hash)
+}
+
+/// Client SCIM authentifié par le jeton bearer de son entité.
+pub struct ScimAuth(pub scim_token::Model);
+
+impl FromRequest for ScimAuth {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let db = req.app_data::<web::Data<DatabaseConnection>>().cloned();
+        let method = req.method().clone();
+        let token = req.headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .map(|t| t.trim().to_string());
+
+        Box::pin(async move {
+            let db = db.ok_or_else(|| ScimError::new(StatusCode::INTERNAL_SERVER_ERROR, None, "Database not configured"))?;
+            let token = token
+                .filter(|t| t.starts_with(TOKEN_PREFIX))
+                .ok_or_else(|| ScimError::unauthorized("Missing or invalid bearer token"))?;
+
+            let record = ScimTokenEntity::find()
+                .filter(ScimTokenColumn::TokenHash.eq(sha256_hex(&token)))
+                .one(db.get_ref())
+                .await
+                .map_err(ScimError::from)?
+                .ok_or_else(|| ScimError::unauthorized("Missing or invalid bearer token"))?;
+
+            let now = Utc::now();
+            if record.expires_at.is_some_and(|t| t <= now) {
+                return Err(ScimError::unauthorized("Bearer token expired").into());
+            }
+
+            // Entité archivée : l'annuaire reste consultable mais n'est plus modifiable
+            if !method.is_safe()
+                && lifecycle::is_archived(db.get_ref(), record.entity_id).await.map_err(ScimError::from)?
+            {
+                return Err(ScimError::new(StatusCode::CONFLICT, None, "Entity is archived and read-only").into());
+            }
+
+            let stale = record.last_used_at
+                .is_none_or(|t| now - t > Duration::seconds(LAST_USED_PRECISION_SECONDS));
+            let record = if stale {
+                let mut active = record.into_active_model();
+                active.last_used_at = Set(Some(now));
+                active.update(db.get_ref()).await.map_err(ScimError::from)?
+            } else {
+                record
+            };
+            Ok(ScimAuth(record))
+        })
+    }
+}
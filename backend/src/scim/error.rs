@@ -0,0 +1,116 @@
+use actix_web::{http::{header, StatusCode}, HttpResponse, ResponseError};
+use serde::Serialize;
+use crate::errors::ApiError;
+use crate::scim::models::{CONTENT_TYPE, SCHEMA_ERROR};
+
+/// Erreur renvoyée par les endpoints SCIM, au format imposé par la RFC 7644
+/// (§3.12) plutôt qu'en `application/problem+json`.
+#[derive(Debug, thiserror::Error)]
+#[error("{detail}")]
+pub struct ScimError {
+    status: StatusCode,
+    scim_type: Option<&'static str>,
+    detail: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScimErrorBody<'a> {
+    schemas: [&'static str; 1],
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scim_type: Option<&'static str>,
+    detail: &'a str,
+}
+
+impl ScimError {
+    pub fn new(status: StatusCode, scim_type: Option<&'static str>, detail: impl Into<String>) -> Self {
+        Self { status, scim_type, detail: detail.into() }
+    }
+
+    pub fn invalid_syntax(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, Some("invalidSyntax"), detail)
+    }
+
+    pub fn invalid_filter(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, Some("invalidFilter"), detail)
+    }
+
+    pub fn invalid_path(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, Some("invalidPath"), detail)
+    }
+
+    pub fn invalid_value(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, Some("invalidValue"), detail)
+    }
+
+    pub fn mutability(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, Some("mutability"), detail)
+    }
+
+    pub fn uniqueness(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, Some("uniqueness"), detail)
+    }
+
+    pub fn unauthorized(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, None, detail)
+    }
+
+    pub fn not_found(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, None, detail)
+    }
+
+    pub fn precondition_failed() -> Self {
+        Self::new(StatusCode::PRECONDITION_FAILED, None, "Resource version does not match If-Match")
+    }
+
+    /// Équivalent de `ApiError::internal` pour les handlers SCIM.
+    pub fn internal<E: std::fmt::Display>(message: &'static str) -> impl FnOnce(E) -> ScimError {
+        move |e| {
+            log::error!("{}: {}", message, e);
+            ScimError::new(StatusCode::INTERNAL_SERVER_ERROR, None, message)
+        }
+    }
+}
+
+impl From<sea_orm::DbErr> for ScimError {
+    fn from(e: sea_orm::DbErr) -> Self {
+        log::error!("Database error (SCIM): {}", e);
+        ScimError::new(StatusCode::INTERNAL_SERVER_ERROR, None, "Database error")
+    }
+}
+
+impl From<ApiError> for ScimError {
+    fn from(e: ApiError) -> Self {
+        let status = e.status_code();
+        let scim_type = match &e {
+            ApiError::Validation(_) | ApiError::BadRequest(_) => Some("invalidValue"),
+            ApiError::Conflict(_) => Some("uniqueness"),
+            _ => None,
+        };
+        if status.is_server_error() {
+            log::error!("SCIM request failed: {}", e);
+        }
+        ScimError::new(status, scim_type, e.to_string())
+    }
+}
+
+impl ResponseError for ScimError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let body = ScimErrorBody {
+            schemas: [SCHEMA_ERROR],
+            status: self.status.as_u16().to_string(),
+            scim_type: self.scim_type,
+            detail: &self.detail,
+        };
+        let mut response = HttpResponse::build(self.status);
+        if self.status == StatusCode::UNAUTHORIZED {
+            response.insert_header((header::WWW_AUTHENTICATE, "Bearer realm=\"scim\""));
+        }
+        response.content_type(CONTENT_TYPE).json(body)
+    }
+}
@@ -0,0 +1,516 @@
+//! Filtres SCIM (RFC 7644 §3.4.2.2) et chemins d'attributs des PATCH
+//! (§3.5.2), évalués sur la représentation JSON des ressources.
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use crate::scim::error::ScimError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Co,
+    Sw,
+    Ew,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+/// Les chemins d'attributs sont conservés en minuscules : les noms
+/// d'attributs SCIM sont insensibles à la casse.
+#[derive(Debug, Clone)]
+pub enum Filter {
+    Compare(Vec<String>, CompareOp, Value),
+    Present(Vec<String>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    ValuePath(Vec<String>, Box<Filter>),
+}
+
+/// Cible d'une opération PATCH : `attr`, `attr.sub`, `attr[filtre]` ou `attr[filtre].sub`.
+#[derive(Debug)]
+pub struct PatchPath {
+    pub attribute: Vec<String>,
+    pub filter: Option<Filter>,
+    pub sub_attribute: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Word(String),
+    Str(String),
+}
+
+fn tokenize(input: &str, error: fn(String) -> ScimError) -> Result<Vec<Token>, ScimError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            c if c.is_whitespace() => i += 1,
+            '(' => { tokens.push(Token::LParen); i += 1; }
+            ')' => { tokens.push(Token::RParen); i += 1; }
+            '[' => { tokens.push(Token::LBracket); i += 1; }
+            ']' => { tokens.push(Token::RBracket); i += 1; }
+            '"' => {
+                let start = i;
+                let mut escaped = false;
+                i += 1;
+                while i < chars.len() && (escaped || chars[i] != '"') {
+                    escaped = !escaped && chars[i] == '\\';
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err(error("Unterminated string literal".to_string()));
+                }
+                let literal: String = chars[start..=i].iter().collect();
+                let value = serde_json::from_str(&literal)
+                    .map_err(|_| error(format!("Invalid string literal {}", literal)))?;
+                tokens.push(Token::Str(value));
+                i += 1;
+            }
+            _ => {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && !"()[]\"".contains(chars[i]) {
+                    i += 1;
+                }
+                tokens.push(Token::Word(chars[start..i].iter().collect()));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// Retire le préfixe de schéma (`urn:...:User:userName`) et découpe les sous-attributs
+fn attribute_path(raw: &str, error: fn(String) -> ScimError) -> Result<Vec<String>, ScimError> {
+    let path = if raw.to_ascii_lowercase().starts_with("urn:") {
+        raw.rsplit_once(':').map(|(_, attr)| attr).unwrap_or(raw)
+    } else {
+        raw
+    };
+    let segments: Vec<String> = path.split('.').map(|s| s.to_ascii_lowercase()).collect();
+    let valid = segments.iter().all(|s| {
+        !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '$')
+    });
+    if !valid {
+        return Err(error(format!("Invalid attribute path {}", raw)));
+    }
+    Ok(segments)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    error: fn(String) -> ScimError,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ScimError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err((self.error)(format!("Expected {:?}", expected))),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, ScimError> {
+        let mut left = self.parse_and()?;
+        while self.keyword("or") {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Filter::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, ScimError> {
+        let mut left = self.parse_unary()?;
+        while self.keyword("and") {
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            left = Filter::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Filter, ScimError> {
+        if self.keyword("not") {
+            self.pos += 1;
+            self.expect(Token::LParen)?;
+            let inner = self.parse_or()?;
+            self.expect(Token::RParen)?;
+            return Ok(Filter::Not(Box::new(inner)));
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let inner = self.parse_or()?;
+            self.expect(Token::RParen)?;
+            return Ok(inner);
+        }
+        self.parse_attribute_expression()
+    }
+
+    fn parse_attribute_expression(&mut self) -> Result<Filter, ScimError> {
+        let path = match self.next() {
+            Some(Token::Word(w)) => attribute_path(&w, self.error)?,
+            _ => return Err((self.error)("Expected an attribute path".to_string())),
+        };
+
+        if self.peek() == Some(&Token::LBracket) {
+            self.pos += 1;
+            let inner = self.parse_or()?;
+            self.expect(Token::RBracket)?;
+            return Ok(Filter::ValuePath(path, Box::new(inner)));
+        }
+
+        let operator = match self.next() {
+            Some(Token::Word(w)) => w.to_ascii_lowercase(),
+            _ => return Err((self.error)("Expected a comparison operator".to_string())),
+        };
+        let op = match operator.as_str() {
+            "pr" => return Ok(Filter::Present(path)),
+            "eq" => CompareOp::Eq,
+            "ne" => CompareOp::Ne,
+            "co" => CompareOp::Co,
+            "sw" => CompareOp::Sw,
+            "ew" => CompareOp::Ew,
+            "gt" => CompareOp::Gt,
+            "ge" => CompareOp::Ge,
+            "lt" => CompareOp::Lt,
+            "le" => CompareOp::Le,
+            other => return Err((self.error)(format!("Unsupported operator {}", other))),
+        };
+
+        let value = match self.next() {
+            Some(Token::Str(s)) => Value::String(s),
+            Some(Token::Word(w)) => match w.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" => Value::Null,
+                _ => serde_json::from_str::<Value>(&w)
+                    .ok()
+                    .filter(Value::is_number)
+                    .ok_or_else(|| (self.error)(format!("Invalid comparison value {}", w)))?,
+            },
+            _ => return Err((self.error)("Expected a comparison value".to_string())),
+        };
+        Ok(Filter::Compare(path, op, value))
+    }
+}
+
+pub fn parse_filter(input: &str) -> Result<Filter, ScimError> {
+    let error: fn(String) -> ScimError = ScimError::invalid_filter;
+    let mut parser = Parser { tokens: tokenize(input, error)?, pos: 0, error };
+    let filter = parser.parse_or()?;
+    if parser.pos != parser.tokens.len() {
+        return Err(error("Unexpected trailing content in filter".to_string()));
+    }
+    Ok(filter)
+}
+
+pub fn parse_path(input: &str) -> Result<PatchPath, ScimError> {
+    let error: fn(String) -> ScimError = ScimError::invalid_path;
+    let mut parser = Parser { tokens: tokenize(input, error)?, pos: 0, error };
+
+    let attribute = match parser.next() {
+        Some(Token::Word(w)) => attribute_path(&w, error)?,
+        _ => return Err(error(format!("Invalid path {}", input))),
+    };
+    let mut path = PatchPath { attribute, filter: None, sub_attribute: None };
+
+    if parser.peek() == Some(&Token::LBracket) {
+        parser.pos += 1;
+        path.filter = Some(parser.parse_or()?);
+        parser.expect(Token::RBracket)?;
+        if let Some(Token::Word(w)) = parser.peek() {
+            let sub = w.strip_prefix('.')
+                .filter(|s| !s.is_empty() && !s.contains('.'))
+                .ok_or_else(|| error(format!("Invalid path {}", input)))?;
+            path.sub_attribute = Some(sub.to_ascii_lowercase());
+            parser.pos += 1;
+        }
+    }
+    if parser.pos != parser.tokens.len() {
+        return Err(error(format!("Invalid path {}", input)));
+    }
+    Ok(path)
+}
+
+/// Lecture d'un attribut sans tenir compte de la casse.
+pub fn get_attribute<'a>(value: &'a Value, name: &str) -> Option<&'a Value> {
+    value.as_object()?
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v)
+}
+
+// Valeurs désignées par le chemin ; un attribut complexe comparé
+// directement l'est par son sous-attribut `value`
+fn resolve<'a>(value: &'a Value, path: &[String]) -> Vec<&'a Value> {
+    match (value, path.split_first()) {
+        (Value::Array(items), _) => items.iter().flat_map(|item| resolve(item, path)).collect(),
+        (Value::Object(_), Some((first, rest))) => get_attribute(value, first)
+            .map(|v| resolve(v, rest))
+            .unwrap_or_default(),
+        (Value::Object(_), None) => get_attribute(value, "value").into_iter().collect(),
+        (_, None) => vec![value],
+        (_, Some(_)) => Vec::new(),
+    }
+}
+
+// Éléments d'un attribut multivalué, pour les filtres `attr[...]`
+fn elements<'a>(value: &'a Value, path: &[String]) -> Vec<&'a Value> {
+    match (value, path.split_first()) {
+        (Value::Array(items), None) => items.iter().collect(),
+        (_, None) => vec![value],
+        (Value::Array(items), Some(_)) => items.iter().flat_map(|item| elements(item, path)).collect(),
+        (_, Some((first, rest))) => get_attribute(value, first)
+            .map(|v| elements(v, rest))
+            .unwrap_or_default(),
+    }
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(s) => s.is_empty(),
+        Value::Array(items) => items.is_empty(),
+        Value::Object(map) => map.is_empty(),
+        _ => false,
+    }
+}
+
+fn ordering_matches(op: CompareOp, ordering: std::cmp::Ordering) -> bool {
+    use std::cmp::Ordering::*;
+    match op {
+        CompareOp::Eq => ordering == Equal,
+        CompareOp::Gt => ordering == Greater,
+        CompareOp::Ge => ordering != Less,
+        CompareOp::Lt => ordering == Less,
+        CompareOp::Le => ordering != Greater,
+        _ => false,
+    }
+}
+
+fn compare(actual: &Value, op: CompareOp, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::String(a), Value::String(b)) => {
+            // Les dates (meta.lastModified…) se comparent chronologiquement
+            if let (Ok(a), Ok(b)) = (DateTime::parse_from_rfc3339(a), DateTime::parse_from_rfc3339(b)) {
+                return ordering_matches(op, a.with_timezone(&Utc).cmp(&b.with_timezone(&Utc)));
+            }
+            let (a, b) = (a.to_lowercase(), b.to_lowercase());
+            match op {
+                CompareOp::Co => a.contains(&b),
+                CompareOp::Sw => a.starts_with(&b),
+                CompareOp::Ew => a.ends_with(&b),
+                _ => ordering_matches(op, a.cmp(&b)),
+            }
+        }
+        (Value::Number(a), Value::Number(b)) => match (a.as_f64(), b.as_f64()) {
+            (Some(a), Some(b)) => a.partial_cmp(&b).is_some_and(|o| ordering_matches(op, o)),
+            _ => false,
+        },
+        (Value::Bool(a), Value::Bool(b)) => op == CompareOp::Eq && a == b,
+        _ => false,
+    }
+}
+
+impl Filter {
+    pub fn matches(&self, resource: &Value) -> bool {
+        match self {
+            Filter::Compare(path, CompareOp::Ne, expected) => {
+                !resolve(resource, path).iter().any(|v| compare(v, CompareOp::Eq, expected))
+            }
+            Filter::Compare(path, op, expected) => {
+                resolve(resource, path).iter().any(|v| compare(v, *op, expected))
+            }
+            Filter::Present(path) => elements(resource, path).iter().any(|v| !is_empty(v)),
+            Filter::And(a, b) => a.matches(resource) && b.matches(resource),
+            Filter::Or(a, b) => a.matches(resource) || b.matches(resource),
+            Filter::Not(inner) => !inner.matches(resource),
+            Filter::ValuePath(path, inner) => elements(resource, path).iter().any(|v| inner.matches(v)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{body::to_bytes, http::StatusCode, ResponseError};
+    use serde_json::json;
+    use super::*;
+
+    fn user() -> Value {
+        json!({
+            "userName": "Alice.Martin@Example.com",
+            "displayName": "Alice \"Ali\" Martin",
+            "name": { "givenName": "Alice", "familyName": "Martin" },
+            "emails": [
+                { "value": "alice@example.com", "type": "work", "primary": true },
+                { "value": "alice@home.test", "type": "home" }
+            ],
+            "title": "",
+            "active": true,
+            "meta": { "lastModified": "2024-05-01T10:00:00Z" }
+        })
+    }
+
+    fn matches(filter: &str) -> bool {
+        parse_filter(filter)
+            .unwrap_or_else(|e| panic!("filter {} should parse: {}", filter, e))
+            .matches(&user())
+    }
+
+    #[test]
+    fn compares_attribute_values() {
+        let cases = [
+            (r#"userName eq "alice.martin@example.com""#, true),
+            (r#"userName eq "alice""#, false),
+            (r#"userName ne "alice""#, true),
+            (r#"userName co "martin@""#, true),
+            (r#"userName co "bob""#, false),
+            (r#"userName sw "ALICE.""#, true),
+            (r#"userName sw "martin""#, false),
+            (r#"userName ew ".com""#, true),
+            (r#"name.familyName eq "Martin""#, true),
+            (r#"emails eq "alice@home.test""#, true),
+            (r#"emails.type eq "work""#, true),
+            ("active eq true", true),
+            ("active eq false", false),
+            (r#"meta.lastModified gt "2024-05-01T09:00:00+00:00""#, true),
+            (r#"meta.lastModified lt "2024-05-01T11:00:00+02:00""#, false),
+        ];
+        for (filter, expected) in cases {
+            assert_eq!(matches(filter), expected, "{}", filter);
+        }
+    }
+
+    #[test]
+    fn presence_ignores_empty_values() {
+        assert!(matches("userName pr"));
+        assert!(matches("emails pr"));
+        assert!(!matches("title pr"));
+        assert!(!matches("nickName pr"));
+        assert!(!matches("name.middleName pr"));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        // a or (b and c) : vrai par `a` seul
+        assert!(matches(r#"userName pr or title pr and nickName pr"#));
+        // (a or b) and c : faux à cause de `c`
+        assert!(!matches(r#"(userName pr or title pr) and nickName pr"#));
+        // (c and a) or b : faux, b l'est aussi
+        assert!(!matches(r#"nickName pr and userName pr or title pr"#));
+        assert!(matches(r#"nickName pr and userName pr or active eq true"#));
+    }
+
+    #[test]
+    fn not_negates_its_group() {
+        assert!(matches("not (title pr)"));
+        assert!(!matches("not (userName pr)"));
+        assert!(matches("not (userName pr and title pr) and active eq true"));
+        assert!(!matches("not (userName pr or title pr)"));
+    }
+
+    #[test]
+    fn value_path_filters_each_element() {
+        assert!(matches(r#"emails[type eq "work" and value co "example.com"]"#));
+        // Les deux conditions doivent porter sur le même élément
+        assert!(!matches(r#"emails[type eq "home" and value co "example.com"]"#));
+        assert!(matches(r#"emails[type eq "home"] and emails[primary eq true]"#));
+    }
+
+    #[test]
+    fn string_literals_unescape_json_sequences() {
+        assert!(matches(r#"displayName eq "Alice \"Ali\" Martin""#));
+        assert!(matches(r#"displayName co "\"Ali\"""#));
+        assert!(matches(r#"displayName sw "\u0041lice""#));
+        assert!(matches(r#"displayName co "(Ali) or" or userName pr"#));
+        assert!(!matches(r#"displayName eq "Alice \\\"Ali\\\" Martin""#));
+    }
+
+    #[test]
+    fn attribute_names_and_keywords_ignore_case() {
+        assert!(matches(r#"USERNAME Eq "alice.martin@example.com""#));
+        assert!(matches(r#"Name.FamilyName SW "mar" AND NOT (Title PR)"#));
+        assert!(matches(r#"urn:ietf:params:scim:schemas:core:2.0:User:UserName pr"#));
+        assert!(matches(r#"EMAILS[TYPE eq "work"]"#));
+    }
+
+    #[test]
+    fn rejects_malformed_filters() {
+        let filters = [
+            "",
+            "userName",
+            "userName eq",
+            r#"userName xx "alice""#,
+            r#"userName regex "a.*""#,
+            r#"userName eq "unterminated"#,
+            r#"userName eq "bad \q escape""#,
+            "userName eq alice",
+            "(userName pr",
+            "userName pr)",
+            "not userName pr",
+            "userName pr and",
+            "or userName pr",
+            r#"userName pr "trailing""#,
+            r#"emails[type eq "work""#,
+            r#"emails]type eq "work"["#,
+            r#""userName" pr"#,
+            "user@name pr",
+            "name..familyName pr",
+        ];
+        for filter in filters {
+            let error = parse_filter(filter).expect_err(filter);
+            assert_eq!(error.status_code(), StatusCode::BAD_REQUEST, "{}", filter);
+        }
+    }
+
+    #[actix_web::test]
+    async fn invalid_filter_renders_scim_error() {
+        let error = parse_filter("userName eq").unwrap_err();
+        let response = error.error_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_slice(&to_bytes(response.into_body()).await.unwrap()).unwrap();
+        assert_eq!(body["scimType"], "invalidFilter");
+        assert_eq!(body["status"], "400");
+    }
+
+    #[test]
+    fn parses_patch_paths() {
+        let path = parse_path(r#"emails[type eq "work"].Value"#).unwrap();
+        assert_eq!(path.attribute, vec!["emails"]);
+        assert_eq!(path.sub_attribute.as_deref(), Some("value"));
+        assert!(path.filter.is_some_and(|f| f.matches(&json!({ "type": "work" }))));
+
+        let path = parse_path("name.givenName").unwrap();
+        assert_eq!(path.attribute, vec!["name", "givenname"]);
+        assert!(path.filter.is_none());
+
+        for invalid in ["", "emails[", r#"emails[type eq "work"].a.b"#, "emails extra"] {
+            assert_eq!(parse_path(invalid).unwrap_err().status_code(), StatusCode::BAD_REQUEST, "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,1021 @@
+use std::collections::BTreeSet;
+use actix_web::{http::{header, StatusCode}, web, HttpRequest, HttpResponse};
+use bcrypt::{hash, DEFAULT_COST};
+use chrono::Utc;
+use sea_orm::{DatabaseConnection, EntityTrait, ColumnTrait, QueryFilter, QueryOrder, PaginatorTrait, Set, IntoActiveModel, ActiveModelTrait};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use uuid::Uuid;
+use validator::ValidateEmail;
+use crate::auth::{api_tokens, sso};
+use crate::entities::email_domains;
+use crate::config::Config;
+use crate::entities_orm::{scim_group, scim_token, scim_user, user};
+use crate::entities_orm::scim_user::{Entity as ScimUserEntity, Column as ScimUserColumn, ActiveModel as ScimUserActiveModel};
+use crate::entities_orm::scim_group::{Entity as ScimGroupEntity, Column as ScimGroupColumn, ActiveModel as ScimGroupActiveModel};
+use crate::entities_orm::scim_group_member::{Entity as ScimGroupMemberEntity, Column as ScimGroupMemberColumn, ActiveModel as ScimGroupMemberActiveModel};
+use crate::entities_orm::user::{Entity as UserEntity, Column as UserColumn, ActiveModel as UserActiveModel};
+use crate::entities_orm::user_entity::{Entity as UserEntityEntity, Column as UserEntityColumn};
+use crate::scim::auth::ScimAuth;
+use crate::scim::error::ScimError;
+use crate::scim::filter::{self, Filter};
+use crate::scim::models::*;
+use crate::scim::provisioning;
+
+// ---- Utilitaires ----
+
+// Le corps est lu brut : les clients envoient `application/scim+json`
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, ScimError> {
+    serde_json::from_slice(body).map_err(|e| ScimError::invalid_syntax(format!("Invalid request body: {}", e)))
+}
+
+fn parse_patch(body: &[u8]) -> Result<PatchRequest, ScimError> {
+    let patch: PatchRequest = parse_body(body)?;
+    if !patch.schemas.iter().any(|s| s == SCHEMA_PATCH_OP) {
+        return Err(ScimError::invalid_syntax(format!("PATCH requests must use the {} schema", SCHEMA_PATCH_OP)));
+    }
+    Ok(patch)
+}
+
+fn parse_id(id: &str, resource: &str) -> Result<Uuid, ScimError> {
+    Uuid::parse_str(id).map_err(|_| ScimError::not_found(format!("{} {} not found", resource, id)))
+}
+
+fn resource_response(status: StatusCode, resource: Value, version: i32) -> HttpResponse {
+    let mut response = HttpResponse::build(status);
+    response.insert_header((header::ETAG, etag(version)));
+    if status == StatusCode::CREATED
+        && let Some(location) = resource["meta"]["location"].as_str()
+    {
+        response.insert_header((header::LOCATION, location.to_string()));
+    }
+    response.content_type(CONTENT_TYPE).json(resource)
+}
+
+fn list_result(query: &ListQuery, resources: Vec<Value>) -> Result<HttpResponse, ScimError> {
+    let filter: Option<Filter> = query.filter.as_deref()
+        .filter(|f| !f.trim().is_empty())
+        .map(filter::parse_filter)
+        .transpose()?;
+
+    let matching: Vec<Value> = resources.into_iter()
+        .filter(|r| filter.as_ref().is_none_or(|f| f.matches(r)))
+        .collect();
+
+    let start_index = query.start_index.unwrap_or(1).max(1) as u64;
+    let count = query.count
+        .map(|c| c.clamp(0, MAX_PAGE_SIZE as i64) as u64)
+        .unwrap_or(DEFAULT_PAGE_SIZE);
+    let total = matching.len();
+    let page = matching.into_iter()
+        .skip((start_index - 1) as usize)
+        .take(count as usize)
+        .collect();
+
+    Ok(HttpResponse::Ok()
+        .content_type(CONTENT_TYPE)
+        .json(list_response(total, start_index, page)))
+}
+
+// Comparaison faible des ETag (RFC 7232 §2.3.2)
+fn etag_matches(header_value: &str, version: i32) -> bool {
+    let current = etag(version);
+    let current = current.trim_start_matches("W/");
+    header_value.split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == current)
+}
+
+fn check_if_match(req: &HttpRequest, version: i32) -> Result<(), ScimError> {
+    match req.headers().get(header::IF_MATCH).map(|h| h.to_str()) {
+        None => Ok(()),
+        Some(Ok(value)) if etag_matches(value, version) => Ok(()),
+        Some(_) => Err(ScimError::precondition_failed()),
+    }
+}
+
+fn not_modified(req: &HttpRequest, version: i32) -> Option<HttpResponse> {
+    let value = req.headers().get(header::IF_NONE_MATCH)?.to_str().ok()?;
+    etag_matches(value, version).then(|| {
+        HttpResponse::NotModified()
+            .insert_header((header::ETAG, etag(version)))
+            .finish()
+    })
+}
+
+fn bool_value(value: &Value) -> Result<bool, ScimError> {
+    match value {
+        Value::Bool(b) => Ok(*b),
+        // Certains clients (Azure AD) envoient "True" / "False"
+        Value::String(s) if s.eq_ignore_ascii_case("true") => Ok(true),
+        Value::String(s) if s.eq_ignore_ascii_case("false") => Ok(false),
+        _ => Err(ScimError::invalid_value("Expected a boolean value")),
+    }
+}
+
+fn optional_string(value: &Value) -> Result<Option<String>, ScimError> {
+    match value {
+        Value::Null => Ok(None),
+        Value::String(s) => Ok(Some(s.trim().to_string()).filter(|s| !s.is_empty())),
+        _ => Err(ScimError::invalid_value("Expected a string value")),
+    }
+}
+
+fn normalize_user_name(user_name: &str) -> Result<String, ScimError> {
+    let user_name = user_name.trim().to_lowercase();
+    if !user_name.validate_email() {
+        return Err(ScimError::invalid_value("userName must be an email address"));
+    }
+    Ok(user_name)
+}
+
+fn operation_value(op: &PatchOperation) -> Result<&Value, ScimError> {
+    op.value.as_ref().ok_or_else(|| ScimError::invalid_value("Operation value is required"))
+}
+
+// ---- Découverte ----
+
+pub async fn get_service_provider_config(config: web::Data<Config>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(CONTENT_TYPE)
+        .json(service_provider_config(&base_url(&config)))
+}
+
+pub async fn list_resource_types(config: web::Data<Config>) -> HttpResponse {
+    let types = resource_types(&base_url(&config));
+    HttpResponse::Ok()
+        .content_type(CONTENT_TYPE)
+        .json(list_response(types.len(), 1, types))
+}
+
+pub async fn list_schemas(config: web::Data<Config>) -> HttpResponse {
+    let schemas = schemas(&base_url(&config));
+    HttpResponse::Ok()
+        .content_type(CONTENT_TYPE)
+        .json(list_response(schemas.len(), 1, schemas))
+}
+
+// ---- Users ----
+
+#[derive(Clone, PartialEq)]
+struct UserState {
+    user_name: String,
+    given_name: Option<String>,
+    family_name: Option<String>,
+    external_id: Option<String>,
+    active: bool,
+}
+
+impl UserState {
+    fn current(scim_user: &scim_user::Model, user: &user::Model) -> Self {
+        Self {
+            user_name: user.email.clone(),
+            given_name: user.first_name.clone(),
+            family_name: user.last_name.clone(),
+            external_id: scim_user.external_id.clone(),
+            active: scim_user.active,
+        }
+    }
+
+    fn from_request(body: UserRequest, default_active: bool) -> Result<Self, ScimError> {
+        let user_name = body.user_name
+            .as_deref()
+            .ok_or_else(|| ScimError::invalid_value("userName is required"))?;
+        let name = body.name.unwrap_or_default();
+        let clean = |v: Option<String>| v.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+        Ok(Self {
+            user_name: normalize_user_name(user_name)?,
+            given_name: clean(name.given_name),
+            family_name: clean(name.family_name),
+            external_id: clean(body.external_id),
+            active: body.active.unwrap_or(default_active),
+        })
+    }
+
+    // Les attributs non pris en charge sont ignorés
+    fn set(&mut self, path: &[String], value: &Value) -> Result<(), ScimError> {
+        let path: Vec<&str> = path.iter().map(String::as_str).collect();
+        match path.as_slice() {
+            ["active"] => self.active = bool_value(value)?,
+            ["username"] => {
+                let user_name = value.as_str().ok_or_else(|| ScimError::invalid_value("userName must be a string"))?;
+                self.user_name = normalize_user_name(user_name)?;
+            }
+            ["externalid"] => self.external_id = optional_string(value)?,
+            ["name", "givenname"] => self.given_name = optional_string(value)?,
+            ["name", "familyname"] => self.family_name = optional_string(value)?,
+            ["name"] => {
+                let name = value.as_object().ok_or_else(|| ScimError::invalid_value("name must be an object"))?;
+                for (key, value) in name {
+                    self.set(&["name".to_string(), key.to_ascii_lowercase()], value)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, path: &[String]) -> Result<(), ScimError> {
+        let path: Vec<&str> = path.iter().map(String::as_str).collect();
+        match path.as_slice() {
+            ["username"] => return Err(ScimError::mutability("userName is required")),
+            ["externalid"] => self.external_id = None,
+            ["name", "givenname"] => self.given_name = None,
+            ["name", "familyname"] => self.family_name = None,
+            ["name"] => {
+                self.given_name = None;
+                self.family_name = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn apply_patch(&mut self, op: &PatchOperation) -> Result<(), ScimError> {
+        match op.op.to_ascii_lowercase().as_str() {
+            "add" | "replace" => {
+                let value = operation_value(op)?;
+                match &op.path {
+                    Some(path) => {
+                        let path = filter::parse_path(path)?;
+                        // Filtres de valeur (emails[type eq "work"]…) : attributs dérivés du compte
+                        if path.filter.is_none() {
+                            self.set(&path.attribute, value)?;
+                        }
+                    }
+                    None => {
+                        let attributes = value.as_object()
+                            .ok_or_else(|| ScimError::invalid_value("Operation value must be an object when path is omitted"))?;
+                        for (key, value) in attributes {
+                            let path = filter::parse_path(key)?;
+                            self.set(&path.attribute, value)?;
+                        }
+                    }
+                }
+            }
+            "remove" => {
+                let path = op.path.as_deref()
+                    .ok_or_else(|| ScimError::new(StatusCode::BAD_REQUEST, Some("noTarget"), "path is required for remove operations"))?;
+                let path = filter::parse_path(path)?;
+                if path.filter.is_none() {
+                    self.remove(&path.attribute)?;
+                }
+            }
+            other => return Err(ScimError::invalid_syntax(format!("Unsupported patch operation {}", other))),
+        }
+        Ok(())
+    }
+}
+
+async fn find_user(
+    db: &DatabaseConnection,
+    entity_id: Uuid,
+    id: &str,
+) -> Result<(scim_user::Model, user::Model), ScimError> {
+    let id = parse_id(id, "User")?;
+    match ScimUserEntity::find_by_id(id)
+        .filter(ScimUserColumn::EntityId.eq(entity_id))
+        .find_also_related(UserEntity)
+        .one(db)
+        .await?
+    {
+        Some((scim_user, Some(user))) => Ok((scim_user, user)),
+        _ => Err(ScimError::not_found(format!("User {} not found", id))),
+    }
+}
+
+async fn render_user(
+    db: &DatabaseConnection,
+    config: &Config,
+    scim_user: &scim_user::Model,
+    user: &user::Model,
+) -> Result<Value, ScimError> {
+    let groups = provisioning::groups_of(db, scim_user.id).await?;
+    Ok(user_resource(&base_url(config), scim_user, user, &groups))
+}
+
+// Un compte partagé avec d'autres entités ne peut pas être renommé par l'une d'elles
+// SCIM ne crée ni ne renomme de compte hors des domaines vérifiés par l'entité :
+// un client ne peut pas réserver l'adresse d'un tiers
+async fn check_user_name_domain(db: &DatabaseConnection, entity_id: Uuid, user_name: &str) -> Result<(), ScimError> {
+    let verified = match sso::email_domain(user_name) {
+        Some(domain) => email_domains::is_verified(db, entity_id, &domain).await?,
+        None => false,
+    };
+    if !verified {
+        return Err(ScimError::invalid_value("userName must belong to an email domain verified by the entity"));
+    }
+    Ok(())
+}
+
+async fn check_user_name_change(
+    db: &DatabaseConnection,
+    entity_id: Uuid,
+    user: &user::Model,
+    user_name: &str,
+) -> Result<(), ScimError> {
+    let shared = UserEntityEntity::find()
+        .filter(UserEntityColumn::UserId.eq(user.id))
+        .filter(UserEntityColumn::EntityId.ne(entity_id))
+        .count(db)
+        .await?;
+    if shared > 0 {
+        return Err(ScimError::mutability("userName of an account shared with other entities cannot be changed"));
+    }
+    check_user_name_domain(db, entity_id, user_name).await?;
+
+    let taken = UserEntity::find()
+        .filter(UserColumn::Email.eq(user_name))
+        .filter(UserColumn::Id.ne(user.id))
+        .one(db)
+        .await?;
+    if taken.is_some() {
+        return Err(ScimError::uniqueness("userName is already in use"));
+    }
+    Ok(())
+}
+
+async fn apply_user_state(
+    db: &DatabaseConnection,
+    token: &scim_token::Model,
+    scim_user: scim_user::Model,
+    user: user::Model,
+    state: UserState,
+) -> Result<(scim_user::Model, user::Model), ScimError> {
+    let previous = UserState::current(&scim_user, &user);
+    if previous == state {
+        return Ok((scim_user, user));
+    }
+
+    let now = Utc::now();
+    let user = if previous.user_name != state.user_name
+        || previous.given_name != state.given_name
+        || previous.family_name != state.family_name
+    {
+        let email_changed = previous.user_name != state.user_name;
+        if email_changed {
+            check_user_name_change(db, token.entity_id, &user, &state.user_name).await?;
+        }
+        let mut user = user.into_active_model();
+        if email_changed {
+            // La nouvelle adresse devra être prouvée (lien de vérification ou SSO de l'entité)
+            user.email = Set(state.user_name.clone());
+            user.email_verified_at = Set(None);
+        }
+        user.first_name = Set(state.given_name.clone());
+        user.last_name = Set(state.family_name.clone());
+        user.updated_at = Set(now);
+        user.update(db).await?
+    } else {
+        user
+    };
+
+    let version = scim_user.version + 1;
+    let mut scim_user = scim_user.into_active_model();
+    scim_user.external_id = Set(state.external_id.clone());
+    scim_user.active = Set(state.active);
+    scim_user.version = Set(version);
+    scim_user.updated_at = Set(now);
+    let scim_user = scim_user.update(db).await?;
+
+    if previous.active != state.active {
+        if state.active {
+            provisioning::reactivate_user(db, user.id).await?;
+        } else {
+            provisioning::deactivate_user(db, user.id, token.entity_id).await?;
+        }
+    }
+    provisioning::sync_membership(db, token, &scim_user).await?;
+
+    // Relecture : la (dés)activation a pu modifier le compte
+    let user = UserEntity::find_by_id(user.id)
+        .one(db)
+        .await?
+        .ok_or_else(|| ScimError::not_found("User not found"))?;
+    Ok((scim_user, user))
+}
+
+pub async fn list_users(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    auth: ScimAuth,
+    query: web::Query<ListQuery>,
+) -> Result<HttpResponse, ScimError> {
+    let ScimAuth(token) = auth;
+    let base = base_url(&config);
+
+    let users = ScimUserEntity::find()
+        .filter(ScimUserColumn::EntityId.eq(token.entity_id))
+        .order_by_asc(ScimUserColumn::CreatedAt)
+        .find_also_related(UserEntity)
+        .all(db.get_ref())
+        .await?;
+    let groups = provisioning::groups_by_user(db.get_ref(), token.entity_id).await?;
+
+    // Filtrage en mémoire sur la représentation SCIM : le volume est celui d'une entité
+    let resources = users.iter()
+        .filter_map(|(scim_user, user)| {
+            let user = user.as_ref()?;
+            let user_groups = groups.get(&scim_user.id).map(Vec::as_slice).unwrap_or_default();
+            Some(user_resource(&base, scim_user, user, user_groups))
+        })
+        .collect();
+
+    list_result(&query, resources)
+}
+
+pub async fn get_user(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    auth: ScimAuth,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ScimError> {
+    let ScimAuth(token) = auth;
+    let (scim_user, user) = find_user(db.get_ref(), token.entity_id, &path).await?;
+    if let Some(response) = not_modified(&req, scim_user.version) {
+        return Ok(response);
+    }
+
+    let resource = render_user(db.get_ref(), &config, &scim_user, &user).await?;
+    Ok(resource_response(StatusCode::OK, resource, scim_user.version))
+}
+
+pub async fn create_user(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    auth: ScimAuth,
+    body: web::Bytes,
+) -> Result<HttpResponse, ScimError> {
+    let ScimAuth(token) = auth;
+    let state = UserState::from_request(parse_body(&body)?, true)?;
+    let now = Utc::now();
+
+    let existing = UserEntity::find()
+        .filter(UserColumn::Email.eq(&state.user_name))
+        .one(db.get_ref())
+        .await?;
+
+    let user = match existing {
+        Some(user) => {
+            let provisioned = ScimUserEntity::find()
+                .filter(ScimUserColumn::EntityId.eq(token.entity_id))
+                .filter(ScimUserColumn::UserId.eq(user.id))
+                .one(db.get_ref())
+                .await?;
+            if provisioned.is_some() {
+                return Err(ScimError::uniqueness("User already provisioned for this entity"));
+            }
+            // Les comptes de service ne relèvent pas de l'annuaire de l'entité
+            if api_tokens::is_service_account(db.get_ref(), user.id).await? {
+                return Err(ScimError::uniqueness("userName belongs to a service account"));
+            }
+            // Seul un compte déjà membre de l'entité peut être repris par son client SCIM
+            let member = UserEntityEntity::find()
+                .filter(UserEntityColumn::UserId.eq(user.id))
+                .filter(UserEntityColumn::EntityId.eq(token.entity_id))
+                .one(db.get_ref())
+                .await?;
+            if member.is_none() {
+                return Err(ScimError::uniqueness("An account with this userName already exists outside this entity"));
+            }
+            if state.given_name.is_some() || state.family_name.is_some() {
+                let mut user = user.into_active_model();
+                user.first_name = Set(state.given_name.clone());
+                user.last_name = Set(state.family_name.clone());
+                user.updated_at = Set(now);
+                user.update(db.get_ref()).await?
+            } else {
+                user
+            }
+        }
+        None => {
+            check_user_name_domain(db.get_ref(), token.entity_id, &state.user_name).await?;
+
+            // Mot de passe aléatoire inutilisable : connexion via le SSO de l'entité ou réinitialisation
+            let password_hash = hash(Uuid::new_v4().to_string(), DEFAULT_COST)
+                .map_err(ScimError::internal("Password hashing failed"))?;
+
+            let new_user = UserActiveModel {
+                id: Set(Uuid::new_v4()),
+                email: Set(state.user_name.clone()),
+                password_hash: Set(password_hash),
+                first_name: Set(state.given_name.clone()),
+                last_name: Set(state.family_name.clone()),
+                is_active: Set(state.active),
+                email_verified_at: Set(None),
+                sessions_valid_after: Set(None),
+                created_at: Set(now),
+                updated_at: Set(now),
+            };
+            UserEntity::insert(new_user)
+                .exec_with_returning(db.get_ref())
+                .await?
+        }
+    };
+
+    let scim_user = ScimUserActiveModel {
+        id: Set(Uuid::new_v4()),
+        entity_id: Set(token.entity_id),
+        user_id: Set(user.id),
+        external_id: Set(state.external_id.clone()),
+        active: Set(state.active),
+        version: Set(1),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+    let scim_user = ScimUserEntity::insert(scim_user)
+        .exec_with_returning(db.get_ref())
+        .await?;
+
+    if state.active {
+        provisioning::reactivate_user(db.get_ref(), user.id).await?;
+    } else {
+        provisioning::deactivate_user(db.get_ref(), user.id, token.entity_id).await?;
+    }
+    provisioning::sync_membership(db.get_ref(), &token, &scim_user).await?;
+
+    let user = UserEntity::find_by_id(user.id)
+        .one(db.get_ref())
+        .await?
+        .ok_or_else(|| ScimError::not_found("User not found"))?;
+    let resource = render_user(db.get_ref(), &config, &scim_user, &user).await?;
+    Ok(resource_response(StatusCode::CREATED, resource, scim_user.version))
+}
+
+pub async fn replace_user(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    auth: ScimAuth,
+    path: web::Path<String>,
+    body: web::Bytes,
+) -> Result<HttpResponse, ScimError> {
+    let ScimAuth(token) = auth;
+    let (scim_user, user) = find_user(db.get_ref(), token.entity_id, &path).await?;
+    check_if_match(&req, scim_user.version)?;
+
+    let state = UserState::from_request(parse_body(&body)?, scim_user.active)?;
+    let (scim_user, user) = apply_user_state(db.get_ref(), &token, scim_user, user, state).await?;
+
+    let resource = render_user(db.get_ref(), &config, &scim_user, &user).await?;
+    Ok(resource_response(StatusCode::OK, resource, scim_user.version))
+}
+
+pub async fn patch_user(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    auth: ScimAuth,
+    path: web::Path<String>,
+    body: web::Bytes,
+) -> Result<HttpResponse, ScimError> {
+    let ScimAuth(token) = auth;
+    let (scim_user, user) = find_user(db.get_ref(), token.entity_id, &path).await?;
+    check_if_match(&req, scim_user.version)?;
+
+    let patch = parse_patch(&body)?;
+    let mut state = UserState::current(&scim_user, &user);
+    for op in &patch.operations {
+        state.apply_patch(op)?;
+    }
+    let (scim_user, user) = apply_user_state(db.get_ref(), &token, scim_user, user, state).await?;
+
+    let resource = render_user(db.get_ref(), &config, &scim_user, &user).await?;
+    Ok(resource_response(StatusCode::OK, resource, scim_user.version))
+}
+
+pub async fn delete_user(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    auth: ScimAuth,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ScimError> {
+    let ScimAuth(token) = auth;
+    let (scim_user, user) = find_user(db.get_ref(), token.entity_id, &path).await?;
+    check_if_match(&req, scim_user.version)?;
+
+    provisioning::deactivate_user(db.get_ref(), user.id, token.entity_id).await?;
+    ScimUserEntity::delete_by_id(scim_user.id)
+        .exec(db.get_ref())
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// ---- Groups ----
+
+#[derive(Clone)]
+struct GroupState {
+    display_name: String,
+    external_id: Option<String>,
+    members: BTreeSet<Uuid>,
+}
+
+fn member_values(value: &Value) -> Result<Vec<String>, ScimError> {
+    let items = match value {
+        Value::Array(items) => items.as_slice(),
+        Value::Object(_) => std::slice::from_ref(value),
+        _ => return Err(ScimError::invalid_value("members must be an array of objects")),
+    };
+    items.iter()
+        .map(|item| {
+            filter::get_attribute(item, "value")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| ScimError::invalid_value("Each member must have a value"))
+        })
+        .collect()
+}
+
+// Les membres doivent être des utilisateurs SCIM de l'entité
+async fn resolve_members(
+    db: &DatabaseConnection,
+    entity_id: Uuid,
+    values: &[String],
+) -> Result<BTreeSet<Uuid>, ScimError> {
+    let ids = values.iter()
+        .map(|v| Uuid::parse_str(v).map_err(|_| ScimError::invalid_value(format!("Unknown member {}", v))))
+        .collect::<Result<BTreeSet<Uuid>, ScimError>>()?;
+
+    let found = ScimUserEntity::find()
+        .filter(ScimUserColumn::EntityId.eq(entity_id))
+        .filter(ScimUserColumn::Id.is_in(ids.iter().copied().collect::<Vec<_>>()))
+        .count(db)
+        .await?;
+    if found as usize != ids.len() {
+        return Err(ScimError::invalid_value("members must reference users of this entity"));
+    }
+    Ok(ids)
+}
+
+impl GroupState {
+    async fn apply_patch(
+        &mut self,
+        db: &DatabaseConnection,
+        entity_id: Uuid,
+        op: &PatchOperation,
+    ) -> Result<(), ScimError> {
+        let kind = op.op.to_ascii_lowercase();
+        let path = op.path.as_deref().map(filter::parse_path).transpose()?;
+        let attribute = path.as_ref().map(|p| p.attribute.join("."));
+
+        match (kind.as_str(), attribute.as_deref()) {
+            ("add" | "replace", None) => {
+                let attributes = operation_value(op)?.as_object()
+                    .ok_or_else(|| ScimError::invalid_value("Operation value must be an object when path is omitted"))?;
+                for (key, value) in attributes {
+                    match key.to_ascii_lowercase().as_str() {
+                        "displayname" => self.set_display_name(value)?,
+                        "externalid" => self.external_id = optional_string(value)?,
+                        "members" => {
+                            let members = resolve_members(db, entity_id, &member_values(value)?).await?;
+                            if kind == "add" {
+                                self.members.extend(members);
+                            } else {
+                                self.members = members;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            ("add" | "replace", Some("displayname")) => self.set_display_name(operation_value(op)?)?,
+            ("add" | "replace", Some("externalid")) => self.external_id = optional_string(operation_value(op)?)?,
+            ("add", Some("members")) => {
+                let members = resolve_members(db, entity_id, &member_values(operation_value(op)?)?).await?;
+                self.members.extend(members);
+            }
+            ("replace", Some("members")) => {
+                self.members = resolve_members(db, entity_id, &member_values(operation_value(op)?)?).await?;
+            }
+            ("remove", Some("members")) => {
+                let filter = path.and_then(|p| p.filter);
+                match (filter, &op.value) {
+                    (Some(filter), _) => {
+                        self.members.retain(|id| !filter.matches(&json!({ "value": id.to_string() })));
+                    }
+                    (None, Some(value)) => {
+                        let removed = member_values(value)?;
+                        self.members.retain(|id| !removed.contains(&id.to_string()));
+                    }
+                    (None, None) => self.members.clear(),
+                }
+            }
+            ("remove", Some("externalid")) => self.external_id = None,
+            ("remove", Some("displayname")) => return Err(ScimError::mutability("displayName is required")),
+            ("remove", None) => {
+                return Err(ScimError::new(StatusCode::BAD_REQUEST, Some("noTarget"), "path is required for remove operations"));
+            }
+            ("add" | "replace" | "remove", Some(_)) => {}
+            (other, _) => return Err(ScimError::invalid_syntax(format!("Unsupported patch operation {}", other))),
+        }
+        Ok(())
+    }
+
+    fn set_display_name(&mut self, value: &Value) -> Result<(), ScimError> {
+        self.display_name = optional_string(value)?
+            .ok_or_else(|| ScimError::invalid_value("displayName is required"))?;
+        Ok(())
+    }
+}
+
+async fn find_group(db: &DatabaseConnection, entity_id: Uuid, id: &str) -> Result<scim_group::Model, ScimError> {
+    let id = parse_id(id, "Group")?;
+    ScimGroupEntity::find_by_id(id)
+        .filter(ScimGroupColumn::EntityId.eq(entity_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| ScimError::not_found(format!("Group {} not found", id)))
+}
+
+async fn group_member_ids(db: &DatabaseConnection, group_id: Uuid) -> Result<BTreeSet<Uuid>, ScimError> {
+    Ok(ScimGroupMemberEntity::find()
+        .filter(ScimGroupMemberColumn::GroupId.eq(group_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|m| m.scim_user_id)
+        .collect())
+}
+
+async fn render_group(db: &DatabaseConnection, config: &Config, group: &scim_group::Model) -> Result<Value, ScimError> {
+    let member_ids: Vec<Uuid> = group_member_ids(db, group.id).await?.into_iter().collect();
+    let members: Vec<(Uuid, String)> = ScimUserEntity::find()
+        .filter(ScimUserColumn::Id.is_in(member_ids))
+        .find_also_related(UserEntity)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(scim_user, user)| user.map(|u| (scim_user.id, u.email)))
+        .collect();
+    Ok(group_resource(&base_url(config), group, &members))
+}
+
+async fn check_display_name(
+    db: &DatabaseConnection,
+    entity_id: Uuid,
+    group_id: Uuid,
+    display_name: &str,
+) -> Result<(), ScimError> {
+    let taken = ScimGroupEntity::find()
+        .filter(ScimGroupColumn::EntityId.eq(entity_id))
+        .filter(ScimGroupColumn::Id.ne(group_id))
+        .all(db)
+        .await?
+        .iter()
+        .any(|g| g.display_name.eq_ignore_ascii_case(display_name));
+    if taken {
+        return Err(ScimError::uniqueness("A group with this displayName already exists"));
+    }
+    Ok(())
+}
+
+// Les membres concernés changent de rôle et de version
+async fn resync_users(
+    db: &DatabaseConnection,
+    token: &scim_token::Model,
+    scim_user_ids: &BTreeSet<Uuid>,
+) -> Result<(), ScimError> {
+    let users = ScimUserEntity::find()
+        .filter(ScimUserColumn::Id.is_in(scim_user_ids.iter().copied().collect::<Vec<_>>()))
+        .all(db)
+        .await?;
+    for scim_user in users {
+        let scim_user = provisioning::touch_user(db, scim_user).await?;
+        provisioning::sync_membership(db, token, &scim_user).await?;
+    }
+    Ok(())
+}
+
+async fn apply_group_state(
+    db: &DatabaseConnection,
+    token: &scim_token::Model,
+    group: scim_group::Model,
+    state: GroupState,
+) -> Result<scim_group::Model, ScimError> {
+    let current = group_member_ids(db, group.id).await?;
+    let renamed = group.display_name != state.display_name;
+    if renamed {
+        check_display_name(db, token.entity_id, group.id, &state.display_name).await?;
+    }
+    if !renamed && group.external_id == state.external_id && current == state.members {
+        return Ok(group);
+    }
+
+    let added: Vec<Uuid> = state.members.difference(&current).copied().collect();
+    let removed: Vec<Uuid> = current.difference(&state.members).copied().collect();
+
+    if !removed.is_empty() {
+        ScimGroupMemberEntity::delete_many()
+            .filter(ScimGroupMemberColumn::GroupId.eq(group.id))
+            .filter(ScimGroupMemberColumn::ScimUserId.is_in(removed.clone()))
+            .exec(db)
+            .await?;
+    }
+    if !added.is_empty() {
+        ScimGroupMemberEntity::insert_many(added.iter().map(|id| ScimGroupMemberActiveModel {
+            group_id: Set(group.id),
+            scim_user_id: Set(*id),
+        }))
+        .exec(db)
+        .await?;
+    }
+
+    let version = group.version + 1;
+    let mut group = group.into_active_model();
+    group.display_name = Set(state.display_name);
+    group.external_id = Set(state.external_id);
+    group.version = Set(version);
+    group.updated_at = Set(Utc::now());
+    let group = group.update(db).await?;
+
+    // Renommer le groupe peut changer le rôle de tous ses membres
+    let affected: BTreeSet<Uuid> = if renamed {
+        current.union(&state.members).copied().collect()
+    } else {
+        added.into_iter().chain(removed).collect()
+    };
+    resync_users(db, token, &affected).await?;
+    Ok(group)
+}
+
+pub async fn list_groups(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    auth: ScimAuth,
+    query: web::Query<ListQuery>,
+) -> Result<HttpResponse, ScimError> {
+    let ScimAuth(token) = auth;
+    let base = base_url(&config);
+
+    let groups = ScimGroupEntity::find()
+        .filter(ScimGroupColumn::EntityId.eq(token.entity_id))
+        .order_by_asc(ScimGroupColumn::DisplayName)
+        .all(db.get_ref())
+        .await?;
+    let users: std::collections::HashMap<Uuid, String> = ScimUserEntity::find()
+        .filter(ScimUserColumn::EntityId.eq(token.entity_id))
+        .find_also_related(UserEntity)
+        .all(db.get_ref())
+        .await?
+        .into_iter()
+        .filter_map(|(scim_user, user)| user.map(|u| (scim_user.id, u.email)))
+        .collect();
+    let members = ScimGroupMemberEntity::find()
+        .filter(ScimGroupMemberColumn::GroupId.is_in(groups.iter().map(|g| g.id).collect::<Vec<_>>()))
+        .all(db.get_ref())
+        .await?;
+
+    let resources = groups.iter()
+        .map(|group| {
+            let group_members: Vec<(Uuid, String)> = members.iter()
+                .filter(|m| m.group_id == group.id)
+                .filter_map(|m| users.get(&m.scim_user_id).map(|email| (m.scim_user_id, email.clone())))
+                .collect();
+            group_resource(&base, group, &group_members)
+        })
+        .collect();
+
+    list_result(&query, resources)
+}
+
+pub async fn get_group(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    auth: ScimAuth,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ScimError> {
+    let ScimAuth(token) = auth;
+    let group = find_group(db.get_ref(), token.entity_id, &path).await?;
+    if let Some(response) = not_modified(&req, group.version) {
+        return Ok(response);
+    }
+
+    let resource = render_group(db.get_ref(), &config, &group).await?;
+    Ok(resource_response(StatusCode::OK, resource, group.version))
+}
+
+pub async fn create_group(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    auth: ScimAuth,
+    body: web::Bytes,
+) -> Result<HttpResponse, ScimError> {
+    let ScimAuth(token) = auth;
+    let body: GroupRequest = parse_body(&body)?;
+    let display_name = body.display_name.as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .ok_or_else(|| ScimError::invalid_value("displayName is required"))?
+        .to_string();
+
+    let id = Uuid::new_v4();
+    check_display_name(db.get_ref(), token.entity_id, id, &display_name).await?;
+    let member_values: Vec<String> = body.members.unwrap_or_default().into_iter().map(|m| m.value).collect();
+    let members = resolve_members(db.get_ref(), token.entity_id, &member_values).await?;
+
+    let now = Utc::now();
+    let group = ScimGroupActiveModel {
+        id: Set(id),
+        entity_id: Set(token.entity_id),
+        display_name: Set(display_name.clone()),
+        external_id: Set(body.external_id.map(|e| e.trim().to_string()).filter(|e| !e.is_empty())),
+        version: Set(1),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+    let group = ScimGroupEntity::insert(group)
+        .exec_with_returning(db.get_ref())
+        .await?;
+
+    if !members.is_empty() {
+        ScimGroupMemberEntity::insert_many(members.iter().map(|member| ScimGroupMemberActiveModel {
+            group_id: Set(group.id),
+            scim_user_id: Set(*member),
+        }))
+        .exec(db.get_ref())
+        .await?;
+        resync_users(db.get_ref(), &token, &members).await?;
+    }
+
+    let resource = render_group(db.get_ref(), &config, &group).await?;
+    Ok(resource_response(StatusCode::CREATED, resource, group.version))
+}
+
+pub async fn replace_group(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    auth: ScimAuth,
+    path: web::Path<String>,
+    body: web::Bytes,
+) -> Result<HttpResponse, ScimError> {
+    let ScimAuth(token) = auth;
+    let group = find_group(db.get_ref(), token.entity_id, &path).await?;
+    check_if_match(&req, group.version)?;
+
+    let body: GroupRequest = parse_body(&body)?;
+    let display_name = body.display_name.as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .ok_or_else(|| ScimError::invalid_value("displayName is required"))?
+        .to_string();
+    let member_values: Vec<String> = body.members.unwrap_or_default().into_iter().map(|m| m.value).collect();
+    let state = GroupState {
+        display_name,
+        external_id: body.external_id.map(|e| e.trim().to_string()).filter(|e| !e.is_empty()),
+        members: resolve_members(db.get_ref(), token.entity_id, &member_values).await?,
+    };
+
+    let group = apply_group_state(db.get_ref(), &token, group, state).await?;
+    let resource = render_group(db.get_ref(), &config, &group).await?;
+    Ok(resource_response(StatusCode::OK, resource, group.version))
+}
+
+pub async fn patch_group(
+    db: web::Data<DatabaseConnection>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    auth: ScimAuth,
+    path: web::Path<String>,
+    body: web::Bytes,
+) -> Result<HttpResponse, ScimError> {
+    let ScimAuth(token) = auth;
+    let group = find_group(db.get_ref(), token.entity_id, &path).await?;
+    check_if_match(&req, group.version)?;
+
+    let patch = parse_patch(&body)?;
+    let mut state = GroupState {
+        display_name: group.display_name.clone(),
+        external_id: group.external_id.clone(),
+        members: group_member_ids(db.get_ref(), group.id).await?,
+    };
+    for op in &patch.operations {
+        state.apply_patch(db.get_ref(), token.entity_id, op).await?;
+    }
+
+    let group = apply_group_state(db.get_ref(), &token, group, state).await?;
+    let resource = render_group(db.get_ref(), &config, &group).await?;
+    Ok(resource_response(StatusCode::OK, resource, group.version))
+}
+
+pub async fn delete_group(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    auth: ScimAuth,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ScimError> {
+    let ScimAuth(token) = auth;
+    let group = find_group(db.get_ref(), token.entity_id, &path).await?;
+    check_if_match(&req, group.version)?;
+
+    let members = group_member_ids(db.get_ref(), group.id).await?;
+    ScimGroupEntity::delete_by_id(group.id)
+        .exec(db.get_ref())
+        .await?;
+    resync_users(db.get_ref(), &token, &members).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
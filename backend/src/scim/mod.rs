@@ -0,0 +1,11 @@
+//! Provisioning SCIM 2.0 (RFC 7643 / RFC 7644) des comptes d'une entité.
+//!
+//! Les ressources `/Users` et `/Groups` sont propres à l'entité du jeton
+//! bearer présenté ; les groupes pilotent le rôle dans `user_entities`.
+
+pub mod auth;
+pub mod error;
+pub mod filter;
+pub mod handlers;
+pub mod models;
+pub mod provisioning;
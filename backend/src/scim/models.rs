@@ -0,0 +1,281 @@
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+use crate::config::Config;
+use crate::entities_orm::{scim_group, scim_user, user};
+
+pub const CONTENT_TYPE: &str = "application/scim+json";
+
+pub const SCHEMA_USER: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+pub const SCHEMA_GROUP: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+pub const SCHEMA_LIST_RESPONSE: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+pub const SCHEMA_PATCH_OP: &str = "urn:ietf:params:scim:api:messages:2.0:PatchOp";
+pub const SCHEMA_ERROR: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+pub const SCHEMA_SERVICE_PROVIDER_CONFIG: &str = "urn:ietf:params:scim:schemas:core:2.0:ServiceProviderConfig";
+pub const SCHEMA_RESOURCE_TYPE: &str = "urn:ietf:params:scim:schemas:core:2.0:ResourceType";
+pub const SCHEMA_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Schema";
+
+pub const DEFAULT_PAGE_SIZE: u64 = 100;
+pub const MAX_PAGE_SIZE: u64 = 200;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListQuery {
+    pub filter: Option<String>,
+    pub start_index: Option<i64>,
+    pub count: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NameRequest {
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+}
+
+// userName est l'adresse email du compte
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserRequest {
+    pub user_name: Option<String>,
+    pub external_id: Option<String>,
+    pub name: Option<NameRequest>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MemberRef {
+    pub value: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupRequest {
+    pub display_name: Option<String>,
+    pub external_id: Option<String>,
+    pub members: Option<Vec<MemberRef>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PatchRequest {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(rename = "Operations", alias = "operations")]
+    pub operations: Vec<PatchOperation>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PatchOperation {
+    pub op: String,
+    pub path: Option<String>,
+    pub value: Option<Value>,
+}
+
+pub fn base_url(config: &Config) -> String {
+    format!("{}/api/scim/v2", config.api_base_url.trim_end_matches('/'))
+}
+
+pub fn etag(version: i32) -> String {
+    format!("W/\"{}\"", version)
+}
+
+// Les attributs sans valeur sont omis plutôt que renvoyés à null (RFC 7643 §2.5)
+fn strip_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k, strip_nulls(v)))
+                .collect::<Map<String, Value>>(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(strip_nulls).collect()),
+        other => other,
+    }
+}
+
+fn display_name(user: &user::Model) -> Option<String> {
+    let parts: Vec<&str> = [user.first_name.as_deref(), user.last_name.as_deref()]
+        .into_iter()
+        .flatten()
+        .filter(|p| !p.is_empty())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+pub fn user_resource(
+    base: &str,
+    scim_user: &scim_user::Model,
+    user: &user::Model,
+    groups: &[(Uuid, String)],
+) -> Value {
+    let location = format!("{}/Users/{}", base, scim_user.id);
+    strip_nulls(json!({
+        "schemas": [SCHEMA_USER],
+        "id": scim_user.id,
+        "externalId": scim_user.external_id,
+        "userName": user.email,
+        "name": {
+            "givenName": user.first_name,
+            "familyName": user.last_name,
+            "formatted": display_name(user),
+        },
+        "displayName": display_name(user),
+        "emails": [{ "value": user.email, "type": "work", "primary": true }],
+        "active": scim_user.active,
+        "groups": groups.iter().map(|(id, name)| json!({
+            "value": id,
+            "display": name,
+            "$ref": format!("{}/Groups/{}", base, id),
+        })).collect::<Vec<_>>(),
+        "meta": {
+            "resourceType": "User",
+            "created": scim_user.created_at.to_rfc3339(),
+            "lastModified": scim_user.updated_at.to_rfc3339(),
+            "location": location,
+            "version": etag(scim_user.version),
+        },
+    }))
+}
+
+pub fn group_resource(base: &str, group: &scim_group::Model, members: &[(Uuid, String)]) -> Value {
+    let location = format!("{}/Groups/{}", base, group.id);
+    strip_nulls(json!({
+        "schemas": [SCHEMA_GROUP],
+        "id": group.id,
+        "externalId": group.external_id,
+        "displayName": group.display_name,
+        "members": members.iter().map(|(id, user_name)| json!({
+            "value": id,
+            "display": user_name,
+            "type": "User",
+            "$ref": format!("{}/Users/{}", base, id),
+        })).collect::<Vec<_>>(),
+        "meta": {
+            "resourceType": "Group",
+            "created": group.created_at.to_rfc3339(),
+            "lastModified": group.updated_at.to_rfc3339(),
+            "location": location,
+            "version": etag(group.version),
+        },
+    }))
+}
+
+pub fn list_response(total: usize, start_index: u64, resources: Vec<Value>) -> Value {
+    json!({
+        "schemas": [SCHEMA_LIST_RESPONSE],
+        "totalResults": total,
+        "startIndex": start_index,
+        "itemsPerPage": resources.len(),
+        "Resources": resources,
+    })
+}
+
+pub fn service_provider_config(base: &str) -> Value {
+    json!({
+        "schemas": [SCHEMA_SERVICE_PROVIDER_CONFIG],
+        "patch": { "supported": true },
+        "bulk": { "supported": false, "maxOperations": 0, "maxPayloadSize": 0 },
+        "filter": { "supported": true, "maxResults": MAX_PAGE_SIZE },
+        "changePassword": { "supported": false },
+        "sort": { "supported": false },
+        "etag": { "supported": true },
+        "authenticationSchemes": [{
+            "type": "oauthbearertoken",
+            "name": "Bearer token",
+            "description": "Jeton SCIM de l'entité, transmis dans l'en-tête Authorization",
+            "primary": true,
+        }],
+        "meta": {
+            "resourceType": "ServiceProviderConfig",
+            "location": format!("{}/ServiceProviderConfig", base),
+        },
+    })
+}
+
+pub fn resource_types(base: &str) -> Vec<Value> {
+    [("User", "/Users", SCHEMA_USER), ("Group", "/Groups", SCHEMA_GROUP)]
+        .into_iter()
+        .map(|(name, endpoint, schema)| json!({
+            "schemas": [SCHEMA_RESOURCE_TYPE],
+            "id": name,
+            "name": name,
+            "endpoint": endpoint,
+            "schema": schema,
+            "meta": {
+                "resourceType": "ResourceType",
+                "location": format!("{}/ResourceTypes/{}", base, name),
+            },
+        }))
+        .collect()
+}
+
+fn attribute(name: &str, kind: &str, multi_valued: bool, required: bool, mutability: &str, uniqueness: &str) -> Value {
+    json!({
+        "name": name,
+        "type": kind,
+        "multiValued": multi_valued,
+        "required": required,
+        "caseExact": false,
+        "mutability": mutability,
+        "returned": "default",
+        "uniqueness": uniqueness,
+    })
+}
+
+fn sub_attributes(mut attr: Value, subs: Vec<Value>) -> Value {
+    attr["subAttributes"] = Value::Array(subs);
+    attr
+}
+
+/// Schémas réellement pris en charge : seuls ces attributs sont conservés.
+pub fn schemas(base: &str) -> Vec<Value> {
+    let user = json!({
+        "schemas": [SCHEMA_SCHEMA],
+        "id": SCHEMA_USER,
+        "name": "User",
+        "description": "Compte utilisateur rattaché à l'entité",
+        "attributes": [
+            attribute("userName", "string", false, true, "readWrite", "server"),
+            attribute("externalId", "string", false, false, "readWrite", "none"),
+            sub_attributes(attribute("name", "complex", false, false, "readWrite", "none"), vec![
+                attribute("givenName", "string", false, false, "readWrite", "none"),
+                attribute("familyName", "string", false, false, "readWrite", "none"),
+                attribute("formatted", "string", false, false, "readOnly", "none"),
+            ]),
+            attribute("displayName", "string", false, false, "readOnly", "none"),
+            sub_attributes(attribute("emails", "complex", true, false, "readOnly", "none"), vec![
+                attribute("value", "string", false, false, "readOnly", "none"),
+                attribute("type", "string", false, false, "readOnly", "none"),
+                attribute("primary", "boolean", false, false, "readOnly", "none"),
+            ]),
+            attribute("active", "boolean", false, false, "readWrite", "none"),
+            sub_attributes(attribute("groups", "complex", true, false, "readOnly", "none"), vec![
+                attribute("value", "string", false, false, "readOnly", "none"),
+                attribute("display", "string", false, false, "readOnly", "none"),
+            ]),
+        ],
+        "meta": {
+            "resourceType": "Schema",
+            "location": format!("{}/Schemas/{}", base, SCHEMA_USER),
+        },
+    });
+    let group = json!({
+        "schemas": [SCHEMA_SCHEMA],
+        "id": SCHEMA_GROUP,
+        "name": "Group",
+        "description": "Groupe de l'entité, associé à un rôle",
+        "attributes": [
+            attribute("displayName", "string", false, true, "readWrite", "server"),
+            attribute("externalId", "string", false, false, "readWrite", "none"),
+            sub_attributes(attribute("members", "complex", true, false, "readWrite", "none"), vec![
+                attribute("value", "string", false, false, "immutable", "none"),
+                attribute("display", "string", false, false, "readOnly", "none"),
+            ]),
+        ],
+        "meta": {
+            "resourceType": "Schema",
+            "location": format!("{}/Schemas/{}", base, SCHEMA_GROUP),
+        },
+    });
+    vec![user, group]
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use chrono::Utc;
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait, ColumnTrait, QueryFilter, QueryOrder, PaginatorTrait, Set, IntoActiveModel, ActiveModelTrait};
+use uuid::Uuid;
+use crate::auth::api_tokens;
+use crate::entities::identity_providers::models::RoleMappingRule;
+use crate::entities_orm::{scim_token, scim_user};
+use crate::entities_orm::scim_group::{Entity as ScimGroupEntity, Column as ScimGroupColumn};
+use crate::entities_orm::scim_group_member::{Entity as ScimGroupMemberEntity, Column as ScimGroupMemberColumn};
+use crate::entities_orm::user::Entity as UserEntity;
+use crate::entities_orm::user_entity::{Entity as UserEntityEntity, Column as UserEntityColumn, ActiveModel as UserEntityActiveModel};
+
+pub fn role_mapping(token: &scim_token::Model) -> Vec<RoleMappingRule> {
+    serde_json::from_value(token.role_mapping.clone()).unwrap_or_default()
+}
+
+/// Groupes (id, displayName) de chaque utilisateur SCIM de l'entité.
+pub async fn groups_by_user(
+    db: &DatabaseConnection,
+    entity_id: Uuid,
+) -> Result<HashMap<Uuid, Vec<(Uuid, String)>>, DbErr> {
+    let groups: HashMap<Uuid, String> = ScimGroupEntity::find()
+        .filter(ScimGroupColumn::EntityId.eq(entity_id))
+        .order_by_asc(ScimGroupColumn::DisplayName)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|g| (g.id, g.display_name))
+        .collect();
+
+    let members = ScimGroupMemberEntity::find()
+        .filter(ScimGroupMemberColumn::GroupId.is_in(groups.keys().copied().collect::<Vec<_>>()))
+        .all(db)
+        .await?;
+
+    let mut by_user: HashMap<Uuid, Vec<(Uuid, String)>> = HashMap::new();
+    for member in members {
+        if let Some(name) = groups.get(&member.group_id) {
+            by_user.entry(member.scim_user_id).or_default().push((member.group_id, name.clone()));
+        }
+    }
+    for groups in by_user.values_mut() {
+        groups.sort_by(|a, b| a.1.cmp(&b.1));
+    }
+    Ok(by_user)
+}
+
+pub async fn groups_of(db: &DatabaseConnection, scim_user_id: Uuid) -> Result<Vec<(Uuid, String)>, DbErr> {
+    let group_ids: Vec<Uuid> = ScimGroupMemberEntity::find()
+        .filter(ScimGroupMemberColumn::ScimUserId.eq(scim_user_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|m| m.group_id)
+        .collect();
+
+    Ok(ScimGroupEntity::find()
+        .filter(ScimGroupColumn::Id.is_in(group_ids))
+        .order_by_asc(ScimGroupColumn::DisplayName)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|g| (g.id, g.display_name))
+        .collect())
+}
+
+// La première règle dont le groupe est présent l'emporte
+fn mapped_role(rules: &[RoleMappingRule], groups: &[(Uuid, String)]) -> Option<String> {
+    rules.iter()
+        .find(|rule| groups.iter().any(|(_, name)| name.eq_ignore_ascii_case(&rule.group)))
+        .map(|rule| rule.role.clone())
+}
+
+/// Aligne `user_entities` sur l'état SCIM : un utilisateur actif est membre
+/// de l'entité avec le rôle déduit de ses groupes, un utilisateur inactif ne
+/// l'est plus. Sans groupe reconnu, le rôle par défaut ne remplace qu'un rôle
+/// accordé par la correspondance ; un rôle attribué à la main est conservé.
+pub async fn sync_membership(
+    db: &DatabaseConnection,
+    token: &scim_token::Model,
+    scim_user: &scim_user::Model,
+) -> Result<(), DbErr> {
+    let membership = UserEntityEntity::find()
+        .filter(UserEntityColumn::UserId.eq(scim_user.user_id))
+        .filter(UserEntityColumn::EntityId.eq(scim_user.entity_id))
+        .one(db)
+        .await?;
+
+    if !scim_user.active {
+        if let Some(membership) = membership {
+            UserEntityEntity::delete_by_id(membership.id).exec(db).await?;
+        }
+        return Ok(());
+    }
+
+    let rules = role_mapping(token);
+    let mapped = mapped_role(&rules, &groups_of(db, scim_user.id).await?);
+    match membership {
+        Some(membership) => {
+            let managed = rules.iter().any(|rule| rule.role == membership.role);
+            let role = match mapped {
+                Some(role) => role,
+                None if managed => token.default_role.clone(),
+                None => membership.role.clone(),
+            };
+            if membership.role != role {
+                let mut membership = membership.into_active_model();
+                membership.role = Set(role);
+                membership.update(db).await?;
+            }
+        }
+        None => {
+            let role = mapped.unwrap_or_else(|| token.default_role.clone());
+            let membership = UserEntityActiveModel {
+                id: Set(Uuid::new_v4()),
+                user_id: Set(scim_user.user_id),
+                entity_id: Set(scim_user.entity_id),
+                role: Set(role),
+                created_at: Set(Utc::now()),
+            };
+            UserEntityEntity::insert(membership).exec(db).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Désactivation d'un utilisateur SCIM : retrait de l'entité, fin de toutes
+/// ses sessions et suppression de ses jetons d'API limités à l'entité (tous
+/// ses jetons si le compte est désactivé). Le compte n'est désactivé
+/// (`users.is_active`) que s'il n'appartient à aucune autre entité, qu'un
+/// client SCIM ne peut pas bloquer.
+pub async fn deactivate_user(db: &DatabaseConnection, user_id: Uuid, entity_id: Uuid) -> Result<(), DbErr> {
+    UserEntityEntity::delete_many()
+        .filter(UserEntityColumn::UserId.eq(user_id))
+        .filter(UserEntityColumn::EntityId.eq(entity_id))
+        .exec(db)
+        .await?;
+
+    let other_memberships = UserEntityEntity::find()
+        .filter(UserEntityColumn::UserId.eq(user_id))
+        .count(db)
+        .await?;
+
+    if let Some(user) = UserEntity::find_by_id(user_id).one(db).await? {
+        let now = Utc::now();
+        let mut user = user.into_active_model();
+        if other_memberships == 0 {
+            user.is_active = Set(false);
+        }
+        user.sessions_valid_after = Set(Some(now));
+        user.updated_at = Set(now);
+        user.update(db).await?;
+    }
+    api_tokens::revoke(db, user_id, (other_memberships > 0).then_some(entity_id)).await?;
+    Ok(())
+}
+
+pub async fn reactivate_user(db: &DatabaseConnection, user_id: Uuid) -> Result<(), DbErr> {
+    if let Some(user) = UserEntity::find_by_id(user_id).one(db).await?
+        && !user.is_active
+    {
+        let mut user = user.into_active_model();
+        user.is_active = Set(true);
+        user.updated_at = Set(Utc::now());
+        user.update(db).await?;
+    }
+    Ok(())
+}
+
+/// Nouvelle version (ETag) d'un utilisateur dont l'appartenance aux groupes a changé.
+pub async fn touch_user(db: &DatabaseConnection, scim_user: scim_user::Model) -> Result<scim_user::Model, DbErr> {
+    let version = scim_user.version + 1;
+    let mut scim_user = scim_user.into_active_model();
+    scim_user.version = Set(version);
+    scim_user.updated_at = Set(Utc::now());
+    scim_user.update(db).await
+}
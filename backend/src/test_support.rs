@@ -0,0 +1,433 @@
+//! Outils communs aux tests : base Postgres, configuration, boîte aux
+//! lettres du mailer `file` et fournisseur OpenID Connect local.
+//!
+//! Les tests qui s'appuient sur Postgres visent la base migrée désignée par
+//! `TEST_DATABASE_URL`. Ils sont marqués `#[ignore = "needs TEST_DATABASE_URL"]`
+//! et se lancent avec `cargo test -- --include-ignored` : un test lancé sans
+//! la variable échoue au lieu de passer sans rien vérifier. Chaque test crée
+//! ses propres comptes, avec une adresse aléatoire, et les supprime.
+
+use std::collections::HashMap;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use actix_web::{web, App, HttpResponse, HttpServer};
+use actix_web::dev::ServerHandle;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use bcrypt::hash;
+use chrono::Utc;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use openssl::rsa::Rsa;
+use sea_orm::{DatabaseConnection, EntityTrait, Set};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use totp_rs::{Algorithm as TotpAlgorithm, Secret, TOTP};
+use uuid::Uuid;
+use crate::auth::mfa;
+use crate::auth::password::PasswordPolicy;
+use crate::auth::throttle::ThrottlePolicy;
+use crate::config::Config;
+use crate::database::get_connection;
+use crate::entities_orm::entity::{Entity as EntityEntity, ActiveModel as EntityActiveModel, Model as EntityModel};
+use crate::entities_orm::user_entity::{Entity as UserEntityEntity, ActiveModel as UserEntityActiveModel};
+use crate::entities_orm::user::{Entity as UserEntity, ActiveModel as UserActiveModel, Model as UserModel};
+use crate::entities_orm::user_totp::{Entity as UserTotpEntity, ActiveModel as UserTotpActiveModel};
+use crate::mailer::FileMailer;
+
+/// Mot de passe des comptes de test, conforme à la politique par défaut.
+pub const TEST_PASSWORD: &str = "Initial-Password-1";
+
+// Coût bcrypt minimal : les comptes de test n'ont pas à résister à une attaque
+const TEST_BCRYPT_COST: u32 = 4;
+
+pub async fn database() -> DatabaseConnection {
+    let url = std::env::var("TEST_DATABASE_URL")
+        .expect("TEST_DATABASE_URL must point to a migrated database to run this test");
+    get_connection(&url).await.expect("Failed to connect to TEST_DATABASE_URL")
+}
+
+/// Configuration indépendante de l'environnement du poste.
+pub fn config() -> Config {
+    let mut config = Config::from_env();
+    config.app_env = "development".to_string();
+    config.jwt_secret = "test-jwt-secret-with-at-least-32-characters".to_string();
+    config.jwt_expiration = 900;
+    config.jwt_keys_dir = None;
+    config.jwt_signing_key_id = None;
+    config.jwt_issuer = "http://localhost:8080".to_string();
+    config.jwt_audience = "hypercyber-api".to_string();
+    config.password_policy = PasswordPolicy::default();
+    config.login_throttle = ThrottlePolicy {
+        account_max_failures: 5,
+        ip_max_failures: 20,
+        backoff_base_seconds: 0,
+        backoff_max_seconds: 0,
+        lockout_seconds: 900,
+    };
+    config.behind_proxy = false;
+    config.require_email_verification = true;
+    config.outbound_allow_insecure = true;
+    config.frontend_url = "http://localhost:3000".to_string();
+    config.oidc_trust_mfa = false;
+    config.oidc_mfa_acr_values = Vec::new();
+    config.api_base_url = "http://localhost:8080".to_string();
+    config.mail_backend = "file".to_string();
+    config.mfa_encryption_key = "test-mfa-encryption-key-of-32-characters".to_string();
+    config.consent_subject_key = "test-consent-subject-key-of-32-characters".to_string();
+    config
+}
+
+pub async fn create_user(db: &DatabaseConnection, verified: bool) -> UserModel {
+    let now = Utc::now();
+    let user = UserActiveModel {
+        id: Set(Uuid::new_v4()),
+        email: Set(format!("test-{}@example.com", Uuid::new_v4().simple())),
+        password_hash: Set(hash(TEST_PASSWORD, TEST_BCRYPT_COST).expect("Password hashing failed")),
+        first_name: Set(None),
+        last_name: Set(None),
+        is_active: Set(true),
+        email_verified_at: Set(verified.then_some(now)),
+        sessions_valid_after: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+    UserEntity::insert(user)
+        .exec_with_returning(db)
+        .await
+        .expect("Failed to create test user")
+}
+
+/// Active un second facteur TOTP confirmé et renvoie son secret base32.
+pub async fn enroll_totp(db: &DatabaseConnection, config: &Config, user_id: Uuid) -> String {
+    let (secret, _) = mfa::new_totp_secret(&config.mfa_issuer, "test").unwrap();
+    let now = Utc::now();
+    let totp = UserTotpActiveModel {
+        user_id: Set(user_id),
+        secret: Set(mfa::encrypt_secret(config, user_id, &secret).unwrap()),
+        confirmed_at: Set(Some(now)),
+        last_used_step: Set(None),
+        created_at: Set(now),
+    };
+    UserTotpEntity::insert(totp).exec(db).await.expect("Failed to enroll TOTP");
+    secret
+}
+
+/// Code TOTP de la période courante décalée de `steps` périodes (un pas déjà
+/// utilisé est refusé : deux vérifications successives prennent `0` puis `1`).
+pub fn totp_code(secret: &str, steps: i64) -> String {
+    let secret = Secret::Encoded(secret.to_string()).to_bytes().unwrap();
+    let totp = TOTP::new(TotpAlgorithm::SHA1, 6, 1, 30, secret, None, "test".to_string()).unwrap();
+    totp.generate((Utc::now().timestamp() + steps * 30) as u64)
+}
+
+// Jetons, échecs de connexion et sessions suivent par cascade
+pub async fn delete_user(db: &DatabaseConnection, user_id: Uuid) {
+    UserEntity::delete_by_id(user_id)
+        .exec(db)
+        .await
+        .expect("Failed to delete test user");
+}
+
+/// Entité de test, filiale de `parent_id` si fourni.
+pub async fn create_entity(db: &DatabaseConnection, parent_id: Option<Uuid>) -> EntityModel {
+    let now = Utc::now();
+    let entity = EntityActiveModel {
+        id: Set(Uuid::new_v4()),
+        name: Set(format!("Test {}", Uuid::new_v4().simple())),
+        description: Set(None),
+        require_mfa: Set(false),
+        created_at: Set(now),
+        updated_at: Set(now),
+        parent_id: Set(parent_id),
+        archived_at: Set(None),
+        deletion_scheduled_at: Set(None),
+        deletion_requested_by: Set(None),
+    };
+    EntityEntity::insert(entity)
+        .exec_with_returning(db)
+        .await
+        .expect("Failed to create test entity")
+}
+
+pub async fn add_member(db: &DatabaseConnection, user_id: Uuid, entity_id: Uuid, role: &str) {
+    let membership = UserEntityActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        entity_id: Set(entity_id),
+        role: Set(role.to_string()),
+        created_at: Set(Utc::now()),
+    };
+    UserEntityEntity::insert(membership).exec(db).await.expect("Failed to add test membership");
+}
+
+// Les filiales d'abord : le rattachement au parent interdit sa suppression
+pub async fn delete_entities(db: &DatabaseConnection, entity_ids: &[Uuid]) {
+    for entity_id in entity_ids.iter().rev() {
+        EntityEntity::delete_by_id(*entity_id)
+            .exec(db)
+            .await
+            .expect("Failed to delete test entity");
+    }
+}
+
+/// Répertoire temporaire du mailer `file`, supprimé en fin de test.
+pub struct Mailbox {
+    directory: PathBuf,
+}
+
+impl Default for Mailbox {
+    fn default() -> Self {
+        Self {
+            directory: std::env::temp_dir().join(format!("hypercyber-mail-{}", Uuid::new_v4())),
+        }
+    }
+}
+
+impl Mailbox {
+    pub fn mailer(&self, config: &Config) -> FileMailer {
+        FileMailer::new(config.mail_from.clone(), self.directory.to_string_lossy().into_owned())
+    }
+
+    /// Messages `.eml` adressés à `to`.
+    pub fn messages(&self, to: &str) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.directory) else {
+            return Vec::new();
+        };
+        let header = format!("\r\nTo: {}\r\n", to);
+        entries
+            .filter_map(|entry| std::fs::read_to_string(entry.ok()?.path()).ok())
+            .filter(|message| message.contains(&header))
+            .collect()
+    }
+
+    /// Jeton du seul lien `<chemin>?token=` envoyé à `to`.
+    pub fn token(&self, to: &str, path: &str) -> String {
+        let marker = format!("/{}?token=", path);
+        let tokens: Vec<String> = self.messages(to)
+            .iter()
+            .filter_map(|message| {
+                let start = message.find(&marker)? + marker.len();
+                let token = message[start..].split_whitespace().next()?;
+                Some(urlencoding::decode(token).ok()?.into_owned())
+            })
+            .collect();
+        assert_eq!(tokens.len(), 1, "expected exactly one {} link for {}", path, to);
+        tokens.into_iter().next().unwrap()
+    }
+}
+
+impl Drop for Mailbox {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.directory).ok();
+    }
+}
+
+/// Fournisseur OpenID Connect local (HTTP, port éphémère) : découverte, JWKS
+/// d'une clé RSA générée, autorisation et échange du code avec contrôle PKCE
+/// S256. Le compte authentifié est désigné par le paramètre `login_hint`.
+pub struct MockIssuer {
+    pub issuer: String,
+    state: web::Data<MockIssuerState>,
+    server: ServerHandle,
+}
+
+struct MockIssuerState {
+    issuer: String,
+    client_id: String,
+    key: EncodingKey,
+    jwks: Value,
+    // Codes d'autorisation en attente d'échange
+    codes: Mutex<HashMap<String, PendingCode>>,
+    // Claims d'authentification (amr, acr) ajoutés aux prochains id_tokens
+    authentication: Mutex<Value>,
+}
+
+struct PendingCode {
+    redirect_uri: String,
+    code_challenge: String,
+    claims: Value,
+}
+
+pub const MOCK_ISSUER_KEY_ID: &str = "mock-issuer-key";
+
+impl MockIssuer {
+    pub async fn start(client_id: &str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind the mock issuer");
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+
+        let rsa = Rsa::generate(2048).unwrap();
+        let jwks = json!({ "keys": [{
+            "kty": "RSA",
+            "use": "sig",
+            "alg": "RS256",
+            "kid": MOCK_ISSUER_KEY_ID,
+            "n": URL_SAFE_NO_PAD.encode(rsa.n().to_vec()),
+            "e": URL_SAFE_NO_PAD.encode(rsa.e().to_vec()),
+        }]});
+        let state = web::Data::new(MockIssuerState {
+            issuer: issuer.clone(),
+            client_id: client_id.to_string(),
+            key: EncodingKey::from_rsa_pem(&rsa.private_key_to_pem().unwrap()).unwrap(),
+            jwks,
+            codes: Mutex::new(HashMap::new()),
+            authentication: Mutex::new(json!({})),
+        });
+
+        let app_state = state.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(app_state.clone())
+                .route("/.well-known/openid-configuration", web::get().to(mock_discovery))
+                .route("/jwks", web::get().to(mock_jwks))
+                .route("/authorize", web::get().to(mock_authorize))
+                .route("/token", web::post().to(mock_token))
+        })
+        .workers(1)
+        .listen(listener)
+        .expect("Failed to start the mock issuer")
+        .run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        Self {
+            issuer,
+            state,
+            server: handle,
+        }
+    }
+
+    /// Suit l'URL d'autorisation en tant que `email` et renvoie la redirection
+    /// vers le client (`redirect_uri?code=...&state=...`).
+    pub async fn authorize(&self, authorization_url: &str, email: &str) -> String {
+        let mut url = reqwest::Url::parse(authorization_url).unwrap();
+        url.query_pairs_mut().append_pair("login_hint", email);
+        let browser = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let response = browser.get(url).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FOUND, "authorization refused by the mock issuer");
+        response.headers()["location"].to_str().unwrap().to_string()
+    }
+
+    /// Authentification déclarée dans les prochains id_tokens (`amr`, `acr`).
+    pub fn authenticate_with(&self, authentication: Value) {
+        *self.state.authentication.lock().unwrap() = authentication;
+    }
+
+    /// Claims d'un id_token valide pour `email`, à modifier par les tests.
+    pub fn claims(&self, email: &str, nonce: &str) -> Value {
+        self.state.claims(email, nonce)
+    }
+
+    /// Signe des claims avec la clé publiée dans le JWKS.
+    pub fn id_token(&self, claims: &Value) -> String {
+        self.state.id_token(claims)
+    }
+}
+
+impl Drop for MockIssuer {
+    fn drop(&mut self) {
+        // L'arrêt est demandé immédiatement ; sa fin n'a pas à être attendue
+        drop(self.server.stop(false));
+    }
+}
+
+impl MockIssuerState {
+    fn claims(&self, email: &str, nonce: &str) -> Value {
+        let now = Utc::now().timestamp();
+        let mut claims = json!({
+            "iss": self.issuer,
+            "sub": format!("mock-{}", email),
+            "aud": self.client_id,
+            "azp": self.client_id,
+            "iat": now,
+            "exp": now + 300,
+            "nonce": nonce,
+            "email": email,
+            "email_verified": true,
+        });
+        if let Value::Object(authentication) = &*self.authentication.lock().unwrap() {
+            claims.as_object_mut().unwrap().extend(authentication.clone());
+        }
+        claims
+    }
+
+    fn id_token(&self, claims: &Value) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(MOCK_ISSUER_KEY_ID.to_string());
+        encode(&header, claims, &self.key).unwrap()
+    }
+}
+
+async fn mock_discovery(state: web::Data<MockIssuerState>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "issuer": state.issuer,
+        "authorization_endpoint": format!("{}/authorize", state.issuer),
+        "token_endpoint": format!("{}/token", state.issuer),
+        "jwks_uri": format!("{}/jwks", state.issuer),
+    }))
+}
+
+async fn mock_jwks(state: web::Data<MockIssuerState>) -> HttpResponse {
+    HttpResponse::Ok().json(&state.jwks)
+}
+
+#[derive(Deserialize)]
+struct MockAuthorizeQuery {
+    client_id: String,
+    redirect_uri: String,
+    state: String,
+    nonce: String,
+    code_challenge: String,
+    code_challenge_method: String,
+    login_hint: String,
+}
+
+async fn mock_authorize(state: web::Data<MockIssuerState>, query: web::Query<MockAuthorizeQuery>) -> HttpResponse {
+    if query.client_id != state.client_id || query.code_challenge_method != "S256" {
+        return HttpResponse::BadRequest().json(json!({ "error": "invalid_request" }));
+    }
+    let code = Uuid::new_v4().simple().to_string();
+    state.codes.lock().unwrap().insert(code.clone(), PendingCode {
+        redirect_uri: query.redirect_uri.clone(),
+        code_challenge: query.code_challenge.clone(),
+        claims: state.claims(&query.login_hint, &query.nonce),
+    });
+    let location = format!(
+        "{}?code={}&state={}",
+        query.redirect_uri,
+        code,
+        urlencoding::encode(&query.state)
+    );
+    HttpResponse::Found().append_header(("Location", location)).finish()
+}
+
+#[derive(Deserialize)]
+struct MockTokenRequest {
+    grant_type: String,
+    code: String,
+    redirect_uri: String,
+    client_id: String,
+    code_verifier: String,
+}
+
+async fn mock_token(state: web::Data<MockIssuerState>, form: web::Form<MockTokenRequest>) -> HttpResponse {
+    // Un code ne s'échange qu'une fois, qu'il soit accepté ou non
+    let pending = state.codes.lock().unwrap().remove(&form.code);
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(form.code_verifier.as_bytes()));
+    let pending = match pending {
+        Some(p) if form.grant_type == "authorization_code"
+            && form.client_id == state.client_id
+            && form.redirect_uri == p.redirect_uri
+            && challenge == p.code_challenge => p,
+        _ => return HttpResponse::BadRequest().json(json!({ "error": "invalid_grant" })),
+    };
+    HttpResponse::Ok().json(json!({
+        "access_token": Uuid::new_v4().to_string(),
+        "token_type": "Bearer",
+        "id_token": state.id_token(&pending.claims),
+        "expires_in": 300,
+    }))
+}
@@ -0,0 +1,127 @@
+use std::borrow::Cow;
+use std::ops::Deref;
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use futures::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
+use crate::errors::{ApiError, FieldError};
+
+/// Extracteur JSON qui applique les règles `validator` du DTO avant
+/// d'appeler le handler. Les erreurs sont renvoyées en 422 avec le détail
+/// de chaque champ.
+pub struct ValidatedJson<T>(pub T);
+
+impl<T> Deref for ValidatedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> FromRequest for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = web::Json::<T>::from_request(req, payload);
+        Box::pin(async move {
+            let value = json.await?.into_inner();
+            value.validate().map_err(ApiError::from)?;
+            Ok(ValidatedJson(value))
+        })
+    }
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut fields = Vec::new();
+        flatten_errors(None, errors, &mut fields);
+        fields.sort_by(|a, b| a.field.cmp(&b.field));
+        ApiError::Validation(fields)
+    }
+}
+
+fn flatten_errors(prefix: Option<&str>, errors: ValidationErrors, out: &mut Vec<FieldError>) {
+    for (field, kind) in errors.into_errors() {
+        // Les erreurs de niveau structure (`schema`) sont rangées sous `__all__`
+        let path = match (prefix, field) {
+            (Some(p), "__all__") => p.to_string(),
+            (None, "__all__") => "body".to_string(),
+            (Some(p), f) => format!("{}.{}", p, f),
+            (None, f) => f.to_string(),
+        };
+        match kind {
+            ValidationErrorsKind::Field(list) => {
+                for error in list {
+                    let message = error.message.clone()
+                        .map(|m| m.to_string())
+                        .unwrap_or_else(|| default_message(&error));
+                    out.push(FieldError::new(path.clone(), error.code.to_string(), message));
+                }
+            }
+            ValidationErrorsKind::Struct(nested) => flatten_errors(Some(&path), *nested, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    flatten_errors(Some(&format!("{}[{}]", path, index)), *nested, out);
+                }
+            }
+        }
+    }
+}
+
+fn default_message(error: &ValidationError) -> String {
+    match error.code.as_ref() {
+        "email" => "Must be a valid email address".to_string(),
+        "length" => match (error.params.get("min"), error.params.get("max")) {
+            (Some(min), Some(max)) => format!("Length must be between {} and {}", min, max),
+            (Some(min), None) => format!("Length must be at least {}", min),
+            (None, Some(max)) => format!("Length must be at most {}", max),
+            _ => "Invalid length".to_string(),
+        },
+        "range" => match (error.params.get("min"), error.params.get("max")) {
+            (Some(min), Some(max)) => format!("Value must be between {} and {}", min, max),
+            (Some(min), None) => format!("Value must be at least {}", min),
+            (None, Some(max)) => format!("Value must be at most {}", max),
+            _ => "Value out of range".to_string(),
+        },
+        code => format!("Invalid value ({})", code),
+    }
+}
+
+/// Construit une erreur de validation avec un message explicite.
+pub fn validation_error(code: &'static str, message: impl Into<Cow<'static, str>>) -> ValidationError {
+    ValidationError::new(code).with_message(message.into())
+}
+
+/// Vérifie qu'une valeur fait partie d'une liste fermée.
+pub fn one_of(value: &str, allowed: &[&str]) -> Result<(), ValidationError> {
+    if allowed.contains(&value) {
+        Ok(())
+    } else {
+        let mut error = validation_error("invalid_choice", format!("Must be one of: {}", allowed.join(", ")));
+        error.add_param(Cow::from("allowed"), &allowed);
+        Err(error)
+    }
+}
+
+/// Refuse les listes contenant des éléments vides.
+pub fn non_blank_items(items: &[String]) -> Result<(), ValidationError> {
+    if items.iter().any(|i| i.trim().is_empty()) {
+        Err(validation_error("blank_item", "Items must not be blank"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Refuse les chaînes composées uniquement d'espaces.
+pub fn not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        Err(validation_error("blank", "Must not be blank"))
+    } else {
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
 import { Routes, Route, Navigate, useSearchParams, useNavigate } from 'react-router-dom'
-import { useEffect } from 'react'
+import { useEffect, useRef } from 'react'
 import { useAuth } from './hooks/useAuth'
 import Login from './pages/Login'
+import VerifyEmail from './pages/VerifyEmail'
+import ForgotPassword from './pages/ForgotPassword'
+import ResetPassword from './pages/ResetPassword'
 import Dashboard from './pages/Dashboard'
 import Entities from './pages/Entities'
 import RGPDRegister from './pages/RGPDRegister'
@@ -18,19 +21,25 @@ function PrivateRoute({ children }: { children: React.ReactNode }) {
 function OIDCCallback() {
   const [searchParams] = useSearchParams()
   const navigate = useNavigate()
-  const token = searchParams.get('token')
-  const refreshToken = searchParams.get('refresh_token')
+  const { exchangeOidcCode } = useAuth()
+  const code = searchParams.get('code')
+  const exchanged = useRef(false)
 
   useEffect(() => {
-    if (token && refreshToken) {
-      localStorage.setItem('token', token)
-      localStorage.setItem('refreshToken', refreshToken)
-      navigate('/')
-      window.location.reload()
-    } else {
+    if (exchanged.current) return
+    exchanged.current = true
+    if (!code) {
       navigate('/login')
+      return
     }
-  }, [token, refreshToken, navigate])
+    // Le code est à usage unique : on le retire de l'historique avant l'échange
+    window.history.replaceState(null, '', '/auth/callback')
+    exchangeOidcCode(code)
+      .then((challenge) => challenge
+        ? navigate('/login', { state: { mfaChallenge: challenge } })
+        : navigate('/'))
+      .catch(() => navigate('/login'))
+  }, [code, exchangeOidcCode, navigate])
 
   return <div>Connexion en cours...</div>
 }
@@ -39,6 +48,9 @@ function App() {
   return (
     <Routes>
       <Route path="/login" element={<Login />} />
+      <Route path="/verify-email" element={<VerifyEmail />} />
+      <Route path="/forgot-password" element={<ForgotPassword />} />
+      <Route path="/reset-password" element={<ResetPassword />} />
       <Route path="/auth/callback" element={<OIDCCallback />} />
       <Route
         path="/"
@@ -12,9 +12,17 @@ export interface RegisterRequest {
   last_name?: string
 }
 
+export interface SsoDiscoveryResponse {
+  provider_id: string
+  name: string
+  protocol: 'oidc' | 'saml'
+  authorize_url: string
+}
+
 export interface AuthResponse {
   token: string
   refresh_token: string
+  active_entity_id?: string
   user: {
     id: string
     email: string
@@ -23,22 +31,103 @@ export interface AuthResponse {
   }
 }
 
+export interface CurrentUser {
+  id: string
+  email: string
+  first_name?: string
+  last_name?: string
+  active_entity_id: string | null
+  memberships: { entity_id: string; role: string; inherited_from?: string }[]
+}
+
+export interface SwitchEntityResponse extends AuthResponse {
+  role: string
+}
+
+export interface RegisterResponse {
+  user: AuthResponse['user']
+  email_verification_required: true
+}
+
+export interface MfaChallengeResponse {
+  mfa_required: true
+  mfa_token: string
+  methods: string[]
+  expires_in: number
+}
+
+export interface MfaVerifyRequest {
+  mfa_token: string
+  method: 'totp' | 'recovery_code'
+  code: string
+}
+
 export const authApi = {
-  login: async (data: LoginRequest): Promise<AuthResponse> => {
-    const response = await apiClient.post<AuthResponse>('/auth/login', data)
+  login: async (data: LoginRequest): Promise<AuthResponse | MfaChallengeResponse> => {
+    const response = await apiClient.post<AuthResponse | MfaChallengeResponse>('/auth/login', data)
+    return response.data
+  },
+
+  verifyMfa: async (data: MfaVerifyRequest): Promise<AuthResponse> => {
+    const response = await apiClient.post<AuthResponse>('/auth/login/mfa', data)
+    return response.data
+  },
+
+  register: async (data: RegisterRequest): Promise<AuthResponse | RegisterResponse> => {
+    const response = await apiClient.post<AuthResponse | RegisterResponse>('/auth/register', data)
     return response.data
   },
 
-  register: async (data: RegisterRequest): Promise<AuthResponse> => {
-    const response = await apiClient.post<AuthResponse>('/auth/register', data)
+  discoverSso: async (email: string): Promise<SsoDiscoveryResponse> => {
+    const response = await apiClient.post<SsoDiscoveryResponse>('/auth/sso/discover', { email })
     return response.data
   },
 
-  getCurrentUser: async () => {
+  exchangeOidcCode: async (code: string): Promise<AuthResponse | MfaChallengeResponse> => {
+    const response = await apiClient.post<AuthResponse | MfaChallengeResponse>('/auth/oidc/exchange', { code })
+    return response.data
+  },
+
+  // Challenge à valider par verifyMfa pour ajouter un second facteur à la session
+  stepUpMfa: async (): Promise<MfaChallengeResponse> => {
+    const response = await apiClient.post<MfaChallengeResponse>('/auth/mfa/step-up')
+    return response.data
+  },
+
+  verifyEmail: async (token: string) => {
+    await apiClient.post('/auth/verify-email', { token })
+  },
+
+  resendVerificationEmail: async (email: string) => {
+    await apiClient.post('/auth/verify-email/resend', { email })
+  },
+
+  forgotPassword: async (email: string) => {
+    await apiClient.post('/auth/password/forgot', { email })
+  },
+
+  resetPassword: async (token: string, newPassword: string) => {
+    await apiClient.post('/auth/password/reset', { token, new_password: newPassword })
+  },
+
+  changePassword: async (currentPassword: string, newPassword: string): Promise<AuthResponse> => {
+    const response = await apiClient.post<AuthResponse>('/auth/password/change', {
+      current_password: currentPassword,
+      new_password: newPassword,
+    })
+    return response.data
+  },
+
+  getCurrentUser: async (): Promise<CurrentUser> => {
     const response = await apiClient.get('/auth/me')
     return response.data
   },
 
+  switchEntity: async (entityId: string): Promise<SwitchEntityResponse> => {
+    const response = await apiClient.post('/auth/switch-entity', { entity_id: entityId })
+    return response.data
+  },
+
   refreshToken: async (refreshToken: string) => {
     const response = await apiClient.post('/auth/refresh', { refresh_token: refreshToken })
     return response.data
@@ -3,6 +3,7 @@ import apiClient from './client'
 // Endpoint
 export interface Endpoint {
   id: string
+  entity_id?: string
   name: string
   endpoint_type: string
   description?: string
@@ -18,11 +19,13 @@ export interface CreateEndpointRequest {
   description?: string
   address?: string
   metadata?: any
+  entity_id?: string
 }
 
 // License Key
 export interface LicenseKey {
   id: string
+  entity_id?: string
   name: string
   license_type: string
   key_value?: string
@@ -42,11 +45,13 @@ export interface CreateLicenseKeyRequest {
   key_value?: string
   description?: string
   expires_at?: string
+  entity_id?: string
 }
 
 // Software Version
 export interface SoftwareVersion {
   id: string
+  entity_id?: string
   name: string
   version: string
   description?: string
@@ -64,11 +69,13 @@ export interface CreateSoftwareVersionRequest {
   release_date?: string
   end_of_life?: string
   metadata?: any
+  entity_id?: string
 }
 
 // Encryption Algorithm
 export interface EncryptionAlgorithm {
   id: string
+  entity_id?: string
   name: string
   algorithm_type: string
   key_size?: number
@@ -86,11 +93,13 @@ export interface CreateEncryptionAlgorithmRequest {
   description?: string
   standard?: string
   metadata?: any
+  entity_id?: string
 }
 
 // Catalogue Relation
 export interface CatalogueRelation {
   id: string
+  entity_id?: string
   source_type: string
   source_id: string
   target_type: string
@@ -107,6 +116,7 @@ export interface CreateCatalogueRelationRequest {
   target_id: string
   relation_type: string
   description?: string
+  entity_id?: string
 }
 
 export const catalogueApi = {
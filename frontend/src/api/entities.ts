@@ -4,6 +4,10 @@ export interface Entity {
   id: string
   name: string
   description?: string
+  require_mfa: boolean
+  parent_id?: string
+  archived_at?: string
+  deletion_scheduled_at?: string
   created_at: string
   updated_at: string
 }
@@ -11,6 +15,18 @@ export interface Entity {
 export interface CreateEntityRequest {
   name: string
   description?: string
+  require_mfa?: boolean
+  parent_id?: string
+}
+
+export interface LifecycleEvent {
+  id: string
+  entity_id: string
+  entity_name: string
+  action: string
+  actor_id?: string
+  details?: any
+  created_at: string
 }
 
 export const entitiesApi = {
@@ -38,5 +54,44 @@ export const entitiesApi = {
     const response = await apiClient.get(`/entities/${id}/users`)
     return response.data
   },
-}
 
+  setParent: async (id: string, parentId: string | null): Promise<Entity> => {
+    const response = await apiClient.put<Entity>(`/entities/${id}/parent`, { parent_id: parentId })
+    return response.data
+  },
+
+  listSubsidiaries: async (id: string): Promise<Entity[]> => {
+    const response = await apiClient.get<Entity[]>(`/entities/${id}/subsidiaries`)
+    return response.data
+  },
+
+  archive: async (id: string): Promise<Entity> => {
+    const response = await apiClient.post<Entity>(`/entities/${id}/archive`)
+    return response.data
+  },
+
+  unarchive: async (id: string): Promise<Entity> => {
+    const response = await apiClient.delete<Entity>(`/entities/${id}/archive`)
+    return response.data
+  },
+
+  scheduleDeletion: async (id: string, confirmName: string): Promise<Entity> => {
+    const response = await apiClient.post<Entity>(`/entities/${id}/deletion`, { confirm_name: confirmName })
+    return response.data
+  },
+
+  cancelDeletion: async (id: string): Promise<Entity> => {
+    const response = await apiClient.delete<Entity>(`/entities/${id}/deletion`)
+    return response.data
+  },
+
+  exportData: async (id: string): Promise<Blob> => {
+    const response = await apiClient.get(`/entities/${id}/export`, { responseType: 'blob' })
+    return response.data
+  },
+
+  listLifecycleEvents: async (id: string): Promise<LifecycleEvent[]> => {
+    const response = await apiClient.get<LifecycleEvent[]>(`/entities/${id}/lifecycle-events`)
+    return response.data
+  },
+}
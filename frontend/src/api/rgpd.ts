@@ -1,20 +1,359 @@
 import apiClient from './client'
+import { entitiesApi } from './entities'
+
+export type RecordType = 'controller' | 'processor'
+export type PartyRole = 'controller' | 'joint_controller' | 'representative' | 'dpo' | 'processor' | 'recipient'
+export type TransferSafeguard = 'adequacy' | 'scc' | 'bcr' | 'derogation' | 'other'
+export type SccModule = 'module_1' | 'module_2' | 'module_3' | 'module_4'
+export type TiaOutcome = 'adequate' | 'adequate_with_measures' | 'not_adequate'
+export type ConsentChannel = 'web_form' | 'mobile_app' | 'email' | 'phone' | 'paper' | 'in_person' | 'other'
+export type LegalBasis =
+  | 'consent'
+  | 'contract'
+  | 'legal_obligation'
+  | 'vital_interests'
+  | 'public_task'
+  | 'legitimate_interests'
+export type SpecialCategory = 'art9' | 'art10'
+export type RetentionTrigger = 'collection' | 'last_contact' | 'end_of_contract' | 'account_closure' | 'other'
+export type RetentionAction = 'delete' | 'anonymize' | 'archive'
+
+// Nomenclatures : bases légales (Art. 6), exceptions (Art. 9(2)), typologie des données
+export interface TaxonomyTerm {
+  key: string
+  article: string
+  label: string
+}
+
+export interface TaxonomyChoice {
+  key: string
+  label: string
+}
+
+export interface DataCategoryKind {
+  key: string
+  label: string
+  special?: SpecialCategory
+  retention_ceiling_months?: number
+}
+
+// Décision d'adéquation de la Commission ; `scope` limite la décision à un secteur ou un cadre
+export interface AdequacyDecision {
+  country: string
+  name: string
+  decision: string
+  scope?: string
+}
+
+export interface Taxonomy {
+  legal_bases: TaxonomyTerm[]
+  special_category_exceptions: TaxonomyTerm[]
+  data_category_kinds: DataCategoryKind[]
+  retention_triggers: TaxonomyChoice[]
+  retention_actions: TaxonomyChoice[]
+  eea_countries: string[]
+  adequacy_decisions: AdequacyDecision[]
+  adequacy_list_updated: string
+  scc_modules: TaxonomyChoice[]
+  tia_outcomes: TaxonomyChoice[]
+  consent_channels: TaxonomyChoice[]
+  breach_assessment: BreachAssessmentScales
+  breach_document_kinds: TaxonomyChoice[]
+  breach_template_placeholders: TaxonomyChoice[]
+}
+
+export interface AssessmentScale {
+  key: string
+  label: string
+  score: number
+}
+
+// Barèmes de la méthode de l'ENISA
+export interface BreachAssessmentScales {
+  data_types: AssessmentScale[]
+  identification_levels: AssessmentScale[]
+  confidentiality_losses: AssessmentScale[]
+  integrity_losses: AssessmentScale[]
+  availability_losses: AssessmentScale[]
+  malicious_intent_score: number
+  max_context_adjustment: number
+  subject_exemptions: TaxonomyChoice[]
+}
+
+export interface DataCategoryRef {
+  id: string
+  name: string
+  kind: string
+  special?: SpecialCategory
+  retention_ceiling_months?: number
+}
+
+export interface RegisterCheck {
+  rule: string
+  severity: 'error' | 'warning'
+  field?: string
+  data_category_id?: string
+  message: string
+}
+
+export interface ReferentialRef {
+  id: string
+  name: string
+}
+
+export type DpaStatus = 'valid' | 'expiring' | 'expired' | 'missing'
+
+export interface RegisterPartyRef {
+  party_id: string
+  name: string
+  role: PartyRole
+  country?: string
+  processor?: { id: string; dpa_status: DpaStatus; transfer_countries: string[] } // Fiche fournisseur d'un sous-traitant
+}
+
+export interface Transfer {
+  id: string
+  party_id?: string
+  party_name?: string
+  country: string
+  safeguard: TransferSafeguard
+  safeguard_details?: string
+  scc_module?: SccModule
+  supplementary_measures?: string
+  tia_outcome?: TiaOutcome // Évaluation d'impact du transfert
+  tia_notes?: string
+  tia_assessed_at?: string
+}
+
+export type TransferInput = Omit<Transfer, 'id' | 'party_name'>
+
+export interface SecurityMeasureRef {
+  id: string
+  name: string
+  category: string
+  catalogue_item_type?: string
+  catalogue_item_id?: string
+}
 
 export interface RegisterEntry {
   id: string
   entity_id: string
+  record_type: RecordType
   processing_name: string
   purpose: string
-  legal_basis: string
-  data_categories: string[]
-  data_subjects: string[]
-  recipients: string[]
+  legal_basis?: LegalBasis
+  legal_basis_details?: string
+  special_category_exception?: string
+  criminal_data_basis?: string
+  consent_mechanism?: string
+  data_categories: DataCategoryRef[]
+  data_subjects: ReferentialRef[]
+  parties: RegisterPartyRef[]
+  transfers: Transfer[]
+  measures: SecurityMeasureRef[]
   retention_period?: string
+  retention_months?: number
+  retention_trigger?: RetentionTrigger
+  retention_action?: RetentionAction
   security_measures?: string
+  shared_with_subsidiaries: boolean
+  checks: RegisterCheck[]
+  dpia?: { id: string; status: DpiaStatus; version: number }
+  consents?: ConsentStats // Traitements fondés sur le consentement
+  created_at: string
+  updated_at: string
+}
+
+// Statistiques du registre des consentements, en nombre de personnes
+export interface ConsentStats {
+  current_version?: number
+  subjects: number
+  active: number
+  withdrawn: number
+  active_on_current_version: number
+  last_consented_at?: string
+  last_withdrawn_at?: string
+}
+
+// Corps de création / modification : les référentiels sont désignés par leur id
+export interface RegisterEntryInput {
+  record_type?: RecordType
+  processing_name?: string
+  purpose?: string
+  legal_basis?: LegalBasis
+  legal_basis_details?: string
+  special_category_exception?: string
+  criminal_data_basis?: string
+  consent_mechanism?: string
+  data_category_ids?: string[]
+  data_subject_ids?: string[]
+  parties?: { party_id: string; role: PartyRole }[]
+  transfers?: TransferInput[]
+  measure_ids?: string[]
+  retention_period?: string
+  retention_months?: number
+  retention_trigger?: RetentionTrigger
+  retention_action?: RetentionAction
+  security_measures?: string
+  shared_with_subsidiaries?: boolean
+}
+
+export interface Category {
+  id: string
+  entity_id: string
+  name: string
+  description?: string
+  created_at: string
+  updated_at: string
+}
+
+export interface DataCategory extends Category {
+  kind: string
+  special?: SpecialCategory
+  retention_ceiling_months?: number
+  recommended_retention_months?: number
+}
+
+export interface Party {
+  id: string
+  entity_id: string
+  name: string
+  description?: string
+  category: 'internal' | 'organisation' | 'public_authority' | 'individual'
+  contact_name?: string
+  email?: string
+  phone?: string
+  address?: string
+  country?: string
+  created_at: string
+  updated_at: string
+}
+
+export interface SecurityMeasure {
+  id: string
+  entity_id: string
+  name: string
+  description?: string
+  category: string
+  catalogue_item_type?: string
+  catalogue_item_id?: string
+  created_at: string
+  updated_at: string
+}
+
+export type RegisterExportFormat = 'pdf' | 'odt' | 'docx' | 'xlsx' | 'ods' | 'jsonld'
+
+export interface RegisterExport {
+  id: string
+  version: number
+  format: RegisterExportFormat
+  entry_count: number
+  sha256: string
+  generated_by?: string
+  generated_at: string
+}
+
+// Analyses d'impact (AIPD)
+export type DpiaStatus = 'screening' | 'not_required' | 'in_progress' | 'approved' | 'rejected'
+export type ScreeningCriterion =
+  | 'evaluation_scoring'
+  | 'automated_decision'
+  | 'systematic_monitoring'
+  | 'sensitive_data'
+  | 'large_scale'
+  | 'matching_datasets'
+  | 'vulnerable_subjects'
+  | 'innovative_technology'
+  | 'prevents_exercise_of_rights'
+export type RiskLevel = 'low' | 'medium' | 'high'
+
+export interface DpiaRisk {
+  id: string
+  risk_type: 'illegitimate_access' | 'unwanted_modification' | 'disappearance'
+  description: string
+  impacts?: string
+  threats?: string
+  likelihood: number
+  severity: number
+  level: RiskLevel
+  residual_likelihood?: number
+  residual_severity?: number
+  residual_level?: RiskLevel
+  created_at: string
+  updated_at: string
+}
+
+export interface DpiaMeasure {
+  id: string
+  risk_id?: string
+  security_measure_id?: string
+  description: string
+  measure_type: 'technical' | 'organisational' | 'legal'
+  status: 'planned' | 'in_progress' | 'implemented'
+  owner?: string
+  due_date?: string
   created_at: string
   updated_at: string
 }
 
+export interface DpiaReview {
+  id: string
+  version: number
+  outcome: 'unchanged' | 'revision_required'
+  comment?: string
+  reviewed_by?: string
+  reviewed_at: string
+}
+
+export interface Dpia {
+  id: string
+  entity_id: string
+  register_entry_id: string
+  title: string
+  status: DpiaStatus
+  screening_criteria: ScreeningCriterion[]
+  screening_result?: 'required' | 'not_required'
+  screening_justification?: string
+  screened_by?: string
+  screened_at?: string
+  context?: string
+  necessity?: string
+  proportionality?: string
+  data_subject_rights?: string
+  risks: DpiaRisk[]
+  measures: DpiaMeasure[]
+  dpo_recommendation?: 'favourable' | 'favourable_with_reservations' | 'unfavourable'
+  dpo_opinion?: string
+  dpo_opinion_by?: string
+  dpo_opinion_at?: string
+  prior_consultation_required: boolean
+  version: number
+  reviews: DpiaReview[]
+  next_review_at?: string
+  last_reviewed_at?: string
+  review_overdue: boolean
+  created_by?: string
+  created_at: string
+  updated_at: string
+}
+
+export interface DpiaVersion {
+  id: string
+  version: number
+  decision: 'approved' | 'rejected'
+  comment?: string
+  snapshot: Dpia
+  signed_off_by?: string
+  signed_off_at: string
+}
+
+export type DpiaRiskInput = Omit<DpiaRisk, 'id' | 'level' | 'residual_level' | 'created_at' | 'updated_at'>
+export type DpiaMeasureInput = Omit<DpiaMeasure, 'id' | 'status' | 'created_at' | 'updated_at'> & {
+  status?: DpiaMeasure['status']
+}
+
+export type ReferentialKind = 'data-categories' | 'data-subjects' | 'parties' | 'security-measures'
+
 export interface AccessRequest {
   id: string
   entity_id: string
@@ -27,6 +366,8 @@ export interface AccessRequest {
   created_at: string
   updated_at: string
   completed_at?: string
+  anonymized_at?: string
+  archived_at?: string
 }
 
 export interface Breach {
@@ -45,38 +386,412 @@ export interface Breach {
   subjects_notified: boolean
   created_at: string
   updated_at: string
+  closed_at?: string
+  anonymized_at?: string
+  archived_at?: string
+  assessment?: BreachAssessment
+}
+
+// Évaluation de gravité selon la méthode de l'ENISA
+export type SubjectsNotification = 'not_required' | 'required' | 'exempted' | 'public_communication'
+
+export interface BreachAssessmentInput {
+  data_type: string
+  context_adjustment?: number
+  ease_of_identification: string
+  confidentiality: string
+  integrity: string
+  availability: string
+  malicious_intent?: boolean
+  subject_exemption?: string
+  severity?: string
+  justification?: string
+}
+
+export interface BreachAssessment {
+  method: string
+  data_type: string
+  context_adjustment: number
+  ease_of_identification: string
+  confidentiality: string
+  integrity: string
+  availability: string
+  malicious_intent: boolean
+  subject_exemption?: string
+  data_processing_context: number
+  ease_of_identification_score: number
+  circumstances_score: number
+  score: number
+  computed_severity: string
+  severity: string
+  authority_notification_required: boolean
+  subjects_notification: SubjectsNotification
+  rationale: string[]
+  justification?: string
+  assessed_by?: string
+  assessed_at: string
+  authority_deadline?: string
+}
+
+// Documents de notification générés à partir des modèles de l'entité
+export type BreachDocumentKind = 'authority_notification' | 'subject_notification'
+
+export interface BreachTemplate {
+  kind: BreachDocumentKind
+  language: string
+  title: string
+  body: string
+  is_default: boolean
+  updated_by?: string
+  updated_at?: string
+}
+
+export interface GenerateBreachDocument {
+  kind: BreachDocumentKind
+  language?: string
+  contact?: string
+  consequences?: string
+  delay_reason?: string
+}
+
+export interface BreachAttachment {
+  id: string
+  breach_id: string
+  file_name: string
+  content_type?: string
+  file_size: number
+  uploaded_by?: string
+  created_at: string
+}
+
+// Conservation des demandes d'accès et des écarts tenus par HyperCyber
+export type RetentionRecordType = 'access_request' | 'breach'
+
+export interface RetentionPolicy {
+  record_type: RetentionRecordType
+  duration_months: number
+  trigger: string // "closed", ou "created" / "discovered" selon le type
+  action: RetentionAction
+  enabled: boolean
+  is_default: boolean
+  register_entry_id?: string // Fiche du registre dont la règle s'applique
+  updated_by?: string
+  updated_at?: string
+}
+
+export interface RetentionPolicyReport {
+  record_type: RetentionRecordType
+  trigger: string
+  action: RetentionPolicy['action']
+  duration_months: number
+  is_default: boolean
+  cutoff: string
+  matched: number
+  processed: number
+  record_ids: string[]
+  files_deleted: number
+}
+
+export interface RetentionRun {
+  id: string
+  entity_id: string
+  triggered_by?: string
+  dry_run: boolean
+  status: 'running' | 'completed' | 'failed'
+  report: RetentionPolicyReport[]
+  error?: string
+  started_at: string
+  finished_at?: string
+}
+
+export interface RetentionArchive {
+  access_requests: AccessRequest[]
+  breaches: Breach[]
+}
+
+// Registre des sous-traitants (Art. 28)
+export interface SubProcessor {
+  id: string
+  party_id: string
+  name: string
+  country?: string
+  service?: string
+  authorised_at?: string
+}
+
+export interface ProcessorAlert {
+  code: string
+  severity: 'error' | 'warning'
+  processor_id?: string
+  party_id: string
+  party_name: string
+  message: string
+}
+
+export interface Processor {
+  id: string
+  entity_id: string
+  party_id: string
+  name: string
+  contact_name?: string
+  email?: string
+  phone?: string
+  address?: string
+  country?: string
+  services?: string
+  transfers: Transfer[]
+  dpa_reference?: string
+  dpa_signed_at?: string
+  dpa_expires_at?: string
+  dpa_status: DpaStatus
+  security_score?: number
+  security_assessed_at?: string
+  notes?: string
+  sub_processors: SubProcessor[]
+  endpoints: { id: string; name: string; endpoint_type: string; address?: string }[]
+  register_entries: { id: string; processing_name: string }[]
+  alerts: ProcessorAlert[]
+  created_at: string
+  updated_at: string
+}
+
+export interface ProcessorInput {
+  party_id: string
+  services?: string
+  transfers?: TransferInput[]
+  dpa_reference?: string
+  dpa_signed_at?: string
+  dpa_expires_at?: string
+  security_score?: number
+  security_assessed_at?: string
+  notes?: string
+  sub_processors: { party_id: string; service?: string; authorised_at?: string }[]
+  endpoint_ids: string[]
+}
+
+export interface ConsentPurpose {
+  id: string
+  register_entry_id: string
+  version: number
+  wording: string
+  created_by?: string
+  created_at: string
+}
+
+export interface Consent {
+  id: string
+  register_entry_id: string
+  processing_name: string
+  purpose_version: number
+  purpose_wording: string
+  channel: ConsentChannel
+  source?: string
+  external_id?: string
+  consented_at: string
+  withdrawn_at?: string
+  withdrawal_channel?: ConsentChannel
+  recorded_by?: string
+  created_at: string
+}
+
+export interface ConsentProof {
+  subject_hash: string
+  register_entry_id?: string
+  status: 'given' | 'withdrawn' | 'none'
+  consents: Consent[]
+  generated_at: string
+}
+
+// Sans entité, agrège les routes imbriquées de chaque entité accessible
+// (les anciennes routes `/rgpd` sont dépréciées). Les entités refusées, par
+// exemple faute de MFA, sont ignorées.
+const forAllEntities = async <T>(path: string, sortKey: keyof T): Promise<T[]> => {
+  const entities = await entitiesApi.list()
+  const results = await Promise.allSettled(
+    entities.map((e) => apiClient.get<T[]>(`/entities/${e.id}/rgpd/${path}`))
+  )
+  return results
+    .flatMap((r) => (r.status === 'fulfilled' ? r.value.data : []))
+    .sort((a, b) => String(b[sortKey]).localeCompare(String(a[sortKey])))
 }
 
 export const rgpdApi = {
-  // Register - New routes with entity_id in path
+  // Register
   getRegister: async (entityId?: string): Promise<RegisterEntry[]> => {
-    if (entityId) {
-      const response = await apiClient.get<RegisterEntry[]>(`/entities/${entityId}/rgpd/register`)
-      return response.data
+    if (!entityId) {
+      return forAllEntities<RegisterEntry>('register', 'created_at')
     }
-    // Fallback to old route for backward compatibility
-    const response = await apiClient.get<RegisterEntry[]>('/rgpd/register')
+    const response = await apiClient.get<RegisterEntry[]>(`/entities/${entityId}/rgpd/register`)
     return response.data
   },
 
-  addToRegister: async (entityId: string, data: Partial<RegisterEntry>): Promise<RegisterEntry> => {
+  addToRegister: async (entityId: string, data: RegisterEntryInput): Promise<RegisterEntry> => {
     const response = await apiClient.post<RegisterEntry>(`/entities/${entityId}/rgpd/register`, data)
     return response.data
   },
 
-  updateRegisterEntry: async (id: string, data: Partial<RegisterEntry>): Promise<RegisterEntry> => {
-    // Use old route for updates (entity_id not needed in path for updates)
-    const response = await apiClient.put<RegisterEntry>(`/rgpd/register/${id}`, data)
+  updateRegisterEntry: async (entityId: string, id: string, data: RegisterEntryInput): Promise<RegisterEntry> => {
+    const response = await apiClient.put<RegisterEntry>(`/entities/${entityId}/rgpd/register/${id}`, data)
     return response.data
   },
 
+  exportRegister: async (entityId: string, format: RegisterExportFormat): Promise<Blob> => {
+    const response = await apiClient.get(`/entities/${entityId}/rgpd/register/export`, {
+      params: { format },
+      responseType: 'blob',
+    })
+    return response.data
+  },
+
+  listRegisterExports: async (entityId: string): Promise<RegisterExport[]> => {
+    const response = await apiClient.get<RegisterExport[]>(`/entities/${entityId}/rgpd/register/exports`)
+    return response.data
+  },
+
+  // Analyses d'impact
+  listDpias: async (entityId: string): Promise<Dpia[]> => {
+    const response = await apiClient.get<Dpia[]>(`/entities/${entityId}/rgpd/dpias`)
+    return response.data
+  },
+
+  getDpia: async (entityId: string, id: string): Promise<Dpia> => {
+    const response = await apiClient.get<Dpia>(`/entities/${entityId}/rgpd/dpias/${id}`)
+    return response.data
+  },
+
+  createDpia: async (entityId: string, data: { register_entry_id: string; title: string }): Promise<Dpia> => {
+    const response = await apiClient.post<Dpia>(`/entities/${entityId}/rgpd/dpias`, data)
+    return response.data
+  },
+
+  updateDpia: async (
+    entityId: string,
+    id: string,
+    data: Partial<Pick<Dpia, 'title' | 'context' | 'necessity' | 'proportionality' | 'data_subject_rights' | 'next_review_at'>>
+  ): Promise<Dpia> => {
+    const response = await apiClient.put<Dpia>(`/entities/${entityId}/rgpd/dpias/${id}`, data)
+    return response.data
+  },
+
+  deleteDpia: async (entityId: string, id: string): Promise<void> => {
+    await apiClient.delete(`/entities/${entityId}/rgpd/dpias/${id}`)
+  },
+
+  screenDpia: async (
+    entityId: string,
+    id: string,
+    data: { criteria: ScreeningCriterion[]; decision?: 'required' | 'not_required'; justification?: string }
+  ): Promise<Dpia> => {
+    const response = await apiClient.put<Dpia>(`/entities/${entityId}/rgpd/dpias/${id}/screening`, data)
+    return response.data
+  },
+
+  createDpiaRisk: async (entityId: string, id: string, data: DpiaRiskInput): Promise<DpiaRisk> => {
+    const response = await apiClient.post<DpiaRisk>(`/entities/${entityId}/rgpd/dpias/${id}/risks`, data)
+    return response.data
+  },
+
+  updateDpiaRisk: async (entityId: string, id: string, riskId: string, data: DpiaRiskInput): Promise<DpiaRisk> => {
+    const response = await apiClient.put<DpiaRisk>(`/entities/${entityId}/rgpd/dpias/${id}/risks/${riskId}`, data)
+    return response.data
+  },
+
+  deleteDpiaRisk: async (entityId: string, id: string, riskId: string): Promise<void> => {
+    await apiClient.delete(`/entities/${entityId}/rgpd/dpias/${id}/risks/${riskId}`)
+  },
+
+  createDpiaMeasure: async (entityId: string, id: string, data: DpiaMeasureInput): Promise<DpiaMeasure> => {
+    const response = await apiClient.post<DpiaMeasure>(`/entities/${entityId}/rgpd/dpias/${id}/measures`, data)
+    return response.data
+  },
+
+  updateDpiaMeasure: async (entityId: string, id: string, measureId: string, data: DpiaMeasureInput): Promise<DpiaMeasure> => {
+    const response = await apiClient.put<DpiaMeasure>(`/entities/${entityId}/rgpd/dpias/${id}/measures/${measureId}`, data)
+    return response.data
+  },
+
+  deleteDpiaMeasure: async (entityId: string, id: string, measureId: string): Promise<void> => {
+    await apiClient.delete(`/entities/${entityId}/rgpd/dpias/${id}/measures/${measureId}`)
+  },
+
+  giveDpoOpinion: async (
+    entityId: string,
+    id: string,
+    data: { recommendation: NonNullable<Dpia['dpo_recommendation']>; opinion: string }
+  ): Promise<Dpia> => {
+    const response = await apiClient.post<Dpia>(`/entities/${entityId}/rgpd/dpias/${id}/dpo-opinion`, data)
+    return response.data
+  },
+
+  signOffDpia: async (
+    entityId: string,
+    id: string,
+    data: { decision: 'approved' | 'rejected'; comment?: string; next_review_at?: string }
+  ): Promise<Dpia> => {
+    const response = await apiClient.post<Dpia>(`/entities/${entityId}/rgpd/dpias/${id}/sign-off`, data)
+    return response.data
+  },
+
+  reviewDpia: async (
+    entityId: string,
+    id: string,
+    data: { outcome: DpiaReview['outcome']; comment?: string; next_review_at?: string }
+  ): Promise<Dpia> => {
+    const response = await apiClient.post<Dpia>(`/entities/${entityId}/rgpd/dpias/${id}/reviews`, data)
+    return response.data
+  },
+
+  listDpiaVersions: async (entityId: string, id: string): Promise<DpiaVersion[]> => {
+    const response = await apiClient.get<DpiaVersion[]>(`/entities/${entityId}/rgpd/dpias/${id}/versions`)
+    return response.data
+  },
+
+  // Référentiels du registre
+  getTaxonomy: async (entityId: string): Promise<Taxonomy> => {
+    const response = await apiClient.get<Taxonomy>(`/entities/${entityId}/rgpd/taxonomy`)
+    return response.data
+  },
+
+  listDataCategories: async (entityId: string): Promise<DataCategory[]> => {
+    const response = await apiClient.get<DataCategory[]>(`/entities/${entityId}/rgpd/data-categories`)
+    return response.data
+  },
+
+  listDataSubjects: async (entityId: string): Promise<Category[]> => {
+    const response = await apiClient.get<Category[]>(`/entities/${entityId}/rgpd/data-subjects`)
+    return response.data
+  },
+
+  listParties: async (entityId: string): Promise<Party[]> => {
+    const response = await apiClient.get<Party[]>(`/entities/${entityId}/rgpd/parties`)
+    return response.data
+  },
+
+  listSecurityMeasures: async (entityId: string): Promise<SecurityMeasure[]> => {
+    const response = await apiClient.get<SecurityMeasure[]>(`/entities/${entityId}/rgpd/security-measures`)
+    return response.data
+  },
+
+  createReferential: async <T>(entityId: string, kind: ReferentialKind, data: Partial<T>): Promise<T> => {
+    const response = await apiClient.post<T>(`/entities/${entityId}/rgpd/${kind}`, data)
+    return response.data
+  },
+
+  updateReferential: async <T>(entityId: string, kind: ReferentialKind, id: string, data: Partial<T>): Promise<T> => {
+    const response = await apiClient.put<T>(`/entities/${entityId}/rgpd/${kind}/${id}`, data)
+    return response.data
+  },
+
+  deleteReferential: async (entityId: string, kind: ReferentialKind, id: string): Promise<void> => {
+    await apiClient.delete(`/entities/${entityId}/rgpd/${kind}/${id}`)
+  },
+
   // Access Requests
   listAccessRequests: async (entityId?: string): Promise<AccessRequest[]> => {
-    if (entityId) {
-      const response = await apiClient.get<AccessRequest[]>(`/entities/${entityId}/rgpd/access-requests`)
-      return response.data
+    if (!entityId) {
+      return forAllEntities<AccessRequest>('access-requests', 'created_at')
     }
-    const response = await apiClient.get<AccessRequest[]>('/rgpd/access-requests')
+    const response = await apiClient.get<AccessRequest[]>(`/entities/${entityId}/rgpd/access-requests`)
     return response.data
   },
 
@@ -85,23 +800,22 @@ export const rgpdApi = {
     return response.data
   },
 
-  getAccessRequest: async (id: string): Promise<AccessRequest> => {
-    const response = await apiClient.get<AccessRequest>(`/rgpd/access-requests/${id}`)
+  getAccessRequest: async (entityId: string, id: string): Promise<AccessRequest> => {
+    const response = await apiClient.get<AccessRequest>(`/entities/${entityId}/rgpd/access-requests/${id}`)
     return response.data
   },
 
-  respondToRequest: async (id: string, data: { status: string; response?: string }): Promise<AccessRequest> => {
-    const response = await apiClient.post<AccessRequest>(`/rgpd/access-requests/${id}/respond`, data)
+  respondToRequest: async (entityId: string, id: string, data: { status: string; response?: string }): Promise<AccessRequest> => {
+    const response = await apiClient.post<AccessRequest>(`/entities/${entityId}/rgpd/access-requests/${id}/respond`, data)
     return response.data
   },
 
   // Breaches
   listBreaches: async (entityId?: string): Promise<Breach[]> => {
-    if (entityId) {
-      const response = await apiClient.get<Breach[]>(`/entities/${entityId}/rgpd/breaches`)
-      return response.data
+    if (!entityId) {
+      return forAllEntities<Breach>('breaches', 'discovery_date')
     }
-    const response = await apiClient.get<Breach[]>('/rgpd/breaches')
+    const response = await apiClient.get<Breach[]>(`/entities/${entityId}/rgpd/breaches`)
     return response.data
   },
 
@@ -110,14 +824,170 @@ export const rgpdApi = {
     return response.data
   },
 
-  getBreach: async (id: string): Promise<Breach> => {
-    const response = await apiClient.get<Breach>(`/rgpd/breaches/${id}`)
+  getBreach: async (entityId: string, id: string): Promise<Breach> => {
+    const response = await apiClient.get<Breach>(`/entities/${entityId}/rgpd/breaches/${id}`)
     return response.data
   },
 
-  updateBreach: async (id: string, data: Partial<Breach>): Promise<Breach> => {
-    const response = await apiClient.put<Breach>(`/rgpd/breaches/${id}`, data)
+  updateBreach: async (entityId: string, id: string, data: Partial<Breach>): Promise<Breach> => {
+    const response = await apiClient.put<Breach>(`/entities/${entityId}/rgpd/breaches/${id}`, data)
     return response.data
   },
-}
 
+  assessBreach: async (entityId: string, id: string, data: BreachAssessmentInput): Promise<Breach> => {
+    const response = await apiClient.put<Breach>(`/entities/${entityId}/rgpd/breaches/${id}/assessment`, data)
+    return response.data
+  },
+
+  generateBreachDocument: async (entityId: string, breachId: string, data: GenerateBreachDocument): Promise<BreachAttachment> => {
+    const response = await apiClient.post<BreachAttachment>(`/entities/${entityId}/rgpd/breaches/${breachId}/documents`, data)
+    return response.data
+  },
+
+  listBreachTemplates: async (entityId: string): Promise<BreachTemplate[]> => {
+    const response = await apiClient.get<BreachTemplate[]>(`/entities/${entityId}/rgpd/breach-templates`)
+    return response.data
+  },
+
+  updateBreachTemplate: async (
+    entityId: string,
+    kind: BreachDocumentKind,
+    language: string,
+    data: { title: string; body: string },
+  ): Promise<BreachTemplate> => {
+    const response = await apiClient.put<BreachTemplate>(`/entities/${entityId}/rgpd/breach-templates/${kind}/${language}`, data)
+    return response.data
+  },
+
+  resetBreachTemplate: async (entityId: string, kind: BreachDocumentKind, language: string): Promise<void> => {
+    await apiClient.delete(`/entities/${entityId}/rgpd/breach-templates/${kind}/${language}`)
+  },
+
+  listBreachAttachments: async (entityId: string, breachId: string): Promise<BreachAttachment[]> => {
+    const response = await apiClient.get<BreachAttachment[]>(`/entities/${entityId}/rgpd/breaches/${breachId}/attachments`)
+    return response.data
+  },
+
+  uploadBreachAttachment: async (entityId: string, breachId: string, file: File): Promise<BreachAttachment> => {
+    const formData = new FormData()
+    formData.append('file', file)
+    const response = await apiClient.post<BreachAttachment>(
+      `/entities/${entityId}/rgpd/breaches/${breachId}/attachments`,
+      formData,
+      { headers: { 'Content-Type': 'multipart/form-data' } }
+    )
+    return response.data
+  },
+
+  downloadBreachAttachment: async (entityId: string, breachId: string, id: string): Promise<Blob> => {
+    const response = await apiClient.get(`/entities/${entityId}/rgpd/breaches/${breachId}/attachments/${id}`, {
+      responseType: 'blob',
+    })
+    return response.data
+  },
+
+  deleteBreachAttachment: async (entityId: string, breachId: string, id: string): Promise<void> => {
+    await apiClient.delete(`/entities/${entityId}/rgpd/breaches/${breachId}/attachments/${id}`)
+  },
+
+  // Sous-traitants
+  listProcessors: async (entityId: string): Promise<Processor[]> => {
+    const response = await apiClient.get<Processor[]>(`/entities/${entityId}/rgpd/processors`)
+    return response.data
+  },
+
+  getProcessor: async (entityId: string, id: string): Promise<Processor> => {
+    const response = await apiClient.get<Processor>(`/entities/${entityId}/rgpd/processors/${id}`)
+    return response.data
+  },
+
+  createProcessor: async (entityId: string, data: ProcessorInput): Promise<Processor> => {
+    const response = await apiClient.post<Processor>(`/entities/${entityId}/rgpd/processors`, data)
+    return response.data
+  },
+
+  updateProcessor: async (entityId: string, id: string, data: ProcessorInput): Promise<Processor> => {
+    const response = await apiClient.put<Processor>(`/entities/${entityId}/rgpd/processors/${id}`, data)
+    return response.data
+  },
+
+  deleteProcessor: async (entityId: string, id: string): Promise<void> => {
+    await apiClient.delete(`/entities/${entityId}/rgpd/processors/${id}`)
+  },
+
+  listProcessorAlerts: async (entityId: string): Promise<ProcessorAlert[]> => {
+    const response = await apiClient.get<ProcessorAlert[]>(`/entities/${entityId}/rgpd/processors/alerts`)
+    return response.data
+  },
+
+  // Registre des consentements
+  listConsentPurposes: async (entityId: string, registerEntryId: string): Promise<ConsentPurpose[]> => {
+    const response = await apiClient.get<ConsentPurpose[]>(`/entities/${entityId}/rgpd/register/${registerEntryId}/consent-purposes`)
+    return response.data
+  },
+
+  createConsentPurpose: async (entityId: string, registerEntryId: string, wording: string): Promise<ConsentPurpose> => {
+    const response = await apiClient.post<ConsentPurpose>(`/entities/${entityId}/rgpd/register/${registerEntryId}/consent-purposes`, { wording })
+    return response.data
+  },
+
+  // L'identifiant de la personne part dans le corps, jamais dans l'URL
+  getConsentProof: async (entityId: string, subjectId: string, registerEntryId?: string): Promise<ConsentProof> => {
+    const response = await apiClient.post<ConsentProof>(`/entities/${entityId}/rgpd/consents/proof`, {
+      subject_id: subjectId,
+      register_entry_id: registerEntryId,
+    })
+    return response.data
+  },
+
+  // Conservation des données
+  listRetentionPolicies: async (entityId: string): Promise<RetentionPolicy[]> => {
+    const response = await apiClient.get<RetentionPolicy[]>(`/entities/${entityId}/rgpd/retention/policies`)
+    return response.data
+  },
+
+  updateRetentionPolicy: async (
+    entityId: string,
+    recordType: RetentionRecordType,
+    data: Partial<Pick<RetentionPolicy, 'duration_months' | 'trigger' | 'action'>> & { enabled?: boolean; register_entry_id?: string }
+  ): Promise<RetentionPolicy> => {
+    const response = await apiClient.put<RetentionPolicy>(`/entities/${entityId}/rgpd/retention/policies/${recordType}`, data)
+    return response.data
+  },
+
+  resetRetentionPolicy: async (entityId: string, recordType: RetentionRecordType): Promise<RetentionPolicy> => {
+    const response = await apiClient.delete<RetentionPolicy>(`/entities/${entityId}/rgpd/retention/policies/${recordType}`)
+    return response.data
+  },
+
+  listRetentionRuns: async (entityId: string): Promise<RetentionRun[]> => {
+    const response = await apiClient.get<RetentionRun[]>(`/entities/${entityId}/rgpd/retention/runs`)
+    return response.data
+  },
+
+  startRetentionRun: async (entityId: string, dryRun = false): Promise<RetentionRun> => {
+    const response = await apiClient.post<RetentionRun>(`/entities/${entityId}/rgpd/retention/runs`, { dry_run: dryRun })
+    return response.data
+  },
+
+  getRetentionArchive: async (entityId: string): Promise<RetentionArchive> => {
+    const response = await apiClient.get<RetentionArchive>(`/entities/${entityId}/rgpd/retention/archive`)
+    return response.data
+  },
+
+  // Vues consolidées : l'entité et toutes ses filiales
+  getConsolidatedRegister: async (entityId: string): Promise<RegisterEntry[]> => {
+    const response = await apiClient.get<RegisterEntry[]>(`/entities/${entityId}/rgpd/consolidated/register`)
+    return response.data
+  },
+
+  listConsolidatedAccessRequests: async (entityId: string): Promise<AccessRequest[]> => {
+    const response = await apiClient.get<AccessRequest[]>(`/entities/${entityId}/rgpd/consolidated/access-requests`)
+    return response.data
+  },
+
+  listConsolidatedBreaches: async (entityId: string): Promise<Breach[]> => {
+    const response = await apiClient.get<Breach[]>(`/entities/${entityId}/rgpd/consolidated/breaches`)
+    return response.data
+  },
+}
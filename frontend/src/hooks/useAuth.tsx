@@ -1,5 +1,5 @@
-import { createContext, useContext, useState, useEffect, ReactNode } from 'react'
-import { authApi } from '../api/auth'
+import { createContext, useContext, useState, useEffect, useCallback, ReactNode } from 'react'
+import { authApi, AuthResponse, MfaChallengeResponse, MfaVerifyRequest } from '../api/auth'
 
 interface User {
   id: string
@@ -11,8 +11,10 @@ interface User {
 interface AuthContextType {
   user: User | null
   isAuthenticated: boolean
-  login: (email: string, password: string) => Promise<void>
-  register: (email: string, password: string, firstName?: string, lastName?: string) => Promise<void>
+  login: (email: string, password: string) => Promise<MfaChallengeResponse | null>
+  verifyMfa: (data: MfaVerifyRequest) => Promise<void>
+  exchangeOidcCode: (code: string) => Promise<MfaChallengeResponse | null>
+  register: (email: string, password: string, firstName?: string, lastName?: string) => Promise<boolean>
   logout: () => void
 }
 
@@ -33,18 +35,44 @@ export function AuthProvider({ children }: { children: ReactNode }) {
     }
   }, [])
 
-  const login = async (email: string, password: string) => {
-    const response = await authApi.login({ email, password })
+  const storeSession = (response: AuthResponse) => {
     localStorage.setItem('token', response.token)
     localStorage.setItem('refreshToken', response.refresh_token)
     setUser(response.user)
   }
 
+  // Renvoie le challenge si un second facteur est exigé
+  const login = async (email: string, password: string) => {
+    const response = await authApi.login({ email, password })
+    if ('mfa_required' in response) {
+      return response
+    }
+    storeSession(response)
+    return null
+  }
+
+  const verifyMfa = async (data: MfaVerifyRequest) => {
+    storeSession(await authApi.verifyMfa(data))
+  }
+
+  // Renvoie le challenge si le second facteur n'a pas été vérifié par le fournisseur
+  const exchangeOidcCode = useCallback(async (code: string) => {
+    const response = await authApi.exchangeOidcCode(code)
+    if ('mfa_required' in response) {
+      return response
+    }
+    storeSession(response)
+    return null
+  }, [])
+
   const register = async (email: string, password: string, firstName?: string, lastName?: string) => {
     const response = await authApi.register({ email, password, first_name: firstName, last_name: lastName })
-    localStorage.setItem('token', response.token)
-    localStorage.setItem('refreshToken', response.refresh_token)
-    setUser(response.user)
+    // Renvoie vrai si l'adresse email doit être vérifiée avant la connexion
+    if ('email_verification_required' in response) {
+      return true
+    }
+    storeSession(response)
+    return false
   }
 
   const logout = () => {
@@ -59,6 +87,8 @@ export function AuthProvider({ children }: { children: ReactNode }) {
         user,
         isAuthenticated: !!user,
         login,
+        verifyMfa,
+        exchangeOidcCode,
         register,
         logout,
       }}
@@ -0,0 +1,59 @@
+import { useState } from 'react'
+import { Link } from 'react-router-dom'
+import { authApi } from '../api/auth'
+import './Login.css'
+
+export default function ForgotPassword() {
+  const [email, setEmail] = useState('')
+  const [sent, setSent] = useState(false)
+  const [error, setError] = useState('')
+  const [loading, setLoading] = useState(false)
+
+  const handleSubmit = async (e: React.FormEvent) => {
+    e.preventDefault()
+    setError('')
+    setLoading(true)
+
+    try {
+      await authApi.forgotPassword(email)
+      setSent(true)
+    } catch (err: any) {
+      setError(err.response?.data?.detail || 'Une erreur est survenue')
+    } finally {
+      setLoading(false)
+    }
+  }
+
+  return (
+    <div className="login-container">
+      <div className="login-card">
+        <h1>HyperCyber</h1>
+        <h2>Mot de passe oublié</h2>
+        {error && <div className="error-message">{error}</div>}
+        {sent ? (
+          <div className="info-message">
+            Si un compte correspond à cette adresse, un lien de réinitialisation vient d'être envoyé.
+          </div>
+        ) : (
+          <form onSubmit={handleSubmit}>
+            <div className="form-group">
+              <label>Email</label>
+              <input
+                type="email"
+                value={email}
+                onChange={(e) => setEmail(e.target.value)}
+                required
+              />
+            </div>
+            <button type="submit" disabled={loading}>
+              {loading ? 'Chargement...' : 'Envoyer le lien'}
+            </button>
+          </form>
+        )}
+        <p>
+          <Link to="/login">Retour à la connexion</Link>
+        </p>
+      </div>
+    </div>
+  )
+}
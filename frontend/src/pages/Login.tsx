@@ -1,8 +1,11 @@
 import { useState } from 'react'
-import { useNavigate } from 'react-router-dom'
+import { Link, useLocation, useNavigate } from 'react-router-dom'
 import { useAuth } from '../hooks/useAuth'
+import { authApi, MfaChallengeResponse } from '../api/auth'
 import './Login.css'
 
+const API_URL = import.meta.env.VITE_API_URL || 'http://localhost:8080/api'
+
 export default function Login() {
   const [email, setEmail] = useState('')
   const [password, setPassword] = useState('')
@@ -10,35 +13,141 @@ export default function Login() {
   const [firstName, setFirstName] = useState('')
   const [lastName, setLastName] = useState('')
   const [error, setError] = useState('')
+  const [info, setInfo] = useState('')
+  const [unverifiedEmail, setUnverifiedEmail] = useState(false)
   const [loading, setLoading] = useState(false)
-  const { login, register } = useAuth()
+  // Challenge transmis par le retour d'une connexion fédérée
+  const location = useLocation()
+  const initialChallenge = (location.state as { mfaChallenge?: MfaChallengeResponse } | null)?.mfaChallenge ?? null
+  const [mfaChallenge, setMfaChallenge] = useState<MfaChallengeResponse | null>(initialChallenge)
+  const [mfaMethod, setMfaMethod] = useState<'totp' | 'recovery_code'>(
+    !initialChallenge || initialChallenge.methods.includes('totp') ? 'totp' : 'recovery_code'
+  )
+  const [mfaCode, setMfaCode] = useState('')
+  const { login, verifyMfa, register } = useAuth()
   const navigate = useNavigate()
 
   const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault()
     setError('')
+    setInfo('')
+    setUnverifiedEmail(false)
     setLoading(true)
 
     try {
       if (isRegister) {
-        await register(email, password, firstName || undefined, lastName || undefined)
+        if (await register(email, password, firstName || undefined, lastName || undefined)) {
+          setIsRegister(false)
+          setInfo('Compte créé. Un lien de vérification vous a été envoyé par email.')
+          return
+        }
       } else {
-        await login(email, password)
+        const challenge = await login(email, password)
+        if (challenge) {
+          setMfaChallenge(challenge)
+          setMfaMethod(challenge.methods.includes('totp') ? 'totp' : 'recovery_code')
+          return
+        }
       }
       navigate('/')
     } catch (err: any) {
-      setError(err.response?.data?.error || 'Une erreur est survenue')
+      setUnverifiedEmail(err.response?.data?.code === 'email_not_verified')
+      setError(err.response?.data?.detail || 'Une erreur est survenue')
+    } finally {
+      setLoading(false)
+    }
+  }
+
+  // Découverte du fournisseur de l'entité à partir du domaine de l'email
+  const handleSso = async () => {
+    setError('')
+    setInfo('')
+    if (!email) {
+      setError('Saisissez votre email pour vous connecter avec le SSO de votre organisation')
+      return
+    }
+    try {
+      const provider = await authApi.discoverSso(email)
+      window.location.href = `${API_URL}${provider.authorize_url}`
+    } catch {
+      setError("Aucun fournisseur d'identité n'est configuré pour ce domaine")
+    }
+  }
+
+  const handleResend = async () => {
+    await authApi.resendVerificationEmail(email)
+    setUnverifiedEmail(false)
+    setError('')
+    setInfo('Un nouveau lien de vérification vous a été envoyé.')
+  }
+
+  const handleMfaSubmit = async (e: React.FormEvent) => {
+    e.preventDefault()
+    if (!mfaChallenge) return
+    setError('')
+    setLoading(true)
+
+    try {
+      await verifyMfa({ mfa_token: mfaChallenge.mfa_token, method: mfaMethod, code: mfaCode })
+      navigate('/')
+    } catch (err: any) {
+      setError(err.response?.data?.detail || 'Une erreur est survenue')
     } finally {
       setLoading(false)
     }
   }
 
+  if (mfaChallenge) {
+    return (
+      <div className="login-container">
+        <div className="login-card">
+          <h1>HyperCyber</h1>
+          <h2>Vérification en deux étapes</h2>
+          {error && <div className="error-message">{error}</div>}
+          <form onSubmit={handleMfaSubmit}>
+            <div className="form-group">
+              <label>{mfaMethod === 'totp' ? "Code de l'application d'authentification" : 'Code de secours'}</label>
+              <input
+                type="text"
+                autoComplete="one-time-code"
+                value={mfaCode}
+                onChange={(e) => setMfaCode(e.target.value)}
+                required
+              />
+            </div>
+            <button type="submit" disabled={loading}>
+              {loading ? 'Chargement...' : 'Vérifier'}
+            </button>
+          </form>
+          {mfaChallenge.methods.includes('totp') && mfaChallenge.methods.includes('recovery_code') && (
+            <p>
+              <button
+                className="link-button"
+                onClick={() => setMfaMethod(mfaMethod === 'totp' ? 'recovery_code' : 'totp')}
+              >
+                {mfaMethod === 'totp' ? 'Utiliser un code de secours' : "Utiliser l'application d'authentification"}
+              </button>
+            </p>
+          )}
+        </div>
+      </div>
+    )
+  }
+
   return (
     <div className="login-container">
       <div className="login-card">
         <h1>HyperCyber</h1>
         <h2>{isRegister ? 'Créer un compte' : 'Connexion'}</h2>
         {error && <div className="error-message">{error}</div>}
+        {info && <div className="info-message">{info}</div>}
+        {unverifiedEmail && (
+          <p>
+            <button type="button" className="link-button" onClick={handleResend}>
+              Renvoyer le lien de vérification
+            </button>
+          </p>
+        )}
         <form onSubmit={handleSubmit}>
           {isRegister && (
             <>
@@ -82,12 +191,17 @@ export default function Login() {
             {loading ? 'Chargement...' : isRegister ? 'Créer un compte' : 'Se connecter'}
           </button>
         </form>
+        {!isRegister && (
+          <p>
+            <Link to="/forgot-password">Mot de passe oublié ?</Link>
+          </p>
+        )}
         {!isRegister && (
           <div style={{ marginTop: '1rem', textAlign: 'center' }}>
             <button
               type="button"
               onClick={() => {
-                window.location.href = `${import.meta.env.VITE_API_URL || 'http://localhost:8080/api'}/auth/oidc/authorize`
+                window.location.href = `${API_URL}/auth/oidc/authorize`
               }}
               style={{
                 padding: '0.5rem 1rem',
@@ -100,6 +214,21 @@ export default function Login() {
             >
               Se connecter avec OIDC
             </button>
+            <button
+              type="button"
+              onClick={handleSso}
+              style={{
+                marginLeft: '0.5rem',
+                padding: '0.5rem 1rem',
+                backgroundColor: '#5f6368',
+                color: 'white',
+                border: 'none',
+                borderRadius: '4px',
+                cursor: 'pointer',
+              }}
+            >
+              SSO de mon organisation
+            </button>
           </div>
         )}
         <p>
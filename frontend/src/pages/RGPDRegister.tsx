@@ -1,22 +1,41 @@
 import { useState } from 'react'
 import { useQuery, useMutation, useQueryClient } from '@tanstack/react-query'
 import { entitiesApi } from '../api/entities'
-import { rgpdApi, RegisterEntry } from '../api/rgpd'
+import { rgpdApi, RegisterEntry, RecordType, PartyRole, RegisterExportFormat, DpiaStatus, LegalBasis, RetentionAction, RetentionTrigger } from '../api/rgpd'
 import './RGPDRegister.css'
 
+const DPIA_STATUS_LABELS: Record<DpiaStatus, string> = {
+  screening: 'Pré-évaluation',
+  not_required: 'Non requise',
+  in_progress: 'En cours',
+  approved: 'Validée',
+  rejected: 'Refusée',
+}
+
 export default function RGPDRegister() {
   const [showCreateForm, setShowCreateForm] = useState(false)
   const [selectedEntity, setSelectedEntity] = useState<string>('')
-  const [formData, setFormData] = useState({
+  const emptyForm = {
+    record_type: 'controller' as RecordType,
     processing_name: '',
     purpose: '',
-    legal_basis: '',
-    data_categories: '',
-    data_subjects: '',
-    recipients: '',
+    legal_basis: '' as LegalBasis | '',
+    legal_basis_details: '',
+    special_category_exception: '',
+    criminal_data_basis: '',
+    consent_mechanism: '',
+    data_category_ids: [] as string[],
+    data_subject_ids: [] as string[],
+    controller_ids: [] as string[],
+    recipient_ids: [] as string[],
+    measure_ids: [] as string[],
     retention_period: '',
+    retention_months: '',
+    retention_trigger: '',
+    retention_action: '',
     security_measures: '',
-  })
+  }
+  const [formData, setFormData] = useState(emptyForm)
   const queryClient = useQueryClient()
 
   const { data: entities } = useQuery({
@@ -29,21 +48,49 @@ export default function RGPDRegister() {
     queryFn: () => rgpdApi.getRegister(selectedEntity || undefined),
   })
 
+  // Référentiels de l'entité sélectionnée
+  const { data: taxonomy } = useQuery({
+    queryKey: ['rgpd-taxonomy', selectedEntity],
+    queryFn: () => rgpdApi.getTaxonomy(selectedEntity),
+    enabled: !!selectedEntity,
+  })
+  const { data: dataCategories } = useQuery({
+    queryKey: ['rgpd-data-categories', selectedEntity],
+    queryFn: () => rgpdApi.listDataCategories(selectedEntity),
+    enabled: !!selectedEntity,
+  })
+  const { data: dataSubjects } = useQuery({
+    queryKey: ['rgpd-data-subjects', selectedEntity],
+    queryFn: () => rgpdApi.listDataSubjects(selectedEntity),
+    enabled: !!selectedEntity,
+  })
+  const { data: parties } = useQuery({
+    queryKey: ['rgpd-parties', selectedEntity],
+    queryFn: () => rgpdApi.listParties(selectedEntity),
+    enabled: !!selectedEntity,
+  })
+  const { data: measures } = useQuery({
+    queryKey: ['rgpd-security-measures', selectedEntity],
+    queryFn: () => rgpdApi.listSecurityMeasures(selectedEntity),
+    enabled: !!selectedEntity,
+  })
+
+  // Mentions complémentaires selon les catégories de données retenues
+  const selectedCategories = dataCategories?.filter((c) => formData.data_category_ids.includes(c.id)) ?? []
+  const hasSpecialData = selectedCategories.some((c) => c.special === 'art9')
+  const hasCriminalData = selectedCategories.some((c) => c.special === 'art10')
+  const needsConsent = formData.legal_basis === 'consent' || formData.special_category_exception === 'explicit_consent'
+  const legalBasisLabel = (key?: string) => taxonomy?.legal_bases.find((b) => b.key === key)?.label ?? key ?? '—'
+
+  const selectedValues = (e: React.ChangeEvent<HTMLSelectElement>) =>
+    Array.from(e.target.selectedOptions).map((o) => o.value)
+
   const createMutation = useMutation({
     mutationFn: (data: any) => rgpdApi.addToRegister(selectedEntity, data),
     onSuccess: () => {
       queryClient.invalidateQueries({ queryKey: ['rgpd-register'] })
       setShowCreateForm(false)
-      setFormData({
-        processing_name: '',
-        purpose: '',
-        legal_basis: '',
-        data_categories: '',
-        data_subjects: '',
-        recipients: '',
-        retention_period: '',
-        security_measures: '',
-      })
+      setFormData(emptyForm)
     },
   })
 
@@ -53,16 +100,51 @@ export default function RGPDRegister() {
       alert('Veuillez sélectionner une entité')
       return
     }
+    const isController = formData.record_type === 'controller'
+    const role = (r: PartyRole) => (id: string) => ({ party_id: id, role: r })
     createMutation.mutate({
-      ...formData,
-      data_categories: formData.data_categories.split(',').map(s => s.trim()).filter(Boolean),
-      data_subjects: formData.data_subjects.split(',').map(s => s.trim()).filter(Boolean),
-      recipients: formData.recipients.split(',').map(s => s.trim()).filter(Boolean),
+      record_type: formData.record_type,
+      processing_name: formData.processing_name,
+      purpose: formData.purpose,
+      legal_basis: isController ? formData.legal_basis || undefined : undefined,
+      legal_basis_details: (isController && formData.legal_basis_details) || undefined,
+      special_category_exception: (isController && hasSpecialData && formData.special_category_exception) || undefined,
+      criminal_data_basis: (isController && hasCriminalData && formData.criminal_data_basis) || undefined,
+      consent_mechanism: (isController && needsConsent && formData.consent_mechanism) || undefined,
+      data_category_ids: formData.data_category_ids,
+      data_subject_ids: formData.data_subject_ids,
+      parties: [
+        ...(isController ? [] : formData.controller_ids.map(role('controller'))),
+        ...formData.recipient_ids.map(role('recipient')),
+      ],
+      measure_ids: formData.measure_ids,
       retention_period: formData.retention_period || undefined,
+      retention_months: formData.retention_months ? Number(formData.retention_months) : undefined,
+      retention_trigger: (formData.retention_trigger as RetentionTrigger) || undefined,
+      retention_action: (formData.retention_action as RetentionAction) || undefined,
       security_measures: formData.security_measures || undefined,
     })
   }
 
+  const { data: exports } = useQuery({
+    queryKey: ['rgpd-register-exports', selectedEntity],
+    queryFn: () => rgpdApi.listRegisterExports(selectedEntity),
+    enabled: !!selectedEntity,
+  })
+
+  const exportMutation = useMutation({
+    mutationFn: (format: RegisterExportFormat) => rgpdApi.exportRegister(selectedEntity, format),
+    onSuccess: (blob, format) => {
+      const url = URL.createObjectURL(blob)
+      const link = document.createElement('a')
+      link.href = url
+      link.download = `registre-${selectedEntity}-v${(exports?.[0]?.version ?? 0) + 1}.${format}`
+      link.click()
+      URL.revokeObjectURL(url)
+      queryClient.invalidateQueries({ queryKey: ['rgpd-register-exports', selectedEntity] })
+    },
+  })
+
   if (isLoading) {
     return <div className="rgpd-register-page">Chargement...</div>
   }
@@ -70,7 +152,7 @@ export default function RGPDRegister() {
   return (
     <div className="rgpd-register-page">
       <div className="page-header">
-        <h1>Registre Léger RGPD</h1>
+        <h1>Registre des traitements RGPD</h1>
         <button onClick={() => setShowCreateForm(!showCreateForm)}>
           {showCreateForm ? 'Annuler' : 'Ajouter une entrée'}
         </button>
@@ -93,6 +175,27 @@ export default function RGPDRegister() {
         </label>
       </div>
 
+      {selectedEntity && (
+        <div className="register-exports">
+          <span>Exporter le registre :</span>
+          {(['pdf', 'odt', 'docx', 'xlsx', 'ods', 'jsonld'] as RegisterExportFormat[]).map((format) => (
+            <button
+              key={format}
+              onClick={() => exportMutation.mutate(format)}
+              disabled={exportMutation.isPending}
+            >
+              {format.toUpperCase()}
+            </button>
+          ))}
+          {exports && exports.length > 0 && (
+            <span>
+              Dernière édition : version {exports[0].version} du{' '}
+              {new Date(exports[0].generated_at).toLocaleString('fr-FR')}
+            </span>
+          )}
+        </div>
+      )}
+
       {showCreateForm && (
         <form className="create-form" onSubmit={handleSubmit}>
           <h2>Ajouter une entrée au registre</h2>
@@ -111,6 +214,16 @@ export default function RGPDRegister() {
               ))}
             </select>
           </div>
+          <div className="form-group">
+            <label>Registre *</label>
+            <select
+              value={formData.record_type}
+              onChange={(e) => setFormData({ ...formData, record_type: e.target.value as RecordType })}
+            >
+              <option value="controller">Responsable de traitement (Art. 30.1)</option>
+              <option value="processor">Sous-traitant (Art. 30.2)</option>
+            </select>
+          </div>
           <div className="form-group">
             <label>Nom du traitement *</label>
             <input
@@ -129,44 +242,129 @@ export default function RGPDRegister() {
               rows={3}
             />
           </div>
+          {formData.record_type === 'controller' ? (
+            <div className="form-group">
+              <label>Base légale *</label>
+              <select
+                value={formData.legal_basis}
+                onChange={(e) => setFormData({ ...formData, legal_basis: e.target.value as LegalBasis })}
+                required
+              >
+                <option value="">Sélectionner…</option>
+                {taxonomy?.legal_bases.map((b) => (
+                  <option key={b.key} value={b.key}>{b.label} (art. {b.article})</option>
+                ))}
+              </select>
+              <input
+                type="text"
+                placeholder="Précision (intérêt poursuivi, texte applicable…)"
+                value={formData.legal_basis_details}
+                onChange={(e) => setFormData({ ...formData, legal_basis_details: e.target.value })}
+              />
+            </div>
+          ) : (
+            <div className="form-group">
+              <label>Responsables de traitement *</label>
+              <select
+                multiple
+                value={formData.controller_ids}
+                onChange={(e) => setFormData({ ...formData, controller_ids: selectedValues(e) })}
+                required
+              >
+                {parties?.map((p) => (
+                  <option key={p.id} value={p.id}>{p.name}</option>
+                ))}
+              </select>
+            </div>
+          )}
           <div className="form-group">
-            <label>Base légale *</label>
-            <input
-              type="text"
-              value={formData.legal_basis}
-              onChange={(e) => setFormData({ ...formData, legal_basis: e.target.value })}
-              required
-            />
+            <label>Catégories de données{formData.record_type === 'controller' ? ' *' : ''}</label>
+            <select
+              multiple
+              value={formData.data_category_ids}
+              onChange={(e) => setFormData({ ...formData, data_category_ids: selectedValues(e) })}
+              required={formData.record_type === 'controller'}
+            >
+              {dataCategories?.map((c) => (
+                <option key={c.id} value={c.id}>
+                  {c.name}{c.special === 'art9' ? ' (sensible, art. 9)' : c.special === 'art10' ? ' (infractions, art. 10)' : ''}
+                </option>
+              ))}
+            </select>
           </div>
+          {formData.record_type === 'controller' && hasSpecialData && (
+            <div className="form-group">
+              <label>Exception (art. 9.2) *</label>
+              <select
+                value={formData.special_category_exception}
+                onChange={(e) => setFormData({ ...formData, special_category_exception: e.target.value })}
+                required
+              >
+                <option value="">Sélectionner…</option>
+                {taxonomy?.special_category_exceptions.map((x) => (
+                  <option key={x.key} value={x.key}>{x.label} (art. {x.article})</option>
+                ))}
+              </select>
+            </div>
+          )}
+          {formData.record_type === 'controller' && hasCriminalData && (
+            <div className="form-group">
+              <label>Texte autorisant le traitement (art. 10) *</label>
+              <input
+                type="text"
+                value={formData.criminal_data_basis}
+                onChange={(e) => setFormData({ ...formData, criminal_data_basis: e.target.value })}
+                required
+              />
+            </div>
+          )}
+          {formData.record_type === 'controller' && needsConsent && (
+            <div className="form-group">
+              <label>Recueil, preuve et retrait du consentement *</label>
+              <textarea
+                value={formData.consent_mechanism}
+                onChange={(e) => setFormData({ ...formData, consent_mechanism: e.target.value })}
+                required
+                rows={2}
+              />
+            </div>
+          )}
           <div className="form-group">
-            <label>Catégories de données (séparées par des virgules) *</label>
-            <input
-              type="text"
-              value={formData.data_categories}
-              onChange={(e) => setFormData({ ...formData, data_categories: e.target.value })}
-              required
-              placeholder="ex: nom, email, adresse"
-            />
+            <label>Personnes concernées{formData.record_type === 'controller' ? ' *' : ''}</label>
+            <select
+              multiple
+              value={formData.data_subject_ids}
+              onChange={(e) => setFormData({ ...formData, data_subject_ids: selectedValues(e) })}
+              required={formData.record_type === 'controller'}
+            >
+              {dataSubjects?.map((c) => (
+                <option key={c.id} value={c.id}>{c.name}</option>
+              ))}
+            </select>
           </div>
           <div className="form-group">
-            <label>Personnes concernées (séparées par des virgules) *</label>
-            <input
-              type="text"
-              value={formData.data_subjects}
-              onChange={(e) => setFormData({ ...formData, data_subjects: e.target.value })}
-              required
-              placeholder="ex: clients, employés"
-            />
+            <label>Destinataires</label>
+            <select
+              multiple
+              value={formData.recipient_ids}
+              onChange={(e) => setFormData({ ...formData, recipient_ids: selectedValues(e) })}
+            >
+              {parties?.map((p) => (
+                <option key={p.id} value={p.id}>{p.name}</option>
+              ))}
+            </select>
           </div>
           <div className="form-group">
-            <label>Destinataires (séparés par des virgules) *</label>
-            <input
-              type="text"
-              value={formData.recipients}
-              onChange={(e) => setFormData({ ...formData, recipients: e.target.value })}
-              required
-              placeholder="ex: service RH, service comptabilité"
-            />
+            <label>Mesures de sécurité (référentiel)</label>
+            <select
+              multiple
+              value={formData.measure_ids}
+              onChange={(e) => setFormData({ ...formData, measure_ids: selectedValues(e) })}
+            >
+              {measures?.map((m) => (
+                <option key={m.id} value={m.id}>{m.name}</option>
+              ))}
+            </select>
           </div>
           <div className="form-group">
             <label>Durée de conservation</label>
@@ -176,9 +374,35 @@ export default function RGPDRegister() {
               onChange={(e) => setFormData({ ...formData, retention_period: e.target.value })}
               placeholder="ex: 5 ans"
             />
+            <input
+              type="number"
+              min={1}
+              max={1200}
+              value={formData.retention_months}
+              onChange={(e) => setFormData({ ...formData, retention_months: e.target.value })}
+              placeholder="Durée maximale en mois"
+            />
+            <select
+              value={formData.retention_trigger}
+              onChange={(e) => setFormData({ ...formData, retention_trigger: e.target.value })}
+            >
+              <option value="">Point de départ…</option>
+              {taxonomy?.retention_triggers.map((t) => (
+                <option key={t.key} value={t.key}>{t.label}</option>
+              ))}
+            </select>
+            <select
+              value={formData.retention_action}
+              onChange={(e) => setFormData({ ...formData, retention_action: e.target.value })}
+            >
+              <option value="">Sort à l'échéance…</option>
+              {taxonomy?.retention_actions.map((a) => (
+                <option key={a.key} value={a.key}>{a.label}</option>
+              ))}
+            </select>
           </div>
           <div className="form-group">
-            <label>Mesures de sécurité</label>
+            <label>Description des mesures de sécurité</label>
             <textarea
               value={formData.security_measures}
               onChange={(e) => setFormData({ ...formData, security_measures: e.target.value })}
@@ -196,20 +420,29 @@ export default function RGPDRegister() {
           <table>
             <thead>
               <tr>
+                <th>Registre</th>
                 <th>Nom du traitement</th>
                 <th>Finalité</th>
                 <th>Base légale</th>
                 <th>Catégories</th>
+                <th>AIPD</th>
                 <th>Date de création</th>
               </tr>
             </thead>
             <tbody>
               {entries.map((entry: RegisterEntry) => (
                 <tr key={entry.id}>
-                  <td>{entry.processing_name}</td>
+                  <td>{entry.record_type === 'controller' ? 'Responsable' : 'Sous-traitant'}</td>
+                  <td>
+                    {entry.processing_name}
+                    {entry.checks.length > 0 && (
+                      <span title={entry.checks.map((c) => c.message).join('\n')}> ⚠ {entry.checks.length}</span>
+                    )}
+                  </td>
                   <td>{entry.purpose}</td>
-                  <td>{entry.legal_basis}</td>
-                  <td>{entry.data_categories.join(', ')}</td>
+                  <td>{entry.record_type === 'controller' ? legalBasisLabel(entry.legal_basis) : '—'}</td>
+                  <td>{entry.data_categories.map((c) => c.name).join(', ')}</td>
+                  <td>{entry.dpia ? `${DPIA_STATUS_LABELS[entry.dpia.status]}${entry.dpia.version ? ` (v${entry.dpia.version})` : ''}` : '—'}</td>
                   <td>{new Date(entry.created_at).toLocaleDateString('fr-FR')}</td>
                 </tr>
               ))}
@@ -7,7 +7,7 @@ import './RGPDRequests.css'
 export default function RGPDRequests() {
   const [showCreateForm, setShowCreateForm] = useState(false)
   const [selectedEntity, setSelectedEntity] = useState<string>('')
-  const [selectedRequest, setSelectedRequest] = useState<string | null>(null)
+  const [selectedRequest, setSelectedRequest] = useState<AccessRequest | null>(null)
   const [responseText, setResponseText] = useState('')
   const [formData, setFormData] = useState({
     requester_name: '',
@@ -42,8 +42,8 @@ export default function RGPDRequests() {
   })
 
   const respondMutation = useMutation({
-    mutationFn: ({ id, status, response }: { id: string; status: string; response?: string }) =>
-      rgpdApi.respondToRequest(id, { status, response }),
+    mutationFn: ({ entityId, id, status, response }: { entityId: string; id: string; status: string; response?: string }) =>
+      rgpdApi.respondToRequest(entityId, id, { status, response }),
     onSuccess: () => {
       queryClient.invalidateQueries({ queryKey: ['rgpd-requests'] })
       setSelectedRequest(null)
@@ -63,9 +63,10 @@ export default function RGPDRequests() {
     })
   }
 
-  const handleRespond = (requestId: string, status: string) => {
+  const handleRespond = (request: AccessRequest, status: string) => {
     respondMutation.mutate({
-      id: requestId,
+      entityId: request.entity_id,
+      id: request.id,
       status,
       response: responseText || undefined,
     })
@@ -211,7 +212,7 @@ export default function RGPDRequests() {
                   <td>
                     {request.status === 'pending' && (
                       <button
-                        onClick={() => setSelectedRequest(request.id)}
+                        onClick={() => setSelectedRequest(request)}
                         className="action-button"
                       >
                         Répondre
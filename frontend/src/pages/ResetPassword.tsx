@@ -0,0 +1,74 @@
+import { useState } from 'react'
+import { Link, useSearchParams } from 'react-router-dom'
+import { authApi } from '../api/auth'
+import './Login.css'
+
+export default function ResetPassword() {
+  const [searchParams] = useSearchParams()
+  const token = searchParams.get('token') || ''
+  const [password, setPassword] = useState('')
+  const [confirmation, setConfirmation] = useState('')
+  const [done, setDone] = useState(false)
+  const [error, setError] = useState('')
+  const [loading, setLoading] = useState(false)
+
+  const handleSubmit = async (e: React.FormEvent) => {
+    e.preventDefault()
+    setError('')
+    if (password !== confirmation) {
+      setError('Les mots de passe ne correspondent pas')
+      return
+    }
+    setLoading(true)
+
+    try {
+      await authApi.resetPassword(token, password)
+      setDone(true)
+    } catch (err: any) {
+      const fieldErrors = err.response?.data?.errors as { message: string }[] | undefined
+      setError(fieldErrors?.map((f) => f.message).join(', ') || err.response?.data?.detail || 'Une erreur est survenue')
+    } finally {
+      setLoading(false)
+    }
+  }
+
+  return (
+    <div className="login-container">
+      <div className="login-card">
+        <h1>HyperCyber</h1>
+        <h2>Nouveau mot de passe</h2>
+        {error && <div className="error-message">{error}</div>}
+        {done ? (
+          <div className="info-message">Votre mot de passe a été modifié.</div>
+        ) : (
+          <form onSubmit={handleSubmit}>
+            <div className="form-group">
+              <label>Nouveau mot de passe</label>
+              <input
+                type="password"
+                value={password}
+                onChange={(e) => setPassword(e.target.value)}
+                required
+              />
+            </div>
+            <div className="form-group">
+              <label>Confirmation</label>
+              <input
+                type="password"
+                value={confirmation}
+                onChange={(e) => setConfirmation(e.target.value)}
+                required
+              />
+            </div>
+            <button type="submit" disabled={loading || !token}>
+              {loading ? 'Chargement...' : 'Enregistrer'}
+            </button>
+          </form>
+        )}
+        <p>
+          <Link to="/login">Retour à la connexion</Link>
+        </p>
+      </div>
+    </div>
+  )
+}
@@ -0,0 +1,40 @@
+import { useEffect, useState } from 'react'
+import { Link, useSearchParams } from 'react-router-dom'
+import { authApi } from '../api/auth'
+import './Login.css'
+
+export default function VerifyEmail() {
+  const [searchParams] = useSearchParams()
+  const token = searchParams.get('token')
+  const [status, setStatus] = useState<'pending' | 'done' | 'error'>('pending')
+  const [error, setError] = useState('')
+
+  useEffect(() => {
+    if (!token) {
+      setStatus('error')
+      setError('Lien de vérification invalide')
+      return
+    }
+    authApi.verifyEmail(token)
+      .then(() => setStatus('done'))
+      .catch((err: any) => {
+        setStatus('error')
+        setError(err.response?.data?.detail || 'Une erreur est survenue')
+      })
+  }, [token])
+
+  return (
+    <div className="login-container">
+      <div className="login-card">
+        <h1>HyperCyber</h1>
+        <h2>Vérification de l'adresse email</h2>
+        {status === 'pending' && <p>Vérification en cours...</p>}
+        {status === 'done' && <div className="info-message">Votre adresse email est vérifiée.</div>}
+        {status === 'error' && <div className="error-message">{error}</div>}
+        <p>
+          <Link to="/login">Retour à la connexion</Link>
+        </p>
+      </div>
+    </div>
+  )
+}